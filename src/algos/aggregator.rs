@@ -58,7 +58,7 @@ impl UnigramProbability {
         let mut total = 0usize;
         for feats in features.iter() {
             for feat_id in feats.iter() {
-                counts[*feat_id] += 1;
+                counts[*feat_id as usize] += 1;
                 total += 1;
             }
         }
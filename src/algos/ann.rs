@@ -1,33 +1,106 @@
+use std::io::{Read,Write,Result as IOResult,Error as IOError,ErrorKind};
+use std::collections::BinaryHeap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+
+use float_ord::FloatOrd;
+use hashbrown::HashSet;
+
 use rand::prelude::*;
 use rand_xorshift::XorShiftRng;
 use rayon::prelude::*;
 
 use crate::graph::NodeID;
-use crate::embeddings::{EmbeddingStore,Entity};
-use crate::algos::graph_ann::NodeDistance;
+use crate::embeddings::{EmbeddingStore,Distance};
+use crate::vocab::Vocab;
+use crate::simd;
+use crate::io::{open_file_for_reading,open_file_for_writing,write_magic,check_magic};
+use crate::io::{write_usize,read_usize,write_f32,read_f32,write_f32s,read_f32s,write_node_ids,read_node_ids};
+
+/// Header for serialized forests; bump the version when the layout changes.
+const ANN_MAGIC: &[u8] = b"CLVRANN2";
+use crate::algos::graph_ann::{NodeDistance,TopK};
+use crate::algos::ann_mmap::MMAP_MAGIC;
 
 struct Hyperplane {
     coef: Vec<f32>,
-    bias: f32
+    bias: f32,
+
+    /// L2 norm of the coefficients, so margins are comparable across hyperplanes
+    norm: f32
 }
 
 impl Hyperplane {
     fn new(coef: Vec<f32>, bias: f32) -> Self {
-        Hyperplane { coef, bias }
+        let norm = simd::norm_squared(&coef).sqrt();
+        Hyperplane { coef, bias, norm }
+    }
+
+    /// Signed distance from the hyperplane; positive is above.
+    fn margin(&self, emb: &[f32]) -> f32 {
+        let m = simd::dot(&self.coef, emb) + self.bias;
+        if self.norm > 0. { m / self.norm } else { m }
     }
 
     fn point_is_above(&self, emb: &[f32]) -> bool {
-        self.coef.iter().zip(emb.iter())
-            .map(|(ci, ei)| ci * ei)
-            .sum::<f32>() + self.bias >= 0.
+        simd::dot(&self.coef, emb) + self.bias >= 0.
     }
 }
 
 type TreeIndex = usize;
 type TreeTable = Vec<Tree>;
 
+/// Int8 copies of a leaf's embeddings, each vector scaled symmetrically by its max magnitude.
+/// Scoring these first avoids touching the full precision store for most candidates.
+#[derive(Clone)]
+struct QuantizedLeaf {
+    dims: usize,
+    scales: Vec<f32>,
+    codes: Vec<i8>
+}
+
+impl QuantizedLeaf {
+    fn new(es: &EmbeddingStore, indices: &[NodeID]) -> Self {
+        let mut ql = QuantizedLeaf {
+            dims: es.dims(),
+            scales: Vec::with_capacity(indices.len()),
+            codes: Vec::with_capacity(indices.len() * es.dims())
+        };
+        indices.iter().for_each(|idx| ql.push(es.get_embedding(*idx)));
+        ql
+    }
+
+    fn push(&mut self, emb: &[f32]) {
+        let max = emb.iter().fold(0f32, |acc, ei| acc.max(ei.abs()));
+        let scale = if max > 0. { max / 127. } else { 1. };
+        self.scales.push(scale);
+        self.codes.extend(emb.iter().map(|ei| (ei / scale).round().max(-127.).min(127.) as i8));
+    }
+
+    /// Keeps only the members flagged in `keep`, which is aligned with the leaf's indices
+    fn retain(&mut self, keep: &[bool]) {
+        let dims = self.dims;
+        let mut scales = Vec::with_capacity(self.scales.len());
+        let mut codes = Vec::with_capacity(self.codes.len());
+        keep.iter().enumerate().filter(|(_, k)| **k).for_each(|(i, _)| {
+            scales.push(self.scales[i]);
+            codes.extend_from_slice(&self.codes[i * dims..(i + 1) * dims]);
+        });
+        self.scales = scales;
+        self.codes = codes;
+    }
+
+    /// Writes the approximate embedding of the ith member into `out`
+    fn dequantize_into(&self, i: usize, out: &mut [f32]) {
+        let scale = self.scales[i];
+        let codes = &self.codes[i * self.dims..(i + 1) * self.dims];
+        out.iter_mut().zip(codes.iter()).for_each(|(oi, ci)| *oi = *ci as f32 * scale);
+    }
+}
+
 enum Tree {
-    Leaf { indices: Vec<NodeID> },
+    Leaf { indices: Vec<NodeID>, quantized: Option<QuantizedLeaf> },
 
     Split {
         hp: Hyperplane,
@@ -37,28 +110,273 @@ enum Tree {
 }
 
 
-fn tree_predict(
+/// The space hyperplanes partition.  Splitting raw embeddings only lines up with Euclidean
+/// distance, so other metrics are mapped into a space where Euclidean neighbors match their own.
+/// Leaves are always scored with the store's metric on the raw embeddings.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) enum SplitSpace {
+    Raw,
+
+    /// Unit vectors, for cosine, where Euclidean distance is monotonic in cosine distance
+    Normalized,
+
+    /// Dot product search reduced to Euclidean search by appending sqrt(M^2 - |x|^2) to items and
+    /// 0 to queries, where M is the max item norm (Bachrach et al, 2014).
+    Augmented { max_norm: f32 }
+}
+
+impl SplitSpace {
+    fn for_store(es: &EmbeddingStore) -> Self {
+        match es.distance() {
+            Distance::Cosine => SplitSpace::Normalized,
+            Distance::Dot => {
+                let max_norm = es.par_iter()
+                    .map(|(_, emb)| simd::norm_squared(emb))
+                    .reduce(|| 0f32, f32::max)
+                    .sqrt();
+                SplitSpace::Augmented { max_norm }
+            },
+            _ => SplitSpace::Raw
+        }
+    }
+
+    fn dims(&self, dims: usize) -> usize {
+        match self {
+            SplitSpace::Augmented { .. } => dims + 1,
+            _ => dims
+        }
+    }
+
+    /// Maps an indexed embedding into the split space
+    fn item<'a>(&self, emb: &'a [f32]) -> Cow<'a, [f32]> {
+        match self {
+            SplitSpace::Raw => Cow::Borrowed(emb),
+            SplitSpace::Normalized => Cow::Owned(normalize(emb)),
+            SplitSpace::Augmented { max_norm } => {
+                // Nodes inserted after fitting may exceed the max norm; clamp rather than NaN
+                let extra = (max_norm.powf(2.) - simd::norm_squared(emb)).max(0.).sqrt();
+                let mut v = emb.to_vec();
+                v.push(extra);
+                Cow::Owned(v)
+            }
+        }
+    }
+
+    /// Maps a query into the split space
+    pub(crate) fn query<'a>(&self, emb: &'a [f32]) -> Cow<'a, [f32]> {
+        match self {
+            SplitSpace::Raw => Cow::Borrowed(emb),
+            SplitSpace::Normalized => Cow::Owned(normalize(emb)),
+            SplitSpace::Augmented { .. } => {
+                let mut v = emb.to_vec();
+                v.push(0.);
+                Cow::Owned(v)
+            }
+        }
+    }
+
+    /// Materializes the store in the split space, or None if it's already there.
+    fn prepare(&self, es: &EmbeddingStore) -> Option<EmbeddingStore> {
+        if *self == SplitSpace::Raw { return None }
+
+        let prepared = EmbeddingStore::new(es.len(), self.dims(es.dims()), Distance::Euclidean);
+        es.par_iter().for_each(|(node_id, emb)| {
+            prepared.get_embedding_mut_hogwild(node_id).copy_from_slice(&self.item(emb));
+        });
+        Some(prepared)
+    }
+}
+
+fn normalize(emb: &[f32]) -> Vec<f32> {
+    let norm = simd::norm_squared(emb).sqrt();
+    if norm > 0. {
+        emb.iter().map(|ei| ei / norm).collect()
+    } else {
+        emb.to_vec()
+    }
+}
+
+/// Scores the leaf the split embedding falls into, returning the k closest to the query
+/// embedding sorted closest first.  Nodes failing the filter are skipped before scoring.
+fn tree_predict<F>(
     tree_table: &TreeTable,
     es: &EmbeddingStore, 
-    emb: &[f32]
-) -> Vec<(NodeID, f32)> {
-    let mut node = tree_table.len() - 1;
-    loop {
-        match &tree_table[node] {
-            Tree::Leaf { ref indices } => {
-                let qemb = Entity::Embedding(emb);
-                return indices.par_iter().map(|idx| {
-                    let d = es.compute_distance(&Entity::Node(*idx), &qemb);
-                    (*idx, d)
-                }).collect()
+    emb: &[f32],
+    split_emb: &[f32],
+    k: usize,
+    filter: &F
+) -> Vec<NodeDistance> 
+    where F: Fn(NodeID) -> bool
+{
+    match &tree_table[tree_leaf_index(tree_table, split_emb)] {
+        Tree::Leaf { ref indices, .. } => {
+            let scorer = es.scorer(emb);
+            let mut heap = TopK::new(k.min(indices.len()));
+            indices.iter().filter(|idx| filter(**idx)).for_each(|idx| {
+                heap.push(*idx, scorer(*idx, es.get_embedding(*idx)));
+            });
+            heap.into_sorted()
+        },
+        _ => Vec::new()
+    }
+}
+
+/// How hyperplanes are chosen when splitting a group of nodes during fit.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum SplitStrategy {
+    /// Samples 5 hyperplanes between random pairs of points, keeping whichever splits a sample
+    /// of 30 points most evenly.  Cheap, but trees get lopsided on clustered data.
+    TwoPoint,
+
+    /// Hyperplane orthogonal to a random pair of points, offset to the median projection of
+    /// `sample` points so both sides are balanced.
+    Median { sample: usize },
+
+    /// Of `candidates` random pair directions, picks the one whose projections of `sample` points
+    /// have the highest variance, then splits at the median.  Slowest to build but separates
+    /// clusters best.
+    Variance { candidates: usize, sample: usize }
+}
+
+impl Default for SplitStrategy {
+    fn default() -> Self {
+        SplitStrategy::TwoPoint
+    }
+}
+
+impl SplitStrategy {
+    fn choose(&self, es: &EmbeddingStore, indices: &[NodeID], rng: &mut impl Rng) -> Hyperplane {
+        match self {
+            SplitStrategy::TwoPoint => two_point_split(es, indices, rng),
+            SplitStrategy::Median { sample } => {
+                let direction = random_direction(es, indices, rng);
+                median_split(es, indices, direction, *sample, rng)
             },
-            Tree::Split { ref hp, ref above, ref below } => {
-                node = if hp.point_is_above(emb) { *above } else { *below };
+            SplitStrategy::Variance { candidates, sample } => {
+                let sampled: Vec<_> = indices.choose_multiple(rng, (*sample).max(2)).cloned().collect();
+                let direction = (0..(*candidates).max(1)).map(|_| {
+                    let direction = random_direction(es, indices, rng);
+                    let projections: Vec<_> = sampled.iter()
+                        .map(|idx| simd::dot(&direction, es.get_embedding(*idx)))
+                        .collect();
+                    (variance(&projections), direction)
+                }).max_by_key(|(var, _)| FloatOrd(*var)).unwrap().1;
+                median_split(es, indices, direction, *sample, rng)
             }
         }
     }
 }
 
+/// Difference between two distinct, randomly chosen points
+fn random_direction(es: &EmbeddingStore, indices: &[NodeID], rng: &mut impl Rng) -> Vec<f32> {
+    let idx_1 = indices.choose(rng).unwrap();
+    let mut idx_2 = indices.choose(rng).unwrap();
+    while idx_1 == idx_2 {
+        idx_2 = indices.choose(rng).unwrap();
+    }
+
+    let pa = es.get_embedding(*idx_1); 
+    let pb = es.get_embedding(*idx_2); 
+    pa.iter().zip(pb.iter()).map(|(pai, pbi)| pai - pbi).collect()
+}
+
+/// Places the hyperplane at the median projection of a sample of the points
+fn median_split(
+    es: &EmbeddingStore,
+    indices: &[NodeID],
+    direction: Vec<f32>,
+    sample: usize,
+    rng: &mut impl Rng
+) -> Hyperplane {
+    let mut projections: Vec<_> = indices.choose_multiple(rng, sample.max(1))
+        .map(|idx| simd::dot(&direction, es.get_embedding(*idx)))
+        .collect();
+    projections.sort_by_key(|p| FloatOrd(*p));
+    let median = projections[projections.len() / 2];
+    Hyperplane::new(direction, -median)
+}
+
+fn variance(xs: &[f32]) -> f32 {
+    let n = xs.len().max(1) as f32;
+    let mean = xs.iter().sum::<f32>() / n;
+    xs.iter().map(|x| (x - mean).powf(2.)).sum::<f32>() / n
+}
+
+/// Drops leaf members failing `keep`, along with their quantized copies.
+fn retain_leaf<F: Fn(NodeID) -> bool>(
+    indices: &mut Vec<NodeID>,
+    quantized: &mut Option<QuantizedLeaf>,
+    keep: F
+) {
+    let flags: Vec<_> = indices.iter().map(|idx| keep(*idx)).collect();
+    if flags.iter().all(|f| *f) { return }
+
+    if let Some(ql) = quantized {
+        ql.retain(&flags);
+    }
+    let mut flags = flags.into_iter();
+    indices.retain(|_| flags.next().unwrap());
+}
+
+/// The original heuristic: a handful of hyperplanes between random pairs, keeping the one which
+/// best balances a small sample.
+fn two_point_split(es: &EmbeddingStore, indices: &[NodeID], rng: &mut impl Rng) -> Hyperplane {
+    let mut best = (0i8, None);
+    for _ in 0..5 {
+        let idx_1 = indices.choose(rng).unwrap();
+        let mut idx_2 = indices.choose(rng).unwrap();
+        while idx_1 == idx_2 {
+            idx_2 = indices.choose(rng).unwrap();
+        }
+
+        let pa = es.get_embedding(*idx_1); 
+        let pb = es.get_embedding(*idx_2); 
+
+        let diff: Vec<_> = pa.iter().zip(pb.iter()).map(|(pai, pbi)| pai - pbi).collect();
+        let bias: f32 = diff.iter().zip(pa.iter().zip(pb.iter()))
+            .map(|(d, (pai, pbi))| d * (pai + pbi) / 2.)
+            .sum();
+
+        let hp = Hyperplane::new(diff, bias);
+        let mut s = 0i8;
+        for _ in 0..30 {
+            let idx = indices.choose(rng).unwrap();
+            let emb = es.get_embedding(*idx);
+            if hp.point_is_above(emb) { s += 1; } 
+        }
+        let score = (s - 15).abs();
+        if best.0 > score || best.1.is_none() {
+            best = (score, Some(hp));
+        }
+    }
+
+    best.1.unwrap()
+}
+
+/// K-way merge of sorted candidate lists, dropping duplicate nodes found by multiple trees.
+pub(crate) fn merge_top_k(lists: Vec<Vec<NodeDistance>>, k: usize) -> Vec<NodeDistance> {
+    // NodeDistance orders the closest as the greatest, so the max heap pops the closest head
+    let mut heads: BinaryHeap<(NodeDistance, usize, usize)> = lists.iter().enumerate()
+        .filter(|(_, list)| !list.is_empty())
+        .map(|(list_idx, list)| (list[0], list_idx, 0))
+        .collect();
+
+    let n = lists.iter().map(|l| l.len()).sum::<usize>();
+    let mut seen = HashSet::with_capacity(k.min(n));
+    let mut results = Vec::with_capacity(k.min(n));
+    while let Some((nd, list_idx, pos)) = heads.pop() {
+        if results.len() >= k { break }
+        if seen.insert(nd.1) {
+            results.push(nd);
+        }
+
+        if let Some(next) = lists[list_idx].get(pos + 1) {
+            heads.push((*next, list_idx, pos + 1));
+        }
+    }
+    results
+}
+
 fn tree_leaf_index(
     tree_table: &TreeTable,
     emb: &[f32]
@@ -66,7 +384,7 @@ fn tree_leaf_index(
     let mut node = tree_table.len() - 1;
     loop {
         match &tree_table[node] {
-            Tree::Leaf { indices: _ } => { return node },
+            Tree::Leaf { .. } => { return node },
             Tree::Split { ref hp, ref above, ref below } => {
                 node = if hp.point_is_above(emb) { *above } else { *below };
             }
@@ -82,7 +400,7 @@ fn tree_leaf_path(
     let mut node = tree_table.len() - 1;
     loop {
         match &tree_table[node] {
-            Tree::Leaf { indices: _ } => { break },
+            Tree::Leaf { .. } => { break },
             Tree::Split { ref hp, ref above, ref below } => {
                 node = if hp.point_is_above(emb) { *above } else { *below };
             }
@@ -97,7 +415,7 @@ fn tree_depth(
     node: TreeIndex
 ) -> usize {
     match &tree_table[node] {
-        Tree::Leaf { indices: _ } =>  1,
+        Tree::Leaf { .. } =>  1,
         Tree::Split { hp: _, above, below } => {
             let above_depth = tree_depth(tree_table, *above);
             let below_depth = tree_depth(tree_table, *below);
@@ -107,13 +425,65 @@ fn tree_depth(
 }
 
 
+/// Common interface for the approximate nearest neighbor backends, allowing callers to swap
+/// between them.  Indices don't own the embeddings; the same EmbeddingStore used to fit the index
+/// must be provided at query time.
+pub trait AnnIndex: Send + Sync {
+    /// Builds the index over every embedding in the store, replacing any existing index.
+    fn fit(&mut self, es: &EmbeddingStore);
+
+    /// Adds a single node, which must already exist in the store, to the index.
+    fn insert(&mut self, es: &EmbeddingStore, node_id: NodeID);
+
+    /// Finds the approximate k nearest neighbors to the embedding, closest first.
+    fn predict(&self, es: &EmbeddingStore, emb: &[f32], k: usize) -> Vec<NodeDistance>;
+
+    /// Finds the approximate k nearest neighbors to a node in the store, excluding the node
+    /// itself.
+    fn predict_node(&self, es: &EmbeddingStore, node_id: NodeID, k: usize) -> Vec<NodeDistance> {
+        let mut results = self.predict(es, es.get_embedding(node_id), k.saturating_add(1));
+        results.retain(|nd| nd.1 != node_id);
+        results.truncate(k);
+        results
+    }
+}
+
 pub struct Ann {
-    trees: Vec<TreeTable>
+    trees: Vec<TreeTable>,
+    n_trees: usize,
+    max_nodes_per_leaf: usize,
+    seed: u64,
+    split: SplitStrategy,
+
+    /// Space the hyperplanes split, derived from the store's metric at fit time
+    space: SplitSpace,
+
+    /// Removed nodes which are still physically present in the leaves
+    deleted: HashSet<NodeID>
 }
 
 impl Ann {
     pub fn new() -> Self {
-        Ann { trees: Vec::new() }
+        Ann::with_params(10, 100, 0)
+    }
+
+    /// Creates an unfit Ann with the parameters used by `AnnIndex::fit`.
+    pub fn with_params(n_trees: usize, max_nodes_per_leaf: usize, seed: u64) -> Self {
+        Ann { 
+            trees: Vec::new(), 
+            n_trees, 
+            max_nodes_per_leaf, 
+            seed, 
+            split: SplitStrategy::default(),
+            space: SplitSpace::Raw,
+            deleted: HashSet::new()
+        }
+    }
+
+    /// Sets how splits are chosen on subsequent fits.  The strategy isn't persisted by `save`.
+    pub fn with_split_strategy(mut self, split: SplitStrategy) -> Self {
+        self.split = split;
+        self
     }
 
     pub fn fit(
@@ -124,6 +494,14 @@ impl Ann {
         seed: u64
     ) {
         self.trees.clear();
+        self.deleted.clear();
+        self.n_trees = n_trees;
+        self.max_nodes_per_leaf = max_nodes_per_leaf;
+        self.seed = seed;
+        self.space = SplitSpace::for_store(es);
+        let prepared = self.space.prepare(es);
+        let split_es = prepared.as_ref().unwrap_or(es);
+
         let mut trees = Vec::with_capacity(n_trees);
         for _ in 0..n_trees {
             trees.push(Vec::new());
@@ -132,7 +510,7 @@ impl Ann {
         trees.par_iter_mut().enumerate().for_each(|(idx, tree) | {
             let indices = (0..es.len()).collect::<Vec<_>>();
             let mut rng = XorShiftRng::seed_from_u64(seed + idx as u64);
-            self.fit_group_(tree, 1, es, indices, max_nodes_per_leaf, &mut rng);
+            self.fit_group_(tree, 1, split_es, indices, max_nodes_per_leaf, &mut rng);
         });
 
         self.trees = trees;
@@ -153,41 +531,11 @@ impl Ann {
         rng: &mut impl Rng
     ) -> TreeIndex {
         if indices.len() < max_nodes_per_leaf {
-            tree_table.push(Tree::Leaf { indices });
+            tree_table.push(Tree::Leaf { indices, quantized: None });
             return tree_table.len() - 1
         }
 
-        // Pick two point
-        let mut best = (0i8, None);
-        for _ in 0..5 {
-            let idx_1 = indices.choose(rng).unwrap();
-            let mut idx_2 = indices.choose(rng).unwrap();
-            while idx_1 == idx_2 {
-                idx_2 = indices.choose(rng).unwrap();
-            }
-
-            let pa = es.get_embedding(*idx_1); 
-            let pb = es.get_embedding(*idx_2); 
-
-            let diff: Vec<_> = pa.iter().zip(pb.iter()).map(|(pai, pbi)| pai - pbi).collect();
-            let bias: f32 = diff.iter().zip(pa.iter().zip(pb.iter()))
-                .map(|(d, (pai, pbi))| d * (pai + pbi) / 2.)
-                .sum();
-
-            let hp = Hyperplane::new(diff, bias);
-            let mut s = 0i8;
-            for _ in 0..30 {
-                let idx = indices.choose(rng).unwrap();
-                let emb = es.get_embedding(*idx);
-                if hp.point_is_above(emb) { s += 1; } 
-            }
-            let score = (s - 15).abs();
-            if best.0 > score || best.1.is_none() {
-                best = (score, Some(hp));
-            }
-        }
-
-        let hp = best.1.unwrap();
+        let hp = self.split.choose(es, &indices, rng);
         let scores = indices.par_iter().map(|idx| {
             hp.point_is_above(es.get_embedding(*idx))
         }).collect::<Vec<_>>();
@@ -210,51 +558,226 @@ impl Ann {
             tree_table.push(Tree::Split { hp: hp, above: above_idx, below: below_idx })
         } else {
             let idxs = if above.len() == 0 { below } else { above };
-            tree_table.push(Tree::Leaf { indices: idxs })
+            tree_table.push(Tree::Leaf { indices: idxs, quantized: None })
         }
         tree_table.len() - 1
     }
 
+    /// Returns the k closest nodes found across the trees, closest first.  Each tree keeps its
+    /// own top k which are then merged, so cost doesn't scale with the sort of every candidate.
     pub fn predict(
         &self, 
         es: &EmbeddingStore, 
-        emb: &[f32]
+        emb: &[f32],
+        k: usize
     ) -> Vec<NodeDistance> {
+        self.predict_filtered(es, emb, k, |_node_id| true)
+    }
+
+    /// Variant of `predict` which only considers nodes passing the filter.  Filtered nodes are
+    /// skipped when scoring leaves rather than removed afterwards, so they never take up slots in
+    /// the top k.  Highly selective filters can leave fewer than k results since only the
+    /// query's leaves are searched; `predict_search_k_filtered` keeps exploring until enough
+    /// candidates pass.
+    pub fn predict_filtered<F>(
+        &self, 
+        es: &EmbeddingStore, 
+        emb: &[f32],
+        k: usize,
+        filter: F
+    ) -> Vec<NodeDistance> 
+        where F: Sync + Fn(NodeID) -> bool
+    {
+        let filter = |node_id| !self.is_removed(node_id) && filter(node_id);
+        let split_emb = self.space.query(emb);
         let scores = self.trees.par_iter().map(|tree| {
-            tree_predict(tree, es, emb)
+            tree_predict(tree, es, emb, &split_emb, k, &filter)
         }).collect::<Vec<_>>();
 
-        let n = scores.iter().map(|x| x.len()).sum::<usize>();
-        let mut all_scores = Vec::with_capacity(n);
-        scores.into_iter().for_each(|subset| {
-            subset.into_iter().for_each(|(node_id, s)| {
-                all_scores.push(NodeDistance(s, node_id));
+        merge_top_k(scores, k)
+    }
+
+    /// Builds int8 copies of every leaf's embeddings for use with `predict_quantized`.  Each tree
+    /// keeps its own copy, a quarter the size of the f32 store, and they are persisted by `save`.
+    pub fn quantize(&mut self, es: &EmbeddingStore) {
+        self.trees.par_iter_mut().for_each(|tree| {
+            tree.iter_mut().for_each(|node| {
+                if let Tree::Leaf { indices, quantized } = node {
+                    *quantized = Some(QuantizedLeaf::new(es, indices));
+                }
             });
         });
+    }
 
-        all_scores.par_sort();
+    /// Whether leaves have quantized copies of their embeddings
+    pub fn is_quantized(&self) -> bool {
+        self.trees.iter().flat_map(|t| t.iter()).any(|node| {
+            matches!(node, Tree::Leaf { quantized: Some(_), .. })
+        })
+    }
 
-        let mut cur_pointer = 1;
-        let mut cur_node_id = all_scores[0].1;
-        for i in 1..n {
-            let next_id = all_scores[i].1;
-            if next_id != cur_node_id {
-                all_scores[cur_pointer] = all_scores[i];
-                cur_node_id = next_id;
-                cur_pointer += 1;
+    /// Two pass search: leaves are first scored against their int8 copies, keeping the best
+    /// `rerank` candidates across the trees, which are then rescored at full precision.  Leaves
+    /// without quantized copies are scored at full precision in the first pass.
+    pub fn predict_quantized(
+        &self, 
+        es: &EmbeddingStore, 
+        emb: &[f32],
+        k: usize,
+        rerank: usize
+    ) -> Vec<NodeDistance> {
+        let rerank = rerank.max(k);
+        let distance = es.distance();
+        let split_emb = self.space.query(emb);
+        let scores = self.trees.par_iter().map(|tree| {
+            if let Tree::Leaf { ref indices, ref quantized } = tree[tree_leaf_index(tree, &split_emb)] {
+                let mut heap = TopK::new(rerank.min(indices.len()));
+                match quantized {
+                    Some(ql) => {
+                        let mut scratch = vec![0f32; ql.dims];
+                        indices.iter().enumerate().filter(|(_, idx)| !self.is_removed(**idx)).for_each(|(i, idx)| {
+                            ql.dequantize_into(i, &mut scratch);
+                            heap.push(*idx, distance.compute(emb, &scratch));
+                        });
+                    },
+                    None => indices.iter().filter(|idx| !self.is_removed(**idx)).for_each(|idx| {
+                        heap.push(*idx, distance.compute(emb, es.get_embedding(*idx)));
+                    })
+                }
+                heap.into_sorted()
+            } else {
+                Vec::new()
             }
+        }).collect::<Vec<_>>();
+
+        let candidates = merge_top_k(scores, rerank);
+        let scorer = es.scorer(emb);
+        let mut heap = TopK::new(k.min(candidates.len()));
+        candidates.iter().for_each(|nd| {
+            heap.push(nd.1, scorer(nd.1, es.get_embedding(nd.1)));
+        });
+        heap.into_sorted()
+    }
+
+    /// Answers many queries at once.  Parallelism is across queries rather than trees, which
+    /// avoids fanning out a tiny rayon job per query, and each worker reuses its candidate buffer
+    /// between queries.  Candidates are deduplicated across trees before scoring so nodes shared
+    /// by multiple leaves are only scored once.
+    pub fn predict_batch(
+        &self, 
+        es: &EmbeddingStore, 
+        queries: &[&[f32]],
+        k: usize
+    ) -> Vec<Vec<NodeDistance>> {
+        queries.par_iter().map_init(HashSet::new, |candidates, emb| {
+            candidates.clear();
+            let split_emb = self.space.query(emb);
+            self.trees.iter().for_each(|tree| {
+                if let Tree::Leaf { ref indices, .. } = tree[tree_leaf_index(tree, &split_emb)] {
+                    candidates.extend(indices.iter().cloned().filter(|idx| !self.is_removed(*idx)));
+                }
+            });
+
+            let scorer = es.scorer(emb);
+            let mut heap = TopK::new(k.min(candidates.len()));
+            candidates.iter().for_each(|idx| {
+                heap.push(*idx, scorer(*idx, es.get_embedding(*idx)));
+            });
+            heap.into_sorted()
+        }).collect()
+    }
+
+    /// Restricts `predict` to nodes of the given type.  Returns nothing if the node type doesn't
+    /// exist in the vocab.
+    pub fn predict_of_type(
+        &self, 
+        es: &EmbeddingStore, 
+        emb: &[f32],
+        k: usize,
+        vocab: &Vocab,
+        node_type: &str
+    ) -> Vec<NodeDistance> {
+        match vocab.get_node_type_id(node_type) {
+            Some(nt_id) => self.predict_filtered(es, emb, k, |node_id| {
+                vocab.get_node_type_id_of(node_id) == Some(nt_id)
+            }),
+            None => Vec::new()
         }
-        all_scores.truncate(cur_pointer);
-        all_scores.reverse();
-        all_scores
+    }
+
+    /// Annoy-style priority search.  Rather than a single leaf per tree, branches across all the
+    /// trees are explored in order of how close the query falls to each split, until at least
+    /// `search_k` candidates have been collected.  This recovers neighbors which landed on the
+    /// other side of a split; a good starting point is n_trees * k.
+    pub fn predict_search_k(
+        &self, 
+        es: &EmbeddingStore, 
+        emb: &[f32],
+        k: usize,
+        search_k: usize
+    ) -> Vec<NodeDistance> {
+        self.predict_search_k_filtered(es, emb, k, search_k, |_node_id| true)
+    }
+
+    /// Priority search which only collects nodes passing the filter.  Only passing nodes count
+    /// toward `search_k`, so restrictive filters explore more of the forest to fill the budget.
+    pub fn predict_search_k_filtered<F>(
+        &self, 
+        es: &EmbeddingStore, 
+        emb: &[f32],
+        k: usize,
+        search_k: usize,
+        filter: F
+    ) -> Vec<NodeDistance> 
+        where F: Sync + Fn(NodeID) -> bool
+    {
+        // Max heap of (margin, tree, node); roots start with infinite priority
+        let mut queue: BinaryHeap<(FloatOrd<f32>, usize, TreeIndex)> = self.trees.iter()
+            .enumerate()
+            .map(|(tree_idx, tree)| (FloatOrd(std::f32::INFINITY), tree_idx, tree.len() - 1))
+            .collect();
+
+        let split_emb = self.space.query(emb);
+        let mut candidates = HashSet::new();
+        while candidates.len() < search_k {
+            let (FloatOrd(priority), tree_idx, node) = match queue.pop() {
+                Some(item) => item,
+                None => break
+            };
+
+            match &self.trees[tree_idx][node] {
+                Tree::Leaf { ref indices, .. } => {
+                    candidates.extend(indices.iter().cloned().filter(|idx| {
+                        !self.is_removed(*idx) && filter(*idx)
+                    }))
+                },
+                Tree::Split { ref hp, ref above, ref below } => {
+                    let margin = hp.margin(&split_emb);
+                    queue.push((FloatOrd(priority.min(margin)), tree_idx, *above));
+                    queue.push((FloatOrd(priority.min(-margin)), tree_idx, *below));
+                }
+            }
+        }
+
+        let candidates: Vec<_> = candidates.into_iter().collect();
+        let k = k.min(candidates.len());
+        let scorer = es.scorer(emb);
+        candidates.par_iter().fold(|| TopK::new(k), |mut heap, node_id| {
+            heap.push(*node_id, scorer(*node_id, es.get_embedding(*node_id)));
+            heap
+        }).reduce(|| TopK::new(k), |mut tk1, tk2| {
+            tk1.extend(tk2);
+            tk1
+        }).into_sorted()
     }
 
     pub fn predict_leaf_indices(
         &self,
         emb: &[f32]
     ) -> Vec<usize> {
+        let split_emb = self.space.query(emb);
         self.trees.par_iter().map(|tree| {
-            tree_leaf_index(tree, emb)
+            tree_leaf_index(tree, &split_emb)
         }).collect()
     }
 
@@ -262,8 +785,9 @@ impl Ann {
         &self,
         emb: &[f32]
     ) -> Vec<Vec<usize>> {
+        let split_emb = self.space.query(emb);
         self.trees.par_iter().map(|tree| {
-            tree_leaf_path(tree, emb)
+            tree_leaf_path(tree, &split_emb)
         }).collect()
     }
 
@@ -271,4 +795,509 @@ impl Ann {
         self.trees.len()
     }
 
+    /// Removes a node from search results without refitting.  The node is tombstoned and skipped
+    /// when scoring leaves; once tombstones exceed a tenth of the indexed nodes the leaves are
+    /// compacted automatically.
+    pub fn remove(&mut self, node_id: NodeID) {
+        self.deleted.insert(node_id);
+        let indexed = self.trees.first().map(|tree| {
+            tree.iter().map(|node| match node {
+                Tree::Leaf { indices, .. } => indices.len(),
+                _ => 0
+            }).sum::<usize>()
+        }).unwrap_or(0);
+
+        if self.deleted.len() * 10 > indexed {
+            self.compact();
+        }
+    }
+
+    pub fn is_removed(&self, node_id: NodeID) -> bool {
+        !self.deleted.is_empty() && self.deleted.contains(&node_id)
+    }
+
+    /// Number of tombstoned nodes awaiting compaction
+    pub fn num_removed(&self) -> usize {
+        self.deleted.len()
+    }
+
+    /// Physically drops tombstoned nodes from the leaves.
+    pub fn compact(&mut self) {
+        if self.deleted.is_empty() { return }
+
+        let deleted = &self.deleted;
+        self.trees.par_iter_mut().for_each(|tree| {
+            tree.iter_mut().for_each(|node| {
+                if let Tree::Leaf { indices, quantized } = node {
+                    retain_leaf(indices, quantized, |idx| !deleted.contains(&idx));
+                }
+            });
+        });
+        self.deleted.clear();
+    }
+
+    /// Saves the forest so it can be shipped to serving without refitting.  Only the trees are
+    /// written; the EmbeddingStore needs to be saved separately.  Paths ending in .gz are
+    /// compressed.
+    pub fn save(&self, path: &str) -> IOResult<()> {
+        let mut w = open_file_for_writing(path, None)?;
+        write_magic(&mut w, ANN_MAGIC)?;
+        write_usize(&mut w, self.n_trees)?;
+        write_usize(&mut w, self.max_nodes_per_leaf)?;
+        write_usize(&mut w, self.seed as usize)?;
+        match self.space {
+            SplitSpace::Raw => w.write_all(&[0u8])?,
+            SplitSpace::Normalized => w.write_all(&[1u8])?,
+            SplitSpace::Augmented { max_norm } => {
+                w.write_all(&[2u8])?;
+                write_f32(&mut w, max_norm)?;
+            }
+        }
+        write_usize(&mut w, self.trees.len())?;
+        for tree in self.trees.iter() {
+            write_usize(&mut w, tree.len())?;
+            for node in tree.iter() {
+                match node {
+                    Tree::Leaf { indices, quantized } => {
+                        // Tombstones aren't persisted, so write the compacted leaf instead
+                        let mut live;
+                        let (indices, quantized) = if self.deleted.is_empty() {
+                            (indices, quantized)
+                        } else {
+                            live = (indices.clone(), quantized.clone());
+                            retain_leaf(&mut live.0, &mut live.1, |idx| !self.is_removed(idx));
+                            (&live.0, &live.1)
+                        };
+
+                        match quantized {
+                            None => {
+                                w.write_all(&[0u8])?;
+                                write_node_ids(&mut w, indices)?;
+                            },
+                            Some(ql) => {
+                                w.write_all(&[2u8])?;
+                                write_node_ids(&mut w, indices)?;
+                                write_usize(&mut w, ql.dims)?;
+                                write_f32s(&mut w, &ql.scales)?;
+                                let codes: Vec<u8> = ql.codes.iter().map(|c| *c as u8).collect();
+                                w.write_all(&codes)?;
+                            }
+                        }
+                    },
+                    Tree::Split { hp, above, below } => {
+                        w.write_all(&[1u8])?;
+                        write_f32s(&mut w, &hp.coef)?;
+                        write_f32(&mut w, hp.bias)?;
+                        write_usize(&mut w, *above)?;
+                        write_usize(&mut w, *below)?;
+                    }
+                }
+            }
+        }
+        w.flush()
+    }
+
+    /// Writes the forest in the flat layout read by `MmapAnn`, which queries the file in place
+    /// rather than deserializing it.  Quantized leaf copies aren't included and tombstoned nodes
+    /// are dropped.  Compression isn't supported.
+    ///
+    /// Layout, little endian: the magic, then the u64 header fields n_trees, split_dims,
+    /// space tag, max_norm (f32 padded to 8 bytes), n_nodes, n_splits, and n_indices.  That's
+    /// followed by the u64 root of each tree, the [u32; 4] nodes, the f32 split biases, the f32
+    /// split coefficients, and finally the u32 leaf indices.  Leaf nodes are [0, start, len, 0]
+    /// and splits are [1, split, above, below], with node ids global across trees.
+    pub fn save_mmap(&self, path: &str) -> IOResult<()> {
+        let split_dims = self.trees.iter().flat_map(|t| t.iter()).find_map(|node| match node {
+            Tree::Split { hp, .. } => Some(hp.coef.len()),
+            _ => None
+        }).unwrap_or(0);
+
+        let mut nodes: Vec<[u32; 4]> = Vec::new();
+        let mut roots = Vec::with_capacity(self.trees.len());
+        let mut biases = Vec::new();
+        let mut coefs = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for tree in self.trees.iter() {
+            let base = nodes.len() as u32;
+            for node in tree.iter() {
+                match node {
+                    Tree::Leaf { indices: members, .. } => {
+                        let start = indices.len() as u32;
+                        indices.extend(members.iter()
+                            .filter(|idx| !self.is_removed(**idx))
+                            .map(|idx| *idx as u32));
+                        nodes.push([0, start, indices.len() as u32 - start, 0]);
+                    },
+                    Tree::Split { hp, above, below } => {
+                        nodes.push([1, biases.len() as u32, base + *above as u32, base + *below as u32]);
+                        biases.push(hp.bias);
+                        coefs.extend_from_slice(&hp.coef);
+                    }
+                }
+            }
+            roots.push(nodes.len() as u64 - 1);
+        }
+
+        let (space_tag, max_norm) = match self.space {
+            SplitSpace::Raw => (0, 0.),
+            SplitSpace::Normalized => (1, 0.),
+            SplitSpace::Augmented { max_norm } => (2, max_norm)
+        };
+
+        let mut w = BufWriter::new(File::create(path)?);
+        write_magic(&mut w, MMAP_MAGIC)?;
+        write_usize(&mut w, self.trees.len())?;
+        write_usize(&mut w, split_dims)?;
+        write_usize(&mut w, space_tag)?;
+        write_f32(&mut w, max_norm)?;
+        w.write_all(&[0u8; 4])?;
+        write_usize(&mut w, nodes.len())?;
+        write_usize(&mut w, biases.len())?;
+        write_usize(&mut w, indices.len())?;
+        for root in roots {
+            w.write_all(&root.to_le_bytes())?;
+        }
+        for node in nodes.iter() {
+            for v in node.iter() {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+        for v in biases.iter().chain(coefs.iter()) {
+            write_f32(&mut w, *v)?;
+        }
+        for v in indices.iter() {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.flush()
+    }
+
+    /// Loads a forest written by `save`.
+    pub fn load(path: &str) -> IOResult<Self> {
+        let mut r = open_file_for_reading(path)?;
+        check_magic(&mut r, ANN_MAGIC)?;
+        let n_trees = read_usize(&mut r)?;
+        let max_nodes_per_leaf = read_usize(&mut r)?;
+        let seed = read_usize(&mut r)? as u64;
+        let mut tag = [0u8];
+        r.read_exact(&mut tag)?;
+        let space = match tag[0] {
+            0 => SplitSpace::Raw,
+            1 => SplitSpace::Normalized,
+            2 => SplitSpace::Augmented { max_norm: read_f32(&mut r)? },
+            t => return Err(IOError::new(ErrorKind::InvalidData, format!("Unknown split space {}", t)))
+        };
+        let num_trees = read_usize(&mut r)?;
+        let mut trees = Vec::with_capacity(num_trees);
+        for _ in 0..num_trees {
+            let num_nodes = read_usize(&mut r)?;
+            let mut tree = Vec::with_capacity(num_nodes);
+            for idx in 0..num_nodes {
+                let mut tag = [0u8];
+                r.read_exact(&mut tag)?;
+                let node = match tag[0] {
+                    0 => Tree::Leaf { indices: read_node_ids(&mut r)?, quantized: None },
+                    2 => {
+                        let indices = read_node_ids(&mut r)?;
+                        let dims = read_usize(&mut r)?;
+                        let scales = read_f32s(&mut r)?;
+                        if scales.len() != indices.len() {
+                            return Err(IOError::new(ErrorKind::InvalidData, "Corrupt quantized leaf!"))
+                        }
+                        let mut codes = vec![0u8; indices.len() * dims];
+                        r.read_exact(&mut codes)?;
+                        let codes = codes.into_iter().map(|c| c as i8).collect();
+                        Tree::Leaf { indices, quantized: Some(QuantizedLeaf { dims, scales, codes }) }
+                    },
+                    1 => {
+                        let coef = read_f32s(&mut r)?;
+                        let bias = read_f32(&mut r)?;
+                        let above = read_usize(&mut r)?;
+                        let below = read_usize(&mut r)?;
+                        // Children are always written before their parent
+                        if above >= idx || below >= idx {
+                            return Err(IOError::new(ErrorKind::InvalidData, "Corrupt tree!"))
+                        }
+                        Tree::Split { hp: Hyperplane::new(coef, bias), above, below }
+                    },
+                    t => return Err(IOError::new(ErrorKind::InvalidData, format!("Unknown node tag {}", t)))
+                };
+                tree.push(node);
+            }
+            if tree.is_empty() {
+                return Err(IOError::new(ErrorKind::InvalidData, "Empty tree!"))
+            }
+            trees.push(tree);
+        }
+        Ok(Ann { 
+            trees, 
+            n_trees, 
+            max_nodes_per_leaf, 
+            seed, 
+            split: SplitStrategy::default(),
+            space,
+            deleted: HashSet::new()
+        })
+    }
+
+}
+
+impl AnnIndex for Ann {
+    fn fit(&mut self, es: &EmbeddingStore) {
+        Ann::fit(self, es, self.n_trees, self.max_nodes_per_leaf, self.seed);
+    }
+
+    /// Drops the node into the matching leaf of each tree.  Leaves aren't resplit, so inserting
+    /// large numbers of nodes degrades query speed until the index is refit.
+    fn insert(&mut self, es: &EmbeddingStore, node_id: NodeID) {
+        // Reinserting a removed node; drop the stale copy first so it isn't indexed twice
+        if self.is_removed(node_id) {
+            self.compact();
+        }
+
+        let emb = es.get_embedding(node_id);
+        let split_emb = self.space.item(emb);
+        self.trees.par_iter_mut().for_each(|tree| {
+            let leaf = tree_leaf_index(tree, &split_emb);
+            if let Tree::Leaf { ref mut indices, ref mut quantized } = tree[leaf] {
+                indices.push(node_id);
+                if let Some(ql) = quantized {
+                    ql.push(emb);
+                }
+            }
+        });
+    }
+
+    fn predict(&self, es: &EmbeddingStore, emb: &[f32], k: usize) -> Vec<NodeDistance> {
+        Ann::predict(self, es, emb, k)
+    }
+}
+
+#[cfg(test)]
+mod ann_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_save_load() {
+        let mut es = EmbeddingStore::new(500, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[(node_id % 25) as f32, (node_id / 25) as f32]);
+        }
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 20, 1234);
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-ann-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        ann.save(path).unwrap();
+        let loaded = Ann::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.num_trees(), 3);
+        assert_eq!(loaded.depth(), ann.depth());
+        let q = [3.2, 7.9];
+        let expected = ann.predict(&es, &q, 10);
+        let results = loaded.predict(&es, &q, 10);
+        assert_eq!(results.len(), expected.len());
+        assert!(results.iter().zip(expected.iter()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_merge_top_k() {
+        let l1 = vec![NodeDistance(0.1, 1), NodeDistance(0.5, 2), NodeDistance(0.9, 3)];
+        let l2 = vec![NodeDistance(0.2, 4), NodeDistance(0.5, 2)];
+        let merged = merge_top_k(vec![l1, l2, Vec::new()], 3);
+        assert_eq!(merged.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![1, 4, 2]);
+
+        let merged = merge_top_k(vec![vec![NodeDistance(0.5, 2)], vec![NodeDistance(0.5, 2)]], 10);
+        assert_eq!(merged.len(), 1);
+    }
+
+    #[test]
+    fn test_predict_search_k() {
+        let mut es = EmbeddingStore::new(1000, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[(node_id % 40) as f32, (node_id / 40) as f32]);
+        }
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 2, 10, 1234);
+
+        // With a budget covering every node, priority search is exact
+        let q = [12.3, 7.6];
+        let results = ann.predict_search_k(&es, &q, 5, es.len());
+        let expected = es.nearest_neighbors(&crate::embeddings::Entity::Embedding(&q), 5);
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), 
+                   expected.iter().map(|nd| nd.1).collect::<Vec<_>>());
+
+        // And a modest budget should still find the true nearest neighbor
+        let results = ann.predict_search_k(&es, &q, 1, 100);
+        assert_eq!(results[0].1, expected[0].1);
+    }
+
+    #[test]
+    fn test_predict_filtered() {
+        let mut es = EmbeddingStore::new(200, 1, Distance::Euclidean);
+        (0..200).for_each(|i| es.set_embedding(i, &[i as f32]));
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 300, 1234);
+
+        // Odd nodes only; the filter shouldn't shrink the result set
+        let results = ann.predict_filtered(&es, &[100.2], 4, |node_id| node_id % 2 == 1);
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![101, 99, 103, 97]);
+
+        let results = ann.predict_search_k_filtered(&es, &[100.], 2, 10, |node_id| node_id > 150);
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![151, 152]);
+    }
+
+    #[test]
+    fn test_predict_batch() {
+        let mut es = EmbeddingStore::new(500, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[(node_id % 25) as f32, (node_id / 25) as f32]);
+        }
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 20, 1234);
+
+        let queries: Vec<&[f32]> = vec![&[3.2f32, 7.9][..], &[20.1, 1.4][..], &[11.5, 15.3][..]];
+        let results = ann.predict_batch(&es, &queries, 5);
+        assert_eq!(results.len(), queries.len());
+        for (q, batch) in queries.iter().zip(results.iter()) {
+            let single = ann.predict(&es, q, 5);
+            assert_eq!(batch.len(), single.len());
+            assert!(batch.iter().zip(single.iter()).all(|(a, b)| a.0 == b.0));
+        }
+    }
+
+    #[test]
+    fn test_predict_quantized() {
+        let mut es = EmbeddingStore::new(500, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[(node_id % 25) as f32, (node_id / 25) as f32]);
+        }
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 20, 1234);
+        assert!(!ann.is_quantized());
+        ann.quantize(&es);
+        assert!(ann.is_quantized());
+
+        // Reranking at full precision returns exact distances for the same candidates
+        let q = [3.2, 7.9];
+        let expected = ann.predict(&es, &q, 5);
+        let results = ann.predict_quantized(&es, &q, 5, 50);
+        assert_eq!(results.len(), expected.len());
+        assert!(results.iter().zip(expected.iter()).all(|(a, b)| a.0 == b.0));
+
+        // Quantized leaves survive a round trip
+        let path = std::env::temp_dir().join(format!("cloverleaf-qann-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        ann.save(path).unwrap();
+        let loaded = Ann::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(loaded.is_quantized());
+        let reloaded = loaded.predict_quantized(&es, &q, 5, 50);
+        assert!(results.iter().zip(reloaded.iter()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_split_strategies_balance() {
+        // Two tight clusters of very different sizes
+        let mut es = EmbeddingStore::new(1000, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            let offset = if node_id < 900 { 0. } else { 100. };
+            let x = (node_id % 30) as f32 * 0.01 + offset;
+            let y = (node_id / 30) as f32 * 0.01;
+            es.set_embedding(node_id, &[x, y]);
+        }
+
+        for split in [SplitStrategy::Median { sample: 101 }, 
+                      SplitStrategy::Variance { candidates: 10, sample: 101 }] {
+            let mut ann = Ann::new().with_split_strategy(split);
+            ann.fit(&es, 2, 10, 1234);
+            // A perfectly balanced tree would be depth 8; allow some slack for sampling
+            assert!(ann.depth().iter().all(|d| *d <= 12), "{:?}: {:?}", split, ann.depth());
+
+            let q = [0.05, 0.05];
+            let results = ann.predict(&es, &q, 1);
+            assert_eq!(results.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut es = EmbeddingStore::new(100, 1, Distance::Euclidean);
+        (0..100).for_each(|i| es.set_embedding(i, &[i as f32]));
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 2, 200, 1234);
+
+        ann.remove(50);
+        assert!(ann.is_removed(50));
+        assert_eq!(ann.num_removed(), 1);
+        let results = ann.predict(&es, &[50.], 3);
+        assert!(results.iter().all(|nd| nd.1 != 50));
+        assert_eq!(results.len(), 3);
+
+        // Compaction physically drops the node, and reinsertion brings it back exactly once
+        ann.compact();
+        assert_eq!(ann.num_removed(), 0);
+        assert!(ann.predict(&es, &[50.], 1)[0].1 != 50);
+        AnnIndex::insert(&mut ann, &es, 50);
+        let results = ann.predict(&es, &[50.], 2);
+        assert_eq!(results[0].1, 50);
+        assert!(results[1].1 != 50);
+
+        // Removing enough nodes triggers compaction on its own
+        (0..11).for_each(|i| ann.remove(i));
+        assert_eq!(ann.num_removed(), 0);
+    }
+
+    #[test]
+    fn test_split_space() {
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(50, 4, Distance::Dot);
+        for node_id in 0..es.len() {
+            let scale = 1. + node_id as f32 / 10.;
+            let emb: Vec<f32> = (0..4).map(|_| rng.gen::<f32>() * scale).collect();
+            es.set_embedding(node_id, &emb);
+        }
+
+        // Euclidean order in the augmented space matches dot product order
+        let space = SplitSpace::for_store(&es);
+        assert_eq!(space.dims(4), 5);
+        let q = [0.3, -0.2, 0.9, 0.1];
+        let split_q = space.query(&q);
+        let mut by_dot: Vec<_> = (0..es.len()).collect();
+        by_dot.sort_by_key(|n| FloatOrd(Distance::Dot.compute(&q, es.get_embedding(*n))));
+        let mut by_l2: Vec<_> = (0..es.len()).collect();
+        by_l2.sort_by_key(|n| FloatOrd(simd::l2_squared(&split_q, &space.item(es.get_embedding(*n)))));
+        assert_eq!(by_dot, by_l2);
+
+        // The split space is persisted alongside the trees
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 10, 1234);
+        let path = std::env::temp_dir().join(format!("cloverleaf-dann-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        ann.save(path).unwrap();
+        let loaded = Ann::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.space, ann.space);
+        assert_eq!(loaded.predict_leaf_indices(&q), ann.predict_leaf_indices(&q));
+    }
+
+    #[test]
+    fn test_predict_node() {
+        let mut es = EmbeddingStore::new(100, 1, Distance::Euclidean);
+        (0..100).for_each(|i| es.set_embedding(i, &[i as f32]));
+
+        let mut ann = Ann::with_params(2, 200, 1234);
+        AnnIndex::fit(&mut ann, &es);
+        let results = ann.predict_node(&es, 50, 2);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|nd| nd.1 != 50));
+        assert!(results.iter().all(|nd| nd.0 == 1.));
+    }
 }
@@ -0,0 +1,136 @@
+//! Measures how well an approximate nearest neighbor index performs against brute force search
+//! over the same EmbeddingStore.  Sampled nodes are used as queries; recall@k and per query
+//! latencies are reported so index parameters like n_trees or ef_search can be tuned against
+//! actual numbers.
+use std::time::{Duration,Instant};
+
+use hashbrown::HashSet;
+use rand::prelude::*;
+use rand::seq::index::sample;
+use rand_xorshift::XorShiftRng;
+
+use crate::embeddings::{EmbeddingStore,Entity};
+use crate::algos::graph_ann::NodeDistance;
+use crate::algos::ann::AnnIndex;
+
+#[derive(Debug,Clone)]
+pub struct AnnEvaluation {
+    /// Number of neighbors requested per query
+    pub k: usize,
+
+    /// Number of queries evaluated
+    pub n_queries: usize,
+
+    /// Mean fraction of the true k nearest neighbors returned by the index
+    pub recall: f32,
+
+    /// Index latency percentiles
+    pub latency_p50: Duration,
+    pub latency_p90: Duration,
+    pub latency_p99: Duration,
+
+    /// Brute force latency percentiles, for reference
+    pub brute_force_p50: Duration,
+    pub brute_force_p99: Duration
+}
+
+/// Evaluates an arbitrary search function, such as a closure over `Ann::predict_search_k`, which
+/// takes a query embedding and k.
+pub fn evaluate<F>(
+    es: &EmbeddingStore,
+    search: F,
+    n_queries: usize,
+    k: usize,
+    seed: u64
+) -> AnnEvaluation
+    where F: Fn(&[f32], usize) -> Vec<NodeDistance>
+{
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    let n_queries = n_queries.min(es.len());
+    let queries = sample(&mut rng, es.len(), n_queries);
+
+    let mut latencies = Vec::with_capacity(n_queries);
+    let mut bf_latencies = Vec::with_capacity(n_queries);
+    let mut total_recall = 0f32;
+    for node_id in queries.iter() {
+        let emb = es.get_embedding(node_id);
+
+        let start = Instant::now();
+        let expected = es.nearest_neighbors(&Entity::Embedding(emb), k);
+        bf_latencies.push(start.elapsed());
+
+        let start = Instant::now();
+        let results = search(emb, k);
+        latencies.push(start.elapsed());
+
+        if !expected.is_empty() {
+            let expected: HashSet<_> = expected.iter().map(|nd| nd.1).collect();
+            let hits = results.iter().filter(|nd| expected.contains(&nd.1)).count();
+            total_recall += hits as f32 / expected.len() as f32;
+        }
+    }
+
+    latencies.sort();
+    bf_latencies.sort();
+    AnnEvaluation {
+        k,
+        n_queries,
+        recall: total_recall / n_queries.max(1) as f32,
+        latency_p50: percentile(&latencies, 0.5),
+        latency_p90: percentile(&latencies, 0.9),
+        latency_p99: percentile(&latencies, 0.99),
+        brute_force_p50: percentile(&bf_latencies, 0.5),
+        brute_force_p99: percentile(&bf_latencies, 0.99)
+    }
+}
+
+/// Evaluates the default search of an index.
+pub fn evaluate_index<A: AnnIndex + ?Sized>(
+    index: &A,
+    es: &EmbeddingStore,
+    n_queries: usize,
+    k: usize,
+    seed: u64
+) -> AnnEvaluation {
+    evaluate(es, |emb, k| index.predict(es, emb, k), n_queries, k, seed)
+}
+
+/// Nearest rank percentile over sorted durations
+fn percentile(sorted: &[Duration], p: f32) -> Duration {
+    if sorted.is_empty() { return Duration::ZERO }
+    let idx = ((sorted.len() as f32 * p).ceil() as usize).max(1) - 1;
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+#[cfg(test)]
+mod ann_eval_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_percentile() {
+        let ds: Vec<_> = (1..=100).map(|i| Duration::from_millis(i)).collect();
+        assert_eq!(percentile(&ds, 0.5), Duration::from_millis(50));
+        assert_eq!(percentile(&ds, 0.99), Duration::from_millis(99));
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_exact_search_has_full_recall() {
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(300, 4, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            let emb: Vec<f32> = (0..4).map(|_| rng.gen::<f32>()).collect();
+            es.set_embedding(node_id, &emb);
+        }
+
+        let brute = |emb: &[f32], k| es.nearest_neighbors(&Entity::Embedding(emb), k);
+        let eval = evaluate(&es, brute, 20, 5, 1234);
+        assert_eq!(eval.n_queries, 20);
+        assert_eq!(eval.recall, 1.);
+        assert!(eval.latency_p50 <= eval.latency_p99);
+
+        let eval = evaluate(&es, |_emb, _k| Vec::new(), 20, 5, 1234);
+        assert_eq!(eval.recall, 0.);
+    }
+}
@@ -0,0 +1,227 @@
+//! Read-only random projection forest queried directly from a memory mapped file written by
+//! `Ann::save_mmap`.  Nothing is deserialized, so opening is nearly free and every process
+//! serving the same file on a host shares one physical copy through the page cache.
+use std::fs::File;
+use std::io::{Result as IOResult,Error as IOError,ErrorKind};
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::simd;
+use crate::algos::graph_ann::{NodeDistance,TopK};
+use crate::algos::ann::{SplitSpace,merge_top_k};
+
+/// Header for memory mappable forests; bump the version when the layout changes.
+pub(crate) const MMAP_MAGIC: &[u8] = b"CLVRMMP1";
+
+/// Magic plus seven 8 byte fields
+const HEADER_SIZE: usize = 64;
+
+pub struct MmapAnn {
+    mmap: Mmap,
+    n_trees: usize,
+    split_dims: usize,
+    space: SplitSpace,
+    n_nodes: usize,
+    n_splits: usize,
+    n_indices: usize,
+
+    // Byte offsets of each section
+    roots_offset: usize,
+    nodes_offset: usize,
+    biases_offset: usize,
+    coefs_offset: usize,
+    indices_offset: usize
+}
+
+fn corrupt(msg: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+impl MmapAnn {
+
+    /// Maps the file and validates its structure.  Validation walks the nodes once but doesn't
+    /// copy anything.
+    pub fn open(path: &str) -> IOResult<Self> {
+        if cfg!(target_endian = "big") {
+            return Err(IOError::new(ErrorKind::Unsupported, "Memory mapped indices require a little endian host"))
+        }
+
+        let file = File::open(path)?;
+        // Safety: the file is treated as read-only; modifying it while mapped is undefined, as
+        // with any memory mapped file.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < HEADER_SIZE || &mmap[..MMAP_MAGIC.len()] != MMAP_MAGIC {
+            return Err(corrupt("Unknown file format; expected a memory mappable Ann"))
+        }
+
+        let field = |i: usize| {
+            let start = MMAP_MAGIC.len() + i * 8;
+            u64::from_le_bytes(mmap[start..start + 8].try_into().unwrap()) as usize
+        };
+        let n_trees = field(0);
+        let split_dims = field(1);
+        let space = match field(2) {
+            0 => SplitSpace::Raw,
+            1 => SplitSpace::Normalized,
+            2 => {
+                let start = MMAP_MAGIC.len() + 3 * 8;
+                let max_norm = f32::from_le_bytes(mmap[start..start + 4].try_into().unwrap());
+                SplitSpace::Augmented { max_norm }
+            },
+            _ => return Err(corrupt("Unknown split space"))
+        };
+        let n_nodes = field(4);
+        let n_splits = field(5);
+        let n_indices = field(6);
+
+        let roots_offset = HEADER_SIZE;
+        let nodes_offset = roots_offset + n_trees * 8;
+        let biases_offset = nodes_offset + n_nodes * 16;
+        let coefs_offset = biases_offset + n_splits * 4;
+        let indices_offset = coefs_offset + n_splits * split_dims * 4;
+        if mmap.len() != indices_offset + n_indices * 4 {
+            return Err(corrupt("Truncated or oversized index file"))
+        }
+
+        let ann = MmapAnn {
+            mmap, n_trees, split_dims, space, n_nodes, n_splits, n_indices,
+            roots_offset, nodes_offset, biases_offset, coefs_offset, indices_offset
+        };
+        ann.validate()?;
+        Ok(ann)
+    }
+
+    /// Checks every reference stays in bounds and children precede parents, which guarantees
+    /// traversals terminate.
+    fn validate(&self) -> IOResult<()> {
+        for node_idx in 0..self.n_nodes {
+            let [tag, a, b, c] = self.node(node_idx);
+            let (a, b, c) = (a as usize, b as usize, c as usize);
+            match tag {
+                0 if a + b <= self.n_indices => (),
+                1 if a < self.n_splits && b < node_idx && c < node_idx => (),
+                _ => return Err(corrupt("Corrupt tree!"))
+            }
+        }
+        for tree in 0..self.n_trees {
+            if self.root(tree) >= self.n_nodes {
+                return Err(corrupt("Corrupt tree!"))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn num_trees(&self) -> usize {
+        self.n_trees
+    }
+
+    fn u32s(&self, offset: usize, len: usize) -> &[u32] {
+        let bytes = &self.mmap[offset..offset + len * 4];
+        assert_eq!(bytes.as_ptr() as usize % std::mem::align_of::<u32>(), 0);
+        // Safety: bounds are checked by the slice above, alignment by the assert, and the host
+        // is little endian.
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const u32, len) }
+    }
+
+    fn f32s(&self, offset: usize, len: usize) -> &[f32] {
+        let bytes = &self.mmap[offset..offset + len * 4];
+        assert_eq!(bytes.as_ptr() as usize % std::mem::align_of::<f32>(), 0);
+        // Safety: as with u32s
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const f32, len) }
+    }
+
+    fn root(&self, tree: usize) -> usize {
+        let start = self.roots_offset + tree * 8;
+        u64::from_le_bytes(self.mmap[start..start + 8].try_into().unwrap()) as usize
+    }
+
+    fn node(&self, node_idx: usize) -> [u32; 4] {
+        let n = self.u32s(self.nodes_offset + node_idx * 16, 4);
+        [n[0], n[1], n[2], n[3]]
+    }
+
+    /// Descends a tree, returning the members of the leaf the embedding lands in.
+    fn leaf(&self, tree: usize, split_emb: &[f32]) -> &[u32] {
+        let mut node_idx = self.root(tree);
+        loop {
+            let [tag, a, b, c] = self.node(node_idx);
+            if tag == 0 {
+                return self.u32s(self.indices_offset + a as usize * 4, b as usize)
+            }
+
+            let split = a as usize;
+            let bias = self.f32s(self.biases_offset + split * 4, 1)[0];
+            let coef = self.f32s(self.coefs_offset + split * self.split_dims * 4, self.split_dims);
+            node_idx = if simd::dot(coef, split_emb) + bias >= 0. { b as usize } else { c as usize };
+        }
+    }
+
+    /// Returns the k closest nodes found across the trees, closest first.
+    pub fn predict(&self, es: &EmbeddingStore, emb: &[f32], k: usize) -> Vec<NodeDistance> {
+        let split_emb = self.space.query(emb);
+        let scores = (0..self.n_trees).into_par_iter().map(|tree| {
+            let members = self.leaf(tree, &split_emb);
+            let scorer = es.scorer(emb);
+            let mut heap = TopK::new(k.min(members.len()));
+            members.iter().for_each(|idx| {
+                let idx = *idx as NodeID;
+                heap.push(idx, scorer(idx, es.get_embedding(idx)));
+            });
+            heap.into_sorted()
+        }).collect::<Vec<_>>();
+
+        merge_top_k(scores, k)
+    }
+
+    /// Finds the k closest nodes to a node in the store, excluding the node itself.
+    pub fn predict_node(&self, es: &EmbeddingStore, node_id: NodeID, k: usize) -> Vec<NodeDistance> {
+        let mut results = self.predict(es, es.get_embedding(node_id), k.saturating_add(1));
+        results.retain(|nd| nd.1 != node_id);
+        results.truncate(k);
+        results
+    }
+}
+
+#[cfg(test)]
+mod ann_mmap_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+    use crate::algos::ann::{Ann,AnnIndex};
+
+    #[test]
+    fn test_mmap_matches_ann() {
+        let mut es = EmbeddingStore::new(500, 2, Distance::Cosine);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[(node_id % 25) as f32 + 1., (node_id / 25) as f32 + 1.]);
+        }
+
+        let mut ann = Ann::new();
+        ann.fit(&es, 3, 20, 1234);
+        ann.remove(7);
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-mmap-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        ann.save_mmap(path).unwrap();
+        let mmap_ann = MmapAnn::open(path).unwrap();
+        assert_eq!(mmap_ann.num_trees(), 3);
+
+        for node_id in [0, 7, 123, 499] {
+            let emb = es.get_embedding(node_id);
+            let expected = ann.predict(&es, emb, 10);
+            let results = mmap_ann.predict(&es, emb, 10);
+            assert_eq!(results.len(), expected.len());
+            assert!(results.iter().zip(expected.iter()).all(|(a, b)| a.0 == b.0));
+            assert!(results.iter().all(|nd| nd.1 != 7));
+            assert_eq!(mmap_ann.predict_node(&es, node_id, 5).len(), ann.predict_node(&es, node_id, 5).len());
+        }
+
+        // Truncated files are rejected rather than read out of bounds
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::write(path, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(MmapAnn::open(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Sampled betweenness and closeness centrality.  Both run a single source shortest path search
+//! from a random set of pivot nodes (Brandes and Pich, 2007; Eppstein and Wang, 2004): BFS for
+//! unweighted graphs and Dijkstra for weighted ones.  Betweenness accumulates each pivot's path
+//! dependencies (Brandes, 2001) and scales them up by n / pivots, while closeness averages the
+//! reciprocal distance from the pivots to every node.  With as many pivots as nodes both are exact.
+//!
+//! Closeness is harmonic, so nodes unreachable from a pivot simply contribute nothing rather than
+//! an infinite distance.  Edges are followed in their stored direction; symmetric graphs should
+//! store both directions.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap,VecDeque};
+
+use float_ord::FloatOrd;
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,NodeID};
+
+pub struct Centrality {
+    /// Number of source nodes to sample.  Error shrinks with 1/sqrt(pivots).
+    pub pivots: usize,
+
+    /// If true, edge lengths are the reciprocal of their weights, so heavier edges are shorter.
+    /// Otherwise every edge has unit length.
+    pub weighted: bool,
+
+    pub seed: u64
+}
+
+/// Per node centrality scores
+pub struct CentralityScores {
+    /// Estimated number of shortest paths between other nodes passing through each node
+    pub betweenness: Vec<f32>,
+
+    /// Mean reciprocal distance from the other nodes, ~ [0, 1] when unweighted
+    pub closeness: Vec<f32>
+}
+
+/// Shortest path DAG from a single source
+struct ShortestPaths {
+    /// Reached nodes in non-decreasing distance from the source
+    order: Vec<NodeID>,
+
+    distance: Vec<f64>,
+
+    /// Number of shortest paths from the source
+    sigma: Vec<f64>
+}
+
+impl Centrality {
+
+    /// Computes centrality over raw edge weights.
+    pub fn compute<G: Graph + Sync>(&self, graph: &G) -> CentralityScores {
+        self.compute_with(graph, |weights| weights.to_vec())
+    }
+
+    /// Computes centrality over a graph storing weights as CDFs, using the transition
+    /// probabilities as weights.
+    pub fn compute_cdf<G: CDFGraph + Sync>(&self, graph: &G) -> CentralityScores {
+        self.compute_with(graph, |weights| CDFtoP::new(weights).collect())
+    }
+
+    fn compute_with<G, F>(&self, graph: &G, node_weights: F) -> CentralityScores
+    where
+        G: Graph + Sync,
+        F: Fn(&[f32]) -> Vec<f32> + Sync
+    {
+        let n = graph.len();
+        let pivots: Vec<NodeID> = if self.pivots >= n {
+            (0..n).collect()
+        } else {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed);
+            rand::seq::index::sample(&mut rng, n, self.pivots).into_vec()
+        };
+
+        let (betweenness, closeness, counts) = pivots.par_iter()
+            .fold(|| (vec![0f64; n], vec![0f64; n], vec![0usize; n]), |(mut bc, mut cc, mut counts), source| {
+                let paths = self.shortest_paths(graph, *source, &node_weights);
+                accumulate_dependencies(graph, &paths, *source, &mut bc, |node_id| self.lengths(graph, node_id, &node_weights));
+                paths.order.iter().filter(|t_n| **t_n != *source).for_each(|t_n| {
+                    cc[*t_n] += 1. / paths.distance[*t_n];
+                });
+                // A pivot doesn't sample its own distance
+                (0..n).for_each(|node_id| if node_id != *source { counts[node_id] += 1; });
+                (bc, cc, counts)
+            })
+            .reduce(|| (vec![0f64; n], vec![0f64; n], vec![0usize; n]), |(mut bc, mut cc, mut counts), (obc, occ, ocounts)| {
+                bc.iter_mut().zip(obc).for_each(|(x, y)| *x += y);
+                cc.iter_mut().zip(occ).for_each(|(x, y)| *x += y);
+                counts.iter_mut().zip(ocounts).for_each(|(x, y)| *x += y);
+                (bc, cc, counts)
+            });
+
+        let scale = n as f64 / pivots.len().max(1) as f64;
+        CentralityScores {
+            betweenness: betweenness.into_iter().map(|b| (b * scale) as f32).collect(),
+            closeness: closeness.into_iter().zip(counts)
+                .map(|(c, count)| if count > 0 { (c / count as f64) as f32 } else { 0. })
+                .collect()
+        }
+    }
+
+    /// Lengths of a node's out edges.  Edges without positive weight are not traversable.
+    fn lengths<G: Graph, F: Fn(&[f32]) -> Vec<f32>>(&self, graph: &G, node_id: NodeID, node_weights: &F) -> Vec<f64> {
+        let (edges, weights) = graph.get_edges(node_id);
+        if self.weighted {
+            node_weights(weights).into_iter()
+                .map(|w| if w > 0. { 1. / w as f64 } else { f64::INFINITY })
+                .collect()
+        } else {
+            vec![1.; edges.len()]
+        }
+    }
+
+    fn shortest_paths<G: Graph, F: Fn(&[f32]) -> Vec<f32>>(&self, graph: &G, source: NodeID, node_weights: &F) -> ShortestPaths {
+        let n = graph.len();
+        let mut distance = vec![f64::INFINITY; n];
+        let mut sigma = vec![0f64; n];
+        let mut order = Vec::new();
+        distance[source] = 0.;
+        sigma[source] = 1.;
+
+        if !self.weighted {
+            let mut queue = VecDeque::new();
+            queue.push_back(source);
+            while let Some(node_id) = queue.pop_front() {
+                order.push(node_id);
+                let d = distance[node_id] + 1.;
+                for t_n in graph.get_edges(node_id).0.iter() {
+                    if distance[*t_n].is_infinite() {
+                        distance[*t_n] = d;
+                        queue.push_back(*t_n);
+                    }
+                    if distance[*t_n] == d {
+                        sigma[*t_n] += sigma[node_id];
+                    }
+                }
+            }
+        } else {
+            let mut visited = vec![false; n];
+            let mut heap = BinaryHeap::new();
+            heap.push(Reverse((FloatOrd(0f64), source)));
+            while let Some(Reverse((FloatOrd(d), node_id))) = heap.pop() {
+                if visited[node_id] || d > distance[node_id] { continue }
+
+                visited[node_id] = true;
+                order.push(node_id);
+                let edges = graph.get_edges(node_id).0;
+                let lengths = self.lengths(graph, node_id, node_weights);
+                for (t_n, len) in edges.iter().zip(lengths) {
+                    if visited[*t_n] || len.is_infinite() { continue }
+
+                    let nd = d + len;
+                    if same_length(nd, distance[*t_n]) {
+                        sigma[*t_n] += sigma[node_id];
+                    } else if nd < distance[*t_n] {
+                        distance[*t_n] = nd;
+                        sigma[*t_n] = sigma[node_id];
+                        heap.push(Reverse((FloatOrd(nd), *t_n)));
+                    }
+                }
+            }
+        }
+
+        ShortestPaths { order, distance, sigma }
+    }
+}
+
+/// Path lengths are sums of floats, so ties are compared with a small relative tolerance
+fn same_length(a: f64, b: f64) -> bool {
+    a.is_finite() && b.is_finite() && (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.)
+}
+
+/// Brandes' dependency accumulation, walking the shortest path DAG back from the furthest nodes.
+/// Successors are found through out edges, so the graph doesn't need to be transposed.
+fn accumulate_dependencies<G, L>(graph: &G, paths: &ShortestPaths, source: NodeID, betweenness: &mut [f64], lengths: L)
+where
+    G: Graph,
+    L: Fn(NodeID) -> Vec<f64>
+{
+    let mut delta = vec![0f64; graph.len()];
+    for node_id in paths.order.iter().rev() {
+        let edges = graph.get_edges(*node_id).0;
+        let d = paths.distance[*node_id];
+        let dependency = edges.iter().zip(lengths(*node_id))
+            .filter(|(t_n, len)| **t_n != *node_id && same_length(d + len, paths.distance[**t_n]))
+            .map(|(t_n, _)| paths.sigma[*node_id] / paths.sigma[*t_n] * (1. + delta[*t_n]))
+            .sum::<f64>();
+        delta[*node_id] = dependency;
+        if *node_id != source {
+            betweenness[*node_id] += dependency;
+        }
+    }
+}
+
+#[cfg(test)]
+mod centrality_tests {
+    use super::*;
+    use crate::graph::CSR;
+
+    fn undirected(edges: &[(NodeID, NodeID, f32)]) -> CSR {
+        let mut all = Vec::new();
+        for (f, t, w) in edges.iter() {
+            all.push((*f, *t, *w));
+            all.push((*t, *f, *w));
+        }
+        CSR::construct_from_edges(all)
+    }
+
+    #[test]
+    fn test_path() {
+        let graph = undirected(&[(0, 1, 1.), (1, 2, 1.), (2, 3, 1.), (3, 4, 1.)]);
+        let centrality = Centrality { pivots: 10, weighted: false, seed: 2023 };
+        let scores = centrality.compute(&graph);
+
+        // Ordered pairs routed through each node
+        assert_eq!(scores.betweenness, vec![0., 6., 8., 6., 0.]);
+
+        // (1/2 + 1 + 1 + 1/2) / 4 and (1 + 1/2 + 1/3 + 1/4) / 4
+        assert!((scores.closeness[2] - 0.75).abs() < 1e-6);
+        assert!((scores.closeness[0] - 25. / 48.).abs() < 1e-6);
+        assert_eq!(scores.closeness[0], scores.closeness[4]);
+
+        // Sampling a few pivots still ranks the middle highest
+        let centrality = Centrality { pivots: 3, weighted: false, seed: 2023 };
+        let scores = centrality.compute(&graph);
+        assert_eq!(scores.betweenness[0], 0.);
+        assert_eq!(scores.betweenness[4], 0.);
+    }
+
+    #[test]
+    fn test_weighted() {
+        // The direct 0 - 2 edge is light, so it's longer than going through 1
+        let graph = undirected(&[(0, 1, 1.), (1, 2, 1.), (0, 2, 0.25)]);
+
+        let centrality = Centrality { pivots: 3, weighted: false, seed: 2023 };
+        assert_eq!(centrality.compute(&graph).betweenness, vec![0., 0., 0.]);
+
+        let centrality = Centrality { pivots: 3, weighted: true, seed: 2023 };
+        let scores = centrality.compute(&graph);
+        assert_eq!(scores.betweenness, vec![0., 2., 0.]);
+        assert!((scores.closeness[0] - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ties() {
+        // Two shortest paths from 0 to 3 split the dependency between 1 and 2
+        let graph = undirected(&[(0, 1, 1.), (0, 2, 1.), (1, 3, 1.), (2, 3, 1.)]);
+        let centrality = Centrality { pivots: 4, weighted: true, seed: 2023 };
+        assert_eq!(centrality.compute(&graph).betweenness, vec![1., 1., 1., 1.]);
+    }
+}
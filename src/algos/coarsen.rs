@@ -0,0 +1,299 @@
+//! Multilevel graph coarsening for hierarchical embedding, in the spirit of HARP and MILE.  Heavy
+//! edge matching repeatedly collapses pairs of strongly connected nodes into a hierarchy of
+//! progressively smaller graphs.  Embeddings are learned on the coarsest graph, which is cheap,
+//! then prolonged to each finer level and refined by smoothing over that level's neighborhoods.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::embeddings::{EmbeddingStore,Distance};
+use crate::graph::{Graph,CDFGraph,CDFtoP,CSR,NodeID,GraphBuilder,EdgeMerge};
+
+/// One coarsening step
+pub struct CoarseLevel {
+    /// Coarsened graph with raw weights
+    pub graph: CSR,
+
+    /// Maps each node of the next finer level to its node in `graph`
+    pub mapping: Vec<NodeID>
+}
+
+/// Levels ordered from finest to coarsest; the first level coarsens the original graph.
+pub struct Hierarchy {
+    pub levels: Vec<CoarseLevel>
+}
+
+impl Hierarchy {
+    /// Maps each node of the original graph to its node in the coarsest graph
+    pub fn flatten(&self, num_nodes: usize) -> Vec<NodeID> {
+        let mut mapping: Vec<NodeID> = (0..num_nodes).collect();
+        for level in self.levels.iter() {
+            mapping.iter_mut().for_each(|c| *c = level.mapping[*c]);
+        }
+        mapping
+    }
+}
+
+/// Matches each node, in random order, with its unmatched neighbor of heaviest edge weight, then
+/// collapses every match into a single node.  Edges between merged nodes are summed and edges
+/// within a match are dropped.  Returns the coarse graph and the fine to coarse mapping.
+pub fn heavy_edge_matching<G, F, R>(graph: &G, node_weights: F, rng: &mut R) -> (CSR, Vec<NodeID>)
+where
+    G: Graph,
+    F: Fn(&[f32]) -> Vec<f32>,
+    R: Rng
+{
+    let mut order: Vec<NodeID> = (0..graph.len()).collect();
+    order.shuffle(rng);
+
+    let mut mapping = vec![None; graph.len()];
+    let mut num_coarse = 0;
+    for node_id in order {
+        if mapping[node_id].is_some() { continue }
+
+        mapping[node_id] = Some(num_coarse);
+        let (edges, weights) = graph.get_edges(node_id);
+        let weights = node_weights(weights);
+        let heaviest = edges.iter().zip(weights.iter())
+            .filter(|(t_n, _)| **t_n != node_id && mapping[**t_n].is_none())
+            .max_by(|a, b| a.1.total_cmp(b.1));
+
+        if let Some((t_n, _)) = heaviest {
+            mapping[*t_n] = Some(num_coarse);
+        }
+        num_coarse += 1;
+    }
+    let mapping: Vec<NodeID> = mapping.into_iter().map(|c| c.unwrap()).collect();
+
+    let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+    builder.set_num_nodes(num_coarse);
+    for node_id in 0..graph.len() {
+        let (edges, weights) = graph.get_edges(node_id);
+        edges.iter().zip(node_weights(weights)).for_each(|(t_n, w)| {
+            let (f_c, t_c) = (mapping[node_id], mapping[*t_n]);
+            if f_c != t_c {
+                builder.add_edge(f_c, t_c, w);
+            }
+        });
+    }
+    (builder.finalize_csr(), mapping)
+}
+
+pub struct Coarsener {
+    /// Stop coarsening once a level has at most this many nodes
+    pub min_nodes: usize,
+
+    pub max_levels: usize,
+
+    pub seed: u64
+}
+
+impl Coarsener {
+    /// Coarsens a graph with raw edge weights.
+    pub fn coarsen<G: Graph>(&self, graph: &G) -> Hierarchy {
+        self.coarsen_with(graph, |weights| weights.to_vec())
+    }
+
+    /// Coarsens a graph storing weights as CDFs, matching by transition probability.
+    pub fn coarsen_cdf<G: CDFGraph>(&self, graph: &G) -> Hierarchy {
+        self.coarsen_with(graph, |weights| CDFtoP::new(weights).collect())
+    }
+
+    fn coarsen_with<G: Graph, F: Fn(&[f32]) -> Vec<f32>>(&self, graph: &G, node_weights: F) -> Hierarchy {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut levels: Vec<CoarseLevel> = Vec::new();
+        let mut num_nodes = graph.len();
+        while levels.len() < self.max_levels && num_nodes > self.min_nodes {
+            let (coarse, mapping) = match levels.last() {
+                None        => heavy_edge_matching(graph, &node_weights, &mut rng),
+                Some(level) => heavy_edge_matching(&level.graph, |w| w.to_vec(), &mut rng)
+            };
+
+            // Graphs without edges left to collapse won't get any smaller
+            if coarse.len() == num_nodes { break }
+            num_nodes = coarse.len();
+            levels.push(CoarseLevel { graph: coarse, mapping });
+        }
+        Hierarchy { levels }
+    }
+}
+
+/// Copies each coarse embedding to the fine nodes mapped to it.
+pub fn prolong(coarse: &EmbeddingStore, mapping: &[NodeID]) -> EmbeddingStore {
+    let dims = coarse.dims();
+    let mut embs = vec![0f32; mapping.len() * dims];
+    embs.par_chunks_mut(dims).zip(mapping.par_iter()).for_each(|(emb, c)| {
+        emb.copy_from_slice(coarse.get_embedding(*c));
+    });
+    EmbeddingStore::new_with_vec(mapping.len(), dims, coarse.distance(), embs)
+        .expect("Sized to the mapping")
+}
+
+pub struct HierarchicalEmbedding {
+    /// Smoothing passes run at each level after prolonging
+    pub passes: usize,
+
+    /// Weight of the neighborhood average versus the node's own embedding in each pass
+    pub alpha: f32
+}
+
+impl HierarchicalEmbedding {
+    /// Learns embeddings for a graph with raw edge weights.  `train` learns embeddings on the
+    /// coarsest graph, which are then prolonged and refined level by level.
+    pub fn learn<G, T>(&self, graph: &G, hierarchy: &Hierarchy, train: T) -> EmbeddingStore
+    where
+        G: Graph + Sync,
+        T: FnOnce(&CSR) -> EmbeddingStore
+    {
+        self.learn_with(graph, hierarchy, train, |weights| weights.to_vec())
+    }
+
+    /// Learns embeddings for a graph storing weights as CDFs, as in `learn`.
+    pub fn learn_cdf<G, T>(&self, graph: &G, hierarchy: &Hierarchy, train: T) -> EmbeddingStore
+    where
+        G: CDFGraph + Sync,
+        T: FnOnce(&CSR) -> EmbeddingStore
+    {
+        self.learn_with(graph, hierarchy, train, |weights| CDFtoP::new(weights).collect())
+    }
+
+    fn learn_with<G, T, F>(&self, graph: &G, hierarchy: &Hierarchy, train: T, node_weights: F) -> EmbeddingStore
+    where
+        G: Graph + Sync,
+        T: FnOnce(&CSR) -> EmbeddingStore,
+        F: Fn(&[f32]) -> Vec<f32> + Sync
+    {
+        let levels = &hierarchy.levels;
+        let mut embs = match levels.last() {
+            Some(level) => train(&level.graph),
+
+            // Nothing to coarsen, so train on a copy of the graph itself
+            None => {
+                let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+                builder.set_num_nodes(graph.len());
+                for node_id in 0..graph.len() {
+                    let (edges, weights) = graph.get_edges(node_id);
+                    edges.iter().zip(node_weights(weights)).for_each(|(t_n, w)| builder.add_edge(node_id, *t_n, w));
+                }
+                return train(&builder.finalize_csr())
+            }
+        };
+
+        // Walk back up the hierarchy, refining on each finer graph
+        for idx in (0..levels.len()).rev() {
+            embs = prolong(&embs, &levels[idx].mapping);
+            embs = if idx > 0 {
+                smooth(&levels[idx - 1].graph, &embs, self.passes, self.alpha, |w| w.to_vec())
+            } else {
+                smooth(graph, &embs, self.passes, self.alpha, &node_weights)
+            };
+        }
+        embs
+    }
+}
+
+/// Mixes each node's embedding with the weighted average of its neighbors', `passes` times,
+/// L2 normalizing the result.  Nodes without edges keep their embedding.
+pub fn smooth<G, F>(graph: &G, embs: &EmbeddingStore, passes: usize, alpha: f32, node_weights: F) -> EmbeddingStore
+where
+    G: Graph + Sync,
+    F: Fn(&[f32]) -> Vec<f32> + Sync
+{
+    let dims = embs.dims();
+    let mut cur = embs.as_slice().to_vec();
+    for _ in 0..passes {
+        let mut next = vec![0f32; cur.len()];
+        next.par_chunks_mut(dims).enumerate().for_each(|(node_id, emb)| {
+            emb.copy_from_slice(&cur[node_id * dims..(node_id + 1) * dims]);
+            let (edges, weights) = graph.get_edges(node_id);
+            let weights = node_weights(weights);
+            let total = weights.iter().sum::<f32>();
+            if edges.is_empty() || total <= 0. { return }
+
+            emb.iter_mut().for_each(|ei| *ei *= 1. - alpha);
+            edges.iter().zip(weights.iter()).for_each(|(t_n, w)| {
+                let scale = alpha * w / total;
+                let neighbor = &cur[t_n * dims..(t_n + 1) * dims];
+                emb.iter_mut().zip(neighbor.iter()).for_each(|(ei, ni)| *ei += scale * ni);
+            });
+
+            let norm = emb.iter().map(|ei| ei * ei).sum::<f32>().sqrt();
+            if norm > 0. {
+                emb.iter_mut().for_each(|ei| *ei /= norm);
+            }
+        });
+        cur = next;
+    }
+    EmbeddingStore::new_with_vec(graph.len(), dims, embs.distance(), cur)
+        .expect("Sized to the graph")
+}
+
+/// Cheap default for training the coarsest graph: random vectors smoothed over the graph, so
+/// connected nodes end up nearby.
+pub fn random_smoothed_embeddings(graph: &CSR, dims: usize, passes: usize, alpha: f32, seed: u64) -> EmbeddingStore {
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    let init: Vec<f32> = (0..graph.len() * dims).map(|_| rng.gen::<f32>() * 2. - 1.).collect();
+    let embs = EmbeddingStore::new_with_vec(graph.len(), dims, Distance::Cosine, init)
+        .expect("Sized to the graph");
+    smooth(graph, &embs, passes, alpha, |w| w.to_vec())
+}
+
+#[cfg(test)]
+mod coarsen_tests {
+    use super::*;
+
+    /// Two cliques joined by a single light edge
+    fn build_graph() -> CSR {
+        let mut edges = Vec::new();
+        for clique in [0, 4] {
+            for i in clique..clique + 4 {
+                for j in clique..clique + 4 {
+                    if i != j { edges.push((i, j, 5.)); }
+                }
+            }
+        }
+        edges.push((3, 4, 1.));
+        edges.push((4, 3, 1.));
+        CSR::construct_from_edges(edges)
+    }
+
+    #[test]
+    fn test_coarsen() {
+        let graph = build_graph();
+        let coarsener = Coarsener { min_nodes: 2, max_levels: 10, seed: 2023 };
+        let hierarchy = coarsener.coarsen(&graph);
+        assert!(!hierarchy.levels.is_empty());
+
+        let mut num_nodes = graph.len();
+        for level in hierarchy.levels.iter() {
+            assert_eq!(level.mapping.len(), num_nodes);
+            assert!(level.graph.len() < num_nodes);
+            num_nodes = level.graph.len();
+        }
+
+        // Edge weight is conserved, minus edges collapsed inside a coarse node
+        let first = &hierarchy.levels[0].graph;
+        let total = |g: &CSR| (0..g.len()).map(|n| g.get_edges(n).1.iter().sum::<f32>()).sum::<f32>();
+        assert!(total(first) < total(&graph));
+        assert!((0..first.len()).all(|n| !first.get_edges(n).0.contains(&n)));
+
+        let flat = hierarchy.flatten(graph.len());
+        assert!(flat.iter().all(|c| *c < num_nodes));
+    }
+
+    #[test]
+    fn test_hierarchical_embedding() {
+        let graph = build_graph();
+        let coarsener = Coarsener { min_nodes: 2, max_levels: 10, seed: 2023 };
+        let hierarchy = coarsener.coarsen(&graph);
+
+        let he = HierarchicalEmbedding { passes: 3, alpha: 0.5 };
+        let embs = he.learn(&graph, &hierarchy, |coarse| random_smoothed_embeddings(coarse, 8, 0, 0.5, 0));
+        assert_eq!(embs.len(), graph.len());
+        assert_eq!(embs.dims(), 8);
+
+        let dist = |a: NodeID, b: NodeID| Distance::Cosine.compute(embs.get_embedding(a), embs.get_embedding(b));
+        assert!(dist(0, 1) < dist(0, 6));
+        assert!(dist(5, 6) < dist(1, 6));
+    }
+}
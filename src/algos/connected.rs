@@ -35,10 +35,10 @@ pub fn find_connected_components(
         while buffer.len() > 0 {
             let node_id = buffer.pop().expect("Shouldn't be empty!");
             es.get_embedding_mut(node_id)[0] = cluster_idx as f32;
-            for edge in graph.get_edges(node_id).0.iter() {
-                if !local.is_set(*edge) {
-                    local.set_bit(*edge);
-                    buffer.push(*edge);
+            for (edge, _) in graph.iter_edges(node_id) {
+                if !local.is_set(edge) {
+                    local.set_bit(edge);
+                    buffer.push(edge);
                 }
             }
         }
@@ -0,0 +1,105 @@
+//! Near-duplicate detection over an embedding store.  Every node queries the ANN index for its
+//! closest neighbors, and any pair within a distance threshold is reported.  Pairs are then
+//! grouped transitively into merge candidates, each led by a representative node.
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::algos::ann::AnnIndex;
+
+pub struct DuplicateFinder {
+    /// Neighbors retrieved per node.  Duplicates further out than the k-th neighbor are missed.
+    pub k: usize,
+
+    /// Largest distance at which two nodes are considered duplicates.  For cosine distance this
+    /// is one minus the similarity threshold.
+    pub max_distance: f32
+}
+
+impl DuplicateFinder {
+
+    /// Returns each duplicate pair once, as (smaller id, larger id, distance), sorted from
+    /// closest to furthest.  `filter` can veto pairs, such as those with different node types.
+    pub fn find<A, F>(&self, index: &A, es: &EmbeddingStore, filter: F) -> Vec<(NodeID, NodeID, f32)>
+    where
+        A: AnnIndex + ?Sized,
+        F: Fn(NodeID, NodeID) -> bool + Sync
+    {
+        let mut pairs: Vec<_> = (0..es.len()).into_par_iter().flat_map_iter(|node_id| {
+            index.predict_node(es, node_id, self.k).into_iter()
+                .filter(|nd| nd.0 <= self.max_distance && nd.1 != node_id)
+                .map(move |nd| (node_id.min(nd.1), node_id.max(nd.1), nd.0))
+                .filter(|(a, b, _)| filter(*a, *b))
+                .collect::<Vec<_>>()
+        }).collect();
+
+        // Both nodes usually find each other
+        pairs.par_sort_by_key(|(a, b, _)| (*a, *b));
+        pairs.dedup_by_key(|(a, b, _)| (*a, *b));
+        pairs.par_sort_by(|x, y| x.2.total_cmp(&y.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+        pairs
+    }
+}
+
+/// Groups duplicate pairs into connected components.  Each group is sorted by node id, so the
+/// first node is a stable representative to merge the rest into.  Groups are ordered by their
+/// representative.
+pub fn merge_groups(num_nodes: usize, pairs: &[(NodeID, NodeID, f32)]) -> Vec<Vec<NodeID>> {
+    let mut parent: Vec<NodeID> = (0..num_nodes).collect();
+    fn find(parent: &mut [NodeID], mut x: NodeID) -> NodeID {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+
+    pairs.iter().for_each(|(a, b, _)| {
+        let (ra, rb) = (find(&mut parent, *a), find(&mut parent, *b));
+        // Keeping the smaller root makes it the representative
+        if ra < rb { parent[rb] = ra; } else if rb < ra { parent[ra] = rb; }
+    });
+
+    let mut groups = vec![Vec::new(); num_nodes];
+    let mut is_duplicate = vec![false; num_nodes];
+    pairs.iter().for_each(|(a, b, _)| {
+        is_duplicate[*a] = true;
+        is_duplicate[*b] = true;
+    });
+    for node_id in 0..num_nodes {
+        if is_duplicate[node_id] {
+            let root = find(&mut parent, node_id);
+            groups[root].push(node_id);
+        }
+    }
+    groups.into_iter().filter(|g| !g.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod dedup_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+    use crate::algos::ann::Ann;
+
+    #[test]
+    fn test_find_duplicates() {
+        let mut es = EmbeddingStore::new(6, 1, Distance::Euclidean);
+        [0., 0.1, 0.14, 5., 10., 10.02].iter().enumerate()
+            .for_each(|(node_id, v)| es.set_embedding(node_id, &[*v]));
+
+        // A single leaf makes the index exact
+        let mut ann = Ann::with_params(1, 100, 1234);
+        AnnIndex::fit(&mut ann, &es);
+
+        let finder = DuplicateFinder { k: 3, max_distance: 0.12 };
+        let pairs = finder.find(&ann, &es, |_, _| true);
+        let ids: Vec<_> = pairs.iter().map(|(a, b, _)| (*a, *b)).collect();
+        assert_eq!(ids, vec![(4, 5), (1, 2), (0, 1)]);
+
+        assert_eq!(merge_groups(6, &pairs), vec![vec![0, 1, 2], vec![4, 5]]);
+
+        // Vetoing pairs with node 0 leaves 1 and 2 on their own
+        let pairs = finder.find(&ann, &es, |a, _| a != 0);
+        assert_eq!(merge_groups(6, &pairs), vec![vec![1, 2], vec![4, 5]]);
+    }
+}
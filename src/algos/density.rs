@@ -0,0 +1,336 @@
+//! Approximate HDBSCAN (Campello et al., 2013) over an embedding store.  Core distances and
+//! candidate edges come from the ANN index rather than all pairs, so the mutual reachability
+//! MST is built from the kNN graph alone.  The resulting single linkage hierarchy is condensed
+//! with a minimum cluster size, and the most stable clusters are selected by excess of mass,
+//! which finds clusters of varying density and size while leaving sparse points as noise.
+//! Components of the kNN graph which never connect are treated as infinitely far apart.
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::algos::ann::AnnIndex;
+
+/// Smallest distance used when converting to density, so duplicate embeddings stay finite
+const MIN_DISTANCE: f32 = 1e-10;
+
+pub struct DensityClustering {
+    /// Smallest group of nodes considered a cluster; anything smaller splitting off is noise
+    pub min_cluster_size: usize,
+
+    /// Neighbor used for the core distance.  Larger values smooth the density estimate and
+    /// label more nodes as noise.
+    pub min_samples: usize,
+
+    /// Number of neighbors retrieved per node for the candidate edges.  Raised to min_samples
+    /// if smaller.
+    pub k: usize
+}
+
+/// Result of density clustering a store
+pub struct DensityClusters {
+    /// Cluster for each node in the store, or None if it's noise
+    pub labels: Vec<Option<usize>>,
+
+    /// GLOSH outlier score for each node, ~ [0, 1].  Higher means the node sits in a sparser
+    /// region than the densest part of its cluster.
+    pub outlier_scores: Vec<f32>,
+
+    pub num_clusters: usize
+}
+
+impl DensityClusters {
+
+    /// Number of nodes in each cluster
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.num_clusters];
+        self.labels.iter().flatten().for_each(|c| sizes[*c] += 1);
+        sizes
+    }
+
+    /// Members of each cluster
+    pub fn members(&self) -> Vec<Vec<NodeID>> {
+        let mut members = vec![Vec::new(); self.num_clusters];
+        self.labels.iter().enumerate().for_each(|(node_id, c)| {
+            if let Some(c) = c { members[*c].push(node_id); }
+        });
+        members
+    }
+
+    /// Nodes which weren't assigned a cluster
+    pub fn noise(&self) -> Vec<NodeID> {
+        self.labels.iter().enumerate()
+            .filter(|(_, c)| c.is_none())
+            .map(|(node_id, _)| node_id)
+            .collect()
+    }
+}
+
+/// Merge in the single linkage hierarchy.  Ids below n are nodes; merge i has id n + i.
+struct Merge {
+    left: usize,
+    right: usize,
+    distance: f32,
+    size: usize
+}
+
+/// Edge in the condensed tree, from a cluster to either a node that falls out of it or a child
+/// cluster.  Cluster ids start at n, with n being the root.
+struct CondensedEdge {
+    parent: usize,
+    child: usize,
+    lambda: f32,
+    size: usize
+}
+
+impl DensityClustering {
+
+    pub fn fit<A: AnnIndex + ?Sized>(&self, index: &A, es: &EmbeddingStore) -> DensityClusters {
+        let n = es.len();
+        if n == 0 {
+            return DensityClusters { labels: Vec::new(), outlier_scores: Vec::new(), num_clusters: 0 }
+        }
+
+        let min_samples = self.min_samples.max(1);
+        let k = self.k.max(min_samples);
+        let neighbors: Vec<_> = (0..n).into_par_iter()
+            .map(|node_id| index.predict_node(es, node_id, k))
+            .collect();
+
+        let core: Vec<f32> = neighbors.par_iter().map(|nds| {
+            nds.get(min_samples.min(nds.len()).saturating_sub(1))
+                .map(|nd| nd.0)
+                .unwrap_or(f32::INFINITY)
+        }).collect();
+
+        let mut edges: Vec<(f32, NodeID, NodeID)> = neighbors.par_iter().enumerate()
+            .flat_map_iter(|(node_id, nds)| {
+                let core = &core;
+                nds.iter().map(move |nd| {
+                    (nd.0.max(core[node_id]).max(core[nd.1]), node_id, nd.1)
+                })
+            }).collect();
+        edges.par_sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let merges = single_linkage(n, edges);
+        let condensed = condense(n, &merges, self.min_cluster_size.max(2));
+        label(n, &condensed)
+    }
+}
+
+/// Kruskal's over the mutual reachability edges, recording each merge.  Disconnected components
+/// are joined at the end at infinite distance so the hierarchy has a single root.
+fn single_linkage(n: usize, edges: Vec<(f32, NodeID, NodeID)>) -> Vec<Merge> {
+    let mut parent: Vec<usize> = (0..(2 * n).max(1)).collect();
+    let mut sizes = vec![1; n];
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+
+    let mut merge = |parent: &mut [usize], left: usize, right: usize, distance: f32| {
+        let id = n + merges.len();
+        let size = sizes[left] + sizes[right];
+        parent[left] = id;
+        parent[right] = id;
+        sizes.push(size);
+        merges.push(Merge { left, right, distance, size });
+        id
+    };
+
+    for (distance, f_n, t_n) in edges {
+        let (left, right) = (find(&mut parent, f_n), find(&mut parent, t_n));
+        if left != right {
+            merge(&mut parent, left, right, distance);
+        }
+    }
+
+    // Remaining roots are separate components
+    let mut roots: Vec<usize> = (0..n).map(|node_id| find(&mut parent, node_id)).collect();
+    roots.sort_unstable();
+    roots.dedup();
+    let mut root = roots[0];
+    for other in roots.into_iter().skip(1) {
+        root = merge(&mut parent, root, other, f32::INFINITY);
+    }
+    merges
+}
+
+/// Walks the hierarchy from the root, only creating a new cluster when both sides of a split
+/// have at least min_cluster_size nodes.  Smaller sides fall out of their parent as noise.
+fn condense(n: usize, merges: &[Merge], min_cluster_size: usize) -> Vec<CondensedEdge> {
+    let size = |id: usize| if id < n { 1 } else { merges[id - n].size };
+    let mut tree = Vec::new();
+    if merges.is_empty() {
+        // Single node
+        tree.push(CondensedEdge { parent: n, child: 0, lambda: 0., size: 1 });
+        return tree
+    }
+
+    let root = n + merges.len() - 1;
+    let mut relabel = vec![0; n + merges.len()];
+    relabel[root] = n;
+    let mut next_label = n + 1;
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if id < n { continue }
+
+        let merge = &merges[id - n];
+        let lambda = 1. / merge.distance.max(MIN_DISTANCE);
+        let parent = relabel[id];
+        let sides = [merge.left, merge.right];
+        let is_split = sides.iter().all(|s| size(*s) >= min_cluster_size);
+        for side in sides {
+            if is_split {
+                relabel[side] = next_label;
+                tree.push(CondensedEdge { parent, child: next_label, lambda, size: size(side) });
+                next_label += 1;
+                stack.push(side);
+            } else if size(side) >= min_cluster_size {
+                // Cluster shrinks but keeps its identity
+                relabel[side] = parent;
+                stack.push(side);
+            } else {
+                let mut sub = vec![side];
+                while let Some(s) = sub.pop() {
+                    if s < n {
+                        tree.push(CondensedEdge { parent, child: s, lambda, size: 1 });
+                    } else {
+                        sub.push(merges[s - n].left);
+                        sub.push(merges[s - n].right);
+                    }
+                }
+            }
+        }
+    }
+    tree
+}
+
+/// Selects clusters by excess of mass and labels the nodes within them.  The root is never
+/// selected, since it would label everything as a single cluster.
+fn label(n: usize, tree: &[CondensedEdge]) -> DensityClusters {
+    let num_condensed = tree.iter().map(|e| e.parent.max(e.child)).fold(n, usize::max) + 1 - n;
+
+    let mut birth = vec![0f32; num_condensed];
+    let mut parent_of = vec![None; num_condensed];
+    let mut children = vec![Vec::new(); num_condensed];
+    let mut node_parent = vec![0; n];
+    let mut node_lambda = vec![0f32; n];
+    for e in tree.iter() {
+        if e.child >= n {
+            birth[e.child - n] = e.lambda;
+            parent_of[e.child - n] = Some(e.parent - n);
+            children[e.parent - n].push(e.child - n);
+        } else {
+            node_parent[e.child] = e.parent - n;
+            node_lambda[e.child] = e.lambda;
+        }
+    }
+
+    let mut stability = vec![0f32; num_condensed];
+    tree.iter().for_each(|e| {
+        let p = e.parent - n;
+        stability[p] += (e.lambda - birth[p]) * e.size as f32;
+    });
+
+    // Children always have larger ids than their parents, so this runs bottom up
+    let mut selected = vec![false; num_condensed];
+    for c in (1..num_condensed).rev() {
+        let child_stability = children[c].iter().map(|cc| stability[*cc]).sum::<f32>();
+        if !children[c].is_empty() && child_stability > stability[c] {
+            stability[c] = child_stability;
+        } else {
+            selected[c] = true;
+            let mut stack = children[c].clone();
+            while let Some(cc) = stack.pop() {
+                selected[cc] = false;
+                stack.extend(children[cc].iter().cloned());
+            }
+        }
+    }
+
+    let mut ids = vec![None; num_condensed];
+    let mut num_clusters = 0;
+    for c in 0..num_condensed {
+        if selected[c] {
+            ids[c] = Some(num_clusters);
+            num_clusters += 1;
+        }
+    }
+
+    let labels = (0..n).map(|node_id| {
+        let mut c = Some(node_parent[node_id]);
+        while let Some(cur) = c {
+            if selected[cur] { return ids[cur] }
+            c = parent_of[cur];
+        }
+        None
+    }).collect();
+
+    // GLOSH: compare where a node falls out against the densest point of its cluster
+    let mut max_lambda = vec![0f32; num_condensed];
+    (0..n).for_each(|node_id| {
+        let p = node_parent[node_id];
+        max_lambda[p] = max_lambda[p].max(node_lambda[node_id]);
+    });
+    for c in (1..num_condensed).rev() {
+        if let Some(p) = parent_of[c] {
+            max_lambda[p] = max_lambda[p].max(max_lambda[c]);
+        }
+    }
+
+    let outlier_scores = (0..n).map(|node_id| {
+        let max = max_lambda[node_parent[node_id]];
+        if max > 0. { (max - node_lambda[node_id]) / max } else { 0. }
+    }).collect();
+
+    DensityClusters { labels, outlier_scores, num_clusters }
+}
+
+#[cfg(test)]
+mod density_tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand_xorshift::XorShiftRng;
+    use crate::embeddings::Distance;
+    use crate::algos::ann::Ann;
+
+    #[test]
+    fn test_two_blobs() {
+        // Two blobs, a far away pair, and a lone point off the first blob
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(43, 2, Distance::Euclidean);
+        for node_id in 0..40 {
+            let c = if node_id % 2 == 0 { 0. } else { 10. };
+            es.set_embedding(node_id, &[c + rng.gen::<f32>() - 0.5, c + rng.gen::<f32>() - 0.5]);
+        }
+        es.set_embedding(40, &[30., 30.]);
+        es.set_embedding(41, &[30.5, 30.]);
+        es.set_embedding(42, &[5., -5.]);
+
+        // A single leaf makes the index exact
+        let mut ann = Ann::with_params(1, 100, 1234);
+        AnnIndex::fit(&mut ann, &es);
+
+        let dc = DensityClustering { min_cluster_size: 5, min_samples: 3, k: 42 };
+        let clusters = dc.fit(&ann, &es);
+        assert_eq!(clusters.num_clusters, 2);
+        for node_id in 2..40 {
+            assert_eq!(clusters.labels[node_id], clusters.labels[node_id % 2]);
+        }
+        assert_ne!(clusters.labels[0], clusters.labels[1]);
+
+        // The pair is too small to be a cluster
+        assert_eq!(clusters.noise(), vec![40, 41]);
+        assert_eq!(clusters.sizes().iter().sum::<usize>(), 41);
+
+        // The lone point joins the first blob, but only as an outlier
+        assert_eq!(clusters.labels[42], clusters.labels[0]);
+        assert!(clusters.outlier_scores[42] > 0.9);
+        assert!(clusters.outlier_scores[..40].iter().all(|s| *s < 0.9));
+    }
+}
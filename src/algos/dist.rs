@@ -36,10 +36,10 @@ pub fn unweighted_walk_distance(
 
     while let Some((vert, cur_dist)) = queue.pop_front() {
         distance[vert] = cur_dist;
-        for out_edge in graph.get_edges(vert).0.iter() {
-            if !seen.is_set(*out_edge) {
-                seen.set_bit(*out_edge);
-                queue.push_back((*out_edge, cur_dist + 1));
+        for (out_edge, _) in graph.iter_edges(vert) {
+            if !seen.is_set(out_edge) {
+                seen.set_bit(out_edge);
+                queue.push_back((out_edge, cur_dist + 1));
             }
         }
     }   
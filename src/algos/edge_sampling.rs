@@ -0,0 +1,133 @@
+//! Samples edges directly from CSR offsets without materializing the edge list, for building
+//! evaluation splits and edge level negatives.  Edges are sampled with replacement.
+use rand::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,NodeID};
+
+/// Finds the node whose edge range contains the global edge index.  Assumes edge ranges are
+/// contiguous and ordered by node, as in CSR formats.
+fn edge_source<G: Graph>(graph: &G, edge_idx: usize) -> NodeID {
+    let (mut lo, mut hi) = (0, graph.len());
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        if graph.get_edge_range(mid).0 <= edge_idx {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Samples `n` edges from a graph with raw edge weights, returning (from, to, weight) triples.
+/// When weighted, edges are sampled proportional to their weight; otherwise uniformly.
+pub fn sample_edges<G: Graph, R: Rng>(
+    graph: &G,
+    n: usize,
+    weighted: bool,
+    rng: &mut R
+) -> Vec<(NodeID, NodeID, f32)> {
+    if graph.edges() == 0 {
+        return Vec::new()
+    }
+
+    if !weighted {
+        return (0..n).map(|_| {
+            let edge_idx = rng.gen_range(0, graph.edges());
+            let node_id = edge_source(graph, edge_idx);
+            let (start, _) = graph.get_edge_range(node_id);
+            let (edges, weights) = graph.get_edges(node_id);
+            (node_id, edges[edge_idx - start], weights[edge_idx - start])
+        }).collect()
+    }
+
+    // Cumulative weight by node keeps memory proportional to nodes rather than edges
+    let mut totals = Vec::with_capacity(graph.len() + 1);
+    let mut acc = 0f64;
+    totals.push(acc);
+    for node_id in 0..graph.len() {
+        acc += graph.get_edges(node_id).1.iter().map(|w| *w as f64).sum::<f64>();
+        totals.push(acc);
+    }
+    if acc <= 0. {
+        return Vec::new()
+    }
+
+    (0..n).map(|_| {
+        let target = rng.gen::<f64>() * acc;
+        let node_id = (totals.partition_point(|t| *t <= target) - 1).min(graph.len() - 1);
+        let (edges, weights) = graph.get_edges(node_id);
+        let mut remaining = target - totals[node_id];
+        let mut idx = 0;
+        while idx + 1 < edges.len() && remaining >= weights[idx] as f64 {
+            remaining -= weights[idx] as f64;
+            idx += 1;
+        }
+        (node_id, edges[idx], weights[idx])
+    }).collect()
+}
+
+/// Samples `n` edges from a graph storing weights as CDFs, returning (from, to, probability)
+/// triples.  Sources are always chosen proportional to out degree; when weighted, the edge is
+/// then chosen by its transition probability rather than uniformly.
+pub fn sample_edges_cdf<G: CDFGraph, R: Rng>(
+    graph: &G,
+    n: usize,
+    weighted: bool,
+    rng: &mut R
+) -> Vec<(NodeID, NodeID, f32)> {
+    if graph.edges() == 0 {
+        return Vec::new()
+    }
+
+    (0..n).map(|_| {
+        let edge_idx = rng.gen_range(0, graph.edges());
+        let node_id = edge_source(graph, edge_idx);
+        let (edges, weights) = graph.get_edges(node_id);
+        let idx = if weighted {
+            let p = rng.gen::<f32>() * weights[weights.len() - 1];
+            weights.partition_point(|w| *w <= p).min(weights.len() - 1)
+        } else {
+            edge_idx - graph.get_edge_range(node_id).0
+        };
+        (node_id, edges[idx], CDFtoP::new(weights).prob(idx))
+    }).collect()
+}
+
+#[cfg(test)]
+mod edge_sampling_tests {
+    use super::*;
+    use rand_xorshift::XorShiftRng;
+    use crate::graph::{CSR,CumCSR};
+
+    #[test]
+    fn test_sample_edges() {
+        let edges = vec![
+            (0, 1, 1.),
+            (0, 2, 3.),
+            (2, 0, 0.),
+            (4, 1, 4.),
+        ];
+        let csr = CSR::construct_from_edges(edges);
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+
+        let samples = sample_edges(&csr, 8000, false, &mut rng);
+        assert_eq!(samples.len(), 8000);
+        let count = |s: &[(NodeID, NodeID, f32)], e: (NodeID, NodeID)| s.iter().filter(|x| (x.0, x.1) == e).count();
+        for e in [(0, 1), (0, 2), (2, 0), (4, 1)] {
+            assert!((count(&samples, e) as f32 - 2000.).abs() < 200.);
+        }
+
+        let samples = sample_edges(&csr, 8000, true, &mut rng);
+        assert_eq!(count(&samples, (2, 0)), 0);
+        assert!((count(&samples, (0, 1)) as f32 - 1000.).abs() < 150.);
+        assert!((count(&samples, (4, 1)) as f32 - 4000.).abs() < 200.);
+        assert!(samples.iter().all(|(f, t, w)| csr.get_edges(*f).0.contains(t) && *w > 0.));
+
+        let ccsr = CumCSR::convert(csr);
+        let samples = sample_edges_cdf(&ccsr, 8000, true, &mut rng);
+        assert!((count(&samples, (0, 2)) as f32 - 3000.).abs() < 200.);
+        assert!(samples.iter().filter(|x| (x.0, x.1) == (0, 2)).all(|x| x.2 == 0.75));
+        assert!((count(&samples, (2, 0)) as f32 - 2000.).abs() < 200.);
+    }
+}
@@ -4,6 +4,7 @@
 pub mod loss;
 pub mod model;
 pub mod attention;
+pub mod supervised;
 
 use std::fmt::Write;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -17,15 +18,28 @@ use rand_xorshift::XorShiftRng;
 use simple_grad::*;
 
 use crate::graph::{Graph as CGraph,NodeID};
-use crate::embeddings::{EmbeddingStore,Distance};
+use crate::embeddings::{EmbeddingStore,Distance,randomize_embedding_store,randomize_embedding};
 use crate::progress::CLProgressBar;
 use crate::feature_store::FeatureStore;
+use crate::vocab::Vocab;
 use crate::algos::grad_utils::scheduler::LRScheduler;
 use crate::algos::grad_utils::optimizer::{Optimizer,AdamOptimizer};
 use crate::algos::grad_utils::node_sampler::*;
 
 use self::loss::*;
 use self::model::{Model,NodeCounts};
+use self::supervised::*;
+
+/// How `extend_feature_embeddings` initializes features without a trained embedding
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum FeatureInit {
+    /// Random unit vectors, the same as a fresh model
+    Random,
+
+    /// The average embedding of the nodes carrying the feature, each node being the mean of its
+    /// already trained features.  Falls back to random when no such node exists.
+    Average
+}
 
 #[derive(Clone,Copy,Debug)]
 pub enum LossWeighting {
@@ -83,9 +97,25 @@ impl EmbeddingPropagation {
         feature_embeddings: Option<EmbeddingStore>,
         model: &M
     ) -> EmbeddingStore {
-        let feat_embeds = self.learn_feature_embeddings(graph, features, feature_embeddings, model);
+        let (feat_embeds, _) = self.learn_feature_embeddings(
+            graph, features, feature_embeddings, model, None);
         feat_embeds
     }
+
+    /// Jointly learns the feature embeddings with a linear softmax classifier over the node
+    /// embeddings of labeled nodes.
+    pub fn learn_supervised<G: CGraph + Send + Sync, M: Model>(
+        &self, 
+        graph: &G, 
+        features: &FeatureStore,
+        feature_embeddings: Option<EmbeddingStore>,
+        model: &M,
+        labels: &NodeLabels
+    ) -> (EmbeddingStore, Classifier) {
+        let (feat_embeds, classifier) = self.learn_feature_embeddings(
+            graph, features, feature_embeddings, model, Some(labels));
+        (feat_embeds, classifier.expect("Classifier is always trained with labels"))
+    }
     
     // The uber expensive function
     fn learn_feature_embeddings<G: CGraph + Send + Sync, M: Model>(
@@ -93,8 +123,9 @@ impl EmbeddingPropagation {
         graph: &G,
         features: &FeatureStore,
         feature_embeddings: Option<EmbeddingStore>,
-        model: &M
-    ) -> EmbeddingStore {
+        model: &M,
+        labels: Option<&NodeLabels>
+    ) -> (EmbeddingStore, Option<Classifier>) {
 
         let mut rng = XorShiftRng::seed_from_u64(self.seed);
 
@@ -114,6 +145,22 @@ impl EmbeddingPropagation {
             feature_embeddings.dims(), 
             feature_embeddings.len()); 
 
+        // Classifier parameters live in their own store, one row per class with the bias as the
+        // last column.  Node embeddings can differ in size from the feature embeddings under
+        // attention so we build one to find out.
+        let classifier = labels.map(|labels| {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed + 1);
+            let node_dims = if graph.len() > 0 {
+                model.construct_node_embedding(0, 1f32, features, &feature_embeddings, &mut rng)
+                    .1.value().len()
+            } else {
+                self.d_model
+            };
+            let es = EmbeddingStore::new(labels.num_classes, node_dims + 1, Distance::Cosine);
+            let optimizer = AdamOptimizer::new(0.9, 0.999, es.dims(), es.len());
+            (es, optimizer)
+        });
+
         // Pull out validation idxs;
         let mut node_idxs: Vec<_> = (0..graph.len()).into_iter().collect();
         node_idxs.shuffle(&mut rng);
@@ -182,7 +229,7 @@ impl EmbeddingPropagation {
                 // Compute grads for batch
                 nodes.par_iter().map(|node_id| {
                     let mut rng = XorShiftRng::seed_from_u64(self.seed + (i + **node_id) as u64);
-                    let (mut loss, hv_vars, thv_vars, hu_vars) = self.run_forward_pass(
+                    let (mut loss, hv, hv_vars, thv_vars, hu_vars) = self.run_forward_pass(
                         graph, **node_id, &features, &feature_embeddings, 
                         model, &sampler, &mut rng);
 
@@ -199,8 +246,21 @@ impl EmbeddingPropagation {
                             loss
                         }
                     };
-                    let grads = self.extract_gradients(&loss, hv_vars, thv_vars, hu_vars);
-                    (loss.value()[0], grads)
+
+                    // Labeled nodes also have to predict their class
+                    let head = match (labels, classifier.as_ref()) {
+                        (Some(labels), Some((es, _))) => labels.labels.get(*node_id).map(|label| {
+                            classification_loss(&hv, *label, es, labels.weight)
+                        }),
+                        _ => None
+                    };
+                    let head_vars = head.map(|(ce, vars)| {
+                        loss = loss.clone() + ce;
+                        vars
+                    });
+                    let (grads, head_grads) = self.extract_gradients(
+                        &loss, hv_vars, thv_vars, hu_vars, head_vars);
+                    (loss.value()[0], grads, head_grads)
                 }).collect_into_vec(&mut grads);
 
                 let mut error = 0f32;
@@ -211,14 +271,19 @@ impl EmbeddingPropagation {
                 // on 0.13.  We'll keep testing it on subsequent fixes but until then
                 // std is the way to go.
                 let mut all_grads = CHashMap::new();
+                let mut all_head_grads = CHashMap::new();
 
                 // Since we're dealing with multiple reconstructions with likely shared features,
                 // we aggregate all the gradients
-                for (err, grad_set) in grads.drain(..nodes.len()) {
+                for (err, grad_set, head_grads) in grads.drain(..nodes.len()) {
                     for (feat, grad) in grad_set.into_iter() {
                         let e = all_grads.entry(feat).or_insert_with(|| vec![0.; grad.len()]);
                         e.iter_mut().zip(grad.iter()).for_each(|(ei, gi)| *ei += *gi);
                     }
+                    for (class, grad) in head_grads.into_iter() {
+                        let e = all_head_grads.entry(class).or_insert_with(|| vec![0.; grad.len()]);
+                        e.iter_mut().zip(grad.iter()).for_each(|(ei, gi)| *ei += *gi);
+                    }
                     error += err;
                     cnt += 1f32;
                 }
@@ -240,6 +305,11 @@ impl EmbeddingPropagation {
                     // Backpropagate embeddings
                     let alpha = lr_scheduler.compute(cur_step);
                     optimizer.update(&feature_embeddings, all_grads, alpha, pass as f32);
+                    if let Some((es, head_optimizer)) = classifier.as_ref() {
+                        if !all_head_grads.is_empty() {
+                            head_optimizer.update(es, all_head_grads, alpha, pass as f32);
+                        }
+                    }
                 }
 
                 // Update progress bar
@@ -275,7 +345,8 @@ impl EmbeddingPropagation {
             }
         }
         pb.finish();
-        feature_embeddings
+        let classifier = classifier.map(|(es, _)| Classifier::from_store(&es));
+        (feature_embeddings, classifier)
     }
 
     fn run_forward_pass<G: CGraph + Send + Sync, R: Rng, S: NodeSampler, M: Model>(
@@ -287,7 +358,7 @@ impl EmbeddingPropagation {
         model: &M,
         sampler: &S,
         rng: &mut R
-    ) -> (ANode, NodeCounts, NodeCounts, Vec<NodeCounts>) {
+    ) -> (ANode, ANode, NodeCounts, NodeCounts, Vec<NodeCounts>) {
         // h(v)
         let (hv_vars, hv) = model.construct_node_embedding(
             node, 1f32, features, &feature_embeddings, rng);
@@ -314,7 +385,7 @@ impl EmbeddingPropagation {
         // Compute error
         let loss = self.loss.compute(thv, hv.clone(), &hus);
 
-        (loss, hv_vars, thv_vars, hu_vars)
+        (loss, hv, hv_vars, thv_vars, hu_vars)
 
     }
 
@@ -323,8 +394,9 @@ impl EmbeddingPropagation {
         loss: &ANode,
         hv_vars: NodeCounts,
         thv_vars: NodeCounts,
-        hu_vars: Vec<NodeCounts>
-    ) -> HashMap<usize, Vec<f32>> {
+        hu_vars: Vec<NodeCounts>,
+        head_vars: Option<Vec<(ANode, ANode)>>
+    ) -> (HashMap<usize, Vec<f32>>, Vec<(usize, Vec<f32>)>) {
 
         // Compute gradients
         let mut agraph = Graph::new();
@@ -343,7 +415,11 @@ impl EmbeddingPropagation {
             extract_grads(&agraph, &mut grads, hu_var.into_iter());
         });
 
-        grads
+        let head_grads = head_vars
+            .map(|vars| extract_classifier_grads(&agraph, vars))
+            .unwrap_or_default();
+
+        (grads, head_grads)
 
     }
 
@@ -369,18 +445,68 @@ pub fn extract_grads(
     }
 }
 
-// Randomize embeddings.  Might make more sense to have in the embedding file.
-fn randomize_embedding_store(es: &mut EmbeddingStore, rng: &mut impl Rng) {
-    for idx in 0..es.len() {
-        let e = es.get_embedding_mut(idx);
-        let mut norm = 0f32;
-        e.iter_mut().for_each(|ei| {
-            *ei = 2f32 * rng.gen::<f32>() - 1f32;
-            norm += ei.powf(2f32);
-        });
-        norm = norm.sqrt();
-        e.iter_mut().for_each(|ei| *ei /= norm);
+/// Aligns previously trained feature embeddings to a FeatureStore which has since gained
+/// features, so training can continue where it left off.  Rows of the returned store follow the
+/// store's feature ids: known features keep their embeddings, matched by name through `vocab`,
+/// and new features are initialized according to `init`.
+pub fn extend_feature_embeddings(
+    features: &FeatureStore,
+    vocab: &Vocab,
+    embeddings: &EmbeddingStore,
+    init: FeatureInit,
+    seed: u64
+) -> EmbeddingStore {
+    let feat_vocab = features.get_vocab();
+    let num_features = features.num_features();
+    let dims = embeddings.dims();
+    let old_ids: Vec<Option<NodeID>> = (0..num_features).into_par_iter()
+        .map(|feat_id| vocab.translate_node(feat_vocab, feat_id))
+        .collect();
+
+    let mut es = EmbeddingStore::new(num_features, dims, embeddings.distance());
+    old_ids.iter().enumerate().for_each(|(feat_id, old_id)| {
+        if let Some(old_id) = old_id {
+            es.set_embedding(feat_id, embeddings.get_embedding(*old_id));
+        }
+    });
+
+    // Sum the node embeddings of every node carrying each new feature
+    let mut counts = vec![0usize; num_features];
+    let mut sums = vec![0f32; if init == FeatureInit::Average { num_features * dims } else { 0 }];
+    if init == FeatureInit::Average {
+        let mut node_emb = vec![0f32; dims];
+        for feats in features.iter() {
+            let known: Vec<NodeID> = feats.iter().filter_map(|f_i| old_ids[*f_i as usize]).collect();
+            if known.is_empty() || known.len() == feats.len() { continue }
+
+            node_emb.iter_mut().for_each(|x| *x = 0.);
+            known.iter().for_each(|old_id| {
+                node_emb.iter_mut().zip(embeddings.get_embedding(*old_id).iter())
+                    .for_each(|(x, ei)| *x += ei / known.len() as f32);
+            });
+            feats.iter().filter(|f_i| old_ids[**f_i as usize].is_none()).for_each(|f_i| {
+                let f_i = *f_i as usize;
+                counts[f_i] += 1;
+                sums[f_i * dims..(f_i + 1) * dims].iter_mut().zip(node_emb.iter())
+                    .for_each(|(si, ni)| *si += ni);
+            });
+        }
     }
+
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    let mut emb = vec![0f32; dims];
+    for feat_id in 0..num_features {
+        if old_ids[feat_id].is_some() { continue }
+
+        if counts[feat_id] > 0 {
+            emb.iter_mut().zip(sums[feat_id * dims..(feat_id + 1) * dims].iter())
+                .for_each(|(ei, si)| *ei = si / counts[feat_id] as f32);
+        } else {
+            randomize_embedding(&mut emb, &mut rng);
+        }
+        es.set_embedding(feat_id, &emb);
+    }
+    es
 }
 
 
@@ -401,6 +527,32 @@ mod ep_tests {
         edges
     }
 
+    #[test]
+    fn test_extend_feature_embeddings() {
+        let mut fs = FeatureStore::new(3, "feat".to_string());
+        fs.set_features(0, vec!["a".into(), "b".into()]);
+        let vocab = fs.clone_vocab();
+        let mut es = EmbeddingStore::new(2, 2, Distance::Cosine);
+        es.set_embedding(0, &[1., 0.]);
+        es.set_embedding(1, &[0., 1.]);
+
+        // "c" shows up next to both known features, "d" next to nothing trained
+        fs.add_features(0, vec!["c".into()]);
+        fs.set_features(1, vec!["a".into(), "c".into()]);
+        fs.set_features(2, vec!["d".into()]);
+
+        let extended = extend_feature_embeddings(&fs, &vocab, &es, FeatureInit::Average, 2023);
+        assert_eq!(extended.len(), 4);
+        assert_eq!(extended.get_embedding(1), &[0., 1.]);
+        assert_eq!(extended.get_embedding(2), &[0.75, 0.25]);
+        let norm = extended.get_embedding(3).iter().map(|x| x * x).sum::<f32>();
+        assert!((norm - 1.).abs() < 1e-5);
+
+        let extended = extend_feature_embeddings(&fs, &vocab, &es, FeatureInit::Random, 2023);
+        assert_eq!(extended.get_embedding(0), &[1., 0.]);
+        assert_ne!(extended.get_embedding(2), &[0.75, 0.25]);
+    }
+
     #[test]
     fn test_simple_learn_dist() {
         let edges = build_star_edges();
@@ -410,12 +562,13 @@ mod ep_tests {
         let mut feature_store = FeatureStore::new(ccsr.len(), "feat".to_string());
         feature_store.fill_missing_nodes();
 
-        let model = super::model::AveragedFeatureModel::new(None, None);
+        let model = super::model::AveragedFeatureModel::new(None, None, false, false, super::model::NamespaceOptions::new());
         let ep = EmbeddingPropagation {
             alpha: 1e-2,
             loss: Loss::MarginLoss(1f32, 1usize),
             batch_size: 32,
             hard_negs: 0,
+            loss_weighting: LossWeighting::None,
             d_model: 5,
             valid_pct: 0.0,
             passes: 50,
@@ -424,7 +577,8 @@ mod ep_tests {
             indicator: false
         };
 
-        let embeddings = ep.learn_feature_embeddings(&ccsr, &feature_store, None, &model);
+        let (embeddings, _) = ep.learn_feature_embeddings(
+            &ccsr, &feature_store, None, &model, None);
         for idx in 0..embeddings.len() {
             let e = embeddings.get_embedding(idx);
             println!("{:?} -> {:?}", idx, e);
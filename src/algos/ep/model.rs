@@ -67,7 +67,11 @@ pub struct AveragedFeatureModel {
     weighted_neighbor_sampling: bool,
        
     /// If true, during reconstruction, nodes are blended according to their edge weights.
-    weighted_neighbor_averaging: bool
+    weighted_neighbor_averaging: bool,
+
+    /// Per feature namespace sampling and weighting.  Empty aggregates all features together;
+    /// otherwise `max_features` applies to each namespace separately unless overridden.
+    namespaces: NamespaceOptions
 }
 
 impl AveragedFeatureModel {
@@ -75,15 +79,21 @@ impl AveragedFeatureModel {
         max_features: Option<usize>,
         max_neighbor_nodes: Option<usize>,
         weighted_neighbor_sampling: bool,
-        weighted_neighbor_averaging: bool
+        weighted_neighbor_averaging: bool,
+        namespaces: NamespaceOptions
     ) -> Self {
         AveragedFeatureModel { 
             max_features, 
             max_neighbor_nodes, 
             weighted_neighbor_averaging,
-            weighted_neighbor_sampling
+            weighted_neighbor_sampling,
+            namespaces
         }
     }
+
+    fn namespaces(&self) -> Option<&NamespaceOptions> {
+        if self.namespaces.is_empty() { None } else { Some(&self.namespaces) }
+    }
 }
 
 impl Model for AveragedFeatureModel {
@@ -101,6 +111,7 @@ impl Model for AveragedFeatureModel {
             feature_store,
             feature_embeddings,
             self.max_features,
+            self.namespaces(),
             rng)
     }
 
@@ -119,6 +130,7 @@ impl Model for AveragedFeatureModel {
             feature_embeddings,
             self.max_neighbor_nodes,
             self.max_features,
+            self.namespaces(),
             None,
             self.weighted_neighbor_sampling,
             self.weighted_neighbor_averaging,
@@ -136,6 +148,7 @@ impl Model for AveragedFeatureModel {
             nodes, feature_store, 
             feature_embeddings, 
             self.max_features,
+            self.namespaces(),
             None, rng)
     }
 
@@ -214,6 +227,7 @@ impl Model for AttentionFeatureModel {
             feature_embeddings,
             self.max_neighbor_nodes,
             self.max_features,
+            None,
             Some(self.mha.clone()),
             self.weighted_neighbor_sampling,
             false,
@@ -231,6 +245,7 @@ impl Model for AttentionFeatureModel {
             nodes, feature_store, 
             feature_embeddings, 
             self.max_features,
+            None,
             Some(self.mha.clone()), rng)
     }
 
@@ -253,6 +268,21 @@ impl Model for AttentionFeatureModel {
 /// probably be abstracted better.
 pub type NodeCounts = HashMap<usize, (ANode, f32)>;
 
+/// Aggregation options for the features within a namespace
+#[derive(Clone, Copy, Debug)]
+pub struct NamespaceAggregation {
+    /// Randomly sample at most this many of the namespace's features per node.  Falls back to
+    /// the model's max_features.
+    pub max_features: Option<usize>,
+
+    /// Multiplies the namespace's features when averaging.  Zero drops the namespace.
+    pub weight: f32
+}
+
+/// Aggregation options keyed by feature namespace.  Namespaces without an entry are sampled
+/// with the model's max_features and weighted by one.
+pub type NamespaceOptions = HashMap<String, NamespaceAggregation>;
+
 /// Gets the feature embeddings for a node, adding or updating the counts
 pub fn collect_embeddings_from_node<R: Rng>(
     node: NodeID,
@@ -261,21 +291,61 @@ pub fn collect_embeddings_from_node<R: Rng>(
     feature_embeddings: &EmbeddingStore,
     feat_map: &mut NodeCounts,
     max_features: Option<usize>,
+    namespaces: Option<&NamespaceOptions>,
     rng: &mut R
 ) {
-    let feats = feature_store.get_features(node);
-    let max_features = max_features.unwrap_or(feats.len());
-    for feat in feats.choose_multiple(rng, max_features) {
-        if let Some((_emb, count)) = feat_map.get_mut(feat) {
-            *count += weight;
-        } else {
-            let emb = feature_embeddings.get_embedding(*feat);
-            let v = Variable::pooled(emb);
-            feat_map.insert(*feat, (v, weight));
+    // Features carry their TF-IDF weight once the store has computed it
+    let feats: Vec<(usize, f32)> = match feature_store.get_feature_weights(node) {
+        Some(weights) => feature_store.get_features(node).iter().map(|f| *f as usize).zip(weights).collect(),
+        None => feature_store.get_features(node).iter().map(|f| (*f as usize, 1f32)).collect()
+    };
+
+    let namespaces = match namespaces {
+        Some(namespaces) => namespaces,
+        None => {
+            let max_features = max_features.unwrap_or(feats.len());
+            for (feat, fw) in feats.choose_multiple(rng, max_features) {
+                add_feature(*feat, weight * fw, feature_embeddings, feat_map);
+            }
+            return
+        }
+    };
+
+    // Group the node's features by namespace, sampling and weighting each group separately
+    let vocab = feature_store.get_vocab();
+    let mut groups: Vec<(usize, Vec<(usize, f32)>)> = Vec::new();
+    for (feat, fw) in feats.iter() {
+        let nt_id = vocab.get_node_type_id_of(*feat).expect("Feature id not in vocab!");
+        match groups.iter_mut().find(|(id, _)| *id == nt_id) {
+            Some((_, group)) => group.push((*feat, *fw)),
+            None => groups.push((nt_id, vec![(*feat, *fw)]))
+        }
+    }
+
+    for (_, group) in groups.iter() {
+        let options = namespaces.get(feature_store.get_feature_namespace(group[0].0).as_str());
+        let ns_weight = options.map(|o| o.weight).unwrap_or(1.);
+        if ns_weight <= 0. { continue }
+
+        let max_features = options.and_then(|o| o.max_features)
+            .or(max_features)
+            .unwrap_or(group.len());
+        for (feat, fw) in group.choose_multiple(rng, max_features) {
+            add_feature(*feat, weight * ns_weight * fw, feature_embeddings, feat_map);
         }
     }
 }
 
+fn add_feature(feat: usize, weight: f32, feature_embeddings: &EmbeddingStore, feat_map: &mut NodeCounts) {
+    if let Some((_emb, count)) = feat_map.get_mut(&feat) {
+        *count += weight;
+    } else {
+        let emb = feature_embeddings.get_embedding(feat);
+        let v = Variable::pooled(emb);
+        feat_map.insert(feat, (v, weight));
+    }
+}
+
 // H(n)
 // Average the features associated with a node
 // to create the node embedding
@@ -285,6 +355,7 @@ pub fn construct_node_embedding<R: Rng>(
     feature_store: &FeatureStore,
     feature_embeddings: &EmbeddingStore,
     max_features: Option<usize>,
+    namespaces: Option<&NamespaceOptions>,
     rng: &mut R
 ) -> (NodeCounts, ANode) {
     let mut feature_map = HashMap::new();
@@ -293,6 +364,7 @@ pub fn construct_node_embedding<R: Rng>(
                                  feature_embeddings, 
                                  &mut feature_map,
                                  max_features,
+                                 namespaces,
                                  rng);
 
     let mean = mean_embeddings(feature_map.values());
@@ -316,15 +388,17 @@ pub fn attention_construct_node_embedding<R: Rng>(
                                  feature_embeddings, 
                                  &mut feature_map,
                                  max_features,
+                                 None,
                                  rng);
 
     let mean = if mha.preserve_feature_order() {
         // Need to preserve order of features for context windows
         let feats = feature_store.get_features(node);
         let it = feats.iter()
-            .filter(|f| feature_map.contains_key(*f))
+            .map(|f| *f as usize)
+            .filter(|f| feature_map.contains_key(f))
             .map(|f| {
-                feature_map.get(f).expect("Some type of error!")
+                feature_map.get(&f).expect("Some type of error!")
             });
         attention_mean(it, &mha, rng)
     } else {
@@ -345,6 +419,7 @@ fn reconstruct_node_embedding<G: CGraph, R: Rng>(
     feature_embeddings: &EmbeddingStore,
     max_nodes: Option<usize>,
     max_features: Option<usize>,
+    namespaces: Option<&NamespaceOptions>,
     mha: Option<MultiHeadedAttention>,
     weighted_neighbor_sampling: bool,
     weighted_neighbor_averaging: bool,
@@ -364,6 +439,7 @@ fn reconstruct_node_embedding<G: CGraph, R: Rng>(
             feature_store,
             feature_embeddings,
             max_features,
+            namespaces,
             mha,
             rng)
     } else {
@@ -377,6 +453,7 @@ fn reconstruct_node_embedding<G: CGraph, R: Rng>(
             feature_store,
             feature_embeddings,
             max_features,
+            namespaces,
             mha,
             rng)
     }
@@ -387,6 +464,7 @@ fn construct_from_multiple_nodes<I: Iterator<Item=(NodeID, f32)>, R: Rng>(
     feature_store: &FeatureStore,
     feature_embeddings: &EmbeddingStore,
     max_features: Option<usize>,
+    namespaces: Option<&NamespaceOptions>,
     mha: Option<MultiHeadedAttention>,
     rng: &mut R,
 ) -> (NodeCounts, ANode) {
@@ -401,6 +479,7 @@ fn construct_from_multiple_nodes<I: Iterator<Item=(NodeID, f32)>, R: Rng>(
                                      feature_embeddings, 
                                      &mut feature_map,
                                      max_features,
+                                     namespaces,
                                      rng);
     }
 
@@ -425,16 +504,17 @@ fn attention_multiple(
     for node in new_nodes {
         feats_per_node.clear();
         let feats = feature_store.get_features(node);
-        for feat in feats.iter() {
-            if let Some((node, _)) = feature_map.get(feat) {
+        for feat in feats.iter().map(|f| *f as usize) {
+            if let Some((node, _)) = feature_map.get(&feat) {
                 let e = feats_per_node.entry(feat).or_insert_with(|| (node.clone(), 0f32));
                 e.1 += 1f32;
             }
         }
         let it = feats.iter()
-            .filter(|f| feats_per_node.contains_key(*f))
+            .map(|f| *f as usize)
+            .filter(|f| feats_per_node.contains_key(f))
             .map(|f| {
-                feats_per_node.get(f).expect("Some type of error!")
+                feats_per_node.get(&f).expect("Some type of error!")
             });
 
         output.push((attention_mean(it, &mha, rng), 1f32))
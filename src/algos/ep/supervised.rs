@@ -0,0 +1,139 @@
+//! Semi-supervised extension to Embedding Propagation.  Labeled nodes add a cross entropy loss
+//! from a linear softmax head over their node embeddings, which is trained jointly with the
+//! feature embeddings.  Unlabeled nodes only see the usual EP loss, so the labels shape the
+//! embedding space while the graph fills in the rest.
+use hashbrown::HashMap;
+use simple_grad::*;
+
+use crate::EmbeddingStore;
+use crate::graph::NodeID;
+use super::attention::softmax;
+
+/// Labels used for semi-supervised training
+pub struct NodeLabels {
+    /// Class index for each labeled node
+    pub labels: HashMap<NodeID, usize>,
+
+    /// Total number of classes.  Class indices must be less than this.
+    pub num_classes: usize,
+
+    /// Weight of the cross entropy loss relative to the EP loss
+    pub weight: f32
+}
+
+/// Linear softmax classifier over node embeddings
+#[derive(Clone,Debug)]
+pub struct Classifier {
+    /// Row major [num_classes, dims] weights
+    pub weights: Vec<Vec<f32>>,
+
+    /// Bias for each class
+    pub bias: Vec<f32>
+}
+
+impl Classifier {
+
+    /// Extracts the classifier from its parameter store, where each row holds a class's weights
+    /// followed by its bias.
+    pub(super) fn from_store(es: &EmbeddingStore) -> Self {
+        let dims = es.dims() - 1;
+        let (weights, bias) = (0..es.len()).map(|class| {
+            let row = es.get_embedding(class);
+            (row[..dims].to_vec(), row[dims])
+        }).unzip();
+        Classifier { weights, bias }
+    }
+
+    pub fn logits(&self, emb: &[f32]) -> Vec<f32> {
+        self.weights.iter().zip(self.bias.iter()).map(|(w, b)| {
+            w.iter().zip(emb.iter()).map(|(wi, ei)| wi * ei).sum::<f32>() + b
+        }).collect()
+    }
+
+    /// Class probabilities for a node embedding
+    pub fn predict(&self, emb: &[f32]) -> Vec<f32> {
+        let mut logits = self.logits(emb);
+        let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        logits.iter_mut().for_each(|l| *l = (*l - max).exp());
+        let total = logits.iter().sum::<f32>();
+        logits.iter_mut().for_each(|l| *l /= total);
+        logits
+    }
+}
+
+/// Builds the weighted cross entropy loss for a labeled node embedding.  Returns the loss and the
+/// per class weight and bias variables so their gradients can be extracted after backprop.
+pub(super) fn classification_loss(
+    hv: &ANode,
+    label: usize,
+    classifier: &EmbeddingStore,
+    weight: f32
+) -> (ANode, Vec<(ANode, ANode)>) {
+    let dims = classifier.dims() - 1;
+    let vars: Vec<_> = (0..classifier.len()).map(|class| {
+        let row = classifier.get_embedding(class);
+        (Variable::new(row[..dims].to_vec()), Variable::new(vec![row[dims]]))
+    }).collect();
+
+    let logits: Vec<_> = vars.iter().map(|(w, b)| w.dot(hv) + b).collect();
+    let p = softmax(logits.concat()).slice(label, 1);
+    (-p.ln() * weight, vars)
+}
+
+/// Pulls the classifier gradients from the backpropagated graph, one per class row.
+pub(super) fn extract_classifier_grads(
+    graph: &Graph,
+    vars: Vec<(ANode, ANode)>
+) -> Vec<(usize, Vec<f32>)> {
+    vars.into_iter().enumerate().filter_map(|(class, (w, b))| {
+        let gw = graph.get_grad(&w)?;
+        let gb = graph.get_grad(&b)?;
+        let mut grad = gw.to_vec();
+        grad.push(gb[0]);
+        if grad.iter().all(|gi| gi.is_finite()) {
+            Some((class, grad))
+        } else {
+            None
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod supervised_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_classifier_predict() {
+        let classifier = Classifier {
+            weights: vec![vec![1., 0.], vec![0., 1.]],
+            bias: vec![0., 1.]
+        };
+        assert_eq!(classifier.logits(&[2., 1.]), vec![2., 2.]);
+        let p = classifier.predict(&[3., 0.]);
+        assert!(p[0] > p[1]);
+        assert!((p.iter().sum::<f32>() - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cross_entropy_grads() {
+        let mut es = EmbeddingStore::new(2, 3, Distance::Cosine);
+        es.get_embedding_mut(0).copy_from_slice(&[1., 0., 0.]);
+        es.get_embedding_mut(1).copy_from_slice(&[0., 1., 0.]);
+
+        // Equal logits, so p(label) = 0.5
+        let hv = Variable::new(vec![1., 1.]);
+        let (loss, vars) = classification_loss(&hv, 0, &es, 1.);
+        assert!((loss.value()[0] - 2f32.ln()).abs() < 1e-6);
+
+        let mut graph = Graph::new();
+        graph.backward(&loss);
+        let grads = extract_classifier_grads(&graph, vars);
+        assert_eq!(grads.len(), 2);
+
+        // d/dz_c = p_c - y_c
+        assert!((grads[0].1[2] + 0.5).abs() < 1e-6);
+        assert!((grads[1].1[2] - 0.5).abs() < 1e-6);
+        assert!((grads[0].1[0] + 0.5).abs() < 1e-6);
+    }
+}
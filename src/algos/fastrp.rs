@@ -0,0 +1,123 @@
+//! FastRP embeddings (Chen et al., 2019).  Every node gets a very sparse random vector which is
+//! repeatedly averaged over its neighbors; the normalized intermediate results for each power of
+//! the transition matrix are then mixed with user supplied weights.  There is no training, so it
+//! runs in a handful of sparse passes over the edges while still approximating the similarity
+//! structure that factorization methods recover.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{CDFGraph,CDFtoP};
+use crate::embeddings::{EmbeddingStore,Distance};
+
+pub struct FastRP {
+    /// Number of dimensions for the embeddings
+    pub dims: usize,
+
+    /// Weight of each power of the transition matrix, starting at A^1.  The number of weights
+    /// sets the number of propagation steps.
+    pub weights: Vec<f32>,
+
+    /// Random vectors are scaled by degree^normalization before propagation.  Negative values
+    /// dampen the influence of hubs; zero disables it.
+    pub normalization: f32,
+
+    /// Fraction of non-zero entries in each random vector.  The paper uses 1/3.
+    pub density: f32,
+
+    pub seed: u64
+}
+
+impl FastRP {
+
+    pub fn new(dims: usize, weights: Vec<f32>, seed: u64) -> Self {
+        FastRP { dims, weights, normalization: 0., density: 1. / 3., seed }
+    }
+
+    /// Embeddings use cosine distance.  Propagation uses the transition probabilities.
+    pub fn learn(&self, graph: &(impl CDFGraph + Sync)) -> EmbeddingStore {
+        let n = graph.len();
+        let dims = self.dims;
+        if n == 0 || dims == 0 {
+            return EmbeddingStore::new(n, dims, Distance::Cosine)
+        }
+
+        let density = self.density.max(1e-6).min(1.);
+        let scale = (1f32 / density).sqrt();
+        let mut cur = vec![0f32; n * dims];
+        cur.par_chunks_mut(dims).enumerate().for_each(|(node_id, row)| {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed + node_id as u64);
+            let norm = (graph.degree(node_id).max(1) as f32).powf(self.normalization);
+            row.iter_mut().for_each(|ri| {
+                let p = rng.gen::<f32>();
+                *ri = if p < density / 2. {
+                    -scale * norm
+                } else if p < density {
+                    scale * norm
+                } else {
+                    0.
+                };
+            });
+        });
+
+        let mut embs = vec![0f32; n * dims];
+        for weight in self.weights.iter() {
+            let mut next = vec![0f32; n * dims];
+            next.par_chunks_mut(dims).enumerate().for_each(|(node_id, row)| {
+                let (edges, ws) = graph.get_edges(node_id);
+                edges.iter().zip(CDFtoP::new(ws)).for_each(|(t_n, p)| {
+                    let other = &cur[t_n * dims..(t_n + 1) * dims];
+                    row.iter_mut().zip(other.iter()).for_each(|(ri, oi)| *ri += p * oi);
+                });
+                normalize(row);
+            });
+
+            embs.par_chunks_mut(dims).zip(next.par_chunks(dims)).for_each(|(emb, row)| {
+                emb.iter_mut().zip(row.iter()).for_each(|(ei, ri)| *ei += weight * ri);
+            });
+            cur = next;
+        }
+
+        EmbeddingStore::new_with_vec(n, dims, Distance::Cosine, embs)
+            .expect("Embedding size should always match!")
+    }
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|vi| vi * vi).sum::<f32>().sqrt();
+    if norm > 0. {
+        v.iter_mut().for_each(|vi| *vi /= norm);
+    }
+}
+
+#[cfg(test)]
+mod fastrp_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR,NodeID};
+    use crate::simd;
+
+    #[test]
+    fn test_disconnected_cliques() {
+        let mut edges = Vec::new();
+        for offset in [0, 4] {
+            for f in 0..4 {
+                for t in 0..4 {
+                    if f != t { edges.push((f + offset, t + offset, 1.)); }
+                }
+            }
+        }
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let es = FastRP::new(64, vec![0., 1., 1.], 2023).learn(&graph);
+        assert_eq!(es.dims(), 64);
+        assert_eq!(es.num_trained(), 8);
+
+        let sim = |a: NodeID, b: NodeID| {
+            let (ea, eb) = (es.get_embedding(a), es.get_embedding(b));
+            simd::dot(ea, eb) / (simd::norm_squared(ea) * simd::norm_squared(eb)).sqrt()
+        };
+        assert!(sim(0, 3) > 0.95);
+        assert!(sim(4, 7) > 0.95);
+        assert!(sim(0, 5).abs() < 0.6);
+    }
+}
@@ -61,7 +61,7 @@ pub fn propagate_features(
                 // propagated.
                 all_propagated &= feats.len() > 0;
                 for feat in feats.into_iter() {
-                    let e = working_map.entry(*feat).or_insert(0.);
+                    let e = working_map.entry(*feat as usize).or_insert(0.);
                     *e += weight;
                 }
             }
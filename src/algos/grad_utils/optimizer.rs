@@ -35,6 +35,18 @@ impl AdamOptimizer {
         let var = EmbeddingStore::new(length, dims, Distance::Cosine);
         AdamOptimizer { beta_1, beta_2, mom, var, eps: 1e-8 }
     }
+
+    /// Restores an optimizer from previously checkpointed first and second moments, such as
+    /// those loaded with `io::load_safetensors`.
+    pub fn from_moments(beta_1: f32, beta_2: f32, mom: EmbeddingStore, var: EmbeddingStore) -> Self {
+        AdamOptimizer { beta_1, beta_2, mom, var, eps: 1e-8 }
+    }
+
+    /// Returns the first and second moments so they can be checkpointed alongside the
+    /// embeddings.
+    pub fn moments(&self) -> (&EmbeddingStore, &EmbeddingStore) {
+        (&self.mom, &self.var)
+    }
 }
 
 impl Optimizer for AdamOptimizer {
@@ -74,6 +86,7 @@ impl Optimizer for AdamOptimizer {
                     let g_i = m_i / (v_i.sqrt() + self.eps);
                     *e_i -= alpha * g_i;
                 });
+                feature_embeddings.mark_trained(feat_id);
 
             }
         });
@@ -154,11 +154,11 @@ fn hill_climb<'a, G: CGraph, R: Rng>(
             let cur_node = heap.pop().expect("Shouldn't be empty!");
             best.push(cur_node.1, cur_node.0);
             // Get edges, compute distances between them and needle, add to the heap
-            for edge in graph.get_edges(cur_node.1).0.iter() {
-                if !seen.contains(edge) {
-                    seen.insert(*edge);
-                    let dist = es.compute_distance(&needle, &Entity::Node(*edge));
-                    heap.push(NodeDistance(dist, *edge));
+            for (edge, _) in graph.iter_edges(cur_node.1) {
+                if !seen.contains(&edge) {
+                    seen.insert(edge);
+                    let dist = es.compute_distance(&needle, &Entity::Node(edge));
+                    heap.push(NodeDistance(dist, edge));
                 }
             }
 
@@ -0,0 +1,188 @@
+//! Summary statistics over a graph for sanity checking: degree and weight distributions,
+//! reciprocity, and isolated nodes.
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,NodeID};
+
+/// Summary of a set of values
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct Distribution {
+    pub min: f32,
+    pub p50: f32,
+    pub p90: f32,
+    pub p99: f32,
+    pub max: f32,
+    pub mean: f32
+}
+
+impl Distribution {
+    /// Sorts the values in place, using nearest rank percentiles.
+    fn from_values(values: &mut [f32]) -> Self {
+        if values.is_empty() {
+            return Distribution::default()
+        }
+
+        values.par_sort_unstable_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f32| {
+            let rank = (p * values.len() as f32).ceil() as usize;
+            values[rank.max(1).min(values.len()) - 1]
+        };
+        let mean = values.par_iter().map(|v| *v as f64).sum::<f64>() / values.len() as f64;
+        Distribution {
+            min: values[0],
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: values[values.len() - 1],
+            mean: mean as f32
+        }
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub out_degree: Distribution,
+    pub in_degree: Distribution,
+    pub weights: Distribution,
+
+    /// Nodes without any in or out edges
+    pub isolated_nodes: usize,
+
+    pub self_loops: usize,
+
+    /// Fraction of edges, excluding self loops, whose reverse edge also exists
+    pub reciprocity: f32
+}
+
+impl GraphStats {
+
+    /// Computes statistics treating the graph's weights as raw edge weights.
+    pub fn compute<G: Graph + Sync>(graph: &G) -> Self {
+        GraphStats::compute_with(graph, |weights, out| out.extend_from_slice(weights))
+    }
+
+    /// Computes statistics for graphs storing weights as CDFs, reporting transition
+    /// probabilities.
+    pub fn compute_cdf<G: CDFGraph + Sync>(graph: &G) -> Self {
+        GraphStats::compute_with(graph, |weights, out| out.extend(CDFtoP::new(weights)))
+    }
+
+    fn compute_with<G, F>(graph: &G, node_weights: F) -> Self
+    where
+        G: Graph + Sync,
+        F: Fn(&[f32], &mut Vec<f32>) + Sync
+    {
+        let n = graph.len();
+        let in_degrees: Vec<_> = (0..n).map(|_| AtomicUsize::new(0)).collect();
+
+        // Adjacencies are usually sorted, letting reverse edge lookups binary search
+        let sorted: Vec<bool> = (0..n).into_par_iter()
+            .map(|node_id| graph.get_edges(node_id).0.windows(2).all(|w| w[0] <= w[1]))
+            .collect();
+
+        let (mut weights, self_loops, reciprocated) = (0..n).into_par_iter()
+            .fold(|| (Vec::new(), 0usize, 0usize), |(mut weights, mut self_loops, mut reciprocated), node_id| {
+                let (edges, ws) = graph.get_edges(node_id);
+                node_weights(ws, &mut weights);
+                edges.iter().for_each(|t_n| {
+                    in_degrees[*t_n].fetch_add(1, Ordering::Relaxed);
+                    if *t_n == node_id {
+                        self_loops += 1;
+                    } else if has_edge(graph, &sorted, *t_n, node_id) {
+                        reciprocated += 1;
+                    }
+                });
+                (weights, self_loops, reciprocated)
+            })
+            .reduce(|| (Vec::new(), 0, 0), |(mut w1, s1, r1), (w2, s2, r2)| {
+                w1.extend(w2);
+                (w1, s1 + s2, r1 + r2)
+            });
+
+        let in_degrees: Vec<usize> = in_degrees.into_iter().map(|d| d.into_inner()).collect();
+        let isolated_nodes = (0..n).into_par_iter()
+            .filter(|node_id| graph.degree(*node_id) == 0 && in_degrees[*node_id] == 0)
+            .count();
+
+        let mut out_degree: Vec<f32> = (0..n).into_par_iter()
+            .map(|node_id: NodeID| graph.degree(node_id) as f32)
+            .collect();
+        let mut in_degree: Vec<f32> = in_degrees.into_iter().map(|d| d as f32).collect();
+
+        let non_loops = graph.edges() - self_loops;
+        GraphStats {
+            nodes: n,
+            edges: graph.edges(),
+            out_degree: Distribution::from_values(&mut out_degree),
+            in_degree: Distribution::from_values(&mut in_degree),
+            weights: Distribution::from_values(&mut weights),
+            isolated_nodes,
+            self_loops,
+            reciprocity: if non_loops > 0 { reciprocated as f32 / non_loops as f32 } else { 0. }
+        }
+    }
+}
+
+fn has_edge<G: Graph>(graph: &G, sorted: &[bool], from_node: NodeID, to_node: NodeID) -> bool {
+    let edges = graph.get_edges(from_node).0;
+    if sorted[from_node] {
+        edges.binary_search(&to_node).is_ok()
+    } else {
+        edges.contains(&to_node)
+    }
+}
+
+#[cfg(test)]
+mod graph_stats_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+
+    #[test]
+    fn test_stats() {
+        let edges = vec![
+            (0, 1, 1.),
+            (1, 0, 2.),
+            (1, 2, 3.),
+            (2, 2, 4.),
+            (3, 1, 5.),
+            (5, 3, 6.)
+        ];
+
+        let csr = CSR::construct_from_edges(edges);
+        let stats = GraphStats::compute(&csr);
+        assert_eq!(stats.nodes, 6);
+        assert_eq!(stats.edges, 6);
+        assert_eq!(stats.isolated_nodes, 1);
+        assert_eq!(stats.self_loops, 1);
+        assert_eq!(stats.reciprocity, 2. / 5.);
+        assert_eq!(stats.out_degree.max, 2.);
+        assert_eq!(stats.out_degree.min, 0.);
+        assert_eq!(stats.in_degree.max, 2.);
+        assert_eq!(stats.weights.p50, 3.);
+        assert_eq!(stats.weights.mean, 3.5);
+
+        let stats = GraphStats::compute_cdf(&CumCSR::convert(csr));
+        assert_eq!(stats.weights.max, 1.);
+        assert!((stats.weights.min - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_reciprocity_unsorted() {
+        // Node 0's adjacency is unsorted, node 1's is sorted
+        let edges = vec![
+            (0, 3, 1.),
+            (0, 1, 1.),
+            (0, 2, 1.),
+            (1, 0, 1.),
+            (1, 2, 1.),
+            (3, 0, 1.)
+        ];
+
+        let stats = GraphStats::compute(&CSR::construct_from_edges(edges));
+        assert_eq!(stats.reciprocity, 4. / 6.);
+    }
+}
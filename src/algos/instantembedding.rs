@@ -1,9 +1,10 @@
 //! 
+use hashbrown::HashMap;
 use rayon::prelude::*;
 
 use crate::algos::utils::FeatureHasher;
 use crate::algos::rwr::{Steps,RWR,ppr_estimate};
-use crate::graph::{Graph as CGraph, CDFGraph};
+use crate::graph::{Graph as CGraph, CDFGraph, NodeID};
 use crate::embeddings::{EmbeddingStore,Distance};
 use crate::progress::CLProgressBar;
 
@@ -24,7 +25,11 @@ pub enum Estimator {
 pub struct InstantEmbeddings {
     pub estimator: Estimator,
     pub dims: usize,
-    pub hashes: usize
+    pub hashes: usize,
+
+    /// If provided, only the top K PPR entries are hashed into the embedding.  Trims the long
+    /// tail of barely visited nodes, which are mostly estimator noise.
+    pub top_k: Option<usize>
 }
 
 impl InstantEmbeddings {
@@ -39,33 +44,130 @@ impl InstantEmbeddings {
         let fh = FeatureHasher::new(self.dims);
         let pb = CLProgressBar::new(n as u64, true);
         (0..graph.len()).into_par_iter().for_each(|node_id| {
-            let ppr = match self.estimator {
-                Estimator::RandomWalk {steps, walks, beta, seed} => {
-                    let rwr = RWR {
-                        steps: steps,
-                        walks: walks,
-                        beta: beta,
-                        single_threaded: false,
-                        seed: seed + node_id as u64
-                    };
-
-                    rwr.sample_bfs(graph, node_id)
-                },
-                Estimator::SparsePPR { p, eps } => ppr_estimate(graph, node_id, p, eps)
-            };
-            
+            let ppr = self.estimate(graph, node_id);
             let embs = es.get_embedding_mut_hogwild(node_id);
-            ppr.into_iter().for_each(|(node_id, weight)| {
-                for hi in 0..self.hashes {
-                    let (sign, dim) = fh.hash(node_id, hi);
-                    embs[dim] += sign as f32 * (weight * n as f32).ln().max(0f32);
-                }
-            });
+            self.hash_into(&fh, ppr, n, embs);
             pb.inc(1);
 
         });
         pb.finish();
         es
     }
+
+    /// Embeds a single node without materializing the full store.  Matches the node's row from
+    /// `learn`.
+    pub fn embed_node<G: CGraph + CDFGraph + Send + Sync>(
+        &self,
+        graph: &G,
+        node_id: NodeID
+    ) -> Vec<f32> {
+        let fh = FeatureHasher::new(self.dims);
+        let mut emb = vec![0f32; self.dims];
+        self.hash_into(&fh, self.estimate(graph, node_id), graph.len(), &mut emb);
+        emb
+    }
+
+    /// Embeds an adhoc node which isn't in the graph from its weighted edges into it.  The node's
+    /// PPR distribution, excluding itself, is the weighted mix of its neighbors' distributions.
+    /// Useful as a cold start fallback for nodes added after the graph was built.
+    pub fn embed_adhoc<G: CGraph + CDFGraph + Send + Sync>(
+        &self,
+        graph: &G,
+        neighbors: &[(NodeID, f32)]
+    ) -> Vec<f32> {
+        let total = neighbors.iter().map(|(_, w)| w.max(0.)).sum::<f32>();
+        let mut ppr = HashMap::new();
+        if total > 0. {
+            neighbors.iter().for_each(|(node_id, w)| {
+                let w = w.max(0.) / total;
+                if w > 0. {
+                    self.estimate(graph, *node_id).into_iter().for_each(|(other, p)| {
+                        *ppr.entry(other).or_insert(0f32) += w * p;
+                    });
+                }
+            });
+        }
+
+        let fh = FeatureHasher::new(self.dims);
+        let mut emb = vec![0f32; self.dims];
+        self.hash_into(&fh, ppr, graph.len(), &mut emb);
+        emb
+    }
+
+    fn estimate<G: CGraph + CDFGraph + Send + Sync>(
+        &self,
+        graph: &G,
+        node_id: NodeID
+    ) -> HashMap<NodeID, f32> {
+        match self.estimator {
+            Estimator::RandomWalk {steps, walks, beta, seed} => {
+                let rwr = RWR {
+                    steps: steps,
+                    walks: walks,
+                    beta: beta,
+                    single_threaded: false,
+                    seed: seed + node_id as u64
+                };
+
+                rwr.sample_bfs(graph, node_id)
+            },
+            Estimator::SparsePPR { p, eps } => ppr_estimate(graph, node_id, p, eps)
+        }
+    }
+
+    fn hash_into(
+        &self, 
+        fh: &FeatureHasher, 
+        ppr: HashMap<NodeID, f32>, 
+        n: usize, 
+        embs: &mut [f32]
+    ) {
+        let mut entries: Vec<_> = ppr.into_iter().collect();
+        if let Some(k) = self.top_k {
+            // Ties broken by node id so the selection is deterministic
+            entries.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            entries.truncate(k);
+        }
+
+        entries.into_iter().for_each(|(node_id, weight)| {
+            for hi in 0..self.hashes {
+                let (sign, dim) = fh.hash(node_id, hi);
+                embs[dim] += sign as f32 * (weight * n as f32).ln().max(0f32);
+            }
+        });
+    }
 }
 
+#[cfg(test)]
+mod instantembedding_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+
+    fn build_graph() -> CumCSR {
+        let mut edges = Vec::new();
+        for (f, t) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)] {
+            edges.push((f, t, 1.));
+            edges.push((t, f, 1.));
+        }
+        CumCSR::convert(CSR::construct_from_edges(edges))
+    }
+
+    #[test]
+    fn test_embed_node_matches_store() {
+        let graph = build_graph();
+        let ie = InstantEmbeddings {
+            estimator: Estimator::SparsePPR { p: 0.15, eps: 1e-6 },
+            dims: 16,
+            hashes: 2,
+            top_k: Some(3)
+        };
+        let es = ie.learn(&graph);
+        for node_id in 0..graph.len() {
+            assert_eq!(es.get_embedding(node_id), ie.embed_node(&graph, node_id).as_slice());
+        }
+
+        // A single neighbor means the adhoc node inherits that neighbor's distribution
+        assert_eq!(ie.embed_adhoc(&graph, &[(4, 2.)]), ie.embed_node(&graph, 4));
+        assert!(ie.embed_adhoc(&graph, &[]).iter().all(|v| *v == 0.));
+    }
+}
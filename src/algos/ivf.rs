@@ -0,0 +1,209 @@
+//! Inverted file index.  A coarse quantizer, learned with k-means, partitions the store into
+//! cells; queries only score the members of the `n_probe` cells whose centroids are closest.
+//! Memory overhead is a single centroid per cell plus the posting lists, which makes this a good
+//! fit for very high dimensional embeddings where hyperplane trees struggle.
+use rand::prelude::*;
+use rand::seq::index::sample;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::algos::graph_ann::{NodeDistance,TopK};
+use crate::algos::ann::AnnIndex;
+
+/// Number of training points sampled per cell when learning the quantizer
+const SAMPLES_PER_LIST: usize = 256;
+
+pub struct Ivf {
+    /// Number of cells to partition the store into.  sqrt(N) is a reasonable default.
+    n_lists: usize,
+
+    /// Number of cells scored at query time.  Higher improves recall at the expense of latency.
+    pub n_probe: usize,
+
+    /// Number of k-means iterations when fitting the quantizer
+    iterations: usize,
+
+    seed: u64,
+
+    centroids: Vec<Vec<f32>>,
+
+    /// Posting list for each cell
+    lists: Vec<Vec<NodeID>>
+}
+
+impl Ivf {
+    pub fn new(n_lists: usize, n_probe: usize, iterations: usize, seed: u64) -> Self {
+        Ivf {
+            n_lists: n_lists.max(1),
+            n_probe: n_probe.max(1),
+            iterations,
+            seed,
+            centroids: Vec::new(),
+            lists: Vec::new()
+        }
+    }
+
+    /// Number of nodes in the index
+    pub fn len(&self) -> usize {
+        self.lists.iter().map(|l| l.len()).sum()
+    }
+
+    pub fn num_lists(&self) -> usize {
+        self.lists.len()
+    }
+
+    /// Sizes of each posting list, useful for spotting badly skewed partitions.
+    pub fn list_sizes(&self) -> Vec<usize> {
+        self.lists.iter().map(|l| l.len()).collect()
+    }
+
+    /// Returns the closest n centroids to the embedding, closest first.
+    fn nearest_centroids(&self, es: &EmbeddingStore, emb: &[f32], n: usize) -> Vec<NodeDistance> {
+        let distance = es.distance();
+        let mut heap = TopK::new(n.min(self.centroids.len()));
+        self.centroids.iter().enumerate().for_each(|(idx, c)| {
+            heap.push(idx, distance.compute(emb, c));
+        });
+        heap.into_sorted()
+    }
+
+    fn assign(&self, es: &EmbeddingStore, emb: &[f32]) -> usize {
+        self.nearest_centroids(es, emb, 1)[0].1
+    }
+
+    /// Lloyd's algorithm over a sample of the store.  Empty cells are reseeded with a random
+    /// training point so every cell stays in use.
+    fn train_quantizer(&mut self, es: &EmbeddingStore) {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let n_samples = (self.n_lists * SAMPLES_PER_LIST).min(es.len());
+        let training: Vec<NodeID> = sample(&mut rng, es.len(), n_samples).into_vec();
+        let n_lists = self.n_lists.min(training.len());
+
+        self.centroids = training.iter().take(n_lists)
+            .map(|node_id| es.get_embedding(*node_id).to_vec())
+            .collect();
+
+        let dims = es.dims();
+        for _ in 0..self.iterations {
+            let new_centroid = || (vec![vec![0f32; dims]; n_lists], vec![0usize; n_lists]);
+            let (sums, counts) = training.par_iter().fold(new_centroid, |(mut sums, mut counts), node_id| {
+                let emb = es.get_embedding(*node_id);
+                let cell = self.assign(es, emb);
+                sums[cell].iter_mut().zip(emb.iter()).for_each(|(si, ei)| *si += ei);
+                counts[cell] += 1;
+                (sums, counts)
+            }).reduce(new_centroid, |(mut s1, mut c1), (s2, c2)| {
+                s1.iter_mut().zip(s2.iter()).for_each(|(v1, v2)| {
+                    v1.iter_mut().zip(v2.iter()).for_each(|(a, b)| *a += b);
+                });
+                c1.iter_mut().zip(c2.iter()).for_each(|(a, b)| *a += b);
+                (s1, c1)
+            });
+
+            self.centroids.iter_mut().zip(sums.into_iter().zip(counts.into_iter()))
+                .for_each(|(centroid, (sum, count))| {
+                    if count > 0 {
+                        centroid.iter_mut().zip(sum.iter())
+                            .for_each(|(ci, si)| *ci = si / count as f32);
+                    } else {
+                        let node_id = training.choose(&mut rng).unwrap();
+                        centroid.copy_from_slice(es.get_embedding(*node_id));
+                    }
+                });
+        }
+    }
+}
+
+impl AnnIndex for Ivf {
+
+    fn fit(&mut self, es: &EmbeddingStore) {
+        self.centroids.clear();
+        self.lists.clear();
+        if es.len() == 0 { return }
+
+        self.train_quantizer(es);
+        let assignments: Vec<_> = (0..es.len()).into_par_iter()
+            .map(|node_id| self.assign(es, es.get_embedding(node_id)))
+            .collect();
+
+        self.lists = vec![Vec::new(); self.centroids.len()];
+        assignments.into_iter().enumerate().for_each(|(node_id, cell)| {
+            self.lists[cell].push(node_id);
+        });
+    }
+
+    /// Adds the node to its closest cell.  Centroids aren't updated, so the index should be refit
+    /// if the distribution of new nodes drifts from the original.
+    fn insert(&mut self, es: &EmbeddingStore, node_id: NodeID) {
+        let emb = es.get_embedding(node_id);
+        if self.centroids.is_empty() {
+            self.centroids.push(emb.to_vec());
+            self.lists.push(Vec::new());
+        }
+        let cell = self.assign(es, emb);
+        self.lists[cell].push(node_id);
+    }
+
+    fn predict(&self, es: &EmbeddingStore, emb: &[f32], k: usize) -> Vec<NodeDistance> {
+        let cells = self.nearest_centroids(es, emb, self.n_probe);
+        let n = cells.iter().map(|nd| self.lists[nd.1].len()).sum::<usize>();
+        let k = k.min(n);
+        let scorer = es.scorer(emb);
+        cells.par_iter().fold(|| TopK::new(k), |mut heap, cell| {
+            self.lists[cell.1].iter().for_each(|node_id| {
+                heap.push(*node_id, scorer(*node_id, es.get_embedding(*node_id)));
+            });
+            heap
+        }).reduce(|| TopK::new(k), |mut tk1, tk2| {
+            tk1.extend(tk2);
+            tk1
+        }).into_sorted()
+    }
+}
+
+#[cfg(test)]
+mod ivf_tests {
+    use super::*;
+    use hashbrown::HashSet;
+    use crate::embeddings::{Distance,Entity};
+
+    #[test]
+    fn test_ivf_recall() {
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(2000, 8, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            let emb: Vec<f32> = (0..8).map(|_| rng.gen::<f32>()).collect();
+            es.set_embedding(node_id, &emb);
+        }
+
+        let mut ivf = Ivf::new(20, 5, 10, 1234);
+        ivf.fit(&es);
+        assert_eq!(ivf.len(), 2000);
+        assert_eq!(ivf.num_lists(), 20);
+
+        let k = 10;
+        let mut hits = 0;
+        for node_id in (0..es.len()).step_by(100) {
+            let emb = es.get_embedding(node_id);
+            let expected: HashSet<_> = es.nearest_neighbors(&Entity::Embedding(emb), k)
+                .into_iter().map(|nd| nd.1).collect();
+
+            let results = ivf.predict(&es, emb, k);
+            assert_eq!(results[0].1, node_id);
+            hits += results.iter().filter(|nd| expected.contains(&nd.1)).count();
+        }
+
+        let recall = hits as f32 / (20 * k) as f32;
+        assert!(recall > 0.7, "Recall too low: {}", recall);
+
+        // Probing every cell is exhaustive
+        ivf.n_probe = 20;
+        let emb = es.get_embedding(0);
+        let expected = es.nearest_neighbors(&Entity::Embedding(emb), k);
+        let results = ivf.predict(&es, emb, k);
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(),
+                   expected.iter().map(|nd| nd.1).collect::<Vec<_>>());
+    }
+}
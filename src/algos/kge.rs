@@ -0,0 +1,357 @@
+//! Knowledge graph embeddings.  A RelationModel scores (head, relation, tail) triples from entity
+//! and relation embeddings; the trainer fits any model with a margin loss against triples whose
+//! head or tail has been corrupted with a random entity.  Gradients are accumulated per batch of
+//! triples and applied with Adam as in EmbeddingPropagation.
+//!
+//! Models differ in which relation patterns they can express: TransE handles antisymmetric and
+//! compositional relations, DistMult only symmetric ones, and ComplEx both symmetric and
+//! antisymmetric.
+use std::fmt::Write;
+use std::collections::{HashMap as CHashMap};
+
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID,RelationalGraph};
+use crate::embeddings::{EmbeddingStore,Distance,randomize_embedding_store};
+use crate::progress::CLProgressBar;
+use crate::algos::skipgram::{Grads,add_grad};
+use crate::algos::grad_utils::scheduler::LRScheduler;
+use crate::algos::grad_utils::optimizer::{Optimizer,AdamOptimizer};
+
+pub trait RelationModel: Sync {
+    /// Plausibility of a triple; higher is more plausible.
+    fn score(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> f32;
+
+    /// Gradients of the score with respect to the head, relation, and tail.
+    fn gradients(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> (Vec<f32>, Vec<f32>, Vec<f32>);
+
+    /// Whether entity embeddings are projected back into the unit ball after each update.
+    fn constrain_entities(&self) -> bool {
+        false
+    }
+}
+
+/// Relations as translations: score is -||h + r - t||
+pub struct TransE;
+
+impl RelationModel for TransE {
+    fn score(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> f32 {
+        -translation(head, relation, tail).iter().map(|xi| xi * xi).sum::<f32>().sqrt()
+    }
+
+    fn gradients(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+        let x = translation(head, relation, tail);
+        let d = x.iter().map(|xi| xi * xi).sum::<f32>().sqrt().max(1e-8);
+        let g: Vec<f32> = x.into_iter().map(|xi| -xi / d).collect();
+        let neg_g = g.iter().map(|gi| -gi).collect();
+        (g.clone(), g, neg_g)
+    }
+
+    fn constrain_entities(&self) -> bool {
+        true
+    }
+}
+
+fn translation(head: &[f32], relation: &[f32], tail: &[f32]) -> Vec<f32> {
+    head.iter().zip(relation.iter().zip(tail.iter()))
+        .map(|(hi, (ri, ti))| hi + ri - ti)
+        .collect()
+}
+
+/// Relations as diagonal bilinear maps: score is sum(h * r * t).  Symmetric by construction.
+pub struct DistMult;
+
+impl RelationModel for DistMult {
+    fn score(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> f32 {
+        head.iter().zip(relation.iter().zip(tail.iter()))
+            .map(|(hi, (ri, ti))| hi * ri * ti)
+            .sum()
+    }
+
+    fn gradients(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+        let mul = |a: &[f32], b: &[f32]| -> Vec<f32> {
+            a.iter().zip(b.iter()).map(|(ai, bi)| ai * bi).collect()
+        };
+        (mul(relation, tail), mul(head, tail), mul(head, relation))
+    }
+
+    fn constrain_entities(&self) -> bool {
+        true
+    }
+}
+
+/// DistMult over complex embeddings: score is Re(sum(h * r * conj(t))).  The first half of each
+/// embedding holds the real components and the second half the imaginary, so dims must be even.
+pub struct ComplEx;
+
+impl RelationModel for ComplEx {
+    fn score(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> f32 {
+        let k = head.len() / 2;
+        let (hr, hi) = head.split_at(k);
+        let (rr, ri) = relation.split_at(k);
+        let (tr, ti) = tail.split_at(k);
+        (0..k).map(|j| {
+            hr[j] * rr[j] * tr[j] + hi[j] * rr[j] * ti[j] + hr[j] * ri[j] * ti[j] - hi[j] * ri[j] * tr[j]
+        }).sum()
+    }
+
+    fn gradients(&self, head: &[f32], relation: &[f32], tail: &[f32]) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+        let k = head.len() / 2;
+        let (hr, hi) = head.split_at(k);
+        let (rr, ri) = relation.split_at(k);
+        let (tr, ti) = tail.split_at(k);
+        let mut gh = vec![0f32; head.len()];
+        let mut gr = vec![0f32; head.len()];
+        let mut gt = vec![0f32; head.len()];
+        for j in 0..k {
+            gh[j] = rr[j] * tr[j] + ri[j] * ti[j];
+            gh[k + j] = rr[j] * ti[j] - ri[j] * tr[j];
+            gr[j] = hr[j] * tr[j] + hi[j] * ti[j];
+            gr[k + j] = hr[j] * ti[j] - hi[j] * tr[j];
+            gt[j] = hr[j] * rr[j] - hi[j] * ri[j];
+            gt[k + j] = hi[j] * rr[j] + hr[j] * ri[j];
+        }
+        (gh, gr, gt)
+    }
+}
+
+/// Scores a triple against learned entity and relation embeddings.
+pub fn score_triple<M: RelationModel>(
+    model: &M,
+    entities: &EmbeddingStore,
+    relations: &EmbeddingStore,
+    head: NodeID,
+    relation: usize,
+    tail: NodeID
+) -> f32 {
+    model.score(entities.get_embedding(head), relations.get_embedding(relation),
+                entities.get_embedding(tail))
+}
+
+pub struct KGTrainer {
+    /// Number of dimensions for entities and relations
+    pub dims: usize,
+
+    /// Minimum gap between the scores of a true triple and a corrupted one
+    pub margin: f32,
+
+    /// Number of corrupted triples per true triple
+    pub negatives: usize,
+
+    /// Number of passes over the triples
+    pub passes: usize,
+
+    /// Number of triples per gradient update
+    pub batch_size: usize,
+
+    /// Peak learning rate
+    pub alpha: f32,
+
+    pub seed: u64,
+
+    pub indicator: bool
+}
+
+fn normalize(es: &EmbeddingStore) {
+    (0..es.len()).into_par_iter().for_each(|node_id| {
+        let emb = es.get_embedding_mut_hogwild(node_id);
+        let norm = emb.iter().map(|ei| ei * ei).sum::<f32>().sqrt();
+        if norm > 1. {
+            emb.iter_mut().for_each(|ei| *ei /= norm);
+        }
+    });
+}
+
+impl KGTrainer {
+
+    /// Learns entity and relation embeddings, in that order, from every edge in the graph.
+    pub fn learn<M: RelationModel, G: Graph>(
+        &self,
+        model: &M,
+        graph: &RelationalGraph<G>
+    ) -> (EmbeddingStore, EmbeddingStore) {
+        let mut triples = Vec::with_capacity(graph.edges());
+        for node_id in 0..graph.len() {
+            let (edges, _, rels) = graph.get_edges_with_relations(node_id);
+            edges.iter().zip(rels.iter()).for_each(|(t_n, r)| {
+                triples.push((node_id, *r as usize, *t_n));
+            });
+        }
+
+        let n = graph.len();
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut entities = EmbeddingStore::new(n, self.dims, Distance::Euclidean);
+        randomize_embedding_store(&mut entities, &mut rng);
+        let mut relations = EmbeddingStore::new(graph.num_relations(), self.dims, Distance::Euclidean);
+        randomize_embedding_store(&mut relations, &mut rng);
+        if triples.is_empty() {
+            return (entities, relations)
+        }
+
+        let entity_optimizer = AdamOptimizer::new(0.9, 0.999, self.dims, n);
+        let relation_optimizer = AdamOptimizer::new(0.9, 0.999, self.dims, graph.num_relations());
+
+        let batch_size = self.batch_size.max(1);
+        let steps_per_pass = (triples.len() as f32 / batch_size as f32).ceil() as usize;
+        let total_updates = steps_per_pass * self.passes;
+        let lr_scheduler = LRScheduler::cos_decay(
+            self.alpha / 100f32, self.alpha, total_updates / 5, total_updates);
+
+        let pb = CLProgressBar::new(total_updates as u64, self.indicator);
+        let mut step = 0;
+        let mut last_error = std::f32::INFINITY;
+        for pass in 1..(self.passes + 1) {
+            pb.update_message(|msg| {
+                msg.clear();
+                write!(msg, "Pass {}/{}, Loss: {:.5}, LR: {:.5}", pass, self.passes,
+                       last_error, lr_scheduler.compute(step))
+                    .expect("Error writing out indicator message!");
+            });
+
+            triples.shuffle(&mut rng);
+            let mut error = 0f32;
+            for batch in triples.chunks(batch_size) {
+                let results: Vec<_> = batch.par_iter().enumerate().map(|(idx, triple)| {
+                    let mut rng = XorShiftRng::seed_from_u64(
+                        self.seed + (step * batch_size + idx) as u64);
+                    self.triple_gradients(model, *triple, &entities, &relations, &mut rng)
+                }).collect();
+
+                let mut all_entity_grads = CHashMap::new();
+                let mut all_relation_grads = CHashMap::new();
+                for (loss, entity_grads, relation_grads) in results {
+                    for (node_id, g) in entity_grads {
+                        add_grad(&mut all_entity_grads, node_id, 1., &g);
+                    }
+                    for (rel, g) in relation_grads {
+                        add_grad(&mut all_relation_grads, rel, 1., &g);
+                    }
+                    error += loss;
+                }
+
+                let alpha = lr_scheduler.compute(step);
+                entity_optimizer.update(&entities, all_entity_grads, alpha, pass as f32);
+                relation_optimizer.update(&relations, all_relation_grads, alpha, pass as f32);
+                if model.constrain_entities() {
+                    normalize(&entities);
+                }
+                step += 1;
+                pb.inc(1);
+            }
+            last_error = error / triples.len() as f32;
+        }
+        pb.finish();
+        (entities, relations)
+    }
+
+    /// Margin loss and gradients for a triple against corrupted copies, replacing either the
+    /// head or the tail with a uniformly sampled entity.  Gradients are for minimizing the loss.
+    fn triple_gradients<M: RelationModel, R: Rng>(
+        &self,
+        model: &M,
+        (head, rel, tail): (NodeID, usize, NodeID),
+        entities: &EmbeddingStore,
+        relations: &EmbeddingStore,
+        rng: &mut R
+    ) -> (f32, Grads, Grads) {
+        let mut entity_grads = CHashMap::new();
+        let mut relation_grads = CHashMap::new();
+        let r = relations.get_embedding(rel);
+        let (h_emb, t_emb) = (entities.get_embedding(head), entities.get_embedding(tail));
+        let pos_score = model.score(h_emb, r, t_emb);
+        let pos_grads = model.gradients(h_emb, r, t_emb);
+
+        let mut loss = 0f32;
+        for _ in 0..self.negatives {
+            let corrupted = rng.gen_range(0, entities.len());
+            let (h, t) = if rng.gen::<bool>() { (corrupted, tail) } else { (head, corrupted) };
+            if (h, t) == (head, tail) { continue }
+
+            let (nh_emb, nt_emb) = (entities.get_embedding(h), entities.get_embedding(t));
+            let l = self.margin - pos_score + model.score(nh_emb, r, nt_emb);
+            if l <= 0. { continue }
+
+            loss += l;
+            let (gh, gr, gt) = &pos_grads;
+            add_grad(&mut entity_grads, head, -1., gh);
+            add_grad(&mut relation_grads, rel, -1., gr);
+            add_grad(&mut entity_grads, tail, -1., gt);
+
+            let (gh, gr, gt) = model.gradients(nh_emb, r, nt_emb);
+            add_grad(&mut entity_grads, h, 1., &gh);
+            add_grad(&mut relation_grads, rel, 1., &gr);
+            add_grad(&mut entity_grads, t, 1., &gt);
+        }
+        (loss, entity_grads, relation_grads)
+    }
+}
+
+#[cfg(test)]
+mod kge_tests {
+    use super::*;
+
+    fn check_gradients<M: RelationModel>(model: &M) {
+        let h = [0.3, -0.2, 0.5, 0.1];
+        let r = [0.7, 0.4, -0.6, 0.2];
+        let t = [-0.1, 0.8, 0.2, -0.3];
+        let (gh, gr, gt) = model.gradients(&h, &r, &t);
+        let eps = 1e-3;
+        let s = model.score(&h, &r, &t);
+        (0..4).for_each(|i| {
+            let mut hp = h;
+            hp[i] += eps;
+            assert!(((model.score(&hp, &r, &t) - s) / eps - gh[i]).abs() < 1e-2);
+            let mut rp = r;
+            rp[i] += eps;
+            assert!(((model.score(&h, &rp, &t) - s) / eps - gr[i]).abs() < 1e-2);
+            let mut tp = t;
+            tp[i] += eps;
+            assert!(((model.score(&h, &r, &tp) - s) / eps - gt[i]).abs() < 1e-2);
+        });
+    }
+
+    #[test]
+    fn test_gradients() {
+        check_gradients(&TransE);
+        check_gradients(&DistMult);
+        check_gradients(&ComplEx);
+    }
+
+    #[test]
+    fn test_symmetry() {
+        let h = [0.3, -0.2, 0.5, 0.1];
+        let r = [0.7, 0.4, -0.6, 0.2];
+        let t = [-0.1, 0.8, 0.2, -0.3];
+        assert!((DistMult.score(&h, &r, &t) - DistMult.score(&t, &r, &h)).abs() < 1e-6);
+        assert!((ComplEx.score(&h, &r, &t) - ComplEx.score(&t, &r, &h)).abs() > 1e-3);
+    }
+
+    #[test]
+    fn test_translation() {
+        // Relation 0 maps i -> i + 5 for the first five entities; relation 1 maps back
+        let mut edges = Vec::new();
+        for i in 0..5 {
+            edges.push((i, i + 5, 1., 0));
+            edges.push((i + 5, i, 1., 1));
+        }
+        let graph = RelationalGraph::construct_from_edges(edges);
+
+        let trainer = KGTrainer {
+            dims: 8, margin: 1., negatives: 5, passes: 200, batch_size: 10,
+            alpha: 0.05, seed: 2023, indicator: false
+        };
+        let (entities, relations) = trainer.learn(&TransE, &graph);
+        assert_eq!(entities.len(), 10);
+        assert_eq!(relations.len(), 2);
+
+        // True tails should outscore every other candidate for most heads
+        let correct = (0..5).filter(|i| {
+            let true_s = score_triple(&TransE, &entities, &relations, *i, 0, i + 5);
+            (0..10).filter(|t| *t != i + 5)
+                .all(|t| score_triple(&TransE, &entities, &relations, *i, 0, t) < true_s)
+        }).count();
+        assert!(correct >= 4);
+    }
+}
@@ -0,0 +1,187 @@
+//! Mini-batch k-means (Sculley, 2010) over an embedding store.  Each iteration assigns a random
+//! batch of nodes to their closest centroid in parallel, then nudges those centroids toward their
+//! members with a per-centroid learning rate that decays as it absorbs more points.  This
+//! converges to nearly the same clusters as Lloyd's algorithm while only touching a fraction of
+//! the store per iteration.
+use rand::prelude::*;
+use rand::seq::index::sample;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::{EmbeddingStore,Distance};
+
+/// Number of candidate nodes sampled per cluster for k-means++ seeding
+const INIT_SAMPLES_PER_CLUSTER: usize = 64;
+
+pub struct MiniBatchKMeans {
+    /// Number of clusters.  Capped at the number of nodes.
+    pub k: usize,
+
+    /// Number of nodes sampled each iteration
+    pub batch_size: usize,
+
+    /// Number of mini-batch updates
+    pub iterations: usize,
+
+    pub seed: u64
+}
+
+/// Result of clustering a store
+pub struct Clusters {
+    /// Distance used for assignment, taken from the clustered store
+    pub distance: Distance,
+
+    pub centroids: Vec<Vec<f32>>,
+
+    /// Cluster for each node in the store
+    pub assignments: Vec<usize>,
+
+    /// Sum of the distances between each node and its centroid
+    pub inertia: f32
+}
+
+impl Clusters {
+
+    /// Closest centroid to the embedding, along with its distance
+    pub fn assign(&self, emb: &[f32]) -> (usize, f32) {
+        nearest(&self.centroids, self.distance, emb)
+    }
+
+    /// Number of nodes in each cluster
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.centroids.len()];
+        self.assignments.iter().for_each(|c| sizes[*c] += 1);
+        sizes
+    }
+
+    /// Members of each cluster
+    pub fn members(&self) -> Vec<Vec<NodeID>> {
+        let mut members = vec![Vec::new(); self.centroids.len()];
+        self.assignments.iter().enumerate().for_each(|(node_id, c)| members[*c].push(node_id));
+        members
+    }
+
+    /// Centroids as an embedding store, one row per cluster
+    pub fn centroid_store(&self) -> EmbeddingStore {
+        let dims = self.centroids.first().map(|c| c.len()).unwrap_or(0);
+        let embs = self.centroids.iter().flat_map(|c| c.iter().cloned()).collect();
+        EmbeddingStore::new_with_vec(self.centroids.len(), dims, self.distance, embs)
+            .expect("Embedding size should always match!")
+    }
+}
+
+fn nearest(centroids: &[Vec<f32>], distance: Distance, emb: &[f32]) -> (usize, f32) {
+    centroids.iter().enumerate()
+        .map(|(idx, c)| (idx, distance.compute(emb, c)))
+        .fold((0, f32::INFINITY), |best, cur| if cur.1 < best.1 { cur } else { best })
+}
+
+impl MiniBatchKMeans {
+
+    pub fn fit(&self, es: &EmbeddingStore) -> Clusters {
+        let n = es.len();
+        let distance = es.distance();
+        let k = self.k.min(n);
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+
+        let mut centroids = self.init_centroids(es, k, &mut rng);
+
+        let mut counts = vec![0usize; k];
+        let batch_size = self.batch_size.max(1).min(n);
+        if k > 0 {
+            for _ in 0..self.iterations {
+                let batch = sample(&mut rng, n, batch_size).into_vec();
+                let assigned: Vec<usize> = batch.par_iter()
+                    .map(|node_id| nearest(&centroids, distance, es.get_embedding(*node_id)).0)
+                    .collect();
+
+                batch.into_iter().zip(assigned.into_iter()).for_each(|(node_id, c)| {
+                    counts[c] += 1;
+                    let eta = 1. / counts[c] as f32;
+                    centroids[c].iter_mut().zip(es.get_embedding(node_id).iter())
+                        .for_each(|(ci, ei)| *ci = (1. - eta) * *ci + eta * ei);
+                });
+            }
+        }
+
+        let (assignments, distances): (Vec<usize>, Vec<f32>) = (0..n).into_par_iter()
+            .map(|node_id| nearest(&centroids, distance, es.get_embedding(node_id)))
+            .unzip();
+
+        Clusters {
+            distance,
+            centroids,
+            assignments,
+            inertia: distances.iter().sum()
+        }
+    }
+
+    /// k-means++ seeding over a sample of the store: each new centroid is drawn with probability
+    /// proportional to its squared distance from the closest existing centroid.
+    fn init_centroids(&self, es: &EmbeddingStore, k: usize, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+        if k == 0 {
+            return Vec::new()
+        }
+
+        let distance = es.distance();
+        let candidates = sample(rng, es.len(), (k * INIT_SAMPLES_PER_CLUSTER).min(es.len())).into_vec();
+        let mut centroids = vec![es.get_embedding(candidates[0]).to_vec()];
+        let mut dists: Vec<f32> = candidates.par_iter()
+            .map(|node_id| distance.compute(es.get_embedding(*node_id), &centroids[0]).max(0.))
+            .collect();
+
+        while centroids.len() < k {
+            let total = dists.iter().map(|d| d * d).sum::<f32>();
+            let idx = if total > 0. {
+                let mut remaining = rng.gen::<f32>() * total;
+                dists.iter().position(|d| {
+                    remaining -= d * d;
+                    remaining < 0.
+                }).unwrap_or(dists.len() - 1)
+            } else {
+                rng.gen_range(0, candidates.len())
+            };
+
+            let centroid = es.get_embedding(candidates[idx]).to_vec();
+            dists.par_iter_mut().zip(candidates.par_iter()).for_each(|(d, node_id)| {
+                *d = d.min(distance.compute(es.get_embedding(*node_id), &centroid).max(0.));
+            });
+            centroids.push(centroid);
+        }
+        centroids
+    }
+}
+
+#[cfg(test)]
+mod kmeans_tests {
+    use super::*;
+
+    #[test]
+    fn test_three_blobs() {
+        let centers = [[0., 0.], [10., 10.], [-10., 10.]];
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(300, 2, Distance::Euclidean);
+        for node_id in 0..300 {
+            let c = centers[node_id % 3];
+            let emb = es.get_embedding_mut(node_id);
+            emb[0] = c[0] + rng.gen::<f32>() - 0.5;
+            emb[1] = c[1] + rng.gen::<f32>() - 0.5;
+        }
+
+        let kmeans = MiniBatchKMeans { k: 3, batch_size: 32, iterations: 50, seed: 2023 };
+        let clusters = kmeans.fit(&es);
+        assert_eq!(clusters.centroids.len(), 3);
+        assert_eq!(clusters.sizes(), vec![100, 100, 100]);
+
+        // Nodes from the same blob share a cluster
+        for node_id in 3..300 {
+            assert_eq!(clusters.assignments[node_id], clusters.assignments[node_id % 3]);
+        }
+        assert!(clusters.inertia / 300. < 1.);
+
+        let centroids = clusters.centroid_store();
+        assert_eq!(centroids.len(), 3);
+        assert_eq!(clusters.assign(&[9., 9.]).0, clusters.assignments[1]);
+    }
+}
@@ -0,0 +1,93 @@
+//! Builds a k-nearest-neighbor graph over an EmbeddingStore using any of the ANN indices, so
+//! embedding-derived graphs can be fed back into the graph algorithms.
+use rayon::prelude::*;
+
+use crate::graph::{CSR,NodeID,GraphBuilder,EdgeMerge};
+use crate::embeddings::EmbeddingStore;
+use crate::algos::ann::AnnIndex;
+
+/// Connects every node to its k approximate nearest neighbors.  Edge weights are
+/// exp(-(d - d_min)) where d_min is the distance to the node's closest neighbor, so the closest
+/// neighbor gets weight 1 regardless of the distance metric.  When `symmetric` is set, reverse
+/// edges are added as well, keeping the larger weight when both directions exist.
+pub fn knn_graph<A: AnnIndex + ?Sized>(
+    index: &A,
+    es: &EmbeddingStore,
+    k: usize,
+    symmetric: bool
+) -> CSR {
+    let edges: Vec<(NodeID, NodeID, f32)> = (0..es.len()).into_par_iter().flat_map_iter(|node_id| {
+        let neighbors = index.predict_node(es, node_id, k);
+        let d_min = neighbors.first().map(|nd| nd.0).unwrap_or(0.);
+        neighbors.into_iter().map(move |nd| (node_id, nd.1, (d_min - nd.0).exp()))
+    }).collect();
+
+    // Every embedding gets a node, even if it has no neighbors and nothing picks it
+    let mut builder = GraphBuilder::new(EdgeMerge::Max);
+    builder.set_num_nodes(es.len());
+    edges.into_iter().for_each(|(f_n, t_n, w)| {
+        builder.add_edge(f_n, t_n, w);
+        if symmetric {
+            builder.add_edge(t_n, f_n, w);
+        }
+    });
+    builder.finalize_csr()
+}
+
+#[cfg(test)]
+mod knn_graph_tests {
+    use super::*;
+    use crate::graph::Graph;
+    use crate::embeddings::Distance;
+    use crate::algos::ann::Ann;
+    use crate::algos::graph_ann::NodeDistance;
+
+    #[test]
+    fn test_knn_graph() {
+        let mut es = EmbeddingStore::new(10, 1, Distance::Euclidean);
+        (0..10).for_each(|i| es.set_embedding(i, &[(i * i) as f32]));
+
+        // A single leaf makes the index exact
+        let mut ann = Ann::with_params(1, 100, 1234);
+        AnnIndex::fit(&mut ann, &es);
+
+        let graph = knn_graph(&ann, &es, 1, false);
+        assert_eq!(graph.len(), 10);
+        assert_eq!(graph.edges(), 10);
+        assert_eq!(graph.get_edges(0), (&[1usize][..], &[1f32][..]));
+        assert_eq!(graph.get_edges(5).0, &[4]);
+
+        // Node 9's nearest is 8, but nothing picks 9, so only the reverse edge adds it
+        let graph = knn_graph(&ann, &es, 1, true);
+        assert_eq!(graph.get_edges(8).0, &[7, 9]);
+        assert_eq!(graph.get_edges(9).0, &[8]);
+        assert_eq!(graph.get_edges(0).0, &[1]);
+    }
+
+    /// Links each node to the next, leaving the last node without any edges
+    struct Chain;
+
+    impl AnnIndex for Chain {
+        fn fit(&mut self, _es: &EmbeddingStore) {}
+
+        fn insert(&mut self, _es: &EmbeddingStore, _node_id: NodeID) {}
+
+        fn predict(&self, _es: &EmbeddingStore, _emb: &[f32], _k: usize) -> Vec<NodeDistance> {
+            Vec::new()
+        }
+
+        fn predict_node(&self, es: &EmbeddingStore, node_id: NodeID, _k: usize) -> Vec<NodeDistance> {
+            if node_id + 2 < es.len() { vec![NodeDistance(0., node_id + 1)] } else { Vec::new() }
+        }
+    }
+
+    #[test]
+    fn test_knn_graph_isolated() {
+        let es = EmbeddingStore::new(4, 1, Distance::Euclidean);
+        for symmetric in [false, true] {
+            let graph = knn_graph(&Chain, &es, 1, symmetric);
+            assert_eq!(graph.len(), 4);
+            assert!(graph.get_edges(3).0.is_empty());
+        }
+    }
+}
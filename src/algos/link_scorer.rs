@@ -0,0 +1,323 @@
+//! Link prediction over learned embeddings.  Pairs of node embeddings are combined into edge
+//! features which a logistic regression scores as the probability of an edge.  Fitting it here
+//! avoids exporting the embeddings just to train a scorer downstream.
+use std::fmt::Write as FmtWrite;
+use std::io::{Read,Write,Result as IOResult,Error as IOError,ErrorKind};
+use std::collections::{HashMap as CHashMap};
+
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::{EmbeddingStore,Distance};
+use crate::progress::CLProgressBar;
+use crate::io::{open_file_for_reading,open_file_for_writing,write_magic,check_magic};
+use crate::io::{write_f32,read_f32,write_f32s,read_f32s};
+use crate::algos::skipgram::sigmoid;
+use crate::algos::grad_utils::scheduler::LRScheduler;
+use crate::algos::grad_utils::optimizer::{Optimizer,AdamOptimizer};
+
+const LINK_SCORER_MAGIC: &[u8] = b"CLVRLNK1";
+
+/// How a pair of node embeddings is combined into edge features
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum EdgeOperator {
+    /// Elementwise product.  Symmetric, and matches dot product style embeddings.
+    Hadamard,
+
+    /// Concatenation of the source and target.  Can learn directed relationships.
+    Concat
+}
+
+impl EdgeOperator {
+    pub fn dims(&self, emb_dims: usize) -> usize {
+        match self {
+            EdgeOperator::Hadamard => emb_dims,
+            EdgeOperator::Concat   => emb_dims * 2
+        }
+    }
+
+    /// Dot product of the edge features with `weights` without materializing them
+    fn dot(&self, weights: &[f32], from: &[f32], to: &[f32]) -> f32 {
+        match self {
+            EdgeOperator::Hadamard => weights.iter().zip(from.iter().zip(to.iter()))
+                .map(|(w, (f, t))| w * f * t)
+                .sum(),
+            EdgeOperator::Concat => {
+                let (wf, wt) = weights.split_at(from.len());
+                wf.iter().zip(from.iter()).map(|(w, f)| w * f).sum::<f32>() +
+                    wt.iter().zip(to.iter()).map(|(w, t)| w * t).sum::<f32>()
+            }
+        }
+    }
+
+    /// Adds scale * features into `grad`
+    fn accumulate(&self, grad: &mut [f32], scale: f32, from: &[f32], to: &[f32]) {
+        match self {
+            EdgeOperator::Hadamard => {
+                grad.iter_mut().zip(from.iter().zip(to.iter()))
+                    .for_each(|(g, (f, t))| *g += scale * f * t);
+            },
+            EdgeOperator::Concat => {
+                let (gf, gt) = grad.split_at_mut(from.len());
+                gf.iter_mut().zip(from.iter()).for_each(|(g, f)| *g += scale * f);
+                gt.iter_mut().zip(to.iter()).for_each(|(g, t)| *g += scale * t);
+            }
+        }
+    }
+}
+
+/// Logistic regression over edge features
+#[derive(Clone,Debug)]
+pub struct LinkScorer {
+    pub operator: EdgeOperator,
+    pub weights: Vec<f32>,
+    pub bias: f32
+}
+
+impl LinkScorer {
+
+    /// Creates an untrained scorer, which gives every pair a probability of 0.5
+    pub fn new(operator: EdgeOperator, emb_dims: usize) -> Self {
+        LinkScorer { operator, weights: vec![0f32; operator.dims(emb_dims)], bias: 0. }
+    }
+
+    pub fn emb_dims(&self) -> usize {
+        match self.operator {
+            EdgeOperator::Hadamard => self.weights.len(),
+            EdgeOperator::Concat   => self.weights.len() / 2
+        }
+    }
+
+    pub fn logit(&self, from: &[f32], to: &[f32]) -> f32 {
+        self.operator.dot(&self.weights, from, to) + self.bias
+    }
+
+    /// Probability of an edge between the two embeddings
+    pub fn score(&self, from: &[f32], to: &[f32]) -> f32 {
+        sigmoid(self.logit(from, to))
+    }
+
+    pub fn score_batch(&self, es: &EmbeddingStore, pairs: &[(NodeID, NodeID)]) -> Vec<f32> {
+        pairs.par_iter()
+            .map(|(f, t)| self.score(es.get_embedding(*f), es.get_embedding(*t)))
+            .collect()
+    }
+
+    pub fn save(&self, path: &str) -> IOResult<()> {
+        let mut w = open_file_for_writing(path, None)?;
+        write_magic(&mut w, LINK_SCORER_MAGIC)?;
+        let tag = match self.operator {
+            EdgeOperator::Hadamard => 0u8,
+            EdgeOperator::Concat   => 1u8
+        };
+        w.write_all(&[tag])?;
+        write_f32(&mut w, self.bias)?;
+        write_f32s(&mut w, &self.weights)?;
+        w.flush()
+    }
+
+    pub fn load(path: &str) -> IOResult<Self> {
+        let mut r = open_file_for_reading(path)?;
+        check_magic(&mut r, LINK_SCORER_MAGIC)?;
+        let mut tag = [0u8];
+        r.read_exact(&mut tag)?;
+        let operator = match tag[0] {
+            0 => EdgeOperator::Hadamard,
+            1 => EdgeOperator::Concat,
+            t => return Err(IOError::new(ErrorKind::InvalidData, format!("Unknown edge operator {}", t)))
+        };
+        let bias = read_f32(&mut r)?;
+        let weights = read_f32s(&mut r)?;
+        if operator == EdgeOperator::Concat && weights.len() % 2 != 0 {
+            return Err(IOError::new(ErrorKind::InvalidData, "Concat weights must be even!"))
+        }
+        Ok(LinkScorer { operator, weights, bias })
+    }
+}
+
+/// Fits a LinkScorer with binary cross entropy, using Adam with L2 regularization.
+pub struct LinkScorerTrainer {
+    pub operator: EdgeOperator,
+
+    /// Number of random pairs sampled as negatives per positive when explicit negatives aren't
+    /// provided
+    pub negatives: usize,
+
+    /// Number of passes over the examples
+    pub passes: usize,
+
+    /// Number of examples per gradient update
+    pub batch_size: usize,
+
+    /// Peak learning rate
+    pub alpha: f32,
+
+    /// L2 penalty on the weights
+    pub l2: f32,
+
+    pub seed: u64,
+
+    pub indicator: bool
+}
+
+impl LinkScorerTrainer {
+
+    pub fn fit(
+        &self,
+        es: &EmbeddingStore,
+        positives: &[(NodeID, NodeID)],
+        negatives: Option<&[(NodeID, NodeID)]>
+    ) -> LinkScorer {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut examples: Vec<_> = positives.iter().map(|(f, t)| (*f, *t, 1f32)).collect();
+        match negatives {
+            Some(negs) => examples.extend(negs.iter().map(|(f, t)| (*f, *t, 0f32))),
+            None if es.len() > 0 => {
+                // Corrupt the target of each positive
+                positives.iter().for_each(|(f, _)| {
+                    for _ in 0..self.negatives {
+                        examples.push((*f, rng.gen_range(0, es.len()), 0f32));
+                    }
+                });
+            },
+            None => {}
+        }
+
+        let dims = self.operator.dims(es.dims());
+        let mut scorer = LinkScorer::new(self.operator, es.dims());
+        if examples.is_empty() {
+            return scorer
+        }
+
+        // Weights and bias are trained as a single row so we can reuse the optimizer
+        let params = EmbeddingStore::new(1, dims + 1, Distance::Cosine);
+        let optimizer = AdamOptimizer::new(0.9, 0.999, dims + 1, 1);
+        let batch_size = self.batch_size.max(1);
+        let steps_per_pass = (examples.len() as f32 / batch_size as f32).ceil() as usize;
+        let total_updates = steps_per_pass * self.passes;
+        let lr_scheduler = LRScheduler::cos_decay(
+            self.alpha / 100f32, self.alpha, total_updates / 5, total_updates);
+
+        let pb = CLProgressBar::new(total_updates as u64, self.indicator);
+        let mut step = 0;
+        let mut last_error = std::f32::INFINITY;
+        for pass in 1..(self.passes + 1) {
+            pb.update_message(|msg| {
+                msg.clear();
+                write!(msg, "Pass {}/{}, Loss: {:.5}, LR: {:.5}", pass, self.passes,
+                       last_error, lr_scheduler.compute(step))
+                    .expect("Error writing out indicator message!");
+            });
+
+            examples.shuffle(&mut rng);
+            let mut error = 0f32;
+            for batch in examples.chunks(batch_size) {
+                let row = params.get_embedding(0);
+                let (w, b) = row.split_at(dims);
+                let (loss, mut grad) = batch.par_iter().map(|(f, t, label)| {
+                    let (ef, et) = (es.get_embedding(*f), es.get_embedding(*t));
+                    let p = sigmoid(self.operator.dot(w, ef, et) + b[0]);
+                    let mut grad = vec![0f32; dims + 1];
+                    self.operator.accumulate(&mut grad[..dims], p - label, ef, et);
+                    grad[dims] = p - label;
+                    let loss = if *label > 0. { -p.max(1e-7).ln() } else { -(1. - p).max(1e-7).ln() };
+                    (loss, grad)
+                }).reduce(|| (0f32, vec![0f32; dims + 1]), |(loss_a, mut grad_a), (loss_b, grad_b)| {
+                    grad_a.iter_mut().zip(grad_b.iter()).for_each(|(ga, gb)| *ga += gb);
+                    (loss_a + loss_b, grad_a)
+                });
+
+                let n = batch.len() as f32;
+                grad.iter_mut().zip(w.iter()).for_each(|(g, wi)| *g = *g / n + self.l2 * wi);
+                grad[dims] /= n;
+                error += loss;
+
+                let mut grads = CHashMap::new();
+                grads.insert(0, grad);
+                optimizer.update(&params, grads, lr_scheduler.compute(step), pass as f32);
+                step += 1;
+                pb.inc(1);
+            }
+            last_error = error / examples.len() as f32;
+        }
+        pb.finish();
+
+        let row = params.get_embedding(0);
+        scorer.weights.copy_from_slice(&row[..dims]);
+        scorer.bias = row[dims];
+        scorer
+    }
+}
+
+#[cfg(test)]
+mod link_scorer_tests {
+    use super::*;
+
+    fn build_store() -> EmbeddingStore {
+        // Nodes 0-3 and 4-7 form two groups
+        let mut es = EmbeddingStore::new(8, 2, Distance::Cosine);
+        for node_id in 0..8 {
+            let emb = if node_id < 4 { [1., 0.1] } else { [-1., 0.1] };
+            es.get_embedding_mut(node_id).copy_from_slice(&emb);
+        }
+        es
+    }
+
+    fn fit(operator: EdgeOperator, is_edge: impl Fn(NodeID, NodeID) -> bool) -> LinkScorer {
+        let mut positives = Vec::new();
+        let mut negatives = Vec::new();
+        for f in 0..8 {
+            for t in 0..8 {
+                if f == t { continue }
+                if is_edge(f, t) { positives.push((f, t)) } else { negatives.push((f, t)) }
+            }
+        }
+
+        let trainer = LinkScorerTrainer {
+            operator, negatives: 0, passes: 50, batch_size: 8, alpha: 0.1, l2: 0.,
+            seed: 2023, indicator: false
+        };
+        trainer.fit(&build_store(), &positives, Some(&negatives))
+    }
+
+    #[test]
+    fn test_hadamard_same_group() {
+        let scorer = fit(EdgeOperator::Hadamard, |f, t| (f < 4) == (t < 4));
+        let scores = scorer.score_batch(&build_store(), &[(0, 1), (0, 5), (5, 0)]);
+        assert!(scores[0] > 0.8);
+        assert!(scores[1] < 0.2);
+        assert!(scores[2] < 0.2);
+    }
+
+    #[test]
+    fn test_concat_directed() {
+        // Only edges from the first group to the second exist
+        let scorer = fit(EdgeOperator::Concat, |f, t| f < 4 && t >= 4);
+        let scores = scorer.score_batch(&build_store(), &[(0, 5), (0, 1), (5, 0)]);
+        assert!(scores[0] > 0.8);
+        assert!(scores[1] < 0.2);
+        assert!(scores[2] < 0.2);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let scorer = LinkScorer {
+            operator: EdgeOperator::Concat,
+            weights: vec![0.5, -1., 2., 0.25],
+            bias: -0.3
+        };
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-link-scorer-save-load.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        scorer.save(path).unwrap();
+        let loaded = LinkScorer::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.operator, EdgeOperator::Concat);
+        assert_eq!(loaded.weights, scorer.weights);
+        assert_eq!(loaded.bias, scorer.bias);
+        assert_eq!(loaded.emb_dims(), 2);
+        assert_eq!(loaded.score(&[1., 2.], &[3., 4.]), scorer.score(&[1., 2.], &[3., 4.]));
+    }
+}
@@ -0,0 +1,254 @@
+//! Modularity based community detection (Blondel et al., 2008).  Each level greedily moves nodes
+//! to the neighboring community with the largest modularity gain, then collapses communities into
+//! nodes and repeats on the smaller graph.  As in Leiden (Traag et al., 2019), communities are
+//! split into their connected components before collapsing, so a community can never be made of
+//! pieces which are only held together through the rest of the graph.
+//!
+//! The graph is treated as undirected, averaging the weights of each edge in both directions.
+//! Unlike label propagation, the result is stable across runs with the same seed and improves
+//! a well defined objective.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,CSR,GraphBuilder,EdgeMerge};
+
+pub struct Louvain {
+    /// Modularity resolution.  Values above one yield more, smaller communities.
+    pub resolution: f32,
+
+    /// Maximum number of aggregation levels
+    pub max_levels: usize,
+
+    /// Maximum number of local moving passes over the nodes at each level
+    pub passes: usize,
+
+    pub seed: u64
+}
+
+/// Community hierarchy, ordered from finest to coarsest
+pub struct Communities {
+    /// Community of every original node at each level
+    pub levels: Vec<Vec<usize>>,
+
+    /// Modularity of each level's partition
+    pub modularity: Vec<f32>
+}
+
+impl Communities {
+
+    /// Communities at the coarsest level
+    pub fn assignments(&self) -> &[usize] {
+        self.levels.last().map(|l| l.as_slice()).unwrap_or(&[])
+    }
+
+    /// Number of communities at a level
+    pub fn num_communities(&self, level: usize) -> usize {
+        self.levels[level].iter().max().map(|c| c + 1).unwrap_or(0)
+    }
+}
+
+impl Louvain {
+
+    /// Detects communities over raw edge weights.
+    pub fn detect<G: Graph>(&self, graph: &G) -> Communities {
+        self.detect_with(graph, |weights| weights.to_vec())
+    }
+
+    /// Detects communities over a graph storing weights as CDFs, using the transition
+    /// probabilities as weights.
+    pub fn detect_cdf<G: CDFGraph>(&self, graph: &G) -> Communities {
+        self.detect_with(graph, |weights| CDFtoP::new(weights).collect())
+    }
+
+    fn detect_with<G: Graph, F: Fn(&[f32]) -> Vec<f32>>(&self, graph: &G, node_weights: F) -> Communities {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+
+        // Symmetrize so modularity is well defined
+        let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+        builder.set_num_nodes(graph.len());
+        for node_id in 0..graph.len() {
+            let (edges, weights) = graph.get_edges(node_id);
+            edges.iter().zip(node_weights(weights)).for_each(|(t_n, w)| {
+                builder.add_edge(node_id, *t_n, w / 2.);
+                builder.add_edge(*t_n, node_id, w / 2.);
+            });
+        }
+        let mut level_graph = builder.finalize_csr();
+
+        let mut mapping: Vec<usize> = (0..graph.len()).collect();
+        let mut levels = Vec::new();
+        let mut modularity = Vec::new();
+        while levels.len() < self.max_levels.max(1) {
+            let communities = self.local_moving(&level_graph, &mut rng);
+            let (communities, num_communities) = split_disconnected(&level_graph, &communities);
+
+            // No merges means nothing left to gain, though there's always at least one level
+            let merged = num_communities < level_graph.len();
+            if !merged && !levels.is_empty() { break }
+
+            mapping.iter_mut().for_each(|c| *c = communities[*c]);
+            levels.push(mapping.clone());
+            modularity.push(compute_modularity(&level_graph, &communities, self.resolution));
+            if !merged { break }
+
+            level_graph = aggregate(&level_graph, &communities, num_communities);
+        }
+
+        Communities { levels, modularity }
+    }
+
+    /// Repeatedly moves nodes, in random order, to the neighboring community which most improves
+    /// modularity until no node moves.
+    fn local_moving(&self, graph: &CSR, rng: &mut impl Rng) -> Vec<usize> {
+        let n = graph.len();
+        let degrees: Vec<f32> = (0..n).map(|node_id| graph.get_edges(node_id).1.iter().sum()).collect();
+        let total = degrees.iter().sum::<f32>();
+        let mut communities: Vec<usize> = (0..n).collect();
+        if total <= 0. { return communities }
+
+        let mut tot = degrees.clone();
+        let mut links = vec![0f32; n];
+        let mut touched = Vec::new();
+        let mut order = communities.clone();
+        for _ in 0..self.passes {
+            order.shuffle(rng);
+            let mut moved = false;
+            for node_id in order.iter().cloned() {
+                let cur = communities[node_id];
+                let (edges, weights) = graph.get_edges(node_id);
+                edges.iter().zip(weights.iter()).for_each(|(t_n, w)| {
+                    if *t_n != node_id {
+                        let c = communities[*t_n];
+                        if links[c] == 0. { touched.push(c); }
+                        links[c] += w;
+                    }
+                });
+
+                let k = degrees[node_id];
+                tot[cur] -= k;
+                let gain = |c: usize| links[c] - self.resolution * tot[c] * k / total;
+                let mut best = (cur, gain(cur));
+                for c in touched.iter().cloned() {
+                    let g = gain(c);
+                    if g > best.1 {
+                        best = (c, g);
+                    }
+                }
+                tot[best.0] += k;
+                if best.0 != cur {
+                    communities[node_id] = best.0;
+                    moved = true;
+                }
+
+                touched.drain(..).for_each(|c| links[c] = 0.);
+            }
+            if !moved { break }
+        }
+        communities
+    }
+}
+
+/// Relabels communities so each is connected within itself, numbering them from zero.  Returns
+/// the new communities and their count.
+fn split_disconnected(graph: &CSR, communities: &[usize]) -> (Vec<usize>, usize) {
+    let n = graph.len();
+    let mut relabeled = vec![usize::MAX; n];
+    let mut count = 0;
+    let mut stack = Vec::new();
+    for start in 0..n {
+        if relabeled[start] != usize::MAX { continue }
+
+        relabeled[start] = count;
+        stack.push(start);
+        while let Some(node_id) = stack.pop() {
+            for t_n in graph.get_edges(node_id).0.iter() {
+                if relabeled[*t_n] == usize::MAX && communities[*t_n] == communities[start] {
+                    relabeled[*t_n] = count;
+                    stack.push(*t_n);
+                }
+            }
+        }
+        count += 1;
+    }
+    (relabeled, count)
+}
+
+/// Collapses each community into a node.  Edges within a community become a self loop so the
+/// coarse graph keeps the same modularity.
+fn aggregate(graph: &CSR, communities: &[usize], num_communities: usize) -> CSR {
+    let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+    builder.set_num_nodes(num_communities);
+    for node_id in 0..graph.len() {
+        let (edges, weights) = graph.get_edges(node_id);
+        edges.iter().zip(weights.iter()).for_each(|(t_n, w)| {
+            builder.add_edge(communities[node_id], communities[*t_n], *w);
+        });
+    }
+    builder.finalize_csr()
+}
+
+/// Modularity of a partition over a symmetric graph: the fraction of weight within communities
+/// minus what's expected at random, scaled by the resolution.
+pub fn compute_modularity(graph: &CSR, communities: &[usize], resolution: f32) -> f32 {
+    let num_communities = communities.iter().max().map(|c| c + 1).unwrap_or(0);
+    let mut internal = vec![0f32; num_communities];
+    let mut tot = vec![0f32; num_communities];
+    for node_id in 0..graph.len() {
+        let c = communities[node_id];
+        let (edges, weights) = graph.get_edges(node_id);
+        edges.iter().zip(weights.iter()).for_each(|(t_n, w)| {
+            tot[c] += w;
+            if communities[*t_n] == c { internal[c] += w; }
+        });
+    }
+
+    let total = tot.iter().sum::<f32>();
+    if total <= 0. { return 0. }
+    internal.iter().zip(tot.iter())
+        .map(|(i, t)| i / total - resolution * (t / total).powi(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod louvain_tests {
+    use super::*;
+
+    fn two_cliques() -> CSR {
+        let mut edges = Vec::new();
+        for offset in [0, 4] {
+            for f in 0..4 {
+                for t in 0..4 {
+                    if f != t { edges.push((f + offset, t + offset, 1.)); }
+                }
+            }
+        }
+        edges.push((3, 4, 1.));
+        edges.push((4, 3, 1.));
+        CSR::construct_from_edges(edges)
+    }
+
+    #[test]
+    fn test_two_cliques() {
+        let graph = two_cliques();
+        let louvain = Louvain { resolution: 1., max_levels: 10, passes: 10, seed: 2023 };
+        let communities = louvain.detect(&graph);
+
+        let assignments = communities.assignments();
+        assert_eq!(communities.num_communities(communities.levels.len() - 1), 2);
+        (0..4).for_each(|node_id| assert_eq!(assignments[node_id], assignments[0]));
+        (4..8).for_each(|node_id| assert_eq!(assignments[node_id], assignments[4]));
+        assert_ne!(assignments[0], assignments[4]);
+
+        // 2 * (12 / 26 - (13 / 26)^2)
+        let q = *communities.modularity.last().unwrap();
+        assert!((q - 11. / 26.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_modularity() {
+        let graph = two_cliques();
+        assert_eq!(compute_modularity(&graph, &[0; 8], 1.), 0.);
+        let singletons: Vec<_> = (0..8).collect();
+        assert!(compute_modularity(&graph, &singletons, 1.) < 0.);
+    }
+}
@@ -0,0 +1,217 @@
+//! Multi-table locality sensitive hashing.  Each table projects embeddings onto random
+//! hyperplanes, packing the signs into a bit signature which keys a bucket.  Queries collect the
+//! members of their bucket, plus buckets within a small Hamming radius, across every table before
+//! scoring them exactly.  There's nothing to learn beyond the store's mean, so builds are far
+//! cheaper than trees or graphs, making this a good fit for indices rebuilt frequently.
+use hashbrown::{HashMap,HashSet};
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::simd;
+use crate::algos::graph_ann::{NodeDistance,TopK};
+use crate::algos::ann::AnnIndex;
+
+struct Table {
+    /// Row major [n_bits, dims] projection matrix
+    planes: Vec<Vec<f32>>,
+    buckets: HashMap<u64, Vec<NodeID>>
+}
+
+impl Table {
+    fn signature(&self, emb: &[f32], mean: &[f32], scratch: &mut Vec<f32>) -> u64 {
+        scratch.clear();
+        scratch.extend(emb.iter().zip(mean.iter()).map(|(ei, mi)| ei - mi));
+        self.planes.iter().enumerate().fold(0u64, |sig, (bit, plane)| {
+            if simd::dot(plane, scratch) >= 0. { sig | (1 << bit) } else { sig }
+        })
+    }
+}
+
+pub struct Lsh {
+    n_tables: usize,
+
+    /// Bits per signature, at most 64.  More bits make smaller, more precise buckets.
+    n_bits: usize,
+
+    /// Also probe buckets whose signatures differ by up to this many bits.  0 or 1 is typical.
+    pub probe_radius: usize,
+
+    seed: u64,
+
+    /// Embeddings are centered before hashing so hyperplanes through the origin split the data
+    mean: Vec<f32>,
+
+    tables: Vec<Table>
+}
+
+impl Lsh {
+    pub fn new(n_tables: usize, n_bits: usize, probe_radius: usize, seed: u64) -> Self {
+        Lsh {
+            n_tables: n_tables.max(1),
+            n_bits: n_bits.max(1).min(64),
+            probe_radius,
+            seed,
+            mean: Vec::new(),
+            tables: Vec::new()
+        }
+    }
+
+    pub fn num_tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Number of nodes in the index
+    pub fn len(&self) -> usize {
+        self.tables.first()
+            .map(|t| t.buckets.values().map(|b| b.len()).sum())
+            .unwrap_or(0)
+    }
+
+    /// Enumerates every signature within `radius` bits of `sig`
+    fn probes(&self, sig: u64, radius: usize, out: &mut Vec<u64>) {
+        out.clear();
+        out.push(sig);
+        let mut frontier = vec![(sig, 0usize)];
+        for _ in 0..radius.min(self.n_bits) {
+            let mut next = Vec::new();
+            for (s, start) in frontier.into_iter() {
+                for bit in start..self.n_bits {
+                    let flipped = s ^ (1 << bit);
+                    out.push(flipped);
+                    next.push((flipped, bit + 1));
+                }
+            }
+            frontier = next;
+        }
+    }
+
+    /// Unique candidates found across the tables
+    fn candidates(&self, emb: &[f32]) -> HashSet<NodeID> {
+        let mut scratch = Vec::with_capacity(emb.len());
+        let mut probes = Vec::new();
+        let mut candidates = HashSet::new();
+        for table in self.tables.iter() {
+            let sig = table.signature(emb, &self.mean, &mut scratch);
+            self.probes(sig, self.probe_radius, &mut probes);
+            for probe in probes.iter() {
+                if let Some(bucket) = table.buckets.get(probe) {
+                    candidates.extend(bucket.iter().cloned());
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl AnnIndex for Lsh {
+
+    fn fit(&mut self, es: &EmbeddingStore) {
+        let dims = es.dims();
+        let n = es.len().max(1) as f32;
+        self.mean = es.par_iter()
+            .fold(|| vec![0f32; dims], |mut acc, (_, emb)| {
+                acc.iter_mut().zip(emb.iter()).for_each(|(ai, ei)| *ai += ei);
+                acc
+            }).reduce(|| vec![0f32; dims], |mut v1, v2| {
+                v1.iter_mut().zip(v2.iter()).for_each(|(a, b)| *a += b);
+                v1
+            }).into_iter()
+            .map(|mi| mi / n)
+            .collect();
+
+        let mean = &self.mean;
+        let n_bits = self.n_bits;
+        let seed = self.seed;
+        self.tables = (0..self.n_tables).into_par_iter().map(|table_idx| {
+            let mut rng = XorShiftRng::seed_from_u64(seed + table_idx as u64);
+            let planes = (0..n_bits)
+                .map(|_| (0..dims).map(|_| rng.sample::<f32,_>(StandardNormal)).collect())
+                .collect();
+
+            let mut table = Table { planes, buckets: HashMap::new() };
+            let mut scratch = Vec::with_capacity(dims);
+            for node_id in 0..es.len() {
+                let sig = table.signature(es.get_embedding(node_id), mean, &mut scratch);
+                table.buckets.entry(sig).or_insert_with(Vec::new).push(node_id);
+            }
+            table
+        }).collect();
+    }
+
+    /// Hashes the node into each table.  The mean isn't updated, which is fine unless the
+    /// distribution of new nodes drifts substantially.
+    fn insert(&mut self, es: &EmbeddingStore, node_id: NodeID) {
+        let emb = es.get_embedding(node_id);
+        if self.mean.is_empty() {
+            self.mean = vec![0f32; emb.len()];
+        }
+        let mean = &self.mean;
+        self.tables.par_iter_mut().for_each(|table| {
+            let mut scratch = Vec::with_capacity(emb.len());
+            let sig = table.signature(emb, mean, &mut scratch);
+            table.buckets.entry(sig).or_insert_with(Vec::new).push(node_id);
+        });
+    }
+
+    fn predict(&self, es: &EmbeddingStore, emb: &[f32], k: usize) -> Vec<NodeDistance> {
+        let candidates = self.candidates(emb);
+        let scorer = es.scorer(emb);
+        let mut heap = TopK::new(k.min(candidates.len()));
+        candidates.iter().for_each(|node_id| {
+            heap.push(*node_id, scorer(*node_id, es.get_embedding(*node_id)));
+        });
+        heap.into_sorted()
+    }
+}
+
+#[cfg(test)]
+mod lsh_tests {
+    use super::*;
+    use crate::embeddings::{Distance,Entity};
+
+    #[test]
+    fn test_probes() {
+        let lsh = Lsh::new(1, 4, 2, 0);
+        let mut out = Vec::new();
+        lsh.probes(0b0000, 1, &mut out);
+        assert_eq!(out, vec![0b0000, 0b0001, 0b0010, 0b0100, 0b1000]);
+
+        // 1 + 4 + 6 signatures, none repeated
+        lsh.probes(0b0101, 2, &mut out);
+        assert_eq!(out.len(), 11);
+        assert_eq!(out.iter().collect::<HashSet<_>>().len(), 11);
+    }
+
+    #[test]
+    fn test_lsh_recall() {
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let mut es = EmbeddingStore::new(2000, 8, Distance::Cosine);
+        for node_id in 0..es.len() {
+            let emb: Vec<f32> = (0..8).map(|_| rng.sample::<f32,_>(StandardNormal)).collect();
+            es.set_embedding(node_id, &emb);
+        }
+
+        let mut lsh = Lsh::new(10, 8, 1, 1234);
+        lsh.fit(&es);
+        assert_eq!(lsh.len(), 2000);
+
+        let k = 10;
+        let mut hits = 0;
+        for node_id in (0..es.len()).step_by(100) {
+            let emb = es.get_embedding(node_id);
+            let expected: HashSet<_> = es.nearest_neighbors(&Entity::Embedding(emb), k)
+                .into_iter().map(|nd| nd.1).collect();
+
+            let results = lsh.predict(&es, emb, k);
+            assert_eq!(results[0].1, node_id);
+            hits += results.iter().filter(|nd| expected.contains(&nd.1)).count();
+        }
+
+        let recall = hits as f32 / (20 * k) as f32;
+        assert!(recall > 0.7, "Recall too low: {}", recall);
+    }
+}
@@ -16,7 +16,7 @@ impl LSR {
     
     pub fn compute(
         &self, 
-        graph: &impl CDFGraph, 
+        graph: &(impl CDFGraph + Sync), 
         degrees: &EmbeddingStore,
         indicator: bool
     ) -> Vec<f32> {
@@ -0,0 +1,177 @@
+//! metapath2vec random walks (Dong et al., 2017) for heterogeneous graphs.  Each step may only
+//! move to a neighbor of the next node type in the metapath, so walks visit node types in a
+//! prescribed ratio instead of being dominated by the most common type.  The walks are meant to
+//! be fed to the skip-gram trainer.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{CDFGraph,CDFtoP,NodeID};
+use crate::vocab::Vocab;
+
+/// A cyclic sequence of node types, such as author -> paper -> venue -> paper -> author.
+pub struct MetaPath {
+    /// Node type ids in path order.  The first and last are the same type.
+    types: Vec<usize>,
+
+    /// Node type id of every node in the vocab
+    node_types: Vec<usize>
+}
+
+impl MetaPath {
+    /// Resolves the node types against the vocab.  Errors if the path has fewer than two types,
+    /// a type isn't in the vocab, or the path doesn't start and end on the same type.
+    pub fn new(vocab: &Vocab, metapath: &[String]) -> Result<Self,String> {
+        if metapath.len() < 2 {
+            Err("Metapaths need at least two node types!")?
+        }
+        if metapath[0] != metapath[metapath.len() - 1] {
+            Err("Metapaths must start and end with the same node type!")?
+        }
+
+        let types = metapath.iter().map(|node_type| {
+            vocab.get_node_type_id(node_type)
+                .ok_or_else(|| format!("Node type '{}' not in vocab!", node_type))
+        }).collect::<Result<Vec<_>,_>>()?;
+
+        let node_types = (0..vocab.len())
+            .map(|node_id| vocab.get_node_type_id_of(node_id).expect("Node should have a type"))
+            .collect();
+
+        Ok(MetaPath { types, node_types })
+    }
+
+    /// Node type id required at a given step of a walk, wrapping around the path.
+    fn type_at(&self, step: usize) -> usize {
+        self.types[step % (self.types.len() - 1)]
+    }
+
+    /// All nodes of the path's first type, which is where walks start
+    pub fn start_nodes(&self) -> Vec<NodeID> {
+        (0..self.node_types.len()).filter(|node_id| self.node_types[*node_id] == self.types[0]).collect()
+    }
+}
+
+pub struct MetaPathWalker {
+    /// Number of nodes in each walk, including the start node
+    pub walk_length: usize,
+
+    /// Number of walks started from each node
+    pub walks_per_node: usize,
+
+    pub seed: u64
+}
+
+impl MetaPathWalker {
+
+    /// Generates `walks_per_node` walks from every node of the metapath's first type.  Walks are
+    /// ordered by pass, then by start node, and are reproducible regardless of thread count.
+    pub fn walks<G: CDFGraph + Send + Sync>(
+        &self,
+        graph: &G,
+        metapath: &MetaPath,
+        weighted: bool
+    ) -> Vec<Vec<NodeID>> {
+        let start_nodes = metapath.start_nodes();
+        let n = start_nodes.len();
+        (0..(n * self.walks_per_node)).into_par_iter().map(|idx| {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed + idx as u64);
+            let mut walk = Vec::with_capacity(self.walk_length);
+            self.walk(graph, metapath, start_nodes[idx % n], weighted, &mut rng, &mut walk);
+            walk
+        }).collect()
+    }
+
+    /// Performs a single walk, appending the visited nodes to `output`.  Ends early if the
+    /// current node has no neighbors of the next type.
+    pub fn walk<G: CDFGraph>(
+        &self,
+        graph: &G,
+        metapath: &MetaPath,
+        start_node: NodeID,
+        weighted: bool,
+        rng: &mut impl Rng,
+        output: &mut Vec<NodeID>
+    ) {
+        if self.walk_length == 0 { return }
+
+        output.push(start_node);
+        let mut cur = start_node;
+        while output.len() < self.walk_length {
+            let next_type = metapath.type_at(output.len());
+            let (edges, weights) = graph.get_edges(cur);
+            let probs = CDFtoP::new(weights);
+            let matches = |idx: &usize| metapath.node_types[edges[*idx]] == next_type;
+            let weight = |idx: usize| if weighted { probs.prob(idx) } else { 1. };
+
+            let total = (0..edges.len()).filter(matches).map(weight).sum::<f32>();
+            if total <= 0. { break }
+
+            let mut remaining = rng.gen::<f32>() * total;
+            let mut chosen = None;
+            for idx in (0..edges.len()).filter(matches) {
+                chosen = Some(idx);
+                remaining -= weight(idx);
+                if remaining < 0. { break }
+            }
+
+            match chosen {
+                Some(idx) => {
+                    cur = edges[idx];
+                    output.push(cur);
+                },
+                None => break
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod metapath_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR,Graph};
+
+    #[test]
+    fn test_metapath_walks() {
+        let mut vocab = Vocab::new();
+        let names = [
+            ("author", "a"), ("author", "b"), ("paper", "p1"), ("paper", "p2"),
+            ("venue", "v"), ("author", "c")
+        ];
+        for (nt, name) in names {
+            vocab.get_or_insert(nt.to_string(), name.to_string());
+        }
+
+        // Authors write papers published at a venue; a and c also co-author directly
+        let mut edges = Vec::new();
+        for (f, t) in [(0, 2), (1, 2), (1, 3), (2, 4), (3, 4), (0, 5)] {
+            edges.push((f, t, 1.));
+            edges.push((t, f, 1.));
+        }
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let path: Vec<String> = ["author", "paper", "venue", "paper", "author"].iter()
+            .map(|s| s.to_string()).collect();
+        let metapath = MetaPath::new(&vocab, &path).unwrap();
+        assert_eq!(metapath.start_nodes(), vec![0, 1, 5]);
+
+        let walker = MetaPathWalker { walk_length: 9, walks_per_node: 5, seed: 2023 };
+        let walks = walker.walks(&graph, &metapath, true);
+        assert_eq!(walks.len(), 15);
+
+        let expected = [0, 1, 2, 1, 0, 1, 2, 1, 0];
+        for walk in walks.iter() {
+            walk.iter().enumerate().for_each(|(step, node_id)| {
+                assert_eq!(metapath.node_types[*node_id], expected[step]);
+            });
+            walk.windows(2).for_each(|s| assert!(graph.get_edges(s[0]).0.contains(&s[1])));
+        }
+
+        // Author c only links to another author so can't follow the path
+        assert!(walks.iter().filter(|w| w[0] == 5).all(|w| w.len() == 1));
+        assert!(walks.iter().filter(|w| w[0] == 0).all(|w| w.len() == 9));
+
+        let bad: Vec<String> = ["author", "paper"].iter().map(|s| s.to_string()).collect();
+        assert!(MetaPath::new(&vocab, &bad).is_err());
+    }
+}
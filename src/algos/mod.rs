@@ -1,4 +1,7 @@
 pub mod rwr;
+pub mod salsa;
+pub mod simrank;
+pub mod resistance;
 pub mod grwr;
 pub mod reweighter;
 pub mod dist;
@@ -8,16 +11,46 @@ pub mod ep;
 pub mod graph_ann;
 pub mod utils;
 pub mod aggregator;
+pub mod attribution;
 pub mod smci;
 pub mod feat_propagation;
 pub mod alignment;
 pub mod pprrank;
 pub mod ann;
+pub mod ann_mmap;
 pub mod emb_aligner;
 pub mod pagerank;
+pub mod centrality;
 pub mod vpcg;
 pub mod pprembed;
 pub mod instantembedding;
 pub mod lsr;
 pub mod connected;
+pub mod reduction;
+pub mod spectral;
+pub mod structural;
+pub mod fastrp;
+pub mod smoothing;
+pub mod procrustes;
+pub mod hnsw;
+pub mod ann_eval;
+pub mod ivf;
+pub mod kmeans;
+pub mod knn_graph;
+pub mod density;
+pub mod dedup;
+pub mod lsh;
+pub mod negative_sampler;
+pub mod node2vec;
+pub mod skipgram;
+pub mod metapath;
+pub mod verse;
+pub mod kge;
+pub mod link_scorer;
+pub mod temporal;
+pub mod graph_stats;
+pub mod edge_sampling;
+pub mod coarsen;
+pub mod louvain;
+pub mod triangles;
 mod grad_utils;
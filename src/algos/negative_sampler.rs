@@ -0,0 +1,160 @@
+//! Hard negatives from an ANN index: nodes which are close in embedding space but not connected
+//! in the graph.  Training algorithms share this rather than each rolling their own lookup.
+//! Embeddings drift as training progresses so the index is periodically refit.
+use rand::prelude::*;
+use rand_distr::{Distribution,Uniform};
+
+use crate::graph::{Graph as CGraph,NodeID};
+use crate::embeddings::EmbeddingStore;
+use crate::algos::ann::AnnIndex;
+use crate::algos::grad_utils::node_sampler::NodeSampler;
+
+pub struct NegativeSampler {
+    index: Box<dyn AnnIndex>,
+
+    /// Number of nearest neighbors retrieved per anchor, from which negatives are drawn.  Larger
+    /// pools give easier, more diverse negatives.
+    pool_size: usize,
+
+    /// Refit the index after this many calls to `step`, typically once per epoch
+    refresh_every: usize,
+
+    steps_since_refresh: usize
+}
+
+impl NegativeSampler {
+    /// Wraps an unfit index; `refresh` must be called with the embeddings before sampling.
+    pub fn new(index: Box<dyn AnnIndex>, pool_size: usize, refresh_every: usize) -> Self {
+        NegativeSampler {
+            index,
+            pool_size: pool_size.max(1),
+            refresh_every: refresh_every.max(1),
+            steps_since_refresh: 0
+        }
+    }
+
+    /// Refits the index against the current embeddings.
+    pub fn refresh(&mut self, es: &EmbeddingStore) {
+        self.index.fit(es);
+        self.steps_since_refresh = 0;
+    }
+
+    /// Advances the refresh schedule, refitting when due.  Returns whether a refresh happened.
+    pub fn step(&mut self, es: &EmbeddingStore) -> bool {
+        self.steps_since_refresh += 1;
+        if self.steps_since_refresh >= self.refresh_every {
+            self.refresh(es);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Close nodes which aren't the anchor or its neighbors, closest first.
+    pub fn candidates(
+        &self,
+        graph: &impl CGraph,
+        es: &EmbeddingStore,
+        anchor: NodeID
+    ) -> Vec<NodeID> {
+        let edges = if anchor < graph.len() { graph.get_edges(anchor).0 } else { &[] };
+        self.index.predict_node(es, anchor, self.pool_size).into_iter()
+            .map(|nd| nd.1)
+            .filter(|node_id| !edges.contains(node_id))
+            .collect()
+    }
+
+    /// Draws up to `num_negs` hard negatives uniformly from the anchor's candidate pool.
+    pub fn sample<R: Rng>(
+        &self,
+        graph: &impl CGraph,
+        es: &EmbeddingStore,
+        anchor: NodeID,
+        num_negs: usize,
+        rng: &mut R
+    ) -> Vec<NodeID> {
+        let candidates = self.candidates(graph, es, anchor);
+        candidates.choose_multiple(rng, num_negs).cloned().collect()
+    }
+
+    /// Adapts the sampler for algorithms using `NodeSampler`, topping up the hard negatives with
+    /// uniform draws from `train_idxs`.  With no `train_idxs`, only hard negatives are returned.
+    pub fn node_sampler<'a>(
+        &'a self,
+        es: &'a EmbeddingStore,
+        num_hard_negatives: usize,
+        train_idxs: &'a [NodeID]
+    ) -> AnnHardSampler<'a> {
+        AnnHardSampler { sampler: self, es, num_hard_negatives, train_idxs }
+    }
+}
+
+pub struct AnnHardSampler<'a> {
+    sampler: &'a NegativeSampler,
+    es: &'a EmbeddingStore,
+    num_hard_negatives: usize,
+    train_idxs: &'a [NodeID]
+}
+
+impl <'a> NodeSampler for AnnHardSampler<'a> {
+    fn sample_negatives<R: Rng>(
+        &self,
+        graph: &impl CGraph,
+        anchor: NodeID,
+        negatives: &mut Vec<NodeID>,
+        num_negs: usize,
+        rng: &mut R
+    ) {
+        let num_hard_negs = self.num_hard_negatives.min(num_negs);
+        negatives.extend(self.sampler.sample(graph, self.es, anchor, num_hard_negs, rng));
+        if self.train_idxs.is_empty() { return }
+
+        let dist = Uniform::new(0, self.train_idxs.len());
+        while negatives.len() < num_negs {
+            negatives.push(self.train_idxs[dist.sample(rng)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod negative_sampler_tests {
+    use super::*;
+    use rand_xorshift::XorShiftRng;
+    use crate::graph::CSR;
+    use crate::embeddings::Distance;
+    use crate::algos::ann::Ann;
+
+    #[test]
+    fn test_excludes_neighbors() {
+        let mut es = EmbeddingStore::new(20, 1, Distance::Euclidean);
+        (0..20).for_each(|i| es.set_embedding(i, &[i as f32]));
+
+        // 10 is connected to its two closest nodes
+        let graph = CSR::construct_from_edges(vec![(10, 9, 1.), (10, 11, 1.), (19, 0, 1.)]);
+
+        let mut sampler = NegativeSampler::new(Box::new(Ann::with_params(1, 100, 1234)), 4, 2);
+        sampler.refresh(&es);
+        let mut candidates = sampler.candidates(&graph, &es, 10);
+        candidates.sort();
+        assert_eq!(candidates, vec![8, 12]);
+
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let negs = sampler.sample(&graph, &es, 10, 5, &mut rng);
+        assert_eq!(negs.len(), 2);
+        assert!(negs.iter().all(|n| *n == 8 || *n == 12));
+
+        let train_idxs: Vec<_> = (0..20).collect();
+        let mut negatives = Vec::new();
+        sampler.node_sampler(&es, 1, &train_idxs).sample_negatives(&graph, 10, &mut negatives, 3, &mut rng);
+        assert_eq!(negatives.len(), 3);
+        assert!(negatives[0] == 8 || negatives[0] == 12);
+
+        // Nothing to top up from
+        negatives.clear();
+        sampler.node_sampler(&es, 1, &[]).sample_negatives(&graph, 10, &mut negatives, 3, &mut rng);
+        assert_eq!(negatives.len(), 1);
+
+        assert!(!sampler.step(&es));
+        assert!(sampler.step(&es));
+    }
+}
@@ -0,0 +1,151 @@
+//! node2vec second order random walks.  The return parameter p controls how likely a walk is to
+//! step straight back to the previous node, while the in-out parameter q trades off BFS-like
+//! exploration of the previous node's neighborhood (q > 1) against DFS-like outward moves (q < 1).
+//! Rather than precomputing transition tables for every edge pair, which is quadratic in degree,
+//! we draw from the first order distribution and accept with the node2vec bias via rejection
+//! sampling.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID};
+use crate::sampler::Sampler;
+
+pub struct Node2Vec {
+    /// Number of nodes in each walk, including the start node
+    pub walk_length: usize,
+
+    /// Number of walks started from each node
+    pub walks_per_node: usize,
+
+    /// Return parameter; higher values discourage immediately revisiting the previous node
+    pub p: f32,
+
+    /// In-out parameter; higher values keep walks local, lower values push them outward
+    pub q: f32,
+
+    pub seed: u64
+}
+
+impl Node2Vec {
+
+    /// Generates `walks_per_node` walks from every start node.  Walks are ordered by pass, then
+    /// by start node, and each is seeded by its position so output is reproducible regardless of
+    /// thread count.  Walks end early when they reach a node without out edges.
+    pub fn walks<G: Graph + Send + Sync>(
+        &self,
+        graph: &G,
+        sampler: &impl Sampler<G>,
+        start_nodes: &[NodeID]
+    ) -> Vec<Vec<NodeID>> {
+        let n = start_nodes.len();
+        (0..(n * self.walks_per_node)).into_par_iter().map(|idx| {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed + idx as u64);
+            let mut walk = Vec::with_capacity(self.walk_length);
+            self.walk(graph, sampler, start_nodes[idx % n], &mut rng, &mut walk);
+            walk
+        }).collect()
+    }
+
+    /// Performs a single walk, appending the visited nodes to `output`.
+    pub fn walk<G: Graph>(
+        &self,
+        graph: &G,
+        sampler: &impl Sampler<G>,
+        start_node: NodeID,
+        rng: &mut impl Rng,
+        output: &mut Vec<NodeID>
+    ) {
+        if self.walk_length == 0 { return }
+
+        output.push(start_node);
+        let inv_p = 1. / self.p;
+        let inv_q = 1. / self.q;
+        let max_bias = inv_p.max(1.).max(inv_q);
+
+        let mut prev: Option<NodeID> = None;
+        let mut cur = start_node;
+        while output.len() < self.walk_length {
+            let next = match prev {
+                // First step has no bias
+                None => sampler.sample(graph, cur, rng),
+                Some(prev) => loop {
+                    let candidate = match sampler.sample(graph, cur, rng) {
+                        Some(c) => c,
+                        None => break None
+                    };
+                    let bias = if candidate == prev {
+                        inv_p
+                    } else if graph.get_edges(prev).0.contains(&candidate) {
+                        1.
+                    } else {
+                        inv_q
+                    };
+                    if rng.gen::<f32>() * max_bias < bias {
+                        break Some(candidate)
+                    }
+                }
+            };
+
+            match next {
+                Some(next) => {
+                    output.push(next);
+                    prev = Some(cur);
+                    cur = next;
+                },
+                None => break
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod node2vec_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::sampler::Weighted;
+
+    fn build_graph() -> CumCSR {
+        // A star: 0 connected to 1..=4, plus a 1-2 edge, undirected
+        let mut edges = Vec::new();
+        for (f, t) in [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2)] {
+            edges.push((f, t, 1.));
+            edges.push((t, f, 1.));
+        }
+        CumCSR::convert(CSR::construct_from_edges(edges))
+    }
+
+    #[test]
+    fn test_walks_reproducible() {
+        let graph = build_graph();
+        let n2v = Node2Vec { walk_length: 10, walks_per_node: 3, p: 1., q: 1., seed: 2023 };
+        let start_nodes: Vec<_> = (0..graph.len()).collect();
+        let walks = n2v.walks(&graph, &Weighted, &start_nodes);
+        assert_eq!(walks.len(), 15);
+        assert!(walks.iter().all(|w| w.len() == 10));
+        assert_eq!(walks[7][0], 2);
+        assert_eq!(walks, n2v.walks(&graph, &Weighted, &start_nodes));
+
+        // Every step follows an edge
+        walks.iter().for_each(|w| w.windows(2).for_each(|s| {
+            assert!(graph.get_edges(s[0]).0.contains(&s[1]));
+        }));
+    }
+
+    #[test]
+    fn test_return_bias() {
+        let graph = build_graph();
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let count_returns = |p: f32, rng: &mut XorShiftRng| {
+            let n2v = Node2Vec { walk_length: 3, walks_per_node: 1, p, q: 1., seed: 0 };
+            (0..1000).filter(|_| {
+                let mut walk = Vec::new();
+                n2v.walk(&graph, &Weighted, 3, rng, &mut walk);
+                walk[2] == 3
+            }).count()
+        };
+
+        // From 3 the walk must go to 0; a low p makes going back to 3 far more likely
+        assert!(count_returns(0.1, &mut rng) > 3 * count_returns(10., &mut rng));
+    }
+}
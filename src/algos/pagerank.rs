@@ -1,7 +1,9 @@
+//! PageRank via power iteration.  Each iteration pulls rank along in-edges so nodes update in
+//! parallel without contention; the transposed transition matrix is built once up front.
 use rayon::prelude::*;
 
 use std::fmt::Write;
-use crate::graph::{CDFGraph, CDFtoP};
+use crate::graph::{CDFGraph, CDFtoP, NodeID};
 use crate::progress::CLProgressBar;
 
 pub struct PageRank {
@@ -10,17 +12,53 @@ pub struct PageRank {
     eps: f32
 }
 
+/// Transition probabilities indexed by destination
+struct InEdges {
+    rows: Vec<usize>,
+    sources: Vec<NodeID>,
+    probs: Vec<f32>
+}
+
+impl InEdges {
+    fn new(graph: &impl CDFGraph) -> Self {
+        let n = graph.len();
+        let mut rows = vec![0; n + 1];
+        for node_id in 0..n {
+            graph.get_edges(node_id).0.iter().for_each(|t_n| rows[*t_n + 1] += 1);
+        }
+        for idx in 1..rows.len() {
+            rows[idx] += rows[idx - 1];
+        }
+
+        let mut counts = rows.clone();
+        let mut sources = vec![0; graph.edges()];
+        let mut probs = vec![0f32; graph.edges()];
+        for node_id in 0..n {
+            let (edges, weights) = graph.get_edges(node_id);
+            edges.iter().zip(CDFtoP::new(weights)).for_each(|(t_n, p)| {
+                sources[counts[*t_n]] = node_id;
+                probs[counts[*t_n]] = p;
+                counts[*t_n] += 1;
+            });
+        }
+        InEdges { rows, sources, probs }
+    }
+}
+
 impl PageRank {
 
     pub fn new(iterations:usize, damping: f32, eps: f32) -> Self {
         PageRank {damping, iterations, eps}
     }
 
-    pub fn compute(&self, graph: &impl CDFGraph, indicator: bool) -> Vec<f32> {
+    /// Iterates until the L2 change in scores drops below eps or the iteration budget is spent.
+    /// Scores sum to 1.
+    pub fn compute(&self, graph: &(impl CDFGraph + Sync), indicator: bool) -> Vec<f32> {
         let n = graph.len();
         let mut policy = vec![1. / n as f32; n];
 
         let mut next_policy = vec![0.; n];
+        let in_edges = InEdges::new(graph);
 
         let pb = CLProgressBar::new(self.iterations as u64, indicator);
         let mut err = std::f32::INFINITY;
@@ -29,28 +67,21 @@ impl PageRank {
                 msg.clear();
                 write!(msg, "Error: {:.5}", err).expect("Should never fail!");
             });
-            next_policy.par_iter_mut().for_each(|vi| *vi = 0.);
-
-            let mut dead_end_weight = 0f32;
-            for node_id in 0..n {
-
-                let (edges, weights) = graph.get_edges(node_id);
-                
-                // Uniformly teleport to all nodes if we're at a dead end
-                if edges.len() == 0 {
-                    dead_end_weight += policy[node_id] / next_policy.len() as f32;
-                } else {
-                    for (edge, pr_k) in edges.iter().zip(CDFtoP::new(weights)) {
-                        next_policy[*edge] += pr_k * policy[node_id];
-                    }
-                }
-            }
-
-            // Update all dead end weights
-            next_policy.par_iter_mut().for_each(|e| {
-                *e += dead_end_weight;
-            });
 
+            // Uniformly teleport to all nodes if we're at a dead end
+            let dead_end_weight = (0..n).into_par_iter()
+                .filter(|node_id| graph.degree(*node_id) == 0)
+                .map(|node_id| policy[node_id])
+                .sum::<f32>() / n as f32;
+
+            next_policy.par_iter_mut().enumerate().for_each(|(node_id, npi)| {
+                let (start, stop) = (in_edges.rows[node_id], in_edges.rows[node_id + 1]);
+                let pulled = in_edges.sources[start..stop].iter()
+                    .zip(in_edges.probs[start..stop].iter())
+                    .map(|(f_n, p)| p * policy[*f_n])
+                    .sum::<f32>();
+                *npi = pulled + dead_end_weight;
+            });
 
             // Random teleportation based on damping
             let s = next_policy.par_iter_mut().map(|pi| {
@@ -71,7 +102,50 @@ impl PageRank {
         pb.finish();
 
         policy
-        
+
     }
 
 }
+
+/// Buckets scores by rank into `buckets` equally sized quantiles, 0 being the lowest.  Rank
+/// buckets are stable across graph sizes, which makes them usable as discrete features.
+pub fn quantile_buckets(scores: &[f32], buckets: usize) -> Vec<usize> {
+    let mut order: Vec<NodeID> = (0..scores.len()).collect();
+    order.par_sort_by(|a, b| scores[*a].total_cmp(&scores[*b]).then(a.cmp(b)));
+    let mut assigned = vec![0; scores.len()];
+    order.into_iter().enumerate().for_each(|(rank, node_id)| {
+        assigned[node_id] = rank * buckets.max(1) / scores.len();
+    });
+    assigned
+}
+
+#[cfg(test)]
+mod pagerank_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+
+    #[test]
+    fn test_pagerank() {
+        // Node 3 is a dead end
+        let edges = vec![
+            (1, 0, 1.),
+            (2, 0, 1.),
+            (2, 1, 1.),
+            (0, 2, 1.),
+            (0, 4, 1.),
+            (4, 3, 1.),
+        ];
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let scores = PageRank::new(100, 0.85, 1e-8).compute(&graph, false);
+        assert!((scores.iter().sum::<f32>() - 1.).abs() < 1e-5);
+        assert!((1..5).all(|node_id| scores[0] > scores[node_id]));
+        assert!(scores[2] > scores[1]);
+
+        let buckets = quantile_buckets(&scores, 5);
+        assert_eq!(buckets[0], 4);
+        let mut sorted = buckets.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+    }
+}
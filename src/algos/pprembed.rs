@@ -57,7 +57,7 @@ impl PPREmbed {
                 .into_iter()
                 .for_each(|(node_id, weight)| {
                     features.get_features(node_id).iter().for_each(|feat_id| {
-                        let e = feat_maps.entry(*feat_id).or_insert(0f32);
+                        let e = feat_maps.entry(*feat_id as usize).or_insert(0f32);
                         *e += weight;
                     });
                 });
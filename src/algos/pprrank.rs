@@ -266,6 +266,7 @@ impl PprRank {
             feature_store,
             feature_embeddings,
             self.num_features,
+            None,
             rng)
     }
 
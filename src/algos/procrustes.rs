@@ -0,0 +1,266 @@
+//! Orthogonal Procrustes alignment between embedding stores.  Retraining rotates an embedding
+//! space arbitrarily, so embeddings from two runs can't be compared directly.  Given anchor nodes
+//! present in both stores, this finds the rotation (and optionally translation) which best maps
+//! one onto the other in the least squares sense (Schönemann, 1966): R = U V^T, where
+//! U S V^T is the SVD of the anchors' cross covariance.  Being orthogonal, the map preserves
+//! distances and norms within the aligned store.
+use rayon::prelude::*;
+
+use crate::graph::NodeID;
+use crate::embeddings::EmbeddingStore;
+use crate::vocab::TranslationTable;
+use crate::algos::reduction::orthonormalize;
+use crate::algos::spectral::symmetric_eigen;
+
+pub struct Procrustes {
+    /// If true, both stores are centered on their anchors' means before rotating.  Leave off for
+    /// cosine embeddings, where the origin matters.
+    pub center: bool
+}
+
+/// A learned orthogonal map: embeddings have `source_mean` removed, are rotated, then have
+/// `target_mean` added.
+pub struct OrthogonalMap {
+    source_mean: Vec<f32>,
+
+    target_mean: Vec<f32>,
+
+    /// Row major [dims, dims] matrix, applied to the centered embedding as a column vector
+    rotation: Vec<Vec<f32>>
+}
+
+impl OrthogonalMap {
+
+    pub fn dims(&self) -> usize {
+        self.rotation.len()
+    }
+
+    pub fn rotation(&self) -> &[Vec<f32>] {
+        &self.rotation
+    }
+
+    /// Maps a single embedding into the target space
+    pub fn transform_into(&self, emb: &[f32], out: &mut [f32]) {
+        self.rotation.iter().zip(out.iter_mut().zip(self.target_mean.iter())).for_each(|(row, (oi, ti))| {
+            *oi = ti + row.iter().zip(emb.iter().zip(self.source_mean.iter()))
+                .map(|(ri, (ei, si))| ri * (ei - si))
+                .sum::<f32>();
+        });
+    }
+
+    pub fn transform(&self, emb: &[f32]) -> Vec<f32> {
+        let mut out = vec![0f32; self.dims()];
+        self.transform_into(emb, &mut out);
+        out
+    }
+
+    /// Materializes a new store in the target space.  The distance metric is retained.
+    pub fn transform_store(&self, es: &EmbeddingStore) -> EmbeddingStore {
+        let mut new_es = EmbeddingStore::new(es.len(), self.dims(), es.distance());
+        es.par_iter().for_each(|(node_id, emb)| {
+            let out = new_es.get_embedding_mut_hogwild(node_id);
+            self.transform_into(emb, out);
+        });
+        (0..es.len()).for_each(|node_id| {
+            if es.is_set(node_id) { new_es.set_bit(node_id) }
+        });
+        new_es
+    }
+
+    /// Root mean squared euclidean distance between mapped source anchors and their targets
+    pub fn rmse(&self, source: &EmbeddingStore, target: &EmbeddingStore, anchors: &[(NodeID, NodeID)]) -> f32 {
+        if anchors.is_empty() { return 0. }
+        let sse = anchors.par_iter().map(|(s, t)| {
+            self.transform(source.get_embedding(*s)).iter().zip(target.get_embedding(*t).iter())
+                .map(|(a, b)| ((a - b) as f64).powi(2))
+                .sum::<f64>()
+        }).sum::<f64>();
+        (sse / anchors.len() as f64).sqrt() as f32
+    }
+}
+
+/// Pairs each source node with its target node, keeping only nodes set in both stores.  The
+/// translation table is typically `source_vocab.create_translation_table(&target_vocab)`.
+pub fn shared_anchors(
+    source: &EmbeddingStore,
+    target: &EmbeddingStore,
+    translation_table: &TranslationTable
+) -> Vec<(NodeID, NodeID)> {
+    translation_table.iter().enumerate()
+        .filter_map(|(s, t)| t.map(|t| (s, t)))
+        .filter(|(s, t)| source.is_set(*s) && target.is_set(*t))
+        .collect()
+}
+
+impl Procrustes {
+
+    /// Learns the map taking each anchor's source embedding to its target embedding.
+    pub fn fit(
+        &self,
+        source: &EmbeddingStore,
+        target: &EmbeddingStore,
+        anchors: &[(NodeID, NodeID)]
+    ) -> OrthogonalMap {
+        assert_eq!(source.dims(), target.dims(), "Embedding dimensions mismatch!");
+        let d = source.dims();
+
+        let mean = |es: &EmbeddingStore, side: fn(&(NodeID, NodeID)) -> NodeID| {
+            let mut mean = vec![0f64; d];
+            if self.center && !anchors.is_empty() {
+                anchors.iter().for_each(|a| {
+                    mean.iter_mut().zip(es.get_embedding(side(a)).iter()).for_each(|(mi, ei)| *mi += *ei as f64);
+                });
+                mean.iter_mut().for_each(|mi| *mi /= anchors.len() as f64);
+            }
+            mean
+        };
+        let source_mean = mean(source, |a| a.0);
+        let target_mean = mean(target, |a| a.1);
+
+        // Cross covariance M = A^T B, flattened [d, d]
+        let m = anchors.par_iter()
+            .fold(|| vec![0f64; d * d], |mut acc, (s, t)| {
+                let a: Vec<f64> = source.get_embedding(*s).iter().zip(source_mean.iter())
+                    .map(|(ei, mi)| *ei as f64 - mi).collect();
+                let b: Vec<f64> = target.get_embedding(*t).iter().zip(target_mean.iter())
+                    .map(|(ei, mi)| *ei as f64 - mi).collect();
+                for i in 0..d {
+                    acc[i * d..(i + 1) * d].iter_mut().zip(b.iter()).for_each(|(x, bj)| *x += a[i] * bj);
+                }
+                acc
+            })
+            .reduce(|| vec![0f64; d * d], |mut x, y| {
+                x.iter_mut().zip(y.into_iter()).for_each(|(xi, yi)| *xi += yi);
+                x
+            });
+
+        let rotation = orthogonal_factor(&m, d);
+        OrthogonalMap {
+            source_mean: source_mean.into_iter().map(|x| x as f32).collect(),
+            target_mean: target_mean.into_iter().map(|x| x as f32).collect(),
+            // Transposed so rows produce the output dimensions
+            rotation: (0..d).map(|j| (0..d).map(|i| rotation[i * d + j] as f32).collect()).collect()
+        }
+    }
+
+    /// Learns the map using every node present in both stores as an anchor.
+    pub fn fit_translated(
+        &self,
+        source: &EmbeddingStore,
+        target: &EmbeddingStore,
+        translation_table: &TranslationTable
+    ) -> OrthogonalMap {
+        self.fit(source, target, &shared_anchors(source, target, translation_table))
+    }
+}
+
+/// Orthogonal polar factor U V^T of a flattened [d, d] matrix M = U S V^T.  V and S come from the
+/// eigendecomposition of M^T M, and U = M V S^-1.  Columns of U for vanishing singular values are
+/// undetermined, so they're completed to an orthonormal basis.
+fn orthogonal_factor(m: &[f64], d: usize) -> Vec<f64> {
+    let mut mtm = vec![0f64; d * d];
+    for i in 0..d {
+        for j in 0..d {
+            mtm[i * d + j] = (0..d).map(|k| m[k * d + i] * m[k * d + j]).sum::<f64>();
+        }
+    }
+    let (values, vectors) = symmetric_eigen(mtm, d);
+
+    // Largest singular values first so the best determined directions are fixed first
+    let mut order: Vec<usize> = (0..d).collect();
+    order.sort_by(|a, b| values[*b].total_cmp(&values[*a]));
+    let v: Vec<Vec<f64>> = order.iter().map(|k| (0..d).map(|i| vectors[i * d + k]).collect()).collect();
+
+    let max_sv = values.iter().cloned().fold(0f64, f64::max).max(0.).sqrt();
+    let mut u: Vec<Vec<f64>> = order.iter().zip(v.iter())
+        .map(|(k, vk)| (values[*k].max(0.).sqrt(), vk))
+        .take_while(|(sv, _)| *sv > 1e-6 * max_sv && *sv > 0.)
+        .map(|(sv, vk)| {
+            (0..d).map(|i| m[i * d..(i + 1) * d].iter().zip(vk.iter()).map(|(mi, vi)| mi * vi).sum::<f64>() / sv).collect()
+        })
+        .collect();
+    orthonormalize(&mut u);
+
+    // Fill in the undetermined columns from the standard basis.  Some basis vector always keeps
+    // at least 1 / sqrt(d) of its norm outside the span, so a small cutoff never runs short.
+    for i in 0..d {
+        if u.len() == d { break }
+        let mut e = vec![0f64; d];
+        e[i] = 1.;
+        for _ in 0..2 {
+            for uk in u.iter() {
+                let dot = uk.iter().zip(e.iter()).map(|(a, b)| a * b).sum::<f64>();
+                e.iter_mut().zip(uk.iter()).for_each(|(ei, ui)| *ei -= dot * ui);
+            }
+        }
+        let norm = e.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 1e-3 {
+            e.iter_mut().for_each(|x| *x /= norm);
+            u.push(e);
+        }
+    }
+
+    let mut r = vec![0f64; d * d];
+    for (uk, vk) in u.iter().zip(v.iter()) {
+        for i in 0..d {
+            r[i * d..(i + 1) * d].iter_mut().zip(vk.iter()).for_each(|(x, vj)| *x += uk[i] * vj);
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod procrustes_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_recovers_rotation() {
+        // Target is the source rotated 90 degrees and shifted by (5, -1)
+        let points = [[1., 0.], [0., 2.], [-1., -1.], [3., 1.], [2., -2.]];
+        let mut source = EmbeddingStore::new(5, 2, Distance::Euclidean);
+        let mut target = EmbeddingStore::new(5, 2, Distance::Euclidean);
+        points.iter().enumerate().for_each(|(node_id, [x, y])| {
+            source.set_embedding(node_id, &[*x, *y]);
+            target.set_embedding(node_id, &[-y + 5., *x - 1.]);
+        });
+
+        // The last node is held out
+        let anchors: Vec<_> = (0..4).map(|node_id| (node_id, node_id)).collect();
+        let map = Procrustes { center: true }.fit(&source, &target, &anchors);
+        assert!(map.rmse(&source, &target, &anchors) < 1e-5);
+
+        let mapped = map.transform(source.get_embedding(4));
+        assert!((mapped[0] - 7.).abs() < 1e-5);
+        assert!((mapped[1] - 1.).abs() < 1e-5);
+
+        // Without centering the translation can't be recovered
+        let map = Procrustes { center: false }.fit(&source, &target, &anchors);
+        assert!(map.rmse(&source, &target, &anchors) > 1.);
+    }
+
+    #[test]
+    fn test_degenerate_anchors() {
+        // A single anchor pins down one direction; the rest must still be orthogonal
+        let mut source = EmbeddingStore::new(3, 3, Distance::Euclidean);
+        let mut target = EmbeddingStore::new(3, 3, Distance::Euclidean);
+        source.set_embedding(0, &[1., 0., 0.]);
+        target.set_embedding(0, &[0., 0., 1.]);
+        source.set_embedding(2, &[0., 1., 0.]);
+
+        let table = vec![Some(0), Some(1), Some(2)];
+        let anchors = shared_anchors(&source, &target, &table);
+        assert_eq!(anchors, vec![(0, 0)]);
+
+        let map = Procrustes { center: false }.fit_translated(&source, &target, &table);
+        assert_eq!(map.transform(&[1., 0., 0.]).iter().map(|x| x.round()).collect::<Vec<_>>(), vec![0., 0., 1.]);
+        let rotation = map.rotation();
+        for i in 0..3 {
+            for j in 0..3 {
+                let dot = (0..3).map(|k| rotation[i][k] * rotation[j][k]).sum::<f32>();
+                let expected = if i == j { 1. } else { 0. };
+                assert!((dot - expected).abs() < 1e-5);
+            }
+        }
+    }
+}
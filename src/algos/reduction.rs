@@ -0,0 +1,244 @@
+//! Dimensionality reduction for embedding stores.  We often train with wide embeddings but need
+//! narrower ones to serve; these project a store into a smaller space without leaving Rust.
+//! Both reducers produce a linear Projection which can be applied to the store it was fit on as
+//! well as any adhoc embeddings living in the same space.
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::embeddings::EmbeddingStore;
+
+/// A learned linear map: embeddings are centered by `mean` then multiplied by `components`.
+pub struct Projection {
+    /// Offset subtracted prior to projection.  Zeros for random projections.
+    mean: Vec<f32>,
+
+    /// Row major [output_dims, input_dims] matrix
+    components: Vec<Vec<f32>>
+}
+
+impl Projection {
+
+    pub fn input_dims(&self) -> usize {
+        self.mean.len()
+    }
+
+    pub fn output_dims(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn components(&self) -> &[Vec<f32>] {
+        &self.components
+    }
+
+    /// Projects a single embedding into the reduced space
+    pub fn transform_into(&self, emb: &[f32], out: &mut [f32]) {
+        self.components.iter().zip(out.iter_mut()).for_each(|(row, oi)| {
+            *oi = row.iter().zip(emb.iter().zip(self.mean.iter()))
+                .map(|(ri, (ei, mi))| ri * (ei - mi))
+                .sum::<f32>();
+        });
+    }
+
+    pub fn transform(&self, emb: &[f32]) -> Vec<f32> {
+        let mut out = vec![0f32; self.output_dims()];
+        self.transform_into(emb, &mut out);
+        out
+    }
+
+    /// Materializes a new, reduced dimension store.  The distance metric is retained.
+    pub fn transform_store(&self, es: &EmbeddingStore) -> EmbeddingStore {
+        let mut new_es = EmbeddingStore::new(es.len(), self.output_dims(), es.distance());
+        es.par_iter().for_each(|(node_id, emb)| {
+            let out = new_es.get_embedding_mut_hogwild(node_id);
+            self.transform_into(emb, out);
+        });
+        (0..es.len()).for_each(|node_id| {
+            if es.is_set(node_id) { new_es.set_bit(node_id) }
+        });
+        new_es
+    }
+}
+
+/// Principal Component Analysis using subspace iteration over the covariance matrix.  The
+/// covariance is [dims, dims] so this is cheap for typical embedding sizes regardless of the
+/// number of nodes.
+pub struct PCA {
+    /// Number of components to keep
+    pub dims: usize,
+
+    /// Number of subspace iterations.  10-20 is typically plenty.
+    pub iterations: usize,
+
+    /// Random seed for initializing the subspace
+    pub seed: u64
+}
+
+impl PCA {
+
+    pub fn fit(&self, es: &EmbeddingStore) -> Projection {
+        let d = es.dims();
+        let k = self.dims.min(d);
+        let n = es.len().max(1) as f64;
+
+        // Compute the mean
+        let mean = es.par_iter()
+            .fold(|| vec![0f64; d], |mut acc, (_, emb)| {
+                acc.iter_mut().zip(emb.iter())
+                    .for_each(|(ai, ei)| *ai += *ei as f64);
+                acc
+            }).reduce(|| vec![0f64; d], add_vecs);
+        let mean: Vec<f64> = mean.into_iter().map(|mi| mi / n).collect();
+
+        // Covariance, stored as a flattened [d, d] matrix
+        let cov = es.par_iter()
+            .fold(|| vec![0f64; d * d], |mut acc, (_, emb)| {
+                for i in 0..d {
+                    let xi = emb[i] as f64 - mean[i];
+                    let row = &mut acc[i*d..(i+1)*d];
+                    row.iter_mut().zip(emb.iter().zip(mean.iter())).for_each(|(ci, (ej, mj))| {
+                        *ci += xi * (*ej as f64 - mj);
+                    });
+                }
+                acc
+            }).reduce(|| vec![0f64; d * d], add_vecs);
+        let cov: Vec<f64> = cov.into_iter().map(|ci| ci / n).collect();
+
+        // Subspace iteration: Q <- orth(C * Q)
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut q: Vec<Vec<f64>> = (0..k)
+            .map(|_| (0..d).map(|_| rng.sample::<f64,_>(StandardNormal)).collect())
+            .collect();
+        orthonormalize(&mut q);
+
+        for _ in 0..self.iterations {
+            q = q.par_iter().map(|v| mat_vec(&cov, v)).collect();
+            orthonormalize(&mut q);
+        }
+
+        // Order components by explained variance
+        let mut scored: Vec<_> = q.into_iter().map(|v| {
+            let cv = mat_vec(&cov, &v);
+            let variance = v.iter().zip(cv.iter()).map(|(vi, ci)| vi * ci).sum::<f64>();
+            (variance, v)
+        }).collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        Projection {
+            mean: mean.into_iter().map(|mi| mi as f32).collect(),
+            components: scored.into_iter()
+                .map(|(_, v)| v.into_iter().map(|vi| vi as f32).collect())
+                .collect()
+        }
+    }
+
+}
+
+/// Sparse random projection (Achlioptas / Li et al).  Each entry of the projection matrix is
+/// +/- sqrt(1 / (density * dims)) with probability density / 2 and zero otherwise.  No fitting is
+/// required, only the input dimensionality.
+pub struct SparseRandomProjection {
+    /// Output dimensions
+    pub dims: usize,
+
+    /// Fraction of non-zero entries in the projection matrix.  1 / sqrt(input_dims) is the usual
+    /// recommendation.
+    pub density: f32,
+
+    /// Random seed
+    pub seed: u64
+}
+
+impl SparseRandomProjection {
+
+    pub fn fit(&self, input_dims: usize) -> Projection {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let density = self.density.max(1e-6).min(1.);
+        let scale = (1f32 / (density * self.dims as f32)).sqrt();
+        let components = (0..self.dims).map(|_| {
+            (0..input_dims).map(|_| {
+                let p = rng.gen::<f32>();
+                if p < density / 2. {
+                    -scale
+                } else if p < density {
+                    scale
+                } else {
+                    0.
+                }
+            }).collect()
+        }).collect();
+
+        Projection {
+            mean: vec![0f32; input_dims],
+            components
+        }
+    }
+}
+
+fn add_vecs(mut v1: Vec<f64>, v2: Vec<f64>) -> Vec<f64> {
+    v1.iter_mut().zip(v2.into_iter()).for_each(|(a, b)| *a += b);
+    v1
+}
+
+/// Multiplies a flattened square matrix by a vector
+fn mat_vec(m: &[f64], v: &[f64]) -> Vec<f64> {
+    let d = v.len();
+    (0..d).map(|i| {
+        m[i*d..(i+1)*d].iter().zip(v.iter()).map(|(mi, vi)| mi * vi).sum::<f64>()
+    }).collect()
+}
+
+/// Modified Gram-Schmidt, in place
+pub(crate) fn orthonormalize(vs: &mut Vec<Vec<f64>>) {
+    for i in 0..vs.len() {
+        let (prev, rest) = vs.split_at_mut(i);
+        let v = &mut rest[0];
+        for p in prev.iter() {
+            let dot = p.iter().zip(v.iter()).map(|(pi, vi)| pi * vi).sum::<f64>();
+            v.iter_mut().zip(p.iter()).for_each(|(vi, pi)| *vi -= dot * pi);
+        }
+        let norm = v.iter().map(|vi| vi * vi).sum::<f64>().sqrt();
+        if norm > 1e-12 {
+            v.iter_mut().for_each(|vi| *vi /= norm);
+        }
+    }
+}
+
+#[cfg(test)]
+mod reduction_tests {
+    use super::*;
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_pca_finds_major_axis() {
+        // Points along the (1, 1, 0) direction with a little noise in z
+        let mut es = EmbeddingStore::new(100, 3, Distance::Euclidean);
+        for node_id in 0..100 {
+            let t = node_id as f32 - 50.;
+            let z = if node_id % 2 == 0 { 0.1 } else { -0.1 };
+            es.set_embedding(node_id, &[t, t, z]);
+        }
+
+        let pca = PCA { dims: 1, iterations: 20, seed: 1234 };
+        let proj = pca.fit(&es);
+        let c = &proj.components()[0];
+        let expected = 1f32 / 2f32.sqrt();
+        assert!((c[0].abs() - expected).abs() < 1e-3);
+        assert!((c[1].abs() - expected).abs() < 1e-3);
+        assert!(c[2].abs() < 1e-3);
+
+        let reduced = proj.transform_store(&es);
+        assert_eq!(reduced.dims(), 1);
+        assert_eq!(reduced.len(), 100);
+    }
+
+    #[test]
+    fn test_random_projection_dims() {
+        let srp = SparseRandomProjection { dims: 4, density: 0.5, seed: 1 };
+        let proj = srp.fit(16);
+        assert_eq!(proj.input_dims(), 16);
+        assert_eq!(proj.output_dims(), 4);
+        assert_eq!(proj.transform(&[0f32; 16]), vec![0f32; 4]);
+    }
+}
@@ -0,0 +1,265 @@
+//! Approximate effective resistance via Laplacian sketching (Spielman and Srivastava, 2008).
+//! The resistance between u and v is ||W^1/2 B L^+ (e_u - e_v)||^2, which a random +/- projection
+//! of the edges preserves up to 1 +/- eps with O(log n / eps^2) dimensions.  Each dimension costs
+//! one Laplacian solve, done with conjugate gradient, after which any pair is a dot product away.
+//!
+//! Edge resistances weighted by the edge's weight are its leverage: bridges and other edges with
+//! few alternatives score near one, while edges inside dense clusters score low.  Sampling edges
+//! by leverage gives a spectral sparsifier of the graph.
+//!
+//! The graph is treated as undirected, averaging the weights of each edge in both directions.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,CSR,NodeID,GraphBuilder,EdgeMerge};
+
+pub struct EffectiveResistance {
+    /// Number of random projections.  Relative error shrinks with 1/sqrt(dims).
+    pub dims: usize,
+
+    /// Maximum conjugate gradient iterations per projection
+    pub iterations: usize,
+
+    /// Conjugate gradient stops once the residual norm falls below this fraction of the
+    /// right hand side's
+    pub tolerance: f32,
+
+    pub seed: u64
+}
+
+/// Sketch from which resistances between any pair of nodes can be read off
+pub struct ResistanceSketch {
+    dims: usize,
+
+    /// Row major [num_nodes, dims] projections of L^+
+    projections: Vec<f32>,
+
+    /// Undirected edges as (smaller id, larger id, weight)
+    edges: Vec<(NodeID, NodeID, f32)>
+}
+
+impl ResistanceSketch {
+
+    pub fn num_nodes(&self) -> usize {
+        self.projections.len() / self.dims.max(1)
+    }
+
+    pub fn edges(&self) -> &[(NodeID, NodeID, f32)] {
+        &self.edges
+    }
+
+    /// Approximate effective resistance between two nodes.  Only meaningful for nodes in the
+    /// same component.
+    pub fn resistance(&self, u: NodeID, v: NodeID) -> f32 {
+        let pu = &self.projections[u * self.dims..(u + 1) * self.dims];
+        let pv = &self.projections[v * self.dims..(v + 1) * self.dims];
+        pu.iter().zip(pv.iter()).map(|(a, b)| (a - b) * (a - b)).sum()
+    }
+
+    /// Resistance of every undirected edge, in the same order as `edges`
+    pub fn edge_resistances(&self) -> Vec<f32> {
+        self.edges.par_iter().map(|(u, v, _)| self.resistance(*u, *v)).collect()
+    }
+
+    /// Leverage of every undirected edge, weight * resistance ~ [0, 1].  Sums to roughly the
+    /// number of nodes minus the number of components.
+    pub fn leverage(&self) -> Vec<f32> {
+        self.edges.par_iter().map(|(u, v, w)| (w * self.resistance(*u, *v)).min(1.)).collect()
+    }
+
+    /// Spectral sparsification: samples edges with replacement proportional to their leverage
+    /// and reweights each sample by 1 / (samples * p), so the sparsified Laplacian matches the
+    /// original in expectation.  Returns a symmetric graph with raw weights.
+    pub fn sparsify(&self, samples: usize, seed: u64) -> CSR {
+        let leverage = self.leverage();
+        let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+        builder.set_num_nodes(self.num_nodes());
+
+        let total = leverage.iter().map(|l| *l as f64).sum::<f64>();
+        if total > 0. && samples > 0 {
+            let mut cdf = Vec::with_capacity(leverage.len());
+            let mut acc = 0f64;
+            leverage.iter().for_each(|l| {
+                acc += *l as f64;
+                cdf.push(acc);
+            });
+
+            let mut rng = XorShiftRng::seed_from_u64(seed);
+            for _ in 0..samples {
+                let target = rng.gen::<f64>() * total;
+                let idx = cdf.partition_point(|c| *c <= target).min(cdf.len() - 1);
+                let (u, v, w) = self.edges[idx];
+                let p = leverage[idx] as f64 / total;
+                let weight = (w as f64 / (samples as f64 * p)) as f32;
+                builder.add_edge(u, v, weight);
+                builder.add_edge(v, u, weight);
+            }
+        }
+        builder.finalize_csr()
+    }
+}
+
+/// Weighted Laplacian of an undirected graph, with a small ridge to keep solves stable
+struct Laplacian {
+    graph: CSR,
+    degrees: Vec<f64>,
+    ridge: f64
+}
+
+impl Laplacian {
+    fn mul(&self, x: &[f64]) -> Vec<f64> {
+        (0..x.len()).into_par_iter().map(|u| {
+            let (edges, weights) = self.graph.get_edges(u);
+            let neighbors = edges.iter().zip(weights.iter())
+                .map(|(v, w)| *w as f64 * x[*v])
+                .sum::<f64>();
+            (self.degrees[u] + self.ridge) * x[u] - neighbors
+        }).collect()
+    }
+
+    /// Conjugate gradient solve of L x = b
+    fn solve(&self, b: &[f64], iterations: usize, tolerance: f64) -> Vec<f64> {
+        let mut x = vec![0f64; b.len()];
+        let mut r = b.to_vec();
+        let mut p = r.clone();
+        let mut rr = dot(&r, &r);
+        let stop = rr * tolerance * tolerance;
+        for _ in 0..iterations {
+            if rr <= stop || rr == 0. { break }
+
+            let lp = self.mul(&p);
+            let alpha = rr / dot(&p, &lp);
+            x.iter_mut().zip(p.iter()).for_each(|(xi, pi)| *xi += alpha * pi);
+            r.iter_mut().zip(lp.iter()).for_each(|(ri, li)| *ri -= alpha * li);
+            let new_rr = dot(&r, &r);
+            let beta = new_rr / rr;
+            p.iter_mut().zip(r.iter()).for_each(|(pi, ri)| *pi = ri + beta * *pi);
+            rr = new_rr;
+        }
+        x
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.par_iter().zip(b.par_iter()).map(|(ai, bi)| ai * bi).sum::<f64>()
+}
+
+impl EffectiveResistance {
+
+    /// Sketches a graph with raw edge weights.
+    pub fn sketch<G: Graph>(&self, graph: &G) -> ResistanceSketch {
+        self.sketch_with(graph, |weights| weights.to_vec())
+    }
+
+    /// Sketches a graph storing weights as CDFs, using the transition probabilities as weights.
+    pub fn sketch_cdf<G: CDFGraph>(&self, graph: &G) -> ResistanceSketch {
+        self.sketch_with(graph, |weights| CDFtoP::new(weights).collect())
+    }
+
+    fn sketch_with<G: Graph, F: Fn(&[f32]) -> Vec<f32>>(&self, graph: &G, node_weights: F) -> ResistanceSketch {
+        let n = graph.len();
+        let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+        builder.set_num_nodes(n);
+        for node_id in 0..n {
+            let (edges, weights) = graph.get_edges(node_id);
+            edges.iter().zip(node_weights(weights)).for_each(|(t_n, w)| {
+                if *t_n != node_id {
+                    builder.add_edge(node_id, *t_n, w / 2.);
+                    builder.add_edge(*t_n, node_id, w / 2.);
+                }
+            });
+        }
+        let sym = builder.finalize_csr();
+
+        let mut edges = Vec::new();
+        for u in 0..n {
+            let (ts, ws) = sym.get_edges(u);
+            ts.iter().zip(ws.iter())
+                .filter(|(v, w)| **v > u && **w > 0.)
+                .for_each(|(v, w)| edges.push((u, *v, *w)));
+        }
+
+        let degrees: Vec<f64> = (0..n)
+            .map(|u| sym.get_edges(u).1.iter().map(|w| *w as f64).sum::<f64>())
+            .collect();
+        let max_degree = degrees.iter().cloned().fold(0f64, f64::max);
+        let laplacian = Laplacian { graph: sym, degrees, ridge: max_degree * 1e-9 };
+
+        let dims = self.dims.max(1);
+        let scale = 1. / (dims as f64).sqrt();
+        let solutions: Vec<Vec<f64>> = (0..dims).into_par_iter().map(|dim| {
+            // Random projection of W^1/2 B, one sign per edge
+            let mut rng = XorShiftRng::seed_from_u64(self.seed + dim as u64);
+            let mut b = vec![0f64; n];
+            edges.iter().for_each(|(u, v, w)| {
+                let q = if rng.gen::<bool>() { scale } else { -scale };
+                let qw = q * (*w as f64).sqrt();
+                b[*u] += qw;
+                b[*v] -= qw;
+            });
+            laplacian.solve(&b, self.iterations, self.tolerance as f64)
+        }).collect();
+
+        let mut projections = vec![0f32; n * dims];
+        projections.par_chunks_mut(dims).enumerate().for_each(|(u, row)| {
+            row.iter_mut().zip(solutions.iter()).for_each(|(ri, z)| *ri = z[u] as f32);
+        });
+
+        ResistanceSketch { dims, projections, edges }
+    }
+}
+
+#[cfg(test)]
+mod resistance_tests {
+    use super::*;
+
+    fn undirected(pairs: &[(NodeID, NodeID)]) -> CSR {
+        let mut edges = Vec::new();
+        for (f, t) in pairs.iter() {
+            edges.push((*f, *t, 1.));
+            edges.push((*t, *f, 1.));
+        }
+        CSR::construct_from_edges(edges)
+    }
+
+    #[test]
+    fn test_path() {
+        let graph = undirected(&[(0, 1), (1, 2), (2, 3)]);
+        let er = EffectiveResistance { dims: 256, iterations: 100, tolerance: 1e-6, seed: 2023 };
+        let sketch = er.sketch(&graph);
+
+        // Tree edges are preserved exactly by the projection
+        assert_eq!(sketch.edges().len(), 3);
+        sketch.edge_resistances().iter().for_each(|r| assert!((r - 1.).abs() < 1e-3));
+
+        // Longer distances are only approximate
+        assert!((sketch.resistance(0, 3) - 3.).abs() < 0.9);
+    }
+
+    #[test]
+    fn test_clique_with_bridge() {
+        // K4 with a pendant node hanging off of node 3
+        let mut pairs = Vec::new();
+        for f in 0..4 {
+            for t in (f + 1)..4 {
+                pairs.push((f, t));
+            }
+        }
+        pairs.push((3, 4));
+        let graph = undirected(&pairs);
+        let er = EffectiveResistance { dims: 256, iterations: 100, tolerance: 1e-6, seed: 2023 };
+        let sketch = er.sketch(&graph);
+
+        // Leverage sums to n - 1, with the bridge as the most important edge
+        let leverage = sketch.leverage();
+        assert!((leverage.iter().sum::<f32>() - 4.).abs() < 0.75);
+        let bridge = sketch.edges().iter().position(|(u, v, _)| (*u, *v) == (3, 4)).unwrap();
+        assert!((leverage[bridge] - 1.).abs() < 1e-3);
+        assert!((sketch.resistance(0, 1) - 0.5).abs() < 0.2);
+
+        let sparse = sketch.sparsify(50, 2023);
+        assert_eq!(sparse.len(), 5);
+        assert!(sparse.get_edges(4).0.contains(&3));
+    }
+}
@@ -181,6 +181,94 @@ impl RWR {
             .collect()
     }
     
+    /// Counts every node visited, rather than just where walks terminate, across walks from the
+    /// start node.  Counts are normalized by the number of walks and discounted by degree^beta
+    /// as in `sample`.  The start node itself is excluded.
+    pub fn visit_counts<G: Graph + Send + Sync>(
+        &self, 
+        graph: &G, 
+        sampler: &impl Sampler<G>,
+        start_node: NodeID
+    ) -> HashMap<NodeID, f32> {
+        let counts = if self.single_threaded {
+            let mut rng = XorShiftRng::seed_from_u64(self.seed);
+            let mut path = Vec::new();
+            let mut counts = HashMap::new();
+            (0..self.walks).for_each(|_| {
+                self.count_walk(graph, sampler, start_node, &mut rng, &mut path, &mut counts);
+            });
+            counts
+        } else {
+            (0..self.walks).into_par_iter()
+                .fold(|| (Vec::new(), HashMap::new()), |(mut path, mut counts), idx| {
+                    let mut rng = XorShiftRng::seed_from_u64(self.seed + idx as u64);
+                    self.count_walk(graph, sampler, start_node, &mut rng, &mut path, &mut counts);
+                    (path, counts)
+                })
+                .map(|(_, counts)| counts)
+                .reduce(|| HashMap::new(), |mut hm1, hm2| {
+                    hm2.into_iter().for_each(|(k, v)| *hm1.entry(k).or_insert(0) += v);
+                    hm1
+                })
+        };
+
+        counts.into_iter()
+            .filter(|(k, _)| *k != start_node)
+            .map(|(k, v)| {
+                let d = (graph.degree(k) as f32).powf(self.beta);
+                (k, v as f32 / ((self.walks as f32) * d))
+            })
+            .collect()
+    }
+
+    fn count_walk<G: Graph + Send + Sync>(
+        &self,
+        graph: &G, 
+        sampler: &impl Sampler<G>,
+        start_node: NodeID,
+        rng: &mut impl Rng,
+        path: &mut Vec<NodeID>,
+        counts: &mut HashMap<NodeID, usize>
+    ) {
+        path.clear();
+        rollout(graph, self.steps, sampler, start_node, rng, path);
+        path.iter().for_each(|node_id| *counts.entry(*node_id).or_insert(0) += 1);
+    }
+
+    /// Computes visit counts for many sources at once, parallelizing across sources.  Each source
+    /// is walked single threaded with its own seed so results match regardless of batch order.
+    pub fn visit_counts_batch<G: Graph + Send + Sync>(
+        &self, 
+        graph: &G, 
+        sampler: &impl Sampler<G>,
+        start_nodes: &[NodeID]
+    ) -> Vec<HashMap<NodeID, f32>> {
+        start_nodes.par_iter().map(|start_node| {
+            let rwr = RWR {
+                single_threaded: true,
+                seed: self.seed + *start_node as u64,
+                ..*self
+            };
+            rwr.visit_counts(graph, sampler, *start_node)
+        }).collect()
+    }
+
+    /// Returns the k most visited nodes for each source, highest score first.
+    pub fn top_visited<G: Graph + Send + Sync>(
+        &self, 
+        graph: &G, 
+        sampler: &impl Sampler<G>,
+        start_nodes: &[NodeID],
+        k: usize
+    ) -> Vec<Vec<(NodeID, f32)>> {
+        self.visit_counts_batch(graph, sampler, start_nodes).into_par_iter().map(|counts| {
+            let mut scores: Vec<_> = counts.into_iter().collect();
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            scores.truncate(k);
+            scores
+        }).collect()
+    }
+
     /// Runs a random walk, returning the terminal node.
     pub fn walk<G: Graph + Send + Sync>(
         &self, 
@@ -310,6 +398,7 @@ mod rwr_tests {
             steps: Steps::Probability(0.1),
             walks: 10_000,
             beta: 0.5,
+            single_threaded: false,
             seed: 20222022
         };
 
@@ -323,4 +412,31 @@ mod rwr_tests {
         assert_eq!(v[2].0, 1);
     }
 
+    #[test]
+    fn test_top_visited() {
+        let edges = build_edges();
+
+        let csr = CSR::construct_from_edges(edges);
+        let ccsr = CumCSR::convert(csr);
+        let rwr = RWR {
+            steps: Steps::Probability(0.3),
+            walks: 10_000,
+            beta: 0.,
+            single_threaded: false,
+            seed: 20222022
+        };
+
+        let top = rwr.top_visited(&ccsr, &Unweighted, &[0, 2], 1);
+        assert_eq!(top.len(), 2);
+
+        // Every walk from 0 must step to 1 first, and from 2 it goes straight to 1
+        assert_eq!(top[0][0].0, 1);
+        assert_eq!(top[1][0].0, 1);
+        assert!(top[0][0].1 >= 1.);
+
+        let counts = rwr.visit_counts_batch(&ccsr, &Unweighted, &[2]);
+        assert!(!counts[0].contains_key(&2));
+        assert_eq!(counts[0], rwr.visit_counts_batch(&ccsr, &Unweighted, &[0, 2])[1]);
+    }
+
 }
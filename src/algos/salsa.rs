@@ -0,0 +1,156 @@
+//! Personalized SALSA (Lempel and Moran, 2001; Gupta et al., 2013) over bipartite graphs.  Walks
+//! leave the seed, cross to the other partition, and come back, alternating between the two
+//! sides until they randomly end.  Visits on the far side score authorities (e.g. items to
+//! recommend a user), while visits on the seed's own side score hubs (e.g. similar users).
+//! Unlike plain random walks with restarts, the two sides are scored separately so popular
+//! nodes on one side don't crowd out the other.
+use hashbrown::HashMap;
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID};
+use crate::bipartite::BipartiteGraph;
+use crate::sampler::Sampler;
+
+pub struct Salsa {
+    /// Number of walks from each seed
+    pub walks: usize,
+
+    /// Probability of ending a walk after each round trip back to the seed's side
+    pub restart: f32,
+
+    /// Maximum number of round trips per walk
+    pub max_steps: usize,
+
+    pub seed: u64
+}
+
+/// Top scoring nodes on each side for a seed, with their visit probabilities, highest first
+pub struct SalsaScores {
+    /// Nodes on the seed's side, excluding the seed itself
+    pub hubs: Vec<(NodeID, f32)>,
+
+    /// Nodes on the other side
+    pub authorities: Vec<(NodeID, f32)>
+}
+
+impl Salsa {
+
+    /// Scores the k best hubs and authorities for a seed node.  Probabilities are the fraction
+    /// of visits to that side which landed on each node.
+    pub fn score<G, S>(
+        &self,
+        graph: &BipartiteGraph<G>,
+        sampler: &S,
+        seed_node: NodeID,
+        k: usize
+    ) -> SalsaScores
+    where
+        G: Graph + Send + Sync,
+        S: Sampler<G>
+    {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed + seed_node as u64);
+        let mut hubs = HashMap::new();
+        let mut authorities = HashMap::new();
+        let restart = self.restart.max(0.).min(1.);
+        for _ in 0..self.walks {
+            let mut cur = seed_node;
+            for _ in 0..self.max_steps {
+                let across = match sampler.sample(graph.graph(), cur, &mut rng) {
+                    Some(node_id) => node_id,
+                    None => break
+                };
+                *authorities.entry(across).or_insert(0usize) += 1;
+
+                cur = match sampler.sample(graph.graph(), across, &mut rng) {
+                    Some(node_id) => node_id,
+                    None => break
+                };
+                *hubs.entry(cur).or_insert(0usize) += 1;
+
+                if rng.gen::<f32>() < restart { break }
+            }
+        }
+
+        let side = graph.side(seed_node);
+        SalsaScores {
+            hubs: top_k(hubs, k, |node_id| node_id != seed_node && graph.side(node_id) == side),
+            authorities: top_k(authorities, k, |node_id| graph.side(node_id) != side)
+        }
+    }
+
+    /// Scores many seeds in parallel.  Each seed uses its own random stream so results don't
+    /// depend on batch order.
+    pub fn score_batch<G, S>(
+        &self,
+        graph: &BipartiteGraph<G>,
+        sampler: &S,
+        seed_nodes: &[NodeID],
+        k: usize
+    ) -> Vec<SalsaScores>
+    where
+        G: Graph + Send + Sync,
+        S: Sampler<G>
+    {
+        seed_nodes.par_iter()
+            .map(|seed_node| self.score(graph, sampler, *seed_node, k))
+            .collect()
+    }
+}
+
+/// Normalizes counts to probabilities, then keeps the k best nodes passing the filter.
+fn top_k(counts: HashMap<NodeID, usize>, k: usize, filter: impl Fn(NodeID) -> bool) -> Vec<(NodeID, f32)> {
+    let total = counts.values().sum::<usize>().max(1) as f32;
+    let mut scores: Vec<_> = counts.into_iter()
+        .filter(|(node_id, _)| filter(*node_id))
+        .map(|(node_id, c)| (node_id, c as f32 / total))
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    scores.truncate(k);
+    scores
+}
+
+#[cfg(test)]
+mod salsa_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::bipartite::Side;
+    use crate::sampler::Weighted;
+    use crate::vocab::Vocab;
+
+    #[test]
+    fn test_user_item() {
+        let mut vocab = Vocab::new();
+        let names = [("user", "a"), ("user", "b"), ("item", "x"), ("item", "y"), ("item", "z")];
+        for (nt, name) in names {
+            vocab.get_or_insert(nt.to_string(), name.to_string());
+        }
+        let edges = vec![
+            (0, 2, 1.), (2, 0, 1.),
+            (0, 3, 1.), (3, 0, 1.),
+            (1, 3, 1.), (3, 1, 1.),
+            (1, 4, 1.), (4, 1, 1.),
+        ];
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+        let bg = BipartiteGraph::new(graph, &vocab, &["user".to_string()], &["item".to_string()]).unwrap();
+
+        let salsa = Salsa { walks: 1000, restart: 0.3, max_steps: 10, seed: 2023 };
+        let scores = salsa.score_batch(&bg, &Weighted, &[0], 10);
+        let scores = &scores[0];
+
+        // y is shared with b, so it outscores a's other item, and z is only reachable through b
+        let items: Vec<_> = scores.authorities.iter().map(|(n, _)| *n).collect();
+        assert_eq!(items, vec![3, 2, 4]);
+        assert!(scores.authorities.iter().all(|(n, _)| bg.side(*n) == Side::Right));
+        assert!((scores.authorities.iter().map(|(_, p)| p).sum::<f32>() - 1.).abs() < 1e-5);
+
+        // The seed is excluded from its own hubs
+        assert_eq!(scores.hubs.len(), 1);
+        assert_eq!(scores.hubs[0].0, 1);
+
+        let top = salsa.score(&bg, &Weighted, 0, 1);
+        assert_eq!(top.authorities.len(), 1);
+        assert_eq!(top.authorities[0].0, 3);
+    }
+}
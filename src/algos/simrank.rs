@@ -0,0 +1,157 @@
+//! Approximate SimRank (Jeh and Widom, 2002): two nodes are similar if their neighbors are
+//! similar.  Pairwise scores use the random surfer formulation, where SimRank is the expected
+//! decay^t for the first step t at which two walks from the nodes meet (Fogaras and Rácz, 2005).
+//! Top-k queries use the linearized single source formulation (Kusumoto et al., 2014) with the
+//! usual (1 - decay) diagonal approximation, which scores every node with a few sparse passes.
+//!
+//! Walks follow the graph's edges.  Classic SimRank walks in-links, so directed graphs should be
+//! transposed first.
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,NodeID};
+use crate::sampler::Sampler;
+
+pub struct SimRank {
+    /// Decay applied per step, ~ (0, 1).  The paper uses 0.8.
+    pub decay: f32,
+
+    /// Number of walk pairs used to estimate a pairwise score
+    pub walks: usize,
+
+    /// Maximum walk length.  Meetings beyond it would contribute at most decay^max_steps.
+    pub max_steps: usize,
+
+    pub seed: u64
+}
+
+impl SimRank {
+
+    /// Estimates SimRank between two nodes from coupled random walks.
+    pub fn similarity<G: Graph, S: Sampler<G>>(
+        &self,
+        graph: &G,
+        sampler: &S,
+        a: NodeID,
+        b: NodeID
+    ) -> f32 {
+        if a == b { return 1. }
+
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let total = (0..self.walks).map(|_| {
+            let (mut ua, mut ub) = (a, b);
+            let mut score = 0f32;
+            let mut weight = 1f32;
+            for _ in 0..self.max_steps {
+                weight *= self.decay;
+                match (sampler.sample(graph, ua, &mut rng), sampler.sample(graph, ub, &mut rng)) {
+                    (Some(na), Some(nb)) => { ua = na; ub = nb; },
+                    _ => break
+                }
+                if ua == ub {
+                    score = weight;
+                    break
+                }
+            }
+            score
+        }).sum::<f32>();
+        total / self.walks.max(1) as f32
+    }
+
+    /// Approximate SimRank from a node to every node in the graph, using the transition
+    /// probabilities.  The node's score with itself is left at zero.
+    pub fn single_source<G: CDFGraph + Sync>(&self, graph: &G, node_id: NodeID) -> Vec<f32> {
+        let n = graph.len();
+        let mut scores = vec![0f32; n];
+        let mut dist = vec![0f32; n];
+        dist[node_id] = 1.;
+
+        let mut weight = 1. - self.decay;
+        for t in 1..=self.max_steps {
+            weight *= self.decay;
+
+            // Where a walk from the node is after t steps
+            let mut next = vec![0f32; n];
+            dist.iter().enumerate().filter(|(_, p)| **p > 0.).for_each(|(f_n, p)| {
+                let (edges, weights) = graph.get_edges(f_n);
+                edges.iter().zip(CDFtoP::new(weights)).for_each(|(t_n, tp)| next[*t_n] += p * tp);
+            });
+            dist = next;
+
+            // Probability of every other node's walk landing on the same spot after t steps
+            let mut meet: Vec<f32> = dist.iter().map(|p| p * weight).collect();
+            for _ in 0..t {
+                meet = (0..n).into_par_iter().map(|f_n| {
+                    let (edges, weights) = graph.get_edges(f_n);
+                    edges.iter().zip(CDFtoP::new(weights)).map(|(t_n, tp)| tp * meet[*t_n]).sum()
+                }).collect();
+            }
+            scores.iter_mut().zip(meet.iter()).for_each(|(s, m)| *s += m);
+        }
+        scores[node_id] = 0.;
+        scores
+    }
+
+    /// Returns the k most similar nodes to each node, highest first.  Nodes with no similarity
+    /// are omitted.
+    pub fn top_k<G: CDFGraph + Sync>(
+        &self,
+        graph: &G,
+        node_ids: &[NodeID],
+        k: usize
+    ) -> Vec<Vec<(NodeID, f32)>> {
+        node_ids.iter().map(|node_id| {
+            let mut scores: Vec<_> = self.single_source(graph, *node_id).into_iter().enumerate()
+                .filter(|(_, s)| *s > 0.)
+                .collect();
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            scores.truncate(k);
+            scores
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod simrank_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::sampler::Weighted;
+
+    fn build() -> CumCSR {
+        // 0 and 1 share neighbor 2; 3 - 4 are disconnected from them
+        let mut edges = Vec::new();
+        for (f, t) in [(0, 2), (1, 2), (3, 4)] {
+            edges.push((f, t, 1.));
+            edges.push((t, f, 1.));
+        }
+        CumCSR::convert(CSR::construct_from_edges(edges))
+    }
+
+    #[test]
+    fn test_similarity() {
+        let graph = build();
+        let simrank = SimRank { decay: 0.8, walks: 100, max_steps: 10, seed: 2023 };
+
+        // Both walks always meet at 2 on the first step
+        assert!((simrank.similarity(&graph, &Weighted, 0, 1) - 0.8).abs() < 1e-5);
+        assert_eq!(simrank.similarity(&graph, &Weighted, 0, 3), 0.);
+        assert_eq!(simrank.similarity(&graph, &Weighted, 2, 2), 1.);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let graph = build();
+        let simrank = SimRank { decay: 0.8, walks: 100, max_steps: 5, seed: 2023 };
+        let scores = simrank.single_source(&graph, 0);
+
+        // Walks from 0 and 2 are always on opposite sides of the star
+        assert_eq!(scores[2], 0.);
+        assert_eq!(scores[3], 0.);
+        assert!(scores[1] > 0.8 * 0.2);
+
+        let top = simrank.top_k(&graph, &[0, 3], 10);
+        assert_eq!(top[0].iter().map(|(n, _)| *n).collect::<Vec<_>>(), vec![1]);
+        assert!(top[1].is_empty());
+    }
+}
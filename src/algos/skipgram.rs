@@ -0,0 +1,257 @@
+//! DeepWalk / node2vec embeddings: skip-gram with negative sampling trained over random walks.
+//! Unlike the feature based learners this needs nothing but the graph, learning a free embedding
+//! per node.  Walks within a batch compute gradients in parallel, which are then summed and
+//! applied with Adam as in EmbeddingPropagation.
+use std::fmt::Write;
+use std::collections::{HashMap as CHashMap};
+
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID};
+use crate::embeddings::{EmbeddingStore,Distance,randomize_embedding_store};
+use crate::progress::CLProgressBar;
+use crate::sampler::Sampler;
+use crate::simd;
+use crate::algos::node2vec::Node2Vec;
+use crate::algos::grad_utils::scheduler::LRScheduler;
+use crate::algos::grad_utils::optimizer::{Optimizer,AdamOptimizer};
+
+pub(crate) type Grads = CHashMap<usize, Vec<f32>>;
+
+pub struct SkipGram {
+    /// Number of dimensions for the learned embeddings
+    pub dims: usize,
+
+    /// Maximum distance within a walk between a node and its contexts
+    pub window: usize,
+
+    /// Number of negatives drawn for each positive pair
+    pub negatives: usize,
+
+    /// Number of passes over the walks
+    pub passes: usize,
+
+    /// Number of walks per gradient update
+    pub batch_size: usize,
+
+    /// Peak learning rate
+    pub alpha: f32,
+
+    pub seed: u64,
+
+    pub indicator: bool
+}
+
+/// Draws negatives proportional to their walk frequency raised to the 3/4 power, as in word2vec.
+struct NegativeTable {
+    cdf: Vec<f64>
+}
+
+impl NegativeTable {
+    fn new(nodes: usize, walks: &[Vec<NodeID>]) -> Self {
+        let mut counts = vec![0usize; nodes];
+        walks.iter().flatten().for_each(|node_id| counts[*node_id] += 1);
+        let mut acc = 0f64;
+        let cdf = counts.into_iter().map(|c| {
+            acc += (c as f64).powf(0.75);
+            acc
+        }).collect();
+        NegativeTable { cdf }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> NodeID {
+        let p = rng.gen::<f64>() * self.cdf[self.cdf.len() - 1];
+        self.cdf.partition_point(|w| *w <= p).min(self.cdf.len() - 1)
+    }
+}
+
+pub(crate) fn sigmoid(x: f32) -> f32 {
+    1. / (1. + (-x).exp())
+}
+
+pub(crate) fn add_grad(grads: &mut Grads, node_id: NodeID, scale: f32, v: &[f32]) {
+    let g = grads.entry(node_id).or_insert_with(|| vec![0.; v.len()]);
+    g.iter_mut().zip(v.iter()).for_each(|(gi, vi)| *gi += scale * vi);
+}
+
+impl SkipGram {
+
+    /// Generates walks from every node with the walker and learns embeddings from them.
+    pub fn learn<G: Graph + Send + Sync>(
+        &self,
+        graph: &G,
+        sampler: &impl Sampler<G>,
+        walker: &Node2Vec
+    ) -> EmbeddingStore {
+        let start_nodes: Vec<_> = (0..graph.len()).collect();
+        let walks = walker.walks(graph, sampler, &start_nodes);
+        self.learn_from_walks(graph.len(), walks)
+    }
+
+    /// Learns embeddings for `nodes` nodes from precomputed walks.  Nodes which never show up in
+    /// a walk keep their random initialization and are left untrained.
+    pub fn learn_from_walks(&self, nodes: usize, mut walks: Vec<Vec<NodeID>>) -> EmbeddingStore {
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut inputs = EmbeddingStore::new(nodes, self.dims, Distance::Cosine);
+        randomize_embedding_store(&mut inputs, &mut rng);
+
+        // Context embeddings start at zero, as in word2vec
+        let contexts = EmbeddingStore::new(nodes, self.dims, Distance::Cosine);
+
+        walks.retain(|walk| walk.len() > 1);
+        if walks.is_empty() || self.window == 0 {
+            return inputs
+        }
+
+        let table = NegativeTable::new(nodes, &walks);
+        let input_optimizer = AdamOptimizer::new(0.9, 0.999, self.dims, nodes);
+        let context_optimizer = AdamOptimizer::new(0.9, 0.999, self.dims, nodes);
+
+        let batch_size = self.batch_size.max(1);
+        let steps_per_pass = (walks.len() as f32 / batch_size as f32).ceil() as usize;
+        let total_updates = steps_per_pass * self.passes;
+        let lr_scheduler = LRScheduler::cos_decay(
+            self.alpha / 100f32, self.alpha, total_updates / 5, total_updates);
+
+        let pb = CLProgressBar::new(total_updates as u64, self.indicator);
+        let mut step = 0;
+        let mut last_error = std::f32::INFINITY;
+        for pass in 1..(self.passes + 1) {
+            pb.update_message(|msg| {
+                msg.clear();
+                write!(msg, "Pass {}/{}, Loss: {:.5}, LR: {:.5}", pass, self.passes,
+                       last_error, lr_scheduler.compute(step))
+                    .expect("Error writing out indicator message!");
+            });
+
+            walks.shuffle(&mut rng);
+            let mut error = 0f32;
+            let mut pairs = 0usize;
+            for batch in walks.chunks(batch_size) {
+                let results: Vec<_> = batch.par_iter().enumerate().map(|(idx, walk)| {
+                    let mut rng = XorShiftRng::seed_from_u64(
+                        self.seed + (step * batch_size + idx) as u64);
+                    self.walk_gradients(walk, &inputs, &contexts, &table, &mut rng)
+                }).collect();
+
+                let mut all_input_grads = CHashMap::new();
+                let mut all_context_grads = CHashMap::new();
+                for (loss, n, input_grads, context_grads) in results {
+                    for (node_id, g) in input_grads {
+                        add_grad(&mut all_input_grads, node_id, 1., &g);
+                    }
+                    for (node_id, g) in context_grads {
+                        add_grad(&mut all_context_grads, node_id, 1., &g);
+                    }
+                    error += loss;
+                    pairs += n;
+                }
+
+                let alpha = lr_scheduler.compute(step);
+                input_optimizer.update(&inputs, all_input_grads, alpha, pass as f32);
+                context_optimizer.update(&contexts, all_context_grads, alpha, pass as f32);
+                step += 1;
+                pb.inc(1);
+            }
+            last_error = error / pairs.max(1) as f32;
+        }
+        pb.finish();
+        inputs
+    }
+
+    /// Accumulates gradients for every (node, context) pair within the window of a walk, along
+    /// with sampled negatives.  Returns the summed loss, number of positive pairs, and the input
+    /// and context gradients.
+    fn walk_gradients<R: Rng>(
+        &self,
+        walk: &[NodeID],
+        inputs: &EmbeddingStore,
+        contexts: &EmbeddingStore,
+        table: &NegativeTable,
+        rng: &mut R
+    ) -> (f32, usize, Grads, Grads) {
+        let mut input_grads = CHashMap::new();
+        let mut context_grads = CHashMap::new();
+        let mut loss = 0f32;
+        let mut pairs = 0;
+        for (i, node_id) in walk.iter().enumerate() {
+            let start = i.saturating_sub(self.window);
+            let stop = (i + self.window + 1).min(walk.len());
+            for j in start..stop {
+                if i == j { continue }
+                let context = walk[j];
+                loss += pair_gradient(*node_id, context, true, inputs, contexts,
+                                      &mut input_grads, &mut context_grads);
+                for _ in 0..self.negatives {
+                    let negative = table.sample(rng);
+                    if negative != context {
+                        loss += pair_gradient(*node_id, negative, false, inputs, contexts,
+                                              &mut input_grads, &mut context_grads);
+                    }
+                }
+                pairs += 1;
+            }
+        }
+        (loss, pairs, input_grads, context_grads)
+    }
+}
+
+/// Logistic loss gradient for a single pair, returning the loss.
+fn pair_gradient(
+    node_id: NodeID,
+    context: NodeID,
+    positive: bool,
+    inputs: &EmbeddingStore,
+    contexts: &EmbeddingStore,
+    input_grads: &mut Grads,
+    context_grads: &mut Grads
+) -> f32 {
+    let u = inputs.get_embedding(node_id);
+    let v = contexts.get_embedding(context);
+    let score = sigmoid(simd::dot(u, v));
+    let (g, p) = if positive { (score - 1., score) } else { (score, 1. - score) };
+    add_grad(input_grads, node_id, g, v);
+    add_grad(context_grads, context, g, u);
+    -p.max(1e-7).ln()
+}
+
+#[cfg(test)]
+mod skipgram_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::sampler::Unweighted;
+
+    #[test]
+    fn test_two_communities() {
+        // Two 4-cliques joined by a single edge between 3 and 4
+        let mut edges = Vec::new();
+        for offset in [0, 4] {
+            for f in 0..4 {
+                for t in 0..4 {
+                    if f != t { edges.push((f + offset, t + offset, 1.)); }
+                }
+            }
+        }
+        edges.push((3, 4, 1.));
+        edges.push((4, 3, 1.));
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let walker = Node2Vec { walk_length: 10, walks_per_node: 20, p: 1., q: 1., seed: 2023 };
+        let sg = SkipGram {
+            dims: 8, window: 2, negatives: 3, passes: 10, batch_size: 16,
+            alpha: 0.05, seed: 2023, indicator: false
+        };
+        let es = sg.learn(&graph, &Unweighted, &walker);
+        assert_eq!(es.len(), 8);
+        assert_eq!(es.num_trained(), 8);
+
+        let sim = |a: NodeID, b: NodeID| {
+            let (ea, eb) = (es.get_embedding(a), es.get_embedding(b));
+            simd::dot(ea, eb) / (simd::norm_squared(ea) * simd::norm_squared(eb)).sqrt()
+        };
+        assert!(sim(0, 1) > sim(0, 6));
+        assert!(sim(5, 6) > sim(1, 6));
+    }
+}
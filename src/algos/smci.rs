@@ -15,7 +15,7 @@ use crate::embeddings::{EmbeddingStore,Entity};
 use crate::hogwild::{Hogwild};
 use crate::sampler::{GreedySampler};
 use crate::algos::rwr::{rollout,Steps};
-use crate::vocab::TranslationTable;
+use crate::vocab::{Vocab,TranslationTable};
 
 pub struct SupervisedMCIteration {
     // number of full passes over the dataset
@@ -49,6 +49,8 @@ pub struct SupervisedMCIteration {
 }
 
 impl SupervisedMCIteration {
+    /// `distances` pairs node embeddings with a table mapping graph nodes to their embedding ids,
+    /// as built by `embedding_distances`.
     pub fn learn(
         &self,
         graph: &(impl CDFGraph + Send + Sync),
@@ -160,6 +162,16 @@ impl SupervisedMCIteration {
     }
 }
 
+/// Pairs embeddings with the translation table `learn` expects, which maps each graph node to its
+/// embedding id.
+pub fn embedding_distances<'a>(
+    graph_vocab: &Vocab,
+    embeddings: &'a EmbeddingStore,
+    embedding_vocab: &Vocab
+) -> (&'a EmbeddingStore, TranslationTable) {
+    (embeddings, graph_vocab.create_translation_table(embedding_vocab))
+}
+
 fn interpolate_edges(alpha: f32, g: &impl CDFGraph, weights: &mut [f32]) {
     let mut t = Vec::new();
     for node_id in 0..g.len() {
@@ -197,4 +209,41 @@ fn scale_weights(weights: &mut [f32], pow: f32) {
 #[cfg(test)]
 mod test_supervised_mc_iteration {
     use super::*;
+    use crate::embeddings::Distance;
+
+    fn build_vocab(names: &[&str]) -> Vocab {
+        let mut vocab = Vocab::new();
+        names.iter().for_each(|name| { vocab.get_or_insert("node".into(), name.to_string()); });
+        vocab
+    }
+
+    #[test]
+    fn test_embedding_distances() {
+        // Embeddings only cover some of the graph nodes, in a different order
+        let graph_vocab = build_vocab(&["a", "b", "c"]);
+        let emb_vocab = build_vocab(&["c", "a"]);
+        let mut embeddings = EmbeddingStore::new(2, 2, Distance::Euclidean);
+        embeddings.set_embedding(0, &[1., 0.]);
+        embeddings.set_embedding(1, &[0., 1.]);
+
+        let distances = embedding_distances(&graph_vocab, &embeddings, &emb_vocab);
+        assert_eq!(distances.1, vec![Some(1), None, Some(0)]);
+
+        // Nothing links to c, so every walk towards it is scored by embedding distance
+        let edges = vec![(0, 1, 1.), (1, 0, 1.), (2, 0, 1.)];
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+        let smci = SupervisedMCIteration {
+            iterations: 2,
+            num_walks: 10,
+            alpha: 0.5,
+            discount: 0.9,
+            step_penalty: 0.,
+            explore_pct: 0.1,
+            compression: 1.,
+            restart_prob: 0.5,
+            seed: 2023
+        };
+        let weights = smci.learn(&graph, &[(0, 2, 1.)], Some(distances));
+        assert_eq!(weights.len(), 3);
+    }
 }
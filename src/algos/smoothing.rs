@@ -0,0 +1,125 @@
+//! Post-processing which smooths trained embeddings over the graph.  Each round blends a node's
+//! embedding with the weighted mean of its neighbors', similar to label propagation.  Nodes with
+//! few or noisy training signals get pulled toward their neighborhood, which tends to help
+//! retrieval, and nodes missing from the store are filled in from their neighbors.
+use rayon::prelude::*;
+
+use crate::graph::{CDFGraph,CDFtoP};
+use crate::embeddings::EmbeddingStore;
+use crate::bitset::BitSet;
+
+pub struct EmbeddingSmoother {
+    /// Number of rounds of propagation
+    pub rounds: usize,
+
+    /// Fraction of a node's own embedding retained each round, ~ [0, 1].  One leaves the
+    /// embeddings untouched; zero replaces them with their neighbors' mean.
+    pub retention: f32
+}
+
+impl EmbeddingSmoother {
+
+    /// Returns the smoothed embeddings.  The graph and store must share node ids.  Only neighbors
+    /// that have embeddings contribute to the mean, with weights renormalized over them.
+    pub fn smooth(&self, graph: &(impl CDFGraph + Sync), es: &EmbeddingStore) -> EmbeddingStore {
+        let n = es.len();
+        let dims = es.dims();
+        let retention = self.retention.max(0.).min(1.);
+
+        let mut known = BitSet::new(n);
+        let mut cur = vec![0f32; n * dims];
+        for node_id in 0..n {
+            if es.is_set(node_id) {
+                known.set_bit(node_id);
+                cur[node_id * dims..(node_id + 1) * dims].copy_from_slice(es.get_embedding(node_id));
+            }
+        }
+
+        if dims > 0 {
+            for _ in 0..self.rounds {
+                let next: Vec<(Vec<f32>, bool)> = (0..n).into_par_iter().map(|node_id| {
+                    let own = &cur[node_id * dims..(node_id + 1) * dims];
+                    let mut mean = vec![0f32; dims];
+                    let mut total = 0f32;
+                    if node_id < graph.len() {
+                        let (edges, weights) = graph.get_edges(node_id);
+                        edges.iter().zip(CDFtoP::new(weights)).for_each(|(t_n, p)| {
+                            if *t_n < n && known.is_set(*t_n) {
+                                let other = &cur[t_n * dims..(t_n + 1) * dims];
+                                mean.iter_mut().zip(other.iter()).for_each(|(mi, oi)| *mi += p * oi);
+                                total += p;
+                            }
+                        });
+                    }
+
+                    let is_known = known.is_set(node_id);
+                    if total <= 0. {
+                        (own.to_vec(), is_known)
+                    } else if !is_known {
+                        mean.iter_mut().for_each(|mi| *mi /= total);
+                        (mean, true)
+                    } else {
+                        mean.iter_mut().zip(own.iter()).for_each(|(mi, oi)| {
+                            *mi = retention * oi + (1. - retention) * *mi / total;
+                        });
+                        (mean, true)
+                    }
+                }).collect();
+
+                next.into_iter().enumerate().for_each(|(node_id, (emb, is_known))| {
+                    cur[node_id * dims..(node_id + 1) * dims].copy_from_slice(&emb);
+                    if is_known { known.set_bit(node_id) }
+                });
+            }
+        }
+
+        let mut new_es = EmbeddingStore::new(n, dims, es.distance());
+        for node_id in 0..n {
+            if known.is_set(node_id) {
+                new_es.get_embedding_mut(node_id)
+                    .copy_from_slice(&cur[node_id * dims..(node_id + 1) * dims]);
+                new_es.set_bit(node_id);
+            }
+        }
+        new_es
+    }
+}
+
+#[cfg(test)]
+mod smoothing_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::embeddings::Distance;
+
+    #[test]
+    fn test_smooth_path() {
+        // Path 0 - 1 - 2, with node 2 missing an embedding
+        let mut edges = Vec::new();
+        for (f, t) in [(0, 1), (1, 2)] {
+            edges.push((f, t, 1.));
+            edges.push((t, f, 1.));
+        }
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let mut es = EmbeddingStore::new(3, 2, Distance::Cosine);
+        es.get_embedding_mut(0).copy_from_slice(&[1., 0.]);
+        es.set_bit(0);
+        es.get_embedding_mut(1).copy_from_slice(&[0., 1.]);
+        es.set_bit(1);
+
+        let smoother = EmbeddingSmoother { rounds: 1, retention: 0.5 };
+        let smoothed = smoother.smooth(&graph, &es);
+        assert_eq!(smoothed.get_embedding(0), &[0.5, 0.5]);
+
+        // Node 2 isn't known yet so node 1 only averages with node 0
+        assert_eq!(smoothed.get_embedding(1), &[0.5, 0.5]);
+
+        // Node 2 takes its only known neighbor's embedding
+        assert!(smoothed.is_set(2));
+        assert_eq!(smoothed.get_embedding(2), &[0., 1.]);
+
+        let unchanged = EmbeddingSmoother { rounds: 3, retention: 1. }.smooth(&graph, &es);
+        assert_eq!(unchanged.get_embedding(0), &[1., 0.]);
+        assert_eq!(unchanged.get_embedding(1), &[0., 1.]);
+    }
+}
@@ -0,0 +1,294 @@
+//! Spectral embeddings via randomized SVD (Halko et al.) of a node similarity matrix, as in HOPE
+//! and NetMF.  Similarities are polynomials of the graph's transition matrix which are never
+//! materialized; we only need products with a thin dense block, so the cost is a handful of
+//! sparse-dense multiplies.  Output is deterministic for a given seed.
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,CDFtoP,NodeID};
+use crate::embeddings::{EmbeddingStore,Distance};
+use crate::algos::reduction::orthonormalize;
+
+/// Similarity matrix to factorize
+#[derive(Clone,Copy,Debug)]
+pub enum Similarity {
+    /// The adjacency matrix itself
+    Adjacency,
+
+    /// Katz index: sum of beta^l * A^l, truncated at `order` hops
+    Katz { beta: f32, order: usize },
+
+    /// Personalized PageRank: sum of restart_p * (1 - restart_p)^l * A^l, truncated at `order`
+    /// hops
+    PPR { restart_p: f32, order: usize }
+}
+
+/// Sparse matrix in CSR form with f64 values for numerically stable products
+struct SparseOp {
+    rows: Vec<usize>,
+    cols: Vec<NodeID>,
+    vals: Vec<f64>
+}
+
+impl SparseOp {
+    fn new<G: Graph>(graph: &G, weights: impl Fn(&[f32]) -> Vec<f32>) -> Self {
+        let mut rows = Vec::with_capacity(graph.len() + 1);
+        let mut cols = Vec::with_capacity(graph.edges());
+        let mut vals = Vec::with_capacity(graph.edges());
+        rows.push(0);
+        for node_id in 0..graph.len() {
+            let (edges, ws) = graph.get_edges(node_id);
+            cols.extend_from_slice(edges);
+            vals.extend(weights(ws).into_iter().map(|w| w as f64));
+            rows.push(cols.len());
+        }
+        SparseOp { rows, cols, vals }
+    }
+
+    fn transpose(&self) -> Self {
+        let n = self.rows.len() - 1;
+        let mut rows = vec![0; n + 1];
+        self.cols.iter().for_each(|c| rows[*c + 1] += 1);
+        for idx in 1..rows.len() {
+            rows[idx] += rows[idx - 1];
+        }
+
+        let mut offsets = rows.clone();
+        let mut cols = vec![0; self.cols.len()];
+        let mut vals = vec![0f64; self.vals.len()];
+        for r in 0..n {
+            for idx in self.rows[r]..self.rows[r + 1] {
+                let c = self.cols[idx];
+                cols[offsets[c]] = r;
+                vals[offsets[c]] = self.vals[idx];
+                offsets[c] += 1;
+            }
+        }
+        SparseOp { rows, cols, vals }
+    }
+
+    fn mul(&self, x: &[f64]) -> Vec<f64> {
+        (0..x.len()).into_par_iter().map(|r| {
+            let (start, stop) = (self.rows[r], self.rows[r + 1]);
+            self.cols[start..stop].iter().zip(self.vals[start..stop].iter())
+                .map(|(c, v)| v * x[*c])
+                .sum::<f64>()
+        }).collect()
+    }
+}
+
+impl Similarity {
+    /// Multiplies the similarity matrix, as a polynomial of `a`, by x
+    fn mul(&self, a: &SparseOp, x: &[f64]) -> Vec<f64> {
+        match *self {
+            Similarity::Adjacency => a.mul(x),
+            Similarity::Katz { beta, order } => {
+                let mut acc = vec![0f64; x.len()];
+                let mut cur = x.to_vec();
+                for _ in 0..order {
+                    cur = a.mul(&cur);
+                    cur.iter_mut().zip(acc.iter_mut()).for_each(|(ci, ai)| {
+                        *ci *= beta as f64;
+                        *ai += *ci;
+                    });
+                }
+                acc
+            },
+            Similarity::PPR { restart_p, order } => {
+                let restart_p = restart_p as f64;
+                let mut acc: Vec<f64> = x.iter().map(|xi| restart_p * xi).collect();
+                let mut cur = x.to_vec();
+                for _ in 0..order {
+                    cur = a.mul(&cur);
+                    cur.iter_mut().zip(acc.iter_mut()).for_each(|(ci, ai)| {
+                        *ci *= 1. - restart_p;
+                        *ai += restart_p * *ci;
+                    });
+                }
+                acc
+            }
+        }
+    }
+}
+
+pub struct SpectralEmbedding {
+    /// Number of dimensions for the embeddings
+    pub dims: usize,
+
+    pub similarity: Similarity,
+
+    /// Extra random vectors sampled beyond dims; improves accuracy of the trailing components.
+    pub oversample: usize,
+
+    /// Number of power iterations.  Sharpens the spectrum when singular values decay slowly.
+    pub power_iterations: usize,
+
+    pub seed: u64
+}
+
+impl SpectralEmbedding {
+
+    pub fn new(dims: usize, similarity: Similarity, seed: u64) -> Self {
+        SpectralEmbedding { dims, similarity, oversample: 10, power_iterations: 2, seed }
+    }
+
+    /// Factorizes a similarity matrix built from the raw edge weights
+    pub fn learn(&self, graph: &impl Graph) -> EmbeddingStore {
+        self.factorize(SparseOp::new(graph, |ws| ws.to_vec()))
+    }
+
+    /// Factorizes a similarity matrix built from the transition probabilities
+    pub fn learn_cdf(&self, graph: &impl CDFGraph) -> EmbeddingStore {
+        self.factorize(SparseOp::new(graph, |ws| CDFtoP::new(ws).collect()))
+    }
+
+    /// Embeds each node as its row of U * sqrt(S), where U S V' approximates the similarity
+    /// matrix.  Components are ordered by singular value.
+    fn factorize(&self, a: SparseOp) -> EmbeddingStore {
+        let n = a.rows.len() - 1;
+        let at = a.transpose();
+        let l = (self.dims + self.oversample).min(n);
+
+        // Range finder: Q = orth((S S')^q S Omega)
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let omega: Vec<Vec<f64>> = (0..l)
+            .map(|_| (0..n).map(|_| rng.sample::<f64,_>(StandardNormal)).collect())
+            .collect();
+        let mut q: Vec<Vec<f64>> = omega.par_iter().map(|c| self.similarity.mul(&a, c)).collect();
+        orthonormalize(&mut q);
+        for _ in 0..self.power_iterations {
+            let mut z: Vec<Vec<f64>> = q.par_iter().map(|c| self.similarity.mul(&at, c)).collect();
+            orthonormalize(&mut z);
+            q = z.par_iter().map(|c| self.similarity.mul(&a, c)).collect();
+            orthonormalize(&mut q);
+        }
+
+        // B = Q' S is small, so its left singular vectors come from the eigenvectors of B B'
+        let bt: Vec<Vec<f64>> = q.par_iter().map(|c| self.similarity.mul(&at, c)).collect();
+        let mut gram = vec![0f64; l * l];
+        for i in 0..l {
+            for j in i..l {
+                let d = dot(&bt[i], &bt[j]);
+                gram[i * l + j] = d;
+                gram[j * l + i] = d;
+            }
+        }
+        let (values, vectors) = symmetric_eigen(gram, l);
+        let mut order: Vec<usize> = (0..l).collect();
+        order.sort_by(|x, y| values[*y].total_cmp(&values[*x]));
+
+        // U = Q W, scaled by sqrt of the singular values
+        let k = self.dims.min(l);
+        let mut embs = vec![0f32; n * self.dims];
+        for (dim, idx) in order.into_iter().take(k).enumerate() {
+            let scale = values[idx].max(0.).sqrt().sqrt();
+            for (i, qi) in q.iter().enumerate() {
+                let w = vectors[i * l + idx] * scale;
+                qi.iter().enumerate().for_each(|(node_id, qv)| {
+                    embs[node_id * self.dims + dim] += (w * qv) as f32;
+                });
+            }
+        }
+
+        EmbeddingStore::new_with_vec(n, self.dims, Distance::Cosine, embs)
+            .expect("Embedding size should always match!")
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(ai, bi)| ai * bi).sum::<f64>()
+}
+
+/// Cyclic Jacobi eigendecomposition of a flattened, symmetric [l, l] matrix.  Returns the
+/// eigenvalues and the eigenvectors as the columns of a flattened matrix.
+pub(crate) fn symmetric_eigen(mut a: Vec<f64>, l: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut v = vec![0f64; l * l];
+    (0..l).for_each(|i| v[i * l + i] = 1.);
+
+    for _sweep in 0..100 {
+        let off = (0..l).flat_map(|i| (0..l).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * l + j].powi(2))
+            .sum::<f64>();
+        let total = a.iter().map(|x| x * x).sum::<f64>();
+        if off <= 1e-24 * total.max(1e-300) { break }
+
+        for p in 0..l {
+            for q in (p + 1)..l {
+                let apq = a[p * l + q];
+                if apq == 0. { continue }
+                let theta = (a[q * l + q] - a[p * l + p]) / (2. * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+                let c = 1. / (t * t + 1.).sqrt();
+                let s = t * c;
+                for k in 0..l {
+                    let (akp, akq) = (a[k * l + p], a[k * l + q]);
+                    a[k * l + p] = c * akp - s * akq;
+                    a[k * l + q] = s * akp + c * akq;
+                }
+                for k in 0..l {
+                    let (apk, aqk) = (a[p * l + k], a[q * l + k]);
+                    a[p * l + k] = c * apk - s * aqk;
+                    a[q * l + k] = s * apk + c * aqk;
+                }
+                for k in 0..l {
+                    let (vkp, vkq) = (v[k * l + p], v[k * l + q]);
+                    v[k * l + p] = c * vkp - s * vkq;
+                    v[k * l + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    ((0..l).map(|i| a[i * l + i]).collect(), v)
+}
+
+#[cfg(test)]
+mod spectral_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::simd;
+
+    #[test]
+    fn test_eigen() {
+        let (values, vectors) = symmetric_eigen(vec![2., 1., 1., 2.], 2);
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        assert!((sorted[0] - 1.).abs() < 1e-9);
+        assert!((sorted[1] - 3.).abs() < 1e-9);
+        let idx = if values[0] > values[1] { 0 } else { 1 };
+        assert!((vectors[idx].abs() - 0.5f64.sqrt()).abs() < 1e-9);
+        assert!((vectors[2 + idx].abs() - 0.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_disconnected_cliques() {
+        let mut edges = Vec::new();
+        for offset in [0, 4] {
+            for f in 0..4 {
+                for t in 0..4 {
+                    if f != t { edges.push((f + offset, t + offset, 1.)); }
+                }
+            }
+        }
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let cos = |es: &EmbeddingStore, a: NodeID, b: NodeID| {
+            let (ea, eb) = (es.get_embedding(a), es.get_embedding(b));
+            simd::dot(ea, eb) / (simd::norm_squared(ea) * simd::norm_squared(eb)).sqrt()
+        };
+        let similarities = [
+            Similarity::Adjacency,
+            Similarity::Katz { beta: 0.5, order: 3 },
+            Similarity::PPR { restart_p: 0.15, order: 10 }
+        ];
+        for similarity in similarities {
+            let es = SpectralEmbedding::new(2, similarity, 2023).learn_cdf(&graph);
+            assert_eq!(es.dims(), 2);
+            assert!(cos(&es, 0, 3) > 0.99);
+            assert!(cos(&es, 4, 7) > 0.99);
+            assert!(cos(&es, 0, 5).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Per node triangle counts and local clustering coefficients.  Edge direction, weights, self
+//! loops, and parallel edges are ignored.  Each triangle is counted once by orienting edges from
+//! lower to higher degree nodes and intersecting the sorted forward neighborhoods.
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID};
+
+pub struct TriangleCounts {
+    /// Number of triangles each node participates in
+    pub triangles: Vec<usize>,
+
+    /// Fraction of pairs of neighbors which are themselves connected
+    pub clustering: Vec<f32>
+}
+
+impl TriangleCounts {
+    /// Total number of distinct triangles in the graph
+    pub fn total(&self) -> usize {
+        self.triangles.par_iter().sum::<usize>() / 3
+    }
+
+    /// Mean local clustering coefficient
+    pub fn average_clustering(&self) -> f32 {
+        if self.clustering.is_empty() { return 0. }
+        self.clustering.par_iter().sum::<f32>() / self.clustering.len() as f32
+    }
+}
+
+/// Sorted, deduplicated neighbors ignoring direction and self loops
+fn undirected_neighbors<G: Graph + Sync>(graph: &G) -> Vec<Vec<NodeID>> {
+    let n = graph.len();
+    let mut in_edges = vec![Vec::new(); n];
+    for node_id in 0..n {
+        for t_n in graph.get_edges(node_id).0.iter() {
+            in_edges[*t_n].push(node_id);
+        }
+    }
+
+    in_edges.into_par_iter().enumerate().map(|(node_id, mut neighbors)| {
+        neighbors.extend_from_slice(graph.get_edges(node_id).0);
+        neighbors.retain(|t_n| *t_n != node_id);
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }).collect()
+}
+
+/// Calls `found` with each element shared between two sorted slices
+fn intersect(a: &[NodeID], b: &[NodeID], mut found: impl FnMut(NodeID)) {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            i += 1;
+        } else if a[i] > b[j] {
+            j += 1;
+        } else {
+            found(a[i]);
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+pub fn count_triangles<G: Graph + Sync>(graph: &G) -> TriangleCounts {
+    let neighbors = undirected_neighbors(graph);
+
+    // Orient each edge towards the higher degree node, breaking ties by id, so every triangle
+    // is found exactly once from its lowest ranked node
+    let rank = |node_id: NodeID| (neighbors[node_id].len(), node_id);
+    let forward: Vec<Vec<NodeID>> = neighbors.par_iter().enumerate().map(|(node_id, ns)| {
+        ns.iter().cloned().filter(|t_n| rank(*t_n) > rank(node_id)).collect()
+    }).collect();
+
+    let counts: Vec<_> = (0..graph.len()).map(|_| AtomicUsize::new(0)).collect();
+    (0..graph.len()).into_par_iter().for_each(|u| {
+        forward[u].iter().for_each(|v| {
+            intersect(&forward[u], &forward[*v], |w| {
+                counts[u].fetch_add(1, Ordering::Relaxed);
+                counts[*v].fetch_add(1, Ordering::Relaxed);
+                counts[w].fetch_add(1, Ordering::Relaxed);
+            });
+        });
+    });
+
+    let triangles: Vec<usize> = counts.into_iter().map(|c| c.into_inner()).collect();
+    let clustering = triangles.par_iter().zip(neighbors.par_iter()).map(|(t, ns)| {
+        let d = ns.len() as f32;
+        if d < 2. { 0. } else { 2. * *t as f32 / (d * (d - 1.)) }
+    }).collect();
+
+    TriangleCounts { triangles, clustering }
+}
+
+#[cfg(test)]
+mod triangles_tests {
+    use super::*;
+    use crate::graph::CSR;
+
+    #[test]
+    fn test_triangles() {
+        // A square 0-1-2-3 with diagonal 0-2, a pendant 4, and a self loop and duplicate edge
+        let edges = vec![
+            (0, 1, 1.),
+            (1, 2, 1.),
+            (2, 3, 1.),
+            (3, 0, 1.),
+            (0, 2, 1.),
+            (2, 0, 1.),
+            (4, 0, 1.),
+            (1, 1, 1.),
+            (1, 2, 1.),
+        ];
+        let counts = count_triangles(&CSR::construct_from_edges(edges));
+        assert_eq!(counts.triangles, vec![2, 1, 2, 1, 0]);
+        assert_eq!(counts.total(), 2);
+        assert_eq!(counts.clustering[1], 1.);
+        assert_eq!(counts.clustering[0], 2. * 2. / (4. * 3.));
+        assert_eq!(counts.clustering[4], 0.);
+    }
+}
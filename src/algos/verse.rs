@@ -0,0 +1,194 @@
+//! VERSE embeddings (Tsitsulin et al., 2018).  Positive pairs are drawn from each node's
+//! personalized PageRank distribution by random walks with restarts, and a single embedding
+//! matrix is fit to them with noise contrastive estimation against uniform negatives.  Because
+//! PPR mass spreads over many hops, this captures more global structure than 1-hop reconstruction.
+use std::fmt::Write;
+use std::collections::{HashMap as CHashMap};
+
+use rand::prelude::*;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,NodeID};
+use crate::embeddings::{EmbeddingStore,Distance,randomize_embedding_store};
+use crate::progress::CLProgressBar;
+use crate::sampler::Sampler;
+use crate::simd;
+use crate::algos::rwr::{Steps,rollout};
+use crate::algos::skipgram::{Grads,sigmoid,add_grad};
+use crate::algos::grad_utils::scheduler::LRScheduler;
+use crate::algos::grad_utils::optimizer::{Optimizer,AdamOptimizer};
+
+pub struct Verse {
+    /// Number of dimensions for the learned embeddings
+    pub dims: usize,
+
+    /// Probability of the PPR walk terminating at each step
+    pub restart_p: f32,
+
+    /// Number of PPR samples drawn per node each pass
+    pub samples_per_node: usize,
+
+    /// Number of uniform negatives per positive sample
+    pub negatives: usize,
+
+    /// Number of passes over the nodes
+    pub passes: usize,
+
+    /// Number of nodes per gradient update
+    pub batch_size: usize,
+
+    /// Peak learning rate
+    pub alpha: f32,
+
+    pub seed: u64,
+
+    pub indicator: bool
+}
+
+impl Verse {
+
+    pub fn learn<G: Graph + Send + Sync>(
+        &self,
+        graph: &G,
+        sampler: &impl Sampler<G>
+    ) -> EmbeddingStore {
+        let n = graph.len();
+        let mut rng = XorShiftRng::seed_from_u64(self.seed);
+        let mut es = EmbeddingStore::new(n, self.dims, Distance::Cosine);
+        randomize_embedding_store(&mut es, &mut rng);
+        if n == 0 || self.samples_per_node == 0 {
+            return es
+        }
+
+        // NCE corrects the logits by the log odds of drawing the sample as a negative
+        let noise_bias = (self.negatives as f32 / n as f32).ln();
+
+        let optimizer = AdamOptimizer::new(0.9, 0.999, self.dims, n);
+        let batch_size = self.batch_size.max(1);
+        let steps_per_pass = (n as f32 / batch_size as f32).ceil() as usize;
+        let total_updates = steps_per_pass * self.passes;
+        let lr_scheduler = LRScheduler::cos_decay(
+            self.alpha / 100f32, self.alpha, total_updates / 5, total_updates);
+
+        let mut node_idxs: Vec<NodeID> = (0..n).collect();
+        let pb = CLProgressBar::new(total_updates as u64, self.indicator);
+        let mut step = 0;
+        let mut last_error = std::f32::INFINITY;
+        for pass in 1..(self.passes + 1) {
+            pb.update_message(|msg| {
+                msg.clear();
+                write!(msg, "Pass {}/{}, Loss: {:.5}, LR: {:.5}", pass, self.passes,
+                       last_error, lr_scheduler.compute(step))
+                    .expect("Error writing out indicator message!");
+            });
+
+            node_idxs.shuffle(&mut rng);
+            let mut error = 0f32;
+            for nodes in node_idxs.chunks(batch_size) {
+                let results: Vec<_> = nodes.par_iter().map(|node_id| {
+                    let mut rng = XorShiftRng::seed_from_u64(
+                        self.seed + (step * batch_size + node_id) as u64);
+                    self.node_gradients(graph, sampler, *node_id, &es, noise_bias, &mut rng)
+                }).collect();
+
+                let mut all_grads = CHashMap::new();
+                for (loss, grads) in results {
+                    for (node_id, g) in grads {
+                        add_grad(&mut all_grads, node_id, 1., &g);
+                    }
+                    error += loss;
+                }
+
+                let alpha = lr_scheduler.compute(step);
+                optimizer.update(&es, all_grads, alpha, pass as f32);
+                step += 1;
+                pb.inc(1);
+            }
+            last_error = error / (n * self.samples_per_node) as f32;
+        }
+        pb.finish();
+        es
+    }
+
+    /// Draws PPR positives and uniform negatives for a node, returning the NCE loss and gradients.
+    fn node_gradients<G: Graph + Send + Sync, R: Rng>(
+        &self,
+        graph: &G,
+        sampler: &impl Sampler<G>,
+        node_id: NodeID,
+        es: &EmbeddingStore,
+        noise_bias: f32,
+        rng: &mut R
+    ) -> (f32, Grads) {
+        let mut grads = CHashMap::new();
+        let mut walk = Vec::new();
+        let mut loss = 0f32;
+        for _ in 0..self.samples_per_node {
+            walk.clear();
+            rollout(graph, Steps::Probability(self.restart_p), sampler, node_id, rng, &mut walk);
+            let positive = walk[walk.len() - 1];
+            loss += nce_gradient(node_id, positive, true, es, noise_bias, &mut grads);
+            for _ in 0..self.negatives {
+                let negative = rng.gen_range(0, graph.len());
+                loss += nce_gradient(node_id, negative, false, es, noise_bias, &mut grads);
+            }
+        }
+        (loss, grads)
+    }
+}
+
+/// NCE gradient for a single pair, returning the loss.  Both nodes share the same embeddings.
+fn nce_gradient(
+    node_id: NodeID,
+    other: NodeID,
+    positive: bool,
+    es: &EmbeddingStore,
+    noise_bias: f32,
+    grads: &mut Grads
+) -> f32 {
+    let u = es.get_embedding(node_id);
+    let v = es.get_embedding(other);
+    let score = sigmoid(simd::dot(u, v) - noise_bias);
+    let (g, p) = if positive { (score - 1., score) } else { (score, 1. - score) };
+    add_grad(grads, node_id, g, v);
+    add_grad(grads, other, g, u);
+    -p.max(1e-7).ln()
+}
+
+#[cfg(test)]
+mod verse_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR};
+    use crate::sampler::Unweighted;
+
+    #[test]
+    fn test_two_communities() {
+        // Two 4-cliques joined by a single edge between 3 and 4
+        let mut edges = Vec::new();
+        for offset in [0, 4] {
+            for f in 0..4 {
+                for t in 0..4 {
+                    if f != t { edges.push((f + offset, t + offset, 1.)); }
+                }
+            }
+        }
+        edges.push((3, 4, 1.));
+        edges.push((4, 3, 1.));
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let verse = Verse {
+            dims: 8, restart_p: 0.15, samples_per_node: 20, negatives: 3, passes: 50,
+            batch_size: 4, alpha: 0.1, seed: 2023, indicator: false
+        };
+        let es = verse.learn(&graph, &Unweighted);
+        assert_eq!(es.num_trained(), 8);
+
+        let sim = |a: NodeID, b: NodeID| {
+            let (ea, eb) = (es.get_embedding(a), es.get_embedding(b));
+            simd::dot(ea, eb) / (simd::norm_squared(ea) * simd::norm_squared(eb)).sqrt()
+        };
+        assert!(sim(0, 1) > sim(0, 6));
+        assert!(sim(5, 6) > sim(1, 6));
+    }
+}
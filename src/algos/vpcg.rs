@@ -44,7 +44,7 @@ impl VPCG {
                 let mut fs = Vec::with_capacity(self.max_terms);
                 let feats = features.get_features(node_id);
                 let weight = 1. / (feats.len() as f32).sqrt();
-                feats.iter().for_each(|f| fs.push((*f, weight)));
+                feats.iter().for_each(|f| fs.push((*f as usize, weight)));
                 fs
             })
             .collect();
@@ -0,0 +1,230 @@
+//! Bipartite graphs, such as user-item interaction graphs.  Partitions are defined by node type
+//! and every edge is validated to cross them, so sampling and retrieval can be restricted to one
+//! side without tracking node types by hand.
+use hashbrown::HashMap;
+use rand::prelude::*;
+use rayon::prelude::*;
+
+use crate::graph::{Graph,CDFGraph,NodeID};
+use crate::algos::rwr::RWR;
+use crate::sampler::Sampler;
+use crate::vocab::Vocab;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Side {
+    Left,
+    Right
+}
+
+impl Side {
+    pub fn other(&self) -> Side {
+        match self {
+            Side::Left  => Side::Right,
+            Side::Right => Side::Left
+        }
+    }
+}
+
+pub struct BipartiteGraph<G> {
+    graph: G,
+    sides: Vec<Side>,
+    left: Vec<NodeID>,
+    right: Vec<NodeID>
+}
+
+impl <G:Graph> BipartiteGraph<G> {
+    /// Assigns every node to a side by its node type.  Errors if the types overlap, a node's type
+    /// is in neither side, or an edge connects two nodes on the same side.
+    pub fn new(
+        graph: G,
+        vocab: &Vocab,
+        left_types: &[String],
+        right_types: &[String]
+    ) -> Result<Self,String> {
+        let mut type_sides = HashMap::new();
+        for (types, side) in [(left_types, Side::Left), (right_types, Side::Right)] {
+            for node_type in types {
+                if type_sides.insert(node_type.as_str(), side).map_or(false, |s| s != side) {
+                    Err(format!("Node type '{}' is in both partitions!", node_type))?
+                }
+            }
+        }
+
+        let sides = (0..graph.len()).map(|node_id| {
+            let node_type = vocab.get_node_type(node_id)
+                .ok_or_else(|| format!("Node {} missing from vocab!", node_id))?;
+            type_sides.get(node_type.as_str()).copied()
+                .ok_or_else(|| format!("Node type '{}' is in neither partition!", node_type))
+        }).collect::<Result<Vec<_>,_>>()?;
+
+        for node_id in 0..graph.len() {
+            if let Some(t_n) = graph.get_edges(node_id).0.iter().find(|t_n| sides[**t_n] == sides[node_id]) {
+                let (f_type, f_name) = vocab.get_name(node_id).unwrap();
+                let (t_type, t_name) = vocab.get_name(*t_n).unwrap();
+                Err(format!("Edge {}:{} -> {}:{} doesn't cross partitions!", f_type, f_name, t_type, t_name))?
+            }
+        }
+
+        let (left, right) = (0..graph.len()).partition(|node_id| sides[*node_id] == Side::Left);
+        Ok(BipartiteGraph { graph, sides, left, right })
+    }
+
+    pub fn graph(&self) -> &G {
+        &self.graph
+    }
+
+    pub fn into_inner(self) -> G {
+        self.graph
+    }
+
+    pub fn side(&self, node_id: NodeID) -> Side {
+        self.sides[node_id]
+    }
+
+    /// Nodes on the given side, in node id order
+    pub fn partition(&self, side: Side) -> &[NodeID] {
+        match side {
+            Side::Left  => &self.left,
+            Side::Right => &self.right
+        }
+    }
+
+    /// Samples nodes uniformly, with replacement, from one side.
+    pub fn sample_nodes<R: Rng>(&self, side: Side, n: usize, rng: &mut R) -> Vec<NodeID> {
+        let nodes = self.partition(side);
+        if nodes.is_empty() {
+            return Vec::new()
+        }
+        (0..n).map(|_| nodes[rng.gen_range(0, nodes.len())]).collect()
+    }
+}
+
+impl <G:Graph + Send + Sync> BipartiteGraph<G> {
+    /// Samples a node on the requested side reachable from `node_id`: one hop when crossing to
+    /// the other side, two hops when returning to the same side (e.g. items co-interacted with).
+    /// Returns None when the walk dead ends.
+    pub fn sample_on_side<S: Sampler<G>, R: Rng>(
+        &self,
+        sampler: &S,
+        node_id: NodeID,
+        side: Side,
+        rng: &mut R
+    ) -> Option<NodeID> {
+        let next = sampler.sample(&self.graph, node_id, rng)?;
+        if self.side(node_id) != side {
+            Some(next)
+        } else {
+            sampler.sample(&self.graph, next, rng)
+        }
+    }
+
+    /// Random walk with restarts retrieval, keeping only the k most visited nodes on `side` for
+    /// each start node.
+    pub fn top_k<S: Sampler<G>>(
+        &self,
+        rwr: &RWR,
+        sampler: &S,
+        start_nodes: &[NodeID],
+        side: Side,
+        k: usize
+    ) -> Vec<Vec<(NodeID, f32)>> {
+        rwr.visit_counts_batch(&self.graph, sampler, start_nodes).into_par_iter().map(|counts| {
+            let mut scores: Vec<_> = counts.into_iter()
+                .filter(|(node_id, _)| self.side(*node_id) == side)
+                .collect();
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            scores.truncate(k);
+            scores
+        }).collect()
+    }
+}
+
+impl <G:Graph> Graph for BipartiteGraph<G> {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Get number of nodes in graph
+    fn edges(&self) -> usize {
+        self.graph.edges()
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        self.graph.degree(idx)
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        self.graph.get_edges(idx)
+    }
+
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        self.graph.get_edge_range(idx)
+    }
+}
+
+impl <G:CDFGraph> CDFGraph for BipartiteGraph<G> {}
+
+#[cfg(test)]
+mod bipartite_tests {
+    use super::*;
+    use rand_xorshift::XorShiftRng;
+    use crate::graph::{CSR,CumCSR};
+    use crate::algos::rwr::Steps;
+    use crate::sampler::Weighted;
+
+    fn build() -> (CumCSR, Vocab) {
+        let mut vocab = Vocab::new();
+        let names = [("user", "a"), ("user", "b"), ("item", "x"), ("item", "y"), ("item", "z")];
+        for (nt, name) in names {
+            vocab.get_or_insert(nt.to_string(), name.to_string());
+        }
+        let edges = vec![
+            (0, 2, 1.), (2, 0, 1.),
+            (0, 3, 1.), (3, 0, 1.),
+            (1, 3, 1.), (3, 1, 1.),
+            (1, 4, 1.), (4, 1, 1.),
+        ];
+        (CumCSR::convert(CSR::construct_from_edges(edges)), vocab)
+    }
+
+    #[test]
+    fn test_validation() {
+        let (graph, vocab) = build();
+        let users = vec!["user".to_string()];
+        let items = vec!["item".to_string()];
+        assert!(BipartiteGraph::new(graph.clone(), &vocab, &users, &users).is_err());
+        assert!(BipartiteGraph::new(graph.clone(), &vocab, &users, &[]).is_err());
+
+        let bg = BipartiteGraph::new(graph, &vocab, &users, &items).unwrap();
+        assert_eq!(bg.partition(Side::Left), &[0, 1]);
+        assert_eq!(bg.partition(Side::Right), &[2, 3, 4]);
+        assert_eq!(bg.side(3), Side::Right);
+
+        let mut vocab = vocab;
+        vocab.get_or_insert("item".to_string(), "w".to_string());
+        let edges = vec![(0, 2, 1.), (2, 5, 1.)];
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+        assert!(BipartiteGraph::new(graph, &vocab, &users, &items).is_err());
+    }
+
+    #[test]
+    fn test_restricted_sampling() {
+        let (graph, vocab) = build();
+        let bg = BipartiteGraph::new(graph, &vocab, &["user".to_string()], &["item".to_string()]).unwrap();
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        for _ in 0..100 {
+            assert_eq!(bg.side(bg.sample_on_side(&Weighted, 0, Side::Right, &mut rng).unwrap()), Side::Right);
+            assert_eq!(bg.side(bg.sample_on_side(&Weighted, 2, Side::Right, &mut rng).unwrap()), Side::Right);
+        }
+        assert!(bg.sample_nodes(Side::Left, 50, &mut rng).iter().all(|n| *n < 2));
+
+        let rwr = RWR { steps: Steps::Fixed(3), walks: 1000, beta: 0., single_threaded: true, seed: 0 };
+        let results = bg.top_k(&rwr, &Weighted, &[0], Side::Right, 2);
+        assert_eq!(results[0].len(), 2);
+        assert!(results[0].iter().all(|(n, _)| bg.side(*n) == Side::Right));
+    }
+}
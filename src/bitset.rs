@@ -1,4 +1,5 @@
 //! BitSet class; we use it in a few places to know when things have been set, such as embeddings.
+use std::sync::atomic::{AtomicU32,Ordering};
 
 /// Simple BitSet class, using 32-bit unsized ints for track flags
 #[derive(Clone)]
@@ -27,6 +28,63 @@ impl BitSet {
         self.bitfield[fo] |= bm;
     }
 
+    /// Grows the bitset so it can hold at least `size` flags.  New flags are unset.
+    pub fn grow(&mut self, size: usize) {
+        let fields = (size / 32) + 1;
+        if fields > self.bitfield.len() {
+            self.bitfield.resize(fields, 0);
+        }
+    }
+
+}
+
+/// BitSet which can be set from multiple threads at once, such as from within the hogwild
+/// optimizers.
+pub struct AtomicBitSet {
+    bitfield: Vec<AtomicU32>
+}
+
+impl AtomicBitSet {
+    pub fn new(size: usize) -> Self {
+        Self { bitfield: (0..(size / 32) + 1).map(|_| AtomicU32::new(0)).collect() }
+    }
+
+    pub fn is_set(&self, idx: usize) -> bool {
+        (self.bitfield[idx / 32].load(Ordering::Relaxed) & (1u32 << (idx % 32))) > 0
+    }
+
+    pub fn set_bit(&self, idx: usize) {
+        self.bitfield[idx / 32].fetch_or(1u32 << (idx % 32), Ordering::Relaxed);
+    }
+
+    /// Unsets all flags
+    pub fn clear(&mut self) {
+        self.bitfield.iter_mut().for_each(|f| *f.get_mut() = 0);
+    }
+
+    /// Grows the bitset so it can hold at least `size` flags.  New flags are unset.
+    pub fn grow(&mut self, size: usize) {
+        let fields = (size / 32) + 1;
+        while self.bitfield.len() < fields {
+            self.bitfield.push(AtomicU32::new(0));
+        }
+    }
+
+    /// Number of flags set
+    pub fn count(&self) -> usize {
+        self.bitfield.iter()
+            .map(|f| f.load(Ordering::Relaxed).count_ones() as usize)
+            .sum()
+    }
+}
+
+impl Clone for AtomicBitSet {
+    fn clone(&self) -> Self {
+        let bitfield = self.bitfield.iter()
+            .map(|f| AtomicU32::new(f.load(Ordering::Relaxed)))
+            .collect();
+        AtomicBitSet { bitfield }
+    }
 }
 
 #[cfg(test)]
@@ -59,6 +117,24 @@ mod bitset_tests {
 
     }
 
+    #[test]
+    fn test_atomic_setting() {
+        let mut bitset = AtomicBitSet::new(40);
+        bitset.set_bit(3);
+        bitset.set_bit(35);
+        assert!(bitset.is_set(3));
+        assert!(bitset.is_set(35));
+        assert!(!bitset.is_set(4));
+        assert_eq!(bitset.count(), 2);
+
+        bitset.grow(100);
+        bitset.set_bit(99);
+        assert_eq!(bitset.count(), 3);
+
+        bitset.clear();
+        assert_eq!(bitset.count(), 0);
+    }
+
     #[test]
     fn test_size_of() {
         let mut bitset = BitSet::new(1201);
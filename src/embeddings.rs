@@ -1,12 +1,19 @@
 //! The main Embedding class.  This defines both distance metrics as well as access to the
 //! embeddings.
+use std::ops::Range;
+use std::sync::{Arc,RwLock};
+use std::sync::atomic::{AtomicBool,AtomicUsize,Ordering};
+
 use float_ord::FloatOrd;
+use ndarray::{ArrayView2,ArrayViewMut2};
 use rayon::prelude::*;
 use rand::prelude::*;
 
 use crate::graph::NodeID;
-use crate::bitset::BitSet;
+use crate::vocab::{Vocab,TranslationTable};
+use crate::bitset::{BitSet,AtomicBitSet};
 use crate::hogwild::Hogwild;
+use crate::simd;
 use crate::algos::graph_ann::{TopK,NodeDistance};
 
 /// Entity allows for adhoc embeddings versus looking up by NodeID within the embedding set
@@ -43,6 +50,20 @@ pub enum Distance {
 }
 
 impl Distance {
+    /// Parses the name of a distance, as written by its Debug impl, back into the metric.  Used by
+    /// the on-disk formats which persist the distance alongside the embeddings.
+    pub fn from_name(name: &str) -> Option<Distance> {
+        match name {
+            "ALT" => Some(Distance::ALT),
+            "Cosine" => Some(Distance::Cosine),
+            "Dot" => Some(Distance::Dot),
+            "Euclidean" => Some(Distance::Euclidean),
+            "Hamming" => Some(Distance::Hamming),
+            "Jaccard" => Some(Distance::Jaccard),
+            _ => None
+        }
+    }
+
     pub fn compute(&self, e1: &[f32], e2: &[f32]) -> f32 {
         match &self {
             Distance::ALT => e1.iter().zip(e2.iter())
@@ -50,32 +71,13 @@ impl Distance {
                 .max_by_key(|v| FloatOrd(*v)).unwrap_or(0.),
 
             Distance::Cosine => {
-                let mut d1 = 0.;
-                let mut d2 = 0.;
-                let dot = e1.iter().zip(e2.iter()).map(|(ei, ej)| {
-                    d1 += ei.powf(2.);
-                    d2 += ej.powf(2.);
-                    ei * ej
-                }).sum::<f32>();
-                let cosine_score = dot / (d1.sqrt() * d2.sqrt());
-                if cosine_score.is_nan() {
-                    std::f32::INFINITY
-                } else {
-                    -cosine_score + 1.
-                }
+                let (dot, d1, d2) = simd::dot_and_norms(e1, e2);
+                cosine_from_parts(dot, d1.sqrt(), d2.sqrt())
             },
 
-            Distance::Euclidean => {
-                e1.iter().zip(e2.iter()).map(|(ei, ej)| {
-                    (*ei - *ej).powf(2.)
-                }).sum::<f32>().sqrt()
-            },
+            Distance::Euclidean => simd::l2_squared(e1, e2).sqrt(),
 
-            Distance::Dot => {
-                -e1.iter().zip(e2.iter()).map(|(ei, ej)| {
-                    *ei * *ej
-                }).sum::<f32>()
-            },
+            Distance::Dot => -simd::dot(e1, e2),
 
             Distance::Hamming => {
                 let not_matches = e1.iter().zip(e2.iter()).map(|(ei, ej)| {
@@ -116,6 +118,30 @@ impl Distance {
     }
 }
 
+/// Converts a dot product and the two norms into a cosine distance.
+#[inline(always)]
+fn cosine_from_parts(dot: f32, n1: f32, n2: f32) -> f32 {
+    let cosine_score = dot / (n1 * n2);
+    if cosine_score.is_nan() {
+        std::f32::INFINITY
+    } else {
+        -cosine_score + 1.
+    }
+}
+
+/// How to resolve embeddings which exist in both stores when merging.
+#[derive(Copy,Clone,Debug)]
+pub enum MergeStrategy {
+    /// Replace the existing embedding with the incoming one
+    Overwrite,
+
+    /// Average the existing and incoming embeddings
+    Average,
+
+    /// Keep the existing embedding, only filling in those which haven't been set
+    Keep
+}
+
 /// The core Embedding Store used everywhere.
 #[derive(Clone)]
 pub struct EmbeddingStore {
@@ -129,11 +155,79 @@ pub struct EmbeddingStore {
     /// Bitfield measuring if an embedding has been set
     bitfield: BitSet,
 
+    /// Bitfield measuring if an embedding holds learned values rather than its initialization.
+    /// Atomic so the optimizers can flag rows as they update them.
+    trained: AtomicBitSet,
+
     /// distance metric to use
     distance: Distance,
 
     /// Number of nodes in the Embedding Store
-    nodes: usize
+    nodes: usize,
+
+    /// Cached L2 norms for Cosine distance, computed lazily on the first search.  Kept up to
+    /// date by `set_embedding` and `push_embedding`; every other write drops the cache.
+    norms: Arc<NormCache>
+}
+
+/// L2 norms for Cosine distance.  Shared between clones along with the embedding buffer, so a
+/// write through any clone drops the cache for all of them.
+#[derive(Default)]
+struct NormCache {
+    norms: RwLock<Option<Arc<Vec<f32>>>>,
+
+    /// Set while norms are cached or being computed, letting hogwild writers skip the lock when
+    /// there is nothing to drop.
+    active: AtomicBool,
+
+    /// Bumped on every invalidation so norms computed concurrently with a write are discarded
+    generation: AtomicUsize
+}
+
+impl NormCache {
+    fn get(&self) -> Option<Arc<Vec<f32>>> {
+        self.norms.read().expect("Norm cache lock poisoned!").clone()
+    }
+
+    /// Computes and caches the norms.  If a write lands while computing, the norms are still
+    /// returned but not cached.
+    fn get_or_compute(&self, compute: impl FnOnce() -> Vec<f32>) -> Arc<Vec<f32>> {
+        if let Some(norms) = self.get() { return norms }
+
+        // We compute outside of the lock: the computation uses rayon and a stolen task
+        // re-entering the lock would deadlock.  Losing a race only wastes the duplicate work.
+        self.active.store(true, Ordering::SeqCst);
+        let generation = self.generation.load(Ordering::SeqCst);
+        let norms = Arc::new(compute());
+        let mut cached = self.norms.write().expect("Norm cache lock poisoned!");
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *cached = Some(norms.clone());
+            self.active.store(true, Ordering::SeqCst);
+        }
+        norms
+    }
+
+    /// Updates the cached norms in place when no reader holds them, rather than copying them;
+    /// otherwise, or while norms are being computed, they're invalidated instead.
+    fn update(&self, f: impl FnOnce(&mut Vec<f32>)) {
+        if !self.active.load(Ordering::SeqCst) { return }
+        let mut cached = self.norms.write().expect("Norm cache lock poisoned!");
+        if let Some(norms) = cached.as_mut().and_then(Arc::get_mut) {
+            f(norms);
+        } else {
+            drop(cached);
+            self.invalidate();
+        }
+    }
+
+    fn invalidate(&self) {
+        if self.active.load(Ordering::SeqCst) {
+            let mut cached = self.norms.write().expect("Norm cache lock poisoned!");
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            self.active.store(false, Ordering::SeqCst);
+            cached.take();
+        }
+    }
 }
 
 impl EmbeddingStore {
@@ -142,8 +236,10 @@ impl EmbeddingStore {
             dims,
             distance,
             bitfield: BitSet::new(nodes),
+            trained: AtomicBitSet::new(nodes),
             embeddings: Hogwild::new(vec![0.; nodes * dims]),
-            nodes
+            nodes,
+            norms: Arc::default()
         }
     }
 
@@ -157,21 +253,90 @@ impl EmbeddingStore {
             None
         } else {
             //
+            // Embeddings provided up front are assumed to be trained
             let mut bitfield = BitSet::new(nodes);
-            (0..nodes).for_each(|node_id| bitfield.set_bit(node_id));
+            let trained = AtomicBitSet::new(nodes);
+            (0..nodes).for_each(|node_id| {
+                bitfield.set_bit(node_id);
+                trained.set_bit(node_id);
+            });
 
             let es = EmbeddingStore {
                 dims,
                 distance,
                 bitfield: bitfield,
+                trained: trained,
                 embeddings: Hogwild::new(vec),
-                nodes
+                nodes,
+                norms: Arc::default()
             };
             Some(es)
         }
     }
 
 
+    /// Creates an empty store with room for `capacity` embeddings before reallocating.  Used
+    /// when nodes are discovered incrementally, such as streaming graph updates.
+    pub fn with_capacity(capacity: usize, dims: usize, distance: Distance) -> Self {
+        EmbeddingStore {
+            dims,
+            distance,
+            bitfield: BitSet::new(capacity),
+            trained: AtomicBitSet::new(capacity),
+            embeddings: Hogwild::new(Vec::with_capacity(capacity * dims)),
+            nodes: 0,
+            norms: Arc::default()
+        }
+    }
+
+    /// Appends an embedding to the end of the store, returning the NodeID it was assigned.  
+    /// If the buffer is shared with clones of the store, this store gets its own copy first.
+    pub fn push_embedding(&mut self, embedding: &[f32]) -> NodeID {
+        assert_eq!(embedding.len(), self.dims, "Embedding dimensions mismatch!");
+        let node_id = self.nodes;
+        self.unshared_embeddings().extend_from_slice(embedding);
+        self.nodes += 1;
+        self.bitfield.grow(self.nodes);
+        self.bitfield.set_bit(node_id);
+        self.trained.grow(self.nodes);
+        self.trained.set_bit(node_id);
+        self.norms.update(|norms| norms.push(simd::norm_squared(embedding).sqrt()));
+        node_id
+    }
+
+    /// Reserves space for at least `additional` more embeddings.
+    pub fn reserve(&mut self, additional: usize) {
+        let dims = self.dims;
+        self.unshared_embeddings().reserve(additional * dims);
+        self.bitfield.grow(self.nodes + additional);
+        self.trained.grow(self.nodes + additional);
+    }
+
+    /// Number of embeddings the store can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        if self.dims == 0 {
+            self.nodes
+        } else {
+            self.embeddings.capacity() / self.dims
+        }
+    }
+
+    /// Releases any excess capacity held by the store.
+    pub fn shrink_to_fit(&mut self) {
+        self.unshared_embeddings().shrink_to_fit();
+    }
+
+    /// Buffer for operations which can reallocate it.  Clones share the buffer, so reallocating
+    /// in place would leave them reading freed memory; instead a shared buffer is copied first,
+    /// detaching the norm cache along with it.
+    fn unshared_embeddings(&mut self) -> &mut Vec<f32> {
+        if self.embeddings.is_shared() {
+            self.embeddings = Hogwild::new(self.embeddings.to_vec());
+            self.norms = Arc::new(NormCache::default());
+        }
+        &mut self.embeddings
+    }
+
     pub fn dims(&self) -> usize {
         self.dims
     }
@@ -180,6 +345,38 @@ impl EmbeddingStore {
         self.bitfield.is_set(node_id)
     }
 
+    /// Returns true if the embedding has been updated with learned values, either by an
+    /// optimizer or explicitly through `set_embedding`.  Untrained embeddings are still at their
+    /// (usually random) initialization and shouldn't be served.
+    pub fn is_trained(&self, node_id: NodeID) -> bool {
+        self.trained.is_set(node_id)
+    }
+
+    /// Flags an embedding as trained.  Safe to call concurrently with hogwild updates.
+    pub fn mark_trained(&self, node_id: NodeID) {
+        self.trained.set_bit(node_id);
+    }
+
+    /// Resets all embeddings to untrained, such as prior to fine tuning.
+    pub fn clear_trained(&mut self) {
+        self.trained.clear();
+    }
+
+    /// Number of trained embeddings
+    pub fn num_trained(&self) -> usize {
+        self.trained.count()
+    }
+
+    /// Iterates over the NodeIDs of trained embeddings
+    pub fn trained_nodes(&self) -> impl Iterator<Item=NodeID> + '_ {
+        (0..self.nodes).filter(move |node_id| self.is_trained(*node_id))
+    }
+
+    /// Iterates over the NodeIDs of embeddings which were never trained
+    pub fn untrained_nodes(&self) -> impl Iterator<Item=NodeID> + '_ {
+        (0..self.nodes).filter(move |node_id| !self.is_trained(*node_id))
+    }
+
     pub fn len(&self) -> usize {
         self.nodes
     }
@@ -189,10 +386,15 @@ impl EmbeddingStore {
     }
 
     pub fn set_embedding(&mut self, node_id: NodeID, embedding: &[f32]) {
-        self.get_embedding_mut(node_id).iter_mut().zip(embedding.iter()).for_each(|(ei, wi)| {
+        let start = node_id * self.dims;
+        let emb = &mut self.embeddings[start..start+self.dims];
+        emb.iter_mut().zip(embedding.iter()).for_each(|(ei, wi)| {
             *ei = *wi;
         });
+        let norm = simd::norm_squared(emb).sqrt();
+        self.norms.update(|norms| norms[node_id] = norm);
         self.bitfield.set_bit(node_id);
+        self.trained.set_bit(node_id);
     }
 
     pub fn get_embedding(&self, node_id: NodeID) -> &[f32] {
@@ -203,18 +405,188 @@ impl EmbeddingStore {
     pub fn get_embedding_mut(&mut self, node_id: NodeID) -> &mut [f32] {
         let start = node_id * self.dims;
         self.bitfield.set_bit(node_id);
+        self.norms.invalidate();
         &mut self.embeddings[start..start+self.dims]
     }
 
     pub fn get_embedding_mut_hogwild(&self, node_id: NodeID) -> &mut [f32] {
         let start = node_id * self.dims;
+        self.norms.invalidate();
         &mut self.embeddings.get()[start..start+self.dims]
     }
 
+    /// Exposes the store as a [nodes, dims] matrix without copying.
+    pub fn as_array_view(&self) -> ArrayView2<'_, f32> {
+        ArrayView2::from_shape((self.nodes, self.dims), self.as_slice())
+            .expect("Embedding buffer should always be nodes * dims!")
+    }
+
+    /// Mutable variant of `as_array_view`, for transforming the embeddings in place.
+    pub fn as_array_view_mut(&mut self) -> ArrayViewMut2<'_, f32> {
+        self.norms.invalidate();
+        let shape = (self.nodes, self.dims);
+        ArrayViewMut2::from_shape(shape, self.embeddings.as_mut_slice())
+            .expect("Embedding buffer should always be nodes * dims!")
+    }
+
+    /// Creates a store from a [nodes, dims] matrix.  All embeddings are considered set.
+    pub fn from_array(array: ArrayView2<f32>, distance: Distance) -> Self {
+        let (nodes, dims) = array.dim();
+        let vec = array.iter().copied().collect();
+        EmbeddingStore::new_with_vec(nodes, dims, distance, vec)
+            .expect("Array shape should always match!")
+    }
+
+    /// Returns the cached norms, computing them if needed.  Only used for Cosine distance.
+    fn norms(&self) -> Option<Arc<Vec<f32>>> {
+        if !matches!(self.distance, Distance::Cosine) { return None }
+        Some(self.norms.get_or_compute(|| {
+            self.par_iter().map(|(_, emb)| simd::norm_squared(emb).sqrt()).collect()
+        }))
+    }
+
+    /// Returns a function scoring embeddings in the store against the query, using the cached
+    /// norms when available.  Prefer this over `compute_distance` when scoring one query against
+    /// many nodes.
+    pub fn scorer<'b>(&'b self, query_emb: &'b [f32]) -> impl Fn(NodeID, &[f32]) -> f32 + Sync + 'b {
+        let cached = self.norms().map(|norms| (norms, simd::norm_squared(query_emb).sqrt()));
+        move |node_id, node_emb| match &cached {
+            Some((norms, q_norm)) => {
+                cosine_from_parts(simd::dot(query_emb, node_emb), *q_norm, norms[node_id])
+            },
+            None => self.distance.compute(query_emb, node_emb)
+        }
+    }
+
+    /// Returns the contiguous backing buffer of all embeddings, laid out row-major.
+    pub fn as_slice(&self) -> &[f32] {
+        self.embeddings.as_slice()
+    }
+
+    /// Iterates over every (NodeID, embedding) pair without copying.
+    pub fn iter(&self) -> impl Iterator<Item=(NodeID, &[f32])> + '_ {
+        self.as_slice().chunks_exact(self.dims.max(1)).enumerate()
+    }
+
+    /// Parallel variant of `iter`.
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item=(NodeID, &[f32])> + '_ {
+        self.as_slice().par_chunks_exact(self.dims.max(1)).enumerate()
+    }
+
     pub fn set_bit(&mut self, node_id: NodeID) {
         self.bitfield.set_bit(node_id);
     }
 
+    /// Computes the full distance matrix between this store and another with matching dims,
+    /// using this store's distance metric.  Returned row-major as [self.len(), other.len()].
+    pub fn distance_matrix(&self, other: &EmbeddingStore) -> Vec<f32> {
+        self.distance_matrix_block(other, 0..self.len(), 0..other.len())
+    }
+
+    /// Computes a block of the distance matrix between this store and another: rows are nodes in
+    /// this store and columns are nodes in `other`.  Useful when the full matrix won't fit in
+    /// memory.  Returned row-major as [rows.len(), cols.len()].
+    pub fn distance_matrix_block(
+        &self, 
+        other: &EmbeddingStore, 
+        rows: Range<NodeID>, 
+        cols: Range<NodeID>
+    ) -> Vec<f32> {
+        assert_eq!(self.dims, other.dims, "Embedding dimensions mismatch!");
+        const TILE: usize = 64;
+
+        let n_cols = cols.len();
+        let mut out = vec![0f32; rows.len() * n_cols];
+        if n_cols == 0 { return out }
+
+        // Each task gets a tile of rows and walks the columns a tile at a time so the column
+        // embeddings stay in cache across the rows.
+        out.par_chunks_mut(TILE * n_cols).enumerate().for_each(|(tile_idx, out_tile)| {
+            let row_start = rows.start + tile_idx * TILE;
+            for col_start in cols.clone().step_by(TILE) {
+                let col_end = (col_start + TILE).min(cols.end);
+                for (r, out_row) in out_tile.chunks_mut(n_cols).enumerate() {
+                    let e1 = self.get_embedding(row_start + r);
+                    for c in col_start..col_end {
+                        out_row[c - cols.start] = self.distance.compute(e1, other.get_embedding(c));
+                    }
+                }
+            }
+        });
+        out
+    }
+
+    /// Computes the centroid of a set of nodes, such as for building user profiles or cluster
+    /// centers.
+    pub fn mean_of(&self, nodes: &[NodeID]) -> Vec<f32> {
+        let mut mean = nodes.par_iter()
+            .fold(|| vec![0f32; self.dims], |mut acc, node_id| {
+                acc.iter_mut().zip(self.get_embedding(*node_id).iter())
+                    .for_each(|(ai, ei)| *ai += *ei);
+                acc
+            }).reduce(|| vec![0f32; self.dims], add_vecs);
+
+        if !nodes.is_empty() {
+            let n = nodes.len() as f32;
+            mean.iter_mut().for_each(|mi| *mi /= n);
+        }
+        mean
+    }
+
+    /// Computes the weighted centroid of a set of nodes.  Weights are normalized by their sum.
+    pub fn weighted_mean_of(&self, nodes: &[(NodeID, f32)]) -> Vec<f32> {
+        let mut mean = nodes.par_iter()
+            .fold(|| vec![0f32; self.dims], |mut acc, (node_id, w)| {
+                acc.iter_mut().zip(self.get_embedding(*node_id).iter())
+                    .for_each(|(ai, ei)| *ai += *w * *ei);
+                acc
+            }).reduce(|| vec![0f32; self.dims], add_vecs);
+
+        let total = nodes.iter().map(|(_, w)| *w).sum::<f32>();
+        if total != 0. {
+            mean.iter_mut().for_each(|mi| *mi /= total);
+        }
+        mean
+    }
+
+    /// Merges embeddings from another store into this one.  The translation table maps NodeIDs
+    /// in `other` to NodeIDs in this store and is typically created with
+    /// `other_vocab.create_translation_table(&vocab)`; nodes which don't translate are skipped.
+    /// Only trained embeddings are merged, and only trained embeddings in this store count as
+    /// existing, so random initializations never win.  Returns the number of embeddings merged.
+    pub fn merge(
+        &mut self, 
+        other: &EmbeddingStore, 
+        translation_table: &TranslationTable,
+        strategy: MergeStrategy
+    ) -> usize {
+        assert_eq!(self.dims, other.dims, "Embedding dimensions mismatch!");
+        let mut merged = 0;
+        for (other_id, node_id) in translation_table.iter().enumerate() {
+            let node_id = match node_id {
+                Some(node_id) if other.is_trained(other_id) => *node_id,
+                _ => continue
+            };
+
+            let existing = self.is_trained(node_id);
+            let incoming = other.get_embedding(other_id);
+            match strategy {
+                MergeStrategy::Keep if existing => continue,
+                MergeStrategy::Average if existing => {
+                    self.get_embedding_mut(node_id).iter_mut().zip(incoming.iter())
+                        .for_each(|(ei, oi)| *ei = (*ei + *oi) / 2.);
+                },
+                _ => {
+                    self.get_embedding_mut(node_id).copy_from_slice(incoming);
+                }
+            }
+
+            self.mark_trained(node_id);
+            merged += 1;
+        }
+        merged
+    }
+
     fn extract_vec<'a>(&'a self, n: &Entity<'a>) -> &'a [f32] {
         match n {
             Entity::Node(node_id) => self.get_embedding(*node_id),
@@ -226,7 +598,14 @@ impl EmbeddingStore {
         let e1 = self.extract_vec(n1);
         let e2 = self.extract_vec(n2);
 
-        self.distance.compute(e1, e2)
+        match (self.norms(), n1, n2) {
+            (Some(norms), Entity::Node(id1), Entity::Node(id2)) => {
+                cosine_from_parts(simd::dot(e1, e2), norms[*id1], norms[*id2])
+            },
+            (Some(_), Entity::Embedding(_), Entity::Node(id2)) => self.scorer(e1)(*id2, e2),
+            (Some(_), Entity::Node(id1), Entity::Embedding(_)) => self.scorer(e2)(*id1, e1),
+            _ => self.distance.compute(e1, e2)
+        }
     }
 
     pub fn score_all<'a>(
@@ -235,10 +614,9 @@ impl EmbeddingStore {
     ) -> EmbeddingStore {
         let es = EmbeddingStore::new(self.len(), 1, self.distance.clone());
 
-        let query_emb = self.extract_vec(q);
-        (0..self.len()).into_par_iter().for_each(|node_id| {
-            let e2 = self.get_embedding(node_id);
-            es.get_embedding_mut_hogwild(node_id)[0] = self.distance.compute(query_emb, e2);
+        let scorer = self.scorer(self.extract_vec(q));
+        self.par_iter().for_each(|(node_id, e2)| {
+            es.get_embedding_mut_hogwild(node_id)[0] = scorer(node_id, e2);
         });
         es
     }
@@ -251,15 +629,10 @@ impl EmbeddingStore {
     ) -> Vec<NodeDistance>  
         where F: Sync + Fn(NodeID) -> bool 
     {
-        let query_emb = self.extract_vec(q);
-        (0..self.len()).into_par_iter().map(|node_id| {
-            let dist = if filter(node_id) {
-                let node_emb = self.get_embedding(node_id);
-                self.distance.compute(query_emb, node_emb)
-            } else {
-                std::f32::MAX
-            };
-            (node_id, dist)
+        let scorer = self.scorer(self.extract_vec(q));
+        // Filtered nodes never enter the heap so they can't take up slots in the top k
+        self.par_iter().filter(|(node_id, _)| filter(*node_id)).map(|(node_id, node_emb)| {
+            (node_id, scorer(node_id, node_emb))
         }).fold(|| TopK::new(k), |mut acc, (node_id, dist)| {
             acc.push(node_id, dist);
             acc
@@ -269,25 +642,102 @@ impl EmbeddingStore {
         }).into_sorted()
     }
 
+    /// Brute force search for the `k` closest embeddings to the query across the entire store.
+    pub fn nearest_neighbors<'a>(
+        &self, 
+        q: &Entity<'a>, 
+        k: usize
+    ) -> Vec<NodeDistance> {
+        self.nearest_neighbor(q, k, |_node_id| true)
+    }
+
+    /// Batched variant of `nearest_neighbors`.  Rather than fanning out once per query, the store
+    /// is chunked across threads and each chunk scores every query, keeping one heap per query
+    /// which are merged at the end.  This keeps a single pass over the embeddings regardless of
+    /// the number of queries.
+    pub fn nearest_neighbors_batch(
+        &self,
+        queries: &[&[f32]],
+        k: usize
+    ) -> Vec<Vec<NodeDistance>> {
+        self.nearest_neighbors_batch_filtered(queries, k, |_node_id| true)
+    }
+
+    /// Top-k search restricted to nodes of a given type, such as only returning items in a
+    /// user/item graph.  Returns nothing if the node type doesn't exist in the vocab.
+    pub fn nearest_neighbors_of_type<'a>(
+        &self, 
+        q: &Entity<'a>, 
+        k: usize,
+        vocab: &Vocab,
+        node_type: &str
+    ) -> Vec<NodeDistance> {
+        match vocab.get_node_type_id(node_type) {
+            Some(nt_id) => self.nearest_neighbor(q, k, |node_id| {
+                vocab.get_node_type_id_of(node_id) == Some(nt_id)
+            }),
+            None => Vec::new()
+        }
+    }
+
+    /// Batched top-k search which only considers nodes passing the filter.
+    pub fn nearest_neighbors_batch_filtered<F>(
+        &self,
+        queries: &[&[f32]],
+        k: usize,
+        filter: F
+    ) -> Vec<Vec<NodeDistance>> 
+        where F: Sync + Fn(NodeID) -> bool 
+    {
+        let new_heaps = || queries.iter().map(|_| TopK::new(k)).collect::<Vec<_>>();
+        let scorers: Vec<_> = queries.iter().map(|q| self.scorer(*q)).collect();
+        self.par_iter()
+            .with_min_len(1024)
+            .filter(|(node_id, _)| filter(*node_id))
+            .fold(new_heaps, |mut heaps, (node_id, node_emb)| {
+                heaps.iter_mut().zip(scorers.iter()).for_each(|(heap, scorer)| {
+                    heap.push(node_id, scorer(node_id, node_emb));
+                });
+                heaps
+            }).reduce(new_heaps, |mut heaps_1, heaps_2| {
+                heaps_1.iter_mut().zip(heaps_2.into_iter()).for_each(|(tk1, tk2)| {
+                    tk1.extend(tk2);
+                });
+                heaps_1
+            }).into_iter()
+            .map(|heap| heap.into_sorted())
+            .collect()
+    }
+
+}
+
+fn add_vecs(mut v1: Vec<f32>, v2: Vec<f32>) -> Vec<f32> {
+    v1.iter_mut().zip(v2.into_iter()).for_each(|(a, b)| *a += b);
+    v1
 }
 
 /// Randomize embeddings.  
 pub fn randomize_embedding_store(es: &mut EmbeddingStore, rng: &mut impl Rng) {
     for idx in 0..es.len() {
-        let e = es.get_embedding_mut(idx);
-        let mut norm = 0f32;
-        e.iter_mut().for_each(|ei| {
-            *ei = 2f32 * rng.gen::<f32>() - 1f32;
-            norm += ei.powf(2f32);
-        });
-        norm = norm.sqrt();
-        e.iter_mut().for_each(|ei| *ei /= norm);
+        randomize_embedding(es.get_embedding_mut(idx), rng);
     }
 }
 
+/// Randomizes a single embedding to a uniformly sampled unit vector.
+pub fn randomize_embedding(e: &mut [f32], rng: &mut impl Rng) {
+    let mut norm = 0f32;
+    e.iter_mut().for_each(|ei| {
+        *ei = 2f32 * rng.gen::<f32>() - 1f32;
+        norm += ei.powf(2f32);
+    });
+    norm = norm.sqrt();
+    e.iter_mut().for_each(|ei| *ei /= norm);
+}
+
 #[cfg(test)]
 mod embedding_tests {
     use super::*;
+    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn test_embeddings() {
@@ -311,6 +761,289 @@ mod embedding_tests {
         assert_eq!(es.compute_distance(&Entity::Node(0), &Entity::Node(35)), 8f32.sqrt());
     }
 
+    #[test]
+    fn test_nearest_neighbors_batch() {
+        let mut es = EmbeddingStore::new(5000, 2, Distance::Euclidean);
+        for node_id in 0..es.len() {
+            es.set_embedding(node_id, &[node_id as f32, 0.]);
+        }
+
+        let q1 = [10.2, 0.];
+        let q2 = [3999.7, 0.];
+        let results = es.nearest_neighbors_batch(&[&q1, &q2], 2);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![10, 11]);
+        assert_eq!(results[1].iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![4000, 3999]);
+
+        let single = es.nearest_neighbors(&Entity::Embedding(&q1), 2);
+        assert_eq!(single, results[0]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut es = EmbeddingStore::new(3, 2, Distance::Euclidean);
+        es.set_embedding(1, &[1., 2.]);
+        es.set_embedding(2, &[3., 4.]);
+
+        let embs: Vec<_> = es.iter().collect();
+        assert_eq!(embs, vec![(0, &[0f32, 0.][..]), (1, &[1., 2.][..]), (2, &[3., 4.][..])]);
+
+        let total = es.par_iter().map(|(_, e)| e.iter().sum::<f32>()).sum::<f32>();
+        assert_eq!(total, 10.);
+    }
+
+    #[test]
+    fn test_trained_flags() {
+        let mut es = EmbeddingStore::new(4, 2, Distance::Cosine);
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        randomize_embedding_store(&mut es, &mut rng);
+        assert_eq!(es.num_trained(), 0);
+
+        es.set_embedding(1, &[1., 0.]);
+        es.mark_trained(3);
+        assert!(es.is_trained(1));
+        assert!(!es.is_trained(2));
+        assert_eq!(es.trained_nodes().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(es.untrained_nodes().collect::<Vec<_>>(), vec![0, 2]);
+
+        es.clear_trained();
+        assert_eq!(es.num_trained(), 0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let build = || {
+            let mut es = EmbeddingStore::new(3, 1, Distance::Euclidean);
+            es.set_embedding(0, &[2.]);
+            es.set_embedding(1, &[4.]);
+            es
+        };
+
+        let mut other = EmbeddingStore::new(3, 1, Distance::Euclidean);
+        other.set_embedding(0, &[10.]);
+        other.set_embedding(1, &[6.]);
+        other.set_embedding(2, &[8.]);
+
+        // Reverse the ids and drop the last one
+        let tt = vec![Some(1), Some(2), None];
+
+        let mut es = build();
+        assert_eq!(es.merge(&other, &tt, MergeStrategy::Overwrite), 2);
+        assert_eq!(es.as_slice(), &[2., 10., 6.]);
+
+        let mut es = build();
+        es.merge(&other, &tt, MergeStrategy::Average);
+        assert_eq!(es.as_slice(), &[2., 7., 6.]);
+
+        let mut es = build();
+        assert_eq!(es.merge(&other, &tt, MergeStrategy::Keep), 1);
+        assert_eq!(es.as_slice(), &[2., 4., 6.]);
+        assert!(es.is_trained(2));
+    }
+
+    #[test]
+    fn test_merge_randomized() {
+        let mut rng = XorShiftRng::seed_from_u64(2023);
+        let build = |rng: &mut XorShiftRng| {
+            let mut es = EmbeddingStore::new(2, 1, Distance::Euclidean);
+            randomize_embedding_store(&mut es, rng);
+            es.set_embedding(1, &[4.]);
+            es
+        };
+
+        let mut other = EmbeddingStore::new(2, 1, Distance::Euclidean);
+        randomize_embedding_store(&mut other, &mut rng);
+        other.set_embedding(0, &[10.]);
+        let tt = vec![Some(0), Some(1)];
+
+        // Random initializations neither count as existing nor get merged
+        let mut es = build(&mut rng);
+        assert_eq!(es.merge(&other, &tt, MergeStrategy::Keep), 1);
+        assert_eq!(es.as_slice(), &[10., 4.]);
+        assert!(es.is_trained(0));
+
+        let mut es = build(&mut rng);
+        assert_eq!(es.merge(&other, &tt, MergeStrategy::Average), 1);
+        assert_eq!(es.as_slice(), &[10., 4.]);
+    }
+
+    #[test]
+    fn test_distance_matrix() {
+        let mut es = EmbeddingStore::new(100, 1, Distance::Euclidean);
+        (0..100).for_each(|i| es.set_embedding(i, &[i as f32]));
+        let mut other = EmbeddingStore::new(70, 1, Distance::Euclidean);
+        (0..70).for_each(|i| other.set_embedding(i, &[2. * i as f32]));
+
+        let dm = es.distance_matrix(&other);
+        assert_eq!(dm.len(), 100 * 70);
+        assert_eq!(dm[0 * 70 + 3], 6.);
+        assert_eq!(dm[99 * 70 + 69], 39.);
+
+        let block = es.distance_matrix_block(&other, 90..100, 65..70);
+        assert_eq!(block.len(), 10 * 5);
+        assert_eq!(block[0], dm[90 * 70 + 65]);
+        assert_eq!(block[9 * 5 + 4], dm[99 * 70 + 69]);
+    }
+
+    #[test]
+    fn test_nearest_neighbors_of_type() {
+        let mut vocab = Vocab::new();
+        let mut es = EmbeddingStore::new(6, 1, Distance::Euclidean);
+        for i in 0..6 {
+            let node_type = if i % 2 == 0 { "user" } else { "item" };
+            let node_id = vocab.get_or_insert(node_type.into(), i.to_string());
+            es.set_embedding(node_id, &[i as f32]);
+        }
+
+        // Asking for more than exist shouldn't leak filtered nodes into the results
+        let q = Entity::Embedding(&[0.]);
+        let results = es.nearest_neighbors_of_type(&q, 5, &vocab, "item");
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![1, 3, 5]);
+
+        assert!(es.nearest_neighbors_of_type(&q, 5, &vocab, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_cached_norms() {
+        let mut es = EmbeddingStore::new(3, 2, Distance::Cosine);
+        es.set_embedding(0, &[1., 0.]);
+        es.set_embedding(1, &[3., 4.]);
+        es.set_embedding(2, &[0., 2.]);
+
+        let q = Entity::Embedding(&[0., 1.]);
+        let expected: Vec<_> = (0..3)
+            .map(|i| Distance::Cosine.compute(&[0., 1.], es.get_embedding(i)))
+            .collect();
+
+        // First search fills the cache
+        let results = es.nearest_neighbors(&q, 3);
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![2, 1, 0]);
+        assert!(es.norms.get().is_some());
+        for i in 0..3 {
+            assert!((es.compute_distance(&q, &Entity::Node(i)) - expected[i]).abs() < 1e-6);
+        }
+
+        // Writes keep it up to date
+        es.set_embedding(0, &[0., 5.]);
+        assert_eq!(es.norms.get().unwrap()[0], 5.);
+        assert!(es.compute_distance(&Entity::Node(0), &Entity::Node(2)).abs() < 1e-6);
+        es.push_embedding(&[6., 8.]);
+        assert_eq!(es.norms.get().unwrap()[3], 10.);
+
+        // Norms held by a reader are dropped rather than copied
+        let held = es.norms.get().unwrap();
+        es.set_embedding(1, &[0., 1.]);
+        assert_eq!(held[1], 5.);
+        assert!(es.norms.get().is_none());
+        assert!(es.compute_distance(&Entity::Node(1), &Entity::Node(2)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_norms_invalidated() {
+        let mut es = EmbeddingStore::new(3, 2, Distance::Cosine);
+        es.set_embedding(0, &[1., 0.]);
+        es.set_embedding(1, &[3., 4.]);
+        es.set_embedding(2, &[0., 2.]);
+        let q = Entity::Embedding(&[0., 1.]);
+        let check = |es: &EmbeddingStore| {
+            for i in 0..3 {
+                let expected = Distance::Cosine.compute(&[0., 1.], es.get_embedding(i));
+                assert!((es.compute_distance(&q, &Entity::Node(i)) - expected).abs() < 1e-6);
+            }
+        };
+
+        es.nearest_neighbors(&q, 3);
+        es.get_embedding_mut(1).iter_mut().for_each(|ei| *ei *= 10.);
+        check(&es);
+
+        es.nearest_neighbors(&q, 3);
+        es.get_embedding_mut_hogwild(2)[1] = 7.;
+        check(&es);
+
+        // Clones share the buffer, so a hogwild write through one drops the other's cache
+        let clone = es.clone();
+        es.nearest_neighbors(&q, 3);
+        clone.get_embedding_mut_hogwild(0)[0] = 2.;
+        check(&es);
+        assert_eq!(es.nearest_neighbors(&q, 1)[0].1, 2);
+
+        es.nearest_neighbors(&q, 3);
+        es.as_array_view_mut().column_mut(1).fill(1.);
+        check(&es);
+    }
+
+    #[test]
+    fn test_ndarray() {
+        let arr = ndarray::arr2(&[[1f32, 2.], [3., 4.], [5., 6.]]);
+        let mut es = EmbeddingStore::from_array(arr.view(), Distance::Euclidean);
+        assert_eq!(es.len(), 3);
+        assert_eq!(es.get_embedding(1), &[3., 4.]);
+        assert_eq!(es.as_array_view(), arr.view());
+
+        es.as_array_view_mut().column_mut(0).fill(0.);
+        assert_eq!(es.get_embedding(2), &[0., 6.]);
+
+        // Transposed views are read in logical order
+        let es = EmbeddingStore::from_array(arr.t(), Distance::Euclidean);
+        assert_eq!(es.get_embedding(0), &[1., 3., 5.]);
+    }
+
+    #[test]
+    fn test_mean_of() {
+        let mut es = EmbeddingStore::new(3, 2, Distance::Euclidean);
+        es.set_embedding(0, &[1., 2.]);
+        es.set_embedding(1, &[3., 4.]);
+        es.set_embedding(2, &[5., 9.]);
+
+        assert_eq!(es.mean_of(&[0, 1, 2]), vec![3., 5.]);
+        assert_eq!(es.mean_of(&[]), vec![0., 0.]);
+        assert_eq!(es.weighted_mean_of(&[(0, 3.), (1, 1.)]), vec![1.5, 2.5]);
+    }
+
+    #[test]
+    fn test_push_embedding() {
+        let mut es = EmbeddingStore::with_capacity(2, 2, Distance::Euclidean);
+        assert_eq!(es.len(), 0);
+
+        assert_eq!(es.push_embedding(&[0., 1.]), 0);
+        assert_eq!(es.push_embedding(&[1., 2.]), 1);
+        assert_eq!(es.push_embedding(&[2., 3.]), 2);
+        assert_eq!(es.len(), 3);
+        assert!(es.capacity() >= 3);
+
+        assert_eq!(es.get_embedding(2), &[2., 3.]);
+        assert_eq!(es.is_set(2), true);
+        for node_id in 33..40 {
+            es.push_embedding(&[node_id as f32, 0.]);
+        }
+        assert_eq!(es.is_set(9), true);
+        assert_eq!(es.get_embedding(9), &[39., 0.]);
+    }
+
+    #[test]
+    fn test_push_embedding_clone() {
+        let mut es = EmbeddingStore::with_capacity(2, 2, Distance::Cosine);
+        es.push_embedding(&[0., 1.]);
+        es.push_embedding(&[1., 0.]);
+        es.nearest_neighbors(&Entity::Node(0), 2);
+
+        // Pushing onto a clone copies the buffer rather than reallocating it under the original
+        let mut clone = es.clone();
+        for node_id in 0..10 {
+            clone.push_embedding(&[node_id as f32, 1.]);
+        }
+        assert_eq!(es.len(), 2);
+        assert_eq!(clone.len(), 12);
+        assert_eq!(es.as_slice(), &[0., 1., 1., 0.]);
+        assert_eq!(clone.get_embedding(11), &[9., 1.]);
+        assert_eq!(es.norms.get().unwrap().len(), 2);
+
+        // And the two no longer share writes
+        clone.set_embedding(0, &[5., 5.]);
+        assert_eq!(es.get_embedding(0), &[0., 1.]);
+        assert_eq!(clone.nearest_neighbors(&Entity::Node(0), 1)[0].1, 0);
+    }
+
     #[test]
     fn test_distances() {
         let alt_d = Distance::ALT.compute(&[1., 2., 1.], &[3., 2., 4.]);
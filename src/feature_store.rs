@@ -1,15 +1,170 @@
 //! Defines the FeatureStore class which is used to define discrete features for each node
+use std::collections::HashMap;
 use std::sync::Arc;
+
+use rayon::prelude::*;
+
 use crate::NodeID;
-use crate::vocab::Vocab;
+use crate::graph::Graph;
+use crate::vocab::{Vocab,TranslationTable,invert_translation_table};
+use crate::tokenizer::Tokenizer;
+
+/// How `fill_missing_nodes_with` assigns features to nodes without any
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum MissingFeatures {
+    /// A unique feature per node.  Each featureless node gets its own embedding, at the cost of
+    /// growing the feature space by one per node.
+    Unique,
+
+    /// A single "UNK" feature shared by every featureless node
+    Shared,
+
+    /// An "UNK:<node type>" feature shared by featureless nodes of the same type
+    NodeType,
+
+    /// The distinct features of the node's out neighbors.  Nodes whose neighbors have no
+    /// features either fall back to the shared "UNK" feature.
+    Neighbors
+}
+
+/// Feature ids are stored as u32s; the feature vocab can't grow past that anyways.
+fn pack(feat_id: usize) -> u32 {
+    u32::try_from(feat_id).expect("Feature ids are limited to 2^32!")
+}
+
+/// Node features packed into a single buffer, CSR style.  A vector per node has surprising
+/// overhead when nodes only have a handful of features, so each node instead owns a run of
+/// `values`.  Runs which grow are moved to the end of the buffer, leaving their old slot as
+/// garbage which is reclaimed once it makes up half the buffer.
+#[derive(Clone,Debug)]
+struct PackedFeatures {
+    /// Start of each node's run in `values`.  Runs of all nodes together can pass 2^32 values,
+    /// so these are u64s.
+    starts: Vec<u64>,
+
+    /// Length of each node's run
+    lens: Vec<u32>,
+
+    values: Vec<u32>,
+
+    /// Number of values no longer part of any run
+    garbage: usize
+}
+
+impl PackedFeatures {
+
+    fn new(num_nodes: usize) -> Self {
+        PackedFeatures {
+            starts: vec![0; num_nodes],
+            lens: vec![0; num_nodes],
+            values: Vec::new(),
+            garbage: 0
+        }
+    }
+
+    /// Packs runs given in node order without leaving any garbage
+    fn from_runs<'a>(runs: impl Iterator<Item=&'a [u32]>) -> Self {
+        let mut packed = PackedFeatures::new(0);
+        runs.for_each(|run| {
+            packed.starts.push(packed.values.len() as u64);
+            packed.lens.push(run.len() as u32);
+            packed.values.extend_from_slice(run);
+        });
+        packed
+    }
+
+    fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    #[inline]
+    fn get(&self, node: NodeID) -> &[u32] {
+        let start = self.starts[node] as usize;
+        &self.values[start..start + self.lens[node] as usize]
+    }
+
+    fn is_last_run(&self, node: NodeID) -> bool {
+        self.starts[node] as usize + self.lens[node] as usize == self.values.len()
+    }
+
+    fn set(&mut self, node: NodeID, feats: &[u32]) {
+        let (start, len) = (self.starts[node] as usize, self.lens[node] as usize);
+        if feats.len() <= len {
+            self.values[start..start + feats.len()].copy_from_slice(feats);
+            self.garbage += len - feats.len();
+        } else if self.is_last_run(node) {
+            self.values.truncate(start);
+            self.values.extend_from_slice(feats);
+        } else {
+            self.garbage += len;
+            self.starts[node] = self.values.len() as u64;
+            self.values.extend_from_slice(feats);
+        }
+        self.lens[node] = feats.len() as u32;
+        self.maybe_compact();
+    }
+
+    fn extend(&mut self, node: NodeID, feats: &[u32]) {
+        if feats.is_empty() { return }
+
+        if !self.is_last_run(node) {
+            let (start, len) = (self.starts[node] as usize, self.lens[node] as usize);
+            self.garbage += len;
+            self.starts[node] = self.values.len() as u64;
+            self.values.extend_from_within(start..start + len);
+        }
+        self.values.extend_from_slice(feats);
+        self.lens[node] += feats.len() as u32;
+        self.maybe_compact();
+    }
+
+    /// Keeps the node's features matching the predicate, returning how many were removed
+    fn retain(&mut self, node: NodeID, f: impl Fn(&u32) -> bool) -> usize {
+        let (start, len) = (self.starts[node] as usize, self.lens[node] as usize);
+        let mut kept = 0;
+        for idx in start..start + len {
+            if f(&self.values[idx]) {
+                self.values[start + kept] = self.values[idx];
+                kept += 1;
+            }
+        }
+        self.lens[node] = kept as u32;
+        self.garbage += len - kept;
+        len - kept
+    }
 
-/// Main FeatureStore struct.  We use a vector of vectors to allow for dynamic numbers of features.
-/// This can and should be updated to a more memory friendly approach since vectors have surprising
-/// overhead and the number of discrete features tends to be relatively small.
+    fn maybe_compact(&mut self) {
+        if self.garbage > 1024 && self.garbage * 2 > self.values.len() {
+            self.compact();
+        }
+    }
+
+    /// Rewrites the runs contiguously in node order, dropping garbage and spare capacity
+    fn compact(&mut self) {
+        let mut values = Vec::with_capacity(self.values.len() - self.garbage);
+        for node in 0..self.len() {
+            let start = values.len();
+            values.extend_from_slice(self.get(node));
+            self.starts[node] = start as u64;
+        }
+        self.values = values;
+        self.garbage = 0;
+    }
+
+    fn iter(&self) -> impl Iterator<Item=&[u32]> {
+        (0..self.len()).map(move |node| self.get(node))
+    }
+
+    fn par_iter(&self) -> impl IndexedParallelIterator<Item=&[u32]> {
+        (0..self.len()).into_par_iter().map(move |node| self.get(node))
+    }
+}
+
+/// Main FeatureStore struct, holding the discrete features of each node.
 #[derive(Debug)]
 pub struct FeatureStore {
     /// Raw storage for features, indexed by node id
-    features: Vec<Vec<usize>>,
+    features: PackedFeatures,
 
     /// Since we often convert features to embeddings, which need namespaces, we have a feature
     /// namespace.
@@ -17,15 +172,26 @@ pub struct FeatureStore {
 
     /// Maps a raw feature to a feature_id
     feature_vocab: Vocab,
+
+    /// Registered feature namespaces and the max number of features a node can hold in each.
+    /// Features prefixed with a registered namespace, such as "brand:acme", are stored under
+    /// that namespace rather than the default one.
+    namespaces: Vec<(Arc<String>, Option<usize>)>,
+
+    /// Inverse document frequency of each feature, set by `compute_tf_idf`.  Features added
+    /// afterwards are treated as unseen.
+    idf: Option<Vec<f32>>
 }
 
 impl FeatureStore {
 
     pub fn new(size: usize, namespace: String) -> Self {
         FeatureStore {
-            features: vec![Vec::with_capacity(0); size],
+            features: PackedFeatures::new(size),
             namespace: namespace,
             feature_vocab: Vocab::new(),
+            namespaces: Vec::new(),
+            idf: None
         }
     }
 
@@ -33,6 +199,57 @@ impl FeatureStore {
         &self.namespace
     }
 
+    /// Registers a feature namespace, or updates its cap if it already exists.  Only features
+    /// added afterwards are affected.
+    pub fn add_namespace(&mut self, namespace: String, cap: Option<usize>) {
+        if let Some(entry) = self.namespaces.iter_mut().find(|(ns, _)| **ns == namespace) {
+            entry.1 = cap;
+        } else {
+            self.namespaces.push((Arc::new(namespace), cap));
+        }
+    }
+
+    /// Registered namespaces with their caps, in registration order
+    pub fn get_namespaces(&self) -> &[(Arc<String>, Option<usize>)] {
+        &self.namespaces
+    }
+
+    /// Returns the namespace a feature id is stored under
+    pub fn get_feature_namespace(&self, feat_id: usize) -> &Arc<String> {
+        self.feature_vocab.get_node_type(feat_id).expect("Feature id not in vocab!")
+    }
+
+    /// Splits a raw feature into its registered namespace index and name.  Features without a
+    /// registered prefix are left whole.
+    fn parse_feature<'a>(&self, feature: &'a str) -> (Option<usize>, &'a str) {
+        feature.split_once(':')
+            .and_then(|(prefix, name)| {
+                self.namespaces.iter().position(|(ns, _)| ns.as_str() == prefix).map(|idx| (Some(idx), name))
+            })
+            .unwrap_or((None, feature))
+    }
+
+    /// Converts raw features to feature ids, dropping any beyond their namespace's cap.  `counts`
+    /// holds how many features the node already has in each registered namespace.
+    fn insert_features(&mut self, node_features: &[String], counts: &mut [usize]) -> Vec<u32> {
+        let default_ns = Arc::new(self.namespace.clone());
+        let mut feat_ids = Vec::with_capacity(node_features.len());
+        for f in node_features.iter() {
+            let (ns_idx, name) = self.parse_feature(f);
+            let ns = match ns_idx {
+                Some(idx) => {
+                    let (ns, cap) = &self.namespaces[idx];
+                    if cap.map(|c| counts[idx] >= c).unwrap_or(false) { continue }
+                    counts[idx] += 1;
+                    ns.clone()
+                },
+                None => default_ns.clone()
+            };
+            feat_ids.push(pack(self.feature_vocab.get_or_insert_shared(ns, name)));
+        }
+        feat_ids
+    }
+
     fn set_nt_features(&mut self, node: NodeID, namespace: String, node_features: Vec<String>) {
         let ns = Arc::new(namespace);
         let fs = node_features.iter().map(|f| f.as_str());
@@ -44,31 +261,77 @@ impl FeatureStore {
         namespace: Arc<String>, 
         node_features: impl Iterator<Item=&'a str>
     ) {
-        self.features[node] = node_features
-            .map(|f| self.feature_vocab.get_or_insert_shared(namespace.clone(), f))
-            .collect()
+        let feats: Vec<u32> = node_features
+            .map(|f| pack(self.feature_vocab.get_or_insert_shared(namespace.clone(), f)))
+            .collect();
+        self.features.set(node, &feats);
     }
 
+    /// Replaces a node's features.  Features prefixed with a registered namespace, such as
+    /// "brand:acme", are stored under it; all others use the default namespace.
     pub fn set_features(&mut self, node: NodeID, node_features: Vec<String>) {
-        self.set_nt_features(node, self.namespace.clone(), node_features);
+        let mut counts = vec![0; self.namespaces.len()];
+        let feats = self.insert_features(&node_features, &mut counts);
+        self.features.set(node, &feats);
+    }
+
+    /// Replaces a node's features with the tokens of a raw string.  Tokens pass through
+    /// namespaces and caps the same way as `set_features`.
+    pub fn set_features_from_text(&mut self, node: NodeID, text: &str, tokenizer: &Tokenizer) {
+        self.set_features(node, tokenizer.tokenize(text));
+    }
+
+    /// Appends features to any the node already has, rather than replacing them.  Namespace caps
+    /// count the node's existing features.
+    pub fn add_features(&mut self, node: NodeID, node_features: Vec<String>) {
+        let mut counts = vec![0; self.namespaces.len()];
+        self.features.get(node).iter().for_each(|f_i| {
+            let ns = self.get_feature_namespace(*f_i as usize);
+            if let Some(idx) = self.namespaces.iter().position(|(n, _)| n == ns) {
+                counts[idx] += 1;
+            }
+        });
+        let new_features = self.insert_features(&node_features, &mut counts);
+        self.features.extend(node, &new_features);
+    }
+
+    /// Removes every occurrence of the given features from a node, returning how many were
+    /// removed.  Feature ids are left in the vocab, so embeddings trained against the store stay
+    /// aligned.
+    pub fn remove_features(&mut self, node: NodeID, node_features: &[String]) -> usize {
+        let default_ns = self.namespace.as_str();
+        let to_remove: Vec<u32> = node_features.iter().filter_map(|f| {
+            let (ns_idx, name) = self.parse_feature(f);
+            let ns = ns_idx.map(|idx| self.namespaces[idx].0.as_str()).unwrap_or(default_ns);
+            self.feature_vocab.get_node_id(ns.to_string(), name.to_string()).map(pack)
+        }).collect();
+
+        self.features.retain(node, |f_i| !to_remove.contains(f_i))
     }
 
     pub fn set_features_raw(&mut self, node: NodeID, node_features: impl Iterator<Item=usize>) {
-        self.features[node].extend(node_features);
+        let feats: Vec<u32> = node_features.map(pack).collect();
+        self.features.extend(node, &feats);
     }
 
-    pub fn get_features(&self, node: NodeID) -> &[usize] {
-        &self.features[node]
+    pub fn get_features(&self, node: NodeID) -> &[u32] {
+        self.features.get(node)
     }
 
-    fn get_pretty_feature(&self, feat_id: usize) -> String {
-        let (_nt, name) = self.feature_vocab.get_name(feat_id).unwrap();
-        name.to_string()
+    /// Features in registered namespaces keep their prefix so they round trip through
+    /// `set_features`.
+    pub fn get_pretty_feature(&self, feat_id: usize) -> String {
+        let (nt, name) = self.feature_vocab.get_name(feat_id).unwrap();
+        if self.namespaces.iter().any(|(ns, _)| *ns == nt) {
+            format!("{}:{}", nt, name)
+        } else {
+            name.to_string()
+        }
     }
 
     pub fn get_pretty_features(&self, node: NodeID) -> Vec<String> {
-        self.features[node].iter().map(|v_id| {
-            self.get_pretty_feature(*v_id)
+        self.features.get(node).iter().map(|v_id| {
+            self.get_pretty_feature(*v_id as usize)
         }).collect()
     }
 
@@ -80,16 +343,140 @@ impl FeatureStore {
         self.features.len()
     }
 
+    /// Reclaims space left behind by features which were replaced or removed.  Worth calling
+    /// after bulk loading features.
+    pub fn compact(&mut self) {
+        self.features.compact();
+        self.features.values.shrink_to_fit();
+    }
+
     /// This method assigns an unique, anonymous feature to all nodes which lack any features.  This is
     /// necessary for all graph embedding algorithms which map {feature} -> Embedding.
     pub fn fill_missing_nodes(&mut self) {
         for i in 0..self.features.len() {
-            if self.features[i].len() == 0 {
+            if self.features.get(i).is_empty() {
                 self.set_nt_features(i, "node".into(), vec![format!("{}", i)]);
             }
         }
     }
 
+    /// Assigns features to all nodes which lack any according to a policy, returning the number
+    /// of nodes filled.  Borrowed neighbor features are taken from the features set before
+    /// filling, so the result doesn't depend on node order.
+    pub fn fill_missing_nodes_with<G: Graph + Send + Sync>(
+        &mut self,
+        policy: MissingFeatures,
+        node_vocab: &Vocab,
+        graph: &G
+    ) -> usize {
+        let missing: Vec<NodeID> = (0..self.features.len())
+            .filter(|node_id| self.features.get(*node_id).is_empty())
+            .collect();
+
+        let borrowed: Vec<Vec<u32>> = if policy == MissingFeatures::Neighbors {
+            missing.par_iter().map(|node_id| {
+                let mut feats: Vec<u32> = if *node_id < graph.len() {
+                    graph.get_edges(*node_id).0.iter()
+                        .filter(|t_n| **t_n < self.features.len())
+                        .flat_map(|t_n| self.features.get(*t_n).iter().cloned())
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                feats.sort_unstable();
+                feats.dedup();
+                feats
+            }).collect()
+        } else {
+            Vec::new()
+        };
+
+        let ns = Arc::new("node".to_string());
+        for (i, node_id) in missing.iter().enumerate() {
+            let name = match policy {
+                MissingFeatures::Unique => format!("{}", node_id),
+                MissingFeatures::NodeType => {
+                    let node_type = node_vocab.get_node_type(*node_id)
+                        .map(|nt| nt.as_str())
+                        .unwrap_or("");
+                    format!("UNK:{}", node_type)
+                },
+                MissingFeatures::Neighbors if !borrowed[i].is_empty() => {
+                    self.features.set(*node_id, &borrowed[i]);
+                    continue
+                },
+                MissingFeatures::Shared | MissingFeatures::Neighbors => "UNK".to_string()
+            };
+            let feat_id = self.feature_vocab.get_or_insert_shared(ns.clone(), &name);
+            self.features.set(*node_id, &[pack(feat_id)]);
+        }
+        missing.len()
+    }
+
+    /// Computes the smoothed inverse document frequency, ln((1 + nodes) / (1 + df)) + 1, of every
+    /// feature in a single parallel pass.  Once computed, `get_feature_weights` returns TF-IDF
+    /// weights and weighted models use them.
+    pub fn compute_tf_idf(&mut self) {
+        let num_features = self.feature_vocab.len();
+        let df = self.features.par_iter()
+            .fold(|| (vec![0usize; num_features], Vec::new()), |(mut df, mut seen), feats| {
+                seen.clear();
+                seen.extend_from_slice(feats);
+                seen.sort_unstable();
+                seen.dedup();
+                seen.iter().for_each(|f_i| df[*f_i as usize] += 1);
+                (df, seen)
+            })
+            .map(|(df, _)| df)
+            .reduce(|| vec![0usize; num_features], |mut x, y| {
+                x.iter_mut().zip(y.into_iter()).for_each(|(xi, yi)| *xi += yi);
+                x
+            });
+
+        let n = self.features.len() as f32;
+        self.idf = Some(df.into_iter().map(|d| idf(n, d)).collect());
+    }
+
+    /// TF-IDF weight of each of a node's features, aligned with `get_features`.  Every occurrence
+    /// carries idf / num_features, so a repeated feature's total weight is its TF-IDF.  Returns
+    /// None until `compute_tf_idf` is called.
+    pub fn get_feature_weights(&self, node: NodeID) -> Option<Vec<f32>> {
+        self.idf.as_ref().map(|idf_vec| {
+            let feats = self.features.get(node);
+            let len = feats.len() as f32;
+            feats.iter().map(|f_i| {
+                let idf_i = idf_vec.get(*f_i as usize).cloned()
+                    .unwrap_or_else(|| idf(self.features.len() as f32, 0));
+                idf_i / len
+            }).collect()
+        })
+    }
+
+    /// Inverse document frequencies, if `compute_tf_idf` has been called
+    pub fn get_idf(&self) -> Option<&[f32]> {
+        self.idf.as_deref()
+    }
+
+    /// Reassembles a store from its pieces, such as when loading one from disk.  Returns None if
+    /// a node references a feature missing from the vocab.
+    pub fn from_parts(
+        features: Vec<Vec<usize>>,
+        namespace: String,
+        feature_vocab: Vocab,
+        namespaces: Vec<(Arc<String>, Option<usize>)>,
+        idf: Option<Vec<f32>>
+    ) -> Option<Self> {
+        let num_features = feature_vocab.len();
+        if features.iter().flatten().any(|f_i| *f_i >= num_features) {
+            return None
+        }
+        let features: Vec<Vec<u32>> = features.into_iter()
+            .map(|feats| feats.into_iter().map(pack).collect())
+            .collect();
+        let features = PackedFeatures::from_runs(features.iter().map(|feats| feats.as_slice()));
+        Some(FeatureStore { features, namespace, feature_vocab, namespaces, idf })
+    }
+
     pub fn get_vocab(&self) -> &Vocab {
         &self.feature_vocab
     }
@@ -98,7 +485,7 @@ impl FeatureStore {
         self.feature_vocab.clone()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item=&Vec<usize>> {
+    pub fn iter(&self) -> impl Iterator<Item=&[u32]> {
         self.features.iter()
     }
 
@@ -108,35 +495,330 @@ impl FeatureStore {
         let mut counts = vec![0usize; self.feature_vocab.len()];
         for feats in self.features.iter() {
             for f_i in feats.iter() {
-                counts[*f_i] += 1;
+                counts[*f_i as usize] += 1;
+            }
+        }
+        counts
+    }
+
+    /// Count the number of nodes each feature appears on.  Unlike `count_features`, a feature
+    /// repeated on a node only counts once.
+    pub fn count_feature_nodes(&self) -> Vec<usize> {
+        let mut counts = vec![0usize; self.feature_vocab.len()];
+        let mut last_node = vec![usize::MAX; self.feature_vocab.len()];
+        for (node_id, feats) in self.features.iter().enumerate() {
+            for f_i in feats.iter().map(|f_i| *f_i as usize) {
+                if last_node[f_i] != node_id {
+                    last_node[f_i] = node_id;
+                    counts[f_i] += 1;
+                }
             }
         }
         counts
     }
 
-    /// Removes features which don't meet the provided `count`.  This is helpful to prevent one-off
+    /// Counts the nodes each unordered pair of distinct features appears on together, keeping
+    /// pairs seen on at least `min_count` nodes.  Returned sparsely as (smaller id, larger id,
+    /// count), sorted by feature ids.  Cost grows quadratically with the features on a node.
+    pub fn count_cooccurrences(&self, min_count: usize) -> Vec<(usize, usize, usize)> {
+        let counts = self.features.par_iter()
+            .fold(|| (HashMap::new(), Vec::new()), |(mut counts, mut seen), feats| {
+                seen.clear();
+                seen.extend_from_slice(feats);
+                seen.sort_unstable();
+                seen.dedup();
+                for (i, f_i) in seen.iter().enumerate() {
+                    for f_j in seen[i + 1..].iter() {
+                        *counts.entry((*f_i as usize, *f_j as usize)).or_insert(0usize) += 1;
+                    }
+                }
+                (counts, seen)
+            })
+            .map(|(counts, _)| counts)
+            .reduce(HashMap::new, |x, y| {
+                let (mut large, small) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+                small.into_iter().for_each(|(pair, c)| *large.entry(pair).or_insert(0) += c);
+                large
+            });
+
+        let mut pairs: Vec<_> = counts.into_iter()
+            .filter(|(_, c)| *c >= min_count)
+            .map(|((f_i, f_j), c)| (f_i, f_j, c))
+            .collect();
+        pairs.par_sort_unstable();
+        pairs
+    }
+
+    /// Pointwise mutual information, ln(p(i, j) / (p(i) p(j))), of the pairs returned by
+    /// `count_cooccurrences`, with probabilities as fractions of nodes.  When normalized, PMI is
+    /// divided by -ln p(i, j), bounding it to [-1, 1] so rare pairs don't dominate.
+    pub fn pmi(&self, min_count: usize, normalized: bool) -> Vec<(usize, usize, f32)> {
+        let df = self.count_feature_nodes();
+        let n = self.features.len() as f64;
+        self.count_cooccurrences(min_count).into_par_iter().map(|(f_i, f_j, c)| {
+            let p_ij = c as f64 / n;
+            let pmi = (p_ij / ((df[f_i] as f64 / n) * (df[f_j] as f64 / n))).ln();
+            let score = if !normalized {
+                pmi
+            } else if p_ij >= 1. {
+                // Both features are on every node
+                1.
+            } else {
+                pmi / -p_ij.ln()
+            };
+            (f_i, f_j, score as f32)
+        }).collect()
+    }
+
+    /// Creates a store over a different set of nodes, such as another graph, which shares this
+    /// store's feature vocab, namespaces, and idf.  Feature ids match between the two, so feature
+    /// embeddings learned against one apply directly to the other.  `translation_table` maps
+    /// each node here to its id in the new store, typically from
+    /// `vocab.create_translation_table(&other_vocab)`; features of unmapped nodes aren't
+    /// carried over.
+    pub fn remap_nodes(&self, translation_table: &TranslationTable, num_nodes: usize) -> FeatureStore {
+        let to_old = invert_translation_table(translation_table, num_nodes);
+        let features = PackedFeatures::from_runs(to_old.iter().map(|old_id| {
+            old_id.filter(|id| *id < self.features.len())
+                .map(|id| self.features.get(id))
+                .unwrap_or(&[])
+        }));
+
+        FeatureStore {
+            features,
+            namespace: self.namespace.clone(),
+            feature_vocab: self.feature_vocab.clone(),
+            namespaces: self.namespaces.clone(),
+            idf: self.idf.clone()
+        }
+    }
+
+    /// Removes features which appear on fewer than `count` nodes, remapping the remaining
+    /// features to a new, compact feature vocab.  This is helpful to prevent one-off
     /// occurences of words acting as node biasesand otherwise harming the quality of the
-    /// embeddings.
+    /// embeddings, and keeps singletons from bloating the feature embeddings.  Features keep
+    /// their namespaces.
     pub fn prune_min_count(&self, count: usize) -> FeatureStore {
-        let counts = self.count_features();
+        let counts = self.count_feature_nodes();
 
         let mut new_fs = FeatureStore::new(self.features.len(), self.namespace.clone());
+        new_fs.namespaces = self.namespaces.clone();
         
         // Filter out features that don't meet the min_count
-        let ns = Arc::new(self.namespace.clone());
         self.features.iter().enumerate().for_each(|(node_id, feats)| {
-            let new_feats = feats.iter()
-                .filter(|f_i| counts[**f_i] >= count)
+            let new_feats: Vec<u32> = feats.iter()
+                .filter(|f_i| counts[**f_i as usize] >= count)
                 .map(|f_i| {
-                    let (_nt, nn) = self.feature_vocab.get_name(*f_i)
+                    let (nt, nn) = self.feature_vocab.get_name(*f_i as usize)
                         .expect("Should never be unavailable!");
-                    nn
-                });
-
-            new_fs.set_nt_features_shared(node_id, ns.clone(), new_feats);
+                    pack(new_fs.feature_vocab.get_or_insert_shared(nt, nn))
+                })
+                .collect();
+            new_fs.features.set(node_id, &new_feats);
         });
+
+        if self.idf.is_some() {
+            new_fs.compute_tf_idf();
+        }
         new_fs
     }
 
 }
 
+
+fn idf(num_nodes: f32, df: usize) -> f32 {
+    ((1. + num_nodes) / (1. + df as f32)).ln() + 1.
+}
+
+#[cfg(test)]
+mod feature_store_tests {
+    use super::*;
+    use crate::graph::CSR;
+
+    #[test]
+    fn test_packed_features() {
+        let mut packed = PackedFeatures::new(3);
+        packed.set(1, &[1, 2]);
+        packed.set(0, &[3]);
+        packed.extend(1, &[4]);
+        packed.set(2, &[5, 6]);
+        packed.set(0, &[]);
+        assert_eq!(packed.get(0), &[] as &[u32]);
+        assert_eq!(packed.get(1), &[1, 2, 4]);
+        assert_eq!(packed.get(2), &[5, 6]);
+
+        // The last run grows in place, others are relocated
+        packed.extend(2, &[7]);
+        assert_eq!(packed.garbage, 3);
+        packed.set(1, &[8, 8, 8, 8]);
+        assert_eq!(packed.retain(1, |f| *f == 8), 0);
+        assert_eq!(packed.retain(2, |f| *f != 6), 1);
+
+        let before: Vec<Vec<u32>> = packed.iter().map(|run| run.to_vec()).collect();
+        packed.compact();
+        assert_eq!(packed.garbage, 0);
+        assert_eq!(packed.values.len(), 6);
+        assert_eq!(packed.iter().map(|run| run.to_vec()).collect::<Vec<_>>(), before);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let mut fs = FeatureStore::new(2, "feat".to_string());
+        fs.add_namespace("brand".to_string(), Some(1));
+        fs.add_namespace("token".to_string(), None);
+
+        let feats = vec!["brand:acme", "brand:globex", "token:red", "token:shoe", "url:a.com"];
+        fs.set_features(0, feats.iter().map(|f| f.to_string()).collect());
+        assert_eq!(fs.get_pretty_features(0), vec!["brand:acme", "token:red", "token:shoe", "url:a.com"]);
+        assert_eq!(fs.get_feature_namespace(fs.get_features(0)[1] as usize).as_str(), "token");
+        assert_eq!(fs.get_feature_namespace(fs.get_features(0)[3] as usize).as_str(), "feat");
+
+        // The cap counts features the node already has
+        fs.add_features(0, vec!["brand:initech".to_string(), "token:blue".to_string()]);
+        assert_eq!(fs.get_features(0).len(), 5);
+
+        // Same name in different namespaces are different features
+        fs.set_features(1, vec!["token:acme".to_string(), "acme".to_string()]);
+        assert_eq!(fs.num_features(), 7);
+
+        let pruned = fs.prune_min_count(1);
+        assert_eq!(pruned.get_pretty_features(0), fs.get_pretty_features(0));
+        assert_eq!(pruned.get_namespaces().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_features() {
+        let mut fs = FeatureStore::new(2, "feat".to_string());
+        fs.add_namespace("brand".to_string(), None);
+        fs.set_features(0, vec!["a".into(), "brand:a".into(), "b".into(), "a".into()]);
+        fs.set_features(1, vec!["a".into()]);
+
+        assert_eq!(fs.remove_features(0, &["a".to_string(), "unknown".to_string()]), 2);
+        assert_eq!(fs.get_pretty_features(0), vec!["brand:a", "b"]);
+        assert_eq!(fs.remove_features(0, &["brand:a".to_string()]), 1);
+        assert_eq!(fs.get_pretty_features(0), vec!["b"]);
+
+        // Other nodes and the vocab are untouched
+        assert_eq!(fs.get_pretty_features(1), vec!["a"]);
+        assert_eq!(fs.num_features(), 3);
+    }
+
+    #[test]
+    fn test_remap_nodes() {
+        let mut fs = FeatureStore::new(3, "feat".to_string());
+        fs.set_features(0, vec!["a".into()]);
+        fs.set_features(1, vec!["b".into(), "c".into()]);
+        fs.set_features(2, vec!["c".into()]);
+
+        // Node 1 becomes node 0 in the new graph, node 2 doesn't exist there
+        let mut new_fs = fs.remap_nodes(&vec![None, Some(0), None], 2);
+        assert_eq!(new_fs.num_nodes(), 2);
+        assert_eq!(new_fs.get_features(0), fs.get_features(1));
+        assert!(new_fs.get_features(1).is_empty());
+
+        // New features are appended without disturbing shared ids
+        new_fs.set_features(1, vec!["d".into(), "a".into()]);
+        assert_eq!(new_fs.get_features(1), &[3, 0]);
+        assert_eq!(fs.num_features(), 3);
+        assert!(fs.get_vocab().translate_node(new_fs.get_vocab(), 3).is_none());
+    }
+
+    #[test]
+    fn test_prune_min_count() {
+        let mut fs = FeatureStore::new(3, "feat".to_string());
+        fs.set_features(0, vec!["a".into(), "b".into(), "b".into()]);
+        fs.set_features(1, vec!["a".into(), "c".into()]);
+        fs.set_features(2, vec!["c".into(), "d".into()]);
+        assert_eq!(fs.count_features(), vec![2, 2, 2, 1]);
+        assert_eq!(fs.count_feature_nodes(), vec![2, 1, 2, 1]);
+
+        // Repeats on a single node don't save "b"
+        let pruned = fs.prune_min_count(2);
+        assert_eq!(pruned.num_features(), 2);
+        assert_eq!(pruned.get_pretty_features(0), vec!["a"]);
+        assert_eq!(pruned.get_pretty_features(1), vec!["a", "c"]);
+        assert_eq!(pruned.get_pretty_features(2), vec!["c"]);
+        assert_eq!(pruned.get_features(2), &[1]);
+    }
+
+    #[test]
+    fn test_cooccurrence() {
+        let mut fs = FeatureStore::new(3, "feat".to_string());
+        fs.set_features(0, vec!["a".into(), "b".into(), "c".into(), "a".into()]);
+        fs.set_features(1, vec!["b".into(), "a".into()]);
+        fs.set_features(2, vec!["c".into()]);
+
+        // Repeats on a node count once
+        assert_eq!(fs.count_cooccurrences(1), vec![(0, 1, 2), (0, 2, 1), (1, 2, 1)]);
+        assert_eq!(fs.count_cooccurrences(2), vec![(0, 1, 2)]);
+
+        let pmi = fs.pmi(1, false);
+        assert!((pmi[0].2 - 1.5f32.ln()).abs() < 1e-6);
+        assert!((pmi[1].2 - 0.75f32.ln()).abs() < 1e-6);
+
+        // a and b always appear together
+        let npmi = fs.pmi(1, true);
+        assert!((npmi[0].2 - 1.).abs() < 1e-6);
+        assert!(npmi[1].2 < 0.);
+    }
+
+    #[test]
+    fn test_fill_missing_nodes() {
+        let mut node_vocab = Vocab::new();
+        ["a", "b", "c"].iter().for_each(|n| { node_vocab.get_or_insert("user".into(), n.to_string()); });
+        node_vocab.get_or_insert("item".into(), "d".into());
+        let graph = CSR::construct_from_edges(vec![(1, 0, 1.), (1, 3, 1.), (2, 1, 1.), (3, 2, 1.)]);
+
+        let build = || {
+            let mut fs = FeatureStore::new(4, "feat".to_string());
+            fs.set_features(0, vec!["x".into(), "y".into()]);
+            fs.set_features(3, vec!["y".into(), "z".into()]);
+            fs
+        };
+
+        let mut fs = build();
+        assert_eq!(fs.fill_missing_nodes_with(MissingFeatures::Shared, &node_vocab, &graph), 2);
+        assert_eq!(fs.get_features(1), fs.get_features(2));
+        assert_eq!(fs.num_features(), 4);
+
+        let mut fs = build();
+        fs.set_features(3, Vec::new());
+        fs.fill_missing_nodes_with(MissingFeatures::NodeType, &node_vocab, &graph);
+        assert_eq!(fs.get_pretty_features(2), vec!["UNK:user"]);
+        assert_eq!(fs.get_pretty_features(3), vec!["UNK:item"]);
+
+        // Node 2's only neighbor is featureless before filling, so it falls back to UNK
+        let mut fs = build();
+        fs.fill_missing_nodes_with(MissingFeatures::Neighbors, &node_vocab, &graph);
+        assert_eq!(fs.get_pretty_features(1), vec!["x", "y", "z"]);
+        assert_eq!(fs.get_pretty_features(2), vec!["UNK"]);
+
+        let mut fs = build();
+        fs.fill_missing_nodes_with(MissingFeatures::Unique, &node_vocab, &graph);
+        assert_eq!(fs.num_features(), 5);
+    }
+
+    #[test]
+    fn test_tf_idf() {
+        let mut fs = FeatureStore::new(3, "feat".to_string());
+        fs.set_features(0, vec!["a".into(), "b".into(), "b".into()]);
+        fs.set_features(1, vec!["a".into()]);
+        fs.set_features(2, vec!["a".into(), "c".into()]);
+        assert!(fs.get_feature_weights(0).is_none());
+
+        fs.compute_tf_idf();
+        let common = idf(3., 3);
+        let rare = idf(3., 1);
+        assert_eq!(common, 1.);
+
+        let weights = fs.get_feature_weights(0).unwrap();
+        assert!((weights[0] - common / 3.).abs() < 1e-6);
+        assert!((weights[1] + weights[2] - 2. * rare / 3.).abs() < 1e-6);
+        assert_eq!(fs.get_feature_weights(1).unwrap(), vec![1.]);
+
+        // Features added after the fact are treated as unseen
+        fs.add_features(1, vec!["d".into()]);
+        let weights = fs.get_feature_weights(1).unwrap();
+        assert!((weights[1] - idf(3., 0) / 2.).abs() < 1e-6);
+    }
+}
@@ -4,6 +4,14 @@
 //! defined in here to allow for swapping of edges while minimizing the amount of memory we have to
 //! copy.
 
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use rand::prelude::*;
+use rayon::prelude::*;
+
+use crate::vocab::{Vocab,TranslationTable};
+
 pub type NodeID = usize;
 
 pub trait Graph {
@@ -21,9 +29,21 @@ pub trait Graph {
     
     /// Get edge offset in graph
     fn get_edge_range(&self, idx: NodeID) -> (usize, usize);
+
+    /// Lazily iterates over neighbors and their weights, as stored, without allocating
+    fn iter_edges(&self, idx: NodeID) -> EdgeIter<'_> {
+        let (edges, weights) = self.get_edges(idx);
+        edges.iter().copied().zip(weights.iter().copied())
+    }
     
 }
 
+/// Iterator over (neighbor, weight) pairs returned by `Graph::iter_edges`
+pub type EdgeIter<'a> = std::iter::Zip<
+    std::iter::Copied<std::slice::Iter<'a, NodeID>>,
+    std::iter::Copied<std::slice::Iter<'a, f32>>
+>;
+
 /// trait which allows graphs to be updated
 pub trait ModifiableGraph {
     /// Get edges and corresponding weights
@@ -49,15 +69,24 @@ pub struct CSR {
 
 impl CSR {
     pub fn construct_from_edges(edges: Vec<(NodeID, NodeID, f32)>) -> Self {
+        let edges = edges.into_iter().map(|(f_n, t_n, w)| (f_n, t_n, w, ())).collect();
+        CSR::construct_with_attributes(edges).0
+    }
+
+    /// Constructs the graph along with a parallel array of per-edge attributes, such as
+    /// timestamps or relation ids, in the same order as the graph's edges.
+    pub fn construct_with_attributes<A: Clone + Default>(
+        edges: Vec<(NodeID, NodeID, f32, A)>
+    ) -> (Self, Vec<A>) {
 
         // Determine the number of rows in the adjacency graph
-        let max_node = edges.iter().map(|(from_node, to_node, _)| {
+        let max_node = edges.iter().map(|(from_node, to_node, _, _)| {
             *from_node.max(to_node)
         }).max().unwrap_or(0);
 
         // Figure out how many out edges per node
         let mut rows = vec![0; max_node+2];
-        edges.iter().for_each(|(from_node, _to_node, _w)| {
+        edges.iter().for_each(|(from_node, _to_node, _w, _a)| {
             rows[*from_node + 1] += 1;
         });
 
@@ -68,18 +97,189 @@ impl CSR {
             *count = offset;
         });
 
-        // Insert columns and weights
+        // Insert columns, weights, and attributes
         let mut counts  = vec![0; max_node+1];
         let mut columns = vec![0; edges.len()];
         let mut data    = vec![0f32; edges.len()];
-        edges.into_iter().for_each(|(from_node, to_node, weight)| {
+        let mut attrs   = vec![A::default(); edges.len()];
+        edges.into_iter().for_each(|(from_node, to_node, weight, attr)| {
             let idx = rows[from_node] + counts[from_node];
             columns[idx] = to_node;
             data[idx] = weight;
+            attrs[idx] = attr;
             counts[from_node] += 1;
         });
 
-        CSR { rows, columns, weights: data }
+        (CSR { rows, columns, weights: data }, attrs)
+    }
+
+    /// Extracts the subgraph induced by `nodes`, keeping only edges with both endpoints in the
+    /// set.  Node i in the subgraph is the i-th unique node provided; the returned table maps
+    /// subgraph ids back to the original ids.  Nodes keep their slot even without any remaining
+    /// edges.
+    pub fn subgraph(graph: &impl Graph, nodes: &[NodeID]) -> (Self, Vec<NodeID>) {
+        CSR::subgraph_with(graph, nodes, |_node_id, weights| weights.to_vec())
+    }
+
+    /// Adds any missing reverse edges so every neighborhood is mutual.  Edges present in both
+    /// directions get the combined weight in each; edges present in only one direction are
+    /// mirrored with their weight.  Assumes no duplicate edges.
+    pub fn symmetrize(graph: &impl Graph, combine: SymmetricWeight) -> Self {
+        CSR::symmetrize_with(graph, combine, |_node_id, weights| weights.to_vec())
+    }
+
+    fn symmetrize_with<G: Graph, F: Fn(NodeID, &[f32]) -> Vec<f32>>(
+        graph: &G,
+        combine: SymmetricWeight,
+        node_weights: F
+    ) -> Self {
+        // Each edge is emitted in both directions, tagged with whether it was the original.
+        // After sorting, each group holds at most one original and one mirrored copy.
+        let mut edges: Vec<(NodeID, NodeID, f32, bool)> = Vec::with_capacity(graph.edges() * 2);
+        for from_node in 0..graph.len() {
+            let (tos, ws) = graph.get_edges(from_node);
+            let ws = node_weights(from_node, ws);
+            tos.iter().zip(ws.into_iter()).for_each(|(to_node, w)| {
+                edges.push((from_node, *to_node, w, true));
+                if from_node != *to_node {
+                    edges.push((*to_node, from_node, w, false));
+                }
+            });
+        }
+        edges.par_sort_by_key(|(f_n, t_n, _, original)| (*f_n, *t_n, !*original));
+
+        let mut rows = vec![0; graph.len() + 1];
+        let mut columns = Vec::with_capacity(edges.len());
+        let mut weights = Vec::with_capacity(edges.len());
+        let mut idx = 0;
+        while idx < edges.len() {
+            let (f_n, t_n, w, _) = edges[idx];
+            let w = match edges.get(idx + 1) {
+                Some((f_n2, t_n2, w2, _)) if *f_n2 == f_n && *t_n2 == t_n => {
+                    idx += 1;
+                    combine.combine(w, *w2)
+                },
+                _ => w
+            };
+            rows[f_n + 1] += 1;
+            columns.push(t_n);
+            weights.push(w);
+            idx += 1;
+        }
+        for idx in 1..rows.len() {
+            rows[idx] += rows[idx - 1];
+        }
+
+        CSR { rows, columns, weights }
+    }
+
+    /// Keeps at most the `k` highest weighted edges for each node and drops edges weighted below
+    /// `min_weight`.  Either may be omitted.  Surviving edges keep their original order.
+    pub fn prune(graph: &(impl Graph + Sync), k: Option<usize>, min_weight: Option<f32>) -> Self {
+        CSR::prune_with(graph, k, min_weight, |_node_id, weights| weights.to_vec())
+    }
+
+    fn prune_with<G: Graph + Sync, F: Fn(NodeID, &[f32]) -> Vec<f32> + Sync>(
+        graph: &G,
+        k: Option<usize>,
+        min_weight: Option<f32>,
+        node_weights: F
+    ) -> Self {
+        CSR::map_edges_with(graph, node_weights, |_node_id, edges, ws| {
+            let mut idxs: Vec<usize> = (0..edges.len())
+                .filter(|idx| min_weight.map(|mw| ws[*idx] >= mw).unwrap_or(true))
+                .collect();
+
+            if let Some(k) = k {
+                if idxs.len() > k {
+                    idxs.sort_by(|a, b| ws[*b].total_cmp(&ws[*a]).then(a.cmp(b)));
+                    idxs.truncate(k);
+                    idxs.sort();
+                }
+            }
+            idxs.into_iter().map(|idx| (edges[idx], ws[idx])).collect()
+        })
+    }
+
+    /// Drops every edge from a node to itself.
+    pub fn remove_self_loops(graph: &(impl Graph + Sync)) -> Self {
+        CSR::map_edges_with(graph, |_node_id, weights| weights.to_vec(), remove_self_loops)
+    }
+
+    /// Adds a self loop with `weight` to every node which doesn't already have one.
+    pub fn add_self_loops(graph: &(impl Graph + Sync), weight: f32) -> Self {
+        CSR::map_edges_with(graph, |_node_id, weights| weights.to_vec(), |node_id, edges, ws| {
+            add_self_loop(node_id, edges, ws, weight)
+        })
+    }
+
+    /// Merges parallel edges between the same pair of nodes into a single edge, at the position
+    /// of the first.
+    pub fn dedup(graph: &(impl Graph + Sync), combine: SymmetricWeight) -> Self {
+        CSR::map_edges_with(graph, |_node_id, weights| weights.to_vec(), |_node_id, edges, ws| {
+            dedup_edges(edges, ws, combine)
+        })
+    }
+
+    /// Rebuilds the graph from each node's transformed edges, computed in parallel.
+    fn map_edges_with<G, F, T>(graph: &G, node_weights: F, transform: T) -> Self
+    where
+        G: Graph + Sync,
+        F: Fn(NodeID, &[f32]) -> Vec<f32> + Sync,
+        T: Fn(NodeID, &[NodeID], &[f32]) -> Vec<(NodeID, f32)> + Sync
+    {
+        let kept: Vec<Vec<(NodeID, f32)>> = (0..graph.len()).into_par_iter().map(|node_id| {
+            let (edges, ws) = graph.get_edges(node_id);
+            let ws = node_weights(node_id, ws);
+            transform(node_id, edges, &ws)
+        }).collect();
+
+        let mut rows = Vec::with_capacity(graph.len() + 1);
+        let mut columns = Vec::new();
+        let mut weights = Vec::new();
+        rows.push(0);
+        kept.into_iter().for_each(|node_edges| {
+            node_edges.into_iter().for_each(|(t_n, w)| {
+                columns.push(t_n);
+                weights.push(w);
+            });
+            rows.push(columns.len());
+        });
+
+        CSR { rows, columns, weights }
+    }
+
+    fn subgraph_with<G: Graph, F: Fn(NodeID, &[f32]) -> Vec<f32>>(
+        graph: &G, 
+        nodes: &[NodeID],
+        node_weights: F
+    ) -> (Self, Vec<NodeID>) {
+        let mut to_new = HashMap::with_capacity(nodes.len());
+        let mut to_orig = Vec::with_capacity(nodes.len());
+        nodes.iter().for_each(|node_id| {
+            if !to_new.contains_key(node_id) {
+                to_new.insert(*node_id, to_orig.len());
+                to_orig.push(*node_id);
+            }
+        });
+
+        let mut rows = Vec::with_capacity(to_orig.len() + 1);
+        let mut columns = Vec::new();
+        let mut weights = Vec::new();
+        rows.push(0);
+        for node_id in to_orig.iter() {
+            let (edges, ws) = graph.get_edges(*node_id);
+            let ws = node_weights(*node_id, ws);
+            edges.iter().zip(ws.into_iter()).for_each(|(t_n, w)| {
+                if let Some(new_id) = to_new.get(t_n) {
+                    columns.push(*new_id);
+                    weights.push(w);
+                }
+            });
+            rows.push(columns.len());
+        }
+
+        (CSR { rows, columns, weights }, to_orig)
     }
 
 }
@@ -124,6 +324,416 @@ impl ModifiableGraph for CSR {
 
 }
 
+/// How GraphBuilder combines repeated edges between the same pair of nodes
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum EdgeMerge {
+    /// Adds the weights together
+    Sum,
+
+    /// Keeps the largest weight
+    Max,
+
+    /// Keeps the smallest weight
+    Min,
+
+    /// Keeps the first weight added
+    First,
+
+    /// Keeps the most recent weight added
+    Last
+}
+
+impl EdgeMerge {
+    pub(crate) fn merge(&self, prev: f32, next: f32) -> f32 {
+        match self {
+            EdgeMerge::Sum   => prev + next,
+            EdgeMerge::Max   => prev.max(next),
+            EdgeMerge::Min   => prev.min(next),
+            EdgeMerge::First => prev,
+            EdgeMerge::Last  => next
+        }
+    }
+}
+
+/// Attaches per-edge attributes to a graph as a parallel array aligned with its edge offsets, so
+/// models and samplers can condition on more than a single weight.  The attribute type is up to
+/// the caller: a timestamp, a relation id, or a struct of several.
+pub struct AttributedGraph<G,A> {
+    graph: G,
+    attributes: Vec<A>
+}
+
+impl <G:Graph,A> AttributedGraph<G,A> {
+    /// Attributes must be in edge order, as produced by `CSR::construct_with_attributes`.
+    pub fn new(graph: G, attributes: Vec<A>) -> Result<Self,&'static str> {
+        if attributes.len() != graph.edges() {
+            Err("attributes length not equal to the number of edges!")?
+        }
+        Ok(AttributedGraph { graph, attributes })
+    }
+
+    pub fn graph(&self) -> &G {
+        &self.graph
+    }
+
+    /// Get edges, corresponding weights, and corresponding attributes
+    pub fn get_edges_with_attributes(&self, idx: NodeID) -> (&[NodeID], &[f32], &[A]) {
+        let (edges, weights) = self.graph.get_edges(idx);
+        (edges, weights, self.get_attributes(idx))
+    }
+
+    /// Get the attributes of the node's edges
+    pub fn get_attributes(&self, idx: NodeID) -> &[A] {
+        let (start, stop) = self.graph.get_edge_range(idx);
+        &self.attributes[start..stop]
+    }
+
+    pub fn into_parts(self) -> (G, Vec<A>) {
+        (self.graph, self.attributes)
+    }
+}
+
+impl <G:Graph,A> Graph for AttributedGraph<G,A> {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Get number of edges in graph
+    fn edges(&self) -> usize {
+        self.graph.edges()
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        self.graph.degree(idx)
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        self.graph.get_edges(idx)
+    }
+    
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        self.graph.get_edge_range(idx)
+    }
+
+}
+
+impl <G:CDFGraph,A> CDFGraph for AttributedGraph<G,A> {}
+
+/// Identifies the relation, or edge type, of an edge
+pub type RelationID = u32;
+
+/// Graph with a relation per edge, as in knowledge graphs.  Each node's edges are grouped by
+/// relation so the neighbors under a single relation form a contiguous slice, found by binary
+/// search.
+pub struct RelationalGraph<G> {
+    graph: AttributedGraph<G,RelationID>,
+    num_relations: usize
+}
+
+impl RelationalGraph<CSR> {
+    /// Constructs the graph from (from, to, weight, relation) edges, grouping each node's edges
+    /// by relation.  Insertion order is otherwise preserved.
+    pub fn construct_from_edges(mut edges: Vec<(NodeID, NodeID, f32, RelationID)>) -> Self {
+        edges.par_sort_by_key(|(f_n, _, _, rel)| (*f_n, *rel));
+        let (csr, relations) = CSR::construct_with_attributes(edges);
+        RelationalGraph::new(csr, relations).expect("Edges are grouped by construction")
+    }
+
+    /// Converts weights to CDFs for sampling, keeping the relations.
+    pub fn into_cdf(self) -> RelationalGraph<CumCSR> {
+        let (csr, relations) = self.graph.into_parts();
+        RelationalGraph {
+            graph: AttributedGraph { graph: CumCSR::convert(csr), attributes: relations },
+            num_relations: self.num_relations
+        }
+    }
+}
+
+impl <G:Graph> RelationalGraph<G> {
+    /// Relations must be in edge order and sorted within each node.
+    pub fn new(graph: G, relations: Vec<RelationID>) -> Result<Self,&'static str> {
+        let graph = AttributedGraph::new(graph, relations)?;
+        for node_id in 0..graph.len() {
+            if graph.get_attributes(node_id).windows(2).any(|w| w[0] > w[1]) {
+                Err("relations must be grouped within each node!")?
+            }
+        }
+        let num_relations = graph.attributes.iter().max().map(|r| *r as usize + 1).unwrap_or(0);
+        Ok(RelationalGraph { graph, num_relations })
+    }
+
+    pub fn graph(&self) -> &G {
+        self.graph.graph()
+    }
+
+    /// One more than the largest relation id
+    pub fn num_relations(&self) -> usize {
+        self.num_relations
+    }
+
+    /// Get edges, corresponding weights, and corresponding relations
+    pub fn get_edges_with_relations(&self, idx: NodeID) -> (&[NodeID], &[f32], &[RelationID]) {
+        self.graph.get_edges_with_attributes(idx)
+    }
+
+    /// Get the offsets, within the node's edges, of the edges with the given relation
+    pub fn get_relation_range(&self, idx: NodeID, relation: RelationID) -> (usize, usize) {
+        let relations = self.graph.get_attributes(idx);
+        let start = relations.partition_point(|r| *r < relation);
+        let stop = relations.partition_point(|r| *r <= relation);
+        (start, stop)
+    }
+
+    /// Get the edges and corresponding weights with the given relation
+    pub fn get_edges_by_relation(&self, idx: NodeID, relation: RelationID) -> (&[NodeID], &[f32]) {
+        let (start, stop) = self.get_relation_range(idx, relation);
+        let (edges, weights) = self.graph.get_edges(idx);
+        (&edges[start..stop], &weights[start..stop])
+    }
+
+    /// Iterates over each relation present at the node along with its edges and weights
+    pub fn relations(&self, idx: NodeID) -> impl Iterator<Item=(RelationID, &[NodeID], &[f32])> {
+        let (edges, weights, relations) = self.graph.get_edges_with_attributes(idx);
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            if start >= relations.len() {
+                return None
+            }
+            let relation = relations[start];
+            let stop = start + relations[start..].partition_point(|r| *r <= relation);
+            let group = (relation, &edges[start..stop], &weights[start..stop]);
+            start = stop;
+            Some(group)
+        })
+    }
+
+    pub fn into_parts(self) -> (G, Vec<RelationID>) {
+        self.graph.into_parts()
+    }
+}
+
+impl <G:CDFGraph> RelationalGraph<G> {
+    /// Samples a neighbor under the given relation, proportional to its transition probability
+    /// renormalized over the relation's edges.  Returns None if the node has no such edges.
+    pub fn sample_by_relation<R: Rng>(&self, idx: NodeID, relation: RelationID, rng: &mut R) -> Option<NodeID> {
+        let (start, stop) = self.get_relation_range(idx, relation);
+        if start == stop {
+            return None
+        }
+        let (edges, weights) = self.graph.get_edges(idx);
+        let lower = if start == 0 { 0. } else { weights[start - 1] };
+        let p = lower + rng.gen::<f32>() * (weights[stop - 1] - lower);
+        let offset = weights[start..stop].partition_point(|w| *w <= p).min(stop - start - 1);
+        Some(edges[start + offset])
+    }
+}
+
+impl <G:Graph> Graph for RelationalGraph<G> {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Get number of edges in graph
+    fn edges(&self) -> usize {
+        self.graph.edges()
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        self.graph.degree(idx)
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        self.graph.get_edges(idx)
+    }
+
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        self.graph.get_edge_range(idx)
+    }
+}
+
+impl <G:CDFGraph> CDFGraph for RelationalGraph<G> {}
+
+/// Combines two graphs defined over different vocabs into one graph over the union of their
+/// nodes.  Edges present in both graphs are combined according to `merge`, with those from `a`
+/// treated as added first.
+pub fn merge_graphs<GA: Graph, GB: Graph>(
+    a: &GA, 
+    vocab_a: &Vocab,
+    b: &GB, 
+    vocab_b: &Vocab,
+    merge: EdgeMerge
+) -> (CSR, Vocab) {
+    let raw_weights = |_node_id: NodeID, weights: &[f32]| weights.to_vec();
+    merge_graphs_with(a, vocab_a, b, vocab_b, merge, raw_weights)
+}
+
+fn merge_graphs_with<GA: Graph, GB: Graph, F: Fn(NodeID, &[f32]) -> Vec<f32>>(
+    a: &GA, 
+    vocab_a: &Vocab,
+    b: &GB, 
+    vocab_b: &Vocab,
+    merge: EdgeMerge,
+    node_weights: F
+) -> (CSR, Vocab) {
+    let (vocab, tt_b) = vocab_a.merge(vocab_b);
+    let mut builder = GraphBuilder::new(merge);
+    builder.set_num_nodes(vocab.len());
+
+    // Nodes from `a` keep their ids in the merged vocab
+    let tt_a: TranslationTable = (0..vocab_a.len()).map(Some).collect();
+    let graphs: [(&dyn Graph, &TranslationTable); 2] = [(a, &tt_a), (b, &tt_b)];
+    for (graph, tt) in graphs {
+        for from_node in 0..graph.len() {
+            let (edges, weights) = graph.get_edges(from_node);
+            let f_n = tt[from_node].expect("Vocab union missing a node!");
+            edges.iter().zip(node_weights(from_node, weights).into_iter()).for_each(|(to_node, w)| {
+                let t_n = tt[*to_node].expect("Vocab union missing a node!");
+                builder.add_edge(f_n, t_n, w);
+            });
+        }
+    }
+
+    (builder.finalize_csr(), vocab)
+}
+
+/// How `CSR::symmetrize` weights edges which exist in both directions and `CSR::dedup` weights
+/// parallel edges
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SymmetricWeight {
+    Max,
+    Sum,
+    Mean
+}
+
+impl SymmetricWeight {
+    fn combine(&self, w1: f32, w2: f32) -> f32 {
+        self.combine_all(&[w1, w2])
+    }
+
+    fn combine_all(&self, weights: &[f32]) -> f32 {
+        match self {
+            SymmetricWeight::Max  => weights.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
+            SymmetricWeight::Sum  => weights.iter().sum(),
+            SymmetricWeight::Mean => weights.iter().sum::<f32>() / weights.len() as f32
+        }
+    }
+}
+
+fn remove_self_loops(node_id: NodeID, edges: &[NodeID], weights: &[f32]) -> Vec<(NodeID, f32)> {
+    edges.iter().zip(weights.iter())
+        .filter(|(t_n, _)| **t_n != node_id)
+        .map(|(t_n, w)| (*t_n, *w))
+        .collect()
+}
+
+fn add_self_loop(node_id: NodeID, edges: &[NodeID], weights: &[f32], weight: f32) -> Vec<(NodeID, f32)> {
+    let mut out: Vec<_> = edges.iter().cloned().zip(weights.iter().cloned()).collect();
+    if !edges.contains(&node_id) {
+        out.push((node_id, weight));
+    }
+    out
+}
+
+fn dedup_edges(edges: &[NodeID], weights: &[f32], combine: SymmetricWeight) -> Vec<(NodeID, f32)> {
+    let mut positions = HashMap::new();
+    let mut groups: Vec<(NodeID, Vec<f32>)> = Vec::new();
+    edges.iter().zip(weights.iter()).for_each(|(t_n, w)| {
+        let idx = *positions.entry(*t_n).or_insert(groups.len());
+        if idx == groups.len() {
+            groups.push((*t_n, Vec::new()));
+        }
+        groups[idx].1.push(*w);
+    });
+    groups.into_iter().map(|(t_n, ws)| (t_n, combine.combine_all(&ws))).collect()
+}
+
+/// Accumulates edges over time, such as from a stream, before freezing them into a CSR.  Buffered
+/// edges are periodically sorted and merged so repeated edges don't grow memory unbounded.
+pub struct GraphBuilder {
+    edges: Vec<(NodeID, NodeID, f32)>,
+    merge: EdgeMerge,
+    num_nodes: usize,
+
+    /// Size of the buffer which triggers the next compaction
+    compact_at: usize
+}
+
+/// Minimum buffer size before compacting
+const MIN_COMPACTION: usize = 1_000_000;
+
+impl GraphBuilder {
+    pub fn new(merge: EdgeMerge) -> Self {
+        GraphBuilder { edges: Vec::new(), merge, num_nodes: 0, compact_at: MIN_COMPACTION }
+    }
+
+    /// Number of buffered edges.  Repeated edges are counted until the next compaction.
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Ensures the finalized graph has at least `num_nodes` nodes, even if some have no edges.
+    pub fn set_num_nodes(&mut self, num_nodes: usize) {
+        self.num_nodes = self.num_nodes.max(num_nodes);
+    }
+
+    pub fn add_edge(&mut self, from_node: NodeID, to_node: NodeID, weight: f32) {
+        self.num_nodes = self.num_nodes.max(from_node.max(to_node) + 1);
+        self.edges.push((from_node, to_node, weight));
+        if self.edges.len() >= self.compact_at {
+            self.compact();
+            self.compact_at = (self.edges.len() * 2).max(MIN_COMPACTION);
+        }
+    }
+
+    /// Sorts the buffer and merges repeated edges.  Sorting is stable, so First and Last respect
+    /// insertion order.
+    fn compact(&mut self) {
+        compact_edges(&mut self.edges, self.merge);
+    }
+
+    /// Freezes the edges into a CSR with raw weights.
+    pub fn finalize_csr(mut self) -> CSR {
+        self.compact();
+        let mut rows = vec![0; self.num_nodes + 1];
+        self.edges.iter().for_each(|(f_n, _, _)| rows[*f_n + 1] += 1);
+        for idx in 1..rows.len() {
+            rows[idx] += rows[idx - 1];
+        }
+        let (columns, weights) = self.edges.into_iter().map(|(_, t_n, w)| (t_n, w)).unzip();
+        CSR { rows, columns, weights }
+    }
+
+    /// Freezes the edges into a CumCSR, ready for sampling.
+    pub fn finalize(self) -> CumCSR {
+        CumCSR::convert(self.finalize_csr())
+    }
+}
+
+/// Sorts edges by (from, to) and merges repeated edges in place.
+pub(crate) fn compact_edges(edges: &mut Vec<(NodeID, NodeID, f32)>, merge: EdgeMerge) {
+    edges.par_sort_by_key(|(f_n, t_n, _)| (*f_n, *t_n));
+    let mut cur_record = 0;
+    for idx in 1..edges.len() {
+        let (f_n, t_n, w) = edges[idx];
+        let c_r = edges[cur_record];
+        if f_n == c_r.0 && t_n == c_r.1 {
+            edges[cur_record].2 = merge.merge(c_r.2, w);
+        } else {
+            cur_record += 1;
+            edges[cur_record] = edges[idx];
+        }
+    }
+    edges.truncate((cur_record + 1).min(edges.len()));
+}
+
 /// Normalizes sum of weights for a node to 1
 pub struct NormalizedCSR(CSR);
 
@@ -198,6 +808,29 @@ impl CumCSR {
         CumCSR(csr)
     }
 
+    /// Reassembles a graph from its raw parts, such as when deserializing.  Weights must already
+    /// be CDFs.
+    pub fn from_parts(
+        rows: Vec<usize>, 
+        columns: Vec<NodeID>, 
+        weights: Vec<f32>
+    ) -> Result<CumCSR,&'static str> {
+        if rows.is_empty() || rows[0] != 0 || rows[rows.len() - 1] != columns.len() {
+            Err("row offsets don't match the number of edges!")?
+        }
+        if columns.len() != weights.len() {
+            Err("columns and weights lengths not equal!")?
+        }
+        if rows.windows(2).any(|w| w[0] > w[1]) {
+            Err("row offsets aren't monotonic!")?
+        }
+        let n_nodes = rows.len() - 1;
+        if columns.iter().any(|c| *c >= n_nodes) {
+            Err("edge references a node outside the graph!")?
+        }
+        Ok(CumCSR(CSR { rows, columns, weights }))
+    }
+
     pub fn clone_with_edges(&self, weights: Vec<f32>) -> Result<CumCSR,&'static str> {
         if weights.len() != self.0.weights.len() {
             Err("weights lengths not equal!")?
@@ -231,6 +864,72 @@ impl CumCSR {
 
         Ok(CumCSR(graph))
     }
+
+    /// Merges two graphs as in `merge_graphs`, operating on transition probabilities.  Raw
+    /// counts aren't recoverable from a CumCSR, so summing two snapshots weights each equally
+    /// regardless of how much activity each saw.
+    pub fn merge(
+        &self, 
+        vocab: &Vocab, 
+        other: &CumCSR, 
+        other_vocab: &Vocab, 
+        merge: EdgeMerge
+    ) -> (Self, Vocab) {
+        let (csr, vocab) = merge_graphs_with(self, vocab, other, other_vocab, merge, |_node_id, cdf| {
+            CDFtoP::new(cdf).collect()
+        });
+        (CumCSR::convert(csr), vocab)
+    }
+
+    /// Prunes the graph as in `CSR::prune`, where `min_weight` applies to transition
+    /// probabilities.  Probabilities are renormalized over the surviving edges.
+    pub fn prune(&self, k: Option<usize>, min_weight: Option<f32>) -> Self {
+        let csr = CSR::prune_with(self, k, min_weight, |_node_id, cdf| {
+            CDFtoP::new(cdf).collect()
+        });
+        CumCSR::convert(csr)
+    }
+
+    /// Removes self loops as in `CSR::remove_self_loops`.  Transition probabilities are
+    /// renormalized over the remaining edges.
+    pub fn remove_self_loops(&self) -> Self {
+        let csr = CSR::map_edges_with(self, |_node_id, cdf| CDFtoP::new(cdf).collect(), remove_self_loops);
+        CumCSR::convert(csr)
+    }
+
+    /// Adds self loops as in `CSR::add_self_loops`, where `weight` is relative to the node's
+    /// transition probabilities, which sum to 1, before renormalizing.
+    pub fn add_self_loops(&self, weight: f32) -> Self {
+        let csr = CSR::map_edges_with(self, |_node_id, cdf| CDFtoP::new(cdf).collect(), |node_id, edges, ws| {
+            add_self_loop(node_id, edges, ws, weight)
+        });
+        CumCSR::convert(csr)
+    }
+
+    /// Merges parallel edges as in `CSR::dedup`, operating on transition probabilities.
+    pub fn dedup(&self, combine: SymmetricWeight) -> Self {
+        let csr = CSR::map_edges_with(self, |_node_id, cdf| CDFtoP::new(cdf).collect(), |_node_id, edges, ws| {
+            dedup_edges(edges, ws, combine)
+        });
+        CumCSR::convert(csr)
+    }
+
+    /// Symmetrizes the graph as in `CSR::symmetrize`, operating on transition probabilities.
+    pub fn symmetrize(&self, combine: SymmetricWeight) -> Self {
+        let csr = CSR::symmetrize_with(self, combine, |_node_id, cdf| {
+            CDFtoP::new(cdf).collect()
+        });
+        CumCSR::convert(csr)
+    }
+
+    /// Extracts the subgraph induced by `nodes` as in `CSR::subgraph`.  Transition probabilities
+    /// are renormalized over the edges which remain.
+    pub fn subgraph(&self, nodes: &[NodeID]) -> (Self, Vec<NodeID>) {
+        let (csr, to_orig) = CSR::subgraph_with(self, nodes, |_node_id, cdf| {
+            CDFtoP::new(cdf).collect()
+        });
+        (CumCSR::convert(csr), to_orig)
+    }
 }
 
 impl Graph for CumCSR {
@@ -272,6 +971,36 @@ impl ModifiableGraph for CumCSR {
 
 impl CDFGraph for CumCSR {}
 
+/// Allows wrappers to own a graph shared with other owners, such as the Python bindings.
+impl <G:Graph> Graph for Arc<G> {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    /// Get number of edges in graph
+    fn edges(&self) -> usize {
+        self.as_ref().edges()
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        self.as_ref().degree(idx)
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        self.as_ref().get_edges(idx)
+    }
+
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        self.as_ref().get_edge_range(idx)
+    }
+}
+
+impl <G:CDFGraph> CDFGraph for Arc<G> {}
+
 /// This is a graph which allows us to swap in a new set of edge weights without having to copy the
 /// entire graph.  We use it in cases where policies update edge transition probabilities.
 pub struct OptCDFGraph<'a,G> {
@@ -348,6 +1077,144 @@ impl <'a,G:Graph> Graph for OptCDFGraph<'a,G> {
 
 impl <'a,G:Graph> CDFGraph for OptCDFGraph<'a,G> {}
 
+/// Precomputed alias tables (Walker/Vose) over a graph's edges, allowing O(1) weighted neighbor
+/// sampling regardless of degree rather than the O(log d) search a CDF requires.  Costs a
+/// probability and an alias for every edge on top of the underlying graph.
+pub struct AliasGraph<'a,G> {
+    graph: &'a G,
+
+    /// Probability of keeping the drawn slot rather than taking its alias, indexed by edge offset
+    probs: Vec<f32>,
+
+    /// Alias for each slot, relative to the start of the node's edges
+    aliases: Vec<u32>
+}
+
+impl <'a,G:Graph> AliasGraph<'a,G> {
+
+    /// Builds alias tables treating the graph's weights as raw, unnormalized edge weights.
+    pub fn from_weights(graph: &'a G) -> Self {
+        AliasGraph::build(graph, |node_id, out| {
+            out.extend_from_slice(graph.get_edges(node_id).1);
+        })
+    }
+
+    fn build<F: Fn(NodeID, &mut Vec<f32>)>(graph: &'a G, weights: F) -> Self {
+        let mut probs = vec![0f32; graph.edges()];
+        let mut aliases = vec![0u32; graph.edges()];
+        let mut scratch = Vec::new();
+        let mut small = Vec::new();
+        let mut large = Vec::new();
+        for node_id in 0..graph.len() {
+            let (start, stop) = graph.get_edge_range(node_id);
+            if start == stop { continue }
+
+            scratch.clear();
+            weights(node_id, &mut scratch);
+            build_alias_table(&mut scratch, &mut small, &mut large, 
+                              &mut probs[start..stop], &mut aliases[start..stop]);
+        }
+        AliasGraph { graph, probs, aliases }
+    }
+
+    /// Samples a neighbor proportional to its edge weight, returning None if the node has no
+    /// edges.
+    pub fn sample_neighbor<R: Rng>(&self, node_id: NodeID, rng: &mut R) -> Option<NodeID> {
+        let (start, stop) = self.graph.get_edge_range(node_id);
+        if start == stop {
+            return None
+        }
+
+        let slot = rng.gen_range(0, stop - start);
+        let idx = if rng.gen::<f32>() < self.probs[start + slot] {
+            slot
+        } else {
+            self.aliases[start + slot] as usize
+        };
+        Some(self.graph.get_edges(node_id).0[idx])
+    }
+}
+
+impl <'a,G:CDFGraph> AliasGraph<'a,G> {
+
+    /// Builds alias tables from a graph whose weights are stored as CDFs, such as CumCSR.
+    pub fn new(graph: &'a G) -> Self {
+        AliasGraph::build(graph, |node_id, out| {
+            out.extend(CDFtoP::new(graph.get_edges(node_id).1));
+        })
+    }
+}
+
+/// Vose's method.  Scales the weights so they average 1 then pairs each underfull slot with an
+/// overfull one, which donates the remainder.
+fn build_alias_table(
+    weights: &mut [f32],
+    small: &mut Vec<usize>,
+    large: &mut Vec<usize>,
+    probs: &mut [f32],
+    aliases: &mut [u32]
+) {
+    let n = weights.len();
+    let total = weights.iter().sum::<f32>();
+    if total > 0. {
+        weights.iter_mut().for_each(|w| *w *= n as f32 / total);
+    } else {
+        // No weights, so sample uniformly
+        weights.iter_mut().for_each(|w| *w = 1.);
+    }
+
+    small.clear();
+    large.clear();
+    weights.iter().enumerate().for_each(|(i, w)| {
+        if *w < 1. { small.push(i) } else { large.push(i) }
+    });
+
+    // Only pop once both have an entry, or the one popped from the other would be lost
+    while !small.is_empty() && !large.is_empty() {
+        let (s, l) = (small.pop().unwrap(), large.pop().unwrap());
+        probs[s] = weights[s];
+        aliases[s] = l as u32;
+        weights[l] = (weights[l] + weights[s]) - 1.;
+        if weights[l] < 1. { small.push(l) } else { large.push(l) }
+    }
+
+    // Whatever remains is within floating point error of 1
+    small.drain(..).chain(large.drain(..)).for_each(|i| {
+        probs[i] = 1.;
+        aliases[i] = i as u32;
+    });
+}
+
+impl <'a,G:Graph> Graph for AliasGraph<'a,G> {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Get number of edges in graph
+    fn edges(&self) -> usize {
+        self.graph.edges()
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        self.graph.degree(idx)
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        self.graph.get_edges(idx)
+    }
+    
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        self.graph.get_edge_range(idx)
+    }
+
+}
+
+impl <'a,G:CDFGraph> CDFGraph for AliasGraph<'a,G> {}
+
 /// Struct which converts CDF format to transition probabilities.
 #[derive(Clone,Copy)]
 pub struct CDFtoP<'a> {
@@ -514,4 +1381,241 @@ mod csr_tests {
         });
     }
 
+    #[test]
+    fn construct_subgraph() {
+        let edges = build_edges();
+
+        let csr = CSR::construct_from_edges(edges);
+        let (sub, to_orig) = CSR::subgraph(&csr, &[1, 0, 1]);
+        assert_eq!(to_orig, vec![1, 0]);
+        assert_eq!(sub.rows, vec![0, 2, 3]);
+        assert_eq!(sub.columns, vec![0, 1, 0]);
+        assert_eq!(sub.weights, vec![3., 10., 1.]);
+
+        // Node 2 keeps its slot despite losing its only edge
+        let ccsr = CumCSR::convert(csr);
+        let (sub, to_orig) = ccsr.subgraph(&[1, 2]);
+        assert_eq!(to_orig, vec![1, 2]);
+        assert_eq!(sub.len(), 2);
+        let (edges, weights) = sub.get_edges(0);
+        assert_eq!(edges, &[0, 1]);
+        assert!((weights[0] - 3./5.).abs() < 1e-6);
+        assert_eq!(weights[1], 1.);
+        assert_eq!(sub.degree(1), 0);
+    }
+
+    #[test]
+    fn construct_attributed() {
+        let edges = build_edges().into_iter().enumerate()
+            .map(|(ts, (f_n, t_n, w))| (f_n, t_n, w, ts as u64))
+            .collect();
+
+        let (csr, timestamps) = CSR::construct_with_attributes(edges);
+        assert_eq!(timestamps, vec![0, 1, 2, 4, 3]);
+
+        let graph = AttributedGraph::new(CumCSR::convert(csr), timestamps).unwrap();
+        let (edges, _weights, ts) = graph.get_edges_with_attributes(1);
+        assert_eq!(edges, &[1, 2, 0]);
+        assert_eq!(ts, &[1, 2, 4]);
+        assert_eq!(graph.get_attributes(2), &[3]);
+
+        let (ccsr, _) = graph.into_parts();
+        assert!(AttributedGraph::new(ccsr, vec![0u64; 4]).is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let mut vocab_a = Vocab::new();
+        let mut vocab_b = Vocab::new();
+        let id = |v: &mut Vocab, name: &str| v.get_or_insert("n".to_string(), name.to_string());
+        let a = CSR::construct_from_edges(vec![
+            (id(&mut vocab_a, "x"), id(&mut vocab_a, "y"), 1.),
+            (id(&mut vocab_a, "y"), id(&mut vocab_a, "x"), 2.)
+        ]);
+        let b = CSR::construct_from_edges(vec![
+            (id(&mut vocab_b, "z"), id(&mut vocab_b, "x"), 3.),
+            (id(&mut vocab_b, "x"), id(&mut vocab_b, "y"), 4.)
+        ]);
+
+        let (merged, vocab) = merge_graphs(&a, &vocab_a, &b, &vocab_b, EdgeMerge::Sum);
+        assert_eq!(vocab.len(), 3);
+        let x = vocab.get_node_id("n".into(), "x".into()).unwrap();
+        let y = vocab.get_node_id("n".into(), "y".into()).unwrap();
+        let z = vocab.get_node_id("n".into(), "z".into()).unwrap();
+        assert_eq!(merged.get_edges(x), (&[y][..], &[5f32][..]));
+        assert_eq!(merged.get_edges(y), (&[x][..], &[2f32][..]));
+        assert_eq!(merged.get_edges(z), (&[x][..], &[3f32][..]));
+
+        let (merged, _) = merge_graphs(&a, &vocab_a, &b, &vocab_b, EdgeMerge::Last);
+        assert_eq!(merged.get_edges(x).1, &[4.]);
+    }
+
+    #[test]
+    fn prune() {
+        let edges = build_edges();
+
+        let csr = CSR::construct_from_edges(edges);
+        let pruned = CSR::prune(&csr, Some(2), None);
+        assert_eq!(pruned.rows, vec![0, 1, 3, 4]);
+        assert_eq!(pruned.get_edges(1), (&[1usize, 0][..], &[3f32, 10.][..]));
+
+        let pruned = CSR::prune(&csr, None, Some(2.5));
+        assert_eq!(pruned.columns, vec![1, 0, 0]);
+        assert_eq!(pruned.rows, vec![0, 0, 2, 3]);
+
+        // Node 1 keeps only its top edge, renormalized to 1
+        let ccsr = CumCSR::convert(csr);
+        let pruned = ccsr.prune(Some(1), Some(0.1));
+        assert_eq!(pruned.get_edges(1), (&[0usize][..], &[1f32][..]));
+        assert_eq!(pruned.edges(), 3);
+    }
+
+    #[test]
+    fn symmetrize() {
+        let edges = build_edges();
+
+        let csr = CSR::construct_from_edges(edges);
+        let sym = CSR::symmetrize(&csr, SymmetricWeight::Max);
+        assert_eq!(sym.rows, vec![0, 2, 5, 7]);
+        assert_eq!(sym.columns, vec![1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!(sym.weights, vec![10., 2.5, 10., 3., 2., 2.5, 2.]);
+
+        let sym = CSR::symmetrize(&csr, SymmetricWeight::Sum);
+        assert_eq!(sym.get_edges(0).1, &[11., 2.5]);
+        assert_eq!(sym.get_edges(1).1, &[11., 3., 2.]);
+
+        let sym = CSR::symmetrize(&csr, SymmetricWeight::Mean);
+        assert_eq!(sym.get_edges(0).1, &[5.5, 2.5]);
+
+        // Every edge now has its reverse
+        (0..sym.len()).for_each(|f_n| sym.get_edges(f_n).0.iter().for_each(|t_n| {
+            assert!(sym.get_edges(*t_n).0.contains(&f_n));
+        }));
+    }
+
+    #[test]
+    fn graph_builder() {
+        let add_edges = |merge| {
+            let mut builder = GraphBuilder::new(merge);
+            builder.set_num_nodes(4);
+            build_edges().into_iter().for_each(|(f, t, w)| builder.add_edge(f, t, w));
+            builder.add_edge(1, 2, 4.);
+            builder.add_edge(1, 2, 1.);
+            builder.finalize_csr()
+        };
+
+        let csr = add_edges(EdgeMerge::Sum);
+        assert_eq!(csr.len(), 4);
+        assert_eq!(csr.rows, vec![0, 1, 4, 5, 5]);
+        assert_eq!(csr.columns, vec![1, 0, 1, 2, 0]);
+        assert_eq!(csr.weights, vec![1., 10., 3., 7., 2.5]);
+
+        assert_eq!(add_edges(EdgeMerge::Max).get_edges(1).1, &[10., 3., 4.]);
+        assert_eq!(add_edges(EdgeMerge::Min).get_edges(1).1, &[10., 3., 1.]);
+        assert_eq!(add_edges(EdgeMerge::First).get_edges(1).1, &[10., 3., 2.]);
+        assert_eq!(add_edges(EdgeMerge::Last).get_edges(1).1, &[10., 3., 1.]);
+
+        let ccsr = GraphBuilder::new(EdgeMerge::Sum).finalize();
+        assert_eq!(ccsr.len(), 0);
+    }
+
+    #[test]
+    fn alias_sampling() {
+        let edges = build_edges();
+
+        let csr = CSR::construct_from_edges(edges);
+        let ccsr = CumCSR::convert(csr.clone());
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(2023);
+
+        let alias = AliasGraph::new(&ccsr);
+        let mut counts = [0usize; 3];
+        let n = 100_000;
+        for _ in 0..n {
+            counts[alias.sample_neighbor(1, &mut rng).unwrap()] += 1;
+        }
+        let exp = [10./15., 3./15., 2./15.];
+        counts.iter().zip(exp.iter()).for_each(|(c, exp_p)| {
+            assert!((*c as f32 / n as f32 - exp_p).abs() < 1e-2);
+        });
+
+        assert_eq!(alias.sample_neighbor(0, &mut rng), Some(1));
+        assert_eq!(AliasGraph::from_weights(&csr).sample_neighbor(2, &mut rng), Some(0));
+    }
+
+    #[test]
+    fn iter_edges() {
+        let csr = CSR::construct_from_edges(build_edges());
+        for node_id in 0..csr.len() {
+            let (edges, weights) = csr.get_edges(node_id);
+            let pairs: Vec<_> = csr.iter_edges(node_id).collect();
+            assert_eq!(pairs.len(), csr.degree(node_id));
+            assert!(pairs.iter().zip(edges.iter().zip(weights.iter())).all(|(p, (e, w))| p.0 == *e && p.1 == *w));
+        }
+
+        let ccsr = CumCSR::convert(csr);
+        assert_eq!(ccsr.iter_edges(1).last().map(|(_, w)| w), Some(1.));
+    }
+
+    #[test]
+    fn self_loops_and_dedup() {
+        let edges = vec![
+            (0, 1, 1.),
+            (0, 0, 2.),
+            (0, 1, 3.),
+            (0, 2, 4.),
+            (2, 2, 1.),
+        ];
+        let csr = CSR::construct_from_edges(edges);
+
+        let cleaned = CSR::remove_self_loops(&csr);
+        assert_eq!(cleaned.get_edges(0), (&[1, 1, 2][..], &[1., 3., 4.][..]));
+        assert_eq!(cleaned.degree(2), 0);
+
+        let looped = CSR::add_self_loops(&cleaned, 0.5);
+        assert_eq!(looped.get_edges(0), (&[1, 1, 2, 0][..], &[1., 3., 4., 0.5][..]));
+        assert_eq!(looped.get_edges(1), (&[1][..], &[0.5][..]));
+        assert_eq!(CSR::add_self_loops(&csr, 0.5).get_edges(2), (&[2][..], &[1.][..]));
+
+        assert_eq!(CSR::dedup(&csr, SymmetricWeight::Sum).get_edges(0), (&[1, 0, 2][..], &[4., 2., 4.][..]));
+        assert_eq!(CSR::dedup(&csr, SymmetricWeight::Max).get_edges(0).1, &[3., 2., 4.]);
+        assert_eq!(CSR::dedup(&csr, SymmetricWeight::Mean).get_edges(0).1, &[2., 2., 4.]);
+
+        let ccsr = CumCSR::convert(csr).dedup(SymmetricWeight::Sum).remove_self_loops();
+        assert_eq!(ccsr.get_edges(0).0, &[1, 2]);
+        assert!((ccsr.get_edges(0).1[0] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn relational_edges() {
+        let edges = vec![
+            (0, 1, 1., 2),
+            (0, 2, 3., 0),
+            (0, 3, 1., 2),
+            (1, 0, 1., 1),
+            (0, 4, 4., 0),
+        ];
+
+        let graph = RelationalGraph::construct_from_edges(edges);
+        assert_eq!(graph.num_relations(), 3);
+        assert_eq!(graph.get_edges_with_relations(0), (&[2, 4, 1, 3][..], &[3., 4., 1., 1.][..], &[0, 0, 2, 2][..]));
+        assert_eq!(graph.get_edges_by_relation(0, 2), (&[1, 3][..], &[1., 1.][..]));
+        assert_eq!(graph.get_edges_by_relation(0, 1).0.len(), 0);
+
+        let groups: Vec<_> = graph.relations(0).map(|(r, e, _)| (r, e.to_vec())).collect();
+        assert_eq!(groups, vec![(0, vec![2, 4]), (2, vec![1, 3])]);
+
+        assert!(RelationalGraph::new(graph.graph().clone(), vec![2, 0, 0, 2, 1]).is_err());
+
+        let graph = graph.into_cdf();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(2023);
+        let mut counts = [0usize; 5];
+        for _ in 0..10_000 {
+            counts[graph.sample_by_relation(0, 0, &mut rng).unwrap()] += 1;
+        }
+        assert_eq!(counts[1] + counts[3], 0);
+        assert!((counts[4] as f32 / 10_000. - 4. / 7.).abs() < 2e-2);
+        assert_eq!(graph.sample_by_relation(0, 1, &mut rng), None);
+        assert_eq!(graph.sample_by_relation(1, 1, &mut rng), Some(0));
+    }
+
 }
@@ -25,6 +25,11 @@ impl<T> Hogwild<T> {
         unsafe { &mut *ptr }
     }
 
+    /// Returns true if other clones point at the same value.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+
     pub fn into_inner(self) -> Option<T> {
         Arc::into_inner(self.0).map(|x| x.into_inner())
     }
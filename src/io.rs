@@ -1,23 +1,34 @@
 //! The beginnings of a refactor of load/save methods currently defined within lib.rs
 use crate::graph::NodeID;
+use std::collections::HashMap as CHashMap;
 use std::fs::File;
-use std::io::{Write,BufWriter,Result as IOResult,BufReader,BufRead};
+use std::io::{Read,Write,BufWriter,Result as IOResult,BufReader,BufRead,Error as IOError,ErrorKind};
 use std::convert::AsRef;
+use std::path::Path;
+use std::sync::Arc;
 
+use arrow::array::{Array,ArrayRef,FixedSizeListArray,Float32Array,Float64Array,StringArray};
+use arrow::datatypes::{DataType,Field,Schema};
+use arrow::record_batch::RecordBatch;
 use fast_float::parse;
-use flate2::Compression;
+use flate2::{Compression,Crc};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use itertools::Itertools;
 use pyo3::exceptions::{PyValueError,PyKeyError,PyIOError};
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use pyo3::prelude::{PyResult,PyErr};
 use rayon::prelude::*;
 use ryu::Buffer;
+use safetensors::{Dtype,SafeTensors,serialize_to_file};
+use safetensors::tensor::TensorView;
 
-use crate::vocab::Vocab;
+use crate::vocab::{Vocab,Normalization};
 use crate::embeddings::{EmbeddingStore,Distance};
-use crate::graph::CumCSR;
+use crate::graph::{CumCSR,Graph,GraphBuilder,EdgeMerge,compact_edges};
 use crate::{CSR,EdgeType};
+use crate::feature_store::FeatureStore;
 
 /// Streaming writer for NodeEmbeddings.  Since Embeddings are often gigantic, creating them adhoc
 /// then streaming them to disk is beneficial.
@@ -181,6 +192,8 @@ impl EmbeddingReader {
                     return Err(PyValueError::new_err("Embeddings have different sizes!"));
                 }
                 m.copy_from_slice(&emb);
+                // Like other loaded stores, the embeddings are assumed to be trained
+                es.mark_trained(node_id);
                 i += 1;
                 Ok(())
             })?;
@@ -189,6 +202,815 @@ impl EmbeddingReader {
     }
 }
 
+/// Parquet counterpart to the EmbeddingWriter.  Embeddings are written with the schema
+/// (node_type: utf8, node_name: utf8, embedding: fixed_size_list<float32>), buffering
+/// `batch_size` rows into each record batch.
+pub struct ParquetEmbeddingWriter<'a> {
+    vocab: &'a Vocab,
+    dims: usize,
+    batch_size: usize,
+    schema: Arc<Schema>,
+    writer: ArrowWriter<File>
+}
+
+impl <'a> ParquetEmbeddingWriter<'a> {
+
+    pub fn new(
+        path: &str, 
+        vocab: &'a Vocab, 
+        dims: usize, 
+        batch_size: usize
+    ) -> PyResult<Self> {
+        let schema = Arc::new(parquet_embedding_schema(dims));
+        let f = File::create(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let writer = ArrowWriter::try_new(f, schema.clone(), None)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        Ok(ParquetEmbeddingWriter {
+            vocab,
+            dims,
+            batch_size: batch_size.max(1),
+            schema,
+            writer
+        })
+    }
+
+    pub fn stream<A: AsRef<[f32]>>(
+        &mut self, 
+        it: impl Iterator<Item=(NodeID, A)>
+    ) -> PyResult<()> {
+        for chunk in &it.chunks(self.batch_size) {
+            let mut node_types = Vec::with_capacity(self.batch_size);
+            let mut names = Vec::with_capacity(self.batch_size);
+            let mut values = Vec::with_capacity(self.batch_size * self.dims);
+            for (node_id, emb) in chunk {
+                let (node_type, name) = self.vocab.get_name(node_id)
+                    .expect("Programming error!");
+
+                let emb = emb.as_ref();
+                if emb.len() != self.dims {
+                    return Err(PyValueError::new_err("Embeddings have different sizes!"))
+                }
+                node_types.push((*node_type).clone());
+                names.push(name.to_string());
+                values.extend_from_slice(emb);
+            }
+
+            let batch = self.build_batch(node_types, names, values)?;
+            self.writer.write(&batch)
+                .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the remaining row groups and writes the parquet footer.  The file is not
+    /// readable until this is called.
+    pub fn finish(self) -> PyResult<()> {
+        self.writer.close()
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        Ok(())
+    }
+
+    fn build_batch(
+        &self, 
+        node_types: Vec<String>, 
+        names: Vec<String>, 
+        values: Vec<f32>
+    ) -> PyResult<RecordBatch> {
+        let item = Arc::new(Field::new("item", DataType::Float32, false));
+        let values: ArrayRef = Arc::new(Float32Array::from(values));
+        let embs = FixedSizeListArray::try_new(item, self.dims as i32, values, None)
+            .map_err(|e| PyValueError::new_err(format!("{:?}", e)))?;
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(node_types)),
+            Arc::new(StringArray::from(names)),
+            Arc::new(embs)
+        ];
+
+        RecordBatch::try_new(self.schema.clone(), columns)
+            .map_err(|e| PyValueError::new_err(format!("{:?}", e)))
+    }
+}
+
+/// Reads embeddings written by the ParquetEmbeddingWriter, or any parquet file following the
+/// same schema.
+pub struct ParquetEmbeddingReader;
+
+impl ParquetEmbeddingReader {
+
+    pub fn load(
+        path: &str,
+        distance: Distance,
+        filter_type: Option<String>
+    ) -> PyResult<(Vocab, EmbeddingStore)> {
+        let f = File::open(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(f)
+            .and_then(|b| b.build())
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let mut vocab = Vocab::new();
+        let mut dims = None;
+        let mut values = Vec::new();
+        for batch in reader {
+            let batch = batch.map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+            let node_types = get_column::<StringArray>(&batch, "node_type")?;
+            let names = get_column::<StringArray>(&batch, "node_name")?;
+            let embs = get_column::<FixedSizeListArray>(&batch, "embedding")?;
+            let emb_values = embs.values().as_any().downcast_ref::<Float32Array>()
+                .ok_or_else(|| PyValueError::new_err("Embedding column must contain float32 values!"))?;
+
+            let d = embs.value_length() as usize;
+            if *dims.get_or_insert(d) != d {
+                return Err(PyValueError::new_err("Embeddings have different sizes!"));
+            }
+
+            for row in 0..batch.num_rows() {
+                let node_type = node_types.value(row);
+                if let Some(ft) = filter_type.as_ref() {
+                    if ft != node_type { continue }
+                }
+
+                let i = vocab.len();
+                let node_id = vocab.get_or_insert(node_type.to_string(), names.value(row).to_string());
+                if node_id < i {
+                    return Err(PyKeyError::new_err(format!("found duplicate node at {}!", i)));
+                }
+
+                let start = embs.value_offset(row) as usize;
+                values.extend_from_slice(&emb_values.values()[start..start + d]);
+            }
+        }
+
+        let es = EmbeddingStore::new_with_vec(vocab.len(), dims.unwrap_or(0), distance, values)
+            .expect("Embedding count and vocab are kept in sync");
+
+        Ok((vocab, es))
+    }
+}
+
+/// Column names of an edge table
+#[derive(Clone,Debug)]
+pub struct EdgeColumns {
+    pub src_type: String,
+    pub src_name: String,
+    pub dst_type: String,
+    pub dst_name: String,
+
+    /// If omitted, every edge has weight 1
+    pub weight: Option<String>
+}
+
+impl Default for EdgeColumns {
+    fn default() -> Self {
+        EdgeColumns {
+            src_type: "src_type".to_string(),
+            src_name: "src_name".to_string(),
+            dst_type: "dst_type".to_string(),
+            dst_name: "dst_name".to_string(),
+            weight: Some("weight".to_string())
+        }
+    }
+}
+
+/// Reads edge tables from Parquet.  Row groups are decoded in parallel, a batch of them at a
+/// time, then added to the graph in file order so node ids are deterministic.
+pub struct ParquetGraphReader;
+
+type ParquetEdge = ((String, String), (String, String), f32);
+
+impl ParquetGraphReader {
+
+    pub fn load(
+        path: &str,
+        columns: &EdgeColumns,
+        edge_type: EdgeType,
+        merge: EdgeMerge
+    ) -> PyResult<(Vocab,CumCSR)> {
+        let f = File::open(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        let n_row_groups = ParquetRecordBatchReaderBuilder::try_new(f)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?
+            .metadata()
+            .num_row_groups();
+
+        let mut vocab = Vocab::new();
+        let mut builder = GraphBuilder::new(merge);
+        let row_groups: Vec<_> = (0..n_row_groups).collect();
+        for chunk in row_groups.chunks(rayon::current_num_threads().max(1)) {
+            let decoded = chunk.par_iter()
+                .map(|rg| ParquetGraphReader::read_row_group(path, *rg, columns))
+                .collect::<PyResult<Vec<_>>>()?;
+
+            decoded.into_iter().flatten().for_each(|(from_node, to_node, w)| {
+                let f_id = vocab.get_or_insert(from_node.0, from_node.1);
+                let t_id = vocab.get_or_insert(to_node.0, to_node.1);
+                builder.add_edge(f_id, t_id, w);
+                if matches!(edge_type, EdgeType::Undirected) {
+                    builder.add_edge(t_id, f_id, w);
+                }
+            });
+        }
+
+        builder.set_num_nodes(vocab.len());
+        Ok((vocab, builder.finalize()))
+    }
+
+    fn read_row_group(path: &str, row_group: usize, columns: &EdgeColumns) -> PyResult<Vec<ParquetEdge>> {
+        let f = File::open(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(f)
+            .map(|b| b.with_row_groups(vec![row_group]))
+            .and_then(|b| b.build())
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let mut edges = Vec::new();
+        let mut first_row = 0;
+        for batch in reader {
+            let batch = batch.map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+            let src_types = get_column::<StringArray>(&batch, &columns.src_type)?;
+            let src_names = get_column::<StringArray>(&batch, &columns.src_name)?;
+            let dst_types = get_column::<StringArray>(&batch, &columns.dst_type)?;
+            let dst_names = get_column::<StringArray>(&batch, &columns.dst_name)?;
+            let weights = match columns.weight.as_ref() {
+                Some(name) => Some(get_weight_column(&batch, name)?),
+                None => None
+            };
+
+            let mut nullable: Vec<(&str, &dyn Array)> = vec![
+                (&columns.src_type, src_types),
+                (&columns.src_name, src_names),
+                (&columns.dst_type, dst_types),
+                (&columns.dst_name, dst_names)
+            ];
+            if let Some(name) = columns.weight.as_ref() {
+                if let Some(ws) = batch.column_by_name(name) {
+                    nullable.push((name, ws.as_ref()));
+                }
+            }
+
+            for row in 0..batch.num_rows() {
+                if let Some((name, _)) = nullable.iter().find(|(_, column)| column.is_null(row)) {
+                    return Err(PyValueError::new_err(format!(
+                        "Null in column '{}' at row {} of row group {}!", name, first_row + row, row_group)))
+                }
+
+                let from_node = (src_types.value(row).to_string(), src_names.value(row).to_string());
+                let to_node = (dst_types.value(row).to_string(), dst_names.value(row).to_string());
+                let w = weights.as_ref().map(|ws| ws[row]).unwrap_or(1.);
+                edges.push((from_node, to_node, w));
+            }
+            first_row += batch.num_rows();
+        }
+        Ok(edges)
+    }
+}
+
+/// Reads a float32 or float64 column as f32s.
+fn get_weight_column(batch: &RecordBatch, name: &str) -> PyResult<Vec<f32>> {
+    let column = batch.column_by_name(name)
+        .ok_or_else(|| PyValueError::new_err(format!("Missing column '{}'!", name)))?;
+    if let Some(ws) = column.as_any().downcast_ref::<Float32Array>() {
+        Ok(ws.values().to_vec())
+    } else if let Some(ws) = column.as_any().downcast_ref::<Float64Array>() {
+        Ok(ws.values().iter().map(|w| *w as f32).collect())
+    } else {
+        Err(PyValueError::new_err(format!("Weight column '{}' must be float32 or float64!", name)))
+    }
+}
+
+fn parquet_embedding_schema(dims: usize) -> Schema {
+    let item = Arc::new(Field::new("item", DataType::Float32, false));
+    Schema::new(vec![
+        Field::new("node_type", DataType::Utf8, false),
+        Field::new("node_name", DataType::Utf8, false),
+        Field::new("embedding", DataType::FixedSizeList(item, dims as i32), false)
+    ])
+}
+
+/// Pulls a column out of a record batch by name, checking it's the expected array type.
+fn get_column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> PyResult<&'a A> {
+    batch.column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<A>())
+        .ok_or_else(|| PyValueError::new_err(format!("Missing or malformed column '{}'!", name)))
+}
+
+/// Writes a set of named EmbeddingStores, such as node embeddings, feature embeddings, or optimizer
+/// moments, into a single safetensors file.  Each store becomes a [len, dims] F32 tensor and its
+/// distance metric is recorded in the header metadata under `<name>.distance`.
+pub fn save_safetensors(path: &str, stores: &[(&str, &EmbeddingStore)]) -> IOResult<()> {
+    let buffers: Vec<Vec<u8>> = stores.iter()
+        .map(|(_, es)| es.as_slice().iter().flat_map(|v| v.to_le_bytes()).collect())
+        .collect();
+
+    let mut metadata = CHashMap::new();
+    let mut tensors = Vec::with_capacity(stores.len());
+    for ((name, es), buffer) in stores.iter().zip(buffers.iter()) {
+        metadata.insert(format!("{}.distance", name), format!("{:?}", es.distance()));
+        let view = TensorView::new(Dtype::F32, vec![es.len(), es.dims()], buffer)
+            .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+        tensors.push((name.to_string(), view));
+    }
+
+    serialize_to_file(tensors, &Some(metadata), Path::new(path))
+        .map_err(|e| IOError::new(ErrorKind::Other, format!("{:?}", e)))
+}
+
+/// Loads every tensor in a safetensors file as an EmbeddingStore, keyed by tensor name.  Tensors
+/// must be two dimensional F32 with a known distance recorded, as `save_safetensors` writes.
+pub fn load_safetensors(path: &str) -> IOResult<CHashMap<String, EmbeddingStore>> {
+    let buffer = std::fs::read(path)?;
+    let (_, metadata) = SafeTensors::read_metadata(&buffer)
+        .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+    let info = metadata.metadata().clone().unwrap_or_default();
+    let tensors = SafeTensors::deserialize(&buffer)
+        .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+    let mut stores = CHashMap::new();
+    for (name, view) in tensors.tensors() {
+        let shape = view.shape();
+        if view.dtype() != Dtype::F32 || shape.len() != 2 {
+            let msg = format!("Tensor {} is not a two dimensional F32 tensor!", name);
+            return Err(IOError::new(ErrorKind::InvalidData, msg))
+        }
+
+        let distance = match info.get(&format!("{}.distance", name)) {
+            Some(d) => Distance::from_name(d).ok_or_else(|| {
+                IOError::new(ErrorKind::InvalidData, format!("Tensor {} has unknown distance {}!", name, d))
+            })?,
+            None => {
+                let msg = format!("Tensor {} has no distance recorded!", name);
+                return Err(IOError::new(ErrorKind::InvalidData, msg))
+            }
+        };
+
+        let vec = view.data().chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let es = EmbeddingStore::new_with_vec(shape[0], shape[1], distance, vec)
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidData, format!("Tensor {} has the wrong size!", name)))?;
+
+        stores.insert(name, es);
+    }
+    Ok(stores)
+}
+
+/// Writes a magic header identifying a binary format and its version.
+pub fn write_magic(w: &mut impl Write, magic: &[u8]) -> IOResult<()> {
+    w.write_all(magic)
+}
+
+/// Checks the magic header, erroring if the file isn't the expected format or version.
+pub fn check_magic(r: &mut impl Read, magic: &[u8]) -> IOResult<()> {
+    let mut buf = vec![0u8; magic.len()];
+    r.read_exact(&mut buf)?;
+    if buf != magic {
+        let msg = format!("Unknown file format; expected header {:?}", String::from_utf8_lossy(magic));
+        return Err(IOError::new(ErrorKind::InvalidData, msg))
+    }
+    Ok(())
+}
+
+pub fn write_usize(w: &mut impl Write, v: usize) -> IOResult<()> {
+    w.write_all(&(v as u64).to_le_bytes())
+}
+
+pub fn read_usize(r: &mut impl Read) -> IOResult<usize> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf) as usize)
+}
+
+pub fn write_f32(w: &mut impl Write, v: f32) -> IOResult<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+pub fn read_f32(r: &mut impl Read) -> IOResult<f32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+/// Writes a length prefixed slice of floats.
+pub fn write_f32s(w: &mut impl Write, v: &[f32]) -> IOResult<()> {
+    write_usize(w, v.len())?;
+    v.iter().try_for_each(|vi| w.write_all(&vi.to_le_bytes()))
+}
+
+/// Reads `n` items of `size` bytes each.  Lengths come from the file, so rather than trusting
+/// them with an up front allocation the buffer only grows as data arrives; a corrupt length
+/// errors once it runs past the end of the file instead of aborting.
+fn read_items(r: &mut impl Read, n: usize, size: usize) -> IOResult<Vec<u8>> {
+    let len = n.checked_mul(size)
+        .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "Length prefix overflows!"))?;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(IOError::new(ErrorKind::InvalidData, "Length prefix runs past the end of the file!"))
+    }
+    Ok(buf)
+}
+
+pub fn read_f32s(r: &mut impl Read) -> IOResult<Vec<f32>> {
+    let n = read_usize(r)?;
+    let buf = read_items(r, n, 4)?;
+    Ok(buf.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+/// Writes a length prefixed list of NodeIDs, packed as u32s.  
+pub fn write_node_ids(w: &mut impl Write, ids: &[NodeID]) -> IOResult<()> {
+    write_usize(w, ids.len())?;
+    for id in ids.iter() {
+        let id = u32::try_from(*id)
+            .map_err(|_| IOError::new(ErrorKind::InvalidInput, "NodeID too large to pack!"))?;
+        w.write_all(&id.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn read_node_ids(r: &mut impl Read) -> IOResult<Vec<NodeID>> {
+    let n = read_usize(r)?;
+    let buf = read_items(r, n, 4)?;
+    Ok(buf.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as NodeID).collect())
+}
+
+/// Writes a length prefixed list of usizes.
+pub fn write_usizes(w: &mut impl Write, v: &[usize]) -> IOResult<()> {
+    write_usize(w, v.len())?;
+    v.iter().try_for_each(|vi| write_usize(w, *vi))
+}
+
+pub fn read_usizes(r: &mut impl Read) -> IOResult<Vec<usize>> {
+    let n = read_usize(r)?;
+    let buf = read_items(r, n, 8)?;
+    Ok(buf.chunks_exact(8).map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize).collect())
+}
+
+/// Writes a length prefixed utf-8 string.
+pub fn write_str(w: &mut impl Write, s: &str) -> IOResult<()> {
+    write_usize(w, s.len())?;
+    w.write_all(s.as_bytes())
+}
+
+pub fn read_str(r: &mut impl Read) -> IOResult<String> {
+    let n = read_usize(r)?;
+    let buf = read_items(r, n, 1)?;
+    String::from_utf8(buf).map_err(|e| IOError::new(ErrorKind::InvalidData, e))
+}
+
+/// Computes a CRC32 over everything written through it.
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc
+}
+
+impl <W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Computes a CRC32 over everything read through it.
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc
+}
+
+impl <R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writes a vocab as its name normalization flags, its node types, then each node's type id and
+/// name, in NodeID order.
+fn write_vocab(w: &mut impl Write, vocab: &Vocab) -> IOResult<()> {
+    let norm = vocab.normalization();
+    w.write_all(&[norm.nfkc as u8, norm.lowercase as u8, norm.trim as u8])?;
+    let types = vocab.node_types();
+    write_usize(w, types.len())?;
+    types.iter().try_for_each(|nt| write_str(w, nt))?;
+    write_usize(w, vocab.len())?;
+    vocab.iter().try_for_each(|(node_id, _node_type, name)| {
+        let type_id = vocab.get_node_type_id_of(node_id).expect("Programming error!");
+        write_usize(w, type_id)?;
+        write_str(w, name)
+    })?;
+    Ok(())
+}
+
+/// Reads back a vocab written by `write_vocab`, preserving NodeIDs, node type ids, and the
+/// normalization.
+fn read_vocab(r: &mut impl Read) -> IOResult<Vocab> {
+    let normalization = Normalization {
+        nfkc: read_flag(r)?,
+        lowercase: read_flag(r)?,
+        trim: read_flag(r)?
+    };
+    let n_types = read_usize(r)?;
+    let mut vocab = Vocab::new();
+    let types = (0..n_types).map(|type_id| {
+        let node_type = Arc::new(read_str(r)?);
+        if vocab.get_or_insert_node_type(node_type.clone()) != type_id {
+            return Err(IOError::new(ErrorKind::InvalidData, "Duplicate node type in vocab!"))
+        }
+        Ok(node_type)
+    }).collect::<IOResult<Vec<_>>>()?;
+    let n_nodes = read_usize(r)?;
+    for node_id in 0..n_nodes {
+        let type_id = read_usize(r)?;
+        let name = read_str(r)?;
+        let node_type = types.get(type_id)
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "Unknown node type!"))?;
+        if vocab.get_or_insert_shared(node_type.clone(), &name) != node_id {
+            return Err(IOError::new(ErrorKind::InvalidData, "Duplicate node in vocab!"))
+        }
+    }
+
+    // Saved names already follow the normalization
+    vocab.set_normalization(normalization);
+    Ok(vocab)
+}
+
+/// Header for binary vocabs; bump the version when the layout changes.
+const VOCAB_MAGIC: &[u8] = b"CLVRVOC2";
+
+/// Persists a vocab on its own, keeping NodeIDs and node type ids, so anything indexed by them
+/// (embeddings, features, ANN indices) can be saved separately and still line up on load.  The
+/// binary format is checksummed like the graph format and keeps the vocab's name normalization;
+/// the TSV format writes one "node_type\tname" line per node in NodeID order, for inspection or
+/// other tools.
+pub struct VocabSerializer;
+
+impl VocabSerializer {
+
+    pub fn save(path: &str, vocab: &Vocab) -> IOResult<()> {
+        let mut w = ChecksumWriter { inner: open_file_for_writing(path, None)?, crc: Crc::new() };
+        write_magic(&mut w, VOCAB_MAGIC)?;
+        write_vocab(&mut w, vocab)?;
+
+        let checksum = w.crc.sum();
+        let mut inner = w.inner;
+        inner.write_all(&checksum.to_le_bytes())?;
+        inner.flush()
+    }
+
+    pub fn load(path: &str) -> IOResult<Vocab> {
+        let mut r = ChecksumReader { inner: open_file_for_reading(path)?, crc: Crc::new() };
+        check_magic(&mut r, VOCAB_MAGIC)?;
+        let vocab = read_vocab(&mut r)?;
+
+        let checksum = r.crc.sum();
+        let mut buf = [0u8; 4];
+        r.inner.read_exact(&mut buf)?;
+        if u32::from_le_bytes(buf) != checksum {
+            return Err(IOError::new(ErrorKind::InvalidData, "Checksum mismatch; vocab file is corrupt!"))
+        }
+        Ok(vocab)
+    }
+
+    /// Writes the vocab as TSV.  Node types without any nodes can't be represented, so type ids
+    /// only survive the round trip when every type has a node, which is always true for vocabs
+    /// built from graphs.  Normalization isn't stored either; pass it to `load_tsv`.
+    pub fn save_tsv(path: &str, vocab: &Vocab, comp_level: Option<u32>) -> IOResult<()> {
+        let mut w = open_file_for_writing(path, comp_level)?;
+        for (_node_id, node_type, name) in vocab.iter() {
+            if [node_type.as_str(), name].iter().any(|s| s.contains(|c: char| c == '\t' || c == '\n' || c == '\r')) {
+                return Err(IOError::new(ErrorKind::InvalidInput,
+                        format!("Node {}:{} contains a tab or newline!", node_type, name)))
+            }
+            writeln!(w, "{}\t{}", node_type, name)?;
+        }
+        w.flush()
+    }
+
+    pub fn load_tsv(path: &str, normalization: Normalization) -> IOResult<Vocab> {
+        let mut vocab = Vocab::with_normalization(normalization);
+        let mut lines = 0;
+        let mut error = None;
+        let nodes = open_file_for_reading(path)?.lines().map_while(|line| {
+            lines += 1;
+            let parsed = line.and_then(|line| match line.split_once('\t') {
+                Some((node_type, name)) => Ok((node_type.to_string(), name.to_string())),
+                None => Err(IOError::new(ErrorKind::InvalidData, format!("Malformed line {}!", lines)))
+            });
+            parsed.map_err(|e| error = Some(e)).ok()
+        });
+        let added = vocab.extend_from_iter(nodes, false);
+        if let Some(e) = error {
+            return Err(e)
+        } else if added != lines {
+            return Err(IOError::new(ErrorKind::InvalidData, "Duplicate node in vocab!"))
+        }
+        Ok(vocab)
+    }
+}
+
+/// Header for binary graphs; bump the version when the layout changes.
+const GRAPH_MAGIC: &[u8] = b"CLVRGRF1";
+
+/// Binary format persisting a graph together with its vocab, followed by a CRC32 of the contents
+/// so corruption is caught on load rather than surfacing as odd results later.  Loading is bulk
+/// reads rather than parsing, so it's far faster than re-reading edge lists.
+pub struct GraphSerializer;
+
+impl GraphSerializer {
+
+    pub fn save(path: &str, graph: &CumCSR, vocab: &Vocab) -> IOResult<()> {
+        let mut w = ChecksumWriter { inner: open_file_for_writing(path, None)?, crc: Crc::new() };
+        write_magic(&mut w, GRAPH_MAGIC)?;
+
+        write_vocab(&mut w, vocab)?;
+
+        // Graph: row offsets, columns packed as u32s, then CDF weights
+        let mut offset = 0;
+        let mut rows = Vec::with_capacity(graph.len() + 1);
+        rows.push(offset);
+        (0..graph.len()).for_each(|node_id| {
+            offset += graph.degree(node_id);
+            rows.push(offset);
+        });
+        write_usizes(&mut w, &rows)?;
+
+        write_usize(&mut w, graph.edges())?;
+        for node_id in 0..graph.len() {
+            for t_n in graph.get_edges(node_id).0.iter() {
+                let t_n = u32::try_from(*t_n)
+                    .map_err(|_| IOError::new(ErrorKind::InvalidInput, "NodeID too large to pack!"))?;
+                w.write_all(&t_n.to_le_bytes())?;
+            }
+        }
+        write_usize(&mut w, graph.edges())?;
+        for node_id in 0..graph.len() {
+            graph.get_edges(node_id).1.iter().try_for_each(|wi| write_f32(&mut w, *wi))?;
+        }
+
+        let checksum = w.crc.sum();
+        let mut inner = w.inner;
+        inner.write_all(&checksum.to_le_bytes())?;
+        inner.flush()
+    }
+
+    pub fn load(path: &str) -> IOResult<(Vocab, CumCSR)> {
+        let mut r = ChecksumReader { inner: open_file_for_reading(path)?, crc: Crc::new() };
+        check_magic(&mut r, GRAPH_MAGIC)?;
+
+        let vocab = read_vocab(&mut r)?;
+
+        let rows = read_usizes(&mut r)?;
+        let columns = read_node_ids(&mut r)?;
+        let weights = read_f32s(&mut r)?;
+
+        let checksum = r.crc.sum();
+        let mut buf = [0u8; 4];
+        r.inner.read_exact(&mut buf)?;
+        if u32::from_le_bytes(buf) != checksum {
+            return Err(IOError::new(ErrorKind::InvalidData, "Checksum mismatch; graph file is corrupt!"))
+        }
+
+        let graph = CumCSR::from_parts(rows, columns, weights)
+            .map_err(|e| IOError::new(ErrorKind::InvalidData, e))?;
+        if graph.len() > vocab.len() {
+            return Err(IOError::new(ErrorKind::InvalidData, "Graph has more nodes than the vocab!"))
+        }
+        Ok((vocab, graph))
+    }
+}
+
+/// Header for feature bundles; bump the version when the layout changes.
+const FEATURE_BUNDLE_MAGIC: &[u8] = b"CLVRFTB1";
+
+/// Binary format persisting everything needed to embed new nodes from their features: the node
+/// vocab, the FeatureStore with its feature vocab, namespaces and idf, and optionally the learned
+/// feature embeddings with their vocab.  Checksummed like the GraphSerializer.
+pub struct FeatureBundle;
+
+impl FeatureBundle {
+
+    pub fn save(
+        path: &str,
+        node_vocab: &Vocab,
+        features: &FeatureStore,
+        feature_embeddings: Option<(&Vocab, &EmbeddingStore)>
+    ) -> IOResult<()> {
+        let mut w = ChecksumWriter { inner: open_file_for_writing(path, None)?, crc: Crc::new() };
+        write_magic(&mut w, FEATURE_BUNDLE_MAGIC)?;
+        write_vocab(&mut w, node_vocab)?;
+
+        // Feature store: default namespace, registered namespaces with caps, the feature vocab,
+        // each node's features, then idf if computed.  Caps are offset by one so 0 is no cap.
+        write_str(&mut w, features.get_ns())?;
+        write_usize(&mut w, features.get_namespaces().len())?;
+        features.get_namespaces().iter().try_for_each(|(ns, cap)| {
+            write_str(&mut w, ns)?;
+            write_usize(&mut w, cap.map(|c| c + 1).unwrap_or(0))
+        })?;
+        write_vocab(&mut w, features.get_vocab())?;
+        write_usize(&mut w, features.num_nodes())?;
+        features.iter().try_for_each(|feats| {
+            let feats: Vec<usize> = feats.iter().map(|f_i| *f_i as usize).collect();
+            write_usizes(&mut w, &feats)
+        })?;
+        match features.get_idf() {
+            Some(idf) => {
+                w.write_all(&[1u8])?;
+                write_f32s(&mut w, idf)?;
+            },
+            None => w.write_all(&[0u8])?
+        }
+
+        match feature_embeddings {
+            Some((vocab, es)) => {
+                w.write_all(&[1u8])?;
+                write_vocab(&mut w, vocab)?;
+                write_usize(&mut w, es.len())?;
+                write_usize(&mut w, es.dims())?;
+                write_str(&mut w, &format!("{:?}", es.distance()))?;
+                write_f32s(&mut w, es.as_slice())?;
+            },
+            None => w.write_all(&[0u8])?
+        }
+
+        let checksum = w.crc.sum();
+        let mut inner = w.inner;
+        inner.write_all(&checksum.to_le_bytes())?;
+        inner.flush()
+    }
+
+    pub fn load(path: &str) -> IOResult<(Vocab, FeatureStore, Option<(Vocab, EmbeddingStore)>)> {
+        let invalid = |msg: &str| IOError::new(ErrorKind::InvalidData, msg.to_string());
+        let mut r = ChecksumReader { inner: open_file_for_reading(path)?, crc: Crc::new() };
+        check_magic(&mut r, FEATURE_BUNDLE_MAGIC)?;
+        let node_vocab = read_vocab(&mut r)?;
+
+        let namespace = read_str(&mut r)?;
+        let n_namespaces = read_usize(&mut r)?;
+        let namespaces = (0..n_namespaces).map(|_| {
+            let ns = read_str(&mut r)?;
+            let cap = read_usize(&mut r)?;
+            Ok((Arc::new(ns), cap.checked_sub(1)))
+        }).collect::<IOResult<Vec<_>>>()?;
+        let feature_vocab = read_vocab(&mut r)?;
+        let n_nodes = read_usize(&mut r)?;
+        let features = (0..n_nodes)
+            .map(|_| read_usizes(&mut r))
+            .collect::<IOResult<Vec<_>>>()?;
+        let idf = if read_flag(&mut r)? { Some(read_f32s(&mut r)?) } else { None };
+
+        let feature_embeddings = if read_flag(&mut r)? {
+            let vocab = read_vocab(&mut r)?;
+            let nodes = read_usize(&mut r)?;
+            let dims = read_usize(&mut r)?;
+            let distance = Distance::from_name(&read_str(&mut r)?)
+                .ok_or_else(|| invalid("Unknown distance!"))?;
+            let es = EmbeddingStore::new_with_vec(nodes, dims, distance, read_f32s(&mut r)?)
+                .ok_or_else(|| invalid("Feature embeddings have the wrong size!"))?;
+            if es.len() != vocab.len() {
+                return Err(invalid("Feature embeddings don't match their vocab!"))
+            }
+            Some((vocab, es))
+        } else {
+            None
+        };
+
+        let checksum = r.crc.sum();
+        let mut buf = [0u8; 4];
+        r.inner.read_exact(&mut buf)?;
+        if u32::from_le_bytes(buf) != checksum {
+            return Err(invalid("Checksum mismatch; feature bundle is corrupt!"))
+        }
+
+        if features.len() > node_vocab.len() {
+            return Err(invalid("Features have more nodes than the node vocab!"))
+        }
+        let features = FeatureStore::from_parts(features, namespace, feature_vocab, namespaces, idf)
+            .ok_or_else(|| invalid("Node references an unknown feature!"))?;
+        Ok((node_vocab, features, feature_embeddings))
+    }
+}
+
+fn read_flag(r: &mut impl Read) -> IOResult<bool> {
+    let mut flag = [0u8];
+    r.read_exact(&mut flag)?;
+    Ok(flag[0] == 1)
+}
+
 pub fn open_file_for_reading(path: &str) -> IOResult<Box<dyn BufRead>> {
     let f = File::open(path)?;
 
@@ -253,47 +1075,40 @@ fn line_to_embedding(line: &String) -> Option<(String,String,Vec<f32>)> {
     emb.ok().map(|e| (node_type.to_string(), name.to_string(), e))
 }
 
+/// Buffered edges are sorted and merged once they reach this many, so graphs with heavily
+/// repeated edges never hold every raw row in memory at once.
+const EDGE_COMPACTION_SIZE: usize = 10_000_000;
+
 pub struct GraphReader; 
 
 impl GraphReader {
-    
-    fn deduplicate_edges(
-        edges: &mut Vec<(NodeID, NodeID, f32)>
-    ) -> () {
-        // Sort edges and combine duplicates
-        edges.par_sort_unstable_by_key(|e| (e.0, e.1));
-        let mut i = 0;
-        let mut j = 0;
-        while j < edges.len() {
-            let (from_node, to_node, _) = edges[j];
-            let mut w = 0f32;
-            while j < edges.len() && edges[j].0 == from_node && edges[j].1 == to_node {
-                w += edges[j].2;
-                j += 1
-            }
-            edges[i] = (from_node, to_node, w);
-            i += 1;
-        }
-        edges.truncate(i);
-    }
 
+    /// Infers the field delimiter from the file extension: CSVs use commas, everything else tabs.
+    pub fn default_delimiter(path: &str) -> char {
+        let path = path.strip_suffix(".gz").unwrap_or(path);
+        if path.ends_with(".csv") { ',' } else { '\t' }
+    }
+    
     pub fn load(
         path: &str, 
         edge_type: EdgeType,
         chunk_size: usize,
         skip_rows: usize,
-        weighted: bool
+        weighted: bool,
+        delimiter: char,
+        normalization: Normalization
     ) -> PyResult<(Vocab,CumCSR)> {
         let reader = open_file_for_reading(path)
             .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?
             .lines().map(|l| l.unwrap());
 
-        let mut vocab = Vocab::new();
+        let mut vocab = Vocab::with_normalization(normalization);
         let mut edges = Vec::new();
+        let mut compact_at = EDGE_COMPACTION_SIZE;
         let rr = RecordReader::new(chunk_size, skip_rows);
         rr.read(reader,
             |i, line| {
-                let pieces: Vec<_> = line.split('\t').collect();
+                let pieces: Vec<_> = line.trim_end_matches('\r').split(delimiter).collect();
                 if pieces.len() != 5 {
                     return Some(Err(PyValueError::new_err(format!("{}: Malformed graph file: Expected 5 fields!", i))))
                 }
@@ -318,13 +1133,1013 @@ impl GraphReader {
                 if matches!(edge_type, EdgeType::Undirected) {
                     edges.push((t_id, f_id, w));
                 }
+                if edges.len() >= compact_at {
+                    compact_edges(&mut edges, EdgeMerge::Sum);
+                    compact_at = (edges.len() * 2).max(EDGE_COMPACTION_SIZE);
+                }
                 Ok::<(), PyErr>(())
             })?;
 
-        GraphReader::deduplicate_edges(&mut edges);
+        compact_edges(&mut edges, EdgeMerge::Sum);
 
         let csr = CSR::construct_from_edges(edges);
 
         Ok((vocab, CumCSR::convert(csr)))
     }
 }
+
+fn malformed(msg: String) -> PyErr {
+    PyValueError::new_err(msg)
+}
+
+/// Reads Matrix Market coordinate files, as distributed by SuiteSparse and SNAP.  Row and column
+/// indices become node names; when the row and column types match they share a namespace.
+pub struct MatrixMarketReader;
+
+impl MatrixMarketReader {
+    pub fn load(path: &str, row_type: &str, col_type: &str) -> PyResult<(Vocab,CumCSR)> {
+        let reader = open_file_for_reading(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        let mut lines = reader.lines().map(|l| l.map_err(|e| PyIOError::new_err(format!("{:?}", e))));
+
+        let header = lines.next().unwrap_or_else(|| Ok(String::new()))?.to_lowercase();
+        let fields: Vec<_> = header.split_whitespace().collect();
+        if fields.len() != 5 || fields[0] != "%%matrixmarket" || fields[1] != "matrix" || fields[2] != "coordinate" {
+            return Err(malformed(format!("Unsupported Matrix Market header: {}", header)))
+        }
+        let pattern = match fields[3] {
+            "real" | "integer" => false,
+            "pattern" => true,
+            f => return Err(malformed(format!("Unsupported Matrix Market field: {}", f)))
+        };
+        let symmetric = match fields[4] {
+            "general" => false,
+            "symmetric" => true,
+            s => return Err(malformed(format!("Unsupported Matrix Market symmetry: {}", s)))
+        };
+
+        let mut lines = lines.filter(|l| {
+            l.as_ref().map(|l| !(l.starts_with('%') || l.trim().is_empty())).unwrap_or(true)
+        });
+        let size = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        let size: Vec<usize> = size.split_whitespace()
+            .map(|s| s.parse::<usize>())
+            .collect::<Result<_,_>>()
+            .map_err(|e| malformed(format!("Malformed size line: {}", e)))?;
+        if size.len() != 3 {
+            return Err(malformed("Size line must have rows, columns, and entries".to_string()))
+        }
+        if symmetric && size[0] != size[1] {
+            return Err(malformed("Symmetric matrices must be square".to_string()))
+        }
+
+        // Register every index up front so isolated nodes are kept and ids follow index order
+        let mut vocab = Vocab::new();
+        let row_type = Arc::new(row_type.to_string());
+        let col_type = Arc::new(col_type.to_string());
+        let rows: Vec<_> = (1..=size[0])
+            .map(|i| vocab.get_or_insert_shared(row_type.clone(), &i.to_string()))
+            .collect();
+        let cols: Vec<_> = (1..=size[1])
+            .map(|i| vocab.get_or_insert_shared(col_type.clone(), &i.to_string()))
+            .collect();
+
+        let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+        builder.set_num_nodes(vocab.len());
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let pieces: Vec<_> = line.split_whitespace().collect();
+            let expected = if pattern { 2 } else { 3 };
+            if pieces.len() != expected {
+                return Err(malformed(format!("{}: Malformed entry: {}", i, line)))
+            }
+            let index = |s: &str, len: usize| {
+                s.parse::<usize>().ok()
+                    .and_then(|idx| idx.checked_sub(1))
+                    .filter(|idx| *idx < len)
+                    .ok_or_else(|| malformed(format!("{}: Index out of bounds: {}", i, s)))
+            };
+            let row = index(pieces[0], rows.len())?;
+            let col = index(pieces[1], cols.len())?;
+            let w = if pattern { 1. } else {
+                pieces[2].parse::<f32>()
+                    .map_err(|e| malformed(format!("{}: Malformed weight! {}", i, e)))?
+            };
+            builder.add_edge(rows[row], cols[col], w);
+            // Only one triangle is stored, so entry (i, j) also stands for (j, i)
+            if symmetric && row != col {
+                builder.add_edge(rows[col], cols[row], w);
+            }
+        }
+
+        Ok((vocab, builder.finalize()))
+    }
+}
+
+/// A start or end tag from an XML document
+struct XmlTag<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, String)>,
+    closing: bool,
+    self_closing: bool
+}
+
+impl <'a> XmlTag<'a> {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Minimal scanner over the tags of an XML document, enough for GraphML.  Returns the text
+/// preceding the tag along with the tag, skipping comments, declarations, and processing
+/// instructions.
+fn next_xml_tag<'a>(doc: &'a str, pos: &mut usize) -> PyResult<Option<(&'a str, XmlTag<'a>)>> {
+    loop {
+        let start = match doc[*pos..].find('<') {
+            Some(offset) => *pos + offset,
+            None => return Ok(None)
+        };
+        let text = &doc[*pos..start];
+        let rest = &doc[start..];
+        let skip_to = |end: &str| rest.find(end).map(|e| start + e + end.len()).unwrap_or(doc.len());
+        if rest.starts_with("<!--") {
+            *pos = skip_to("-->");
+            continue
+        } else if rest.starts_with("<?") {
+            *pos = skip_to("?>");
+            continue
+        } else if rest.starts_with("<!") {
+            *pos = skip_to(">");
+            continue
+        }
+
+        // Quoted attribute values may themselves contain '>'
+        let mut quote = None;
+        let end = rest.char_indices().find(|(_, c)| match quote {
+            Some(q) => {
+                if *c == q { quote = None; }
+                false
+            },
+            None if *c == '"' || *c == '\'' => {
+                quote = Some(*c);
+                false
+            },
+            None => *c == '>'
+        });
+        let end = match end {
+            Some((offset, _)) => start + offset,
+            None => return Ok(None)
+        };
+        *pos = end + 1;
+        let mut body = &doc[start + 1..end];
+        let closing = body.starts_with('/');
+        if closing { body = &body[1..]; }
+        let self_closing = body.ends_with('/');
+        if self_closing { body = &body[..body.len() - 1]; }
+
+        let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let name = &body[..name_end];
+        let mut attrs = Vec::new();
+        let mut rem = &body[name_end..];
+        while let Some(eq) = rem.find('=') {
+            let key = rem[..eq].trim();
+            let after = rem[eq + 1..].trim_start();
+            let quote = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(malformed(format!("Unquoted value for attribute '{}' in <{}>!", key, name)))
+            };
+            let value_end = after[1..].find(quote)
+                .ok_or_else(|| malformed(format!("Unterminated value for attribute '{}' in <{}>!", key, name)))? + 1;
+            attrs.push((key, unescape_xml(&after[1..value_end])));
+            rem = &after[value_end + 1..];
+        }
+        return Ok(Some((text, XmlTag { name, attrs, closing, self_closing })))
+    }
+}
+
+/// Reads GraphML documents.  Edge weights are read from the edge data key named "weight", if
+/// declared, defaulting to 1.  Undirected edges, by graph default or per edge, are added in both
+/// directions.
+pub struct GraphMLReader;
+
+impl GraphMLReader {
+    pub fn load(path: &str, node_type: &str) -> PyResult<(Vocab,CumCSR)> {
+        let mut doc = String::new();
+        open_file_for_reading(path)
+            .and_then(|mut r| r.read_to_string(&mut doc))
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let node_type = Arc::new(node_type.to_string());
+        let mut vocab = Vocab::new();
+        let mut builder = GraphBuilder::new(EdgeMerge::Sum);
+        let mut weight_key: Option<String> = None;
+        let mut directed_default = true;
+
+        // Edge currently being parsed: source, target, directed, weight
+        let mut edge: Option<(NodeID, NodeID, bool, f32)> = None;
+        let mut in_weight = false;
+
+        let mut pos = 0;
+        while let Some((text, tag)) = next_xml_tag(&doc, &mut pos)? {
+            if in_weight {
+                if let Some(e) = edge.as_mut() {
+                    e.3 = text.trim().parse::<f32>()
+                        .map_err(|e| malformed(format!("Malformed edge weight! {}", e)))?;
+                }
+                in_weight = false;
+            }
+
+            match (tag.name, tag.closing) {
+                ("key", false) => {
+                    let for_edges = matches!(tag.attr("for"), Some("edge") | Some("all"));
+                    if for_edges && tag.attr("attr.name") == Some("weight") {
+                        weight_key = tag.attr("id").map(|id| id.to_string());
+                    }
+                },
+                ("graph", false) => {
+                    directed_default = tag.attr("edgedefault") != Some("undirected");
+                },
+                ("node", false) => {
+                    let id = tag.attr("id")
+                        .ok_or_else(|| malformed("Node missing id!".to_string()))?;
+                    vocab.get_or_insert_shared(node_type.clone(), id);
+                },
+                ("edge", false) => {
+                    let (source, target) = match (tag.attr("source"), tag.attr("target")) {
+                        (Some(s), Some(t)) => (s, t),
+                        _ => return Err(malformed("Edge missing source or target!".to_string()))
+                    };
+                    let f_id = vocab.get_or_insert_shared(node_type.clone(), source);
+                    let t_id = vocab.get_or_insert_shared(node_type.clone(), target);
+                    let directed = tag.attr("directed")
+                        .map(|d| d == "true")
+                        .unwrap_or(directed_default);
+                    edge = Some((f_id, t_id, directed, 1.));
+                },
+                ("data", false) if edge.is_some() && !tag.self_closing => {
+                    in_weight = weight_key.is_some() && tag.attr("key") == weight_key.as_deref();
+                },
+                _ => ()
+            }
+
+            let edge_done = tag.name == "edge" && (tag.closing || tag.self_closing);
+            if edge_done {
+                if let Some((f_id, t_id, directed, w)) = edge.take() {
+                    builder.add_edge(f_id, t_id, w);
+                    if !directed && f_id != t_id {
+                        builder.add_edge(t_id, f_id, w);
+                    }
+                }
+            }
+        }
+
+        builder.set_num_nodes(vocab.len());
+        Ok((vocab, builder.finalize()))
+    }
+}
+
+/// Number of unknown nodes kept as examples in a FeatureLoadReport
+const MAX_UNKNOWN_EXAMPLES: usize = 10;
+
+/// Outcome of loading a feature file
+#[derive(Debug, Default)]
+pub struct FeatureLoadReport {
+    /// Number of records whose features were set
+    pub loaded: usize,
+
+    /// Number of records naming nodes missing from the vocab
+    pub unknown: usize,
+
+    /// The first few unknown nodes as (record, node_type, node_name), to help track down
+    /// mismatched vocabs
+    pub unknown_examples: Vec<(usize, String, String)>
+}
+
+/// Streams node features into a FeatureStore keyed by an existing vocab.  Records are parsed in
+/// parallel chunks and are either TSV lines of `node_type<TAB>node_name<TAB>f1 f2 ...` or, for
+/// .jsonl/.json files, JSON objects such as
+/// `{"node_type": "user", "node_name": "u1", "features": ["token:red", "brand:acme"]}`, where
+/// features may also be a single whitespace delimited string.  Later records for a node replace
+/// earlier ones.
+pub struct FeatureReader;
+
+impl FeatureReader {
+
+    pub fn is_json_lines(path: &str) -> bool {
+        let path = path.strip_suffix(".gz").unwrap_or(path);
+        path.ends_with(".jsonl") || path.ends_with(".json")
+    }
+
+    /// Loads features for nodes in `vocab`.  Unknown nodes are counted and skipped unless
+    /// `strict` is set, in which case they're an error like malformed records.
+    pub fn load(
+        path: &str,
+        vocab: &Vocab,
+        features: &mut FeatureStore,
+        chunk_size: usize,
+        skip_rows: usize,
+        strict: bool
+    ) -> PyResult<FeatureLoadReport> {
+        let reader = open_file_for_reading(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?
+            .lines().map(|l| l.unwrap());
+
+        let json = FeatureReader::is_json_lines(path);
+        let mut report = FeatureLoadReport::default();
+        let rr = RecordReader::new(chunk_size, skip_rows);
+        rr.read(reader,
+            |i, line| {
+                let line = line.trim_end_matches('\r');
+                if line.trim().is_empty() { return None }
+
+                let record = if json {
+                    parse_json_features(line)
+                } else {
+                    parse_tsv_features(line)
+                };
+                Some(record
+                    .map(|(node_type, name, feats)| {
+                        let node_id = vocab.get_node_id(node_type.clone(), name.clone());
+                        (node_id, node_type, name, feats)
+                    })
+                    .map_err(|e| PyValueError::new_err(format!("{}: Malformed feature record! {}", i, e))))
+            },
+            |i, record| {
+                let (node_id, node_type, name, feats) = record?;
+                match node_id {
+                    Some(node_id) => {
+                        features.set_features(node_id, feats);
+                        report.loaded += 1;
+                    },
+                    None if strict => {
+                        return Err(PyKeyError::new_err(format!("{}: Node {}:{} not in vocab!", i, node_type, name)))
+                    },
+                    None => {
+                        if report.unknown_examples.len() < MAX_UNKNOWN_EXAMPLES {
+                            report.unknown_examples.push((i, node_type, name));
+                        }
+                        report.unknown += 1;
+                    }
+                }
+                Ok::<(), PyErr>(())
+            })?;
+
+        features.compact();
+        Ok(report)
+    }
+}
+
+fn parse_tsv_features(line: &str) -> Result<(String, String, Vec<String>), String> {
+    let pieces: Vec<_> = line.splitn(3, '\t').collect();
+    if pieces.len() != 3 {
+        return Err("Need node_type<TAB>name<TAB>f1 f2 ...".to_string())
+    }
+    let feats = pieces[2].split_whitespace().map(|f| f.to_string()).collect();
+    Ok((pieces[0].to_string(), pieces[1].to_string(), feats))
+}
+
+fn parse_json_features(line: &str) -> Result<(String, String, Vec<String>), String> {
+    let mut chars = line.chars().peekable();
+    let fields = match parse_json_value(&mut chars)? {
+        JsonValue::Object(fields) => fields,
+        _ => return Err("Expected a JSON object".to_string())
+    };
+    skip_json_whitespace(&mut chars);
+    if chars.peek().is_some() {
+        return Err("Trailing characters after JSON object".to_string())
+    }
+
+    let mut node_type = None;
+    let mut name = None;
+    let mut feats = Vec::new();
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("node_type", JsonValue::Str(s)) => node_type = Some(s),
+            ("node_name", JsonValue::Str(s)) => name = Some(s),
+            ("features", JsonValue::Str(s)) => {
+                feats = s.split_whitespace().map(|f| f.to_string()).collect();
+            },
+            ("features", JsonValue::Array(values)) => {
+                feats = values.into_iter().map(|v| match v {
+                    JsonValue::Str(s) => Ok(s),
+                    _ => Err("Features must be strings".to_string())
+                }).collect::<Result<_,_>>()?;
+            },
+            ("node_type", _) | ("node_name", _) | ("features", _) => {
+                return Err(format!("Unexpected value type for {}", key))
+            },
+            _ => {}
+        }
+    }
+    match (node_type, name) {
+        (Some(node_type), Some(name)) => Ok((node_type, name, feats)),
+        _ => Err("Missing node_type or node_name".to_string())
+    }
+}
+
+/// Just enough JSON for feature records.  Numbers, booleans, and nulls are only validated since
+/// they're never used; numbers go through Rust's float parser, which tolerates a few forms JSON
+/// doesn't, such as leading zeros.
+enum JsonValue {
+    Str(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+    Scalar
+}
+
+type JsonChars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_json_whitespace(chars: &mut JsonChars) {
+    while chars.peek().map(|c| matches!(c, ' ' | '\t' | '\n' | '\r')).unwrap_or(false) {
+        chars.next();
+    }
+}
+
+fn parse_json_value(chars: &mut JsonChars) -> Result<JsonValue, String> {
+    skip_json_whitespace(chars);
+    match chars.peek() {
+        Some('"') => parse_json_string(chars).map(JsonValue::Str),
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_json_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(JsonValue::Array(values))
+            }
+            loop {
+                values.push(parse_json_value(chars)?);
+                skip_json_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(JsonValue::Array(values)),
+                    _ => return Err("Expected ',' or ']'".to_string())
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_json_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(JsonValue::Object(fields))
+            }
+            loop {
+                skip_json_whitespace(chars);
+                let key = parse_json_string(chars)?;
+                skip_json_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err("Expected ':'".to_string())
+                }
+                fields.push((key, parse_json_value(chars)?));
+                skip_json_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(JsonValue::Object(fields)),
+                    _ => return Err("Expected ',' or '}'".to_string())
+                }
+            }
+        },
+        Some(c) if *c == '-' || c.is_ascii_alphanumeric() => {
+            let mut token = String::new();
+            while let Some(c) = chars.peek().filter(|c| **c == '-' || **c == '+' || **c == '.' || c.is_ascii_alphanumeric()) {
+                token.push(*c);
+                chars.next();
+            }
+            let is_number = token.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+                && token.parse::<f64>().is_ok();
+            if is_number || token == "true" || token == "false" || token == "null" {
+                Ok(JsonValue::Scalar)
+            } else {
+                Err(format!("Invalid value: {}", token))
+            }
+        },
+        _ => Err("Unexpected character".to_string())
+    }
+}
+
+fn parse_json_string(chars: &mut JsonChars) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("Expected a string".to_string())
+    }
+    let mut out = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(out),
+            Some('\\') => match chars.next() {
+                Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => out.push(c),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('b') => out.push('\u{8}'),
+                Some('f') => out.push('\u{c}'),
+                Some('u') => {
+                    let mut code = parse_json_hex(chars)?;
+                    // Characters outside the BMP are escaped as surrogate pairs
+                    if (0xD800..0xDC00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("Unpaired high surrogate".to_string())
+                        }
+                        let low = parse_json_hex(chars)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err("High surrogate not followed by a low surrogate".to_string())
+                        }
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                    } else if (0xDC00..0xE000).contains(&code) {
+                        return Err("Unpaired low surrogate".to_string())
+                    }
+                    out.push(char::from_u32(code).ok_or_else(|| "Invalid unicode escape".to_string())?);
+                },
+                Some(c) => return Err(format!("Invalid escape: \\{}", c)),
+                None => return Err("Unterminated string".to_string())
+            },
+            Some(c) if (c as u32) < 0x20 => return Err("Unescaped control character in string".to_string()),
+            Some(c) => out.push(c),
+            None => return Err("Unterminated string".to_string())
+        }
+    }
+}
+
+fn parse_json_hex(chars: &mut JsonChars) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    if hex.len() != 4 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid unicode escape: {}", hex))
+    }
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("Invalid unicode escape: {}", hex))
+}
+
+#[cfg(test)]
+mod io_tests {
+    use super::*;
+
+    fn write_temp(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_vocab_serializer() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("user".into(), "alice".into());
+        vocab.get_or_insert("item".into(), "alice".into());
+        vocab.get_or_insert("user".into(), "bob smith".into());
+
+        let check = |loaded: &Vocab| {
+            assert_eq!(loaded.len(), 3);
+            assert_eq!(loaded.node_types(), vocab.node_types());
+            (0..3).for_each(|node_id| {
+                assert_eq!(loaded.get_name(node_id), vocab.get_name(node_id));
+                assert_eq!(loaded.get_node_type_id_of(node_id), vocab.get_node_type_id_of(node_id));
+            });
+        };
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-vocab.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        VocabSerializer::save(path, &vocab).unwrap();
+        check(&VocabSerializer::load(path).unwrap());
+        std::fs::remove_file(path).unwrap();
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-vocab.tsv", std::process::id()));
+        let path = path.to_str().unwrap();
+        VocabSerializer::save_tsv(path, &vocab, None).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "user\talice\nitem\talice\nuser\tbob smith\n");
+        check(&VocabSerializer::load_tsv(path, Normalization::default()).unwrap());
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_temp("vocab-dup.tsv", "user\ta\nuser\ta\n");
+        assert!(VocabSerializer::load_tsv(&path, Normalization::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        // Names which only differ by normalization collapse into one node
+        let norm = Normalization { lowercase: true, ..Normalization::default() };
+        let path = write_temp("vocab-norm.tsv", "user\tA\nuser\ta\n");
+        assert!(VocabSerializer::load_tsv(&path, norm).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let mut normalized = Vocab::with_normalization(norm);
+        normalized.get_or_insert("user".into(), "Alice".into());
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-vocab-norm.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        VocabSerializer::save(path, &normalized).unwrap();
+        let loaded = VocabSerializer::load(path).unwrap();
+        assert_eq!(loaded.normalization(), norm);
+        assert_eq!(loaded.get_node_id("user".into(), "ALICE".into()), Some(0));
+
+        let mut bad = Vocab::new();
+        bad.get_or_insert("user".into(), "a\tb".into());
+        assert!(VocabSerializer::save_tsv(&path, &bad, None).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_graph_serializer() {
+        let mut vocab = Vocab::with_normalization(Normalization { lowercase: true, ..Normalization::default() });
+        let edges = [("a", "b", 1.), ("a", "c", 3.), ("b", "c", 2.)].iter().map(|(f, t, w)| {
+            let f = vocab.get_or_insert("node".into(), f.to_string());
+            let t = vocab.get_or_insert("node".into(), t.to_string());
+            (f, t, *w)
+        }).collect::<Vec<_>>();
+        let graph = CumCSR::convert(CSR::construct_from_edges(edges));
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-graph.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        GraphSerializer::save(path, &graph, &vocab).unwrap();
+        let (loaded_vocab, loaded) = GraphSerializer::load(path).unwrap();
+        assert_eq!(loaded_vocab.len(), 3);
+        assert_eq!(loaded_vocab.normalization(), vocab.normalization());
+        assert_eq!(loaded_vocab.get_node_id("node".into(), "B".into()), Some(1));
+        assert_eq!(loaded.len(), graph.len());
+        (0..graph.len()).for_each(|node_id| {
+            assert_eq!(loaded_vocab.get_name(node_id), vocab.get_name(node_id));
+            assert_eq!(loaded.get_edges(node_id), graph.get_edges(node_id));
+        });
+
+        // A flipped byte fails the checksum, and a truncated file or a corrupt length prefix
+        // errors instead of allocating
+        let bytes = std::fs::read(path).unwrap();
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 5;
+        flipped[last] ^= 0xff;
+        std::fs::write(path, &flipped).unwrap();
+        assert_eq!(GraphSerializer::load(path).err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+
+        std::fs::write(path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(GraphSerializer::load(path).is_err());
+
+        // The first node type's length follows the magic, normalization flags, and number of types
+        let mut corrupt = bytes.clone();
+        let offset = GRAPH_MAGIC.len() + 3 + 8;
+        corrupt[offset..offset + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        std::fs::write(path, &corrupt).unwrap();
+        assert_eq!(GraphSerializer::load(path).err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_feature_bundle() {
+        let norm = Normalization { nfkc: true, trim: true, ..Normalization::default() };
+        let mut node_vocab = Vocab::with_normalization(norm);
+        node_vocab.get_or_insert("n".into(), "a".into());
+        node_vocab.get_or_insert("n".into(), "b".into());
+
+        let mut fs = FeatureStore::new(2, "feat".to_string());
+        fs.add_namespace("brand".to_string(), Some(1));
+        fs.set_features(0, vec!["brand:acme".into(), "red".into()]);
+        fs.set_features(1, vec!["red".into()]);
+        fs.compute_tf_idf();
+
+        let feat_vocab = fs.clone_vocab();
+        let mut es = EmbeddingStore::new(feat_vocab.len(), 2, Distance::Euclidean);
+        es.set_embedding(1, &[0.5, -1.]);
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-bundle.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        FeatureBundle::save(path, &node_vocab, &fs, Some((&feat_vocab, &es))).unwrap();
+        let (new_vocab, new_fs, embeddings) = FeatureBundle::load(path).unwrap();
+
+        assert_eq!(new_vocab.len(), 2);
+        assert_eq!(new_vocab.get_node_id("n".into(), " b ".into()), Some(1));
+        assert_eq!(new_vocab.normalization(), norm);
+        assert_eq!(new_fs.get_vocab().normalization(), Normalization::default());
+        assert_eq!(new_fs.get_pretty_features(0), vec!["brand:acme", "red"]);
+        assert_eq!(new_fs.get_features(1), fs.get_features(1));
+        assert_eq!(new_fs.get_namespaces()[0].1, Some(1));
+        assert_eq!(new_fs.get_feature_weights(0), fs.get_feature_weights(0));
+        let (new_feat_vocab, new_es) = embeddings.unwrap();
+        assert_eq!(new_feat_vocab.get_name(1), feat_vocab.get_name(1));
+        assert_eq!(new_es.get_embedding(1), &[0.5, -1.]);
+
+        // Bundles without embeddings round trip, and corruption is caught
+        FeatureBundle::save(path, &node_vocab, &fs, None).unwrap();
+        assert!(FeatureBundle::load(path).unwrap().2.is_none());
+        let mut bytes = std::fs::read(path).unwrap();
+        let last = bytes.len() - 6;
+        bytes[last] ^= 1;
+        std::fs::write(path, bytes).unwrap();
+        assert!(FeatureBundle::load(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_json_features() {
+        let (node_type, name, feats) = parse_json_features(
+            r#"{"node_type": "user", "node_name": "a\"b\/\ud83d\ude00", "features": ["x"], "n": null}"#).unwrap();
+        assert_eq!((node_type.as_str(), name.as_str()), ("user", "a\"b/\u{1F600}"));
+        assert_eq!(feats, vec!["x"]);
+
+        let malformed = [
+            r#"{"node_type": "user", "node_name": "\ud83d"}"#,
+            r#"{"node_type": "user", "node_name": "\ud83d\u0041"}"#,
+            r#"{"node_type": "user", "node_name": "\ude00"}"#,
+            r#"{"node_type": "user", "node_name": "\u12"}"#,
+            r#"{"node_type": "user", "node_name": "\u+123"}"#,
+            r#"{"node_type": "user", "node_name": "\x"}"#,
+            "{\"node_type\": \"user\", \"node_name\": \"a\tb\"}",
+            r#"{"node_type": "user", "node_name": "a", "n": nope}"#,
+            r#"{"node_type": "user", "node_name": "a", "n": 1.2.3}"#,
+            r#"{"node_type": "user", "node_name": "a"} extra"#,
+            r#"["node_type", "user"]"#,
+        ];
+        for line in malformed.iter() {
+            assert!(parse_json_features(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_lengths() {
+        let mut buf = Vec::new();
+        write_f32s(&mut buf, &[1., 2.]).unwrap();
+        write_node_ids(&mut buf, &[3, 4, 5]).unwrap();
+        let mut r = buf.as_slice();
+        assert_eq!(read_f32s(&mut r).unwrap(), vec![1., 2.]);
+        assert_eq!(read_node_ids(&mut r).unwrap(), vec![3, 4, 5]);
+
+        // Lengths past the end of the data, or which overflow, error rather than allocating
+        for n in [3, 1 << 40, usize::MAX].iter() {
+            let mut buf = Vec::new();
+            write_usize(&mut buf, *n).unwrap();
+            buf.extend_from_slice(&[0u8; 8]);
+            let err = read_f32s(&mut buf.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            let err = read_node_ids(&mut buf.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_graph_reader() {
+        // Repeated edges are summed
+        let path = write_temp("edges.csv", "n,a,n,b,1\nn,a,n,c,2\nn,a,n,b,1\n");
+        let (vocab, graph) = GraphReader::load(&path, EdgeType::Directed, 2, 0, true, ',', Normalization::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(vocab.len(), 3);
+        assert_eq!(graph.edges(), 2);
+        assert_eq!(graph.get_edges(0), (&[1usize, 2][..], &[0.5f32, 1.][..]));
+
+        let path = write_temp("edges.tsv", "n\ta\tn\tb\t1\nn\tb\tn\ta\t1\n");
+        let (_, graph) = GraphReader::load(&path, EdgeType::Undirected, 2, 0, false, '\t', Normalization::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(graph.edges(), 2);
+    }
+
+    #[test]
+    fn test_matrix_market() {
+        let path = write_temp("graph.mtx", "%%MatrixMarket matrix coordinate real symmetric\n\
+            % comment\n\
+            4 4 3\n\
+            2 1 0.5\n\
+            3 3 2\n\
+            4 1 1\n");
+        let (vocab, graph) = MatrixMarketReader::load(&path, "n", "n").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(vocab.len(), 4);
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.edges(), 5);
+        let one = vocab.get_node_id("n".into(), "1".into()).unwrap();
+        assert_eq!(graph.get_edges(one).0.len(), 2);
+
+        // Mirrored entries still go from rows to columns when they have different types
+        let path = write_temp("bipartite.mtx", "%%MatrixMarket matrix coordinate pattern symmetric\n\
+            2 2 2\n\
+            2 1\n\
+            2 2\n");
+        let (vocab, graph) = MatrixMarketReader::load(&path, "user", "item").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(graph.edges(), 3);
+        let id = |node_type: &str, name: &str| vocab.get_node_id(node_type.into(), name.into()).unwrap();
+        assert_eq!(graph.get_edges(id("user", "1")).0, &[id("item", "2")]);
+        assert_eq!(graph.get_edges(id("user", "2")).0, &[id("item", "1"), id("item", "2")]);
+        assert!(graph.get_edges(id("item", "1")).0.is_empty());
+
+        let path = write_temp("rect.mtx", "%%MatrixMarket matrix coordinate pattern symmetric\n2 3 0\n");
+        assert!(MatrixMarketReader::load(&path, "user", "item").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_graphml() {
+        let path = write_temp("graph.graphml", r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="edge" attr.name="weight" attr.type="double"/>
+              <graph id="G" edgedefault="undirected">
+                <!-- nodes -->
+                <node id="a"/>
+                <node id="b &amp; c"/>
+                <node id="d"/>
+                <edge source="a" target="b &amp; c"><data key="d0">2.5</data></edge>
+                <edge source="d" target="a" directed="true"/>
+              </graph>
+            </graphml>"#);
+        let (vocab, graph) = GraphMLReader::load(&path, "node").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(vocab.len(), 3);
+        assert_eq!(graph.edges(), 3);
+        let a = vocab.get_node_id("node".into(), "a".into()).unwrap();
+        let bc = vocab.get_node_id("node".into(), "b & c".into()).unwrap();
+        let d = vocab.get_node_id("node".into(), "d".into()).unwrap();
+        assert_eq!(graph.get_edges(a).0, &[bc]);
+        assert_eq!(graph.get_edges(bc).0, &[a]);
+        assert_eq!(graph.get_edges(d).0, &[a]);
+
+        // Quoted values can hold '>' without ending the tag
+        let path = write_temp("quoted.graphml", r#"<graphml><graph edgedefault="directed">
+            <node id='a>b'/><node id="c"/>
+            <edge source='a>b' target="c"/>
+            </graph></graphml>"#);
+        let (vocab, graph) = GraphMLReader::load(&path, "node").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(vocab.len(), 2);
+        let ab = vocab.get_node_id("node".into(), "a>b".into()).unwrap();
+        let c = vocab.get_node_id("node".into(), "c".into()).unwrap();
+        assert_eq!(graph.get_edges(ab).0, &[c]);
+
+        // Unquoted values are malformed, even when they start with a multibyte character
+        let path = write_temp("unquoted.graphml", "<graphml><graph><node id=é/></graph></graphml>");
+        assert!(GraphMLReader::load(&path, "node").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_feature_reader() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("user".into(), "u1".into());
+        vocab.get_or_insert("user".into(), "u2".into());
+
+        let path = write_temp("features.tsv", "user\tu1\ttoken:red brand:acme\n\
+            user\tu3\tred\n\
+            user\tu2\t\n");
+        let mut fs = FeatureStore::new(vocab.len(), "feat".to_string());
+        fs.add_namespace("brand".to_string(), None);
+        let report = FeatureReader::load(&path, &vocab, &mut fs, 2, 0, false).unwrap();
+        assert_eq!(report.loaded, 2);
+        assert_eq!(report.unknown, 1);
+        assert_eq!(report.unknown_examples, vec![(1, "user".to_string(), "u3".to_string())]);
+        assert_eq!(fs.get_pretty_features(0), vec!["token:red", "brand:acme"]);
+        assert!(fs.get_features(1).is_empty());
+
+        let mut fs = FeatureStore::new(vocab.len(), "feat".to_string());
+        assert!(FeatureReader::load(&path, &vocab, &mut fs, 2, 0, true).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let path = write_temp("features.jsonl", r#"{"node_type": "user", "node_name": "u1", "features": ["a b", "caf\u00e9"], "score": -1.5e3}
+            {"node_name": "u2", "extra": {"nested": [1, null, true]}, "node_type": "user", "features": "x y"}"#);
+        let mut fs = FeatureStore::new(vocab.len(), "feat".to_string());
+        let report = FeatureReader::load(&path, &vocab, &mut fs, 1, 0, true).unwrap();
+        assert_eq!(report.loaded, 2);
+        assert_eq!(fs.get_pretty_features(0), vec!["a b", "café"]);
+        assert_eq!(fs.get_pretty_features(1), vec!["x", "y"]);
+
+        let path_bad = write_temp("bad.jsonl", r#"{"node_type": "user", "node_name": "u1""#);
+        assert!(FeatureReader::load(&path_bad, &vocab, &mut fs, 1, 0, false).is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&path_bad).unwrap();
+    }
+
+    #[test]
+    fn test_parquet_embeddings() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("user".into(), "alice".into());
+        vocab.get_or_insert("item".into(), "book".into());
+        vocab.get_or_insert("user".into(), "bob".into());
+        let mut es = EmbeddingStore::new(3, 2, Distance::Euclidean);
+        (0..3).for_each(|i| es.set_embedding(i, &[i as f32, -0.5 * i as f32]));
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-embeddings.parquet", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // Smaller batches than nodes, so the file holds several record batches
+        let mut writer = ParquetEmbeddingWriter::new(path, &vocab, 2, 2).unwrap();
+        writer.stream((0..3).map(|i| (i, es.get_embedding(i).to_vec()))).unwrap();
+        writer.finish().unwrap();
+
+        let (loaded_vocab, loaded) = ParquetEmbeddingReader::load(path, Distance::Euclidean, None).unwrap();
+        assert_eq!(loaded_vocab.len(), 3);
+        assert_eq!(loaded.dims(), 2);
+        (0..3).for_each(|i| {
+            assert_eq!(loaded_vocab.get_name(i), vocab.get_name(i));
+            assert_eq!(loaded.get_embedding(i), es.get_embedding(i));
+        });
+
+        let (users, loaded) = ParquetEmbeddingReader::load(path, Distance::Cosine, Some("user".into())).unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users.get_node_id("user".into(), "bob".into()), Some(1));
+        assert_eq!(loaded.get_embedding(1), es.get_embedding(2));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parquet_graph_reader() {
+        let str_col = |vals: &[&str]| -> ArrayRef { Arc::new(StringArray::from(vals.to_vec())) };
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("src_type", DataType::Utf8, false),
+            Field::new("src_name", DataType::Utf8, false),
+            Field::new("dst_type", DataType::Utf8, false),
+            Field::new("dst_name", DataType::Utf8, false),
+            Field::new("w", DataType::Float64, false)
+        ]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![
+            str_col(&["u", "u", "u", "u"]),
+            str_col(&["a", "b", "a", "a"]),
+            str_col(&["i", "i", "i", "i"]),
+            str_col(&["x", "x", "x", "y"]),
+            Arc::new(Float64Array::from(vec![1., 2., 3., 1.]))
+        ]).unwrap();
+
+        // Two rows per row group, so decoding spans several
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-edges.parquet", std::process::id()));
+        let path = path.to_str().unwrap();
+        let props = parquet::file::properties::WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), schema, Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let columns = EdgeColumns { weight: Some("w".to_string()), ..EdgeColumns::default() };
+        let (vocab, graph) = ParquetGraphReader::load(path, &columns, EdgeType::Directed, EdgeMerge::Sum).unwrap();
+        assert_eq!(vocab.len(), 4);
+        assert_eq!(vocab.get_node_id("i".into(), "y".into()), Some(3));
+        assert_eq!(graph.edges(), 3);
+        assert_eq!(graph.get_edges(0), (&[1usize, 3][..], &[0.8f32, 1.][..]));
+        assert_eq!(graph.get_edges(2).0, &[1]);
+
+        let (_, graph) = ParquetGraphReader::load(path, &columns, EdgeType::Undirected, EdgeMerge::Max).unwrap();
+        assert_eq!(graph.edges(), 6);
+        assert_eq!(graph.get_edges(1).0, &[0, 2]);
+
+        // The default weight column doesn't exist
+        assert!(ParquetGraphReader::load(path, &EdgeColumns::default(), EdgeType::Directed, EdgeMerge::Sum).is_err());
+        std::fs::remove_file(path).unwrap();
+
+        // Nulls in any column are rejected
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("src_type", DataType::Utf8, false),
+            Field::new("src_name", DataType::Utf8, false),
+            Field::new("dst_type", DataType::Utf8, false),
+            Field::new("dst_name", DataType::Utf8, true),
+            Field::new("w", DataType::Float64, true)
+        ]));
+        let nulls = [
+            (StringArray::from(vec![Some("x"), None]), Float64Array::from(vec![Some(1.), Some(2.)])),
+            (StringArray::from(vec![Some("x"), Some("y")]), Float64Array::from(vec![Some(1.), None]))
+        ];
+        for (dst_names, weights) in nulls {
+            let batch = RecordBatch::try_new(schema.clone(), vec![
+                str_col(&["u", "u"]),
+                str_col(&["a", "b"]),
+                str_col(&["i", "i"]),
+                Arc::new(dst_names),
+                Arc::new(weights)
+            ]).unwrap();
+            let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), schema.clone(), None).unwrap();
+            writer.write(&batch).unwrap();
+            writer.close().unwrap();
+            assert!(ParquetGraphReader::load(path, &columns, EdgeType::Directed, EdgeMerge::Sum).is_err());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_safetensors() {
+        let mut nodes = EmbeddingStore::new(3, 2, Distance::Euclidean);
+        (0..3).for_each(|i| nodes.set_embedding(i, &[i as f32, 1. / (i + 1) as f32]));
+        let mut moments = EmbeddingStore::new(2, 4, Distance::Dot);
+        moments.set_embedding(1, &[-1., 0., f32::MAX, 1e-30]);
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-stores.safetensors", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_safetensors(path, &[("nodes", &nodes), ("moments", &moments)]).unwrap();
+
+        let stores = load_safetensors(path).unwrap();
+        assert_eq!(stores.len(), 2);
+        for (name, es) in [("nodes", &nodes), ("moments", &moments)] {
+            let loaded = &stores[name];
+            assert_eq!(format!("{:?}", loaded.distance()), format!("{:?}", es.distance()));
+            assert_eq!((loaded.len(), loaded.dims()), (es.len(), es.dims()));
+            assert_eq!(loaded.as_slice(), es.as_slice());
+        }
+
+        // Stores must say which distance they use
+        for metadata in [None, Some(CHashMap::from([("t.distance".to_string(), "Manhattan".to_string())]))] {
+            let view = TensorView::new(Dtype::F32, vec![1, 1], &[0u8; 4]).unwrap();
+            serialize_to_file(vec![("t", view)], &metadata, Path::new(path)).unwrap();
+            assert_eq!(load_safetensors(path).err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -6,6 +6,12 @@
 /// Main interface for defining graphs
 pub mod graph;
 
+/// Read-only graphs memory mapped from disk
+pub mod mmap_graph;
+
+/// Graphs partitioned by node type, such as users and items
+pub mod bipartite;
+
 /// We define all the algorithms within this module
 pub mod algos;
 
@@ -13,7 +19,7 @@ pub mod algos;
 mod sampler;
 
 /// Maps node types, node names to internal IDs and back
-mod vocab;
+pub mod vocab;
 
 /// Where we store embeddings.  These are both node and feature embeddings
 mod embeddings;
@@ -21,6 +27,9 @@ mod embeddings;
 /// Simple bitset
 mod bitset;
 
+/// SIMD kernels for distance computations
+mod simd;
+
 /// This interface allows us to update embeddings (and other structures) in multiple threads
 /// without having to gain exclusive write access.  Do _not_ clone hogwild structures as they
 /// will still point to the underlying data
@@ -32,12 +41,19 @@ mod progress;
 /// Mapping from nodes -> features
 mod feature_store;
 
+/// Splits raw text into features
+mod tokenizer;
+
+/// Larger-than-RAM embeddings, sharded across files on disk
+mod sharded_store;
+
 /// Beginnings of refactoring out IO operations for efficient loading/writing of different data
 /// structures
 mod io;
 
 use std::sync::Arc;
 use std::ops::Deref;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Write,BufWriter,BufReader,BufRead};
 
@@ -50,31 +66,70 @@ use rand::prelude::*;
 use rand_xorshift::XorShiftRng;
 use rand_distr::Uniform;
 
-use crate::graph::{CSR,CumCSR,Graph as CGraph,NodeID,CDFtoP};
-use crate::vocab::Vocab;
+use crate::graph::{CSR,CumCSR,Graph as CGraph,NodeID,CDFtoP,RelationalGraph};
+use crate::graph::{GraphBuilder as CGraphBuilder,EdgeMerge as EEdgeMerge,SymmetricWeight as ESymmetricWeight};
+use crate::vocab::{Vocab,Normalization};
 use crate::sampler::{Weighted,Unweighted};
-use crate::embeddings::{EmbeddingStore,Distance as EDist,Entity};
-use crate::feature_store::FeatureStore;
-use crate::io::{EmbeddingWriter,EmbeddingReader,GraphReader,open_file_for_reading,open_file_for_writing};
+use crate::embeddings::{EmbeddingStore,Distance as EDist,Entity,MergeStrategy as EMergeStrategy};
+use crate::feature_store::{FeatureStore,MissingFeatures as EMissingFeatures};
+use crate::tokenizer::Tokenizer as CTokenizer;
+use crate::io::{EmbeddingWriter,EmbeddingReader,GraphReader,GraphSerializer,open_file_for_writing};
+use crate::io::{MatrixMarketReader,GraphMLReader,FeatureReader,FeatureBundle};
+use crate::io::{ParquetEmbeddingWriter,ParquetEmbeddingReader,ParquetGraphReader,EdgeColumns};
+use crate::sharded_store::{ShardedEmbeddingStore,write_sharded_vocab,read_sharded_vocab};
 
 use crate::algos::rwr::{Steps,RWR,ppr_estimate,rollout};
+use crate::algos::salsa::Salsa;
+use crate::algos::simrank::SimRank as CSimRank;
+use crate::algos::resistance::{EffectiveResistance as CEffectiveResistance,ResistanceSketch as CResistanceSketch};
 use crate::algos::grwr::{Steps as GSteps,GuidedRWR};
 use crate::algos::reweighter::{Reweighter};
-use crate::algos::ep::{EmbeddingPropagation,LossWeighting as EPLW};
+use crate::algos::ep::{EmbeddingPropagation,LossWeighting as EPLW,FeatureInit as EFeatureInit,extend_feature_embeddings};
 use crate::algos::ep::loss::Loss;
-use crate::algos::ep::model::{AveragedFeatureModel,AttentionFeatureModel};
+use crate::algos::ep::model::{AveragedFeatureModel,AttentionFeatureModel,NamespaceAggregation};
 use crate::algos::ep::attention::{AttentionType,MultiHeadedAttention};
+use crate::algos::ep::supervised::{NodeLabels,Classifier};
 use crate::algos::graph_ann::NodeDistance;
 use crate::algos::aggregator::{WeightedAggregator,UnigramProbability,AvgAggregator,AttentionAggregator, EmbeddingBuilder};
+use crate::algos::attribution::{attribute_embedding,attribute_similarity};
 use crate::algos::feat_propagation::propagate_features;
 use crate::algos::alignment::{NeighborhoodAligner as NA};
-use crate::algos::smci::SupervisedMCIteration;
+use crate::algos::smci::{SupervisedMCIteration,embedding_distances};
 use crate::algos::pprrank::{PprRank, Loss as PprLoss};
-use crate::algos::ann::Ann;
+use crate::algos::ann::{Ann,AnnIndex,SplitStrategy as ESplitStrategy};
+use crate::algos::ann_mmap::MmapAnn;
+use crate::algos::hnsw::Hnsw;
+use crate::algos::ivf::Ivf;
+use crate::algos::kmeans::{MiniBatchKMeans as CMiniBatchKMeans,Clusters as CClusters};
+use crate::algos::lsh::Lsh;
+use crate::algos::knn_graph::knn_graph;
+use crate::algos::density::{DensityClustering,DensityClusters as CDensityClusters};
+use crate::algos::dedup::{DuplicateFinder,merge_groups};
+use crate::algos::node2vec::Node2Vec;
+use crate::algos::skipgram::SkipGram;
+use crate::algos::metapath::{MetaPath,MetaPathWalker as CMetaPathWalker};
+use crate::algos::verse::Verse;
+use crate::algos::kge::{self,KGTrainer,score_triple};
+use crate::algos::link_scorer::{self,LinkScorer as CLinkScorer,LinkScorerTrainer};
+use crate::algos::graph_stats::GraphStats;
+use crate::bipartite::{BipartiteGraph as CBipartiteGraph,Side as ESide};
+use crate::algos::edge_sampling::sample_edges_cdf;
+use crate::algos::pagerank::quantile_buckets;
+use crate::algos::centrality::Centrality as CCentrality;
+use crate::algos::coarsen::{Coarsener,HierarchicalEmbedding,random_smoothed_embeddings};
+use crate::algos::louvain::{Louvain as CLouvain,Communities as CCommunities};
+use crate::algos::ann_eval::{AnnEvaluation,evaluate,evaluate_index};
 use crate::algos::pprembed::PPREmbed;
 use crate::algos::instantembedding::{InstantEmbeddings as IE,Estimator};
 use crate::algos::lsr::{LSR as ALSR};
 use crate::algos::connected::find_connected_components;
+use crate::algos::triangles::count_triangles;
+use crate::algos::reduction::{PCA,SparseRandomProjection};
+use crate::algos::procrustes::{Procrustes,OrthogonalMap,shared_anchors};
+use crate::algos::spectral::{SpectralEmbedding,Similarity};
+use crate::algos::structural::StructuralEmbedding;
+use crate::algos::fastrp::FastRP;
+use crate::algos::smoothing::EmbeddingSmoother as CEmbeddingSmoother;
 
 /// Defines a constant seed for use when a seed is not provided.  This is specifically hardcoded to
 /// allow for deterministic performance across all algorithms using any stochasticity.
@@ -353,6 +408,62 @@ impl Graph {
         VocabIterator::new(self.vocab.clone())
     }
 
+    ///    Returns the names of every node of a given type, in node id order.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    node_type : str
+    ///         Node type to list.  Unknown types return an empty list.
+    ///
+    ///    Returns
+    ///    -------
+    ///    List[str]
+    ///         Node names.
+    ///
+    pub fn nodes_of_type(&self, node_type: &str) -> Vec<String> {
+        self.vocab.iter_type(node_type).map(|(_node_id, name)| name.to_string()).collect()
+    }
+
+    ///    Counts the nodes of each type.
+    ///
+    ///    Returns
+    ///    -------
+    ///    List[(str, Int)]
+    ///         Node types and their number of nodes.
+    ///
+    pub fn node_type_counts(&self) -> Vec<(String, usize)> {
+        self.vocab.type_counts().into_iter()
+            .map(|(node_type, count)| ((*node_type).clone(), count))
+            .collect()
+    }
+
+    ///    Finds nodes of a given type whose names match a glob pattern.  '*' matches any run of
+    ///    characters and '?' any single character, so "abc*" is a prefix search.  The first
+    ///    search builds a sorted name index, making later searches fast.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    node_type : str
+    ///         Node type to search.
+    ///
+    ///    pattern : str
+    ///         Glob pattern to match names against.
+    ///
+    ///    limit : Int - Optional
+    ///         If provided, returns at most this many names.
+    ///
+    ///    Returns
+    ///    -------
+    ///    List[str]
+    ///         Matching node names, in sorted order.
+    ///
+    pub fn find_nodes(&self, node_type: &str, pattern: &str, limit: Option<usize>) -> Vec<String> {
+        self.vocab.find(node_type, pattern).into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|node_id| self.vocab.get_name(node_id).unwrap().1.to_string())
+            .collect()
+    }
+
     ///    Saves a graph to disk at the provided path.
     ///
     ///    Parameters
@@ -381,6 +492,403 @@ impl Graph {
         Ok(())
     }
 
+    ///    Extracts the subgraph induced by a set of nodes, keeping only edges between them.
+    ///    Transition probabilities are renormalized over the remaining edges.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    nodes : List[FQNode]
+    ///        Nodes to keep.  Nodes without any remaining edges are still kept.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph - Can throw exception
+    ///        New graph with its own vocab over the provided nodes.
+    ///     
+    pub fn subgraph(&self, py: Python<'_>, nodes: Vec<FQNode>) -> PyResult<Graph> {
+        let vocab = self.vocab.deref();
+        let node_ids = nodes.into_iter()
+            .map(|(nt, name)| get_node_id(vocab, nt, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let graph = self.graph.as_ref();
+        Ok(py.allow_threads(move || {
+            let (csr, to_orig) = graph.subgraph(&node_ids);
+            Graph {
+                graph: Arc::new(csr),
+                vocab: Arc::new(vocab.subset(&to_orig))
+            }
+        }))
+    }
+
+    ///    Samples edges with replacement directly from the graph, without materializing the
+    ///    edge list.  Useful for evaluation splits and edge level negatives.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    n : Int
+    ///        Number of edges to sample.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed to use.  Default is a fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, samples each source's edges by transition probability; otherwise
+    ///        uniformly.  Sources are chosen proportional to out degree either way.  Default is
+    ///        false.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[((str, str), (str, str), Float)]
+    ///        Sampled (source, destination, transition probability) triples.
+    ///     
+    pub fn sample_edges(
+        &self,
+        py: Python<'_>,
+        n: usize,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> Vec<(FQNode, FQNode, f32)> {
+        let graph = self.graph.as_ref();
+        let edges = py.allow_threads(move || {
+            let mut rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED));
+            sample_edges_cdf(graph, n, weighted.unwrap_or(false), &mut rng)
+        });
+
+        let vocab = self.vocab.deref();
+        edges.into_iter()
+            .map(|(f_n, t_n, p)| (convert_node_id_to_fqn(vocab, f_n), convert_node_id_to_fqn(vocab, t_n), p))
+            .collect()
+    }
+
+    ///    Computes summary statistics over the graph: out and in degree distributions,
+    ///    transition probability distribution, reciprocity, self loops, and isolated nodes.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Dict[str, Float]
+    ///        Flat dictionary of statistics.  Distributions are reported as min, p50, p90, p99,
+    ///        max, and mean, such as out_degree_p50.
+    ///     
+    pub fn stats(&self, py: Python<'_>) -> HashMap<String, f64> {
+        let graph = self.graph.as_ref();
+        let stats = py.allow_threads(move || GraphStats::compute_cdf(graph));
+
+        let mut d = HashMap::new();
+        d.insert("nodes".to_string(), stats.nodes as f64);
+        d.insert("edges".to_string(), stats.edges as f64);
+        d.insert("isolated_nodes".to_string(), stats.isolated_nodes as f64);
+        d.insert("self_loops".to_string(), stats.self_loops as f64);
+        d.insert("reciprocity".to_string(), stats.reciprocity as f64);
+        for (name, dist) in [("out_degree", stats.out_degree), ("in_degree", stats.in_degree), ("weights", stats.weights)] {
+            d.insert(format!("{}_min", name), dist.min as f64);
+            d.insert(format!("{}_p50", name), dist.p50 as f64);
+            d.insert(format!("{}_p90", name), dist.p90 as f64);
+            d.insert(format!("{}_p99", name), dist.p99 as f64);
+            d.insert(format!("{}_max", name), dist.max as f64);
+            d.insert(format!("{}_mean", name), dist.mean as f64);
+        }
+        d
+    }
+
+    ///    Merges this graph with another, such as a later snapshot, over the union of their
+    ///    nodes.  Operates on transition probabilities.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    other : Graph
+    ///        Graph to merge with.  Its edges are treated as added after this graph's.
+    ///
+    ///    merge : EdgeMerge - Optional
+    ///        How to combine edges present in both graphs.  Default is Sum.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph with a unified vocab.
+    ///     
+    pub fn merge(&self, py: Python<'_>, other: &Graph, merge: Option<EdgeMerge>) -> Graph {
+        let merge = merge.unwrap_or(EdgeMerge::Sum).to_emerge();
+        let (graph, vocab) = (self.graph.as_ref(), self.vocab.as_ref());
+        let (other_graph, other_vocab) = (other.graph.as_ref(), other.vocab.as_ref());
+        let (merged, vocab) = py.allow_threads(move || {
+            graph.merge(vocab, other_graph, other_vocab, merge)
+        });
+        Graph {
+            graph: Arc::new(merged),
+            vocab: Arc::new(vocab)
+        }
+    }
+
+    ///    Prunes edges, keeping only the highest weighted edges per node and/or those above a
+    ///    threshold.  Transition probabilities are renormalized over the remaining edges.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    k : Int - Optional
+    ///        If provided, keeps at most the top K edges for each node.
+    ///
+    ///    min_weight : Float - Optional
+    ///        If provided, drops edges with a transition probability below min_weight.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph sharing this graph's vocab.
+    ///     
+    pub fn prune(&self, py: Python<'_>, k: Option<usize>, min_weight: Option<f32>) -> Graph {
+        let graph = self.graph.as_ref();
+        let pruned = py.allow_threads(move || graph.prune(k, min_weight));
+        Graph {
+            graph: Arc::new(pruned),
+            vocab: self.vocab.clone()
+        }
+    }
+
+    ///    Removes every edge from a node to itself.  Transition probabilities are renormalized
+    ///    over the remaining edges.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph sharing this graph's vocab.
+    ///     
+    pub fn remove_self_loops(&self, py: Python<'_>) -> Graph {
+        let graph = self.graph.as_ref();
+        let cleaned = py.allow_threads(move || graph.remove_self_loops());
+        Graph {
+            graph: Arc::new(cleaned),
+            vocab: self.vocab.clone()
+        }
+    }
+
+    ///    Adds a self loop to every node without one.  Transition probabilities are
+    ///    renormalized afterwards.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    weight : Float
+    ///        Weight of each self loop, relative to the node's transition probabilities which
+    ///        sum to 1.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph sharing this graph's vocab.
+    ///     
+    pub fn add_self_loops(&self, py: Python<'_>, weight: f32) -> Graph {
+        let graph = self.graph.as_ref();
+        let looped = py.allow_threads(move || graph.add_self_loops(weight));
+        Graph {
+            graph: Arc::new(looped),
+            vocab: self.vocab.clone()
+        }
+    }
+
+    ///    Merges parallel edges between the same pair of nodes into a single edge.  Operates on
+    ///    transition probabilities.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    combine : SymmetricWeight - Optional
+    ///        How to weight merged edges.  Default is Sum.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph sharing this graph's vocab.
+    ///     
+    pub fn dedup(&self, py: Python<'_>, combine: Option<SymmetricWeight>) -> Graph {
+        let graph = self.graph.as_ref();
+        let combine = combine.unwrap_or(SymmetricWeight::Sum).to_esymmetric();
+        let deduped = py.allow_threads(move || graph.dedup(combine));
+        Graph {
+            graph: Arc::new(deduped),
+            vocab: self.vocab.clone()
+        }
+    }
+
+    ///    Adds any missing reverse edges so every neighborhood is mutual.  Operates on
+    ///    transition probabilities.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    combine : SymmetricWeight - Optional
+    ///        How to weight edges which exist in both directions.  Edges existing in only one
+    ///        direction are mirrored with their weight.  Default is Max.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        New graph sharing this graph's vocab.
+    ///     
+    pub fn symmetrize(&self, py: Python<'_>, combine: Option<SymmetricWeight>) -> Graph {
+        let combine = combine.unwrap_or(SymmetricWeight::Max).to_esymmetric();
+        let graph = self.graph.as_ref();
+        let sym = py.allow_threads(move || graph.symmetrize(combine));
+        Graph {
+            graph: Arc::new(sym),
+            vocab: self.vocab.clone()
+        }
+    }
+
+    #[staticmethod]
+    ///    Loads a graph from a Matrix Market coordinate file (.mtx).  Indices become node names.
+    ///    Symmetric matrices produce edges in both directions.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to the .mtx file, optionally gzipped.
+    ///    
+    ///    row_type : str - Optional
+    ///        Node type for row indices.  Default is "node".
+    ///    
+    ///    col_type : str - Optional
+    ///        Node type for column indices.  Defaults to row_type, sharing one namespace.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph - Can throw exception
+    ///        
+    pub fn load_matrix_market(
+        py: Python<'_>,
+        path: &str,
+        row_type: Option<String>,
+        col_type: Option<String>
+    ) -> PyResult<Graph> {
+        let row_type = row_type.unwrap_or_else(|| "node".to_string());
+        let col_type = col_type.unwrap_or_else(|| row_type.clone());
+        let (vocab, graph) = py.allow_threads(move || {
+            MatrixMarketReader::load(path, &row_type, &col_type)
+        })?;
+        Ok(Graph {
+            graph: Arc::new(graph),
+            vocab: Arc::new(vocab)
+        })
+    }
+
+    #[staticmethod]
+    ///    Loads a graph from a GraphML document.  Edge weights come from the edge attribute
+    ///    named "weight" when declared; undirected edges produce edges in both directions.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to the GraphML file, optionally gzipped.
+    ///    
+    ///    node_type : str - Optional
+    ///        Node type for every node.  Default is "node".
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph - Can throw exception
+    ///        
+    pub fn load_graphml(py: Python<'_>, path: &str, node_type: Option<String>) -> PyResult<Graph> {
+        let node_type = node_type.unwrap_or_else(|| "node".to_string());
+        let (vocab, graph) = py.allow_threads(move || GraphMLReader::load(path, &node_type))?;
+        Ok(Graph {
+            graph: Arc::new(graph),
+            vocab: Arc::new(vocab)
+        })
+    }
+
+    #[staticmethod]
+    ///    Loads a graph from a Parquet edge table with string node columns and an optional
+    ///    float32 or float64 weight column.  Row groups are decoded in parallel.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to the Parquet file.
+    ///    
+    ///    edge_type : EdgeType
+    ///        EdgeType to use, either Directed or Undirected
+    ///    
+    ///    src_type, src_name, dst_type, dst_name : str - Optional
+    ///        Column names for each end of the edge.  Defaults to the parameter names.
+    ///    
+    ///    weight : str - Optional
+    ///        Column name for edge weights.  Default is "weight".
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If false, ignores the weight column and all edges have weight 1.  Default is true.
+    ///    
+    ///    merge : EdgeMerge - Optional
+    ///        How to combine duplicate edges.  Default is EdgeMerge.Sum.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph - Can throw exception
+    ///        
+    pub fn load_parquet(
+        py: Python<'_>,
+        path: &str,
+        edge_type: EdgeType,
+        src_type: Option<String>,
+        src_name: Option<String>,
+        dst_type: Option<String>,
+        dst_name: Option<String>,
+        weight: Option<String>,
+        weighted: Option<bool>,
+        merge: Option<EdgeMerge>
+    ) -> PyResult<Graph> {
+        let defaults = EdgeColumns::default();
+        let columns = EdgeColumns {
+            src_type: src_type.unwrap_or(defaults.src_type),
+            src_name: src_name.unwrap_or(defaults.src_name),
+            dst_type: dst_type.unwrap_or(defaults.dst_type),
+            dst_name: dst_name.unwrap_or(defaults.dst_name),
+            weight: if weighted.unwrap_or(true) { weight.or(defaults.weight) } else { None }
+        };
+        let merge = merge.unwrap_or(EdgeMerge::Sum).to_emerge();
+        let (vocab, graph) = py.allow_threads(move || {
+            ParquetGraphReader::load(path, &columns, edge_type, merge)
+        })?;
+        Ok(Graph {
+            graph: Arc::new(graph),
+            vocab: Arc::new(vocab)
+        })
+    }
+
+    ///    Saves the graph and its vocab, including its name normalization, in a checksummed
+    ///    binary format which loads far faster than edge lists.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///     Where to save the the graph.
+    ///
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///     
+    pub fn save_binary(&self, path: &str) -> PyResult<()> {
+        GraphSerializer::save(path, self.graph.as_ref(), self.vocab.as_ref())
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
+    }
+
+    #[staticmethod]
+    ///    Loads a graph written by save_binary, verifying its checksum.
+    ///
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///     Path to the saved graph.
+    ///
+    ///    Returns
+    ///    -------
+    ///    Graph - Can throw exception
+    ///     
+    pub fn load_binary(py: Python<'_>, path: &str) -> PyResult<Graph> {
+        let (vocab, graph) = py.allow_threads(move || GraphSerializer::load(path))
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        Ok(Graph {
+            graph: Arc::new(graph),
+            vocab: Arc::new(vocab)
+        })
+    }
+
     /// Returns the number of nodes in the graph
     pub fn __len__(&self) -> PyResult<usize> {
         Ok(self.nodes())
@@ -402,6 +910,24 @@ impl Graph {
     ///    edge_type : EdgeType
     ///        EdgeType to use, either Directed or Undirected
     ///    
+    ///    chunk_size : Int - Optional
+    ///        Number of rows to parse in parallel at a time.  Default is 1, parsing serially.
+    ///    
+    ///    skip_rows : Int - Optional
+    ///        Number of leading rows, such as headers, to skip.  Default is 0.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, reads edge weights from the fifth field; otherwise all edges have weight 1.
+    ///        Default is true.
+    ///    
+    ///    delimiter : str - Optional
+    ///        Field delimiter for the (src_type, src_name, dst_type, dst_name, weight) rows.
+    ///        Defaults to a comma for .csv files and a tab otherwise.
+    ///    
+    ///    normalization : NameNormalization - Optional
+    ///        If provided, node names are normalized when loading and when looking nodes up
+    ///        later.  Default is None, using names exactly as written.
+    ///    
     ///    Returns
     ///    -------
     ///    Self - Can throw exception
@@ -412,7 +938,9 @@ impl Graph {
         edge_type: EdgeType, 
         chunk_size: Option<usize>,
         skip_rows: Option<usize>,
-        weighted: Option<bool>
+        weighted: Option<bool>,
+        delimiter: Option<char>,
+        normalization: Option<NameNormalization>
         ) -> PyResult<Self> {
 
         py.allow_threads(move || {
@@ -421,7 +949,9 @@ impl Graph {
                 edge_type, 
                 chunk_size.unwrap_or(1),
                 skip_rows.unwrap_or(0),
-                weighted.unwrap_or(true)
+                weighted.unwrap_or(true),
+                delimiter.unwrap_or_else(|| GraphReader::default_delimiter(path)),
+                normalization.map(|n| n.to_enorm()).unwrap_or_default()
             )?;
 
             let g = Graph {
@@ -547,34 +1077,252 @@ impl RandomWalker {
         Ok(convert_scores(&graph.vocab, results.into_iter(), k, filter_type))
     }
 
-}
-
-/// Rp3b walker with the ability to bias walks according to a provided embedding set.
-#[pyclass]
-#[derive(Clone)]
-struct BiasedRandomWalker {
-    restarts: f32,
-    walks: usize,
-    beta: Option<f32>,
-    blend: Option<f32>,
-}
-
-#[pymethods]
-impl BiasedRandomWalker {
-
-    #[new]
-    ///    Creates a BiasedRandomWalker instance.
-    ///
-    ///    BiasedRandomWalkers perform random walks while allowing external embeddings to influence
-    ///    the direction a random walker takes.  When two nodes have a closer distance, the
-    ///    randomwalker will reweight scores to explore in that direction more often.  This is
-    ///    helpful when wanting to perform a random walk but also have it focus on areas compatible
-    ///    with embeddings - for example, random walks between queries and products, where the
-    ///    embeddings represent user preferences.
+    ///    Performs random walks with restarts from each of the provided nodes, scoring every node
+    ///    visited along the way rather than only where walks terminate.
     ///    
     ///    Parameters
     ///    ----------
-    ///    restarts : Float
+    ///    graph : Graph
+    ///        Graph to perform random walks on
+    ///    
+    ///    nodes : List[FQNode]
+    ///        Source nodes, each receiving its own set of walks.
+    ///    
+    ///    k : Int - Optional
+    ///        If provided, truncates each list to the top K.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only returns nodes that match the provided node type.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether to perform a weighted random walk.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[(FQNode, Float)]] - Can throw exception
+    ///        For each source, the visited nodes and their visit scores, highest first.  The
+    ///        source itself is excluded.
+    ///
+    pub fn visits(
+        &self, 
+        py: Python<'_>,
+        graph: &Graph,
+        nodes: Vec<FQNode>, 
+        k: Option<usize>, 
+        seed: Option<u64>, 
+        filter_type: Option<String>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<Vec<(FQNode, f32)>>> {
+        let vocab = graph.vocab.deref();
+        let node_ids = nodes.into_iter()
+            .map(|(nt, name)| get_node_id(vocab, nt, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let steps = Steps::from_float(self.restarts)
+            .ok_or_else(|| PyValueError::new_err("Alpha must be between [0, inf)"))?;
+
+        let rwr = RWR {
+            steps: steps,
+            walks: self.walks,
+            beta: self.beta.unwrap_or(0.5),
+            single_threaded: true,
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let g = graph.graph.as_ref();
+        let results = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                rwr.visit_counts_batch(g, &Weighted, &node_ids)
+            } else {
+                rwr.visit_counts_batch(g, &Unweighted, &node_ids)
+            }
+        });
+
+        Ok(results.into_iter()
+           .map(|counts| convert_scores(vocab, counts.into_iter(), k, filter_type.clone()))
+           .collect())
+    }
+
+}
+
+/// Approximate SimRank for measuring structural similarity between nodes.
+#[pyclass]
+struct SimRank {
+    decay: f32,
+    walks: usize,
+    max_steps: usize
+}
+
+#[pymethods]
+impl SimRank {
+
+    ///    Creates a SimRank instance.  Walks follow the graph's edges, so directed graphs should
+    ///    be transposed to compare nodes by their in-links.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    decay : Float - Optional
+    ///        Decay applied per step, ~ (0, 1).  Default is 0.8.
+    ///    
+    ///    walks : Int - Optional
+    ///        Number of walk pairs used to estimate pairwise similarity.  Default is 1000.
+    ///    
+    ///    max_steps : Int - Optional
+    ///        Maximum walk length.  Default is 10.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    #[new]
+    fn new(decay: Option<f32>, walks: Option<usize>, max_steps: Option<usize>) -> Self {
+        SimRank {
+            decay: decay.unwrap_or(0.8),
+            walks: walks.unwrap_or(1000),
+            max_steps: max_steps.unwrap_or(10)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("SimRank<decay={}, walks={}, max_steps={}>", self.decay, self.walks, self.max_steps)
+    }
+
+    ///    Estimates the SimRank between two nodes from random walks.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to walk.
+    ///    
+    ///    a : FQNode
+    ///        First node.
+    ///    
+    ///    b : FQNode
+    ///        Second node.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, follows edges by weight; otherwise uniformly.  Default is true.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Float - Can throw exception
+    ///        Similarity ~ [0, 1].  Throws if either node doesn't exist.
+    ///        
+    pub fn similarity(
+        &self,
+        graph: &Graph,
+        a: FQNode,
+        b: FQNode,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<f32> {
+        let vocab = graph.vocab.deref();
+        let a = get_node_id(vocab, a.0, a.1)?;
+        let b = get_node_id(vocab, b.0, b.1)?;
+        let simrank = self.to_simrank(seed);
+        let g = graph.graph.as_ref();
+        Ok(if weighted.unwrap_or(true) {
+            simrank.similarity(g, &Weighted, a, b)
+        } else {
+            simrank.similarity(g, &Unweighted, a, b)
+        })
+    }
+
+    ///    Returns the most similar nodes to each node using the linearized SimRank
+    ///    approximation over the transition probabilities.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to score.
+    ///    
+    ///    nodes : List[FQNode]
+    ///        Nodes to find similar nodes for.
+    ///    
+    ///    k : Int - Optional
+    ///        If provided, truncates each list to the top K.
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only returns nodes that match the provided node type.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[(FQNode, Float)]] - Can throw exception
+    ///        Similar nodes and their scores for each node, highest first.  Throws if a node
+    ///        doesn't exist.
+    ///        
+    pub fn top_k(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        nodes: Vec<FQNode>,
+        k: Option<usize>,
+        filter_type: Option<String>
+    ) -> PyResult<Vec<Vec<(FQNode, f32)>>> {
+        let vocab = graph.vocab.deref();
+        let node_ids = nodes.into_iter()
+            .map(|(nt, name)| get_node_id(vocab, nt, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let simrank = self.to_simrank(None);
+        let g = graph.graph.as_ref();
+        let results = py.allow_threads(move || {
+            node_ids.iter().map(|node_id| simrank.single_source(g, *node_id)).collect::<Vec<_>>()
+        });
+
+        Ok(results.into_iter()
+           .map(|scores| {
+               let scores = scores.into_iter().enumerate().filter(|(_, s)| *s > 0.);
+               convert_scores(vocab, scores, k, filter_type.clone())
+           })
+           .collect())
+    }
+}
+
+impl SimRank {
+    fn to_simrank(&self, seed: Option<u64>) -> CSimRank {
+        CSimRank {
+            decay: self.decay,
+            walks: self.walks,
+            max_steps: self.max_steps,
+            seed: seed.unwrap_or(SEED)
+        }
+    }
+}
+
+/// Rp3b walker with the ability to bias walks according to a provided embedding set.
+#[pyclass]
+#[derive(Clone)]
+struct BiasedRandomWalker {
+    restarts: f32,
+    walks: usize,
+    beta: Option<f32>,
+    blend: Option<f32>,
+}
+
+#[pymethods]
+impl BiasedRandomWalker {
+
+    #[new]
+    ///    Creates a BiasedRandomWalker instance.
+    ///
+    ///    BiasedRandomWalkers perform random walks while allowing external embeddings to influence
+    ///    the direction a random walker takes.  When two nodes have a closer distance, the
+    ///    randomwalker will reweight scores to explore in that direction more often.  This is
+    ///    helpful when wanting to perform a random walk but also have it focus on areas compatible
+    ///    with embeddings - for example, random walks between queries and products, where the
+    ///    embeddings represent user preferences.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    restarts : Float
     ///        If restarts is ~ (0,1), performs probabalistic termination (ie. pagewalk).  If
     ///        restarts is [1,inf], performs fixed length walks (ie. rp3b, pixie).
     ///    
@@ -783,33 +1531,161 @@ pub enum EdgeType {
     Undirected
 }
 
-/// Allows the user to build a graph incrementally before converting it into a proper CSR graph
+/// How Graph.symmetrize weights edges which exist in both directions and Graph.dedup weights
+/// parallel edges
 #[pyclass]
-struct GraphBuilder {
-    vocab: Vocab,
-    edges: Vec<(NodeID, NodeID, f32)>
+#[derive(Clone,Copy)]
+pub enum SymmetricWeight {
+    Max,
+    Sum,
+    Mean
 }
 
-impl GraphBuilder {
-    fn compact_edges(edges: &mut Vec<(NodeID, NodeID, f32)>) {
-        edges.par_sort_by_key(|(f_n, t_n, _)| (*f_n, *t_n));
-        let mut cur_record = 0;
-        let mut idx = 1;
-        while idx < edges.len() {
-            let (f_n, t_n, w) = edges[idx];
-            let c_r = edges[cur_record];
-            // Same edge, add the weights.
-            if f_n == c_r.0 && t_n == c_r.1 {
-                (&mut edges[cur_record]).2 += w;
-            } else {
-                // Different record, move it
-                cur_record += 1;
-                edges[cur_record] = edges[idx];
-            }
-            idx += 1;
+impl SymmetricWeight {
+    fn to_esymmetric(&self) -> ESymmetricWeight {
+        match self {
+            SymmetricWeight::Max  => ESymmetricWeight::Max,
+            SymmetricWeight::Sum  => ESymmetricWeight::Sum,
+            SymmetricWeight::Mean => ESymmetricWeight::Mean
+        }
+    }
+}
+
+/// How repeated edges between the same pair of nodes are combined by the GraphBuilder
+#[pyclass]
+#[derive(Clone,Copy)]
+pub enum EdgeMerge {
+    Sum,
+    Max,
+    Min,
+    First,
+    Last
+}
+
+impl EdgeMerge {
+    fn to_emerge(&self) -> EEdgeMerge {
+        match self {
+            EdgeMerge::Sum   => EEdgeMerge::Sum,
+            EdgeMerge::Max   => EEdgeMerge::Max,
+            EdgeMerge::Min   => EEdgeMerge::Min,
+            EdgeMerge::First => EEdgeMerge::First,
+            EdgeMerge::Last  => EEdgeMerge::Last
+        }
+    }
+}
+
+/// How FeatureSet.fill_missing_nodes assigns features to nodes without any
+#[pyclass]
+#[derive(Clone,Copy)]
+pub enum MissingFeatures {
+    Unique,
+    Shared,
+    NodeType,
+    Neighbors
+}
+
+impl MissingFeatures {
+    fn to_emissing(&self) -> EMissingFeatures {
+        match self {
+            MissingFeatures::Unique    => EMissingFeatures::Unique,
+            MissingFeatures::Shared    => EMissingFeatures::Shared,
+            MissingFeatures::NodeType  => EMissingFeatures::NodeType,
+            MissingFeatures::Neighbors => EMissingFeatures::Neighbors
+        }
+    }
+}
+
+/// How FeatureSet.extend_embeddings initializes features without a trained embedding
+#[pyclass]
+#[derive(Clone,Copy)]
+pub enum FeatureInit {
+    Random,
+    Average
+}
+
+impl FeatureInit {
+    fn to_einit(&self) -> EFeatureInit {
+        match self {
+            FeatureInit::Random  => EFeatureInit::Random,
+            FeatureInit::Average => EFeatureInit::Average
+        }
+    }
+}
+
+/// How node names are normalized before they're stored or looked up.  Ingestion and queries
+/// share the graph's normalization, so lookups match however names were written.
+#[pyclass]
+#[derive(Clone,Copy)]
+pub struct NameNormalization {
+    nfkc: bool,
+    lowercase: bool,
+    trim: bool
+}
+
+impl NameNormalization {
+    fn to_enorm(&self) -> Normalization {
+        Normalization { nfkc: self.nfkc, lowercase: self.lowercase, trim: self.trim }
+    }
+}
+
+#[pymethods]
+impl NameNormalization {
+
+    ///    Creates a NameNormalization.  Steps apply in the order NFKC, lowercasing, then
+    ///    trimming.  Node types are never normalized.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    nfkc : Bool - Optional
+    ///        If true, applies Unicode NFKC normalization, folding compatibility characters such
+    ///        as full width letters.  Default is True.
+    ///    
+    ///    lowercase : Bool - Optional
+    ///        If true, lowercases names.  Default is True.
+    ///    
+    ///    trim : Bool - Optional
+    ///        If true, strips leading and trailing whitespace.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[new]
+    pub fn new(nfkc: Option<bool>, lowercase: Option<bool>, trim: Option<bool>) -> Self {
+        NameNormalization {
+            nfkc: nfkc.unwrap_or(true),
+            lowercase: lowercase.unwrap_or(true),
+            trim: trim.unwrap_or(true)
         }
-        edges.truncate(cur_record + 1);
     }
+
+    ///    Normalizes a name as a graph using this normalization would.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    name : String
+    ///        Name to normalize.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    String
+    ///    
+    pub fn normalize(&self, name: &str) -> String {
+        self.to_enorm().apply(name).into_owned()
+    }
+
+    /// Simple Python representation
+    pub fn __repr__(&self) -> String {
+        format!("NameNormalization<nfkc={}, lowercase={}, trim={}>", self.nfkc, self.lowercase, self.trim)
+    }
+}
+
+/// Allows the user to build a graph incrementally before converting it into a proper CSR graph
+#[pyclass]
+struct GraphBuilder {
+    vocab: Vocab,
+    merge: EEdgeMerge,
+    builder: CGraphBuilder
 }
 
 #[pymethods]
@@ -817,22 +1693,34 @@ impl GraphBuilder {
     ///    Creates a new graph builder instance.  This allows for the programatic construction of
     ///    graphs, creating a fully fledged and optimized graph at the end.
     ///    
+    ///    Parameters
+    ///    ----------
+    ///    merge : EdgeMerge - Optional
+    ///        How to combine weights when the same edge is added more than once.  Default is Sum.
+    ///    
+    ///    normalization : NameNormalization - Optional
+    ///        If provided, node names are normalized when adding edges and when looking nodes up
+    ///        in the built graph.  Default is None, using names exactly as given.
+    ///    
     ///    Returns
     ///    -------
     ///    Self
     ///        
     ///    
     #[new]
-    pub fn new() -> Self {
+    pub fn new(merge: Option<EdgeMerge>, normalization: Option<NameNormalization>) -> Self {
+        let merge = merge.unwrap_or(EdgeMerge::Sum).to_emerge();
+        let normalization = normalization.map(|n| n.to_enorm()).unwrap_or_default();
         GraphBuilder {
-            vocab: Vocab::new(),
-            edges: Vec::new()
+            vocab: Vocab::with_normalization(normalization),
+            merge,
+            builder: CGraphBuilder::new(merge)
         }
     }
     
     /// Simple representation of the GraphBuilder
     pub fn __repr__(&self) -> String {
-        format!("GraphBuilder<Nodes={}, Edges={}>", self.vocab.len(), self.edges.len())
+        format!("GraphBuilder<Nodes={}, Edges={}>", self.vocab.len(), self.builder.len())
     }
  
     ///    Adds an edge to the graph.
@@ -861,13 +1749,13 @@ impl GraphBuilder {
     ) {
         let f_id = self.vocab.get_or_insert(from_node.0, from_node.1);
         let t_id = self.vocab.get_or_insert(to_node.0, to_node.1);
-        self.edges.push((f_id, t_id, weight));
+        self.builder.add_edge(f_id, t_id, weight);
         if matches!(node_type, EdgeType::Undirected) {
-            self.edges.push((t_id, f_id, weight));
+            self.builder.add_edge(t_id, f_id, weight);
         }
     }
 
-    ///    Constructs the graph
+    ///    Constructs the graph, resetting the builder.
     ///    
     ///    Returns
     ///    -------
@@ -875,45 +1763,361 @@ impl GraphBuilder {
     ///        Creates a Graph for usage.  If no edges have been specified, returns None.
     ///    
     pub fn build_graph(&mut self) -> Option<Graph> {
-        if self.edges.len() == 0 {
+        if self.builder.len() == 0 {
             return None
         }
         // We swap the internal buffers with new buffers; we do this to preserve memory whenever
         // possible.
-        let mut vocab = Vocab::new(); 
-        let mut edges = Vec::new();
-        std::mem::swap(&mut vocab, &mut self.vocab);
-        std::mem::swap(&mut edges, &mut self.edges);
-
-        GraphBuilder::compact_edges(&mut edges);
-        let graph = CSR::construct_from_edges(edges);
+        let normalization = self.vocab.normalization();
+        let vocab = std::mem::replace(&mut self.vocab, Vocab::with_normalization(normalization));
+        let mut builder = std::mem::replace(&mut self.builder, CGraphBuilder::new(self.merge));
+        builder.set_num_nodes(vocab.len());
 
         Some(Graph {
-            graph: Arc::new(CumCSR::convert(graph)),
+            graph: Arc::new(builder.finalize()),
             vocab: Arc::new(vocab)
         })
     }
 
 }
 
+/// Which partition of a bipartite graph a node belongs to
 #[pyclass]
-#[derive(Clone)]
-struct LossWeighting {
-    loss: EPLW
+#[derive(Clone,Copy)]
+pub enum Side {
+    Left,
+    Right
 }
 
-#[pymethods]
-impl LossWeighting {
-
-    #[allow(non_snake_case)]
-    #[staticmethod]
-    pub fn Log() -> Self {
-        LossWeighting { loss: EPLW::DegreeLog }
+impl Side {
+    fn to_eside(&self) -> ESide {
+        match self {
+            Side::Left  => ESide::Left,
+            Side::Right => ESide::Right
+        }
     }
+}
 
-    #[allow(non_snake_case)]
-    #[staticmethod]
-    pub fn Exponential(weight: f32) -> Self {
+/// Graph split into two partitions by node type, such as users and items, where every edge
+/// crosses partitions.
+#[pyclass]
+struct BipartiteGraph {
+    graph: CBipartiteGraph<Arc<CumCSR>>,
+    vocab: Arc<Vocab>
+}
+
+#[pymethods]
+impl BipartiteGraph {
+
+    ///    Wraps a graph as bipartite, validating the partitions.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to wrap.  The graph is shared, not copied.
+    ///    
+    ///    left_types : List[str]
+    ///        Node types in the left partition, such as users.
+    ///    
+    ///    right_types : List[str]
+    ///        Node types in the right partition, such as items.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        Throws if a node type is in both or neither partition, or an edge connects two
+    ///        nodes in the same partition.
+    ///        
+    #[new]
+    fn new(
+        py: Python<'_>,
+        graph: &Graph,
+        left_types: Vec<String>,
+        right_types: Vec<String>
+    ) -> PyResult<Self> {
+        let g = graph.graph.clone();
+        let vocab = graph.vocab.clone();
+        let bg = py.allow_threads(|| CBipartiteGraph::new(g, &vocab, &left_types, &right_types))
+            .map_err(PyValueError::new_err)?;
+        Ok(BipartiteGraph { graph: bg, vocab })
+    }
+
+    ///    Returns the side a node belongs to
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : (str, str)
+    ///        Node to look up.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Side - Can throw exception
+    ///        
+    pub fn side(&self, node: FQNode) -> PyResult<Side> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(match self.graph.side(node_id) {
+            ESide::Left  => Side::Left,
+            ESide::Right => Side::Right
+        })
+    }
+
+    ///    Returns every node in a partition
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    side : Side
+    ///        Partition to return.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(str, str)]
+    ///        
+    pub fn nodes(&self, side: Side) -> Vec<FQNode> {
+        self.graph.partition(side.to_eside()).iter()
+            .map(|node_id| convert_node_id_to_fqn(self.vocab.deref(), *node_id))
+            .collect()
+    }
+
+    ///    Samples nodes uniformly, with replacement, from a partition.  Useful for negatives.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    side : Side
+    ///        Partition to sample from.
+    ///    
+    ///    n : Int
+    ///        Number of nodes to sample.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed to use.  Default is a fixed seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(str, str)]
+    ///        
+    pub fn sample_nodes(&self, side: Side, n: usize, seed: Option<u64>) -> Vec<FQNode> {
+        let mut rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED));
+        self.graph.sample_nodes(side.to_eside(), n, &mut rng).into_iter()
+            .map(|node_id| convert_node_id_to_fqn(self.vocab.deref(), node_id))
+            .collect()
+    }
+
+    ///    Samples nodes in a partition reachable from a node: one hop when crossing partitions,
+    ///    two hops when staying in the node's own partition.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : (str, str)
+    ///        Node to sample from.
+    ///    
+    ///    side : Side
+    ///        Partition to sample.
+    ///    
+    ///    n : Int
+    ///        Number of samples.  Dead ends are skipped, so fewer may be returned.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed to use.  Default is a fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, follows edges by weight; otherwise uniformly.  Default is true.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(str, str)] - Can throw exception
+    ///        
+    pub fn sample_neighbors(
+        &self,
+        node: FQNode,
+        side: Side,
+        n: usize,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<FQNode>> {
+        let vocab = self.vocab.deref();
+        let node_id = get_node_id(vocab, node.0, node.1)?;
+        let mut rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED));
+        let side = side.to_eside();
+        Ok((0..n)
+            .filter_map(|_| if weighted.unwrap_or(true) {
+                self.graph.sample_on_side(&Weighted, node_id, side, &mut rng)
+            } else {
+                self.graph.sample_on_side(&Unweighted, node_id, side, &mut rng)
+            })
+            .map(|node_id| convert_node_id_to_fqn(vocab, node_id))
+            .collect())
+    }
+
+    ///    Retrieves the most visited nodes in a partition using random walks with restarts.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    walker : RandomWalker
+    ///        Defines the restart probability, number of walks, and degree discount.
+    ///    
+    ///    nodes : List[(str, str)]
+    ///        Nodes to start walks from.
+    ///    
+    ///    side : Side
+    ///        Partition to retrieve from.
+    ///    
+    ///    k : Int
+    ///        Number of nodes to return for each start node.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed to use.  Default is a fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, follows edges by weight; otherwise uniformly.  Default is true.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[((str, str), Float)]] - Can throw exception
+    ///        For each start node, retrieved nodes and their scores, highest first.
+    ///        
+    pub fn top_k(
+        &self,
+        py: Python<'_>,
+        walker: &RandomWalker,
+        nodes: Vec<FQNode>,
+        side: Side,
+        k: usize,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<Vec<(FQNode, f32)>>> {
+        let vocab = self.vocab.deref();
+        let node_ids = nodes.into_iter()
+            .map(|(nt, name)| get_node_id(vocab, nt, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let steps = Steps::from_float(walker.restarts)
+            .ok_or_else(|| PyValueError::new_err("Alpha must be between [0, inf)"))?;
+
+        let rwr = RWR {
+            steps: steps,
+            walks: walker.walks,
+            beta: walker.beta.unwrap_or(0.5),
+            single_threaded: true,
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let graph = &self.graph;
+        let side = side.to_eside();
+        let results = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                graph.top_k(&rwr, &Weighted, &node_ids, side, k)
+            } else {
+                graph.top_k(&rwr, &Unweighted, &node_ids, side, k)
+            }
+        });
+
+        Ok(results.into_iter()
+            .map(|scores| scores.into_iter()
+                .map(|(node_id, score)| (convert_node_id_to_fqn(vocab, node_id), score))
+                .collect())
+            .collect())
+    }
+
+    ///    Scores related nodes with personalized SALSA.  Walks alternate between partitions,
+    ///    scoring nodes across from each start node (authorities, such as items to recommend)
+    ///    separately from nodes on its own side (hubs, such as similar users).
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    nodes : List[(str, str)]
+    ///        Nodes to start walks from.
+    ///    
+    ///    k : Int
+    ///        Number of authorities and hubs to return for each start node.
+    ///    
+    ///    walks : Int - Optional
+    ///        Number of walks from each start node.  Default is 1000.
+    ///    
+    ///    restart : Float - Optional
+    ///        Probability of ending a walk after each round trip.  Default is 0.3.
+    ///    
+    ///    max_steps : Int - Optional
+    ///        Maximum round trips per walk.  Default is 10.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed to use.  Default is a fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        If true, follows edges by weight; otherwise uniformly.  Default is true.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(List[((str, str), Float)], List[((str, str), Float)])] - Can throw exception
+    ///        For each start node, its authorities and hubs with their visit probabilities,
+    ///        highest first.  The start node is excluded from its hubs.
+    ///        
+    pub fn salsa(
+        &self,
+        py: Python<'_>,
+        nodes: Vec<FQNode>,
+        k: usize,
+        walks: Option<usize>,
+        restart: Option<f32>,
+        max_steps: Option<usize>,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<(Vec<(FQNode, f32)>, Vec<(FQNode, f32)>)>> {
+        let vocab = self.vocab.deref();
+        let node_ids = nodes.into_iter()
+            .map(|(nt, name)| get_node_id(vocab, nt, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let salsa = Salsa {
+            walks: walks.unwrap_or(1000),
+            restart: restart.unwrap_or(0.3),
+            max_steps: max_steps.unwrap_or(10),
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let graph = &self.graph;
+        let results = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                salsa.score_batch(graph, &Weighted, &node_ids, k)
+            } else {
+                salsa.score_batch(graph, &Unweighted, &node_ids, k)
+            }
+        });
+
+        let convert = |scores: Vec<(NodeID, f32)>| -> Vec<(FQNode, f32)> {
+            scores.into_iter()
+                .map(|(node_id, score)| (convert_node_id_to_fqn(vocab, node_id), score))
+                .collect()
+        };
+        Ok(results.into_iter()
+            .map(|scores| (convert(scores.authorities), convert(scores.hubs)))
+            .collect())
+    }
+
+    /// Returns the number of nodes in the graph
+    pub fn __len__(&self) -> usize {
+        self.graph.len()
+    }
+
+}
+
+#[pyclass]
+#[derive(Clone)]
+struct LossWeighting {
+    loss: EPLW
+}
+
+#[pymethods]
+impl LossWeighting {
+
+    #[allow(non_snake_case)]
+    #[staticmethod]
+    pub fn Log() -> Self {
+        LossWeighting { loss: EPLW::DegreeLog }
+    }
+
+    #[allow(non_snake_case)]
+    #[staticmethod]
+    pub fn Exponential(weight: f32) -> Self {
         LossWeighting { loss: EPLW::DegreeExponential(weight) }
     }
 
@@ -1155,6 +2359,13 @@ impl EmbeddingPropagator {
     ///
     ///        Default is None.
     ///    
+    ///    namespaces : Dict[str, (Int | None, Float)] - Optional
+    ///        Per feature namespace (max_features, weight), such as {"token": (10, 1.0), "brand":
+    ///        (None, 2.0)}.  Each namespace is sampled separately, falling back to `max_features`,
+    ///        and its features are weighted when averaged.  Only used by the averaged model.
+    ///
+    ///        Default is None, which aggregates all features together.
+    ///    
     ///    Returns
     ///    -------
     ///    Self
@@ -1215,7 +2426,10 @@ impl EmbeddingPropagator {
         context_window: Option<usize>,
 
         // Use gradient noise where we sample from the normal distribution and blend with `noise`
-        noise: Option<f32>
+        noise: Option<f32>,
+
+        // Per namespace (max_features, weight) for the averaged model
+        namespaces: Option<HashMap<String, (Option<usize>, f32)>>
     ) -> Self {
         let loss_weighting = loss_weighting.map(|lw| lw.loss).unwrap_or(EPLW::None);
         let ep = EmbeddingPropagation {
@@ -1247,8 +2461,11 @@ impl EmbeddingPropagator {
             let mha = MultiHeadedAttention::new(num_heads, d_k, at);
             ModelType::Attention(AttentionFeatureModel::new(mha, None, max_nodes, wns))
         } else {
+            let namespaces = namespaces.unwrap_or_default().into_iter()
+                .map(|(ns, (max_features, weight))| (ns, NamespaceAggregation { max_features, weight }))
+                .collect();
             ModelType::Averaged(AveragedFeatureModel::new(
-                    max_features, max_nodes, wns, wna
+                    max_features, max_nodes, wns, wna, namespaces
             ))
         };
 
@@ -1317,6 +2534,99 @@ impl EmbeddingPropagator {
         feature_embeddings
 
     }
+
+    ///    Learns the features jointly with a linear classifier over the node embeddings of
+    ///    labeled nodes.  Unlabeled nodes only contribute the usual EP loss, so a handful of
+    ///    labels is enough to shape the embedding space.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to learn against.
+    ///    
+    ///    features : FeatureSet
+    ///        FeatureSet for nodes in the graph
+    ///    
+    ///    labels : List[(FQNode, Str)]
+    ///        Class label for each labeled node.
+    ///    
+    ///    label_weight : Float - Optional
+    ///        Weight of the classification loss relative to the EP loss.  Default is 1.
+    ///    
+    ///    feature_embeddings : mut NodeEmbeddings - Optional
+    ///        If not provided, creates a new randomized feature_embedding set.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (NodeEmbeddings, NodeClassifier) - Can throw exception
+    ///        A mapping from features -> embedding and the classifier trained with them.  Throws
+    ///        if a labeled node isn't in the graph.
+    ///    
+    pub fn learn_supervised(
+        &mut self, 
+        graph: &Graph, 
+        features: &mut FeatureSet,
+        labels: Vec<(FQNode, String)>,
+        label_weight: Option<f32>,
+        feature_embeddings: Option<&mut NodeEmbeddings>
+    ) -> PyResult<(NodeEmbeddings, NodeClassifier)> {
+
+        let mut classes = Vec::new();
+        let mut class_ids = HashMap::new();
+        let mut node_labels = HashMap::new();
+        for ((node_type, name), label) in labels.into_iter() {
+            let node_id = get_node_id(graph.vocab.deref(), node_type, name)?;
+            let class = *class_ids.entry(label.clone()).or_insert_with(|| {
+                classes.push(label);
+                classes.len() - 1
+            });
+            node_labels.insert(node_id, class);
+        }
+
+        let labels = NodeLabels {
+            labels: node_labels.into_iter().collect(),
+            num_classes: classes.len(),
+            weight: label_weight.unwrap_or(1.)
+        };
+
+        features.features.fill_missing_nodes();
+
+        // Pull out the EmbeddingStore
+        let feature_embeddings = feature_embeddings.map(|fes| {
+           let mut sfes = EmbeddingStore::new(fes.vocab.len(), 0, EDist::Cosine);
+           std::mem::swap(&mut sfes, &mut fes.embeddings);
+           sfes
+        });
+
+        let (feat_embeds, classifier) = match &self.model {
+            ModelType::Averaged(model) => {
+                self.ep.learn_supervised(
+                    graph.graph.as_ref(), 
+                    &features.features,
+                    feature_embeddings,
+                    model,
+                    &labels
+                )
+            },
+            ModelType::Attention(model) => {
+                self.ep.learn_supervised(
+                    graph.graph.as_ref(), 
+                    &features.features,
+                    feature_embeddings,
+                    model,
+                    &labels
+                )
+            }
+        };
+
+        let vocab = features.features.clone_vocab();
+
+        let feature_embeddings = NodeEmbeddings {
+            vocab: Arc::new(vocab),
+            embeddings: feat_embeds};
+
+        Ok((feature_embeddings, NodeClassifier { classes, classifier }))
+    }
     
     /// Simple Python representation 
     pub fn __repr__(&self) -> String {
@@ -1325,43 +2635,208 @@ impl EmbeddingPropagator {
 
 }
 
-/// Defines the FeatureSet class, which allows setting discrete features for a node
+/// Linear softmax classifier learned jointly with EP feature embeddings.  Operates on node
+/// embeddings constructed from those features.
 #[pyclass]
-pub struct FeatureSet {
-    vocab: Arc<Vocab>,
-    features: FeatureStore
+struct NodeClassifier {
+    classes: Vec<String>,
+    classifier: Classifier
 }
 
-impl FeatureSet {
+#[pymethods]
+impl NodeClassifier {
 
-    fn read_vocab_from_file(path: &str) -> PyResult<Vocab> {
-        let mut vocab = Vocab::new();
-        let f = File::open(path)
-            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+    /// Class labels, in the same order as the weights
+    pub fn classes(&self) -> Vec<String> {
+        self.classes.clone()
+    }
 
-        let br = BufReader::new(f);
-        for line in br.lines() {
-            let line = line.unwrap();
-            let pieces: Vec<_> = line.split('\t').collect();
-            if pieces.len() != 3 {
-                return Err(PyValueError::new_err("Malformed feature line! Need node_type<TAB>name<TAB>f1 f2 ..."))
-            }
-            vocab.get_or_insert(pieces[0].into(), pieces[1].into());
-        }
-        Ok(vocab)
+    /// Row major [num_classes, dims] weight matrix
+    pub fn weights(&self) -> Vec<Vec<f32>> {
+        self.classifier.weights.clone()
     }
-}
 
-#[pymethods]
-impl FeatureSet {
+    /// Bias for each class
+    pub fn bias(&self) -> Vec<f32> {
+        self.classifier.bias.clone()
+    }
 
-    ///    Creates a FeatureSet tied to a graph.  We do this to minimize duplicate vocabularies and
-    ///    guaranteed 1:1 mapping of nodes to features
+    ///    Predicts class probabilities for a node embedding.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to construct FeatureSet for
+    ///    embedding : List[Float]
+    ///        Node embedding constructed from the learned feature embeddings.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(Str, Float)] - Can throw exception
+    ///        Class probabilities, sorted from most to least likely.  Throws if the embedding
+    ///        has the wrong dimensions.
+    ///    
+    pub fn predict(&self, embedding: Vec<f32>) -> PyResult<Vec<(String, f32)>> {
+        let dims = self.classifier.weights.first().map(|w| w.len()).unwrap_or(0);
+        if embedding.len() != dims {
+            return Err(PyValueError::new_err(format!(
+                "Embedding has {} dims but the classifier expects {}!", embedding.len(), dims)))
+        }
+
+        let mut preds: Vec<_> = self.classes.iter().cloned()
+            .zip(self.classifier.predict(&embedding).into_iter())
+            .collect();
+        preds.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(preds)
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        let dims = self.classifier.weights.first().map(|w| w.len()).unwrap_or(0);
+        format!("NodeClassifier<classes={}, dims={}>", self.classes.len(), dims)
+    }
+}
+
+/// Splits raw text into features, so training and serving tokenize identically
+#[pyclass]
+pub struct Tokenizer {
+    tokenizer: CTokenizer
+}
+
+#[pymethods]
+impl Tokenizer {
+
+    ///    Creates a Tokenizer.  Text is split on whitespace and punctuation, keeping runs of
+    ///    alphanumeric characters as tokens.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    lowercase : Bool - Optional
+    ///        If true, lowercases tokens.  Default is True.
+    ///    
+    ///    remove_stopwords : Bool - Optional
+    ///        If true, drops stopwords.  Default is False.
+    ///    
+    ///    stopwords : List[String] - Optional
+    ///        Stopwords to drop when remove_stopwords is set.  Default is a built-in English list.
+    ///    
+    ///    min_length : Int - Optional
+    ///        Tokens with fewer characters are dropped.  Default is 1.
+    ///    
+    ///    namespace : String - Optional
+    ///        If provided, tokens are emitted as "namespace:token".  Register the namespace on
+    ///        the FeatureSet to store them under it.  Default is None.
+    ///    
+    ///    word_ngrams : Int - Optional
+    ///        Also emits runs of up to this many consecutive tokens joined by '_', such as
+    ///        "new_york" for 2.  Default is 1, single tokens only.
+    ///    
+    ///    char_ngrams : (Int, Int) - Optional
+    ///        If provided, the inclusive range of character n-gram lengths to emit per token.
+    ///        Tokens are padded with '<' and '>' and n-grams are prefixed with '#', so "john"
+    ///        yields "#<jo", "#joh", "#ohn", "#hn>" for (3, 3).  Useful for typo robust matching.
+    ///        Default is None.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[new]
+    pub fn new(
+        lowercase: Option<bool>,
+        remove_stopwords: Option<bool>,
+        stopwords: Option<Vec<String>>,
+        min_length: Option<usize>,
+        namespace: Option<String>,
+        word_ngrams: Option<usize>,
+        char_ngrams: Option<(usize, usize)>
+    ) -> Self {
+        let stopwords = if remove_stopwords.unwrap_or(false) {
+            stopwords.unwrap_or_else(CTokenizer::english_stopwords)
+        } else {
+            Vec::new()
+        };
+        let tokenizer = CTokenizer::new(lowercase.unwrap_or(true), min_length.unwrap_or(1), namespace, stopwords)
+            .with_ngrams(word_ngrams.unwrap_or(1), char_ngrams);
+        Tokenizer { tokenizer }
+    }
+
+    ///    Splits text into tokens.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    text : String
+    ///        Raw text.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[String]
+    ///        Tokens in order of appearance, including repeats.
+    ///    
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenizer.tokenize(text)
+    }
+
+    ///    Expands already split tokens with the configured n-grams and namespace, for features
+    ///    tokenized elsewhere.  Stopwords and casing are not applied.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    tokens : List[String]
+    ///        Tokens in order.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[String]
+    ///        Tokens followed by word n-grams and character n-grams.
+    ///    
+    pub fn expand(&self, tokens: Vec<String>) -> Vec<String> {
+        self.tokenizer.expand(&tokens)
+    }
+
+    /// Simple Python representation
+    pub fn __repr__(&self) -> String {
+        format!("Tokenizer<lowercase={}, stopwords={}, min_length={}, namespace={:?}, word_ngrams={}, char_ngrams={:?}>",
+            self.tokenizer.lowercase, self.tokenizer.num_stopwords(), self.tokenizer.min_length,
+            self.tokenizer.namespace, self.tokenizer.word_ngrams, self.tokenizer.char_ngrams)
+    }
+}
+
+/// Defines the FeatureSet class, which allows setting discrete features for a node
+#[pyclass]
+pub struct FeatureSet {
+    vocab: Arc<Vocab>,
+    features: FeatureStore
+}
+
+impl FeatureSet {
+
+    fn read_vocab_from_file(path: &str) -> PyResult<Vocab> {
+        let mut vocab = Vocab::new();
+        let f = File::open(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+
+        let br = BufReader::new(f);
+        for line in br.lines() {
+            let line = line.unwrap();
+            let pieces: Vec<_> = line.split('\t').collect();
+            if pieces.len() != 3 {
+                return Err(PyValueError::new_err("Malformed feature line! Need node_type<TAB>name<TAB>f1 f2 ..."))
+            }
+            vocab.get_or_insert(pieces[0].into(), pieces[1].into());
+        }
+        Ok(vocab)
+    }
+}
+
+#[pymethods]
+impl FeatureSet {
+
+    ///    Creates a FeatureSet tied to a graph.  We do this to minimize duplicate vocabularies and
+    ///    guaranteed 1:1 mapping of nodes to features
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to construct FeatureSet for
     ///    
     ///    path : String - Optional
     ///        If provided, reads features from a file.  If not, creates a FeatureSet which is
@@ -1444,175 +2919,540 @@ impl FeatureSet {
         Ok(())
     }
 
-    ///    Retrieves the set of features defined for a node.
+    ///    Sets the features for a Node from raw text, replacing any it has.
     ///    
     ///    Parameters
     ///    ----------
     ///    node : FQNode
     ///        Fully qualified Node.
     ///    
+    ///    text : String
+    ///        Text to tokenize into features.
+    ///    
+    ///    tokenizer : Tokenizer - Optional
+    ///        Tokenizer to use.  Default lowercases and splits on whitespace and punctuation.
+    ///    
     ///    Returns
     ///    -------
-    ///    List[String] - Can throw exception
-    ///        Set of features specified for node
+    ///    () - Can throw exception
+    ///        
     ///    
-    pub fn get_features(&self, node: FQNode) -> PyResult<Vec<String>> {
+    pub fn set_features_from_text(&mut self, node: FQNode, text: &str, tokenizer: Option<&Tokenizer>) -> PyResult<()> {
         let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
-        Ok(self.features.get_pretty_features(node_id))
+        match tokenizer {
+            Some(t) => self.features.set_features_from_text(node_id, text, &t.tokenizer),
+            None => self.features.set_features_from_text(node_id, text, &CTokenizer::default())
+        }
+        Ok(())
     }
 
-    ///    Loads a file defining fully qualified nodes to features into a feature set.
+    ///    Adds features to a Node, keeping those it already has.
     ///    
     ///    Parameters
     ///    ----------
-    ///    path : String
-    ///        Path point to features definitions.
+    ///    node : FQNode
+    ///        Fully qualified Node.
+    ///    
+    ///    features : List[String]
+    ///        Features to add to this node.
     ///    
     ///    Returns
     ///    -------
     ///    () - Can throw exception
     ///        
     ///    
-    pub fn load_into(
-        &mut self, 
-        path: String
-    ) -> PyResult<()> {
-        let reader = open_file_for_reading(&path)
-            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
-
-        for line in reader.lines() {
-            let line = line.unwrap();
-            let pieces: Vec<_> = line.split('\t').collect();
-            if pieces.len() != 3 {
-                return Err(PyValueError::new_err("Malformed feature line! Need node_type<TAB>name<TAB>f1 f2 ..."))
-            }
-            let bow = pieces[2].split_whitespace()
-                .map(|s| s.to_string()).collect();
-            let _ = self.set_features((pieces[0].to_string(), pieces[1].to_string()), bow);
-                
-        }
+    pub fn add_features(&mut self, node: FQNode, features: Vec<String>) -> PyResult<()> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        self.features.add_features(node_id, features);
         Ok(())
     }
 
-    ///    Returns the number of nodes in the feature set.
+    ///    Removes features from a Node.  The features stay in the feature vocabulary, so
+    ///    existing feature embeddings remain valid.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Fully qualified Node.
+    ///    
+    ///    features : List[String]
+    ///        Features to remove from this node.  Unknown features are ignored.
     ///    
     ///    Returns
     ///    -------
-    ///    Int
+    ///    Int - Can throw exception
+    ///        Number of features removed.
     ///    
-    pub fn nodes(&self) -> usize {
-        self.vocab.len()
+    pub fn remove_features(&mut self, node: FQNode, features: Vec<String>) -> PyResult<usize> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.features.remove_features(node_id, &features))
     }
 
-    ///    Returns the number of unique features defined in the featureset
+    ///    Extends feature embeddings trained on an earlier version of this FeatureSet to cover
+    ///    features added since, so they can be passed back into learn_features to fine-tune.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    feature_embeddings : NodeEmbeddings
+    ///        Previously learned feature embeddings.
+    ///    
+    ///    init : FeatureInit - Optional
+    ///        Random initializes new features as random unit vectors, while Average uses the
+    ///        mean embedding of the nodes carrying them.  Default is FeatureInit.Random.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed for initialization.
     ///    
     ///    Returns
     ///    -------
-    ///    Int
-    ///        
+    ///    NodeEmbeddings
+    ///        Feature embeddings covering every feature in the FeatureSet.
     ///    
-    pub fn num_features(&self) -> usize {
-        self.features.num_features()
+    pub fn extend_embeddings(
+        &self,
+        py: Python<'_>,
+        feature_embeddings: &NodeEmbeddings,
+        init: Option<FeatureInit>,
+        seed: Option<u64>
+    ) -> NodeEmbeddings {
+        let init = init.unwrap_or(FeatureInit::Random).to_einit();
+        let embeddings = py.allow_threads(|| {
+            extend_feature_embeddings(
+                &self.features,
+                feature_embeddings.vocab.as_ref(),
+                &feature_embeddings.embeddings,
+                init,
+                seed.unwrap_or(SEED))
+        });
+        NodeEmbeddings {
+            vocab: Arc::new(self.features.clone_vocab()),
+            embeddings
+        }
     }
 
-    ///    Iterator over fully qualified nodes.
+    ///    Assigns features to nodes which have none.  Learners otherwise give each featureless
+    ///    node its own unique feature, which bloats the feature space; call this first to use a
+    ///    different policy.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph the FeatureSet was built for, used to look up node types and neighbors.
+    ///    
+    ///    policy : MissingFeatures - Optional
+    ///        Unique gives each node its own feature, Shared a single "UNK" feature, NodeType an
+    ///        "UNK:<node type>" feature per node type, and Neighbors the distinct features of the
+    ///        node's neighbors, falling back to "UNK".  Default is MissingFeatures.Unique.
     ///    
     ///    Returns
     ///    -------
-    ///    VocabIterator
-    ///        
+    ///    Int
+    ///        Number of nodes filled.
     ///    
-    pub fn vocab(&self) -> VocabIterator {
-        VocabIterator::new(self.vocab.clone())
+    pub fn fill_missing_nodes(&mut self, graph: &Graph, policy: Option<MissingFeatures>) -> usize {
+        let policy = policy.unwrap_or(MissingFeatures::Unique).to_emissing();
+        self.features.fill_missing_nodes_with(policy, graph.vocab.as_ref(), graph.graph.as_ref())
     }
 
-    ///    Returns a new featureset with only features greater than `count`.
+    ///    Registers a feature namespace.  Afterwards, features prefixed with it, such as
+    ///    "brand:acme", are stored under that namespace instead of the default one, letting
+    ///    models treat them separately.  Register namespaces before setting or loading features.
     ///    
     ///    Parameters
     ///    ----------
-    ///    count : Int
-    ///        Filtering threshhold.
+    ///    namespace : String
+    ///        Namespace name, without the trailing colon.
+    ///    
+    ///    cap : Int - Optional
+    ///        If provided, the maximum number of features a node keeps in this namespace.
+    ///        Features beyond the cap are dropped in the order given.  Default is unlimited.
     ///    
     ///    Returns
     ///    -------
-    ///    Self
-    ///        
+    ///    ()
     ///    
-    pub fn prune_min_count(&self, count: usize) -> Self {
-        FeatureSet {
-            features: self.features.prune_min_count(count),
-            vocab: self.vocab.clone()
-        }
+    pub fn add_namespace(&mut self, namespace: String, cap: Option<usize>) {
+        self.features.add_namespace(namespace, cap);
     }
-    
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("FeatureSet<Nodes={},UniqueFeatures={}>", self.vocab.len(), self.features.num_features())
-    }
-
-}
-
-/// Propagates features within a feature set, using a graph to find neighbors.
-#[pyclass]
-pub struct FeaturePropagator {
-    /// Max number of features for each node
-    k: usize,
-
-    /// Filters out feature which don't meet the minimum feature count
-    threshold: f32,
 
-    /// Number of passes to run.  In practice, this should be pretty small.
-    max_iters: usize
-}
+    ///    Returns the registered feature namespaces.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(String, Int | None)]
+    ///        Each namespace with its cap, in registration order.
+    ///    
+    pub fn namespaces(&self) -> Vec<(String, Option<usize>)> {
+        self.features.get_namespaces().iter()
+            .map(|(ns, cap)| (ns.to_string(), *cap))
+            .collect()
+    }
 
-#[pymethods]
-impl FeaturePropagator {
-    ///    Uses a limited form of propagation to fill in FeatureSets where node features are
-    ///    missing.  Of limited value in many cases but can be helpful for extremely large graphs
-    ///    in some cases.
+    ///    Retrieves the set of features defined for a node.
     ///    
     ///    Parameters
     ///    ----------
-    ///    k : Int
-    ///        Max number of nodes to use.
+    ///    node : FQNode
+    ///        Fully qualified Node.
     ///    
-    ///    threshold : Float - Optional
-    ///        if the count / norm is less than threshold, filters out the feature.  Default is 0.
+    ///    Returns
+    ///    -------
+    ///    List[String] - Can throw exception
+    ///        Set of features specified for node
     ///    
-    ///    max_iters : Int - Optional
-    ///        Number of passes to run.  Default is 20.
+    pub fn get_features(&self, node: FQNode) -> PyResult<Vec<String>> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.features.get_pretty_features(node_id))
+    }
+
+    ///    Computes TF-IDF statistics over the FeatureSet in one parallel pass.  Afterwards, the
+    ///    EmbeddingPropagator weights each node's features by TF-IDF when averaging them.
+    ///    Features added later are treated as unseen, so call this once features are loaded.
     ///    
     ///    Returns
     ///    -------
-    ///    Self
-    ///        
+    ///    ()
     ///    
-    #[new]
-    pub fn new(k: usize, threshold: Option<f32>, max_iters: Option<usize>) -> Self {
-        FeaturePropagator { 
-            k: k, 
-            threshold: threshold.unwrap_or(0.),
-            max_iters: max_iters.unwrap_or(20)
-        }
-    }
-    
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("FeaturePropagator<k={},threshold={},max_iters={}>", self.k, self.threshold, self.max_iters)
+    pub fn compute_tf_idf(&mut self) {
+        self.features.compute_tf_idf();
     }
 
-    ///    Propagates features throughout the graph.
+    ///    Retrieves a node's features along with their TF-IDF weights.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to propagate.
+    ///    node : FQNode
+    ///        Fully qualified Node.
     ///    
-    ///    features : mut FeatureSet
-    ///        Features to modify.
+    ///    Returns
+    ///    -------
+    ///    List[(String, Float)] - Can throw exception
+    ///        Each feature occurrence with its weight.  Throws if the node doesn't exist or
+    ///        `compute_tf_idf` hasn't been called.
     ///    
-    pub fn propagate(&self,
-        graph: &Graph,
+    pub fn get_feature_weights(&self, node: FQNode) -> PyResult<Vec<(String, f32)>> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        let weights = self.features.get_feature_weights(node_id)
+            .ok_or_else(|| PyValueError::new_err("TF-IDF hasn't been computed!"))?;
+        Ok(self.features.get_pretty_features(node_id).into_iter().zip(weights).collect())
+    }
+
+    ///    Loads a file defining fully qualified nodes to features into a feature set.  Nodes
+    ///    missing from the FeatureSet are skipped.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///        Path point to features definitions.  See `load_features` for formats.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        
+    ///    
+    pub fn load_into(
+        &mut self, 
+        path: String
+    ) -> PyResult<()> {
+        self.load_features(path, None, None, None).map(|_| ())
+    }
+
+    ///    Loads features from a file in parallel, reporting nodes which aren't in the
+    ///    FeatureSet's vocab.  Later records for a node replace earlier ones.
+    ///    
+    ///    Files ending in .jsonl or .json (optionally gzipped) hold one JSON object per line:
+    ///    {"node_type": "user", "node_name": "u1", "features": ["token:red", "brand:acme"]}
+    ///    where features can also be a single whitespace delimited string.  All other files are
+    ///    read as node_type<TAB>name<TAB>f1 f2 ...
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///        Path to the feature file.
+    ///    
+    ///    chunk_size : Int - Optional
+    ///        Number of records to parse in parallel at once.  Default is 10,000.
+    ///    
+    ///    skip_rows : Int - Optional
+    ///        Number of leading rows to skip, such as a header.  Default is 0.
+    ///    
+    ///    strict : Bool - Optional
+    ///        If True, throws on the first unknown node instead of skipping it.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (Int, Int, List[FQNode]) - Can throw exception
+    ///        Number of records loaded, number of records with unknown nodes, and the first few
+    ///        unknown nodes.  Throws on malformed records.
+    ///    
+    pub fn load_features(
+        &mut self,
+        path: String,
+        chunk_size: Option<usize>,
+        skip_rows: Option<usize>,
+        strict: Option<bool>
+    ) -> PyResult<(usize, usize, Vec<FQNode>)> {
+        let report = FeatureReader::load(
+            &path,
+            self.vocab.deref(),
+            &mut self.features,
+            chunk_size.unwrap_or(10_000),
+            skip_rows.unwrap_or(0),
+            strict.unwrap_or(false))?;
+
+        let examples = report.unknown_examples.into_iter()
+            .map(|(_, node_type, name)| (node_type, name))
+            .collect();
+        Ok((report.loaded, report.unknown, examples))
+    }
+
+    ///    Creates a FeatureSet for another graph which shares this one's feature vocabulary,
+    ///    namespaces, and TF-IDF statistics.  Feature embeddings learned against this FeatureSet
+    ///    apply directly to the new one, allowing a model trained on one graph to embed another.
+    ///    Features added to either afterwards don't affect the other.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to create the FeatureSet for.
+    ///    
+    ///    copy_features : Bool - Optional
+    ///        If true, nodes present in both graphs keep their features.  Otherwise the new
+    ///        FeatureSet starts empty.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    FeatureSet
+    ///    
+    pub fn share_with(&self, graph: &Graph, copy_features: Option<bool>) -> FeatureSet {
+        let table = if copy_features.unwrap_or(true) {
+            self.vocab.create_translation_table(graph.vocab.as_ref())
+        } else {
+            Vec::new()
+        };
+        FeatureSet {
+            vocab: graph.vocab.clone(),
+            features: self.features.remap_nodes(&table, graph.graph.len())
+        }
+    }
+
+    ///    Saves the FeatureSet, its feature vocab and namespaces, TF-IDF statistics, and
+    ///    optionally the learned feature embeddings, as a single checksummed file.  Loading the
+    ///    bundle later is enough to embed new nodes from their features.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///        Where to save the bundle.
+    ///    
+    ///    feature_embeddings : NodeEmbeddings - Optional
+    ///        Feature embeddings to store alongside, such as those learned by the
+    ///        EmbeddingPropagator.  Default is None.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn save_bundle(&self, path: &str, feature_embeddings: Option<&NodeEmbeddings>) -> PyResult<()> {
+        let fe = feature_embeddings.map(|fe| (fe.vocab.as_ref(), &fe.embeddings));
+        FeatureBundle::save(path, self.vocab.as_ref(), &self.features, fe)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
+    }
+
+    ///    Loads a bundle written by save_bundle, verifying its checksum.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : String
+    ///        Path to the saved bundle.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (FeatureSet, NodeEmbeddings | None) - Can throw exception
+    ///        The FeatureSet and the feature embeddings, if any were saved.
+    ///    
+    #[staticmethod]
+    pub fn load_bundle(py: Python<'_>, path: &str) -> PyResult<(FeatureSet, Option<NodeEmbeddings>)> {
+        let (vocab, features, fe) = py.allow_threads(move || FeatureBundle::load(path))
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        let fs = FeatureSet { vocab: Arc::new(vocab), features };
+        let fe = fe.map(|(vocab, embeddings)| NodeEmbeddings { vocab: Arc::new(vocab), embeddings });
+        Ok((fs, fe))
+    }
+
+    ///    Returns the number of nodes in the feature set.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int
+    ///    
+    pub fn nodes(&self) -> usize {
+        self.vocab.len()
+    }
+
+    ///    Returns the number of unique features defined in the featureset
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int
+    ///        
+    ///    
+    pub fn num_features(&self) -> usize {
+        self.features.num_features()
+    }
+
+    ///    Counts how many nodes each pair of distinct features appears on together.  Useful for
+    ///    pruning the feature vocabulary or clustering features.  Cost grows quadratically with
+    ///    the number of features on a node.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    min_count : Int - Optional
+    ///        Minimum number of nodes a pair must co-occur on to be returned.  Default is 1.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(String, String, Int)]
+    ///        Each co-occurring feature pair and its count.
+    ///    
+    pub fn feature_cooccurrence(&self, py: Python<'_>, min_count: Option<usize>) -> Vec<(String, String, usize)> {
+        let pairs = py.allow_threads(|| self.features.count_cooccurrences(min_count.unwrap_or(1)));
+        pairs.into_iter()
+            .map(|(f_i, f_j, c)| (self.features.get_pretty_feature(f_i), self.features.get_pretty_feature(f_j), c))
+            .collect()
+    }
+
+    ///    Computes the pointwise mutual information between co-occurring features, treating
+    ///    each node as an observation.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    min_count : Int - Optional
+    ///        Minimum number of nodes a pair must co-occur on to be returned.  PMI is noisy for
+    ///        rare pairs.  Default is 1.
+    ///    
+    ///    normalized : Bool - Optional
+    ///        If true, returns normalized PMI in [-1, 1], where 1 means the features always
+    ///        appear together.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(String, String, Float)]
+    ///        Each co-occurring feature pair and its PMI.
+    ///    
+    pub fn feature_pmi(
+        &self,
+        py: Python<'_>,
+        min_count: Option<usize>,
+        normalized: Option<bool>
+    ) -> Vec<(String, String, f32)> {
+        let pairs = py.allow_threads(|| {
+            self.features.pmi(min_count.unwrap_or(1), normalized.unwrap_or(false))
+        });
+        pairs.into_iter()
+            .map(|(f_i, f_j, pmi)| (self.features.get_pretty_feature(f_i), self.features.get_pretty_feature(f_j), pmi))
+            .collect()
+    }
+
+    ///    Iterator over fully qualified nodes.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    VocabIterator
+    ///        
+    ///    
+    pub fn vocab(&self) -> VocabIterator {
+        VocabIterator::new(self.vocab.clone())
+    }
+
+    ///    Returns a new featureset with only features appearing on at least `count` nodes.
+    ///    Remaining features are renumbered, so feature embeddings only cover what survives.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    count : Int
+    ///        Minimum number of nodes a feature must appear on.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    pub fn prune_min_count(&self, count: usize) -> Self {
+        FeatureSet {
+            features: self.features.prune_min_count(count),
+            vocab: self.vocab.clone()
+        }
+    }
+    
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("FeatureSet<Nodes={},UniqueFeatures={}>", self.vocab.len(), self.features.num_features())
+    }
+
+}
+
+/// Propagates features within a feature set, using a graph to find neighbors.
+#[pyclass]
+pub struct FeaturePropagator {
+    /// Max number of features for each node
+    k: usize,
+
+    /// Filters out feature which don't meet the minimum feature count
+    threshold: f32,
+
+    /// Number of passes to run.  In practice, this should be pretty small.
+    max_iters: usize
+}
+
+#[pymethods]
+impl FeaturePropagator {
+    ///    Uses a limited form of propagation to fill in FeatureSets where node features are
+    ///    missing.  Of limited value in many cases but can be helpful for extremely large graphs
+    ///    in some cases.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    k : Int
+    ///        Max number of nodes to use.
+    ///    
+    ///    threshold : Float - Optional
+    ///        if the count / norm is less than threshold, filters out the feature.  Default is 0.
+    ///    
+    ///    max_iters : Int - Optional
+    ///        Number of passes to run.  Default is 20.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(k: usize, threshold: Option<f32>, max_iters: Option<usize>) -> Self {
+        FeaturePropagator { 
+            k: k, 
+            threshold: threshold.unwrap_or(0.),
+            max_iters: max_iters.unwrap_or(20)
+        }
+    }
+    
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("FeaturePropagator<k={},threshold={},max_iters={}>", self.k, self.threshold, self.max_iters)
+    }
+
+    ///    Propagates features throughout the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to propagate.
+    ///    
+    ///    features : mut FeatureSet
+    ///        Features to modify.
+    ///    
+    pub fn propagate(&self,
+        graph: &Graph,
         features: &mut FeatureSet
     ) {
         propagate_features(
@@ -1899,6 +3739,21 @@ impl NodeEmbedder {
         }
     }
 
+    /// Translates a node's features into ids in the feature embeddings, dropping features
+    /// without one
+    fn translate_features(
+        &self,
+        feat_set: &FeatureSet,
+        node: FQNode,
+        feature_embeddings: &NodeEmbeddings
+    ) -> PyResult<Vec<NodeID>> {
+        let node_id = get_node_id(feat_set.vocab.deref(), node.0, node.1)?;
+        let fs_vocab = feat_set.features.get_vocab();
+        Ok(feat_set.features.get_features(node_id).iter()
+            .filter_map(|feat_id| feature_embeddings.vocab.translate_node(fs_vocab, *feat_id as usize))
+            .collect())
+    }
+
     fn get_dims(&self, feat_embs: &NodeEmbeddings) -> usize {
         match self.feat_agg.at {
             AggregatorType::Attention { num_heads, d_k, window:_ } =>  {
@@ -1968,7 +3823,7 @@ impl NodeEmbedder {
             // Translate nodes
             let new_feats: Vec<_> = feat_set.features.get_features(node)
                 .iter()
-                .map(|feat_id| feature_embeddings.vocab.translate_node(&fs_vocab, *feat_id))
+                .map(|feat_id| feature_embeddings.vocab.translate_node(&fs_vocab, *feat_id as usize))
                 .filter(|n| n.is_some())
                 .map(|n| n.unwrap())
                 .collect();
@@ -2065,41 +3920,130 @@ impl NodeEmbedder {
         results
     }
 
-}
-
-/// Struct for defining ALT embeddings
-#[pyclass]
-struct DistanceEmbedder {
-    landmarks: algos::dist::LandmarkSelection,
-    n_landmarks: usize
-}
-
-#[pymethods]
-impl DistanceEmbedder {
-    ///    Creates a Distance embedding.  Distance embedding uses a method called landmark
-    ///    triangulation to compute a "Distance" embedding.  The procedure works as follows:
-    ///
-    ///    1. Select K landmarks.
-    ///    2. Compute the distance from each of those landmarks to every node in the graph.
-    ///    3. Create an embedding which is the distance for each node.
-    ///    4. Compute distances between modes using an algorithm called ALT (A* landmark
-    ///       triangulation).
-    ///
-    ///    It's very fast and can encode useful properties of a graph, but does require a fully
-    ///    connected graph.
+    ///    Explains a node's embedding by removing each of its features in turn and measuring
+    ///    how much the embedding changes.
     ///    
     ///    Parameters
     ///    ----------
-    ///    n_landmarks : Int
-    ///        Number of landmarks to use.  The larger the landmark size, the greater the fidelity
-    ///        of the embedding.
+    ///    feat_set : FeatureSet
+    ///        Feature set containing the node.
     ///    
-    ///    seed : Int - Optional
-    ///        If provided, randomly selects landmarks randomly rather than using the top K
-    ///        landmarks by degree.
+    ///    node : FQNode
+    ///        Node to explain.
     ///    
-    ///    Returns
-    ///    -------
+    ///    feature_embeddings : NodeEmbeddings
+    ///        Feature embeddings.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, Float)] - Can throw exception
+    ///        Each feature with an embedding, and the norm of the change removing it causes
+    ///        relative to the embedding's norm.  Sorted by contribution, largest first.
+    ///    
+    pub fn explain_embedding(
+        &self,
+        feat_set: &FeatureSet,
+        node: FQNode,
+        feature_embeddings: &NodeEmbeddings
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let feats = self.translate_features(feat_set, node, feature_embeddings)?;
+        let dims = self.get_dims(feature_embeddings);
+        let agg = self.get_aggregator(&feature_embeddings.embeddings, &self.feat_agg.at);
+        Ok(attribute_embedding(agg.as_ref(), dims, &feats).into_iter()
+            .map(|(f_i, c)| (feature_name(feature_embeddings, f_i), c))
+            .collect())
+    }
+
+    ///    Explains why two nodes are close by removing each feature from its node in turn and
+    ///    measuring how much the distance between the nodes changes.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    feat_set : FeatureSet
+    ///        Feature set containing both nodes.
+    ///    
+    ///    left : FQNode
+    ///        First node.
+    ///    
+    ///    right : FQNode
+    ///        Second node.
+    ///    
+    ///    feature_embeddings : NodeEmbeddings
+    ///        Feature embeddings.  Their distance metric is used to compare the nodes.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (Float, List[(FQNode, Float | None)], List[(FQNode, Float | None)]) - Can throw exception
+    ///        Distance between the nodes, then the contributions of the left and right node's
+    ///        features, largest first.  Positive contributions pull the nodes together.  A
+    ///        node's only feature has a contribution of None, since it can't be removed.  Throws
+    ///        if either node has no features with embeddings.
+    ///    
+    pub fn explain_similarity(
+        &self,
+        feat_set: &FeatureSet,
+        left: FQNode,
+        right: FQNode,
+        feature_embeddings: &NodeEmbeddings
+    ) -> PyResult<(f32, Vec<(FQNode, Option<f32>)>, Vec<(FQNode, Option<f32>)>)> {
+        let left = self.translate_features(feat_set, left, feature_embeddings)?;
+        let right = self.translate_features(feat_set, right, feature_embeddings)?;
+        let dims = self.get_dims(feature_embeddings);
+        let distance = feature_embeddings.embeddings.distance();
+        let agg = self.get_aggregator(&feature_embeddings.embeddings, &self.feat_agg.at);
+        let pair = attribute_similarity(agg.as_ref(), dims, distance, &left, &right)
+            .ok_or_else(|| PyValueError::new_err("Both nodes need features with embeddings!"))?;
+
+        let names = |attributions: Vec<(usize, Option<f32>)>| {
+            attributions.into_iter()
+                .map(|(f_i, c)| (feature_name(feature_embeddings, f_i), c))
+                .collect()
+        };
+        Ok((pair.distance, names(pair.left), names(pair.right)))
+    }
+
+}
+
+/// Fully qualified name of a feature in a set of feature embeddings
+fn feature_name(feature_embeddings: &NodeEmbeddings, feat_id: NodeID) -> FQNode {
+    let (node_type, name) = feature_embeddings.vocab.get_name(feat_id)
+        .expect("Feature id should always be in the vocab!");
+    (node_type.to_string(), name.to_string())
+}
+
+/// Struct for defining ALT embeddings
+#[pyclass]
+struct DistanceEmbedder {
+    landmarks: algos::dist::LandmarkSelection,
+    n_landmarks: usize
+}
+
+#[pymethods]
+impl DistanceEmbedder {
+    ///    Creates a Distance embedding.  Distance embedding uses a method called landmark
+    ///    triangulation to compute a "Distance" embedding.  The procedure works as follows:
+    ///
+    ///    1. Select K landmarks.
+    ///    2. Compute the distance from each of those landmarks to every node in the graph.
+    ///    3. Create an embedding which is the distance for each node.
+    ///    4. Compute distances between modes using an algorithm called ALT (A* landmark
+    ///       triangulation).
+    ///
+    ///    It's very fast and can encode useful properties of a graph, but does require a fully
+    ///    connected graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    n_landmarks : Int
+    ///        Number of landmarks to use.  The larger the landmark size, the greater the fidelity
+    ///        of the embedding.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, randomly selects landmarks randomly rather than using the top K
+    ///        landmarks by degree.
+    ///    
+    ///    Returns
+    ///    -------
     ///    Self
     ///        
     ///    
@@ -2210,6 +4154,232 @@ impl ClusterLPAEmbedder {
     }
 }
 
+/// Modularity based community detection.  Produces a hierarchy of increasingly coarse
+/// communities which, unlike LPA, is stable for a given seed.
+#[pyclass]
+struct Louvain {
+    resolution: f32,
+    max_levels: usize,
+    passes: usize
+}
+
+#[pymethods]
+impl Louvain {
+
+    ///    Creates a Louvain instance.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    resolution : Float - Optional
+    ///        Modularity resolution.  Higher values produce more, smaller communities.  Default
+    ///        is 1.
+    ///    
+    ///    max_levels : Int - Optional
+    ///        Maximum number of levels in the hierarchy.  Default is 10.
+    ///    
+    ///    passes : Int - Optional
+    ///        Maximum number of passes over the nodes at each level.  Default is 10.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[new]
+    pub fn new(resolution: Option<f32>, max_levels: Option<usize>, passes: Option<usize>) -> Self {
+        Louvain {
+            resolution: resolution.unwrap_or(1.),
+            max_levels: max_levels.unwrap_or(10),
+            passes: passes.unwrap_or(10)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("Louvain<resolution={}, max_levels={}, passes={}>", 
+                self.resolution, self.max_levels, self.passes)
+    }
+
+    ///    Detects communities in the graph.  The graph is treated as undirected, using the
+    ///    transition probabilities of each edge as its weight.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to partition.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Communities
+    ///        Community hierarchy from finest to coarsest.
+    ///    
+    pub fn detect(&self, py: Python<'_>, graph: &Graph, seed: Option<u64>) -> Communities {
+        let louvain = CLouvain {
+            resolution: self.resolution,
+            max_levels: self.max_levels,
+            passes: self.passes,
+            seed: seed.unwrap_or(SEED)
+        };
+        let g = graph.graph.as_ref();
+        let communities = py.allow_threads(move || louvain.detect_cdf(g));
+        Communities { vocab: graph.vocab.clone(), communities }
+    }
+}
+
+/// Community hierarchy found by Louvain.  Levels are ordered from finest to coarsest; methods
+/// default to the coarsest level.
+#[pyclass]
+struct Communities {
+    vocab: Arc<Vocab>,
+    communities: CCommunities
+}
+
+impl Communities {
+    fn get_level(&self, level: Option<usize>) -> PyResult<usize> {
+        let num_levels = self.communities.levels.len();
+        match level {
+            None => Ok(num_levels - 1),
+            Some(level) if level < num_levels => Ok(level),
+            Some(level) => Err(PyValueError::new_err(format!("Level {} doesn't exist!", level)))
+        }
+    }
+}
+
+#[pymethods]
+impl Communities {
+
+    /// Number of levels in the hierarchy
+    pub fn __len__(&self) -> usize {
+        self.communities.levels.len()
+    }
+
+    ///    Returns the community for every node.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    level : Int - Optional
+    ///        Level of the hierarchy.  Default is the coarsest.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, Int)] - Can throw exception
+    ///        Throws if the level doesn't exist.
+    ///    
+    pub fn assignments(&self, level: Option<usize>) -> PyResult<Vec<(FQNode, usize)>> {
+        let level = self.get_level(level)?;
+        let vocab = self.vocab.deref();
+        Ok(self.communities.levels[level].iter().enumerate()
+            .map(|(node_id, c)| (convert_node_id_to_fqn(vocab, node_id), *c))
+            .collect())
+    }
+
+    ///    Looks up the community for a node.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Node to look up.
+    ///    
+    ///    level : Int - Optional
+    ///        Level of the hierarchy.  Default is the coarsest.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int - Can throw exception
+    ///        Community id.  Throws if the node or level doesn't exist.
+    ///    
+    pub fn get_community(&self, node: FQNode, level: Option<usize>) -> PyResult<usize> {
+        let level = self.get_level(level)?;
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.communities.levels[level][node_id])
+    }
+
+    ///    Returns the nodes in a community.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    community : Int
+    ///        Community id.
+    ///    
+    ///    level : Int - Optional
+    ///        Level of the hierarchy.  Default is the coarsest.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[FQNode] - Can throw exception
+    ///        Throws if the community or level doesn't exist.
+    ///    
+    pub fn members(&self, community: usize, level: Option<usize>) -> PyResult<Vec<FQNode>> {
+        let level = self.get_level(level)?;
+        if community >= self.communities.num_communities(level) {
+            return Err(PyValueError::new_err(format!("Community {} doesn't exist!", community)))
+        }
+        let vocab = self.vocab.deref();
+        Ok(self.communities.levels[level].iter().enumerate()
+            .filter(|(_, c)| **c == community)
+            .map(|(node_id, _)| convert_node_id_to_fqn(vocab, node_id))
+            .collect())
+    }
+
+    ///    Number of communities at a level.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    level : Int - Optional
+    ///        Level of the hierarchy.  Default is the coarsest.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int - Can throw exception
+    ///        Throws if the level doesn't exist.
+    ///    
+    pub fn num_communities(&self, level: Option<usize>) -> PyResult<usize> {
+        let level = self.get_level(level)?;
+        Ok(self.communities.num_communities(level))
+    }
+
+    /// Modularity of each level, from finest to coarsest
+    pub fn modularity(&self) -> Vec<f32> {
+        self.communities.modularity.clone()
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        let last = self.communities.levels.len() - 1;
+        format!("Communities<levels={}, communities={}, modularity={}>", 
+                self.communities.levels.len(), 
+                self.communities.num_communities(last),
+                self.communities.modularity[last])
+    }
+}
+
+///    Determines how NodeEmbeddings.merge resolves nodes which exist in both embedding sets.
+///
+#[pyclass]
+#[derive(Clone,Copy)]
+pub enum MergeStrategy {
+    /// Replaces existing embeddings with the incoming ones
+    Overwrite,
+
+    /// Averages existing and incoming embeddings
+    Average,
+
+    /// Keeps existing embeddings, only adding new nodes
+    Keep
+}
+
+impl MergeStrategy {
+    fn to_estrategy(&self) -> EMergeStrategy {
+        match self {
+            MergeStrategy::Overwrite => EMergeStrategy::Overwrite,
+            MergeStrategy::Average => EMergeStrategy::Average,
+            MergeStrategy::Keep => EMergeStrategy::Keep
+        }
+    }
+}
+
 #[pyclass]
 #[derive(Clone,Copy)]
 pub enum ListenerRule {
@@ -2388,31 +4558,84 @@ impl PageRank {
         }
     }
 
-}
+    ///    Computes PageRank and adds each node's score, bucketed by rank, as a discrete feature
+    ///    such as "pagerank:9".  Rank buckets capture importance without depending on the scale
+    ///    of the scores.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use.
+    ///    
+    ///    features : FeatureSet
+    ///        FeatureSet to add the features to.  Existing features are kept.
+    ///    
+    ///    buckets : Int - Optional
+    ///        Number of equally sized rank buckets.  Default is 10.
+    ///    
+    ///    prefix : str - Optional
+    ///        Feature prefix.  Default is "pagerank".
+    ///    
+    ///    indicator : Bool - Optional
+    ///        If provided, uses an indicator.  Default is True
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        Throws if the FeatureSet doesn't share the graph's vocab.
+    ///    
+    pub fn add_features(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        features: &mut FeatureSet,
+        buckets: Option<usize>,
+        prefix: Option<String>,
+        indicator: Option<bool>
+    ) -> PyResult<()> {
+        if !features.vocab.is_identical(&graph.vocab) {
+            return Err(PyValueError::new_err("FeatureSet and Graph must share a vocab!"))
+        }
 
+        let page_rank = crate::algos::pagerank::PageRank::new(self.iterations, self.damping, self.eps);
+        let g = graph.graph.as_ref();
+        let scores = py.allow_threads(move || page_rank.compute(g, indicator.unwrap_or(true)));
+        let prefix = prefix.unwrap_or_else(|| "pagerank".to_string());
+        quantile_buckets(&scores, buckets.unwrap_or(10)).into_iter().enumerate().for_each(|(node_id, bucket)| {
+            features.features.add_features(node_id, vec![format!("{}:{}", prefix, bucket)]);
+        });
+        Ok(())
+    }
 
+}
 
-/// Wrapper for EmbeddingStore.
+/// Computes sampled betweenness and closeness centrality for all nodes in the graph.
 #[pyclass]
-pub struct NodeEmbeddings {
-    vocab: Arc<Vocab>,
-    embeddings: EmbeddingStore
+struct Centrality {
+    pivots: usize,
+    weighted: bool
+}
+
+impl Centrality {
+    fn to_centrality(&self, seed: Option<u64>) -> CCentrality {
+        CCentrality { pivots: self.pivots, weighted: self.weighted, seed: seed.unwrap_or(SEED) }
+    }
 }
 
 #[pymethods]
-impl NodeEmbeddings {
-    ///    Creates a NodeEmbedding set from a given graph.
+impl Centrality {
+    ///    Initializes a Centrality struct.  Shortest paths are searched from a random sample of
+    ///    pivot nodes, then extrapolated to the whole graph.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to use.
-    ///    
-    ///    dims : Int
-    ///        Number of dimensions for each embedding.
+    ///    pivots : Int - Optional
+    ///        Number of source nodes to sample.  Results are exact when this is at least the
+    ///        number of nodes in the graph.  Default is 256.
     ///    
-    ///    distance : Distance
-    ///        Distance metric for computing distances.
+    ///    weighted : Bool - Optional
+    ///        If True, edge lengths are the reciprocal of their transition probabilities, so
+    ///        likelier edges are shorter.  Otherwise every edge has unit length.  Default is False.
     ///    
     ///    Returns
     ///    -------
@@ -2420,27 +4643,149 @@ impl NodeEmbeddings {
     ///        
     ///    
     #[new]
-    pub fn new(graph: &Graph, dims: usize, distance: Distance) -> Self {
-        let dist = distance.to_edist();
-
-        let es = EmbeddingStore::new(graph.graph.len(), dims, dist);
-        NodeEmbeddings {
-            vocab: graph.vocab.clone(),
-            embeddings: es
-        }
+    pub fn new(pivots: Option<usize>, weighted: Option<bool>) -> Self {
+        Centrality { pivots: pivots.unwrap_or(256), weighted: weighted.unwrap_or(false) }
     }
 
-    #[staticmethod]
-    pub fn new_from_list(
-        list: Vec<((String, String), Vec<f32>)>, 
-        distance: Distance
-    ) -> Self {
-        let mut vocab = Vocab::new();
-        let mut embs = Vec::with_capacity(list.len());
-        let mut max_emb_size = 0;
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("Centrality<pivots={}, weighted={}>", self.pivots, self.weighted)
+    }
 
-        // Add to vocab
-        list.into_iter().for_each(|((node_type, node_name), emb)| {
+    ///    Computes betweenness and closeness centrality on a graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed for sampling pivots.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (NodeEmbeddings, NodeEmbeddings)
+    ///        Betweenness and closeness, each as NodeEmbeddings of dimension=1.  Betweenness is
+    ///        the estimated number of shortest paths through a node, while closeness is the mean
+    ///        reciprocal distance to the node from the rest of the graph.
+    ///    
+    pub fn compute(&self, py: Python<'_>, graph: &Graph, seed: Option<u64>) -> (NodeEmbeddings, NodeEmbeddings) {
+        let centrality = self.to_centrality(seed);
+        let g = graph.graph.as_ref();
+        let scores = py.allow_threads(move || centrality.compute_cdf(g));
+        let to_embeddings = |scores: Vec<f32>| {
+            let es = EmbeddingStore::new(scores.len(), 1, EDist::Euclidean);
+            scores.par_iter().enumerate().for_each(|(node_id, score)| {
+                let e1 = es.get_embedding_mut_hogwild(node_id);
+                e1[0] = *score;
+            });
+            NodeEmbeddings {
+                vocab: graph.vocab.clone(),
+                embeddings: es
+            }
+        };
+        (to_embeddings(scores.betweenness), to_embeddings(scores.closeness))
+    }
+
+    ///    Computes centrality and adds each node's scores, bucketed by rank, as discrete features
+    ///    such as "betweenness:9" and "closeness:3".
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use.
+    ///    
+    ///    features : FeatureSet
+    ///        FeatureSet to add the features to.  Existing features are kept.
+    ///    
+    ///    buckets : Int - Optional
+    ///        Number of equally sized rank buckets.  Default is 10.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed for sampling pivots.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        Throws if the FeatureSet doesn't share the graph's vocab.
+    ///    
+    pub fn add_features(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        features: &mut FeatureSet,
+        buckets: Option<usize>,
+        seed: Option<u64>
+    ) -> PyResult<()> {
+        if !features.vocab.is_identical(&graph.vocab) {
+            return Err(PyValueError::new_err("FeatureSet and Graph must share a vocab!"))
+        }
+
+        let centrality = self.to_centrality(seed);
+        let g = graph.graph.as_ref();
+        let scores = py.allow_threads(move || centrality.compute_cdf(g));
+        let buckets = buckets.unwrap_or(10);
+        for (prefix, values) in [("betweenness", &scores.betweenness), ("closeness", &scores.closeness)] {
+            quantile_buckets(values, buckets).into_iter().enumerate().for_each(|(node_id, bucket)| {
+                features.features.add_features(node_id, vec![format!("{}:{}", prefix, bucket)]);
+            });
+        }
+        Ok(())
+    }
+
+}
+
+
+
+/// Wrapper for EmbeddingStore.
+#[pyclass]
+pub struct NodeEmbeddings {
+    vocab: Arc<Vocab>,
+    embeddings: EmbeddingStore
+}
+
+#[pymethods]
+impl NodeEmbeddings {
+    ///    Creates a NodeEmbedding set from a given graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use.
+    ///    
+    ///    dims : Int
+    ///        Number of dimensions for each embedding.
+    ///    
+    ///    distance : Distance
+    ///        Distance metric for computing distances.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(graph: &Graph, dims: usize, distance: Distance) -> Self {
+        let dist = distance.to_edist();
+
+        let es = EmbeddingStore::new(graph.graph.len(), dims, dist);
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings: es
+        }
+    }
+
+    #[staticmethod]
+    pub fn new_from_list(
+        list: Vec<((String, String), Vec<f32>)>, 
+        distance: Distance
+    ) -> Self {
+        let mut vocab = Vocab::new();
+        let mut embs = Vec::with_capacity(list.len());
+        let mut max_emb_size = 0;
+
+        // Add to vocab
+        list.into_iter().for_each(|((node_type, node_name), emb)| {
             let node_id = vocab.get_or_insert(node_type, node_name);
             max_emb_size = max_emb_size.max(emb.len());
             embs.push((node_id, emb))
@@ -2518,6 +4863,170 @@ impl NodeEmbeddings {
         Ok(())
     }
 
+    ///    Sets the embedding for a node, adding the node if it doesn't exist yet.  Unlike
+    ///    `set_embedding`, this allows NodeEmbeddings to grow as new nodes are discovered.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Fully qualified Node
+    ///    
+    ///    embedding : List[Float]
+    ///        Embedding to set it to.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        Throws an exception if the embedding dimensions don't match.
+    ///    
+    pub fn add_embedding(&mut self, node: FQNode, embedding: Vec<f32>) -> PyResult<()> {
+        if embedding.len() != self.embeddings.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions mismatch!"));
+        }
+
+        let vocab = Arc::make_mut(&mut self.vocab);
+        let node_id = vocab.get_or_insert(node.0, node.1);
+        if node_id < self.embeddings.len() {
+            self.embeddings.set_embedding(node_id, &embedding);
+        } else {
+            self.embeddings.push_embedding(&embedding);
+        }
+        Ok(())
+    }
+
+    ///    Computes the pairwise distance matrix between these embeddings and another set, using
+    ///    this set's distance metric.  Rows correspond to nodes in this set and columns to nodes
+    ///    in other, both in vocab order.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    other : NodeEmbeddings
+    ///        Embeddings to compare against.  Must have the same dimensions.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[Float]] - Can throw exception
+    ///        Distance matrix.
+    ///    
+    pub fn distance_matrix(
+        &self, 
+        py: Python<'_>, 
+        other: &NodeEmbeddings
+    ) -> PyResult<Vec<Vec<f32>>> {
+        if other.embeddings.dims() != self.embeddings.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions mismatch!"));
+        }
+
+        let n_cols = other.embeddings.len();
+        let dm = py.allow_threads(move || {
+            self.embeddings.distance_matrix(&other.embeddings)
+        });
+        Ok((0..self.embeddings.len())
+            .map(|row| dm[row * n_cols..(row + 1) * n_cols].to_vec())
+            .collect())
+    }
+
+    ///    Computes the centroid of a set of nodes, such as a user profile built from the items
+    ///    they've interacted with.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    nodes : List[FQNode]
+    ///        Nodes to average
+    ///    
+    ///    weights : List[Float] - Optional
+    ///        If provided, computes a weighted average.  Must be the same length as nodes.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float] - Can throw exception
+    ///        Centroid embedding.  Throws an exception if a node doesn't exist.
+    ///    
+    pub fn mean_of(&self, nodes: Vec<FQNode>, weights: Option<Vec<f32>>) -> PyResult<Vec<f32>> {
+        let node_ids = nodes.into_iter()
+            .map(|(node_type, name)| get_node_id(self.vocab.deref(), node_type, name))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        if let Some(weights) = weights {
+            if weights.len() != node_ids.len() {
+                return Err(PyValueError::new_err("Nodes and weights must be the same length!"));
+            }
+            let weighted: Vec<_> = node_ids.into_iter().zip(weights.into_iter()).collect();
+            Ok(self.embeddings.weighted_mean_of(&weighted))
+        } else {
+            Ok(self.embeddings.mean_of(&node_ids))
+        }
+    }
+
+    ///    Merges another set of embeddings into this one, such as those from an incremental
+    ///    training run.  Nodes which don't exist yet are added.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    other : NodeEmbeddings
+    ///        Embeddings to merge in.  Must have the same dimensions.
+    ///    
+    ///    strategy : MergeStrategy
+    ///        How to resolve nodes which exist in both.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        Throws an exception if the embedding dimensions don't match.
+    ///    
+    pub fn merge(&mut self, other: &NodeEmbeddings, strategy: MergeStrategy) -> PyResult<()> {
+        if other.embeddings.dims() != self.embeddings.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions mismatch!"));
+        }
+
+        let tt = other.vocab.create_translation_table(self.vocab.deref());
+        self.embeddings.merge(&other.embeddings, &tt, strategy.to_estrategy());
+
+        // Add the nodes we don't know about yet
+        let vocab = Arc::make_mut(&mut self.vocab);
+        tt.iter().enumerate()
+            .filter(|(other_id, node_id)| node_id.is_none() && other.embeddings.is_set(*other_id))
+            .for_each(|(other_id, _)| {
+                let (node_type, name) = other.vocab.get_name(other_id)
+                    .expect("Should never be missing!");
+                vocab.get_or_insert_shared(node_type, name);
+                self.embeddings.push_embedding(other.embeddings.get_embedding(other_id));
+            });
+        Ok(())
+    }
+
+    ///    Checks whether a node's embedding was learned during training rather than left at its
+    ///    initialization.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Fully qualified Node
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Bool - Can throw exception
+    ///        True if the embedding has been trained or explicitly set.
+    ///    
+    pub fn is_trained(&self, node: FQNode) -> PyResult<bool> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.embeddings.is_trained(node_id))
+    }
+
+    ///    Returns all nodes whose embeddings were never trained.  These are typically still
+    ///    random and shouldn't be used for retrieval.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[FQNode]
+    ///        Untrained nodes
+    ///    
+    pub fn untrained_nodes(&self) -> Vec<FQNode> {
+        self.embeddings.untrained_nodes()
+            .map(|node_id| convert_node_id_to_fqn(&self.vocab, node_id))
+            .collect()
+    }
+
     ///    Iterates over the Nodes defined in the NodeEmbeddings.
     ///    
     ///    Returns
@@ -2559,18 +5068,58 @@ impl NodeEmbeddings {
     ) -> Vec<(FQNode, f32)> {
         let emb = Entity::Embedding(&emb);
         let dists = if let Some(node_type) = filter_type {
-            let ant = Arc::new(node_type);
-            let filter_type = Some(&ant);
-            self.embeddings.nearest_neighbor(&emb, k, |node_id| {
-                let nt = self.vocab.get_node_type(node_id);
-                nt == filter_type
-            })
+            self.embeddings.nearest_neighbors_of_type(&emb, k, &self.vocab, &node_type)
         } else {
             self.embeddings.nearest_neighbor(&emb, k, |_node_id| true)
         };
         convert_node_distance(&self.vocab, dists)
     }
 
+    ///    Finds the nearest K neighbors for each embedding in a batch.  This is substantially
+    ///    faster than calling `nearest_neighbor` in a loop as the embeddings are only scanned
+    ///    once for the whole batch.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embs : List[List[Float]]
+    ///        Embeddings to nearest neighbor
+    ///    
+    ///    k : Int
+    ///        Top K items to return for each embedding
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only returns nodes matching the filter_type
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[(FQNode, f32)]]
+    ///        Set of fully qualified nodes and distances for each embedding.
+    ///    
+    pub fn bulk_nearest_neighbor(
+        &self, 
+        py: Python<'_>,
+        embs: Vec<Vec<f32>>, 
+        k: usize,
+        filter_type: Option<String>
+    ) -> Vec<Vec<(FQNode, f32)>> {
+        py.allow_threads(move || {
+            let queries: Vec<_> = embs.iter().map(|e| e.as_slice()).collect();
+            let results = if let Some(node_type) = filter_type {
+                match self.vocab.get_node_type_id(&node_type) {
+                    Some(nt_id) => self.embeddings.nearest_neighbors_batch_filtered(&queries, k, |node_id| {
+                        self.vocab.get_node_type_id_of(node_id) == Some(nt_id)
+                    }),
+                    None => queries.iter().map(|_| Vec::new()).collect()
+                }
+            } else {
+                self.embeddings.nearest_neighbors_batch(&queries, k)
+            };
+            results.into_iter()
+                .map(|dists| convert_node_distance(&self.vocab, dists))
+                .collect()
+        })
+    }
+
     ///    Returns the number of dimensions for an embedding.
     ///    
     ///    Returns
@@ -2687,10 +5236,7 @@ impl NodeEmbeddings {
         let mut writer = EmbeddingWriter::new(path, self.vocab.as_ref(), comp_level)
             .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
 
-        let it = (0..self.vocab.len())
-            .map(|node_id| (node_id, self.embeddings.get_embedding(node_id)));
-
-        writer.stream(it)
+        writer.stream(self.embeddings.iter())
             .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
 
         Ok(())
@@ -2747,66 +5293,147 @@ impl NodeEmbeddings {
             Ok(ne)
         })
     }
-}
-
-/// Allows the user to build NodeEmbeddings incrementally.
-#[pyclass]
-struct NodeEmbeddingsBuilder {
-    vocab: Vocab,
-    distance: Distance,
-    embeddings: Vec<Vec<f32>>
-}
 
-#[pymethods]
-impl NodeEmbeddingsBuilder {
-    ///    Creates a NodeEmbeddingBuilder.  Allows progressive addition of nodes and embeddings.
+    ///    Saves the NodeEmbeddings to disk as a sharded store which can be searched without
+    ///    loading it into memory.  See ShardedNodeEmbeddings.
     ///    
     ///    Parameters
     ///    ----------
-    ///    dist : Distance
-    ///        Distance to construct the NodeEmbeddings.
+    ///    path : str
+    ///        Directory to store the shards in.
+    ///    
+    ///    shard_size : Int - Optional
+    ///        Number of embeddings per shard.  Default is 1_000_000.
     ///    
     ///    Returns
     ///    -------
-    ///    Self
+    ///    () - Can throw exception
     ///        
     ///    
-    #[new]
-    pub fn new(dist: Distance) -> Self {
-        NodeEmbeddingsBuilder {
-            distance: dist,
-            vocab: Vocab::new(),
-            embeddings: Vec::new()
-        }
-    }
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("NodeEmbeddingsBuilder<Nodes={}, Distance={:?}>", self.vocab.len(), self.distance.to_edist())
+    pub fn save_sharded(&self, path: &str, shard_size: Option<usize>) -> PyResult<()> {
+        let shard_size = shard_size.unwrap_or(1_000_000);
+        ShardedEmbeddingStore::write(path, &self.embeddings, shard_size)
+            .and_then(|_| write_sharded_vocab(path, self.vocab.as_ref()))
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
     }
 
-    ///    Adds a fully qualified node and associated embedding to the buffer.
+    ///    Saves the NodeEmbeddings to disk in Parquet format, using the schema 
+    ///    (node_type: str, node_name: str, embedding: fixed_size_list<float32>).
     ///    
     ///    Parameters
     ///    ----------
-    ///    node : FQNode
-    ///        Fully qualified Node
+    ///    path : str
+    ///        Path to store NodeEmbeddings.
     ///    
-    ///    embedding : List[Float]
-    ///        Associated embedding
+    ///    batch_size : Int - Optional
+    ///        Number of rows written per record batch.  Default is 10_000.
     ///    
     ///    Returns
     ///    -------
     ///    () - Can throw exception
     ///        
     ///    
-    pub fn add_embedding(
-        &mut self, 
-        node: FQNode, 
-        embedding: Vec<f32>
-    ) -> PyResult<()> {
+    pub fn save_parquet(&self, path: &str, batch_size: Option<usize>) -> PyResult<()> {
+        let mut writer = ParquetEmbeddingWriter::new(
+            path, self.vocab.as_ref(), self.embeddings.dims(), batch_size.unwrap_or(10_000))?;
 
-        let n = self.embeddings.len();
-        if n > 1 && self.embeddings[0].len() != embedding.len() {
+        writer.stream(self.embeddings.iter())?;
+        writer.finish()
+    }
+
+    ///    Loads NodeEmbeddings from a Parquet file written by `save_parquet`.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path where NodeEmbeddings are stored.
+    ///    
+    ///    distance : Distance
+    ///        Distance method to use for computing embedding similarity.
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only loads embeddings which match the provided node type.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        
+    ///    
+    #[staticmethod]
+    pub fn load_parquet(
+        py: Python<'_>,
+        path: &str, 
+        distance: Distance, 
+        filter_type: Option<String>
+    ) -> PyResult<Self> {
+        py.allow_threads(move || {
+            let (vocab, es) = ParquetEmbeddingReader::load(path, distance.to_edist(), filter_type)?;
+            Ok(NodeEmbeddings {
+                vocab: Arc::new(vocab),
+                embeddings: es
+            })
+        })
+    }
+}
+
+/// Allows the user to build NodeEmbeddings incrementally.
+#[pyclass]
+struct NodeEmbeddingsBuilder {
+    vocab: Vocab,
+    distance: Distance,
+    embeddings: Vec<Vec<f32>>
+}
+
+#[pymethods]
+impl NodeEmbeddingsBuilder {
+    ///    Creates a NodeEmbeddingBuilder.  Allows progressive addition of nodes and embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dist : Distance
+    ///        Distance to construct the NodeEmbeddings.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(dist: Distance) -> Self {
+        NodeEmbeddingsBuilder {
+            distance: dist,
+            vocab: Vocab::new(),
+            embeddings: Vec::new()
+        }
+    }
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("NodeEmbeddingsBuilder<Nodes={}, Distance={:?}>", self.vocab.len(), self.distance.to_edist())
+    }
+
+    ///    Adds a fully qualified node and associated embedding to the buffer.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Fully qualified Node
+    ///    
+    ///    embedding : List[Float]
+    ///        Associated embedding
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///        
+    ///    
+    pub fn add_embedding(
+        &mut self, 
+        node: FQNode, 
+        embedding: Vec<f32>
+    ) -> PyResult<()> {
+
+        let n = self.embeddings.len();
+        if n > 1 && self.embeddings[0].len() != embedding.len() {
             return Err(PyValueError::new_err("Embedding dimensions mismatch!"));
         }
 
@@ -3107,7 +5734,7 @@ impl EmbeddingAligner {
         let query_embedding = lookup_embedding(emb, translated_embeddings)?;
         
         // Get the original neighbors and distances
-        let neighbors = orig_ann.ann.predict(&orig_embeddings.embeddings, query_embedding);
+        let neighbors = orig_ann.ann.predict(&orig_embeddings.embeddings, query_embedding, usize::MAX);
         let rand_neighbors = if self.random_nodes > 0 {
             let mut rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED + 123123));
 
@@ -3274,6 +5901,46 @@ impl GraphAnn {
     }
 }
 
+///    Determines how EmbAnn chooses hyperplanes when building trees.
+///
+#[pyclass]
+#[derive(Clone,Copy)]
+pub enum SplitStrategy {
+    /// Hyperplanes between random pairs of points, keeping the most balanced of a few.  Fast,
+    /// but trees get lopsided on clustered data.
+    TwoPoint,
+
+    /// Random direction split at the median projection, keeping trees balanced
+    Median,
+
+    /// Highest variance of several random directions, split at the median.  Slowest to build.
+    Variance
+}
+
+impl SplitStrategy {
+    fn to_esplit(&self) -> ESplitStrategy {
+        match self {
+            SplitStrategy::TwoPoint => ESplitStrategy::TwoPoint,
+            SplitStrategy::Median => ESplitStrategy::Median { sample: 256 },
+            SplitStrategy::Variance => ESplitStrategy::Variance { candidates: 10, sample: 256 }
+        }
+    }
+}
+
+/// Flattens an ANN evaluation into a python dict, with latencies in milliseconds.
+fn evaluation_to_dict(eval: AnnEvaluation) -> HashMap<String, f64> {
+    let mut d = HashMap::new();
+    d.insert("k".to_string(), eval.k as f64);
+    d.insert("n_queries".to_string(), eval.n_queries as f64);
+    d.insert("recall".to_string(), eval.recall as f64);
+    d.insert("latency_p50_ms".to_string(), eval.latency_p50.as_secs_f64() * 1000.);
+    d.insert("latency_p90_ms".to_string(), eval.latency_p90.as_secs_f64() * 1000.);
+    d.insert("latency_p99_ms".to_string(), eval.latency_p99.as_secs_f64() * 1000.);
+    d.insert("brute_force_p50_ms".to_string(), eval.brute_force_p50.as_secs_f64() * 1000.);
+    d.insert("brute_force_p99_ms".to_string(), eval.brute_force_p99.as_secs_f64() * 1000.);
+    d
+}
+
 /// Wrapper for a much better ANN solution for embeddings
 #[pyclass]
 struct EmbAnn {
@@ -3302,6 +5969,9 @@ impl EmbAnn {
     ///    seed : Int - Optional
     ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
     ///    
+    ///    split_strategy : SplitStrategy - Optional
+    ///        How hyperplanes are chosen when splitting nodes.  Defaults to SplitStrategy.TwoPoint.
+    ///    
     ///    Returns
     ///    -------
     ///    Self
@@ -3312,9 +5982,11 @@ impl EmbAnn {
         embs: &NodeEmbeddings, 
         n_trees: usize,
         max_nodes_per_leaf: usize,
-        seed: Option<u64>
+        seed: Option<u64>,
+        split_strategy: Option<SplitStrategy>
     ) -> Self {
-        let mut ann = Ann::new();
+        let split = split_strategy.map(|s| s.to_esplit()).unwrap_or_default();
+        let mut ann = Ann::new().with_split_strategy(split);
         let seed = seed.unwrap_or(SEED + 10);
         ann.fit(&embs.embeddings, n_trees, max_nodes_per_leaf, seed);
 
@@ -3337,6 +6009,23 @@ impl EmbAnn {
     ///    query : Query
     ///        Query of item to look for
     ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors to return.  If omitted, returns every candidate found.
+    ///    
+    ///    search_k : Int - Optional
+    ///        If provided, explores multiple branches per tree, closest splits first, until at
+    ///        least search_k candidates have been scored.  Improves recall near split
+    ///        boundaries.  A good starting point is n_trees * k.
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only considers nodes matching the filter_type.  Other nodes are skipped
+    ///        during the search so they don't crowd out results.
+    ///    
+    ///    rerank : Int - Optional
+    ///        If provided and the index has been quantized, leaves are first scored with their
+    ///        int8 copies and the best rerank candidates are rescored at full precision.  Ignored
+    ///        when search_k or filter_type are provided.
+    ///    
     ///    Returns
     ///    -------
     ///    List[(FQNode, f32)] - Can throw exception
@@ -3345,10 +6034,92 @@ impl EmbAnn {
     pub fn find(
         &self, 
         embeddings: &NodeEmbeddings,
-        query: &Query
+        query: &Query,
+        k: Option<usize>,
+        search_k: Option<usize>,
+        filter_type: Option<String>,
+        rerank: Option<usize>
     ) -> PyResult<Vec<(FQNode, f32)>> {
         let query_embedding = lookup_embedding(query, embeddings)?;
-        let nodes = self.ann.predict(&embeddings.embeddings, query_embedding);
+        let k = k.unwrap_or(usize::MAX);
+        let es = &embeddings.embeddings;
+        let vocab = embeddings.vocab.deref();
+        let nt_id = match filter_type {
+            Some(node_type) => match vocab.get_node_type_id(&node_type) {
+                Some(nt_id) => Some(nt_id),
+                None => return Ok(Vec::new())
+            },
+            None => None
+        };
+        let filter = |node_id| nt_id.map(|nt_id| vocab.get_node_type_id_of(node_id) == Some(nt_id)).unwrap_or(true);
+        let nodes = match (search_k, rerank) {
+            (Some(search_k), _) => self.ann.predict_search_k_filtered(es, query_embedding, k, search_k, filter),
+            (None, Some(rerank)) if nt_id.is_none() => self.ann.predict_quantized(es, query_embedding, k, rerank),
+            _ => self.ann.predict_filtered(es, query_embedding, k, filter)
+        };
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Finds the nearest neighbors for a batch of embeddings.  Queries are processed in
+    ///    parallel, which is substantially faster than calling `find` in a loop.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    queries : List[List[Float]]
+    ///        Embeddings to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return for each query
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[(FQNode, f32)]]
+    ///        Fully qualified nodes and their associated distances for each query.
+    ///    
+    pub fn find_batch(
+        &self, 
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        queries: Vec<Vec<f32>>,
+        k: usize
+    ) -> Vec<Vec<(FQNode, f32)>> {
+        py.allow_threads(move || {
+            let queries: Vec<_> = queries.iter().map(|q| q.as_slice()).collect();
+            self.ann.predict_batch(&embeddings.embeddings, &queries, k).into_iter()
+                .map(|nodes| convert_node_distance(&embeddings.vocab, nodes))
+                .collect()
+        })
+    }
+
+    ///    Finds the nearest neighbors of a node in the embeddings, excluding the node itself.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    node : FQNode
+    ///        Node to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find_node(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        node: FQNode,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        let nodes = self.ann.predict_node(&embeddings.embeddings, node_id, k);
         Ok(convert_node_distance(&embeddings.vocab, nodes))
     }
 
@@ -3371,1071 +6142,4676 @@ impl EmbAnn {
     pub fn depth(&self) -> Vec<usize> {
         self.ann.depth()
     }
-}
-
-
-///
-/// Wrapper for the Supervised Monte-Carlo Iteration.  It stores the reward maps on the struct.
-#[pyclass]
-struct Smci {
-    graph: Arc<CumCSR>,
-    vocab: Arc<Vocab>,
-    /// Maps Node 1 -> Node 2 = Reward
-    rewards: Vec<(NodeID,NodeID,f32)>
-}
 
-#[pymethods]
-impl Smci {
-    #[new]
-    pub fn new(graph: &Graph) -> Self {
-        Smci {
-            graph: graph.graph.clone(),
-            vocab: graph.vocab.clone(),
-            rewards: Vec::new()
-        }
+    ///    Evaluates the index against brute force search, sampling nodes from the embeddings as
+    ///    queries.  Useful for tuning n_trees, max_nodes_per_leaf, and search_k.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    n_queries : Int
+    ///        Number of nodes to sample as queries
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to retrieve per query
+    ///    
+    ///    search_k : Int - Optional
+    ///        If provided, evaluates the priority search with this budget.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for sampling queries.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Dict[str, Float]
+    ///        recall, along with latency percentiles in milliseconds for the index and brute
+    ///        force search.
+    ///    
+    pub fn evaluate(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        n_queries: usize,
+        k: usize,
+        search_k: Option<usize>,
+        seed: Option<u64>
+    ) -> HashMap<String, f64> {
+        let seed = seed.unwrap_or(SEED);
+        py.allow_threads(move || {
+            let es = &embeddings.embeddings;
+            let eval = match search_k {
+                Some(search_k) => evaluate(es, |emb, k| {
+                    self.ann.predict_search_k(es, emb, k, search_k)
+                }, n_queries, k, seed),
+                None => evaluate_index(&self.ann, es, n_queries, k, seed)
+            };
+            evaluation_to_dict(eval)
+        })
     }
 
-    pub fn add_reward(
-        &mut self, 
-        from_node: FQNode, 
-        to_node: FQNode, 
-        reward: f32
-    ) -> PyResult<()> {
-        let f_n = get_node_id(self.vocab.deref(), from_node.0, from_node.1)?;
-        let t_n = get_node_id(self.vocab.deref(), to_node.0, to_node.1)?;
-        self.rewards.push((f_n, t_n, reward));
+    ///    Removes a node from the index so it no longer appears in results.  Removed nodes are
+    ///    tombstoned and periodically compacted out of the trees, avoiding a full rebuild.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    node : FQNode
+    ///        Node to remove
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn remove(&mut self, embeddings: &NodeEmbeddings, node: FQNode) -> PyResult<()> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        self.ann.remove(node_id);
         Ok(())
     }
 
-    pub fn optimize(
-        &self, 
-        iterations: usize,
-        num_walks: usize,
-        alpha: f32,
-        discount: f32,
-        step_penalty: f32,
-        explore_pct: f32,
-        restart_prob: f32,
-        compression: Option<f32>,
-        embeddings: Option<&NodeEmbeddings>,
-        seed: Option<u64>
-    ) -> PyResult<Graph> {
-        let smci = SupervisedMCIteration {
-            iterations,
-            num_walks,
-            alpha,
-            discount,
-            step_penalty,
-            explore_pct,
-            restart_prob,
-            compression: compression.unwrap_or(1.0),
-            seed: seed.unwrap_or(SEED)
-        };
-
-        let embs = embeddings.map(|e| {
-            let tt = e.vocab.create_translation_table(self.vocab.deref());
-            (&e.embeddings, tt)
-        });
-        let weights = smci.learn(self.graph.deref(), &self.rewards, embs);
+    ///    Physically drops removed nodes from the trees.  This happens automatically as removals
+    ///    accumulate, but can be forced, such as prior to serving.
+    ///    
+    pub fn compact(&mut self) {
+        self.ann.compact();
+    }
 
-        let new_graph = self.graph.clone_with_edges(weights)
+    ///    Builds int8 copies of each leaf's embeddings, allowing find to use a cheaper first
+    ///    scoring pass via the rerank parameter.  Quantized leaves are persisted by save.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    pub fn quantize(&mut self, py: Python<'_>, embeddings: &NodeEmbeddings) {
+        let ann = &mut self.ann;
+        py.allow_threads(move || ann.quantize(&embeddings.embeddings));
+    }
+
+    ///    Saves the EmbANN index to disk so it can be loaded without refitting.  The embeddings
+    ///    used to build it need to be saved separately.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to save the index to.  Paths ending in .gz are compressed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        self.ann.save(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
+    }
+
+    ///    Saves the EmbANN index in a flat layout which MmapEmbAnn can query in place, letting
+    ///    many processes share one copy through the page cache.  Quantized leaves aren't
+    ///    included.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to save the index to.  Compression isn't supported.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn save_mmap(&self, path: &str) -> PyResult<()> {
+        self.ann.save_mmap(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
+    }
+
+    ///    Loads an EmbANN index written with save.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to load the index from.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    EmbAnn - Can throw exception
+    ///    
+    #[staticmethod]
+    pub fn load(path: &str) -> PyResult<Self> {
+        let ann = Ann::load(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        Ok(EmbAnn { ann })
+    }
+
+    ///    Builds a k-nearest-neighbor graph over the embeddings using this index.  Edge weights
+    ///    decay exponentially with distance, relative to each node's closest neighbor.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to connect each node to.
+    ///    
+    ///    symmetric : Bool - Optional
+    ///        If true, adds reverse edges so the graph is undirected.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        Graph sharing the vocab of the embeddings.
+    ///    
+    pub fn knn_graph(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        k: usize,
+        symmetric: Option<bool>
+    ) -> Graph {
+        let graph = py.allow_threads(move || {
+            knn_graph(&self.ann, &embeddings.embeddings, k, symmetric.unwrap_or(false))
+        });
+        Graph {
+            graph: Arc::new(CumCSR::convert(graph)),
+            vocab: embeddings.vocab.clone()
+        }
+    }
+
+    ///    Clusters the embeddings by density, HDBSCAN style, using this index for the nearest
+    ///    neighbors.  Finds clusters of varying size and density, leaving nodes in sparse regions
+    ///    unassigned.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    min_cluster_size : Int
+    ///        Smallest group of nodes considered a cluster.
+    ///    
+    ///    min_samples : Int - Optional
+    ///        Neighbor used to estimate each node's density.  Larger values are more
+    ///        conservative, labeling more nodes as noise.  Default is 5.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors retrieved per node when building the spanning tree.  Default is
+    ///        3 * min_samples.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    DensityClusters
+    ///        Cluster labels and outlier scores.
+    ///    
+    pub fn density_clusters(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        min_cluster_size: usize,
+        min_samples: Option<usize>,
+        k: Option<usize>
+    ) -> DensityClusters {
+        fit_density_clusters(py, &self.ann, embeddings, min_cluster_size, min_samples, k)
+    }
+
+    ///    Finds pairs of near-duplicate nodes using this index.  Only each node's k nearest
+    ///    neighbors are considered.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    max_distance : Float
+    ///        Largest distance between duplicates.  For cosine distance, this is one minus the
+    ///        similarity threshold.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors checked per node.  Default is 10.
+    ///    
+    ///    same_type : Bool - Optional
+    ///        If true, only pairs nodes of the same node type.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Duplicates
+    ///        Duplicate pairs and merge candidates.
+    ///    
+    pub fn find_duplicates(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        max_distance: f32,
+        k: Option<usize>,
+        same_type: Option<bool>
+    ) -> Duplicates {
+        find_duplicates(py, &self.ann, embeddings, max_distance, k, same_type)
+    }
+}
+
+/// Read-only EmbAnn queried directly from a memory mapped file written by EmbAnn.save_mmap.
+#[pyclass]
+struct MmapEmbAnn {
+    ann: MmapAnn
+}
+
+#[pymethods]
+impl MmapEmbAnn {
+
+    ///    Memory maps an index written by EmbAnn.save_mmap.  Opening is nearly free as nothing
+    ///    is deserialized.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    path : str
+    ///        Path to the index.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    MmapEmbAnn - Can throw exception
+    ///    
+    #[staticmethod]
+    pub fn open(path: &str) -> PyResult<Self> {
+        let ann = MmapAnn::open(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        Ok(MmapEmbAnn { ann })
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("MmapEmbAnn<N_Trees={}>", self.ann.num_trees())
+    }
+
+    ///    Find the nearest neighbors of a provided embedding.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    query : Query
+    ///        Query of item to look for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        query: &Query,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let query_embedding = lookup_embedding(query, embeddings)?;
+        let nodes = self.ann.predict(&embeddings.embeddings, query_embedding, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Finds the nearest neighbors of a node in the embeddings, excluding the node itself.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the trees.
+    ///    
+    ///    node : FQNode
+    ///        Node to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find_node(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        node: FQNode,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        let nodes = self.ann.predict_node(&embeddings.embeddings, node_id, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+}
+
+/// HNSW graph based ANN.  Slower to build than EmbAnn but maintains recall on very large
+/// embedding sets, and supports adding nodes after construction.
+#[pyclass]
+struct HnswAnn {
+    index: Hnsw
+}
+
+#[pymethods]
+impl HnswAnn {
+
+    ///    Creates an HNSW index on a set of node embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embs : NodeEmbeddings
+    ///        Node embedding set for building the ANN 
+    ///    
+    ///    m : Int - Optional
+    ///        Number of links per node per layer.  Higher is more accurate at the expense of
+    ///        memory and build time.  Default is 16.
+    ///    
+    ///    ef_construction : Int - Optional
+    ///        Beam width used during construction.  Default is 200.
+    ///    
+    ///    ef_search : Int - Optional
+    ///        Beam width used during search.  Higher is more accurate but slower.  Default is 64.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(
+        py: Python<'_>,
+        embs: &NodeEmbeddings, 
+        m: Option<usize>,
+        ef_construction: Option<usize>,
+        ef_search: Option<usize>,
+        seed: Option<u64>
+    ) -> Self {
+        let mut index = Hnsw::new(
+            m.unwrap_or(16), 
+            ef_construction.unwrap_or(200), 
+            ef_search.unwrap_or(64), 
+            seed.unwrap_or(SEED + 10));
+
+        py.allow_threads(|| index.fit(&embs.embeddings));
+        HnswAnn { index }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("HnswAnn<Nodes={}, Layers={}, EfSearch={}>", self.index.len(), 
+                self.index.num_layers(), self.index.ef_search)
+    }
+
+    ///    Adds a node to the index.  The node must already exist in the embeddings, such as
+    ///    after NodeEmbeddings.add_embedding.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to add
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn insert(&mut self, embeddings: &NodeEmbeddings, node: FQNode) -> PyResult<()> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        self.index.insert(&embeddings.embeddings, node_id);
+        Ok(())
+    }
+
+    ///    Find the nearest neighbors of a provided embedding using the HNSW index.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    query : Query
+    ///        Query of item to look for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        query: &Query,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let query_embedding = lookup_embedding(query, embeddings)?;
+        let nodes = self.index.predict(&embeddings.embeddings, query_embedding, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Finds the nearest neighbors of a node in the embeddings, excluding the node itself.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find_node(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        node: FQNode,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        let nodes = self.index.predict_node(&embeddings.embeddings, node_id, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Evaluates the index against brute force search, sampling nodes from the embeddings as
+    ///    queries.  Useful for tuning m and ef_search.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    n_queries : Int
+    ///        Number of nodes to sample as queries
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to retrieve per query
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for sampling queries.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Dict[str, Float]
+    ///        recall, along with latency percentiles in milliseconds for the index and brute
+    ///        force search.
+    ///    
+    pub fn evaluate(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        n_queries: usize,
+        k: usize,
+        seed: Option<u64>
+    ) -> HashMap<String, f64> {
+        let seed = seed.unwrap_or(SEED);
+        py.allow_threads(move || {
+            let eval = evaluate_index(&self.index, &embeddings.embeddings, n_queries, k, seed);
+            evaluation_to_dict(eval)
+        })
+    }
+
+    ///    Builds a k-nearest-neighbor graph over the embeddings using this index.  Edge weights
+    ///    decay exponentially with distance, relative to each node's closest neighbor.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to connect each node to.
+    ///    
+    ///    symmetric : Bool - Optional
+    ///        If true, adds reverse edges so the graph is undirected.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        Graph sharing the vocab of the embeddings.
+    ///    
+    pub fn knn_graph(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        k: usize,
+        symmetric: Option<bool>
+    ) -> Graph {
+        let graph = py.allow_threads(move || {
+            knn_graph(&self.index, &embeddings.embeddings, k, symmetric.unwrap_or(false))
+        });
+        Graph {
+            graph: Arc::new(CumCSR::convert(graph)),
+            vocab: embeddings.vocab.clone()
+        }
+    }
+
+    ///    Clusters the embeddings by density, HDBSCAN style, using this index for the nearest
+    ///    neighbors.  Finds clusters of varying size and density, leaving nodes in sparse regions
+    ///    unassigned.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    min_cluster_size : Int
+    ///        Smallest group of nodes considered a cluster.
+    ///    
+    ///    min_samples : Int - Optional
+    ///        Neighbor used to estimate each node's density.  Larger values are more
+    ///        conservative, labeling more nodes as noise.  Default is 5.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors retrieved per node when building the spanning tree.  Default is
+    ///        3 * min_samples.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    DensityClusters
+    ///        Cluster labels and outlier scores.
+    ///    
+    pub fn density_clusters(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        min_cluster_size: usize,
+        min_samples: Option<usize>,
+        k: Option<usize>
+    ) -> DensityClusters {
+        fit_density_clusters(py, &self.index, embeddings, min_cluster_size, min_samples, k)
+    }
+
+    ///    Finds pairs of near-duplicate nodes using this index.  Only each node's k nearest
+    ///    neighbors are considered.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    max_distance : Float
+    ///        Largest distance between duplicates.  For cosine distance, this is one minus the
+    ///        similarity threshold.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors checked per node.  Default is 10.
+    ///    
+    ///    same_type : Bool - Optional
+    ///        If true, only pairs nodes of the same node type.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Duplicates
+    ///        Duplicate pairs and merge candidates.
+    ///    
+    pub fn find_duplicates(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        max_distance: f32,
+        k: Option<usize>,
+        same_type: Option<bool>
+    ) -> Duplicates {
+        find_duplicates(py, &self.index, embeddings, max_distance, k, same_type)
+    }
+}
+
+/// Inverted file ANN.  Partitions the embeddings into cells with k-means and only searches the
+/// cells closest to the query.  Cheap on memory and well suited to high dimensional embeddings.
+#[pyclass]
+struct IvfAnn {
+    index: Ivf
+}
+
+#[pymethods]
+impl IvfAnn {
+
+    ///    Creates an IVF index on a set of node embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embs : NodeEmbeddings
+    ///        Node embedding set for building the ANN 
+    ///    
+    ///    n_lists : Int - Optional
+    ///        Number of cells to partition the embeddings into.  Defaults to sqrt(N).
+    ///    
+    ///    n_probe : Int - Optional
+    ///        Number of cells searched per query.  Higher is more accurate but slower.  Default
+    ///        is 10.
+    ///    
+    ///    iterations : Int - Optional
+    ///        Number of k-means iterations.  Default is 10.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(
+        py: Python<'_>,
+        embs: &NodeEmbeddings, 
+        n_lists: Option<usize>,
+        n_probe: Option<usize>,
+        iterations: Option<usize>,
+        seed: Option<u64>
+    ) -> Self {
+        let n_lists = n_lists.unwrap_or_else(|| (embs.embeddings.len() as f64).sqrt() as usize);
+        let mut index = Ivf::new(
+            n_lists, 
+            n_probe.unwrap_or(10), 
+            iterations.unwrap_or(10), 
+            seed.unwrap_or(SEED + 10));
+
+        py.allow_threads(|| index.fit(&embs.embeddings));
+        IvfAnn { index }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("IvfAnn<Nodes={}, Lists={}, NProbe={}>", self.index.len(), 
+                self.index.num_lists(), self.index.n_probe)
+    }
+
+    ///    Adds a node to the index.  The node must already exist in the embeddings, such as
+    ///    after NodeEmbeddings.add_embedding.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to add
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn insert(&mut self, embeddings: &NodeEmbeddings, node: FQNode) -> PyResult<()> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        self.index.insert(&embeddings.embeddings, node_id);
+        Ok(())
+    }
+
+    ///    Find the nearest neighbors of a provided embedding using the IVF index.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    query : Query
+    ///        Query of item to look for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        query: &Query,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let query_embedding = lookup_embedding(query, embeddings)?;
+        let nodes = self.index.predict(&embeddings.embeddings, query_embedding, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Finds the nearest neighbors of a node in the embeddings, excluding the node itself.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find_node(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        node: FQNode,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        let nodes = self.index.predict_node(&embeddings.embeddings, node_id, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Sets the number of cells searched per query.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    n_probe : Int
+    ///        Number of cells to search.
+    ///    
+    pub fn set_n_probe(&mut self, n_probe: usize) {
+        self.index.n_probe = n_probe.max(1);
+    }
+
+    ///    Returns the number of nodes in each cell, useful for spotting skewed partitions.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Int]
+    ///    
+    pub fn list_sizes(&self) -> Vec<usize> {
+        self.index.list_sizes()
+    }
+
+    ///    Evaluates the index against brute force search, sampling nodes from the embeddings as
+    ///    queries.  Useful for tuning n_lists and n_probe.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    n_queries : Int
+    ///        Number of nodes to sample as queries
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to retrieve per query
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for sampling queries.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Dict[str, Float]
+    ///        recall, along with latency percentiles in milliseconds for the index and brute
+    ///        force search.
+    ///    
+    pub fn evaluate(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        n_queries: usize,
+        k: usize,
+        seed: Option<u64>
+    ) -> HashMap<String, f64> {
+        let seed = seed.unwrap_or(SEED);
+        py.allow_threads(move || {
+            let eval = evaluate_index(&self.index, &embeddings.embeddings, n_queries, k, seed);
+            evaluation_to_dict(eval)
+        })
+    }
+
+    ///    Builds a k-nearest-neighbor graph over the embeddings using this index.  Edge weights
+    ///    decay exponentially with distance, relative to each node's closest neighbor.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to connect each node to.
+    ///    
+    ///    symmetric : Bool - Optional
+    ///        If true, adds reverse edges so the graph is undirected.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        Graph sharing the vocab of the embeddings.
+    ///    
+    pub fn knn_graph(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        k: usize,
+        symmetric: Option<bool>
+    ) -> Graph {
+        let graph = py.allow_threads(move || {
+            knn_graph(&self.index, &embeddings.embeddings, k, symmetric.unwrap_or(false))
+        });
+        Graph {
+            graph: Arc::new(CumCSR::convert(graph)),
+            vocab: embeddings.vocab.clone()
+        }
+    }
+
+    ///    Clusters the embeddings by density, HDBSCAN style, using this index for the nearest
+    ///    neighbors.  Finds clusters of varying size and density, leaving nodes in sparse regions
+    ///    unassigned.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    min_cluster_size : Int
+    ///        Smallest group of nodes considered a cluster.
+    ///    
+    ///    min_samples : Int - Optional
+    ///        Neighbor used to estimate each node's density.  Larger values are more
+    ///        conservative, labeling more nodes as noise.  Default is 5.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors retrieved per node when building the spanning tree.  Default is
+    ///        3 * min_samples.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    DensityClusters
+    ///        Cluster labels and outlier scores.
+    ///    
+    pub fn density_clusters(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        min_cluster_size: usize,
+        min_samples: Option<usize>,
+        k: Option<usize>
+    ) -> DensityClusters {
+        fit_density_clusters(py, &self.index, embeddings, min_cluster_size, min_samples, k)
+    }
+
+    ///    Finds pairs of near-duplicate nodes using this index.  Only each node's k nearest
+    ///    neighbors are considered.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    max_distance : Float
+    ///        Largest distance between duplicates.  For cosine distance, this is one minus the
+    ///        similarity threshold.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors checked per node.  Default is 10.
+    ///    
+    ///    same_type : Bool - Optional
+    ///        If true, only pairs nodes of the same node type.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Duplicates
+    ///        Duplicate pairs and merge candidates.
+    ///    
+    pub fn find_duplicates(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        max_distance: f32,
+        k: Option<usize>,
+        same_type: Option<bool>
+    ) -> Duplicates {
+        find_duplicates(py, &self.index, embeddings, max_distance, k, same_type)
+    }
+}
+
+/// Locality sensitive hashing ANN.  Hashes embeddings into buckets with random hyperplanes across
+/// several tables.  Very cheap to build, which suits indices that are rebuilt frequently.
+#[pyclass]
+struct LshAnn {
+    index: Lsh
+}
+
+#[pymethods]
+impl LshAnn {
+
+    ///    Creates an LSH index on a set of node embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embs : NodeEmbeddings
+    ///        Node embedding set for building the ANN 
+    ///    
+    ///    n_tables : Int - Optional
+    ///        Number of hash tables.  More tables improve recall at the expense of memory and
+    ///        latency.  Default is 10.
+    ///    
+    ///    n_bits : Int - Optional
+    ///        Bits per signature, up to 64.  More bits create smaller buckets.  Defaults to
+    ///        log2(N / 8), keeping roughly 8 nodes per bucket.
+    ///    
+    ///    probe_radius : Int - Optional
+    ///        Also searches buckets differing by up to this many bits.  Default is 1.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(
+        py: Python<'_>,
+        embs: &NodeEmbeddings, 
+        n_tables: Option<usize>,
+        n_bits: Option<usize>,
+        probe_radius: Option<usize>,
+        seed: Option<u64>
+    ) -> Self {
+        let n_bits = n_bits.unwrap_or_else(|| {
+            ((embs.embeddings.len() as f64 / 8.).max(2.)).log2().ceil() as usize
+        });
+        let mut index = Lsh::new(
+            n_tables.unwrap_or(10), 
+            n_bits,
+            probe_radius.unwrap_or(1), 
+            seed.unwrap_or(SEED + 10));
+
+        py.allow_threads(|| index.fit(&embs.embeddings));
+        LshAnn { index }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("LshAnn<Nodes={}, Tables={}, ProbeRadius={}>", self.index.len(), 
+                self.index.num_tables(), self.index.probe_radius)
+    }
+
+    ///    Adds a node to the index.  The node must already exist in the embeddings, such as
+    ///    after NodeEmbeddings.add_embedding.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to add
+    ///    
+    ///    Returns
+    ///    -------
+    ///    () - Can throw exception
+    ///    
+    pub fn insert(&mut self, embeddings: &NodeEmbeddings, node: FQNode) -> PyResult<()> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        self.index.insert(&embeddings.embeddings, node_id);
+        Ok(())
+    }
+
+    ///    Find the nearest neighbors of a provided embedding using the LSH index.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    query : Query
+    ///        Query of item to look for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        query: &Query,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let query_embedding = lookup_embedding(query, embeddings)?;
+        let nodes = self.index.predict(&embeddings.embeddings, query_embedding, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Finds the nearest neighbors of a node in the embeddings, excluding the node itself.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    node : FQNode
+    ///        Node to find neighbors for
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to return
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        List of fully qualified nodes and their associated distances.
+    ///    
+    pub fn find_node(
+        &self, 
+        embeddings: &NodeEmbeddings,
+        node: FQNode,
+        k: usize
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let node_id = get_node_id(embeddings.vocab.deref(), node.0, node.1)?;
+        let nodes = self.index.predict_node(&embeddings.embeddings, node_id, k);
+        Ok(convert_node_distance(&embeddings.vocab, nodes))
+    }
+
+    ///    Sets how many bits a bucket's signature may differ by and still be searched.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    probe_radius : Int
+    ///        Hamming radius to search.
+    ///    
+    pub fn set_probe_radius(&mut self, probe_radius: usize) {
+        self.index.probe_radius = probe_radius;
+    }
+
+    ///    Evaluates the index against brute force search, sampling nodes from the embeddings as
+    ///    queries.  Useful for tuning n_tables, n_bits, and probe_radius.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    n_queries : Int
+    ///        Number of nodes to sample as queries
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to retrieve per query
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for sampling queries.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Dict[str, Float]
+    ///        recall, along with latency percentiles in milliseconds for the index and brute
+    ///        force search.
+    ///    
+    pub fn evaluate(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        n_queries: usize,
+        k: usize,
+        seed: Option<u64>
+    ) -> HashMap<String, f64> {
+        let seed = seed.unwrap_or(SEED);
+        py.allow_threads(move || {
+            let eval = evaluate_index(&self.index, &embeddings.embeddings, n_queries, k, seed);
+            evaluation_to_dict(eval)
+        })
+    }
+
+    ///    Builds a k-nearest-neighbor graph over the embeddings using this index.  Edge weights
+    ///    decay exponentially with distance, relative to each node's closest neighbor.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    k : Int
+    ///        Number of neighbors to connect each node to.
+    ///    
+    ///    symmetric : Bool - Optional
+    ///        If true, adds reverse edges so the graph is undirected.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Graph
+    ///        Graph sharing the vocab of the embeddings.
+    ///    
+    pub fn knn_graph(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        k: usize,
+        symmetric: Option<bool>
+    ) -> Graph {
+        let graph = py.allow_threads(move || {
+            knn_graph(&self.index, &embeddings.embeddings, k, symmetric.unwrap_or(false))
+        });
+        Graph {
+            graph: Arc::new(CumCSR::convert(graph)),
+            vocab: embeddings.vocab.clone()
+        }
+    }
+
+    ///    Clusters the embeddings by density, HDBSCAN style, using this index for the nearest
+    ///    neighbors.  Finds clusters of varying size and density, leaving nodes in sparse regions
+    ///    unassigned.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    min_cluster_size : Int
+    ///        Smallest group of nodes considered a cluster.
+    ///    
+    ///    min_samples : Int - Optional
+    ///        Neighbor used to estimate each node's density.  Larger values are more
+    ///        conservative, labeling more nodes as noise.  Default is 5.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors retrieved per node when building the spanning tree.  Default is
+    ///        3 * min_samples.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    DensityClusters
+    ///        Cluster labels and outlier scores.
+    ///    
+    pub fn density_clusters(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        min_cluster_size: usize,
+        min_samples: Option<usize>,
+        k: Option<usize>
+    ) -> DensityClusters {
+        fit_density_clusters(py, &self.index, embeddings, min_cluster_size, min_samples, k)
+    }
+
+    ///    Finds pairs of near-duplicate nodes using this index.  Only each node's k nearest
+    ///    neighbors are considered.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings used in constructing the index.
+    ///    
+    ///    max_distance : Float
+    ///        Largest distance between duplicates.  For cosine distance, this is one minus the
+    ///        similarity threshold.
+    ///    
+    ///    k : Int - Optional
+    ///        Number of neighbors checked per node.  Default is 10.
+    ///    
+    ///    same_type : Bool - Optional
+    ///        If true, only pairs nodes of the same node type.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Duplicates
+    ///        Duplicate pairs and merge candidates.
+    ///    
+    pub fn find_duplicates(
+        &self,
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        max_distance: f32,
+        k: Option<usize>,
+        same_type: Option<bool>
+    ) -> Duplicates {
+        find_duplicates(py, &self.index, embeddings, max_distance, k, same_type)
+    }
+}
+
+
+///
+/// Wrapper for the Supervised Monte-Carlo Iteration.  It stores the reward maps on the struct.
+#[pyclass]
+struct Smci {
+    graph: Arc<CumCSR>,
+    vocab: Arc<Vocab>,
+    /// Maps Node 1 -> Node 2 = Reward
+    rewards: Vec<(NodeID,NodeID,f32)>
+}
+
+#[pymethods]
+impl Smci {
+    #[new]
+    pub fn new(graph: &Graph) -> Self {
+        Smci {
+            graph: graph.graph.clone(),
+            vocab: graph.vocab.clone(),
+            rewards: Vec::new()
+        }
+    }
+
+    pub fn add_reward(
+        &mut self, 
+        from_node: FQNode, 
+        to_node: FQNode, 
+        reward: f32
+    ) -> PyResult<()> {
+        let f_n = get_node_id(self.vocab.deref(), from_node.0, from_node.1)?;
+        let t_n = get_node_id(self.vocab.deref(), to_node.0, to_node.1)?;
+        self.rewards.push((f_n, t_n, reward));
+        Ok(())
+    }
+
+    pub fn optimize(
+        &self, 
+        iterations: usize,
+        num_walks: usize,
+        alpha: f32,
+        discount: f32,
+        step_penalty: f32,
+        explore_pct: f32,
+        restart_prob: f32,
+        compression: Option<f32>,
+        embeddings: Option<&NodeEmbeddings>,
+        seed: Option<u64>
+    ) -> PyResult<Graph> {
+        let smci = SupervisedMCIteration {
+            iterations,
+            num_walks,
+            alpha,
+            discount,
+            step_penalty,
+            explore_pct,
+            restart_prob,
+            compression: compression.unwrap_or(1.0),
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let embs = embeddings.map(|e| {
+            embedding_distances(self.vocab.deref(), &e.embeddings, e.vocab.deref())
+        });
+        let weights = smci.learn(self.graph.deref(), &self.rewards, embs);
+
+        let new_graph = self.graph.clone_with_edges(weights)
             .expect("this is all internal, should just work");
 
-        Ok(Graph {
-            graph: Arc::new(new_graph),
-            vocab: self.vocab.clone()
-        })
+        Ok(Graph {
+            graph: Arc::new(new_graph),
+            vocab: self.vocab.clone()
+        })
+
+    }
+}
+
+/// 
+#[pyclass]
+#[derive(Clone,Debug)]
+struct PprRankLearner {
+    alpha: f32,
+    batch_size: usize,
+    dims: usize,
+    passes: usize,
+    negatives: usize,
+    loss: String,
+    weight_decay: f32,
+    steps: Steps,
+    walks: usize,
+    k: usize,
+    num_features: Option<usize>,
+    compression: f32,
+    valid_pct: f32,
+    beta: f32
+}
+
+#[pymethods]
+impl PprRankLearner {
+
+    ///    Createsa a PPR Rank Learner.  PPR Rank Learner optimizes similarities based on a nodes
+    ///    local neighborhood:
+    ///
+    ///    1. For each node, compute their top K local neighborhood based on sampling, including
+    ///       relative weight.
+    ///    2. During optimization, sample N negatives
+    ///    3. Optimize a listwise lost which pushes the local neighborhood higher than the randomly
+    ///       sampled negatives.
+    ///
+    ///    This has some interesting dynamics.  Unlike EmbeddingPropagation, this captures
+    ///    neighborhoods and local clusters instead of just immediate neighbors; this allows the
+    ///    algorithm to better bias toward local topologies rather than basic connections.
+    ///    It also is able to use ranking listwise metrics for optimization since the PPR estimate
+    ///    gives an ordering to nodes.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    alpha : Float
+    ///        Learning Rate
+    ///    
+    ///    batch_size : Int
+    ///        Batch size
+    ///    
+    ///    dims : Int
+    ///        Dimension of the feature and eventual node embeddings.
+    ///    
+    ///    passes : Int
+    ///        Number of passes to use
+    ///    
+    ///    steps : Float
+    ///        Restart probability.  When steps ~ [0,1), it results in a restart probability.  When
+    ///        steps ~ [1,inf], it is treated as a fixed length walk.
+    ///    
+    ///    walks : Int
+    ///        Number of samples to collect.
+    ///    
+    ///    k : Int
+    ///        Top K nearest neighbors according to PPR estimates.
+    ///    
+    ///    negatives : Int
+    ///        Number of random negatives to use.
+    ///    
+    ///    loss : String - Optional
+    ///        Supports 'listnet' and 'listmle'.
+    ///    
+    ///    compression : Float - Optional
+    ///        This will either accentuate or depress the PPR scores for the local neighborhood.
+    ///        Higher compressions will accentuate, lower compressions will depress.
+    ///    
+    ///    beta : Float - Optional
+    ///        Determines how much to bias toward community structure versus rarer nodes.
+    ///
+    ///        Default is 0.8
+    ///    
+    ///    num_features : Int - Optional
+    ///        How many features to use to construct nodes.  If provided, will randomly sample
+    ///        features each construction.
+    ///
+    ///        Default is all
+    ///    
+    ///    weight_decay : Float - Optional
+    ///        If provided, applies weight decar to feature embeddings.
+    ///
+    ///        Default is 0.
+    ///    
+    ///    valid_pct : Float - Optional
+    ///        If provided, uses a subset of the graph for validation.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        
+    ///    
+    #[new]
+    fn new(
+         // Learning rate
+        alpha: f32, 
+ 
+        // Batch size 
+        batch_size: usize, 
+
+        // Node embedding size
+        dims: usize,
+
+        // Number of passes to run
+        passes: usize,
+
+        // Number of steps to take
+        steps: f32,
+
+        // Number of walks per node
+        walks: usize,
+
+        // Number of neighbors to extract from random walk
+        k: usize,
+
+        // Number of  negatives, produced from random walks.  The quality of these deeply
+        // depend on the quality of the graph
+        negatives: usize,
+
+        // Loss to use
+        loss: Option<String>,
+
+        // The PPR scores are either accentuated or depressed by the compression factor
+        compression: Option<f32>,
+        
+        // How much to benefit rarer/popular items
+        beta: Option<f32>,
+
+        num_features: Option<usize>,
+
+        weight_decay: Option<f32>, 
+
+        // Percentage of nodes to use for validation
+        valid_pct: Option<f32>,
+
+    ) -> PyResult<Self> {
+
+        let steps = if steps >= 1. {
+            Steps::Fixed(steps as usize)
+        } else if steps > 0. {
+            Steps::Probability(steps)
+        } else {
+            return Err(PyValueError::new_err("Alpha must be between [0, inf)"))
+        };
+
+        Ok(PprRankLearner {
+            alpha,
+            batch_size,
+            dims,
+            passes,
+            steps,
+            negatives,
+            walks,
+            k,
+            num_features,
+            weight_decay: weight_decay.unwrap_or(0f32),
+            loss: loss.unwrap_or_else(||"listnet".into()),
+            compression: compression.unwrap_or(1f32),
+            valid_pct: valid_pct.unwrap_or(0.1),
+            beta: beta.unwrap_or(0.8)
+        })
+    }
+    
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    ///    Learns the feature embeddings for a given graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use for construction.
+    ///    
+    ///    features : FeatureSet
+    ///        FeatureSet mapping nodes -> features
+    ///    
+    ///    feature_embeddings : mut NodeEmbeddings - Optional
+    ///        If provided, uses the feature_embeddings passed in as a starting point.  Otherwise,
+    ///        creates a new randomly initialized NodeEmbedding.
+    ///
+    ///        Default is new.
+    ///    
+    ///    indicator : Bool - Optional
+    ///        If provided, shows an indicator.
+    ///
+    ///        Default is True
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses the seed for all stochastic processes.
+    ///
+    ///        Default is a fixed global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Learned Feature Embeddings.
+    ///    
+    pub fn learn_features(
+        &self, 
+        graph: &Graph, 
+        features: &mut FeatureSet,
+        feature_embeddings: Option<&mut NodeEmbeddings>,
+        indicator: Option<bool>,
+        seed: Option<u64>
+    ) -> NodeEmbeddings {
+
+        let loss = if self.loss == "listnet" {
+            PprLoss::ListNet { passive: true, weight_decay: self.weight_decay }
+        } else {
+            PprLoss::ListMLE { weight_decay: self.weight_decay }
+        };
+
+        let ppr_rank = PprRank {
+            alpha: self.alpha,
+            batch_size: self.batch_size,
+            dims: self.dims,
+            passes: self.passes,
+            steps: self.steps,
+            loss: loss,
+            negatives: self.negatives,
+            num_walks: self.walks,
+            beta: self.beta,
+            k: self.k,
+            num_features: self.num_features,
+            compression: self.compression,
+            valid_pct: self.valid_pct,
+            indicator: indicator.unwrap_or(true),
+            seed: seed.unwrap_or(SEED)
+        };
+
+        features.features.fill_missing_nodes();
+
+        // Pull out the EmbeddingStore
+        let feature_embeddings = feature_embeddings.map(|fes| {
+           let mut sfes = EmbeddingStore::new(fes.vocab.len(), 0, EDist::Cosine);
+           std::mem::swap(&mut sfes, &mut fes.embeddings);
+           sfes
+        });
+
+        let feat_embeds = ppr_rank.learn(&*graph.graph, &features.features, feature_embeddings);
+        let vocab = features.features.clone_vocab();
+
+        let feature_embeddings = NodeEmbeddings {
+            vocab: Arc::new(vocab),
+            embeddings: feat_embeds};
+
+        feature_embeddings
+    }
+
+}
+
+/// Learns VPCG vectors on a graph.
+#[pyclass]
+struct VpcgEmbedder {
+    max_terms: usize, 
+    passes: usize,
+    dims: usize,
+    alpha: f32,
+    err: f32
+}
+
+#[pymethods]
+impl VpcgEmbedder {
+    ///    Creates a VPCGEmbedder.  VPCG uses bipartite graphs with features to learn embeddings
+    ///    that attempt to bridge the semantic gap.  It is highly effective on Search and
+    ///    Recommendation problems.
+    ///
+    ///    The graph needs to be a bipartite graph where one side of the graph has a distinct type
+    ///    from the other side.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    max_terms : Int
+    ///        Max terms to store on each node during propagation.
+    ///    
+    ///    passes : Int
+    ///        Number of passes to perform.  A good starting point is 10
+    ///    
+    ///    dims : Int
+    ///        Number of dimensions for the final NodeEmbeddings.
+    ///    
+    ///    alpha : Float - Optional
+    ///        If provided, alpha ~ [0,1] allows nodes to reinforce their own features over the propagated
+    ///        features.  Higher alphas rely more on propagated features whereas lower alphas
+    ///        attempt to preserve the original feature set more.
+    ///
+    ///        Default is 1.
+    ///    
+    ///    err : Float - Optional
+    ///        Suppresses terms with a weight of less than err.  Default is 1e-5
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(
+        max_terms: usize, 
+        passes: usize, 
+        dims: usize, 
+        alpha: Option<f32>, 
+        err: Option<f32>
+    ) -> Self {
+        VpcgEmbedder { 
+            max_terms, 
+            passes, 
+            dims,
+            alpha: alpha.unwrap_or(1f32),
+            err: err.unwrap_or(1e-5f32)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("VPCGEmbedder<MaxTerms={}, Passes={}, Dims={}, Alpha={}, Err={}>",
+                self.max_terms, self.passes, self.dims, self.alpha, self.err)
+    }
+
+    ///    Learns VPCG embeddings on the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use for bipartite structure
+    ///    
+    ///    features : FeatureSet
+    ///        Features to propagate between nodes
+    ///    
+    ///    start_node_type : String
+    ///        Starting node type for propagation.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        New NodeEmbeddings capturing the VPCG Embeddings.
+    ///    
+    pub fn learn(&self, 
+        graph: &Graph, 
+        features: &mut FeatureSet,
+        start_node_type: String
+    ) -> NodeEmbeddings  {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for node_id in 0..graph.graph.len() {
+            let nt = graph.vocab.get_node_type(node_id).unwrap();
+            if nt.as_ref() == &start_node_type {
+                left.push(node_id);
+            } else {
+                right.push(node_id)
+            }
+        }
+
+        features.features.fill_missing_nodes();
+        let vpcg = crate::algos::vpcg::VPCG {
+            max_terms: self.max_terms, 
+            iterations: self.passes,
+            dims: self.dims,
+            alpha: self.alpha,
+            err: self.err
+        };
+        let embs = vpcg.learn(graph.graph.as_ref(), &features.features, (&left, &right));
+        let node_embeddings = NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings:embs 
+        };
+
+        node_embeddings 
+    }
+
+}
+
+/// Learns embeddings on a coarsened graph, then prolongs and refines them down to the full graph
+#[pyclass]
+struct HierarchicalEmbedder {
+    dims: usize,
+    min_nodes: usize,
+    max_levels: usize,
+    passes: usize,
+    alpha: f32
+}
+
+#[pymethods]
+impl HierarchicalEmbedder {
+    ///    Creates a HierarchicalEmbedder.  The graph is repeatedly coarsened with heavy edge
+    ///    matching; random embeddings are smoothed over the coarsest graph, then copied to each
+    ///    finer level and smoothed again.  Much faster than training on very large graphs.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the final NodeEmbeddings.
+    ///    
+    ///    min_nodes : Int - Optional
+    ///        Stops coarsening once a level has at most this many nodes.  Default is 1000.
+    ///    
+    ///    max_levels : Int - Optional
+    ///        Maximum number of coarsening levels.  Default is 10.
+    ///    
+    ///    passes : Int - Optional
+    ///        Smoothing passes to run at each level.  Default is 3.
+    ///    
+    ///    alpha : Float - Optional
+    ///        Weight of the neighborhood average versus the node's own embedding in each pass.
+    ///        Default is 0.5.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    #[new]
+    pub fn new(
+        dims: usize,
+        min_nodes: Option<usize>,
+        max_levels: Option<usize>,
+        passes: Option<usize>,
+        alpha: Option<f32>
+    ) -> Self {
+        HierarchicalEmbedder {
+            dims,
+            min_nodes: min_nodes.unwrap_or(1000),
+            max_levels: max_levels.unwrap_or(10),
+            passes: passes.unwrap_or(3),
+            alpha: alpha.unwrap_or(0.5)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("HierarchicalEmbedder<Dims={}, MinNodes={}, MaxLevels={}, Passes={}, Alpha={}>",
+                self.dims, self.min_nodes, self.max_levels, self.passes, self.alpha)
+    }
+
+    ///    Learns embeddings for the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    seed : Int - Optional
+    ///        Random seed for coarsening and initialization.  Default is a fixed seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        
+    pub fn learn(&self, py: Python<'_>, graph: &Graph, seed: Option<u64>) -> NodeEmbeddings {
+        let seed = seed.unwrap_or(SEED);
+        let coarsener = Coarsener { min_nodes: self.min_nodes, max_levels: self.max_levels, seed };
+        let he = HierarchicalEmbedding { passes: self.passes, alpha: self.alpha };
+        let (dims, passes, alpha) = (self.dims, self.passes, self.alpha);
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || {
+            let hierarchy = coarsener.coarsen_cdf(g);
+            he.learn_cdf(g, &hierarchy, |coarse| {
+                random_smoothed_embeddings(coarse, dims, passes, alpha, seed)
+            })
+        });
+
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+}
+
+/// Computes embeddings from features using PPR
+#[pyclass]
+struct PPREmbedder {
+    dims: usize,
+    num_walks: usize,
+    steps: f32,
+    beta: f32,
+    eps: f32
+}
+
+#[pymethods]
+impl PPREmbedder {
+    ///    Creates a PPREmbedder.  PPREmbedder constructs the local personal page rank for a node
+    ///    and then performs a weighted blend of those features to construct a new embedding.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Dimension of the embedding space
+    ///    
+    ///    num_walks : Int
+    ///        Number of walks for sampling the local neighborhood
+    ///    
+    ///    steps : Float
+    ///        if steps ~ [0,1), uses restart probabilities to terminate. if steps ~[1, inf], uses
+    ///        fixed length walks.
+    ///    
+    ///    beta : Float - Optional
+    ///        Beta parameter to suppress higher degree nodes.
+    ///
+    ///        Default is 0.8
+    ///    
+    ///    eps : Float - Optional
+    ///        Suppresses terms under eps.  
+    ///
+    ///        Default is 1e-5
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(
+        dims: usize,
+        num_walks: usize, 
+        steps: f32, 
+        beta: Option<f32>, 
+        eps: Option<f32>
+    ) -> Self {
+        PPREmbedder { 
+            dims,
+            num_walks,
+            steps,
+            beta: beta.unwrap_or(0.8),
+            eps: eps.unwrap_or(1e-5)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("PPREmbedder<Dims={}, NumWalks={}, Steps={}, Beta={}, EPS={}>",
+                self.dims, self.num_walks, self.steps, self.beta, self.eps)
+    }
+
+    ///    Constructs Node Embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to use.
+    ///    
+    ///    features : FeatureSet
+    ///        FeatureSet with original features for propagation.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses the random seed.  Otherwise, uses global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings - Can throw exception
+    ///    
+    pub fn learn(&self, 
+        graph: &Graph, 
+        features: &mut FeatureSet,
+        seed: Option<u64>
+    ) -> PyResult<NodeEmbeddings> {
+        features.features.fill_missing_nodes();
+
+        let steps = if self.steps >= 1. {
+            Steps::Fixed(self.steps as usize)
+        } else if self.steps > 0. {
+            Steps::Probability(self.steps)
+        } else {
+            return Err(PyValueError::new_err("Alpha must be between [0, inf)"))
+        };
+
+        let embedder = PPREmbed {
+            dims: self.dims,
+            num_walks: self.num_walks,
+            steps: steps,
+            beta: self.beta,
+            eps: self.eps,
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let embs = embedder.learn(graph.graph.as_ref(), &features.features);
+        
+        let node_embeddings = NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings:embs 
+        };
+
+        Ok(node_embeddings)
+    }
+
+}
+
+/// Computes node embeddings using InstantEmbeddings
+#[pyclass]
+struct InstantEmbeddings {
+    dims: usize,
+    hashes: usize,
+    estimator: Estimator,
+    top_k: Option<usize>
+}
+
+#[pymethods]
+impl InstantEmbeddings {
+
+    ///    Creates an InstantEmbeddings instance.  Instance embeddings construct topological
+    ///    embeddings in graphs by using random walks to capturing local neighborhoods.  They're
+    ///    fast, scalable, and perform well in practice.
+    ///
+    ///    This variant uses sampling to construct the local neighborhood, which can be faster when
+    ///    nodes exist with high degree counts.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Dimension of the InstantEmbeddings.
+    ///    
+    ///    hashes : Int
+    ///        Number of hashes to use for mapping a Node to embedding space.
+    ///    
+    ///    num_walks : Int
+    ///        Number of walks to perform for sampling InstantEmbeddings.
+    ///    
+    ///    steps : Float
+    ///        If steps ~ [0,1), uses restart probabilities to terminate. if steps ~[1, inf], uses
+    ///        fixed length walks.
+    ///    
+    ///    beta : Float - Optional
+    ///        Beta parameter to suppress higher degree nodes.
+    ///
+    ///        Default is 0.8
+    ///    
+    ///    seed : Int - Optional
+    ///        
+    ///    
+    ///    top_k : Int - Optional
+    ///        If provided, only hashes the top K PPR entries for each node.  Default hashes all
+    ///        entries.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        
+    ///    
+    #[staticmethod]
+    pub fn random_walk(
+        dims: usize,
+        hashes: usize,
+        num_walks: usize, 
+        steps: f32, 
+        beta: Option<f32>,
+        seed: Option<u64>,
+        top_k: Option<usize>
+    ) -> PyResult<Self> {
+        let steps = if steps >= 1. {
+            Steps::Fixed(steps as usize)
+        } else if steps > 0. {
+            Steps::Probability(steps)
+        } else {
+            return Err(PyValueError::new_err("Steps must be between [0, inf)"))
+        };
+
+        let ie = InstantEmbeddings { 
+            dims,
+            hashes,
+            estimator: Estimator::RandomWalk {
+                walks: num_walks,
+                steps,
+                beta: beta.unwrap_or(0.8),
+                seed: seed.unwrap_or(SEED)
+            },
+            top_k
+        };
+        Ok(ie)
+    }
+
+    ///    Creates an InstantEmbedding implementation using a sparse PPR estimation which is
+    ///    deterministic.  This is usually faster (assuming a reasonable EPS) when the degree count
+    ///    of the nodes are relatively low; when nodes have large numbers of degrees, it can be
+    ///    substantially slower than sampling based approaches.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Dimension of the InstantEmbeddings.
+    ///    
+    ///    hashes : Int
+    ///        Number of hashes to use for mapping a Node to embedding space.
+    ///    
+    ///    steps : Float
+    ///        Restart probability for random walks.
+    ///    
+    ///    eps : Float - Optional
+    ///        Tolerable error in PPR estimates.  
+    ///
+    ///        Default is 1e-5
+    ///    
+    ///    top_k : Int - Optional
+    ///        If provided, only hashes the top K PPR entries for each node.  Default hashes all
+    ///        entries.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        
+    ///    
+    #[staticmethod]
+    pub fn sparse_ppr(
+        dims: usize,
+        hashes: usize,
+        steps: f32, 
+        eps: Option<f32>,
+        top_k: Option<usize>
+    ) -> PyResult<Self> {
+        if steps <= 0f32 || steps >= 1f32 {
+            return Err(PyValueError::new_err("Steps must be between (0, 1)"))
+        }
+
+        let ie = InstantEmbeddings { 
+            dims,
+            hashes,
+            estimator: Estimator::SparsePPR {
+                p: steps,
+                eps: eps.unwrap_or(1e-5)
+            },
+            top_k
+        };
+
+        Ok(ie)
+    }
+
+    ///    Learns Instant Embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to learn instant embeddings on.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings - Can throw exception
+    ///        
+    ///    
+    pub fn learn(&self, 
+        graph: &Graph, 
+    ) -> PyResult<NodeEmbeddings> {
+
+        let embs = self.embedder().learn(graph.graph.as_ref());
+        
+        let node_embeddings = NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings:embs 
+        };
+
+        Ok(node_embeddings)
+    }
+
+    ///    Embeds a single node in the graph without learning the full set of embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph the node belongs to.
+    ///    
+    ///    node : FQNode
+    ///        Node to embed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float] - Can throw exception
+    ///        Embedding matching the node's row in `learn`.  Throws if the node isn't in the
+    ///        graph.
+    ///    
+    pub fn embed(&self, py: Python<'_>, graph: &Graph, node: FQNode) -> PyResult<Vec<f32>> {
+        let node_id = get_node_id(graph.vocab.deref(), node.0, node.1)?;
+        let embedder = self.embedder();
+        let g = graph.graph.as_ref();
+        Ok(py.allow_threads(move || embedder.embed_node(g, node_id)))
+    }
+
+    ///    Embeds a node which isn't in the graph from its edges into the graph, such as an
+    ///    item added after the graph was built.  Useful as a cold start fallback for nodes
+    ///    missing from a trained embedding store.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed within.
+    ///    
+    ///    neighbors : List[(FQNode, Float)]
+    ///        Nodes in the graph the new node links to, with edge weights.  Unknown nodes are
+    ///        ignored.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float]
+    ///        Embedding in the same space as `learn`.  All zeros if no neighbors are known.
+    ///    
+    pub fn embed_adhoc(
+        &self, 
+        py: Python<'_>, 
+        graph: &Graph, 
+        neighbors: Vec<(FQNode, f32)>
+    ) -> Vec<f32> {
+        let vocab = graph.vocab.deref();
+        let neighbors: Vec<_> = neighbors.into_iter().filter_map(|((node_type, name), w)| {
+            vocab.get_node_id(node_type, name).map(|node_id| (node_id, w))
+        }).collect();
+        let embedder = self.embedder();
+        let g = graph.graph.as_ref();
+        py.allow_threads(move || embedder.embed_adhoc(g, &neighbors))
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("InstantEmbeddings<Dims={}, Hashes={}, Estimator={:?}, TopK={:?}>",
+                self.dims, self.hashes, self.estimator, self.top_k)
+    }
+
+}
+
+impl InstantEmbeddings {
+    fn embedder(&self) -> IE {
+        IE {
+            dims: self.dims,
+            hashes: self.hashes,
+            estimator: self.estimator,
+            top_k: self.top_k
+        }
+    }
+}
+
+#[pyclass]
+struct TournamentBuilder {
+    gb: GraphBuilder,
+    degrees: Vec<f32>
+}
+
+#[pymethods]
+impl TournamentBuilder {
+    ///    Creates a new tournament builder.  This will create a special graph which can be used to
+    ///    compute Plakett-luce models.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new() -> Self {
+        TournamentBuilder {
+            gb: GraphBuilder::new(None, None),
+            degrees: Vec::new()
+        }
+    }
+
+    ///    Adds an outcome with a winner and a loser as well as its associated weight.  
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    winner : FQNode
+    ///        Winner node.
+    ///    
+    ///    loser : FQNode
+    ///        Loser node
+    ///    
+    ///    weight : Float
+    ///        Relative weight
+    ///    
+    pub fn add_outcome(
+        &mut self,
+        winner: FQNode,
+        loser: FQNode,
+        weight: f32
+    ) {
+        let mut v_len = self.gb.vocab.len();
+        let d = weight / 2f32;
+        // Add edge from loser to winner
+        self.gb.add_edge( loser.clone(), winner, d, EdgeType::Directed);
+
+        
+        while v_len < self.gb.vocab.len() {
+            self.degrees.push(1f32);
+            v_len += 1;
+        }
+        
+        // Update norm
+        let node_id = self.gb.vocab.get_node_id(loser.0, loser.1)
+            .expect("Should never fail!");
+
+        self.degrees[node_id] += d;
+    }
+
+    ///    Adds a ordered list where teams in earlier positions out competed teams in later
+    ///    positions.  For example, [Racer1, Racer3, Racer2], gets contructed as:
+    ///    1. Racer1 > Racer3
+    ///    2. Racer1 > Racer2
+    ///    3. Racer3 > Racer1
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    order : List[FQNode]
+    ///        Ordered list of fully qualified nodes where position demarcates ranking in
+    ///        comparison to other nodes in the list.
+    ///    
+    ///    weight : Float
+    ///        Overall weight to give list
+    ///    
+    pub fn add_ranked_outcomes(
+        &mut self,
+        order: Vec<FQNode>,
+        weight: f32
+    ) {
+        for i in 0..order.len() {
+            for j in 1..order.len() {
+                self.add_outcome(order[i].clone(), order[j].clone(), weight)
+            }
+        }
+    }
+
+
+    ///    Builds the tournament.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Tournament - Optional
+    ///        Tournament object capturing orderings.
+    ///    
+    pub fn build(
+        &mut self
+    ) -> Option<Tournament> {
+        if let Some(graph) = self.gb.build_graph() {
+            let mut degrees = Vec::with_capacity(0);
+            std::mem::swap(&mut degrees, &mut self.degrees);
+            let es = EmbeddingStore::new_with_vec(graph.nodes(), 1, EDist::Euclidean, degrees)
+                .expect("Should be correct");
+
+            let embs = NodeEmbeddings {
+                vocab: graph.vocab.clone(),
+                embeddings: es
+            };
+            Some(Tournament { graph: graph, norms: embs })
+        } else {
+            None
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("TournamentBuilder<Nodes={}, Outcomes={}>",
+                self.gb.vocab.len(), self.gb.builder.len())
+    }
+
+}
+
+#[pyclass]
+struct Tournament {
+    graph: Graph,
+    norms: NodeEmbeddings
+}
+
+#[pymethods]
+impl Tournament {
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("Tournament<Nodes={}, Outcomes={}>",
+                self.graph.graph.len(), self.graph.graph.edges())
+    }
+
+}
+
+#[pyclass]
+struct LSR {
+    passes: usize
+}
+
+#[pymethods]
+impl LSR {
+
+    ///    Initializes a Luce Spectral Ranker instance.  Luce Spectral Ranking, or LSR, is a method
+    ///    for learning placket-luce models from a given tournament. It's fast, scalable, and can
+    ///    recover the original parameters with low error.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    passes : Int
+    ///        
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    ///    
+    #[new]
+    pub fn new(passes: usize) -> Self {
+        LSR { passes }
+    }
+    
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("LSR<Passes={}>", self.passes)
+    }
+
+    ///    Fits a Placket-Luce model to the provided tournament.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph representing the markovian state used by LSR models.  This graph is
+    ///        constructed via the TournamentBuilder instance.
+    ///    
+    ///    team_norms : NodeEmbeddings
+    ///        Captures the 
+    ///    
+    ///    indicator : Bool - Optional
+    ///        
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings 
+    ///        Logits for rankings
+    ///    
+    pub fn learn(
+        &self,
+        tournament: &Tournament,
+        indicator: Option<bool>
+    ) -> NodeEmbeddings {
+        let g = tournament.graph.graph.as_ref();
+        let norms = &tournament.norms.embeddings;
+        let lsr = ALSR { passes: self.passes };
+        let scores = lsr.compute(g, norms, indicator.unwrap_or(true));
+        // Find the page rank of the tournament graph
+
+        let embs = EmbeddingStore::new(g.len(), 1, EDist::Euclidean);
+        scores.par_iter().enumerate().for_each(|(node_id, score)| {
+            let e1 = embs.get_embedding_mut_hogwild(node_id);
+            e1[0] = *score;
+        });
+
+        let node_embeddings = NodeEmbeddings {
+            vocab: tournament.graph.vocab.clone(),
+            embeddings: embs 
+        };
+
+        node_embeddings
+       
+    }
+}
+
+#[pyclass]
+struct ConnectedComponents {}
+
+#[pymethods]
+impl ConnectedComponents {
+
+    ///    Computes the graph, looking for connected components.  This is only guaranteed to work
+    ///    correctly for undirected graphs.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to find connected components in
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        NodeEmbeddings of 1 dimension where the value of the embedding references the
+    ///        component id.  All nodes sharing the same component id are connected.
+    ///    
+    #[staticmethod]
+    pub fn learn(graph: &Graph) -> NodeEmbeddings {
+        let es = find_connected_components(graph.graph.as_ref());
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings: es
+        }
+    }
+}
+
+#[pyclass]
+struct Triangles {}
+
+#[pymethods]
+impl Triangles {
+
+    ///    Counts the triangles each node participates in along with its local clustering
+    ///    coefficient.  Edge direction and weights are ignored.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to count triangles in
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        NodeEmbeddings of 2 dimensions: the triangle count and the local clustering
+    ///        coefficient.
+    ///    
+    #[staticmethod]
+    pub fn learn(py: Python<'_>, graph: &Graph) -> NodeEmbeddings {
+        let g = graph.graph.as_ref();
+        let counts = py.allow_threads(move || count_triangles(g));
+        let mut es = EmbeddingStore::new(counts.triangles.len(), 2, EDist::Euclidean);
+        counts.triangles.iter().zip(counts.clustering.iter()).enumerate().for_each(|(node_id, (t, c))| {
+            es.set_embedding(node_id, &[*t as f32, *c]);
+        });
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings: es
+        }
+    }
+
+    ///    Counts the distinct triangles in the graph and its average clustering coefficient.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to count triangles in
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (Int, Float)
+    ///        Total triangles and the mean local clustering coefficient.
+    ///    
+    #[staticmethod]
+    pub fn summarize(py: Python<'_>, graph: &Graph) -> (usize, f32) {
+        let g = graph.graph.as_ref();
+        py.allow_threads(move || {
+            let counts = count_triangles(g);
+            (counts.total(), counts.average_clustering())
+        })
+    }
+}
+
+#[derive(Clone)]
+enum ReducerType {
+    PCA { dims: usize, iterations: usize },
+    RandomProjection { dims: usize, density: Option<f32> }
+}
+
+/// Reduces the dimensionality of NodeEmbeddings, either through PCA or sparse random projections.
+#[pyclass]
+struct EmbeddingReducer {
+    reducer: ReducerType,
+    seed: u64
+}
+
+#[pymethods]
+impl EmbeddingReducer {
+
+    ///    Reduces embeddings using Principal Component Analysis.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of components to keep.
+    ///    
+    ///    iterations : Int - Optional
+    ///        Number of subspace iterations to run when estimating the components.  Default is 20.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[staticmethod]
+    pub fn pca(dims: usize, iterations: Option<usize>, seed: Option<u64>) -> Self {
+        let reducer = ReducerType::PCA { dims, iterations: iterations.unwrap_or(20) };
+        EmbeddingReducer { reducer, seed: seed.unwrap_or(SEED) }
+    }
+
+    ///    Reduces embeddings using a sparse random projection.  Much cheaper than PCA and
+    ///    approximately preserves distances.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of output dimensions.
+    ///    
+    ///    density : Float - Optional
+    ///        Fraction of non-zero values in the projection matrix.  Default is 1 / sqrt(input dims).
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[staticmethod]
+    pub fn random_projection(dims: usize, density: Option<f32>, seed: Option<u64>) -> Self {
+        let reducer = ReducerType::RandomProjection { dims, density };
+        EmbeddingReducer { reducer, seed: seed.unwrap_or(SEED) }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        match self.reducer {
+            ReducerType::PCA { dims, iterations } => 
+                format!("EmbeddingReducer<PCA, dims={}, iterations={}>", dims, iterations),
+            ReducerType::RandomProjection { dims, density } => 
+                format!("EmbeddingReducer<RandomProjection, dims={}, density={:?}>", dims, density)
+        }
+    }
+
+    ///    Creates a new, reduced set of NodeEmbeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings to reduce.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings with the reduced dimensionality, using the same distance.
+    ///    
+    pub fn reduce(&self, embeddings: &NodeEmbeddings) -> NodeEmbeddings {
+        let es = &embeddings.embeddings;
+        let projection = match self.reducer {
+            ReducerType::PCA { dims, iterations } => {
+                PCA { dims, iterations, seed: self.seed }.fit(es)
+            },
+            ReducerType::RandomProjection { dims, density } => {
+                let density = density.unwrap_or(1f32 / (es.dims() as f32).sqrt());
+                SparseRandomProjection { dims, density, seed: self.seed }.fit(es.dims())
+            }
+        };
+
+        NodeEmbeddings {
+            vocab: embeddings.vocab.clone(),
+            embeddings: projection.transform_store(es)
+        }
+    }
+}
+
+/// Rotates NodeEmbeddings onto another set of NodeEmbeddings, such as from a previous training
+/// run, using the nodes they share as anchors.
+#[pyclass]
+struct ProcrustesAligner {
+    map: OrthogonalMap,
+    anchors: usize,
+    rmse: f32
+}
+
+#[pymethods]
+impl ProcrustesAligner {
+
+    ///    Learns the orthogonal map taking the source embeddings onto the target embeddings.
+    ///    Nodes are matched by type and name, so the two embeddings can use different vocabs.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    source : NodeEmbeddings
+    ///        Embeddings to rotate.
+    ///    
+    ///    target : NodeEmbeddings
+    ///        Embeddings defining the space to rotate into.
+    ///    
+    ///    center : Bool - Optional
+    ///        If True, also learns a translation by centering both sets of anchors.  Leave off
+    ///        for cosine embeddings.  Default is False.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        Throws if the dimensions differ or the embeddings share no nodes.
+    ///    
+    #[staticmethod]
+    pub fn fit(
+        py: Python<'_>,
+        source: &NodeEmbeddings,
+        target: &NodeEmbeddings,
+        center: Option<bool>
+    ) -> PyResult<Self> {
+        if source.embeddings.dims() != target.embeddings.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions must match!"))
+        }
+
+        let table = source.vocab.create_translation_table(&target.vocab);
+        let anchors = shared_anchors(&source.embeddings, &target.embeddings, &table);
+        if anchors.is_empty() {
+            return Err(PyValueError::new_err("Embeddings share no nodes to align on!"))
+        }
+
+        let num_anchors = anchors.len();
+        let procrustes = Procrustes { center: center.unwrap_or(false) };
+        let (s_es, t_es) = (&source.embeddings, &target.embeddings);
+        let (map, rmse) = py.allow_threads(move || {
+            let map = procrustes.fit(s_es, t_es, &anchors);
+            let rmse = map.rmse(s_es, t_es, &anchors);
+            (map, rmse)
+        });
+
+        Ok(ProcrustesAligner { map, anchors: num_anchors, rmse })
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("ProcrustesAligner<dims={}, anchors={}, rmse={}>", self.map.dims(), self.anchors, self.rmse)
+    }
+
+    ///    Number of nodes shared between the source and target which were used as anchors.
+    pub fn num_anchors(&self) -> usize {
+        self.anchors
+    }
+
+    ///    Root mean squared euclidean distance between the rotated source anchors and their
+    ///    targets.  Large values mean the spaces differ by more than a rotation.
+    pub fn rmse(&self) -> f32 {
+        self.rmse
+    }
+
+    ///    Rotates a set of NodeEmbeddings into the target space.  These are typically the
+    ///    source embeddings, but can be any embeddings living in the same space.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings to rotate.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings - Can throw exception
+    ///        Rotated embeddings, keeping their vocab and distance.  Throws if the dimensions
+    ///        don't match.
+    ///    
+    pub fn transform(&self, embeddings: &NodeEmbeddings) -> PyResult<NodeEmbeddings> {
+        if embeddings.embeddings.dims() != self.map.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions must match!"))
+        }
+        Ok(NodeEmbeddings {
+            vocab: embeddings.vocab.clone(),
+            embeddings: self.map.transform_store(&embeddings.embeddings)
+        })
+    }
+
+    ///    Rotates a single embedding into the target space.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embedding : List[Float]
+    ///        Embedding to rotate.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float] - Can throw exception
+    ///        Rotated embedding.  Throws if the dimensions don't match.
+    ///    
+    pub fn transform_embedding(&self, embedding: Vec<f32>) -> PyResult<Vec<f32>> {
+        if embedding.len() != self.map.dims() {
+            return Err(PyValueError::new_err("Embedding dimensions must match!"))
+        }
+        Ok(self.map.transform(&embedding))
+    }
+}
+
+/// Deterministic spectral embeddings from a randomized SVD of a node similarity matrix.  A fast,
+/// strong baseline for small and medium graphs.
+#[pyclass]
+struct SpectralEmbedder {
+    dims: usize,
+    similarity: Similarity,
+    seed: u64
+}
+
+#[pymethods]
+impl SpectralEmbedder {
+
+    ///    Factorizes the transition matrix.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the embeddings.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[staticmethod]
+    pub fn adjacency(dims: usize, seed: Option<u64>) -> Self {
+        SpectralEmbedder { dims, similarity: Similarity::Adjacency, seed: seed.unwrap_or(SEED) }
+    }
+
+    ///    Factorizes the truncated Katz index, as in HOPE.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the embeddings.
+    ///    
+    ///    beta : Float - Optional
+    ///        Decay applied to each additional hop.  Default is 0.1.
+    ///    
+    ///    order : Int - Optional
+    ///        Number of hops to sum over.  Default is 3.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[staticmethod]
+    pub fn katz(dims: usize, beta: Option<f32>, order: Option<usize>, seed: Option<u64>) -> Self {
+        let similarity = Similarity::Katz { beta: beta.unwrap_or(0.1), order: order.unwrap_or(3) };
+        SpectralEmbedder { dims, similarity, seed: seed.unwrap_or(SEED) }
+    }
+
+    ///    Factorizes the truncated personalized PageRank matrix.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the embeddings.
+    ///    
+    ///    restart_p : Float - Optional
+    ///        Probability of restarting at each step.  Default is 0.15.
+    ///    
+    ///    order : Int - Optional
+    ///        Number of hops to sum over.  Default is 10.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[staticmethod]
+    pub fn ppr(dims: usize, restart_p: Option<f32>, order: Option<usize>, seed: Option<u64>) -> Self {
+        let similarity = Similarity::PPR { 
+            restart_p: restart_p.unwrap_or(0.15), 
+            order: order.unwrap_or(10) 
+        };
+        SpectralEmbedder { dims, similarity, seed: seed.unwrap_or(SEED) }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("SpectralEmbedder<dims={}, similarity={:?}>", self.dims, self.similarity)
+    }
+
+    ///    Learns embeddings for the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings using cosine distance, ordered by singular value.
+    ///    
+    pub fn learn(&self, py: Python<'_>, graph: &Graph) -> NodeEmbeddings {
+        let se = SpectralEmbedding::new(self.dims, self.similarity, self.seed);
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || se.learn_cdf(g));
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+}
+
+/// Structural role embeddings from neighborhood degree histograms.  Nodes with similar local
+/// shapes, such as hubs of similar rings, end up close even when they share no neighbors.
+#[pyclass]
+struct StructuralEmbedder {
+    hops: usize,
+    bins: usize
+}
+
+#[pymethods]
+impl StructuralEmbedder {
+
+    ///    Creates a StructuralEmbedder.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    hops : Int - Optional
+    ///        Number of hops of neighbor degree histograms to include.  Default is 3.
+    ///    
+    ///    bins : Int - Optional
+    ///        Number of log2 degree bins per hop.  Default is 16.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[new]
+    pub fn new(hops: Option<usize>, bins: Option<usize>) -> Self {
+        StructuralEmbedder { hops: hops.unwrap_or(3), bins: bins.unwrap_or(16) }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("StructuralEmbedder<hops={}, bins={}>", self.hops, self.bins)
+    }
+
+    ///    Learns structural embeddings for the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings of bins * (hops + 1) dimensions using cosine distance.
+    ///    
+    pub fn learn(&self, py: Python<'_>, graph: &Graph) -> NodeEmbeddings {
+        let se = StructuralEmbedding { hops: self.hops, bins: self.bins };
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || se.learn(g));
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+}
+
+/// Smooths trained embeddings over the graph by repeatedly blending each node with its weighted
+/// neighbors.  Helps noisy, low signal nodes and fills in nodes missing embeddings.
+#[pyclass]
+struct EmbeddingSmoother {
+    rounds: usize,
+    retention: f32
+}
+
+#[pymethods]
+impl EmbeddingSmoother {
+
+    ///    Creates an EmbeddingSmoother.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    rounds : Int - Optional
+    ///        Number of rounds of smoothing.  Default is 2.
+    ///    
+    ///    retention : Float - Optional
+    ///        Fraction of each node's own embedding kept every round, ~ [0, 1].  Default is 0.5.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///    
+    #[new]
+    pub fn new(rounds: Option<usize>, retention: Option<f32>) -> PyResult<Self> {
+        let retention = retention.unwrap_or(0.5);
+        if !(0f32..=1f32).contains(&retention) {
+            return Err(PyValueError::new_err("retention must be between [0, 1]"))
+        }
+        Ok(EmbeddingSmoother { rounds: rounds.unwrap_or(2), retention })
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("EmbeddingSmoother<rounds={}, retention={}>", self.rounds, self.retention)
+    }
+
+    ///    Smooths embeddings over the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph whose edge weights guide the smoothing.
+    ///    
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings trained on the graph, sharing its vocab.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings - Can throw exception
+    ///        Smoothed embeddings with the same distance.  Throws if the embeddings don't cover
+    ///        the graph's nodes.
+    ///    
+    pub fn smooth(
+        &self, 
+        py: Python<'_>, 
+        graph: &Graph, 
+        embeddings: &NodeEmbeddings
+    ) -> PyResult<NodeEmbeddings> {
+        if embeddings.embeddings.len() != graph.graph.len() {
+            return Err(PyValueError::new_err(format!(
+                "Embeddings have {} nodes but the graph has {}!",
+                embeddings.embeddings.len(), graph.graph.len())))
+        }
+
+        let smoother = CEmbeddingSmoother { rounds: self.rounds, retention: self.retention };
+        let (g, es) = (graph.graph.as_ref(), &embeddings.embeddings);
+        let smoothed = py.allow_threads(move || smoother.smooth(g, es));
+        Ok(NodeEmbeddings {
+            vocab: embeddings.vocab.clone(),
+            embeddings: smoothed
+        })
+    }
+}
+
+/// FastRP embeddings: sparse random projections propagated over several powers of the transition
+/// matrix.  No training is involved, so it is orders of magnitude faster than EP on large graphs.
+#[pyclass]
+struct FastRPEmbedder {
+    dims: usize,
+    weights: Vec<f32>,
+    normalization: f32,
+    density: f32
+}
+
+#[pymethods]
+impl FastRPEmbedder {
+
+    ///    Creates a FastRPEmbedder.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the embeddings.
+    ///    
+    ///    weights : List[Float] - Optional
+    ///        Weight of each power of the transition matrix, starting at the first hop.  The
+    ///        length determines the number of hops.  Default is [0., 1., 1.].
+    ///    
+    ///    normalization : Float - Optional
+    ///        Scales each node's random vector by degree ^ normalization.  Negative values reduce
+    ///        the influence of hubs.  Default is 0.
+    ///    
+    ///    density : Float - Optional
+    ///        Fraction of non-zero entries in the random vectors.  Default is 1/3.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///    
+    #[new]
+    pub fn new(
+        dims: usize, 
+        weights: Option<Vec<f32>>, 
+        normalization: Option<f32>, 
+        density: Option<f32>
+    ) -> Self {
+        FastRPEmbedder {
+            dims,
+            weights: weights.unwrap_or_else(|| vec![0., 1., 1.]),
+            normalization: normalization.unwrap_or(0.),
+            density: density.unwrap_or(1. / 3.)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("FastRPEmbedder<dims={}, weights={:?}, normalization={}, density={}>", 
+                self.dims, self.weights, self.normalization, self.density)
+    }
+
+    ///    Learns embeddings for the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, uses this seed for randomization.  Otherwise uses the global seed.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings using cosine distance.
+    ///    
+    pub fn learn(&self, py: Python<'_>, graph: &Graph, seed: Option<u64>) -> NodeEmbeddings {
+        let frp = FastRP {
+            dims: self.dims,
+            weights: self.weights.clone(),
+            normalization: self.normalization,
+            density: self.density,
+            seed: seed.unwrap_or(SEED)
+        };
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || frp.learn(g));
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+}
+
+#[pyclass]
+struct RandomPath {
+    rng: XorShiftRng
+}
+
+#[pymethods]
+impl RandomPath {
+
+    #[new]
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED + 125));
+        RandomPath { rng }
+    }
+    
+    ///    Performs random walks on a graph and returns the full path of nodes visited.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to find connected components in
+    ///
+    ///    node : FQNode
+    ///        Start Node for the random walks
+    ///
+    ///    counts : Positive Int
+    ///        Number of random walks to run
+    ///
+    ///    restarts : Float
+    ///        restarts ~ (0,1), determines the probability a random walk will terminate with
+    ///        lower restarts resulting in longer walks.
+    ///    
+    ///    weighted : Bool 
+    ///        Weighted versus unweighted sampling.
+    ///        
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[FQNode]]
+    ///        Provides a list of random walks with nodes traversed in order until termination.
+    ///    
+    pub fn rollout<>(
+        &mut self,
+        graph: &Graph, 
+        node: FQNode,
+        count: usize,
+        restarts: f32, 
+        weighted: bool,
+    ) -> PyResult<Vec<Vec<FQNode>>> {
+        let steps = Steps::from_float(restarts)
+            .ok_or_else(|| PyValueError::new_err("restarts must be between [0, inf)"))?;
+        //let sampler: Box<dyn Sampler<_>> = if weighted { Box::new(Weighted) } else { Box::new(Unweighted) };
+        let g = graph.graph.as_ref();
+
+        let mut outputs: Vec<Vec<NodeID>> = vec![Vec::new(); count];
+        let rngs: Vec<_> = (0..count as u64)
+            .map(|i| XorShiftRng::seed_from_u64(self.rng.gen::<u64>() + i))
+            .collect();
+
+        let vocab = graph.vocab.deref(); 
+        let node_id = get_node_id(vocab, node.0, node.1)?;
+        outputs.par_iter_mut().zip(rngs.into_par_iter()).for_each(|(walk, mut rng)| {
+            walk.push(node_id);
+            if weighted {
+                rollout(g, steps, &Weighted, node_id, &mut rng, walk);
+            } else {
+                rollout(g, steps, &Weighted, node_id, &mut rng, walk);
+            };
+        });
+
+        let it = outputs.into_iter().map(|walk| walk.into_iter().map(|node_id| {
+            convert_node_id_to_fqn(vocab, node_id)
+        }).collect());
+
+        Ok(it.collect())
+    }
+
+}
+
+/// Generates node2vec biased random walks, suitable as sentences for skip-gram style training.
+#[pyclass]
+#[derive(Clone)]
+struct Node2VecWalker {
+    walk_length: usize,
+    walks_per_node: usize,
+    p: f32,
+    q: f32
+}
+
+#[pymethods]
+impl Node2VecWalker {
+
+    #[new]
+    ///    Creates a Node2VecWalker instance.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    walk_length : Int
+    ///        Number of nodes in each walk, including the start node.
+    ///    
+    ///    walks_per_node : Int
+    ///        Number of walks to start from each node.
+    ///    
+    ///    p : Float - Optional
+    ///        Return parameter.  Higher values discourage immediately revisiting the previous
+    ///        node.  Default is 1.
+    ///    
+    ///    q : Float - Optional
+    ///        In-out parameter.  q > 1 keeps walks close to the start node (BFS-like) while q < 1
+    ///        pushes them outward (DFS-like).  Default is 1.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    pub fn new(walk_length: usize, walks_per_node: usize, p: Option<f32>, q: Option<f32>) -> PyResult<Self> {
+        let p = p.unwrap_or(1.);
+        let q = q.unwrap_or(1.);
+        if p <= 0. || q <= 0. {
+            return Err(PyValueError::new_err("p and q must be positive"))
+        }
+        Ok(Node2VecWalker { walk_length, walks_per_node, p, q })
+    }
+
+    /// Simple representation of the Node2VecWalker
+    pub fn __repr__(&self) -> String {
+        format!("Node2VecWalker<walk_length={}, walks_per_node={}, p={}, q={}>", 
+                self.walk_length, self.walks_per_node, self.p, self.q)
+    }
+
+    ///    Generates walks over the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to walk.
+    ///    
+    ///    nodes : List[FQNode] - Optional
+    ///        Nodes to start walks from.  Defaults to every node in the graph.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether to sample neighbors proportional to edge weights.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[FQNode]] - Can throw exception
+    ///        Walks ordered by pass, then by start node.  Walks reaching a node without edges
+    ///        terminate early.
+    ///    
+    pub fn walks(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        nodes: Option<Vec<FQNode>>,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<Vec<FQNode>>> {
+        let vocab = graph.vocab.deref();
+        let start_nodes = if let Some(nodes) = nodes {
+            nodes.into_iter()
+                .map(|(nt, name)| get_node_id(vocab, nt, name))
+                .collect::<PyResult<Vec<_>>>()?
+        } else {
+            (0..graph.graph.len()).collect()
+        };
+
+        let n2v = Node2Vec {
+            walk_length: self.walk_length,
+            walks_per_node: self.walks_per_node,
+            p: self.p,
+            q: self.q,
+            seed: seed.unwrap_or(SEED)
+        };
+
+        let g = graph.graph.as_ref();
+        let walks = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                n2v.walks(g, &Weighted, &start_nodes)
+            } else {
+                n2v.walks(g, &Unweighted, &start_nodes)
+            }
+        });
+
+        Ok(walks.into_iter().map(|walk| walk.into_iter().map(|node_id| {
+            convert_node_id_to_fqn(vocab, node_id)
+        }).collect()).collect())
+    }
+
+}
+
+/// Generates metapath constrained random walks over heterogeneous graphs, as in metapath2vec.
+#[pyclass]
+#[derive(Clone)]
+struct MetaPathWalker {
+    metapath: Vec<String>,
+    walk_length: usize,
+    walks_per_node: usize
+}
+
+#[pymethods]
+impl MetaPathWalker {
+
+    #[new]
+    ///    Creates a MetaPathWalker instance.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    metapath : List[Str]
+    ///        Node types to visit in order, such as ['author', 'paper', 'venue', 'paper',
+    ///        'author'].  Must start and end with the same type; walks cycle through it.
+    ///    
+    ///    walk_length : Int
+    ///        Number of nodes in each walk, including the start node.
+    ///    
+    ///    walks_per_node : Int
+    ///        Number of walks to start from each node of the first type.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self - Can throw exception
+    ///        
+    pub fn new(metapath: Vec<String>, walk_length: usize, walks_per_node: usize) -> PyResult<Self> {
+        if metapath.len() < 2 || metapath[0] != metapath[metapath.len() - 1] {
+            return Err(PyValueError::new_err("metapath must start and end with the same node type"))
+        }
+        Ok(MetaPathWalker { metapath, walk_length, walks_per_node })
+    }
+
+    /// Simple representation of the MetaPathWalker
+    pub fn __repr__(&self) -> String {
+        format!("MetaPathWalker<metapath={:?}, walk_length={}, walks_per_node={}>", 
+                self.metapath, self.walk_length, self.walks_per_node)
+    }
 
+    ///    Generates walks over the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to walk.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether to sample neighbors proportional to edge weights.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[FQNode]] - Can throw exception
+    ///        Walks ordered by pass, then by start node.  Walks reaching a node without
+    ///        neighbors of the next type terminate early.
+    ///    
+    pub fn walks(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        seed: Option<u64>,
+        weighted: Option<bool>
+    ) -> PyResult<Vec<Vec<FQNode>>> {
+        let walks = self.generate(py, graph, seed.unwrap_or(SEED), weighted.unwrap_or(true))?;
+        let vocab = graph.vocab.deref();
+        Ok(walks.into_iter().map(|walk| walk.into_iter().map(|node_id| {
+            convert_node_id_to_fqn(vocab, node_id)
+        }).collect()).collect())
     }
 }
 
-/// 
+impl MetaPathWalker {
+    fn generate(&self, py: Python<'_>, graph: &Graph, seed: u64, weighted: bool) -> PyResult<Vec<Vec<NodeID>>> {
+        let metapath = MetaPath::new(graph.vocab.deref(), &self.metapath)
+            .map_err(PyValueError::new_err)?;
+        let walker = CMetaPathWalker {
+            walk_length: self.walk_length,
+            walks_per_node: self.walks_per_node,
+            seed
+        };
+        let g = graph.graph.as_ref();
+        Ok(py.allow_threads(move || walker.walks(g, &metapath, weighted)))
+    }
+}
+
+/// Learns node embeddings without features by training skip-gram with negative sampling over
+/// node2vec walks, as in DeepWalk and node2vec.
 #[pyclass]
-#[derive(Clone,Debug)]
-struct PprRankLearner {
-    alpha: f32,
-    batch_size: usize,
+struct SkipGramEmbedder {
     dims: usize,
+    window: usize,
+    negatives: usize,
     passes: usize,
+    batch_size: usize,
+    alpha: f32
+}
+
+#[pymethods]
+impl SkipGramEmbedder {
+
+    #[new]
+    ///    Creates a SkipGramEmbedder instance.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the learned embeddings.
+    ///    
+    ///    window : Int - Optional
+    ///        Maximum distance within a walk between a node and its contexts.  Default is 5.
+    ///    
+    ///    negatives : Int - Optional
+    ///        Number of negatives sampled for each positive pair.  Default is 5.
+    ///    
+    ///    passes : Int - Optional
+    ///        Number of passes over the walks.  Default is 5.
+    ///    
+    ///    batch_size : Int - Optional
+    ///        Number of walks per gradient update.  Default is 128.
+    ///    
+    ///    alpha : Float - Optional
+    ///        Peak learning rate for Adam.  Default is 0.05.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    pub fn new(
+        dims: usize,
+        window: Option<usize>,
+        negatives: Option<usize>,
+        passes: Option<usize>,
+        batch_size: Option<usize>,
+        alpha: Option<f32>
+    ) -> Self {
+        SkipGramEmbedder {
+            dims,
+            window: window.unwrap_or(5),
+            negatives: negatives.unwrap_or(5),
+            passes: passes.unwrap_or(5),
+            batch_size: batch_size.unwrap_or(128),
+            alpha: alpha.unwrap_or(0.05)
+        }
+    }
+
+    /// Simple representation of the SkipGramEmbedder
+    pub fn __repr__(&self) -> String {
+        format!("SkipGramEmbedder<dims={}, window={}, negatives={}, passes={}, batch_size={}, alpha={}>",
+                self.dims, self.window, self.negatives, self.passes, self.batch_size, self.alpha)
+    }
+
+    ///    Generates walks from every node and learns embeddings from them.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    walker : Node2VecWalker
+    ///        Walk parameters.  p = q = 1 recovers DeepWalk.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether to sample neighbors proportional to edge weights.  Default is True.
+    ///    
+    ///    indicator : Bool - Optional
+    ///        Shows a progress bar.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings using cosine distance.  Nodes never visited by a walk are untrained.
+    ///    
+    pub fn learn(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        walker: &Node2VecWalker,
+        seed: Option<u64>,
+        weighted: Option<bool>,
+        indicator: Option<bool>
+    ) -> NodeEmbeddings {
+        let seed = seed.unwrap_or(SEED);
+        let n2v = Node2Vec {
+            walk_length: walker.walk_length,
+            walks_per_node: walker.walks_per_node,
+            p: walker.p,
+            q: walker.q,
+            seed
+        };
+        let sg = SkipGram {
+            dims: self.dims,
+            window: self.window,
+            negatives: self.negatives,
+            passes: self.passes,
+            batch_size: self.batch_size,
+            alpha: self.alpha,
+            seed,
+            indicator: indicator.unwrap_or(true)
+        };
+
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                sg.learn(g, &Weighted, &n2v)
+            } else {
+                sg.learn(g, &Unweighted, &n2v)
+            }
+        });
+
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+
+    ///    Learns embeddings from metapath constrained walks, as in metapath2vec.  Only nodes
+    ///    with types in the metapath are trained.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    walker : MetaPathWalker
+    ///        Metapath and walk parameters.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether to sample neighbors proportional to edge weights.  Default is True.
+    ///    
+    ///    indicator : Bool - Optional
+    ///        Shows a progress bar.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings - Can throw exception
+    ///        Embeddings using cosine distance.
+    ///    
+    pub fn learn_metapath(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        walker: &MetaPathWalker,
+        seed: Option<u64>,
+        weighted: Option<bool>,
+        indicator: Option<bool>
+    ) -> PyResult<NodeEmbeddings> {
+        let seed = seed.unwrap_or(SEED);
+        let walks = walker.generate(py, graph, seed, weighted.unwrap_or(true))?;
+        let sg = SkipGram {
+            dims: self.dims,
+            window: self.window,
+            negatives: self.negatives,
+            passes: self.passes,
+            batch_size: self.batch_size,
+            alpha: self.alpha,
+            seed,
+            indicator: indicator.unwrap_or(true)
+        };
+
+        let nodes = graph.graph.len();
+        let embeddings = py.allow_threads(move || sg.learn_from_walks(nodes, walks));
+        Ok(NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        })
+    }
+}
+
+/// Learns node embeddings by fitting each node's personalized PageRank distribution, as in VERSE.
+#[pyclass]
+struct VerseEmbedder {
+    dims: usize,
+    restart_p: f32,
+    samples_per_node: usize,
     negatives: usize,
-    loss: String,
-    weight_decay: f32,
-    steps: Steps,
-    walks: usize,
+    passes: usize,
+    batch_size: usize,
+    alpha: f32
+}
+
+#[pymethods]
+impl VerseEmbedder {
+
+    #[new]
+    ///    Creates a VerseEmbedder instance.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    dims : Int
+    ///        Number of dimensions for the learned embeddings.
+    ///    
+    ///    restart_p : Float - Optional
+    ///        Probability of the PPR walk terminating at each step.  Default is 0.15.
+    ///    
+    ///    samples_per_node : Int - Optional
+    ///        Number of PPR samples drawn for each node per pass.  Default is 10.
+    ///    
+    ///    negatives : Int - Optional
+    ///        Number of uniform negatives per PPR sample.  Default is 3.
+    ///    
+    ///    passes : Int - Optional
+    ///        Number of passes over the nodes.  Default is 10.
+    ///    
+    ///    batch_size : Int - Optional
+    ///        Number of nodes per gradient update.  Default is 256.
+    ///    
+    ///    alpha : Float - Optional
+    ///        Peak learning rate for Adam.  Default is 0.05.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Self
+    ///        
+    pub fn new(
+        dims: usize,
+        restart_p: Option<f32>,
+        samples_per_node: Option<usize>,
+        negatives: Option<usize>,
+        passes: Option<usize>,
+        batch_size: Option<usize>,
+        alpha: Option<f32>
+    ) -> PyResult<Self> {
+        let restart_p = restart_p.unwrap_or(0.15);
+        if restart_p <= 0. || restart_p > 1. {
+            return Err(PyValueError::new_err("restart_p must be in (0, 1]"))
+        }
+        Ok(VerseEmbedder {
+            dims,
+            restart_p,
+            samples_per_node: samples_per_node.unwrap_or(10),
+            negatives: negatives.unwrap_or(3),
+            passes: passes.unwrap_or(10),
+            batch_size: batch_size.unwrap_or(256),
+            alpha: alpha.unwrap_or(0.05)
+        })
+    }
+
+    /// Simple representation of the VerseEmbedder
+    pub fn __repr__(&self) -> String {
+        format!("VerseEmbedder<dims={}, restart_p={}, samples_per_node={}, negatives={}, passes={}, batch_size={}, alpha={}>",
+                self.dims, self.restart_p, self.samples_per_node, self.negatives, self.passes,
+                self.batch_size, self.alpha)
+    }
+
+    ///    Learns embeddings for the graph.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    graph : Graph
+    ///        Graph to embed.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    weighted : Bool - Optional
+    ///        Whether PPR walks follow edge weights.  Default is True.
+    ///    
+    ///    indicator : Bool - Optional
+    ///        Shows a progress bar.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Embeddings using cosine distance.
+    ///    
+    pub fn learn(
+        &self,
+        py: Python<'_>,
+        graph: &Graph,
+        seed: Option<u64>,
+        weighted: Option<bool>,
+        indicator: Option<bool>
+    ) -> NodeEmbeddings {
+        let verse = Verse {
+            dims: self.dims,
+            restart_p: self.restart_p,
+            samples_per_node: self.samples_per_node,
+            negatives: self.negatives,
+            passes: self.passes,
+            batch_size: self.batch_size,
+            alpha: self.alpha,
+            seed: seed.unwrap_or(SEED),
+            indicator: indicator.unwrap_or(true)
+        };
+
+        let g = graph.graph.as_ref();
+        let embeddings = py.allow_threads(move || {
+            if weighted.unwrap_or(true) {
+                verse.learn(g, &Weighted)
+            } else {
+                verse.learn(g, &Unweighted)
+            }
+        });
+
+        NodeEmbeddings {
+            vocab: graph.vocab.clone(),
+            embeddings
+        }
+    }
+}
+
+/// Mini-batch k-means over node embeddings, for sharding, candidate generation, and analysis.
+#[pyclass]
+struct MiniBatchKMeans {
     k: usize,
-    num_features: Option<usize>,
-    compression: f32,
-    valid_pct: f32,
-    beta: f32
+    batch_size: usize,
+    iterations: usize
 }
 
 #[pymethods]
-impl PprRankLearner {
+impl MiniBatchKMeans {
 
-    ///    Createsa a PPR Rank Learner.  PPR Rank Learner optimizes similarities based on a nodes
-    ///    local neighborhood:
-    ///
-    ///    1. For each node, compute their top K local neighborhood based on sampling, including
-    ///       relative weight.
-    ///    2. During optimization, sample N negatives
-    ///    3. Optimize a listwise lost which pushes the local neighborhood higher than the randomly
-    ///       sampled negatives.
-    ///
-    ///    This has some interesting dynamics.  Unlike EmbeddingPropagation, this captures
-    ///    neighborhoods and local clusters instead of just immediate neighbors; this allows the
-    ///    algorithm to better bias toward local topologies rather than basic connections.
-    ///    It also is able to use ranking listwise metrics for optimization since the PPR estimate
-    ///    gives an ordering to nodes.
+    ///    Creates a MiniBatchKMeans instance.
     ///    
     ///    Parameters
     ///    ----------
-    ///    alpha : Float
-    ///        Learning Rate
-    ///    
-    ///    batch_size : Int
-    ///        Batch size
+    ///    k : Int
+    ///        Number of clusters.
     ///    
-    ///    dims : Int
-    ///        Dimension of the feature and eventual node embeddings.
+    ///    batch_size : Int - Optional
+    ///        Number of nodes sampled for each update.  Default is 1024.
     ///    
-    ///    passes : Int
-    ///        Number of passes to use
+    ///    iterations : Int - Optional
+    ///        Number of mini-batch updates.  Default is 100.
     ///    
-    ///    steps : Float
-    ///        Restart probability.  When steps ~ [0,1), it results in a restart probability.  When
-    ///        steps ~ [1,inf], it is treated as a fixed length walk.
+    ///    Returns
+    ///    -------
+    ///    Self
     ///    
-    ///    walks : Int
-    ///        Number of samples to collect.
+    #[new]
+    pub fn new(k: usize, batch_size: Option<usize>, iterations: Option<usize>) -> Self {
+        MiniBatchKMeans {
+            k,
+            batch_size: batch_size.unwrap_or(1024),
+            iterations: iterations.unwrap_or(100)
+        }
+    }
+
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("MiniBatchKMeans<k={}, batch_size={}, iterations={}>", 
+                self.k, self.batch_size, self.iterations)
+    }
+
+    ///    Clusters the embeddings using their distance metric.
     ///    
-    ///    k : Int
-    ///        Top K nearest neighbors according to PPR estimates.
+    ///    Parameters
+    ///    ----------
+    ///    embeddings : NodeEmbeddings
+    ///        Embeddings to cluster.
     ///    
-    ///    negatives : Int
-    ///        Number of random negatives to use.
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
     ///    
-    ///    loss : String - Optional
-    ///        Supports 'listnet' and 'listmle'.
+    ///    Returns
+    ///    -------
+    ///    Clusters
+    ///        Assignments and centroids.
     ///    
-    ///    compression : Float - Optional
-    ///        This will either accentuate or depress the PPR scores for the local neighborhood.
-    ///        Higher compressions will accentuate, lower compressions will depress.
+    pub fn fit(&self, py: Python<'_>, embeddings: &NodeEmbeddings, seed: Option<u64>) -> Clusters {
+        let kmeans = CMiniBatchKMeans {
+            k: self.k,
+            batch_size: self.batch_size,
+            iterations: self.iterations,
+            seed: seed.unwrap_or(SEED)
+        };
+        let es = &embeddings.embeddings;
+        let clusters = py.allow_threads(move || kmeans.fit(es));
+        Clusters { vocab: embeddings.vocab.clone(), clusters }
+    }
+}
+
+/// Clusters learned by MiniBatchKMeans
+#[pyclass]
+struct Clusters {
+    vocab: Arc<Vocab>,
+    clusters: CClusters
+}
+
+#[pymethods]
+impl Clusters {
+
+    /// Number of clusters
+    pub fn __len__(&self) -> usize {
+        self.clusters.centroids.len()
+    }
+
+    /// Cluster assignment for every node
+    pub fn assignments(&self) -> Vec<(FQNode, usize)> {
+        let vocab = self.vocab.deref();
+        self.clusters.assignments.iter().enumerate()
+            .map(|(node_id, c)| (convert_node_id_to_fqn(vocab, node_id), *c))
+            .collect()
+    }
+
+    ///    Looks up the cluster for a node.
     ///    
-    ///    beta : Float - Optional
-    ///        Determines how much to bias toward community structure versus rarer nodes.
-    ///
-    ///        Default is 0.8
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Node to look up.
     ///    
-    ///    num_features : Int - Optional
-    ///        How many features to use to construct nodes.  If provided, will randomly sample
-    ///        features each construction.
-    ///
-    ///        Default is all
+    ///    Returns
+    ///    -------
+    ///    Int - Can throw exception
+    ///        Cluster id.  Throws if the node wasn't clustered.
     ///    
-    ///    weight_decay : Float - Optional
-    ///        If provided, applies weight decar to feature embeddings.
-    ///
-    ///        Default is 0.
+    pub fn get_cluster(&self, node: FQNode) -> PyResult<usize> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.clusters.assignments[node_id])
+    }
+
+    ///    Returns the nodes assigned to a cluster.
     ///    
-    ///    valid_pct : Float - Optional
-    ///        If provided, uses a subset of the graph for validation.
+    ///    Parameters
+    ///    ----------
+    ///    cluster : Int
+    ///        Cluster id.
     ///    
     ///    Returns
     ///    -------
-    ///    Self - Can throw exception
-    ///        
+    ///    List[FQNode] - Can throw exception
+    ///        Throws if the cluster doesn't exist.
     ///    
-    #[new]
-    fn new(
-         // Learning rate
-        alpha: f32, 
- 
-        // Batch size 
-        batch_size: usize, 
-
-        // Node embedding size
-        dims: usize,
-
-        // Number of passes to run
-        passes: usize,
-
-        // Number of steps to take
-        steps: f32,
-
-        // Number of walks per node
-        walks: usize,
+    pub fn members(&self, cluster: usize) -> PyResult<Vec<FQNode>> {
+        if cluster >= self.clusters.centroids.len() {
+            return Err(PyValueError::new_err(format!("Cluster {} doesn't exist!", cluster)))
+        }
+        let vocab = self.vocab.deref();
+        Ok(self.clusters.assignments.iter().enumerate()
+            .filter(|(_, c)| **c == cluster)
+            .map(|(node_id, _)| convert_node_id_to_fqn(vocab, node_id))
+            .collect())
+    }
 
-        // Number of neighbors to extract from random walk
-        k: usize,
+    /// Number of nodes in each cluster
+    pub fn sizes(&self) -> Vec<usize> {
+        self.clusters.sizes()
+    }
 
-        // Number of  negatives, produced from random walks.  The quality of these deeply
-        // depend on the quality of the graph
-        negatives: usize,
+    /// Sum of distances between each node and its centroid
+    pub fn inertia(&self) -> f32 {
+        self.clusters.inertia
+    }
 
-        // Loss to use
-        loss: Option<String>,
+    /// Cluster centroids
+    pub fn centroids(&self) -> Vec<Vec<f32>> {
+        self.clusters.centroids.clone()
+    }
 
-        // The PPR scores are either accentuated or depressed by the compression factor
-        compression: Option<f32>,
-        
-        // How much to benefit rarer/popular items
-        beta: Option<f32>,
+    ///    Returns the centroids as NodeEmbeddings, using node type "cluster" with the cluster id
+    ///    as the name.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    NodeEmbeddings
+    ///        Centroids with the same distance as the clustered embeddings.
+    ///    
+    pub fn centroid_embeddings(&self) -> NodeEmbeddings {
+        let mut vocab = Vocab::new();
+        (0..self.clusters.centroids.len()).for_each(|c| {
+            vocab.get_or_insert("cluster".to_string(), c.to_string());
+        });
+        NodeEmbeddings {
+            vocab: Arc::new(vocab),
+            embeddings: self.clusters.centroid_store()
+        }
+    }
 
-        num_features: Option<usize>,
+    ///    Assigns an adhoc embedding to its closest cluster.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    embedding : List[Float]
+    ///        Embedding in the same space as the clustered embeddings.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    (Int, Float) - Can throw exception
+    ///        Cluster id and distance to its centroid.  Throws if the embedding has the wrong
+    ///        dimensions.
+    ///    
+    pub fn assign(&self, embedding: Vec<f32>) -> PyResult<(usize, f32)> {
+        let dims = self.clusters.centroids.first().map(|c| c.len()).unwrap_or(0);
+        if embedding.len() != dims {
+            return Err(PyValueError::new_err(format!("Embedding must have {} dims!", dims)))
+        }
+        Ok(self.clusters.assign(&embedding))
+    }
 
-        weight_decay: Option<f32>, 
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("Clusters<k={}, inertia={}>", self.clusters.centroids.len(), self.clusters.inertia)
+    }
+}
 
-        // Percentage of nodes to use for validation
-        valid_pct: Option<f32>,
+fn fit_density_clusters<A: AnnIndex + ?Sized>(
+    py: Python<'_>,
+    index: &A,
+    embeddings: &NodeEmbeddings,
+    min_cluster_size: usize,
+    min_samples: Option<usize>,
+    k: Option<usize>
+) -> DensityClusters {
+    let min_samples = min_samples.unwrap_or(5);
+    let dc = DensityClustering {
+        min_cluster_size,
+        min_samples,
+        k: k.unwrap_or(3 * min_samples)
+    };
+    let es = &embeddings.embeddings;
+    let clusters = py.allow_threads(move || dc.fit(index, es));
+    DensityClusters { vocab: embeddings.vocab.clone(), clusters }
+}
 
-    ) -> PyResult<Self> {
+/// Clusters found by density clustering over an ANN index.  Nodes in sparse regions are left
+/// unassigned as noise.
+#[pyclass]
+struct DensityClusters {
+    vocab: Arc<Vocab>,
+    clusters: CDensityClusters
+}
 
-        let steps = if steps >= 1. {
-            Steps::Fixed(steps as usize)
-        } else if steps > 0. {
-            Steps::Probability(steps)
-        } else {
-            return Err(PyValueError::new_err("Alpha must be between [0, inf)"))
-        };
+#[pymethods]
+impl DensityClusters {
 
-        Ok(PprRankLearner {
-            alpha,
-            batch_size,
-            dims,
-            passes,
-            steps,
-            negatives,
-            walks,
-            k,
-            num_features,
-            weight_decay: weight_decay.unwrap_or(0f32),
-            loss: loss.unwrap_or_else(||"listnet".into()),
-            compression: compression.unwrap_or(1f32),
-            valid_pct: valid_pct.unwrap_or(0.1),
-            beta: beta.unwrap_or(0.8)
-        })
+    /// Number of clusters
+    pub fn __len__(&self) -> usize {
+        self.clusters.num_clusters
     }
-    
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("{:?}", self)
+
+    /// Cluster label for every node, None for noise
+    pub fn labels(&self) -> Vec<(FQNode, Option<usize>)> {
+        let vocab = self.vocab.deref();
+        self.clusters.labels.iter().enumerate()
+            .map(|(node_id, c)| (convert_node_id_to_fqn(vocab, node_id), *c))
+            .collect()
     }
 
-    ///    Learns the feature embeddings for a given graph.
+    ///    Looks up the cluster for a node.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to use for construction.
-    ///    
-    ///    features : FeatureSet
-    ///        FeatureSet mapping nodes -> features
-    ///    
-    ///    feature_embeddings : mut NodeEmbeddings - Optional
-    ///        If provided, uses the feature_embeddings passed in as a starting point.  Otherwise,
-    ///        creates a new randomly initialized NodeEmbedding.
-    ///
-    ///        Default is new.
-    ///    
-    ///    indicator : Bool - Optional
-    ///        If provided, shows an indicator.
-    ///
-    ///        Default is True
-    ///    
-    ///    seed : Int - Optional
-    ///        If provided, uses the seed for all stochastic processes.
-    ///
-    ///        Default is a fixed global seed.
+    ///    node : FQNode
+    ///        Node to look up.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings
-    ///        Learned Feature Embeddings.
+    ///    Int - Optional - Can throw exception
+    ///        Cluster id, or None if the node is noise.  Throws if the node wasn't clustered.
     ///    
-    pub fn learn_features(
-        &self, 
-        graph: &Graph, 
-        features: &mut FeatureSet,
-        feature_embeddings: Option<&mut NodeEmbeddings>,
-        indicator: Option<bool>,
-        seed: Option<u64>
-    ) -> NodeEmbeddings {
+    pub fn get_cluster(&self, node: FQNode) -> PyResult<Option<usize>> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        Ok(self.clusters.labels[node_id])
+    }
+
+    ///    Returns the nodes assigned to a cluster.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    cluster : Int
+    ///        Cluster id.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[FQNode] - Can throw exception
+    ///        Throws if the cluster doesn't exist.
+    ///    
+    pub fn members(&self, cluster: usize) -> PyResult<Vec<FQNode>> {
+        if cluster >= self.clusters.num_clusters {
+            return Err(PyValueError::new_err(format!("Cluster {} doesn't exist!", cluster)))
+        }
+        let vocab = self.vocab.deref();
+        Ok(self.clusters.labels.iter().enumerate()
+            .filter(|(_, c)| **c == Some(cluster))
+            .map(|(node_id, _)| convert_node_id_to_fqn(vocab, node_id))
+            .collect())
+    }
 
-        let loss = if self.loss == "listnet" {
-            PprLoss::ListNet { passive: true, weight_decay: self.weight_decay }
-        } else {
-            PprLoss::ListMLE { weight_decay: self.weight_decay }
-        };
+    /// Nodes which weren't assigned to any cluster
+    pub fn noise(&self) -> Vec<FQNode> {
+        let vocab = self.vocab.deref();
+        self.clusters.noise().into_iter()
+            .map(|node_id| convert_node_id_to_fqn(vocab, node_id))
+            .collect()
+    }
 
-        let ppr_rank = PprRank {
-            alpha: self.alpha,
-            batch_size: self.batch_size,
-            dims: self.dims,
-            passes: self.passes,
-            steps: self.steps,
-            loss: loss,
-            negatives: self.negatives,
-            num_walks: self.walks,
-            beta: self.beta,
-            k: self.k,
-            num_features: self.num_features,
-            compression: self.compression,
-            valid_pct: self.valid_pct,
-            indicator: indicator.unwrap_or(true),
-            seed: seed.unwrap_or(SEED)
-        };
+    /// Number of nodes in each cluster
+    pub fn sizes(&self) -> Vec<usize> {
+        self.clusters.sizes()
+    }
 
-        features.features.fill_missing_nodes();
+    /// GLOSH outlier score for every node, ~ [0, 1].  Higher is more anomalous.
+    pub fn outlier_scores(&self) -> Vec<(FQNode, f32)> {
+        let vocab = self.vocab.deref();
+        self.clusters.outlier_scores.iter().enumerate()
+            .map(|(node_id, s)| (convert_node_id_to_fqn(vocab, node_id), *s))
+            .collect()
+    }
 
-        // Pull out the EmbeddingStore
-        let feature_embeddings = feature_embeddings.map(|fes| {
-           let mut sfes = EmbeddingStore::new(fes.vocab.len(), 0, EDist::Cosine);
-           std::mem::swap(&mut sfes, &mut fes.embeddings);
-           sfes
-        });
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("DensityClusters<clusters={}, noise={}>", 
+                self.clusters.num_clusters, self.clusters.noise().len())
+    }
+}
 
-        let feat_embeds = ppr_rank.learn(&*graph.graph, &features.features, feature_embeddings);
-        let vocab = features.features.clone_vocab();
+fn find_duplicates<A: AnnIndex + ?Sized>(
+    py: Python<'_>,
+    index: &A,
+    embeddings: &NodeEmbeddings,
+    max_distance: f32,
+    k: Option<usize>,
+    same_type: Option<bool>
+) -> Duplicates {
+    let finder = DuplicateFinder { k: k.unwrap_or(10), max_distance };
+    let same_type = same_type.unwrap_or(true);
+    let es = &embeddings.embeddings;
+    let vocab = embeddings.vocab.deref();
+    let pairs = py.allow_threads(move || {
+        finder.find(index, es, |a, b| {
+            !same_type || vocab.get_node_type_id_of(a) == vocab.get_node_type_id_of(b)
+        })
+    });
+    Duplicates { vocab: embeddings.vocab.clone(), pairs }
+}
 
-        let feature_embeddings = NodeEmbeddings {
-            vocab: Arc::new(vocab),
-            embeddings: feat_embeds};
+/// Near-duplicate node pairs found over an ANN index
+#[pyclass]
+struct Duplicates {
+    vocab: Arc<Vocab>,
+    pairs: Vec<(NodeID, NodeID, f32)>
+}
 
-        feature_embeddings
+#[pymethods]
+impl Duplicates {
+
+    /// Number of duplicate pairs
+    pub fn __len__(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Duplicate pairs and their distances, closest first
+    pub fn pairs(&self) -> Vec<(FQNode, FQNode, f32)> {
+        let vocab = self.vocab.deref();
+        self.pairs.iter()
+            .map(|(a, b, d)| (convert_node_id_to_fqn(vocab, *a), convert_node_id_to_fqn(vocab, *b), *d))
+            .collect()
+    }
+
+    ///    Groups duplicates transitively into merge candidates.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[List[FQNode]]
+    ///        Nodes to merge together.  The first node in each group is a stable representative
+    ///        to merge the rest into.
+    ///    
+    pub fn groups(&self) -> Vec<Vec<FQNode>> {
+        let vocab = self.vocab.deref();
+        merge_groups(vocab.len(), &self.pairs).into_iter()
+            .map(|group| group.into_iter().map(|node_id| convert_node_id_to_fqn(vocab, node_id)).collect())
+            .collect()
     }
 
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("Duplicates<pairs={}>", self.pairs.len())
+    }
 }
 
-/// Learns VPCG vectors on a graph.
+/// Approximates effective resistance between nodes by sketching the graph Laplacian.  Useful for
+/// scoring edge importance and sparsifying graphs before training.
 #[pyclass]
-struct VpcgEmbedder {
-    max_terms: usize, 
-    passes: usize,
+struct EffectiveResistance {
     dims: usize,
-    alpha: f32,
-    err: f32
+    iterations: usize,
+    tolerance: f32
 }
 
 #[pymethods]
-impl VpcgEmbedder {
-    ///    Creates a VPCGEmbedder.  VPCG uses bipartite graphs with features to learn embeddings
-    ///    that attempt to bridge the semantic gap.  It is highly effective on Search and
-    ///    Recommendation problems.
-    ///
-    ///    The graph needs to be a bipartite graph where one side of the graph has a distinct type
-    ///    from the other side.
+impl EffectiveResistance {
+
+    ///    Creates an EffectiveResistance instance.  The graph is treated as undirected, using the
+    ///    transition probabilities of each edge as its weight.
     ///    
     ///    Parameters
     ///    ----------
-    ///    max_terms : Int
-    ///        Max terms to store on each node during propagation.
-    ///    
-    ///    passes : Int
-    ///        Number of passes to perform.  A good starting point is 10
-    ///    
-    ///    dims : Int
-    ///        Number of dimensions for the final NodeEmbeddings.
+    ///    dims : Int - Optional
+    ///        Number of random projections.  Error shrinks with 1/sqrt(dims).  Default is 64.
     ///    
-    ///    alpha : Float - Optional
-    ///        If provided, alpha ~ [0,1] allows nodes to reinforce their own features over the propagated
-    ///        features.  Higher alphas rely more on propagated features whereas lower alphas
-    ///        attempt to preserve the original feature set more.
-    ///
-    ///        Default is 1.
+    ///    iterations : Int - Optional
+    ///        Maximum conjugate gradient iterations per projection.  Default is 100.
     ///    
-    ///    err : Float - Optional
-    ///        Suppresses terms with a weight of less than err.  Default is 1e-5
+    ///    tolerance : Float - Optional
+    ///        Relative residual at which each solve stops early.  Default is 1e-4.
     ///    
     ///    Returns
     ///    -------
     ///    Self
-    ///        
     ///    
     #[new]
-    pub fn new(
-        max_terms: usize, 
-        passes: usize, 
-        dims: usize, 
-        alpha: Option<f32>, 
-        err: Option<f32>
-    ) -> Self {
-        VpcgEmbedder { 
-            max_terms, 
-            passes, 
-            dims,
-            alpha: alpha.unwrap_or(1f32),
-            err: err.unwrap_or(1e-5f32)
+    pub fn new(dims: Option<usize>, iterations: Option<usize>, tolerance: Option<f32>) -> Self {
+        EffectiveResistance {
+            dims: dims.unwrap_or(64),
+            iterations: iterations.unwrap_or(100),
+            tolerance: tolerance.unwrap_or(1e-4)
         }
     }
 
     /// Simple Python representation 
     pub fn __repr__(&self) -> String {
-        format!("VPCGEmbedder<MaxTerms={}, Passes={}, Dims={}, Alpha={}, Err={}>",
-                self.max_terms, self.passes, self.dims, self.alpha, self.err)
+        format!("EffectiveResistance<dims={}, iterations={}, tolerance={}>", 
+                self.dims, self.iterations, self.tolerance)
     }
 
-    ///    Learns VPCG embeddings on the graph.
+    ///    Sketches the graph so resistances can be queried.
     ///    
     ///    Parameters
     ///    ----------
     ///    graph : Graph
-    ///        Graph to use for bipartite structure
-    ///    
-    ///    features : FeatureSet
-    ///        Features to propagate between nodes
+    ///        Graph to sketch.
     ///    
-    ///    start_node_type : String
-    ///        Starting node type for propagation.
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings
-    ///        New NodeEmbeddings capturing the VPCG Embeddings.
+    ///    ResistanceSketch
     ///    
-    pub fn learn(&self, 
-        graph: &Graph, 
-        features: &mut FeatureSet,
-        start_node_type: String
-    ) -> NodeEmbeddings  {
-        let (mut left, mut right) = (Vec::new(), Vec::new());
-        for node_id in 0..graph.graph.len() {
-            let nt = graph.vocab.get_node_type(node_id).unwrap();
-            if nt.as_ref() == &start_node_type {
-                left.push(node_id);
-            } else {
-                right.push(node_id)
-            }
-        }
-
-        features.features.fill_missing_nodes();
-        let vpcg = crate::algos::vpcg::VPCG {
-            max_terms: self.max_terms, 
-            iterations: self.passes,
+    pub fn sketch(&self, py: Python<'_>, graph: &Graph, seed: Option<u64>) -> ResistanceSketch {
+        let er = CEffectiveResistance {
             dims: self.dims,
-            alpha: self.alpha,
-            err: self.err
-        };
-        let embs = vpcg.learn(graph.graph.as_ref(), &features.features, (&left, &right));
-        let node_embeddings = NodeEmbeddings {
-            vocab: graph.vocab.clone(),
-            embeddings:embs 
+            iterations: self.iterations,
+            tolerance: self.tolerance,
+            seed: seed.unwrap_or(SEED)
         };
-
-        node_embeddings 
+        let g = graph.graph.as_ref();
+        let sketch = py.allow_threads(move || er.sketch_cdf(g));
+        ResistanceSketch { vocab: graph.vocab.clone(), sketch }
     }
-
 }
 
-/// Computes embeddings from features using PPR
+/// Sketch of a graph's Laplacian for querying effective resistances
 #[pyclass]
-struct PPREmbedder {
-    dims: usize,
-    num_walks: usize,
-    steps: f32,
-    beta: f32,
-    eps: f32
+struct ResistanceSketch {
+    vocab: Arc<Vocab>,
+    sketch: CResistanceSketch
+}
+
+impl ResistanceSketch {
+    fn convert_edges(&self, scores: Vec<f32>) -> Vec<(FQNode, FQNode, f32)> {
+        let vocab = self.vocab.deref();
+        self.sketch.edges().iter().zip(scores.into_iter())
+            .map(|((u, v, _), s)| (convert_node_id_to_fqn(vocab, *u), convert_node_id_to_fqn(vocab, *v), s))
+            .collect()
+    }
 }
 
 #[pymethods]
-impl PPREmbedder {
-    ///    Creates a PPREmbedder.  PPREmbedder constructs the local personal page rank for a node
-    ///    and then performs a weighted blend of those features to construct a new embedding.
+impl ResistanceSketch {
+
+    ///    Approximate effective resistance between two nodes.
     ///    
     ///    Parameters
     ///    ----------
-    ///    dims : Int
-    ///        Dimension of the embedding space
-    ///    
-    ///    num_walks : Int
-    ///        Number of walks for sampling the local neighborhood
-    ///    
-    ///    steps : Float
-    ///        if steps ~ [0,1), uses restart probabilities to terminate. if steps ~[1, inf], uses
-    ///        fixed length walks.
-    ///    
-    ///    beta : Float - Optional
-    ///        Beta parameter to suppress higher degree nodes.
-    ///
-    ///        Default is 0.8
+    ///    a : FQNode
+    ///        First node.
     ///    
-    ///    eps : Float - Optional
-    ///        Suppresses terms under eps.  
-    ///
-    ///        Default is 1e-5
+    ///    b : FQNode
+    ///        Second node.
     ///    
     ///    Returns
     ///    -------
-    ///    Self
-    ///        
+    ///    Float - Can throw exception
+    ///        Resistance, only meaningful for nodes in the same component.  Throws if either
+    ///        node doesn't exist.
     ///    
-    #[new]
-    pub fn new(
-        dims: usize,
-        num_walks: usize, 
-        steps: f32, 
-        beta: Option<f32>, 
-        eps: Option<f32>
-    ) -> Self {
-        PPREmbedder { 
-            dims,
-            num_walks,
-            steps,
-            beta: beta.unwrap_or(0.8),
-            eps: eps.unwrap_or(1e-5)
-        }
+    pub fn resistance(&self, a: FQNode, b: FQNode) -> PyResult<f32> {
+        let vocab = self.vocab.deref();
+        let a = get_node_id(vocab, a.0, a.1)?;
+        let b = get_node_id(vocab, b.0, b.1)?;
+        Ok(self.sketch.resistance(a, b))
     }
 
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("PPREmbedder<Dims={}, NumWalks={}, Steps={}, Beta={}, EPS={}>",
-                self.dims, self.num_walks, self.steps, self.beta, self.eps)
+    /// Resistance of every undirected edge
+    pub fn edge_resistances(&self) -> Vec<(FQNode, FQNode, f32)> {
+        self.convert_edges(self.sketch.edge_resistances())
     }
 
-    ///    Constructs Node Embeddings.
+    /// Leverage, weight * resistance, of every undirected edge.  Edges near one are bridges or
+    /// otherwise hard to route around; edges inside dense clusters score low.
+    pub fn leverage(&self) -> Vec<(FQNode, FQNode, f32)> {
+        self.convert_edges(self.sketch.leverage())
+    }
+
+    ///    Sparsifies the graph by sampling edges proportional to their leverage, reweighting
+    ///    them so the graph's spectrum is preserved in expectation.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to use.
-    ///    
-    ///    features : FeatureSet
-    ///        FeatureSet with original features for propagation.
+    ///    samples : Int
+    ///        Number of edges to sample, with replacement.  Repeated samples are merged.
     ///    
     ///    seed : Int - Optional
-    ///        If provided, uses the random seed.  Otherwise, uses global seed.
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings - Can throw exception
+    ///    Graph
+    ///        Undirected graph sharing the vocab of the sketched graph.
     ///    
-    pub fn learn(&self, 
-        graph: &Graph, 
-        features: &mut FeatureSet,
-        seed: Option<u64>
-    ) -> PyResult<NodeEmbeddings> {
-        features.features.fill_missing_nodes();
-
-        let steps = if self.steps >= 1. {
-            Steps::Fixed(self.steps as usize)
-        } else if self.steps > 0. {
-            Steps::Probability(self.steps)
-        } else {
-            return Err(PyValueError::new_err("Alpha must be between [0, inf)"))
-        };
-
-        let embedder = PPREmbed {
-            dims: self.dims,
-            num_walks: self.num_walks,
-            steps: steps,
-            beta: self.beta,
-            eps: self.eps,
-            seed: seed.unwrap_or(SEED)
-        };
-
-        let embs = embedder.learn(graph.graph.as_ref(), &features.features);
-        
-        let node_embeddings = NodeEmbeddings {
-            vocab: graph.vocab.clone(),
-            embeddings:embs 
-        };
+    pub fn sparsify(&self, py: Python<'_>, samples: usize, seed: Option<u64>) -> Graph {
+        let sketch = &self.sketch;
+        let graph = py.allow_threads(move || sketch.sparsify(samples, seed.unwrap_or(SEED)));
+        Graph {
+            graph: Arc::new(CumCSR::convert(graph)),
+            vocab: self.vocab.clone()
+        }
+    }
 
-        Ok(node_embeddings)
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("ResistanceSketch<nodes={}, edges={}>", self.sketch.num_nodes(), self.sketch.edges().len())
     }
+}
+
+/// Relation scoring models for knowledge graph embeddings.
+#[pyclass]
+#[derive(Clone,Copy,Debug)]
+pub enum RelationModel {
+    /// Relations as translations, h + r ~= t.  Handles antisymmetric and composed relations.
+    TransE,
+
+    /// Relations as diagonal bilinear maps.  Only expresses symmetric relations.
+    DistMult,
 
+    /// DistMult over complex embeddings.  Expresses both symmetric and antisymmetric relations;
+    /// dims must be even.
+    ComplEx
 }
 
-/// Computes node embeddings using InstantEmbeddings
+/// Knowledge graph embeddings, learning an embedding per entity and per relation from typed
+/// edges.
 #[pyclass]
-struct InstantEmbeddings {
+struct KGEmbedder {
     dims: usize,
-    hashes: usize,
-    estimator: Estimator
+    model: RelationModel,
+    margin: f32,
+    negatives: usize,
+    passes: usize,
+    batch_size: usize,
+    alpha: f32
 }
 
 #[pymethods]
-impl InstantEmbeddings {
+impl KGEmbedder {
 
-    ///    Creates an InstantEmbeddings instance.  Instance embeddings construct topological
-    ///    embeddings in graphs by using random walks to capturing local neighborhoods.  They're
-    ///    fast, scalable, and perform well in practice.
-    ///
-    ///    This variant uses sampling to construct the local neighborhood, which can be faster when
-    ///    nodes exist with high degree counts.
+    #[new]
+    ///    Creates a KGEmbedder instance.
     ///    
     ///    Parameters
     ///    ----------
     ///    dims : Int
-    ///        Dimension of the InstantEmbeddings.
+    ///        Number of dimensions for entities and relations.
     ///    
-    ///    hashes : Int
-    ///        Number of hashes to use for mapping a Node to embedding space.
+    ///    model : RelationModel - Optional
+    ///        How triples are scored.  Default is TransE.
     ///    
-    ///    num_walks : Int
-    ///        Number of walks to perform for sampling InstantEmbeddings.
+    ///    margin : Float - Optional
+    ///        Minimum score gap between true and corrupted triples.  Default is 1.
     ///    
-    ///    steps : Float
-    ///        If steps ~ [0,1), uses restart probabilities to terminate. if steps ~[1, inf], uses
-    ///        fixed length walks.
+    ///    negatives : Int - Optional
+    ///        Number of corrupted triples per true triple.  Default is 5.
     ///    
-    ///    beta : Float - Optional
-    ///        Beta parameter to suppress higher degree nodes.
-    ///
-    ///        Default is 0.8
+    ///    passes : Int - Optional
+    ///        Number of passes over the triples.  Default is 10.
     ///    
-    ///    seed : Int - Optional
-    ///        
+    ///    batch_size : Int - Optional
+    ///        Number of triples per gradient update.  Default is 512.
+    ///    
+    ///    alpha : Float - Optional
+    ///        Peak learning rate for Adam.  Default is 0.01.
     ///    
     ///    Returns
     ///    -------
     ///    Self - Can throw exception
     ///        
-    ///    
-    #[staticmethod]
-    pub fn random_walk(
+    pub fn new(
         dims: usize,
-        hashes: usize,
-        num_walks: usize, 
-        steps: f32, 
-        beta: Option<f32>,
-        seed: Option<u64>
+        model: Option<RelationModel>,
+        margin: Option<f32>,
+        negatives: Option<usize>,
+        passes: Option<usize>,
+        batch_size: Option<usize>,
+        alpha: Option<f32>
     ) -> PyResult<Self> {
-        let steps = if steps >= 1. {
-            Steps::Fixed(steps as usize)
-        } else if steps > 0. {
-            Steps::Probability(steps)
-        } else {
-            return Err(PyValueError::new_err("Steps must be between [0, inf)"))
-        };
-
-        let ie = InstantEmbeddings { 
+        let model = model.unwrap_or(RelationModel::TransE);
+        if matches!(model, RelationModel::ComplEx) && dims % 2 != 0 {
+            return Err(PyValueError::new_err("ComplEx requires an even number of dims"))
+        }
+        Ok(KGEmbedder {
             dims,
-            hashes,
-            estimator: Estimator::RandomWalk {
-                walks: num_walks,
-                steps,
-                beta: beta.unwrap_or(0.8),
-                seed: seed.unwrap_or(SEED)
-            }
-        };
-        Ok(ie)
+            model,
+            margin: margin.unwrap_or(1.),
+            negatives: negatives.unwrap_or(5),
+            passes: passes.unwrap_or(10),
+            batch_size: batch_size.unwrap_or(512),
+            alpha: alpha.unwrap_or(0.01)
+        })
     }
 
-    ///    Creates an InstantEmbedding implementation using a sparse PPR estimation which is
-    ///    deterministic.  This is usually faster (assuming a reasonable EPS) when the degree count
-    ///    of the nodes are relatively low; when nodes have large numbers of degrees, it can be
-    ///    substantially slower than sampling based approaches.
+    /// Simple representation of the KGEmbedder
+    pub fn __repr__(&self) -> String {
+        format!("KGEmbedder<dims={}, model={:?}, margin={}, negatives={}, passes={}, batch_size={}, alpha={}>",
+                self.dims, self.model, self.margin, self.negatives, self.passes, self.batch_size, self.alpha)
+    }
+
+    ///    Learns entity and relation embeddings from (head, relation, tail) triples.
     ///    
     ///    Parameters
     ///    ----------
-    ///    dims : Int
-    ///        Dimension of the InstantEmbeddings.
+    ///    triples : List[(FQNode, Str, FQNode)]
+    ///        Typed edges to train on.
     ///    
-    ///    hashes : Int
-    ///        Number of hashes to use for mapping a Node to embedding space.
-    ///    
-    ///    steps : Float
-    ///        Restart probability for random walks.
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
     ///    
-    ///    eps : Float - Optional
-    ///        Tolerable error in PPR estimates.  
-    ///
-    ///        Default is 1e-5
+    ///    indicator : Bool - Optional
+    ///        Shows a progress bar.  Default is True.
     ///    
     ///    Returns
     ///    -------
-    ///    Self - Can throw exception
-    ///        
+    ///    (NodeEmbeddings, NodeEmbeddings)
+    ///        Entity embeddings and relation embeddings, both using Euclidean distance.  Relations
+    ///        are keyed by the node type 'relation'.
     ///    
-    #[staticmethod]
-    pub fn sparse_ppr(
-        dims: usize,
-        hashes: usize,
-        steps: f32, 
-        eps: Option<f32>
-    ) -> PyResult<Self> {
-        if steps <= 0f32 || steps >= 1f32 {
-            return Err(PyValueError::new_err("Steps must be between (0, 1)"))
-        }
+    pub fn learn(
+        &self,
+        py: Python<'_>,
+        triples: Vec<(FQNode, String, FQNode)>,
+        seed: Option<u64>,
+        indicator: Option<bool>
+    ) -> (NodeEmbeddings, NodeEmbeddings) {
+        let mut vocab = Vocab::new();
+        let mut relation_vocab = Vocab::new();
+        let edges = triples.into_iter().map(|((ht, hn), relation, (tt, tn))| {
+            let head = vocab.get_or_insert(ht, hn);
+            let tail = vocab.get_or_insert(tt, tn);
+            let rel = relation_vocab.get_or_insert("relation".to_string(), relation);
+            (head, tail, 1., rel as u32)
+        }).collect::<Vec<_>>();
+
+        let trainer = KGTrainer {
+            dims: self.dims,
+            margin: self.margin,
+            negatives: self.negatives,
+            passes: self.passes,
+            batch_size: self.batch_size,
+            alpha: self.alpha,
+            seed: seed.unwrap_or(SEED),
+            indicator: indicator.unwrap_or(true)
+        };
 
-        let ie = InstantEmbeddings { 
-            dims,
-            hashes,
-            estimator: Estimator::SparsePPR {
-                p: steps,
-                eps: eps.unwrap_or(1e-5)
+        let model = self.model;
+        let (entities, relations) = py.allow_threads(move || {
+            let graph = RelationalGraph::construct_from_edges(edges);
+            match model {
+                RelationModel::TransE   => trainer.learn(&kge::TransE, &graph),
+                RelationModel::DistMult => trainer.learn(&kge::DistMult, &graph),
+                RelationModel::ComplEx  => trainer.learn(&kge::ComplEx, &graph)
             }
-        };
+        });
 
-        Ok(ie)
+        let entities = NodeEmbeddings { vocab: Arc::new(vocab), embeddings: entities };
+        let relations = NodeEmbeddings { vocab: Arc::new(relation_vocab), embeddings: relations };
+        (entities, relations)
     }
 
-    ///    Learns Instant Embeddings.
+    ///    Scores triples with this embedder's relation model.  Higher is more plausible.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to learn instant embeddings on.
+    ///    entities : NodeEmbeddings
+    ///        Entity embeddings returned by learn.
+    ///    
+    ///    relations : NodeEmbeddings
+    ///        Relation embeddings returned by learn.
+    ///    
+    ///    triples : List[(FQNode, Str, FQNode)]
+    ///        Triples to score.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings - Can throw exception
-    ///        
+    ///    List[Float] - Can throw exception
+    ///        Score for each triple.  Throws if an entity or relation is unknown.
     ///    
-    pub fn learn(&self, 
-        graph: &Graph, 
-    ) -> PyResult<NodeEmbeddings> {
-
-        let embedder = IE {
-            dims: self.dims,
-            hashes: self.hashes,
-            estimator: self.estimator
-        };
+    pub fn score(
+        &self,
+        entities: &NodeEmbeddings,
+        relations: &NodeEmbeddings,
+        triples: Vec<(FQNode, String, FQNode)>
+    ) -> PyResult<Vec<f32>> {
+        let (ev, rv) = (entities.vocab.deref(), relations.vocab.deref());
+        let (ee, re) = (&entities.embeddings, &relations.embeddings);
+        triples.into_iter().map(|((ht, hn), relation, (tt, tn))| {
+            let head = get_node_id(ev, ht, hn)?;
+            let tail = get_node_id(ev, tt, tn)?;
+            let rel = get_node_id(rv, "relation".to_string(), relation)?;
+            Ok(match self.model {
+                RelationModel::TransE   => score_triple(&kge::TransE, ee, re, head, rel, tail),
+                RelationModel::DistMult => score_triple(&kge::DistMult, ee, re, head, rel, tail),
+                RelationModel::ComplEx  => score_triple(&kge::ComplEx, ee, re, head, rel, tail)
+            })
+        }).collect()
+    }
+}
 
-        let embs = embedder.learn(graph.graph.as_ref());
-        
-        let node_embeddings = NodeEmbeddings {
-            vocab: graph.vocab.clone(),
-            embeddings:embs 
-        };
+/// How pairs of node embeddings are combined into edge features for link prediction.
+#[pyclass]
+#[derive(Clone,Copy,Debug)]
+pub enum EdgeOperator {
+    /// Elementwise product of the two embeddings.  Symmetric.
+    Hadamard,
 
-        Ok(node_embeddings)
-    }
+    /// Concatenated embeddings.  Can model directed edges.
+    Concat
+}
 
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("InstantEmbeddings<Dims={}, Hashes={}, Estimator={:?}>",
-                self.dims, self.hashes, self.estimator)
+impl EdgeOperator {
+    fn to_operator(self) -> link_scorer::EdgeOperator {
+        match self {
+            EdgeOperator::Hadamard => link_scorer::EdgeOperator::Hadamard,
+            EdgeOperator::Concat   => link_scorer::EdgeOperator::Concat
+        }
     }
+}
 
+fn get_edge_ids(vocab: &Vocab, edges: Vec<(FQNode, FQNode)>) -> PyResult<Vec<(NodeID, NodeID)>> {
+    edges.into_iter().map(|((ft, fname), (tt, tname))| {
+        Ok((get_node_id(vocab, ft, fname)?, get_node_id(vocab, tt, tname)?))
+    }).collect()
 }
 
+/// Logistic regression link predictor over node embeddings, trained in Rust.
 #[pyclass]
-struct TournamentBuilder {
-    gb: GraphBuilder,
-    degrees: Vec<f32>
+struct LinkScorer {
+    scorer: CLinkScorer
 }
 
 #[pymethods]
-impl TournamentBuilder {
-    ///    Creates a new tournament builder.  This will create a special graph which can be used to
-    ///    compute Plakett-luce models.
-    ///    
-    ///    Returns
-    ///    -------
-    ///    Self
-    ///        
-    ///    
-    #[new]
-    pub fn new() -> Self {
-        TournamentBuilder {
-            gb: GraphBuilder::new(),
-            degrees: Vec::new()
-        }
-    }
+impl LinkScorer {
 
-    ///    Adds an outcome with a winner and a loser as well as its associated weight.  
+    ///    Fits a link scorer to known edges.
     ///    
     ///    Parameters
     ///    ----------
-    ///    winner : FQNode
-    ///        Winner node.
+    ///    embeddings : NodeEmbeddings
+    ///        Node embeddings to build edge features from.
     ///    
-    ///    loser : FQNode
-    ///        Loser node
+    ///    edges : List[(FQNode, FQNode)]
+    ///        Positive edges.
     ///    
-    ///    weight : Float
-    ///        Relative weight
+    ///    negatives : List[(FQNode, FQNode)] - Optional
+    ///        Negative edges.  If not provided, samples random targets for each positive.
     ///    
-    pub fn add_outcome(
-        &mut self,
-        winner: FQNode,
-        loser: FQNode,
-        weight: f32
-    ) {
-        let mut v_len = self.gb.vocab.len();
-        let d = weight / 2f32;
-        // Add edge from loser to winner
-        self.gb.add_edge( loser.clone(), winner, d, EdgeType::Directed);
-
-        
-        while v_len < self.gb.vocab.len() {
-            self.degrees.push(1f32);
-            v_len += 1;
-        }
-        
-        // Update norm
-        let node_id = self.gb.vocab.get_node_id(loser.0, loser.1)
-            .expect("Should never fail!");
+    ///    operator : EdgeOperator - Optional
+    ///        How to combine the embeddings.  Default is EdgeOperator.Hadamard.
+    ///    
+    ///    num_negatives : Int - Optional
+    ///        Random negatives sampled per positive when negatives aren't provided.  Default is 1.
+    ///    
+    ///    passes : Int - Optional
+    ///        Number of passes over the examples.  Default is 10.
+    ///    
+    ///    batch_size : Int - Optional
+    ///        Examples per gradient update.  Default is 256.
+    ///    
+    ///    alpha : Float - Optional
+    ///        Peak learning rate.  Default is 0.05.
+    ///    
+    ///    l2 : Float - Optional
+    ///        L2 penalty on the weights.  Default is 1e-4.
+    ///    
+    ///    seed : Int - Optional
+    ///        If provided, sets the random seed.  Otherwise, uses a global fixed seed.
+    ///    
+    ///    indicator : Bool - Optional
+    ///        Shows a progress bar.  Default is True.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    LinkScorer - Can throw exception
+    ///        Throws if an edge references a node missing from the embeddings.
+    ///    
+    #[staticmethod]
+    pub fn fit(
+        py: Python<'_>,
+        embeddings: &NodeEmbeddings,
+        edges: Vec<(FQNode, FQNode)>,
+        negatives: Option<Vec<(FQNode, FQNode)>>,
+        operator: Option<EdgeOperator>,
+        num_negatives: Option<usize>,
+        passes: Option<usize>,
+        batch_size: Option<usize>,
+        alpha: Option<f32>,
+        l2: Option<f32>,
+        seed: Option<u64>,
+        indicator: Option<bool>
+    ) -> PyResult<Self> {
+        let vocab = embeddings.vocab.deref();
+        let positives = get_edge_ids(vocab, edges)?;
+        let negatives = negatives.map(|negs| get_edge_ids(vocab, negs)).transpose()?;
+        let trainer = LinkScorerTrainer {
+            operator: operator.unwrap_or(EdgeOperator::Hadamard).to_operator(),
+            negatives: num_negatives.unwrap_or(1),
+            passes: passes.unwrap_or(10),
+            batch_size: batch_size.unwrap_or(256),
+            alpha: alpha.unwrap_or(0.05),
+            l2: l2.unwrap_or(1e-4),
+            seed: seed.unwrap_or(SEED),
+            indicator: indicator.unwrap_or(true)
+        };
 
-        self.degrees[node_id] += d;
+        let es = &embeddings.embeddings;
+        let scorer = py.allow_threads(move || {
+            trainer.fit(es, &positives, negatives.as_deref())
+        });
+        Ok(LinkScorer { scorer })
     }
 
-    ///    Adds a ordered list where teams in earlier positions out competed teams in later
-    ///    positions.  For example, [Racer1, Racer3, Racer2], gets contructed as:
-    ///    1. Racer1 > Racer3
-    ///    2. Racer1 > Racer2
-    ///    3. Racer3 > Racer1
+    ///    Scores edges, returning the probability each exists.
     ///    
     ///    Parameters
     ///    ----------
-    ///    order : List[FQNode]
-    ///        Ordered list of fully qualified nodes where position demarcates ranking in
-    ///        comparison to other nodes in the list.
-    ///    
-    ///    weight : Float
-    ///        Overall weight to give list
+    ///    embeddings : NodeEmbeddings
+    ///        Node embeddings in the same space the scorer was fit on.
     ///    
-    pub fn add_ranked_outcomes(
-        &mut self,
-        order: Vec<FQNode>,
-        weight: f32
-    ) {
-        for i in 0..order.len() {
-            for j in 1..order.len() {
-                self.add_outcome(order[i].clone(), order[j].clone(), weight)
-            }
+    ///    edges : List[(FQNode, FQNode)]
+    ///        Edges to score.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float] - Can throw exception
+    ///        Throws if an edge references an unknown node or the embeddings have the wrong
+    ///        dimensions.
+    ///    
+    pub fn score(
+        &self, 
+        py: Python<'_>, 
+        embeddings: &NodeEmbeddings, 
+        edges: Vec<(FQNode, FQNode)>
+    ) -> PyResult<Vec<f32>> {
+        if embeddings.embeddings.dims() != self.scorer.emb_dims() {
+            return Err(PyValueError::new_err(format!(
+                "Embeddings have {} dims but the scorer expects {}!",
+                embeddings.embeddings.dims(), self.scorer.emb_dims())))
         }
+        let pairs = get_edge_ids(embeddings.vocab.deref(), edges)?;
+        let (scorer, es) = (&self.scorer, &embeddings.embeddings);
+        Ok(py.allow_threads(move || scorer.score_batch(es, &pairs)))
     }
 
-
-    ///    Builds the tournament.
+    ///    Scores a single pair of adhoc embeddings.
+    ///    
+    ///    Parameters
+    ///    ----------
+    ///    from_emb : List[Float]
+    ///        Embedding of the source node.
+    ///    
+    ///    to_emb : List[Float]
+    ///        Embedding of the target node.
     ///    
     ///    Returns
     ///    -------
-    ///    Tournament - Optional
-    ///        Tournament object capturing orderings.
+    ///    Float - Can throw exception
+    ///        Probability of the edge.  Throws if the embeddings have the wrong dimensions.
     ///    
-    pub fn build(
-        &mut self
-    ) -> Option<Tournament> {
-        if let Some(graph) = self.gb.build_graph() {
-            let mut degrees = Vec::with_capacity(0);
-            std::mem::swap(&mut degrees, &mut self.degrees);
-            let es = EmbeddingStore::new_with_vec(graph.nodes(), 1, EDist::Euclidean, degrees)
-                .expect("Should be correct");
-
-            let embs = NodeEmbeddings {
-                vocab: graph.vocab.clone(),
-                embeddings: es
-            };
-            Some(Tournament { graph: graph, norms: embs })
-        } else {
-            None
+    pub fn score_embeddings(&self, from_emb: Vec<f32>, to_emb: Vec<f32>) -> PyResult<f32> {
+        let dims = self.scorer.emb_dims();
+        if from_emb.len() != dims || to_emb.len() != dims {
+            return Err(PyValueError::new_err(format!("Embeddings must have {} dims!", dims)))
         }
+        Ok(self.scorer.score(&from_emb, &to_emb))
     }
 
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("TournamentBuilder<Nodes={}, Outcomes={}>",
-                self.gb.vocab.len(), self.gb.edges.len())
+    /// Learned weights over the edge features
+    pub fn weights(&self) -> Vec<f32> {
+        self.scorer.weights.clone()
     }
 
-}
-
-#[pyclass]
-struct Tournament {
-    graph: Graph,
-    norms: NodeEmbeddings
-}
-
-#[pymethods]
-impl Tournament {
-
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("Tournament<Nodes={}, Outcomes={}>",
-                self.graph.graph.len(), self.graph.graph.edges())
+    /// Learned bias
+    pub fn bias(&self) -> f32 {
+        self.scorer.bias
     }
 
-}
-
-#[pyclass]
-struct LSR {
-    passes: usize
-}
-
-#[pymethods]
-impl LSR {
-
-    ///    Initializes a Luce Spectral Ranker instance.  Luce Spectral Ranking, or LSR, is a method
-    ///    for learning placket-luce models from a given tournament. It's fast, scalable, and can
-    ///    recover the original parameters with low error.
+    ///    Saves the scorer to disk.
     ///    
     ///    Parameters
     ///    ----------
-    ///    passes : Int
-    ///        
+    ///    path : str
+    ///        Path to save the scorer to.  Paths ending in .gz are compressed.
     ///    
     ///    Returns
     ///    -------
-    ///    Self
-    ///        
+    ///    () - Can throw exception
     ///    
-    #[new]
-    pub fn new(passes: usize) -> Self {
-        LSR { passes }
-    }
-    
-    /// Simple Python representation 
-    pub fn __repr__(&self) -> String {
-        format!("LSR<Passes={}>", self.passes)
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        self.scorer.save(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
     }
 
-    ///    Fits a Placket-Luce model to the provided tournament.
+    ///    Loads a scorer written with save.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph representing the markovian state used by LSR models.  This graph is
-    ///        constructed via the TournamentBuilder instance.
-    ///    
-    ///    team_norms : NodeEmbeddings
-    ///        Captures the 
-    ///    
-    ///    indicator : Bool - Optional
-    ///        
+    ///    path : str
+    ///        Path to load the scorer from.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings 
-    ///        Logits for rankings
+    ///    LinkScorer - Can throw exception
     ///    
-    pub fn learn(
-        &self,
-        tournament: &Tournament,
-        indicator: Option<bool>
-    ) -> NodeEmbeddings {
-        let g = tournament.graph.graph.as_ref();
-        let norms = &tournament.norms.embeddings;
-        let lsr = ALSR { passes: self.passes };
-        let scores = lsr.compute(g, norms, indicator.unwrap_or(true));
-        // Find the page rank of the tournament graph
-
-        let embs = EmbeddingStore::new(g.len(), 1, EDist::Euclidean);
-        scores.par_iter().enumerate().for_each(|(node_id, score)| {
-            let e1 = embs.get_embedding_mut_hogwild(node_id);
-            e1[0] = *score;
-        });
-
-        let node_embeddings = NodeEmbeddings {
-            vocab: tournament.graph.vocab.clone(),
-            embeddings: embs 
-        };
+    #[staticmethod]
+    pub fn load(path: &str) -> PyResult<Self> {
+        let scorer = CLinkScorer::load(path)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
+        Ok(LinkScorer { scorer })
+    }
 
-        node_embeddings
-       
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("LinkScorer<operator={:?}, dims={}>", self.scorer.operator, self.scorer.emb_dims())
     }
 }
 
+/// Embeddings which live on disk, partitioned into shards with only the most recently used shards
+/// kept in memory.  Useful when the embeddings are larger than RAM.
 #[pyclass]
-struct ConnectedComponents {}
+struct ShardedNodeEmbeddings {
+    vocab: Arc<Vocab>,
+    embeddings: ShardedEmbeddingStore
+}
 
 #[pymethods]
-impl ConnectedComponents {
+impl ShardedNodeEmbeddings {
 
-    ///    Computes the graph, looking for connected components.  This is only guaranteed to work
-    ///    correctly for undirected graphs.
+    ///    Opens a sharded store written with NodeEmbeddings.save_sharded.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to find connected components in
+    ///    path : str
+    ///        Directory containing the shards.
+    ///    
+    ///    max_shards : Int - Optional
+    ///        Maximum number of shards to keep in memory at once.  Default is 4.
     ///    
     ///    Returns
     ///    -------
-    ///    NodeEmbeddings
-    ///        NodeEmbeddings of 1 dimension where the value of the embedding references the
-    ///        component id.  All nodes sharing the same component id are connected.
+    ///    ShardedNodeEmbeddings - Can throw exception
     ///    
     #[staticmethod]
-    pub fn learn(graph: &Graph) -> NodeEmbeddings {
-        let es = find_connected_components(graph.graph.as_ref());
-        NodeEmbeddings {
-            vocab: graph.vocab.clone(),
-            embeddings: es
+    pub fn open(path: &str, max_shards: Option<usize>) -> PyResult<Self> {
+        let convert = |e: std::io::Error| PyIOError::new_err(format!("{:?}", e));
+        let embeddings = ShardedEmbeddingStore::open(path, max_shards.unwrap_or(4))
+            .map_err(convert)?;
+        let vocab = read_sharded_vocab(path).map_err(convert)?;
+        if vocab.len() != embeddings.len() {
+            return Err(PyValueError::new_err("Vocab and embeddings are different sizes!"))
         }
-    }
-}
 
-#[pyclass]
-struct RandomPath {
-    rng: XorShiftRng
-}
+        Ok(ShardedNodeEmbeddings { vocab: Arc::new(vocab), embeddings })
+    }
 
-#[pymethods]
-impl RandomPath {
+    /// Simple Python representation 
+    pub fn __repr__(&self) -> String {
+        format!("ShardedNodeEmbeddings<Nodes={}, Dims={}, Shards={}, Distance={:?}>", 
+                self.embeddings.len(), self.embeddings.dims(), self.embeddings.num_shards(), 
+                self.embeddings.distance())
+    }
 
-    #[new]
-    pub fn new(seed: Option<u64>) -> Self {
-        let rng = XorShiftRng::seed_from_u64(seed.unwrap_or(SEED + 125));
-        RandomPath { rng }
+    ///    Returns the Embedding defined for a fully qualified Node.
+    ///     
+    ///    Parameters
+    ///    ----------
+    ///    node : FQNode
+    ///        Fully qualified Node
+    ///    
+    ///    Returns
+    ///    -------
+    ///    List[Float] - Can throw exception
+    ///        Embedding associated with the Node
+    ///    
+    pub fn get_embedding(&self, node: FQNode) -> PyResult<Vec<f32>> {
+        let node_id = get_node_id(self.vocab.deref(), node.0, node.1)?;
+        self.embeddings.get_embedding(node_id)
+            .map_err(|e| PyIOError::new_err(format!("{:?}", e)))
     }
-    
-    ///    Performs random walks on a graph and returns the full path of nodes visited.
+
+    ///    Using a provided embedding, finds the nearest K neighbors to that embedding.  Each
+    ///    shard is scanned in turn.
     ///    
     ///    Parameters
     ///    ----------
-    ///    graph : Graph
-    ///        Graph to find connected components in
-    ///
-    ///    node : FQNode
-    ///        Start Node for the random walks
-    ///
-    ///    counts : Positive Int
-    ///        Number of random walks to run
-    ///
-    ///    restarts : Float
-    ///        restarts ~ (0,1), determines the probability a random walk will terminate with
-    ///        lower restarts resulting in longer walks.
+    ///    emb : List[Float]
+    ///        Embedding to nearest neighbor
     ///    
-    ///    weighted : Bool 
-    ///        Weighted versus unweighted sampling.
-    ///        
+    ///    k : Int
+    ///        Top K items to return
+    ///    
+    ///    filter_type : String - Optional
+    ///        If provided, only returns nodes matching the filter_type
     ///    
     ///    Returns
     ///    -------
-    ///    List[List[FQNode]]
-    ///        Provides a list of random walks with nodes traversed in order until termination.
+    ///    List[(FQNode, f32)] - Can throw exception
+    ///        Set of fully qualified nodes and distances.
     ///    
-    pub fn rollout<>(
-        &mut self,
-        graph: &Graph, 
-        node: FQNode,
-        count: usize,
-        restarts: f32, 
-        weighted: bool,
-    ) -> PyResult<Vec<Vec<FQNode>>> {
-        let steps = Steps::from_float(restarts)
-            .ok_or_else(|| PyValueError::new_err("restarts must be between [0, inf)"))?;
-        //let sampler: Box<dyn Sampler<_>> = if weighted { Box::new(Weighted) } else { Box::new(Unweighted) };
-        let g = graph.graph.as_ref();
+    pub fn nearest_neighbor(
+        &self, 
+        py: Python<'_>,
+        emb: Vec<f32>, 
+        k: usize,
+        filter_type: Option<String>
+    ) -> PyResult<Vec<(FQNode, f32)>> {
+        let nt_id = match filter_type {
+            Some(node_type) => match self.vocab.get_node_type_id(&node_type) {
+                Some(nt_id) => Some(nt_id),
+                None => return Ok(Vec::new())
+            },
+            None => None
+        };
 
-        let mut outputs: Vec<Vec<NodeID>> = vec![Vec::new(); count];
-        let rngs: Vec<_> = (0..count as u64)
-            .map(|i| XorShiftRng::seed_from_u64(self.rng.gen::<u64>() + i))
-            .collect();
+        let dists = py.allow_threads(move || {
+            let q = Entity::Embedding(&emb);
+            self.embeddings.nearest_neighbor(&q, k, |node_id| {
+                nt_id.map(|nt_id| self.vocab.get_node_type_id_of(node_id) == Some(nt_id))
+                    .unwrap_or(true)
+            })
+        }).map_err(|e| PyIOError::new_err(format!("{:?}", e)))?;
 
-        let vocab = graph.vocab.deref(); 
-        let node_id = get_node_id(vocab, node.0, node.1)?;
-        outputs.par_iter_mut().zip(rngs.into_par_iter()).for_each(|(walk, mut rng)| {
-            walk.push(node_id);
-            if weighted {
-                rollout(g, steps, &Weighted, node_id, &mut rng, walk);
-            } else {
-                rollout(g, steps, &Weighted, node_id, &mut rng, walk);
-            };
-        });
+        Ok(convert_node_distance(&self.vocab, dists))
+    }
 
-        let it = outputs.into_iter().map(|walk| walk.into_iter().map(|node_id| {
-            convert_node_id_to_fqn(vocab, node_id)
-        }).collect());
+    ///    Returns the number of dimensions for an embedding.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int
+    ///        
+    ///    
+    pub fn dims(&self) -> usize {
+        self.embeddings.dims()
+    }
 
-        Ok(it.collect())
+    ///    Returns the number of nodes in the embedding set.
+    ///    
+    ///    Returns
+    ///    -------
+    ///    Int
+    ///        
+    ///    
+    pub fn len(&self) -> usize {
+        self.embeddings.len()
     }
 
 }
 
-
 /// Helper method for looking up an embedding.
 fn lookup_embedding<'a>(
     query: &'a Query, 
@@ -4468,10 +10844,20 @@ fn cloverleaf(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<Graph>()?;
     m.add_class::<Distance>()?;
     m.add_class::<GraphBuilder>()?;
+    m.add_class::<NameNormalization>()?;
     m.add_class::<EdgeType>()?;
+    m.add_class::<EdgeMerge>()?;
+    m.add_class::<MissingFeatures>()?;
+    m.add_class::<FeatureInit>()?;
+    m.add_class::<SymmetricWeight>()?;
+    m.add_class::<Side>()?;
+    m.add_class::<BipartiteGraph>()?;
     m.add_class::<EmbeddingPropagator>()?;
+    m.add_class::<NodeClassifier>()?;
     m.add_class::<DistanceEmbedder>()?;
     m.add_class::<ClusterLPAEmbedder>()?;
+    m.add_class::<Louvain>()?;
+    m.add_class::<Communities>()?;
     m.add_class::<SLPAEmbedder>()?;
     m.add_class::<NodeEmbeddings>()?;
     m.add_class::<VocabIterator>()?;
@@ -4479,28 +10865,60 @@ fn cloverleaf(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<GraphAnn>()?;
     m.add_class::<EmbAnn>()?;
     m.add_class::<FeatureSet>()?;
+    m.add_class::<Tokenizer>()?;
     m.add_class::<FeaturePropagator>()?;
     m.add_class::<NodeEmbedder>()?;
     m.add_class::<FeatureAggregator>()?;
     m.add_class::<Query>()?;
     m.add_class::<RandomWalker>()?;
+    m.add_class::<SimRank>()?;
     m.add_class::<BiasedRandomWalker>()?;
     m.add_class::<SparsePPR>()?;
     m.add_class::<NeighborhoodAligner>()?;
     m.add_class::<EmbeddingAligner>()?;
     m.add_class::<PprRankLearner>()?;
     m.add_class::<PageRank>()?;
+    m.add_class::<Centrality>()?;
     m.add_class::<Smci>()?;
     m.add_class::<VpcgEmbedder>()?;
     m.add_class::<PPREmbedder>()?;
+    m.add_class::<HierarchicalEmbedder>()?;
     m.add_class::<InstantEmbeddings>()?;
     m.add_class::<LSR>()?;
     m.add_class::<TournamentBuilder>()?;
     m.add_class::<Tournament>()?;
     m.add_class::<ConnectedComponents>()?;
+    m.add_class::<Triangles>()?;
     m.add_class::<ListenerRule>()?;
     m.add_class::<LossWeighting>()?;
     m.add_class::<RandomPath>()?;
+    m.add_class::<Node2VecWalker>()?;
+    m.add_class::<MetaPathWalker>()?;
+    m.add_class::<SkipGramEmbedder>()?;
+    m.add_class::<VerseEmbedder>()?;
+    m.add_class::<RelationModel>()?;
+    m.add_class::<MiniBatchKMeans>()?;
+    m.add_class::<Clusters>()?;
+    m.add_class::<DensityClusters>()?;
+    m.add_class::<Duplicates>()?;
+    m.add_class::<EffectiveResistance>()?;
+    m.add_class::<ResistanceSketch>()?;
+    m.add_class::<KGEmbedder>()?;
+    m.add_class::<EdgeOperator>()?;
+    m.add_class::<LinkScorer>()?;
+    m.add_class::<EmbeddingReducer>()?;
+    m.add_class::<ProcrustesAligner>()?;
+    m.add_class::<SpectralEmbedder>()?;
+    m.add_class::<StructuralEmbedder>()?;
+    m.add_class::<FastRPEmbedder>()?;
+    m.add_class::<EmbeddingSmoother>()?;
+    m.add_class::<MergeStrategy>()?;
+    m.add_class::<ShardedNodeEmbeddings>()?;
+    m.add_class::<MmapEmbAnn>()?;
+    m.add_class::<HnswAnn>()?;
+    m.add_class::<IvfAnn>()?;
+    m.add_class::<LshAnn>()?;
+    m.add_class::<SplitStrategy>()?;
     Ok(())
 }
 
@@ -0,0 +1,471 @@
+//! Read-only CSR graphs memory mapped from disk.  Edges are never loaded into RAM; the OS pages
+//! them in as they're traversed, so graphs far larger than memory can be walked from fast
+//! storage.  Weights are always stored as CDFs so the graph works with the weighted samplers.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{Write,BufWriter,BufReader,Result as IOResult,Error as IOError,ErrorKind};
+use std::path::{Path,PathBuf};
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use memmap2::Mmap;
+
+use crate::graph::{Graph,CDFGraph,NodeID,EdgeMerge,convert_edges_to_cdf,compact_edges};
+use crate::io::{write_magic,write_usize,read_usize,write_f32,read_f32};
+
+/// Header for memory mappable graphs; bump the version when the layout changes.
+const MMAP_GRAPH_MAGIC: &[u8] = b"CLVRCSR1";
+
+/// Magic plus node and edge counts
+const HEADER_SIZE: usize = 24;
+
+pub struct MmapCSR {
+    mmap: Mmap,
+    n_nodes: usize,
+    n_edges: usize,
+
+    // Byte offsets of each section
+    rows_offset: usize,
+    columns_offset: usize,
+    weights_offset: usize
+}
+
+fn corrupt(msg: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+impl MmapCSR {
+
+    /// Writes a graph with raw weights, converting them to CDFs.
+    pub fn save(graph: &impl Graph, path: &str) -> IOResult<()> {
+        MmapCSR::write(graph, path, true)
+    }
+
+    /// Writes a graph whose weights are already CDFs, such as a CumCSR.
+    pub fn save_cdf(graph: &impl CDFGraph, path: &str) -> IOResult<()> {
+        MmapCSR::write(graph, path, false)
+    }
+
+    fn write(graph: &impl Graph, path: &str, convert: bool) -> IOResult<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        write_magic(&mut w, MMAP_GRAPH_MAGIC)?;
+        write_usize(&mut w, graph.len())?;
+        write_usize(&mut w, graph.edges())?;
+
+        // Row offsets are rebuilt from degrees since the source may not be compact
+        let mut offset = 0;
+        write_usize(&mut w, offset)?;
+        for node_id in 0..graph.len() {
+            offset += graph.degree(node_id);
+            write_usize(&mut w, offset)?;
+        }
+        for node_id in 0..graph.len() {
+            for t_n in graph.get_edges(node_id).0.iter() {
+                write_usize(&mut w, *t_n)?;
+            }
+        }
+
+        let mut scratch = Vec::new();
+        for node_id in 0..graph.len() {
+            scratch.clear();
+            scratch.extend_from_slice(graph.get_edges(node_id).1);
+            if convert && !scratch.is_empty() {
+                convert_edges_to_cdf(&mut scratch);
+            }
+            for wi in scratch.iter() {
+                write_f32(&mut w, *wi)?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Maps the file and validates its structure.  Row offsets are checked once on open so
+    /// traversals never read out of the file; edge targets are trusted.
+    pub fn open(path: &str) -> IOResult<Self> {
+        if cfg!(target_endian = "big") || cfg!(not(target_pointer_width = "64")) {
+            return Err(IOError::new(ErrorKind::Unsupported, "Memory mapped graphs require a little endian, 64 bit host"))
+        }
+
+        let file = File::open(path)?;
+        // Safety: the file is treated as read-only; modifying it while mapped is undefined, as
+        // with any memory mapped file.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < HEADER_SIZE || &mmap[..MMAP_GRAPH_MAGIC.len()] != MMAP_GRAPH_MAGIC {
+            return Err(corrupt("Unknown file format; expected a memory mappable graph"))
+        }
+
+        let field = |i: usize| {
+            let start = MMAP_GRAPH_MAGIC.len() + i * 8;
+            u64::from_le_bytes(mmap[start..start + 8].try_into().unwrap()) as usize
+        };
+        let n_nodes = field(0);
+        let n_edges = field(1);
+
+        let rows_offset = HEADER_SIZE;
+        let columns_offset = rows_offset + (n_nodes + 1) * 8;
+        let weights_offset = columns_offset + n_edges * 8;
+        if mmap.len() != weights_offset + n_edges * 4 {
+            return Err(corrupt("Truncated or oversized graph file"))
+        }
+
+        let graph = MmapCSR { mmap, n_nodes, n_edges, rows_offset, columns_offset, weights_offset };
+        let rows = graph.rows();
+        if rows[0] != 0 || rows[n_nodes] != n_edges || rows.windows(2).any(|w| w[0] > w[1]) {
+            return Err(corrupt("Corrupt row offsets!"))
+        }
+        Ok(graph)
+    }
+
+    fn rows(&self) -> &[usize] {
+        self.slice(self.rows_offset, self.n_nodes + 1)
+    }
+
+    fn slice<T>(&self, offset: usize, len: usize) -> &[T] {
+        let size = std::mem::size_of::<T>();
+        let bytes = &self.mmap[offset..offset + len * size];
+        assert_eq!(bytes.as_ptr() as usize % std::mem::align_of::<T>(), 0);
+        // Safety: only instantiated with usize and f32, for which every bit pattern is valid.
+        // Bounds are checked by the slice above, alignment by the assert, and the host is little
+        // endian and 64 bit.
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, len) }
+    }
+}
+
+impl Graph for MmapCSR {
+    /// Get number of nodes in graph
+    fn len(&self) -> usize {
+        self.n_nodes
+    }
+
+    /// Get number of nodes in graph
+    fn edges(&self) -> usize {
+        self.n_edges
+    }
+
+    /// Get degree of node in graph
+    fn degree(&self, idx: NodeID) -> usize {
+        let (start, stop) = self.get_edge_range(idx);
+        stop - start
+    }
+
+    /// Get edges and corresponding weights
+    fn get_edges(&self, idx: NodeID) -> (&[NodeID], &[f32]) {
+        let (start, stop) = self.get_edge_range(idx);
+        let edges: &[NodeID] = self.slice(self.columns_offset + start * 8, stop - start);
+        let weights: &[f32] = self.slice(self.weights_offset + start * 4, stop - start);
+        (edges, weights)
+    }
+
+    /// Get edge Range
+    fn get_edge_range(&self, idx: NodeID) -> (usize, usize) {
+        let rows = self.rows();
+        (rows[idx], rows[idx + 1])
+    }
+}
+
+impl CDFGraph for MmapCSR {}
+
+/// Temporary file removed when dropped
+struct TempFile(PathBuf);
+
+/// Disambiguates temporary files between builders in the same process
+static TEMP_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl TempFile {
+    fn new(dir: &Path, name: &str) -> Self {
+        let id = TEMP_FILE_ID.fetch_add(1, Ordering::Relaxed);
+        TempFile(dir.join(format!("cloverleaf-{}-{}-{}.bin", name, std::process::id(), id)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Sorted run of edges spilled to disk
+struct Run {
+    file: TempFile,
+    len: usize
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+    remaining: usize
+}
+
+impl RunReader {
+    fn next(&mut self) -> IOResult<Option<(NodeID, NodeID, f32)>> {
+        if self.remaining == 0 {
+            return Ok(None)
+        }
+        self.remaining -= 1;
+        let f_n = read_usize(&mut self.reader)?;
+        let t_n = read_usize(&mut self.reader)?;
+        Ok(Some((f_n, t_n, read_f32(&mut self.reader)?)))
+    }
+}
+
+/// Receives merged edges in sorted order, writing columns and CDF weights one node at a time.
+struct EdgeSink {
+    columns: BufWriter<File>,
+    weights: BufWriter<File>,
+    degrees: Vec<usize>,
+    node: NodeID,
+    edges: Vec<NodeID>,
+    node_weights: Vec<f32>
+}
+
+impl EdgeSink {
+    fn push(&mut self, from_node: NodeID, to_node: NodeID, weight: f32) -> IOResult<()> {
+        if from_node != self.node {
+            self.flush_node()?;
+            self.node = from_node;
+        }
+        self.edges.push(to_node);
+        self.node_weights.push(weight);
+        Ok(())
+    }
+
+    fn flush_node(&mut self) -> IOResult<()> {
+        if self.edges.is_empty() {
+            return Ok(())
+        }
+        convert_edges_to_cdf(&mut self.node_weights);
+        for t_n in self.edges.iter() {
+            write_usize(&mut self.columns, *t_n)?;
+        }
+        for wi in self.node_weights.iter() {
+            write_f32(&mut self.weights, *wi)?;
+        }
+        self.degrees[self.node] = self.edges.len();
+        self.edges.clear();
+        self.node_weights.clear();
+        Ok(())
+    }
+}
+
+/// Builds memory mapped graphs from edge lists too large to construct in memory.  Edges are
+/// buffered up to a memory budget, then sorted, merged, and spilled to disk as a sorted run.
+/// Finalizing merges the runs and streams the CSR to disk, keeping only node degrees in memory.
+pub struct ExternalGraphBuilder {
+    edges: Vec<(NodeID, NodeID, f32)>,
+    merge: EdgeMerge,
+    num_nodes: usize,
+
+    /// Number of buffered edges which triggers a spill
+    max_buffered: usize,
+
+    spill_dir: PathBuf,
+    runs: Vec<Run>
+}
+
+impl ExternalGraphBuilder {
+    /// `memory_budget` is the approximate number of bytes of edges to buffer before spilling
+    /// sorted runs into `spill_dir`.
+    pub fn new(merge: EdgeMerge, memory_budget: usize, spill_dir: impl AsRef<Path>) -> Self {
+        let max_buffered = (memory_budget / std::mem::size_of::<(NodeID, NodeID, f32)>()).max(1);
+        ExternalGraphBuilder {
+            edges: Vec::new(),
+            merge,
+            num_nodes: 0,
+            max_buffered,
+            spill_dir: spill_dir.as_ref().to_path_buf(),
+            runs: Vec::new()
+        }
+    }
+
+    /// Number of sorted runs spilled to disk so far
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Ensures the finalized graph has at least `num_nodes` nodes, even if some have no edges.
+    pub fn set_num_nodes(&mut self, num_nodes: usize) {
+        self.num_nodes = self.num_nodes.max(num_nodes);
+    }
+
+    pub fn add_edge(&mut self, from_node: NodeID, to_node: NodeID, weight: f32) -> IOResult<()> {
+        self.num_nodes = self.num_nodes.max(from_node.max(to_node) + 1);
+        self.edges.push((from_node, to_node, weight));
+        if self.edges.len() >= self.max_buffered {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> IOResult<()> {
+        compact_edges(&mut self.edges, self.merge);
+        let file = TempFile::new(&self.spill_dir, "run");
+        let mut w = BufWriter::new(File::create(&file.0)?);
+        for (f_n, t_n, wi) in self.edges.iter() {
+            write_usize(&mut w, *f_n)?;
+            write_usize(&mut w, *t_n)?;
+            write_f32(&mut w, *wi)?;
+        }
+        w.flush()?;
+        self.runs.push(Run { file, len: self.edges.len() });
+
+        // Release the buffer's memory rather than holding it through finalization
+        self.edges = Vec::new();
+        Ok(())
+    }
+
+    /// Merges the spilled runs, writes the graph to `path` with weights converted to CDFs, and
+    /// maps it.  Repeated edges are merged across runs in insertion order, matching GraphBuilder.
+    pub fn finalize(mut self, path: &str) -> IOResult<MmapCSR> {
+        if !self.edges.is_empty() {
+            self.spill()?;
+        }
+
+        let mut readers = self.runs.iter()
+            .map(|run| Ok(RunReader { reader: BufReader::new(File::open(&run.file.0)?), remaining: run.len }))
+            .collect::<IOResult<Vec<_>>>()?;
+
+        // Ties are broken by run index so earlier insertions merge first
+        let mut heads = Vec::with_capacity(readers.len());
+        let mut heap = BinaryHeap::new();
+        for (run_idx, reader) in readers.iter_mut().enumerate() {
+            let head = reader.next()?;
+            if let Some((f_n, t_n, _)) = head {
+                heap.push(Reverse((f_n, t_n, run_idx)));
+            }
+            heads.push(head);
+        }
+
+        let columns_file = TempFile::new(&self.spill_dir, "columns");
+        let weights_file = TempFile::new(&self.spill_dir, "weights");
+        let mut sink = EdgeSink {
+            columns: BufWriter::new(File::create(&columns_file.0)?),
+            weights: BufWriter::new(File::create(&weights_file.0)?),
+            degrees: vec![0; self.num_nodes],
+            node: 0,
+            edges: Vec::new(),
+            node_weights: Vec::new()
+        };
+
+        let mut cur: Option<(NodeID, NodeID, f32)> = None;
+        while let Some(Reverse((f_n, t_n, run_idx))) = heap.pop() {
+            let w = heads[run_idx].expect("heap entries always have a head").2;
+            heads[run_idx] = readers[run_idx].next()?;
+            if let Some((nf_n, nt_n, _)) = heads[run_idx] {
+                heap.push(Reverse((nf_n, nt_n, run_idx)));
+            }
+
+            cur = match cur {
+                Some((c_f, c_t, c_w)) if c_f == f_n && c_t == t_n => Some((c_f, c_t, self.merge.merge(c_w, w))),
+                Some((c_f, c_t, c_w)) => {
+                    sink.push(c_f, c_t, c_w)?;
+                    Some((f_n, t_n, w))
+                },
+                None => Some((f_n, t_n, w))
+            };
+        }
+        if let Some((c_f, c_t, c_w)) = cur {
+            sink.push(c_f, c_t, c_w)?;
+        }
+        sink.flush_node()?;
+        sink.columns.flush()?;
+        sink.weights.flush()?;
+        drop(readers);
+        self.runs.clear();
+
+        let n_edges = sink.degrees.iter().sum::<usize>();
+        let mut w = BufWriter::new(File::create(path)?);
+        write_magic(&mut w, MMAP_GRAPH_MAGIC)?;
+        write_usize(&mut w, self.num_nodes)?;
+        write_usize(&mut w, n_edges)?;
+        let mut offset = 0;
+        write_usize(&mut w, offset)?;
+        for degree in sink.degrees.iter() {
+            offset += degree;
+            write_usize(&mut w, offset)?;
+        }
+        std::io::copy(&mut File::open(&columns_file.0)?, &mut w)?;
+        std::io::copy(&mut File::open(&weights_file.0)?, &mut w)?;
+        w.flush()?;
+        drop(w);
+
+        MmapCSR::open(path)
+    }
+}
+
+#[cfg(test)]
+mod mmap_graph_tests {
+    use super::*;
+    use crate::graph::{CSR,CumCSR,GraphBuilder};
+
+    #[test]
+    fn test_mmap_matches_csr() {
+        let edges = vec![
+            (0, 1, 1.),
+            (1, 1, 3.),
+            (1, 2, 2.),
+            (2, 1, 0.5),
+            (1, 0, 10.),
+        ];
+        let csr = CSR::construct_from_edges(edges);
+        let ccsr = CumCSR::convert(csr.clone());
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-csr-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        for cdf in [false, true] {
+            if cdf {
+                MmapCSR::save_cdf(&ccsr, path).unwrap();
+            } else {
+                MmapCSR::save(&csr, path).unwrap();
+            }
+            let graph = MmapCSR::open(path).unwrap();
+            assert_eq!(graph.len(), ccsr.len());
+            assert_eq!(graph.edges(), ccsr.edges());
+            for node_id in 0..ccsr.len() {
+                assert_eq!(graph.get_edges(node_id), ccsr.get_edges(node_id));
+                assert_eq!(graph.degree(node_id), ccsr.degree(node_id));
+            }
+        }
+
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::write(path, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(MmapCSR::open(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_external_builder() {
+        let edges = vec![
+            (0, 1, 1.),
+            (1, 2, 2.),
+            (0, 1, 3.),
+            (3, 0, 1.),
+            (1, 2, 5.),
+            (1, 0, 4.),
+            (0, 3, 2.),
+            (0, 1, 7.),
+        ];
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("cloverleaf-ext-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        for merge in [EdgeMerge::Sum, EdgeMerge::First, EdgeMerge::Last] {
+            let mut expected = GraphBuilder::new(merge);
+            let mut builder = ExternalGraphBuilder::new(merge, 3 * 24, &dir);
+            for (f_n, t_n, w) in edges.iter() {
+                expected.add_edge(*f_n, *t_n, *w);
+                builder.add_edge(*f_n, *t_n, *w).unwrap();
+            }
+            expected.set_num_nodes(6);
+            builder.set_num_nodes(6);
+            assert_eq!(builder.runs(), 2);
+
+            let expected = expected.finalize();
+            let graph = builder.finalize(path).unwrap();
+            assert_eq!(graph.len(), 6);
+            assert_eq!(graph.edges(), expected.edges());
+            for node_id in 0..expected.len() {
+                assert_eq!(graph.get_edges(node_id), expected.get_edges(node_id));
+            }
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
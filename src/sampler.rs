@@ -3,7 +3,7 @@ use rand::distributions::WeightedIndex;
 use rand_distr::{Distribution,Uniform};
 use float_ord::FloatOrd;
 
-use crate::graph::{CDFGraph,Graph,NodeID,CSR,NormalizedCSR};
+use crate::graph::{CDFGraph,Graph,NodeID,CSR,NormalizedCSR,AliasGraph};
 
 pub trait Sampler<G>: Send + Sync {
     fn sample<R: Rng>(&self, g: &G, node_id: NodeID, rng: &mut R) -> Option<NodeID>;
@@ -54,6 +54,15 @@ impl Sampler<NormalizedCSR> for Weighted {
 }
 
 
+/// Weighted sampling in constant time using precomputed alias tables.
+pub struct Alias;
+
+impl <'a,G: Graph> Sampler<AliasGraph<'a,G>> for Alias {
+    fn sample<R: Rng>(&self, g: &AliasGraph<'a,G>, node_id: NodeID, rng: &mut R) -> Option<NodeID> {
+        g.sample_neighbor(node_id, rng)
+    }
+}
+
 pub struct Unweighted;
 
 impl <G: Graph> Sampler<G> for Unweighted {
@@ -0,0 +1,363 @@
+//! Embedding storage for graphs whose embeddings don't fit in memory.  Rows are partitioned into
+//! fixed size shards, each its own file on disk, and an LRU keeps the hot shards resident.  Shards
+//! are loaded as regular EmbeddingStores so lookups and top-k search reuse the same distance
+//! kernels as the in-memory store.
+//!
+//! On disk a sharded store is a directory containing:
+//!   meta.txt      - key=value lines describing nodes, dims, shard_size, and distance
+//!   nodes.bin     - the vocab, as written by VocabSerializer, keeping its name normalization
+//!   shard-N.bin   - little endian f32 rows for shard N
+use std::collections::{HashMap,VecDeque};
+use std::fs::{self,File};
+use std::io::{Write,BufWriter,BufRead,Result as IOResult,Error as IOError,ErrorKind};
+use std::path::{Path,PathBuf};
+use std::sync::{Arc,Mutex};
+
+use crate::graph::NodeID;
+use crate::vocab::Vocab;
+use crate::embeddings::{EmbeddingStore,Distance,Entity};
+use crate::algos::graph_ann::{TopK,NodeDistance};
+use crate::io::{open_file_for_reading,VocabSerializer};
+
+const META_FILE: &str = "meta.txt";
+const NODES_FILE: &str = "nodes.bin";
+
+fn shard_path(dir: &Path, shard: usize) -> PathBuf {
+    dir.join(format!("shard-{}.bin", shard))
+}
+
+fn invalid_data(msg: String) -> IOError {
+    IOError::new(ErrorKind::InvalidData, msg)
+}
+
+/// The io helpers take paths as strings, so paths which aren't UTF-8 can't be opened.
+fn path_str(path: &Path) -> IOResult<&str> {
+    path.to_str().ok_or_else(|| {
+        IOError::new(ErrorKind::InvalidInput, format!("Path {:?} isn't valid UTF-8!", path))
+    })
+}
+
+/// Streams embeddings to disk as a sharded store, rolling over to a new shard file every
+/// `shard_size` rows.
+pub struct ShardedEmbeddingWriter {
+    dir: PathBuf,
+    dims: usize,
+    distance: Distance,
+    shard_size: usize,
+    nodes: usize,
+    output: Option<BufWriter<File>>
+}
+
+impl ShardedEmbeddingWriter {
+    pub fn new(dir: &str, dims: usize, distance: Distance, shard_size: usize) -> IOResult<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(ShardedEmbeddingWriter {
+            dir: PathBuf::from(dir),
+            dims,
+            distance,
+            shard_size: shard_size.max(1),
+            nodes: 0,
+            output: None
+        })
+    }
+
+    /// Appends the next embedding, returning the NodeID it was written as.
+    pub fn push(&mut self, embedding: &[f32]) -> IOResult<NodeID> {
+        if embedding.len() != self.dims {
+            return Err(invalid_data("Embedding dimensions mismatch!".into()))
+        }
+
+        if self.nodes % self.shard_size == 0 {
+            if let Some(mut out) = self.output.take() {
+                out.flush()?;
+            }
+            let f = File::create(shard_path(&self.dir, self.nodes / self.shard_size))?;
+            self.output = Some(BufWriter::new(f));
+        }
+
+        let out = self.output.as_mut().expect("Shard is always opened above");
+        for ei in embedding.iter() {
+            out.write_all(&ei.to_le_bytes())?;
+        }
+        self.nodes += 1;
+        Ok(self.nodes - 1)
+    }
+
+    /// Flushes the last shard and writes the metadata.  The store isn't readable until this is
+    /// called.
+    pub fn finish(mut self) -> IOResult<()> {
+        if let Some(mut out) = self.output.take() {
+            out.flush()?;
+        }
+
+        let mut meta = BufWriter::new(File::create(self.dir.join(META_FILE))?);
+        writeln!(meta, "nodes={}", self.nodes)?;
+        writeln!(meta, "dims={}", self.dims)?;
+        writeln!(meta, "shard_size={}", self.shard_size)?;
+        writeln!(meta, "distance={:?}", self.distance)?;
+        meta.flush()
+    }
+}
+
+/// Small LRU of resident shards.  Shards are handed out as Arcs so an evicted shard stays alive
+/// until its last reader is done with it.
+struct ShardCache {
+    capacity: usize,
+    shards: HashMap<usize, Arc<EmbeddingStore>>,
+    order: VecDeque<usize>
+}
+
+impl ShardCache {
+    fn get(&mut self, shard: usize) -> Option<Arc<EmbeddingStore>> {
+        let es = self.shards.get(&shard)?.clone();
+        if let Some(pos) = self.order.iter().position(|s| *s == shard) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(shard);
+        Some(es)
+    }
+
+    fn insert(&mut self, shard: usize, es: Arc<EmbeddingStore>) {
+        if self.shards.insert(shard, es).is_none() {
+            self.order.push_back(shard);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.shards.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// Read-only embedding store backed by shard files on disk.
+pub struct ShardedEmbeddingStore {
+    dir: PathBuf,
+    nodes: usize,
+    dims: usize,
+    shard_size: usize,
+    distance: Distance,
+    cache: Mutex<ShardCache>
+}
+
+impl ShardedEmbeddingStore {
+
+    /// Opens a sharded store, keeping at most `max_shards` shards in memory at once.
+    pub fn open(dir: &str, max_shards: usize) -> IOResult<Self> {
+        let dir = PathBuf::from(dir);
+        let meta_path = dir.join(META_FILE);
+        let reader = open_file_for_reading(path_str(&meta_path)?)?;
+        let mut meta = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            if let Some((key, value)) = line.split_once('=') {
+                meta.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        let get_usize = |key: &str| -> IOResult<usize> {
+            meta.get(key).and_then(|v| v.parse().ok())
+                .ok_or_else(|| invalid_data(format!("Missing or malformed {} in metadata!", key)))
+        };
+
+        let distance = meta.get("distance")
+            .and_then(|d| Distance::from_name(d))
+            .ok_or_else(|| invalid_data("Missing or malformed distance in metadata!".into()))?;
+
+        Ok(ShardedEmbeddingStore {
+            nodes: get_usize("nodes")?,
+            dims: get_usize("dims")?,
+            shard_size: get_usize("shard_size")?.max(1),
+            distance,
+            dir,
+            cache: Mutex::new(ShardCache {
+                capacity: max_shards.max(1),
+                shards: HashMap::new(),
+                order: VecDeque::new()
+            })
+        })
+    }
+
+    /// Writes an in-memory store out as a sharded store.
+    pub fn write(dir: &str, es: &EmbeddingStore, shard_size: usize) -> IOResult<()> {
+        let mut writer = ShardedEmbeddingWriter::new(dir, es.dims(), es.distance(), shard_size)?;
+        for (_node_id, emb) in es.iter() {
+            writer.push(emb)?;
+        }
+        writer.finish()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    pub fn distance(&self) -> Distance {
+        self.distance
+    }
+
+    pub fn num_shards(&self) -> usize {
+        (self.nodes + self.shard_size - 1) / self.shard_size
+    }
+
+    /// Number of rows in a given shard; the last shard is usually short.
+    fn shard_len(&self, shard: usize) -> usize {
+        self.shard_size.min(self.nodes - shard * self.shard_size)
+    }
+
+    /// Returns the shard, loading it from disk and evicting the least recently used shard if
+    /// it isn't resident.
+    pub fn get_shard(&self, shard: usize) -> IOResult<Arc<EmbeddingStore>> {
+        if let Some(es) = self.cache.lock().unwrap().get(shard) {
+            return Ok(es)
+        }
+
+        // Load without holding the lock so other shards can still be served
+        let buffer = fs::read(shard_path(&self.dir, shard))?;
+        let vec: Vec<f32> = buffer.chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let es = EmbeddingStore::new_with_vec(self.shard_len(shard), self.dims, self.distance, vec)
+            .map(Arc::new)
+            .ok_or_else(|| invalid_data(format!("Shard {} has the wrong size!", shard)))?;
+
+        self.cache.lock().unwrap().insert(shard, es.clone());
+        Ok(es)
+    }
+
+    /// Copies out the embedding for a node.
+    pub fn get_embedding(&self, node_id: NodeID) -> IOResult<Vec<f32>> {
+        if node_id >= self.nodes {
+            return Err(invalid_data(format!("NodeID {} is out of range!", node_id)))
+        }
+        let shard = self.get_shard(node_id / self.shard_size)?;
+        Ok(shard.get_embedding(node_id % self.shard_size).to_vec())
+    }
+
+    pub fn compute_distance<'a>(&self, n1: &Entity<'a>, n2: &Entity<'a>) -> IOResult<f32> {
+        let e1 = self.extract_vec(n1)?;
+        let e2 = self.extract_vec(n2)?;
+        Ok(self.distance.compute(&e1, &e2))
+    }
+
+    fn extract_vec<'a>(&self, n: &Entity<'a>) -> IOResult<Vec<f32>> {
+        match n {
+            Entity::Node(node_id) => self.get_embedding(*node_id),
+            Entity::Embedding(emb) => Ok(emb.to_vec())
+        }
+    }
+
+    /// Top-k search across every shard.  Shards are scanned one at a time, each in parallel, so
+    /// only a single shard beyond the cache needs to be resident at once.
+    pub fn nearest_neighbor<'a,F>(
+        &self,
+        q: &Entity<'a>,
+        k: usize,
+        filter: F
+    ) -> IOResult<Vec<NodeDistance>>
+        where F: Sync + Fn(NodeID) -> bool
+    {
+        let query_emb = self.extract_vec(q)?;
+        let mut results = self.nearest_neighbors_batch(&[query_emb.as_slice()], k, filter)?;
+        Ok(results.pop().unwrap_or_default())
+    }
+
+    /// Batched top-k search, scanning each shard once for all the queries.
+    pub fn nearest_neighbors_batch<F>(
+        &self,
+        queries: &[&[f32]],
+        k: usize,
+        filter: F
+    ) -> IOResult<Vec<Vec<NodeDistance>>>
+        where F: Sync + Fn(NodeID) -> bool
+    {
+        let mut heaps: Vec<_> = queries.iter().map(|_| TopK::new(k)).collect();
+        for shard_id in 0..self.num_shards() {
+            let offset = shard_id * self.shard_size;
+            let shard = self.get_shard(shard_id)?;
+            let shard_results = shard.nearest_neighbors_batch_filtered(queries, k, |node_id| {
+                filter(node_id + offset)
+            });
+
+            heaps.iter_mut().zip(shard_results.into_iter()).for_each(|(heap, nds)| {
+                nds.into_iter().for_each(|nd| heap.push(nd.1 + offset, nd.0));
+            });
+        }
+        Ok(heaps.into_iter().map(|heap| heap.into_sorted()).collect())
+    }
+}
+
+/// Writes the vocab in NodeID order alongside a sharded store.
+pub fn write_sharded_vocab(dir: &str, vocab: &Vocab) -> IOResult<()> {
+    let path = Path::new(dir).join(NODES_FILE);
+    VocabSerializer::save(path_str(&path)?, vocab)
+}
+
+/// Reads back the vocab written by `write_sharded_vocab`.
+pub fn read_sharded_vocab(dir: &str) -> IOResult<Vocab> {
+    let path = Path::new(dir).join(NODES_FILE);
+    VocabSerializer::load(path_str(&path)?)
+}
+
+#[cfg(test)]
+mod sharded_store_tests {
+    use super::*;
+    use crate::vocab::Normalization;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cloverleaf-sharded-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+
+        let mut es = EmbeddingStore::new(10, 2, Distance::Euclidean);
+        (0..10).for_each(|i| es.set_embedding(i, &[i as f32, 0.]));
+        ShardedEmbeddingStore::write(dir, &es, 3).unwrap();
+
+        // Only allow two shards resident to exercise eviction
+        let sharded = ShardedEmbeddingStore::open(dir, 2).unwrap();
+        assert_eq!(sharded.len(), 10);
+        assert_eq!(sharded.num_shards(), 4);
+        assert_eq!(sharded.get_embedding(9).unwrap(), vec![9., 0.]);
+        assert_eq!(sharded.get_embedding(4).unwrap(), vec![4., 0.]);
+        assert!(sharded.get_embedding(10).is_err());
+
+        let q = Entity::Embedding(&[6.2, 0.]);
+        let results = sharded.nearest_neighbor(&q, 3, |node_id| node_id != 7).unwrap();
+        assert_eq!(results.iter().map(|nd| nd.1).collect::<Vec<_>>(), vec![6, 5, 8]);
+        assert_eq!(results, es.nearest_neighbor(&q, 3, |node_id| node_id != 7));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"shards-\xff"));
+        assert_eq!(path_str(path).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_vocab() {
+        let dir = std::env::temp_dir().join(format!("cloverleaf-sharded-vocab-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let norm = Normalization { lowercase: true, trim: true, ..Normalization::default() };
+        let mut vocab = Vocab::with_normalization(norm);
+        vocab.get_or_insert("user".into(), "Alice".into());
+        vocab.get_or_insert("item".into(), "widget".into());
+        write_sharded_vocab(dir, &vocab).unwrap();
+
+        let loaded = read_sharded_vocab(dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.normalization(), norm);
+        assert_eq!(loaded.get_node_id("user".into(), " ALICE".into()), Some(0));
+        assert_eq!(loaded.get_node_id("item".into(), "widget".into()), Some(1));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
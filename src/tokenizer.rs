@@ -0,0 +1,150 @@
+//! Turns raw text into discrete features.  Keeping tokenization in Rust means training and
+//! serving split text the same way, rather than each caller reimplementing it.
+use std::collections::HashSet;
+
+/// Common English function words, removed when stopword filtering is enabled without a custom
+/// list.
+pub const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are",
+    "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but",
+    "by", "can", "did", "do", "does", "doing", "down", "during", "each", "few", "for", "from",
+    "further", "had", "has", "have", "having", "he", "her", "here", "hers", "herself", "him",
+    "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "me",
+    "more", "most", "my", "myself", "no", "nor", "not", "now", "of", "off", "on", "once", "only",
+    "or", "other", "our", "ours", "ourselves", "out", "over", "own", "same", "she", "should", "so",
+    "some", "such", "than", "that", "the", "their", "theirs", "them", "themselves", "then",
+    "there", "these", "they", "this", "those", "through", "to", "too", "under", "until", "up",
+    "very", "was", "we", "were", "what", "when", "where", "which", "while", "who", "whom", "why",
+    "will", "with", "you", "your", "yours", "yourself", "yourselves"
+];
+
+/// Splits text on whitespace and punctuation: tokens are maximal runs of alphanumeric
+/// characters, so "Don't stop!" becomes ["don", "t", "stop"] when lowercasing.
+#[derive(Clone, Debug)]
+pub struct Tokenizer {
+    /// Lowercases tokens before filtering
+    pub lowercase: bool,
+
+    /// Tokens shorter than this many characters are dropped
+    pub min_length: usize,
+
+    /// If provided, tokens are emitted as "namespace:token" so they land in a registered
+    /// feature namespace
+    pub namespace: Option<String>,
+
+    /// Emits joined runs of up to this many consecutive tokens, such as "new_york" for 2.  One
+    /// keeps only single tokens.
+    pub word_ngrams: usize,
+
+    /// If provided, the inclusive range of character n-gram lengths emitted for each token.
+    /// Tokens are padded with '<' and '>' so prefixes and suffixes are distinct, and n-grams are
+    /// prefixed with '#' so they never collide with whole tokens: "john" yields "#<jo", "#joh",
+    /// ... for trigrams.
+    pub char_ngrams: Option<(usize, usize)>,
+
+    /// Tokens to drop, compared after lowercasing
+    stopwords: HashSet<String>
+}
+
+impl Tokenizer {
+
+    pub fn new(lowercase: bool, min_length: usize, namespace: Option<String>, stopwords: Vec<String>) -> Self {
+        let stopwords = stopwords.into_iter()
+            .map(|s| if lowercase { s.to_lowercase() } else { s })
+            .collect();
+        Tokenizer { lowercase, min_length, namespace, word_ngrams: 1, char_ngrams: None, stopwords }
+    }
+
+    /// Configures n-gram expansion, applied after stopword removal.
+    pub fn with_ngrams(mut self, word_ngrams: usize, char_ngrams: Option<(usize, usize)>) -> Self {
+        self.word_ngrams = word_ngrams.max(1);
+        self.char_ngrams = char_ngrams.map(|(lo, hi)| (lo.max(1), hi.max(lo.max(1))));
+        self
+    }
+
+    pub fn english_stopwords() -> Vec<String> {
+        ENGLISH_STOPWORDS.iter().map(|s| s.to_string()).collect()
+    }
+
+    pub fn num_stopwords(&self) -> usize {
+        self.stopwords.len()
+    }
+
+    /// Tokenizes text into features, in order of appearance.  Repeated tokens are kept.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let tokens: Vec<String> = text.split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty() && t.chars().count() >= self.min_length)
+            .map(|t| if self.lowercase { t.to_lowercase() } else { t.to_string() })
+            .filter(|t| !self.stopwords.contains(t))
+            .collect();
+        self.expand(&tokens)
+    }
+
+    /// Adds the configured n-grams to already split tokens and applies the namespace.  Tokens
+    /// come first, followed by word n-grams, followed by character n-grams.
+    pub fn expand(&self, tokens: &[String]) -> Vec<String> {
+        let mut features = tokens.to_vec();
+        for n in 2..=self.word_ngrams {
+            features.extend(tokens.windows(n).map(|w| w.join("_")));
+        }
+
+        if let Some((lo, hi)) = self.char_ngrams {
+            tokens.iter().for_each(|t| {
+                let chars: Vec<char> = std::iter::once('<').chain(t.chars()).chain(std::iter::once('>')).collect();
+                for n in lo..=hi.min(chars.len()) {
+                    features.extend(chars.windows(n).map(|w| {
+                        let mut gram = String::with_capacity(n + 1);
+                        gram.push('#');
+                        gram.extend(w.iter());
+                        gram
+                    }));
+                }
+            });
+        }
+
+        if let Some(ns) = &self.namespace {
+            features.iter_mut().for_each(|f| *f = format!("{}:{}", ns, f));
+        }
+        features
+    }
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Tokenizer::new(true, 1, None, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tokenizer_tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let text = "The quick-brown Fox, isn't\tit?  Über fox!";
+        assert_eq!(Tokenizer::default().tokenize(text),
+            vec!["the", "quick", "brown", "fox", "isn", "t", "it", "über", "fox"]);
+
+        let tokenizer = Tokenizer::new(true, 2, Some("token".to_string()), Tokenizer::english_stopwords());
+        assert_eq!(tokenizer.tokenize(text),
+            vec!["token:quick", "token:brown", "token:fox", "token:isn", "token:über", "token:fox"]);
+
+        // Stopwords are matched exactly when case is preserved
+        let tokenizer = Tokenizer::new(false, 1, None, vec!["The".to_string()]);
+        assert_eq!(tokenizer.tokenize("The the"), vec!["the"]);
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let tokenizer = Tokenizer::new(true, 1, None, vec!["of".to_string()])
+            .with_ngrams(2, Some((3, 3)));
+        assert_eq!(tokenizer.tokenize("Bank of Al"),
+            vec!["bank", "al", "bank_al", "#<ba", "#ban", "#ank", "#nk>", "#<al", "#al>"]);
+
+        // Token lengths cap the character n-grams, and a short enough token emits none
+        let tokenizer = Tokenizer::new(true, 1, Some("t".to_string()), Vec::new())
+            .with_ngrams(3, Some((4, 10)));
+        assert_eq!(tokenizer.expand(&["a".to_string(), "bc".to_string()]),
+            vec!["t:a", "t:bc", "t:a_bc", "t:#<bc>"]);
+    }
+}
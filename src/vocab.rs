@@ -1,65 +1,301 @@
 use lasso::{Rodeo,Spur};
-use hashbrown::HashMap;
+use hashbrown::{HashMap,HashSet};
+use unicode_normalization::{UnicodeNormalization,IsNormalized,is_nfkc_quick};
 use crate::graph::NodeID;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize,Ordering};
+use std::borrow::Cow;
+use std::sync::{Arc,RwLock};
+use std::sync::atomic::{AtomicBool,AtomicUsize,Ordering};
 
 static VOCAB_ID: AtomicUsize = AtomicUsize::new(0);
 
+fn pack(idx: usize) -> u32 {
+    u32::try_from(idx).expect("Vocab is limited to 2^32 nodes and node types!")
+}
+
 pub type TranslationTable = Vec<Option<NodeID>>;
 
-#[derive(Clone,Debug)]
+/// Summary of how completely a translation table maps its source
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct TranslationStats {
+    /// Entries with a target
+    pub mapped: usize,
+
+    /// Entries without a target
+    pub unmapped: usize,
+
+    /// Mapped entries whose target was already claimed by an earlier entry; nonzero means the
+    /// table can't be inverted without losing entries
+    pub collisions: usize
+}
+
+pub fn translation_stats(table: &TranslationTable) -> TranslationStats {
+    let mut seen = HashSet::new();
+    let mut stats = TranslationStats { mapped: 0, unmapped: 0, collisions: 0 };
+    table.iter().for_each(|target| match target {
+        Some(target) => {
+            stats.mapped += 1;
+            if !seen.insert(*target) { stats.collisions += 1; }
+        },
+        None => stats.unmapped += 1
+    });
+    stats
+}
+
+/// Inverts a table from A to B into one from B to A, where B has `num_targets` nodes.  Targets
+/// claimed by several entries map back to the first of them, and targets out of range are
+/// ignored.
+pub fn invert_translation_table(table: &TranslationTable, num_targets: usize) -> TranslationTable {
+    let mut inverse = vec![None; num_targets];
+    table.iter().enumerate().for_each(|(source, target)| {
+        if let Some(slot) = target.and_then(|t| inverse.get_mut(t)) {
+            if slot.is_none() { *slot = Some(source); }
+        }
+    });
+    inverse
+}
+
+/// Chains a table from A to B with one from B to C into one from A to C.  Entries are unmapped if
+/// either step is.
+pub fn compose_translation_tables(a_to_b: &TranslationTable, b_to_c: &TranslationTable) -> TranslationTable {
+    a_to_b.iter()
+        .map(|b| b.and_then(|b| b_to_c.get(b).copied().flatten()))
+        .collect()
+}
+
+/// How node names are normalized before insertion and lookup, so "Apple ", "apple", and
+/// "ａｐｐｌｅ" can all resolve to the same node.  Node types are left as is.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Normalization {
+    /// Applies Unicode NFKC, folding compatibility characters such as full width letters
+    pub nfkc: bool,
+
+    /// Lowercases names
+    pub lowercase: bool,
+
+    /// Strips leading and trailing whitespace
+    pub trim: bool
+}
+
+impl Normalization {
+
+    /// Normalizes a name, in the order NFKC, lowercasing, then trimming, since NFKC can produce
+    /// whitespace.  Names which are already normalized are borrowed rather than copied.
+    pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut name = Cow::Borrowed(name);
+        if self.nfkc && is_nfkc_quick(name.chars()) != IsNormalized::Yes {
+            name = Cow::Owned(name.nfkc().collect());
+        }
+        if self.lowercase && name.chars().any(|c| c.to_lowercase().ne(std::iter::once(c))) {
+            name = Cow::Owned(name.to_lowercase());
+        }
+        if self.trim && name.trim().len() != name.len() {
+            name = match name {
+                Cow::Borrowed(n) => Cow::Borrowed(n.trim()),
+                Cow::Owned(n)    => Cow::Owned(n.trim().to_string())
+            };
+        }
+        name
+    }
+}
+
+/// NodeIDs sorted by (type id, name), covering the first `len` nodes of the vocab.
+#[derive(Debug)]
+struct NameIndex {
+    len: usize,
+    sorted: Vec<u32>
+}
+
+/// Lazily built name index.  Vocabs only append, so a stale index is detected by its length and
+/// rebuilt on the next query.
+#[derive(Debug,Default)]
+struct IndexCache(RwLock<Option<Arc<NameIndex>>>);
+
+impl Clone for IndexCache {
+    fn clone(&self) -> Self {
+        IndexCache(RwLock::new(self.0.read().unwrap().clone()))
+    }
+}
+
+/// Matches a glob supporting '*' for any run of characters and '?' for any single character.
+fn glob_match(pattern: &[char], name: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            },
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match backtrack {
+                // Let the last star absorb one more character
+                Some((bp, bn)) => {
+                    backtrack = Some((bp, bn + 1));
+                    p = bp + 1;
+                    n = bn + 1;
+                },
+                None => return false
+            }
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Names live once in the interner's string arena, and each node is stored as a packed
+/// (type id, name key) pair of u32s, so per node overhead is a few dozen bytes regardless of
+/// name length.  Node type strings are only held once per type.
+///
+/// Clones share an identity until either adds a node, at which point it forks: it takes a fresh
+/// identity and remembers the one it forked from, along with the length they had in common.
+#[derive(Debug)]
 pub struct Vocab {
     interner: Rodeo,
     vocab_id: usize,
-    vocab_to_idx: HashMap<(usize, Spur), NodeID>,
-    node_id_to_node: Vec<(usize,Spur)>,
+
+    /// Set once the vocab has been cloned, so the next insert forks its identity
+    shared: AtomicBool,
+
+    /// Identity and length of the vocab this forked from.  Only the latest fork is remembered.
+    parent: Option<(usize, usize)>,
+    vocab_to_idx: HashMap<(u32, Spur), u32>,
+    node_id_to_node: Vec<(u32, Spur)>,
     node_type_to_id: HashMap<Arc<String>, usize>,
     id_to_node_type: Vec<Arc<String>>,
+
+    /// NodeIDs of each node type, in insertion order
+    nodes_by_type: Vec<Vec<u32>>,
+    normalization: Normalization,
+    name_index: IndexCache
+}
+
+impl Clone for Vocab {
+    fn clone(&self) -> Self {
+        self.shared.store(true, Ordering::SeqCst);
+        Vocab {
+            interner: self.interner.clone(),
+            vocab_id: self.vocab_id,
+            shared: AtomicBool::new(true),
+            parent: self.parent,
+            vocab_to_idx: self.vocab_to_idx.clone(),
+            node_id_to_node: self.node_id_to_node.clone(),
+            node_type_to_id: self.node_type_to_id.clone(),
+            id_to_node_type: self.id_to_node_type.clone(),
+            nodes_by_type: self.nodes_by_type.clone(),
+            normalization: self.normalization,
+            name_index: self.name_index.clone()
+        }
+    }
 }
 
 impl Vocab {
     pub fn new() -> Self {
+        Vocab::with_normalization(Normalization::default())
+    }
+
+    /// Creates a vocab which normalizes names on insertion and lookup.
+    pub fn with_normalization(normalization: Normalization) -> Self {
         let vocab_id = VOCAB_ID.fetch_add(1, Ordering::SeqCst);
         Vocab { 
             interner: Rodeo::default(),
             vocab_id: vocab_id,
+            shared: AtomicBool::new(false),
+            parent: None,
             node_type_to_id: HashMap::new(),
             id_to_node_type: Vec::new(),
+            nodes_by_type: Vec::new(),
             vocab_to_idx: HashMap::new(),
-            node_id_to_node: Vec::new()
+            node_id_to_node: Vec::new(),
+            normalization,
+            name_index: IndexCache::default()
         }
     }
 
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Changes the normalization for future inserts and lookups.  Existing names aren't
+    /// renormalized, so only use this on an empty vocab or one whose names already follow it,
+    /// such as one restored from disk.
+    pub fn set_normalization(&mut self, normalization: Normalization) {
+        self.normalization = normalization;
+    }
+
     pub fn is_identical(&self, other: &Vocab) -> bool {
         self.vocab_id == other.vocab_id
     }
 
+    /// Number of leading node ids which refer to the same nodes in both vocabs, known without
+    /// looking up any names.  Vocabs only ever append, so a fork agrees with its parent up to
+    /// the length they had when it forked.
+    fn shared_prefix(&self, other: &Vocab) -> usize {
+        let len = self.len().min(other.len());
+        if self.is_identical(other) { return len }
+        match (self.parent, other.parent) {
+            (Some((p_id, p_len)), _) if p_id == other.vocab_id => p_len.min(len),
+            (_, Some((p_id, p_len))) if p_id == self.vocab_id => p_len.min(len),
+            (Some((p1_id, p1_len)), Some((p2_id, p2_len))) if p1_id == p2_id => p1_len.min(p2_len),
+            _ => 0
+        }
+    }
+
+    /// Gives the vocab a fresh identity before it adds a node, if a clone may still hold the
+    /// current one.
+    fn fork_if_shared(&mut self) {
+        if *self.shared.get_mut() {
+            self.parent = Some((self.vocab_id, self.len()));
+            self.vocab_id = VOCAB_ID.fetch_add(1, Ordering::SeqCst);
+            *self.shared.get_mut() = false;
+        }
+    }
+
     pub fn get_node_id(&self, node_type: String, name: String) -> Option<NodeID> {
         self.get_node_id_int(&Arc::new(node_type), &name)
     }
 
     fn get_node_id_int(&self, node_type: &Arc<String>, name: &str) -> Option<NodeID> {
         self.node_type_to_id.get(node_type).and_then(|nt_id| {
-            self.interner.get(name).and_then(|key| {
-                self.vocab_to_idx.get(&(*nt_id, key)).map(|n| n.clone())
+            self.interner.get(self.normalization.apply(name)).and_then(|key| {
+                self.vocab_to_idx.get(&(*nt_id as u32, key)).map(|n| *n as NodeID)
             })
         })
     }
 
     pub fn get_node_type(&self, node: NodeID) -> Option<&Arc<String>> {
         self.node_id_to_node.get(node).map(|(nt_id, _name)| {
-            &self.id_to_node_type[*nt_id]
+            &self.id_to_node_type[*nt_id as usize]
         })
     }
 
-    fn get_or_insert_node_type(&mut self, node_type: Arc<String>) -> usize {
+    /// Returns the internal id for a node type, which is cheaper to compare than the type itself.
+    pub fn get_node_type_id(&self, node_type: &str) -> Option<usize> {
+        self.node_type_to_id.get(&Arc::new(node_type.to_string())).copied()
+    }
+
+    /// Returns the internal node type id for a node.
+    pub fn get_node_type_id_of(&self, node: NodeID) -> Option<usize> {
+        self.node_id_to_node.get(node).map(|(nt_id, _name)| *nt_id as usize)
+    }
+
+    /// Node types in internal id order.
+    pub fn node_types(&self) -> &[Arc<String>] {
+        &self.id_to_node_type
+    }
+
+    /// Registers a node type without adding any nodes, returning its internal id.  Used when
+    /// restoring a vocab so type ids come back exactly as saved.
+    pub fn get_or_insert_node_type(&mut self, node_type: Arc<String>) -> usize {
         if let Some(nt_id) = self.node_type_to_id.get(&node_type) {
             *nt_id
         } else {
             let new_idx = self.id_to_node_type.len();
-            self.node_type_to_id.insert(node_type.clone(), new_idx.clone());
+            self.node_type_to_id.insert(node_type.clone(), new_idx);
             self.id_to_node_type.push(node_type);
+            self.nodes_by_type.push(Vec::new());
             new_idx
         }
     }
@@ -70,23 +306,86 @@ impl Vocab {
 
     pub fn get_or_insert_shared(&mut self, node_type: Arc<String>, name: &str) -> NodeID {
         let nt_id = self.get_or_insert_node_type(node_type);
-        let name_key = self.interner.get_or_intern(name);
-        let t = (nt_id, name_key);
+        self.get_or_insert_with_type_id(nt_id, name)
+    }
+
+    fn get_or_insert_with_type_id(&mut self, nt_id: usize, name: &str) -> NodeID {
+        let name_key = self.interner.get_or_intern(self.normalization.apply(name));
+        let t = (pack(nt_id), name_key);
         if let Some(node_id) = self.vocab_to_idx.get(&t) {
-            node_id.clone()
+            *node_id as NodeID
         } else {
+            self.fork_if_shared();
             let new_idx = self.node_id_to_node.len();
-            self.vocab_to_idx.insert((t.0.clone(), t.1.clone()), new_idx.clone());
-            self.node_id_to_node.push((t.0, t.1));
+            self.vocab_to_idx.insert(t, pack(new_idx));
+            self.node_id_to_node.push(t);
+            self.nodes_by_type[nt_id].push(pack(new_idx));
             new_idx
         }
- 
+    }
+
+    /// Inserts many nodes at once, returning how many were new.  Tables are sized up front from
+    /// the iterator's length hint, and runs of nodes sharing a type only resolve the type once.
+    ///
+    /// With `sort_and_freeze`, the new nodes are sorted by type then name before insertion, so
+    /// their ids don't depend on input order, and spare capacity is released afterwards since
+    /// the vocab is assumed complete.  Further inserts still work, they just reallocate.
+    pub fn extend_from_iter<I, T, N>(&mut self, nodes: I, sort_and_freeze: bool) -> usize
+    where
+        I: IntoIterator<Item=(T, N)>,
+        T: AsRef<str>,
+        N: AsRef<str>
+    {
+        let start = self.len();
+        let nodes = nodes.into_iter();
+        if sort_and_freeze {
+            let mut nodes: Vec<(String, String)> = nodes
+                .map(|(t, n)| (t.as_ref().to_string(), self.normalization.apply(n.as_ref()).into_owned()))
+                .collect();
+            nodes.sort_unstable();
+            nodes.dedup();
+            let n = nodes.len();
+            self.insert_bulk(nodes.into_iter(), n);
+            self.shrink_to_fit();
+        } else {
+            let (lower, _) = nodes.size_hint();
+            self.insert_bulk(nodes, lower);
+        }
+        self.len() - start
+    }
+
+    fn insert_bulk<T: AsRef<str>, N: AsRef<str>>(&mut self, nodes: impl Iterator<Item=(T, N)>, size_hint: usize) {
+        self.vocab_to_idx.reserve(size_hint);
+        self.node_id_to_node.reserve(size_hint);
+        let mut last_type: Option<(Arc<String>, usize)> = None;
+        for (node_type, name) in nodes {
+            let node_type = node_type.as_ref();
+            let nt_id = match &last_type {
+                Some((lt, nt_id)) if lt.as_str() == node_type => *nt_id,
+                _ => {
+                    let lt = Arc::new(node_type.to_string());
+                    let nt_id = self.get_or_insert_node_type(lt.clone());
+                    last_type = Some((lt, nt_id));
+                    nt_id
+                }
+            };
+            self.get_or_insert_with_type_id(nt_id, name.as_ref());
+        }
+    }
+
+    /// Releases capacity reserved for future inserts.
+    pub fn shrink_to_fit(&mut self) {
+        self.vocab_to_idx.shrink_to_fit();
+        self.node_id_to_node.shrink_to_fit();
+        self.node_type_to_id.shrink_to_fit();
+        self.id_to_node_type.shrink_to_fit();
+        self.nodes_by_type.iter_mut().for_each(|ids| ids.shrink_to_fit());
     }
 
     pub fn get_name(&self, node: NodeID) -> Option<(Arc<String>, &str)> {
         self.node_id_to_node.get(node).map(|(nt_id, name)| {
             let nn = self.interner.resolve(name);
-            (self.id_to_node_type[*nt_id].clone(), nn)
+            (self.id_to_node_type[*nt_id as usize].clone(), nn)
         })
     }
 
@@ -94,8 +393,115 @@ impl Vocab {
         self.node_id_to_node.len()
     }
 
+    /// Iterates over every node as (NodeID, node type, name), in NodeID order.
+    pub fn iter(&self) -> impl Iterator<Item=(NodeID, &Arc<String>, &str)> + '_ {
+        self.node_id_to_node.iter().enumerate().map(move |(node_id, (nt_id, name))| {
+            (node_id, &self.id_to_node_type[*nt_id as usize], self.interner.resolve(name))
+        })
+    }
+
+    /// Iterates over the nodes of a single type as (NodeID, name), in NodeID order.  Unknown
+    /// types yield nothing.
+    pub fn iter_type<'a>(&'a self, node_type: &str) -> impl Iterator<Item=(NodeID, &'a str)> + 'a {
+        self.node_ids_of_type(node_type).map(move |node_id| {
+            (node_id, self.interner.resolve(&self.node_id_to_node[node_id].1))
+        })
+    }
+
+    /// Iterates over the NodeIDs of a single type in ascending order, without scanning other
+    /// types.  Unknown types yield nothing.
+    pub fn node_ids_of_type(&self, node_type: &str) -> impl Iterator<Item=NodeID> + '_ {
+        let ids: &[u32] = self.get_node_type_id(node_type)
+            .map(|nt_id| self.nodes_by_type[nt_id].as_slice())
+            .unwrap_or(&[]);
+        ids.iter().map(|node_id| *node_id as NodeID)
+    }
+
+    /// Number of nodes of each type, in node type id order
+    pub fn type_counts(&self) -> Vec<(Arc<String>, usize)> {
+        self.id_to_node_type.iter().zip(self.nodes_by_type.iter())
+            .map(|(node_type, ids)| (node_type.clone(), ids.len()))
+            .collect()
+    }
+
+    /// Finds the nodes of a type whose names match a glob pattern, where '*' matches any run of
+    /// characters and '?' any single one, so "abc*" is a prefix query.  Matches are returned in
+    /// name order.  The first query builds a sorted index over all names, which later queries
+    /// reuse to jump straight to the pattern's literal prefix.
+    pub fn find(&self, node_type: &str, pattern: &str) -> Vec<NodeID> {
+        let nt_id = match self.get_node_type_id(node_type) {
+            Some(nt_id) => pack(nt_id),
+            None => return Vec::new()
+        };
+        let pattern: Vec<char> = self.normalization.apply(pattern).chars().collect();
+        let prefix: String = pattern.iter().take_while(|c| **c != '*' && **c != '?').collect();
+
+        let index = self.name_index();
+        let key = |node_id: &u32| {
+            let (t, name) = &self.node_id_to_node[*node_id as usize];
+            (*t, self.interner.resolve(name))
+        };
+        let start = index.sorted.partition_point(|node_id| key(node_id) < (nt_id, prefix.as_str()));
+        index.sorted[start..].iter()
+            .map(|node_id| (*node_id, key(node_id)))
+            .take_while(|(_, (t, name))| *t == nt_id && name.starts_with(prefix.as_str()))
+            .filter(|(_, (_, name))| glob_match(&pattern, name))
+            .map(|(node_id, _)| node_id as NodeID)
+            .collect()
+    }
+
+    fn name_index(&self) -> Arc<NameIndex> {
+        if let Some(index) = self.name_index.0.read().unwrap().as_ref() {
+            if index.len == self.len() { return index.clone() }
+        }
+
+        let mut sorted: Vec<u32> = (0..self.len()).map(pack).collect();
+        sorted.sort_unstable_by_key(|node_id| {
+            let (t, name) = &self.node_id_to_node[*node_id as usize];
+            (*t, self.interner.resolve(name))
+        });
+        let index = Arc::new(NameIndex { len: self.len(), sorted });
+        *self.name_index.0.write().unwrap() = Some(index.clone());
+        index
+    }
+
+    /// Builds a new vocab over the given nodes, in order, such as for a subgraph.  Duplicates
+    /// are only added once.
+    pub fn subset(&self, nodes: &[NodeID]) -> Vocab {
+        let mut vocab = Vocab::with_normalization(self.normalization);
+        nodes.iter().for_each(|node_id| {
+            let (node_type, name) = self.get_name(*node_id)
+                .expect("Node ID not in vocab!");
+            vocab.get_or_insert_shared(node_type, name);
+        });
+        vocab
+    }
+
+    /// Builds a new vocab containing every node in both vocabs.  Nodes from `self` keep their
+    /// ids; nodes only in `other` are appended after them.
+    pub fn union(&self, other: &Vocab) -> Vocab {
+        self.merge(other).0
+    }
+
+    /// Like `union`, but also returns where each of `other`'s nodes landed in the merged vocab.
+    /// Since `self`'s ids are unchanged, this is everything needed to remap data indexed by
+    /// either vocab.  Every entry of the table is set.  The merged vocab normalizes like `self`,
+    /// so nodes of `other` which only differ by normalization share a node.
+    pub fn merge(&self, other: &Vocab) -> (Vocab, TranslationTable) {
+        let mut vocab = Vocab::with_normalization(self.normalization);
+        self.iter().for_each(|(_, node_type, name)| {
+            vocab.get_or_insert_shared(node_type.clone(), name);
+        });
+        let table = other.iter().map(|(_, node_type, name)| {
+            Some(vocab.get_or_insert_shared(node_type.clone(), name))
+        }).collect();
+        (vocab, table)
+    }
+
+    /// Ids within the prefix shared with a clone translate directly; the rest are looked up by
+    /// name.
     pub fn translate_node(&self, other: &Vocab, other_node_id: NodeID) -> Option<NodeID> {
-        if self.is_identical(other) {
+        if other_node_id < self.shared_prefix(other) {
             Some(other_node_id)
         } else {
             other.get_name(other_node_id).and_then(|(node_type, node_name)| {
@@ -105,15 +511,13 @@ impl Vocab {
     }
 
     pub fn create_translation_table(&self, to_vocab: &Vocab) -> TranslationTable {
-        if self.is_identical(to_vocab) {
-            (0..self.node_id_to_node.len()).map(|idx| Some(idx)).collect()
-        } else {
-            self.node_id_to_node.iter().map(|(node_type_id, node_name)| {
-                let node_type = &self.id_to_node_type[*node_type_id];
-                let name = self.interner.resolve(node_name);
-                to_vocab.get_node_id_int(node_type, name)
-            }).collect()
-        }
+        let prefix = self.shared_prefix(to_vocab);
+        self.node_id_to_node.iter().enumerate().map(|(idx, (node_type_id, node_name))| {
+            if idx < prefix { return Some(idx) }
+            let node_type = &self.id_to_node_type[*node_type_id as usize];
+            let name = self.interner.resolve(node_name);
+            to_vocab.get_node_id_int(node_type, name)
+        }).collect()
     }
 
 }
@@ -149,4 +553,254 @@ mod vocab_tests {
         });
     }
 
+    #[test]
+    fn test_iter() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("a".to_string(), "1".to_string());
+        vocab.get_or_insert("b".to_string(), "1".to_string());
+        vocab.get_or_insert("a".to_string(), "2".to_string());
+
+        let all: Vec<_> = vocab.iter().map(|(node_id, nt, name)| (node_id, nt.as_str(), name)).collect();
+        assert_eq!(all, vec![(0, "a", "1"), (1, "b", "1"), (2, "a", "2")]);
+        assert_eq!(vocab.iter_type("a").collect::<Vec<_>>(), vec![(0, "1"), (2, "2")]);
+        assert_eq!(vocab.iter_type("c").count(), 0);
+
+        assert_eq!(vocab.node_ids_of_type("a").collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(vocab.type_counts(), vec![(Arc::new("a".to_string()), 2), (Arc::new("b".to_string()), 1)]);
+    }
+
+    #[test]
+    fn test_type_lists() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("a".to_string(), "1".to_string());
+        vocab.extend_from_iter(vec![("b", "1"), ("a", "2"), ("a", "1"), ("b", "2")], false);
+        assert_eq!(vocab.node_ids_of_type("a").collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(vocab.node_ids_of_type("b").collect::<Vec<_>>(), vec![1, 3]);
+
+        // Sorted inserts still list each type in id order
+        vocab.extend_from_iter(vec![("c", "1"), ("b", "0"), ("a", "3"), ("c", "1")], true);
+        assert_eq!(vocab.node_ids_of_type("a").collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(vocab.node_ids_of_type("b").collect::<Vec<_>>(), vec![1, 3, 5]);
+        assert_eq!(vocab.node_ids_of_type("c").collect::<Vec<_>>(), vec![6]);
+
+        let mut other = Vocab::new();
+        other.get_or_insert("c".to_string(), "2".to_string());
+        other.get_or_insert("a".to_string(), "1".to_string());
+        other.get_or_insert("d".to_string(), "1".to_string());
+        let (merged, _) = vocab.merge(&other);
+        assert_eq!(merged.node_ids_of_type("a").collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(merged.node_ids_of_type("c").collect::<Vec<_>>(), vec![6, 7]);
+        assert_eq!(merged.node_ids_of_type("d").collect::<Vec<_>>(), vec![8]);
+
+        let counts: Vec<_> = merged.type_counts().into_iter().map(|(nt, c)| (nt.to_string(), c)).collect();
+        let expected = vec![("a", 3), ("b", 3), ("c", 2), ("d", 1)];
+        assert_eq!(counts, expected.into_iter().map(|(nt, c)| (nt.to_string(), c)).collect::<Vec<_>>());
+        assert_eq!(counts.iter().map(|(_, c)| c).sum::<usize>(), merged.len());
+    }
+
+    #[test]
+    fn test_find() {
+        let mut vocab = Vocab::new();
+        ["apple", "apricot", "banana", "grape", "pineapple"].iter().for_each(|n| {
+            vocab.get_or_insert("fruit".to_string(), n.to_string());
+        });
+        vocab.get_or_insert("tree".to_string(), "apple".to_string());
+
+        assert_eq!(vocab.find("fruit", "ap*"), vec![0, 1]);
+        assert_eq!(vocab.find("fruit", "*apple"), vec![0, 4]);
+        assert_eq!(vocab.find("fruit", "gr?pe"), vec![3]);
+        assert_eq!(vocab.find("fruit", "apple"), vec![0]);
+        assert_eq!(vocab.find("tree", "*"), vec![5]);
+        assert!(vocab.find("fruit", "ap").is_empty());
+        assert!(vocab.find("vegetable", "*").is_empty());
+
+        // The index picks up nodes added after it was built
+        vocab.get_or_insert("fruit".to_string(), "apple pie".to_string());
+        assert_eq!(vocab.find("fruit", "apple*"), vec![0, 6]);
+
+        assert!(glob_match(&"a*b*c".chars().collect::<Vec<_>>(), "aXbYbZc"));
+        assert!(!glob_match(&"a*b?".chars().collect::<Vec<_>>(), "ab"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let norm = Normalization { nfkc: true, lowercase: true, trim: true };
+        assert_eq!(norm.apply(" Ｂａｎａｎａ\u{3000}"), "banana");
+        assert!(matches!(norm.apply("banana"), Cow::Borrowed(_)));
+        assert!(matches!(norm.apply(" banana "), Cow::Borrowed("banana")));
+
+        let mut vocab = Vocab::with_normalization(norm);
+        let node_id = vocab.get_or_insert("fruit".to_string(), "Apple ".to_string());
+        assert_eq!(vocab.get_or_insert("fruit".to_string(), "ａｐｐｌｅ".to_string()), node_id);
+        assert_eq!(vocab.get_node_id("fruit".to_string(), "APPLE".to_string()), Some(node_id));
+        assert_eq!(vocab.get_name(node_id), Some((Arc::new("fruit".to_string()), "apple")));
+        assert_eq!(vocab.find("fruit", "AP*"), vec![node_id]);
+
+        // Types aren't normalized
+        assert_eq!(vocab.get_node_id("Fruit".to_string(), "apple".to_string()), None);
+
+        // Lookups from an unnormalized vocab find their normalized counterparts
+        let mut raw = Vocab::new();
+        raw.get_or_insert("fruit".to_string(), "APPLE".to_string());
+        assert_eq!(vocab.create_translation_table(&raw), vec![None]);
+        assert_eq!(raw.create_translation_table(&vocab), vec![Some(node_id)]);
+        assert_eq!(vocab.merge(&raw).1, vec![Some(node_id)]);
+    }
+
+    #[test]
+    fn test_union() {
+        let mut v1 = Vocab::new();
+        let mut v2 = Vocab::new();
+        v1.get_or_insert("a".to_string(), "1".to_string());
+        v1.get_or_insert("a".to_string(), "2".to_string());
+        v2.get_or_insert("b".to_string(), "1".to_string());
+        v2.get_or_insert("a".to_string(), "2".to_string());
+
+        let union = v1.union(&v2);
+        assert_eq!(union.len(), 3);
+        assert_eq!(v1.create_translation_table(&union), vec![Some(0), Some(1)]);
+        assert_eq!(v2.create_translation_table(&union), vec![Some(2), Some(1)]);
+
+        let (merged, table) = v1.merge(&v2);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(table, vec![Some(2), Some(1)]);
+        assert_eq!(merged.get_name(2), v2.get_name(0));
+        assert_eq!(v1.merge(&Vocab::new()).1, Vec::new());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut v1 = Vocab::new();
+        v1.get_or_insert("user".to_string(), "alice".to_string());
+        v1.get_or_insert("item".to_string(), "book".to_string());
+        let mut v2 = Vocab::new();
+        v2.get_or_insert("item".to_string(), "pen".to_string());
+        v2.get_or_insert("user".to_string(), "alice".to_string());
+        v2.get_or_insert("tag".to_string(), "book".to_string());
+
+        let (merged, table) = v1.merge(&v2);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(table, vec![Some(2), Some(0), Some(3)]);
+        assert_eq!(merged.create_translation_table(&v1), vec![Some(0), Some(1), None, None]);
+
+        // Data indexed by either vocab lands on the same node
+        v2.iter().for_each(|(node_id, node_type, name)| {
+            assert_eq!(merged.get_name(table[node_id].unwrap()), Some((node_type.clone(), name)));
+        });
+        v1.iter().for_each(|(node_id, node_type, name)| {
+            assert_eq!(merged.get_node_id(node_type.to_string(), name.to_string()), Some(node_id));
+        });
+
+        // New types come after the existing ones
+        let types: Vec<_> = merged.node_types().iter().map(|nt| nt.as_str()).collect();
+        assert_eq!(types, vec!["user", "item", "tag"]);
+
+        // Merging with itself or a clone adds nothing
+        let (same, table) = v1.merge(&v1.clone());
+        assert_eq!(same.len(), 2);
+        assert_eq!(table, vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn test_extend_from_iter() {
+        let mut vocab = Vocab::new();
+        vocab.get_or_insert("a".to_string(), "z".to_string());
+        let added = vocab.extend_from_iter(vec![("a", "z"), ("b", "y"), ("a", "x"), ("b", "y")], false);
+        assert_eq!(added, 2);
+        assert_eq!(vocab.get_node_id("b".to_string(), "y".to_string()), Some(1));
+        assert_eq!(vocab.get_node_id("a".to_string(), "x".to_string()), Some(2));
+
+        // Sorting only orders the new nodes; existing ids are kept
+        let names = vec![("b".to_string(), "w".to_string()), ("a".to_string(), "z".to_string()),
+                         ("a".to_string(), "v".to_string()), ("a".to_string(), "v".to_string())];
+        assert_eq!(vocab.extend_from_iter(names, true), 2);
+        assert_eq!(vocab.get_name(3), Some((Arc::new("a".to_string()), "v")));
+        assert_eq!(vocab.get_name(4), Some((Arc::new("b".to_string()), "w")));
+        assert_eq!(vocab.get_node_type_id("b"), Some(1));
+    }
+
+    #[test]
+    fn test_packed_entries() {
+        // Ids up to the last u32 pack losslessly
+        assert_eq!(pack(u32::MAX as usize - 1), u32::MAX - 1);
+        assert_eq!(pack(u32::MAX as usize), u32::MAX);
+
+        // Names shared across types are only interned once
+        let mut vocab = Vocab::new();
+        let nodes = [("user", "alice"), ("item", "alice"), ("user", ""), ("tag", "café ☕"), ("item", "")];
+        nodes.iter().for_each(|(nt, n)| { vocab.get_or_insert(nt.to_string(), n.to_string()); });
+        assert_eq!(vocab.len(), 5);
+        assert_eq!(vocab.interner.len(), 3);
+        assert_eq!(vocab.get_node_id("item".to_string(), "".to_string()), Some(4));
+
+        let path = std::env::temp_dir().join(format!("cloverleaf-{}-vocab-packed.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        crate::io::VocabSerializer::save(path, &vocab).unwrap();
+        let loaded = crate::io::VocabSerializer::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(loaded.iter().eq(vocab.iter()));
+        assert_eq!(loaded.node_types(), vocab.node_types());
+        assert_eq!(loaded.interner.len(), 3);
+        nodes.iter().enumerate().for_each(|(node_id, (nt, n))| {
+            assert_eq!(loaded.get_node_id(nt.to_string(), n.to_string()), Some(node_id));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Vocab is limited to 2^32 nodes")]
+    fn test_pack_overflow() {
+        pack(u32::MAX as usize + 1);
+    }
+
+    #[test]
+    fn test_translation_tables() {
+        let a_to_b = vec![Some(2), None, Some(0), Some(2)];
+        assert_eq!(translation_stats(&a_to_b), TranslationStats { mapped: 3, unmapped: 1, collisions: 1 });
+        assert_eq!(invert_translation_table(&a_to_b, 4), vec![Some(2), None, Some(0), None]);
+        assert_eq!(invert_translation_table(&a_to_b, 1), vec![Some(2)]);
+
+        let b_to_c = vec![Some(5), Some(6), None];
+        assert_eq!(compose_translation_tables(&a_to_b, &b_to_c), vec![None, None, Some(5), None]);
+        assert_eq!(compose_translation_tables(&a_to_b, &Vec::new()), vec![None; 4]);
+    }
+
+    #[test]
+    fn test_grown_clone() {
+        let mut v1 = Vocab::new();
+        v1.get_or_insert("a".to_string(), "1".to_string());
+        let mut v2 = v1.clone();
+        v2.get_or_insert("a".to_string(), "2".to_string());
+
+        assert_eq!(v1.translate_node(&v2, 0), Some(0));
+        assert_eq!(v1.translate_node(&v2, 1), None);
+        assert_eq!(v2.create_translation_table(&v1), vec![Some(0), None]);
+    }
+
+    #[test]
+    fn test_diverging_clones() {
+        let mut v1 = Vocab::new();
+        v1.get_or_insert("a".to_string(), "1".to_string());
+        let mut v2 = v1.clone();
+        assert!(v1.is_identical(&v2));
+
+        // Both grow with different nodes at the same id
+        v1.get_or_insert("a".to_string(), "2".to_string());
+        v1.get_or_insert("a".to_string(), "3".to_string());
+        v2.get_or_insert("a".to_string(), "3".to_string());
+        v2.get_or_insert("a".to_string(), "4".to_string());
+        assert!(!v1.is_identical(&v2));
+
+        assert_eq!(v1.translate_node(&v2, 0), Some(0));
+        assert_eq!(v1.translate_node(&v2, 1), Some(2));
+        assert_eq!(v1.translate_node(&v2, 2), None);
+        assert_eq!(v2.create_translation_table(&v1), vec![Some(0), Some(2), None]);
+        assert_eq!(v1.create_translation_table(&v2), vec![Some(0), None, Some(1)]);
+
+        // A clone of a fork still shares the prefix with the original
+        let v3 = v2.clone();
+        assert!(v3.is_identical(&v2));
+        assert_eq!(v3.create_translation_table(&v1), vec![Some(0), Some(2), None]);
+    }
+
 }